@@ -0,0 +1,2 @@
+-- Preferred language for SMS and API messages ('en' or 'sw')
+ALTER TABLE users ADD COLUMN preferred_language VARCHAR(8) NOT NULL DEFAULT 'en';
@@ -0,0 +1,17 @@
+-- Optional business profile (KYC) per user account
+CREATE TABLE business_profiles (
+    user_id UUID PRIMARY KEY REFERENCES users(id) ON DELETE CASCADE,
+    business_name VARCHAR(255),
+    sector VARCHAR(100),
+    county VARCHAR(100),
+    registration_number VARCHAR(100),
+    -- Profile fields the owner allows on the public verification page
+    public_fields TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_business_profiles_sector ON business_profiles(sector);
+
+CREATE TRIGGER update_business_profiles_updated_at BEFORE UPDATE ON business_profiles
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
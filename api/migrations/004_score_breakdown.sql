@@ -0,0 +1,5 @@
+-- Per-component score points committed by the guest
+ALTER TABLE proof_sessions ADD COLUMN score_breakdown JSONB;
+
+-- What the owner allows the public verification page to disclose
+ALTER TABLE proof_sessions ADD COLUMN disclosure_policy JSONB NOT NULL DEFAULT '{}';
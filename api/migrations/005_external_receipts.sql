@@ -0,0 +1,5 @@
+-- Receipts move to object storage; the database keeps a pointer and hash.
+-- receipt_data stays readable for sessions proved before this migration.
+ALTER TABLE proof_sessions ADD COLUMN receipt_key TEXT;
+ALTER TABLE proof_sessions ADD COLUMN receipt_sha256 VARCHAR(64);
+ALTER TABLE proof_sessions ADD COLUMN receipt_size BIGINT;
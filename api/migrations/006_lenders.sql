@@ -0,0 +1,24 @@
+-- Lenders and their API keys
+CREATE TABLE lenders (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(255) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE lender_api_keys (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    lender_id UUID NOT NULL REFERENCES lenders(id) ON DELETE CASCADE,
+    key_hash VARCHAR(64) UNIQUE NOT NULL, -- SHA-256 of the key; plaintext is never stored
+    key_prefix VARCHAR(16) NOT NULL,
+    -- Sandbox keys only ever see the synthetic proof fixtures
+    sandbox BOOLEAN NOT NULL DEFAULT false,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_lender_api_keys_lender ON lender_api_keys(lender_id);
+
+CREATE TRIGGER update_lenders_updated_at BEFORE UPDATE ON lenders
+    FOR EACH ROW EXECUTE FUNCTION update_updated_at_column();
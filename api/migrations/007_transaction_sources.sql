@@ -0,0 +1,5 @@
+-- Which statement a transaction came from ('mpesa', 'bank')
+ALTER TABLE transactions ADD COLUMN source VARCHAR(32) NOT NULL DEFAULT 'mpesa';
+
+-- Second source scored alongside M-Pesa in a composite proof
+ALTER TABLE proof_sessions ADD COLUMN secondary_source VARCHAR(32);
@@ -0,0 +1,4 @@
+-- Threshold proofs commit only "score >= threshold", never the score itself
+ALTER TABLE proof_sessions ADD COLUMN proof_type VARCHAR(20) NOT NULL DEFAULT 'full';
+ALTER TABLE proof_sessions ADD COLUMN score_threshold INTEGER;
+ALTER TABLE proof_sessions ADD COLUMN meets_threshold BOOLEAN;
@@ -0,0 +1,17 @@
+-- Chargebacks and disputes raised against individual transactions
+CREATE TABLE disputes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    till_id UUID NOT NULL REFERENCES business_tills(id) ON DELETE CASCADE,
+    transaction_id UUID NOT NULL REFERENCES transactions(id) ON DELETE CASCADE,
+    -- NULL when raised by an operator
+    lender_id UUID REFERENCES lenders(id) ON DELETE SET NULL,
+    reason VARCHAR(255) NOT NULL,
+    evidence TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    UNIQUE(transaction_id)
+);
+
+CREATE INDEX idx_disputes_till ON disputes(till_id);
+
+-- Set once disputed volume on the till crosses the contest threshold
+ALTER TABLE proof_sessions ADD COLUMN contested_at TIMESTAMPTZ;
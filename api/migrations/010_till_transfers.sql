@@ -0,0 +1,10 @@
+-- Ownership history; past proofs keep their original user_id
+CREATE TABLE till_transfers (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    till_id UUID NOT NULL REFERENCES business_tills(id) ON DELETE CASCADE,
+    from_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    to_user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_till_transfers_till ON till_transfers(till_id);
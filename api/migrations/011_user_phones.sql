@@ -0,0 +1,11 @@
+-- Extra phones (e.g. an employee's) that can act on a merchant's account
+CREATE TABLE user_phones (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    phone_number VARCHAR(20) UNIQUE NOT NULL,
+    role VARCHAR(20) NOT NULL DEFAULT 'uploader',
+    verified_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_user_phones_user ON user_phones(user_id);
@@ -0,0 +1,16 @@
+-- Where and whether to email a lender
+ALTER TABLE lenders ADD COLUMN contact_email VARCHAR(255);
+ALTER TABLE lenders ADD COLUMN notify_on_verification BOOLEAN NOT NULL DEFAULT false;
+
+-- Every live verification a lender performs, for usage reports
+CREATE TABLE lender_verifications (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    lender_id UUID NOT NULL REFERENCES lenders(id) ON DELETE CASCADE,
+    key_id UUID REFERENCES lender_api_keys(id) ON DELETE SET NULL,
+    verification_code VARCHAR(50) NOT NULL,
+    status VARCHAR(20) NOT NULL, -- valid, invalid, expired, revoked, not_found
+    credit_score INTEGER,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_lender_verifications_lender ON lender_verifications(lender_id, created_at);
@@ -0,0 +1,15 @@
+-- Resumable uploads: chunks live in object storage until the upload completes
+CREATE TABLE upload_sessions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    till_id UUID NOT NULL REFERENCES business_tills(id) ON DELETE CASCADE,
+    file_type VARCHAR(255) NOT NULL,
+    source VARCHAR(32) NOT NULL DEFAULT 'mpesa',
+    total_chunks INTEGER NOT NULL,
+    received_chunks INTEGER[] NOT NULL DEFAULT '{}',
+    completed_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_upload_sessions_user ON upload_sessions(user_id);
@@ -0,0 +1,7 @@
+-- Proving timings for the public status page
+ALTER TABLE proof_sessions
+    ADD COLUMN proving_started_at TIMESTAMPTZ,
+    ADD COLUMN proving_finished_at TIMESTAMPTZ;
+
+CREATE INDEX idx_proof_sessions_proving_finished ON proof_sessions(proving_finished_at)
+    WHERE proving_finished_at IS NOT NULL;
@@ -0,0 +1,89 @@
+-- Monthly range partitioning for transactions. Unique constraints on a
+-- partitioned table must include the partition key, so the primary key
+-- becomes (id, timestamp) and the dedup key gains timestamp; a receipt's
+-- timestamp never changes, so re-imports still collapse onto one row.
+
+ALTER TABLE disputes DROP CONSTRAINT disputes_transaction_id_fkey;
+ALTER TABLE transactions RENAME TO transactions_unpartitioned;
+ALTER TABLE transactions_unpartitioned RENAME CONSTRAINT transactions_pkey TO transactions_unpartitioned_pkey;
+ALTER TABLE transactions_unpartitioned
+    RENAME CONSTRAINT transactions_till_id_reference_key TO transactions_unpartitioned_till_id_reference_key;
+ALTER TABLE transactions_unpartitioned
+    RENAME CONSTRAINT transactions_till_id_fkey TO transactions_unpartitioned_till_id_fkey;
+ALTER INDEX idx_transactions_till RENAME TO idx_transactions_unpartitioned_till;
+ALTER INDEX idx_transactions_timestamp RENAME TO idx_transactions_unpartitioned_timestamp;
+ALTER INDEX idx_transactions_reference RENAME TO idx_transactions_unpartitioned_reference;
+
+CREATE TABLE transactions (
+    id UUID NOT NULL DEFAULT uuid_generate_v4(),
+    till_id UUID NOT NULL REFERENCES business_tills(id) ON DELETE CASCADE,
+    timestamp TIMESTAMPTZ NOT NULL,
+    amount BIGINT NOT NULL, -- In cents
+    transaction_type VARCHAR(50) NOT NULL,
+    reference VARCHAR(255) NOT NULL, -- Hashed
+    raw_data JSONB,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    source VARCHAR(32) NOT NULL DEFAULT 'mpesa',
+    PRIMARY KEY (id, timestamp),
+    UNIQUE (till_id, reference, timestamp)
+) PARTITION BY RANGE (timestamp);
+
+-- Catches rows outside every monthly partition, e.g. far-past statements
+CREATE TABLE transactions_default PARTITION OF transactions DEFAULT;
+
+-- Creates one partition per month in [from_month, to_month). Months that
+-- already have a partition are skipped. Returns how many were created.
+CREATE OR REPLACE FUNCTION create_transaction_partitions(from_month DATE, to_month DATE)
+RETURNS INTEGER AS $$
+DECLARE
+    month_start DATE := date_trunc('month', from_month)::DATE;
+    partition_name TEXT;
+    created INTEGER := 0;
+BEGIN
+    WHILE month_start < to_month LOOP
+        partition_name := 'transactions_' || to_char(month_start, 'YYYY_MM');
+        IF to_regclass(partition_name) IS NULL THEN
+            EXECUTE format(
+                'CREATE TABLE %I PARTITION OF transactions FOR VALUES FROM (%L) TO (%L)',
+                partition_name,
+                month_start,
+                (month_start + INTERVAL '1 month')::DATE
+            );
+            created := created + 1;
+        END IF;
+        month_start := (month_start + INTERVAL '1 month')::DATE;
+    END LOOP;
+    RETURN created;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Partitions for the last two years (the guest scores at most twelve months)
+-- and the next three months; older rows land in the default partition
+SELECT create_transaction_partitions(
+    (date_trunc('month', NOW()) - INTERVAL '24 months')::DATE,
+    (date_trunc('month', NOW()) + INTERVAL '3 months')::DATE
+);
+
+INSERT INTO transactions (id, till_id, timestamp, amount, transaction_type, reference, raw_data, created_at, source)
+SELECT id, till_id, timestamp, amount, transaction_type, reference, raw_data, created_at, source
+FROM transactions_unpartitioned;
+
+-- The worker's per-till, time-ordered scan is answered from the index
+-- except for raw_data, which is fetched only for matching rows
+CREATE INDEX idx_transactions_till_time ON transactions (till_id, timestamp)
+    INCLUDE (id, amount, transaction_type, reference, source, created_at);
+CREATE INDEX idx_transactions_reference ON transactions (reference);
+
+-- Disputes now reference the composite key
+ALTER TABLE disputes ADD COLUMN transaction_timestamp TIMESTAMPTZ;
+UPDATE disputes d
+SET transaction_timestamp = t.timestamp
+FROM transactions t
+WHERE t.id = d.transaction_id;
+ALTER TABLE disputes ALTER COLUMN transaction_timestamp SET NOT NULL;
+ALTER TABLE disputes
+    ADD CONSTRAINT disputes_transaction_fkey
+    FOREIGN KEY (transaction_id, transaction_timestamp)
+    REFERENCES transactions (id, timestamp) ON DELETE CASCADE;
+
+DROP TABLE transactions_unpartitioned;
@@ -0,0 +1,46 @@
+-- Per-till monthly totals kept current on insert, so coverage can be shown
+-- without scanning raw transactions
+CREATE TABLE till_monthly_aggregates (
+    till_id UUID NOT NULL REFERENCES business_tills(id) ON DELETE CASCADE,
+    month DATE NOT NULL,
+    source VARCHAR(32) NOT NULL,
+    transaction_count BIGINT NOT NULL DEFAULT 0,
+    volume_cents BIGINT NOT NULL DEFAULT 0,
+    first_transaction_at TIMESTAMPTZ NOT NULL,
+    last_transaction_at TIMESTAMPTZ NOT NULL,
+    PRIMARY KEY (till_id, month, source)
+);
+
+-- Rows skipped by ON CONFLICT DO NOTHING don't fire AFTER INSERT, so
+-- re-imports aren't double counted
+CREATE OR REPLACE FUNCTION update_till_monthly_aggregates()
+RETURNS TRIGGER AS $$
+BEGIN
+    INSERT INTO till_monthly_aggregates
+        (till_id, month, source, transaction_count, volume_cents, first_transaction_at, last_transaction_at)
+    VALUES
+        (NEW.till_id, date_trunc('month', NEW.timestamp AT TIME ZONE 'UTC')::DATE, NEW.source, 1, NEW.amount,
+         NEW.timestamp, NEW.timestamp)
+    ON CONFLICT (till_id, month, source) DO UPDATE
+    SET transaction_count = till_monthly_aggregates.transaction_count + 1,
+        volume_cents = till_monthly_aggregates.volume_cents + EXCLUDED.volume_cents,
+        first_transaction_at = LEAST(till_monthly_aggregates.first_transaction_at, EXCLUDED.first_transaction_at),
+        last_transaction_at = GREATEST(till_monthly_aggregates.last_transaction_at, EXCLUDED.last_transaction_at);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_update_monthly_aggregates AFTER INSERT ON transactions
+    FOR EACH ROW EXECUTE FUNCTION update_till_monthly_aggregates();
+
+INSERT INTO till_monthly_aggregates
+    (till_id, month, source, transaction_count, volume_cents, first_transaction_at, last_transaction_at)
+SELECT till_id,
+       date_trunc('month', timestamp AT TIME ZONE 'UTC')::DATE,
+       source,
+       COUNT(*),
+       SUM(amount),
+       MIN(timestamp),
+       MAX(timestamp)
+FROM transactions
+GROUP BY 1, 2, 3;
@@ -0,0 +1,12 @@
+-- Cross-check of each completed proof against a native run of the scoring core
+ALTER TABLE proof_sessions ADD COLUMN native_consistent BOOLEAN;
+
+CREATE TABLE proof_divergences (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    session_id UUID NOT NULL REFERENCES proof_sessions(id) ON DELETE CASCADE,
+    native_output JSONB NOT NULL,
+    proven_output JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_proof_divergences_created ON proof_divergences(created_at);
@@ -0,0 +1,14 @@
+-- PayBill account numbers (e.g. one per branch), for branch-level proofs
+ALTER TABLE transactions ADD COLUMN account_number VARCHAR(64);
+
+UPDATE transactions
+SET account_number = NULLIF(raw_data->>'bill_reference', '')
+WHERE raw_data ? 'bill_reference';
+
+CREATE INDEX idx_transactions_till_account ON transactions (till_id, account_number)
+    WHERE account_number IS NOT NULL;
+
+ALTER TABLE proof_sessions
+    ADD COLUMN account_number VARCHAR(64),
+    -- Committed in the journal; lenders compare it to the hash of the account they lend against
+    ADD COLUMN account_hash VARCHAR(64);
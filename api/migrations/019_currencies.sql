@@ -0,0 +1,23 @@
+-- Currencies transactions can be recorded in. Amounts are stored in minor
+-- units (10^minor_unit_exponent per major unit); volume thresholds are the
+-- monthly major-unit cut-offs between the five volume bands.
+CREATE TABLE currencies (
+    code VARCHAR(3) PRIMARY KEY,
+    minor_unit_exponent SMALLINT NOT NULL CHECK (minor_unit_exponent BETWEEN 0 AND 4),
+    volume_thresholds BIGINT[] NOT NULL CHECK (array_length(volume_thresholds, 1) = 4),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO currencies (code, minor_unit_exponent, volume_thresholds) VALUES
+    ('KES', 2, '{50000,250000,1000000,5000000}'),
+    ('UGX', 0, '{1500000,7500000,30000000,150000000}'),
+    ('TZS', 2, '{1000000,5000000,20000000,100000000}'),
+    ('RWF', 0, '{500000,2500000,10000000,50000000}'),
+    ('USD', 2, '{400,2000,8000,40000}');
+
+-- Everything imported so far was KES in cents
+ALTER TABLE transactions ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'KES' REFERENCES currencies(code);
+
+ALTER TABLE upload_sessions ADD COLUMN currency VARCHAR(3) NOT NULL DEFAULT 'KES' REFERENCES currencies(code);
+
+ALTER TABLE proof_sessions ADD COLUMN currency VARCHAR(3) REFERENCES currencies(code);
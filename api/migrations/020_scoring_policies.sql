@@ -0,0 +1,21 @@
+-- Volume band thresholds a lender or market scores under, in place of the
+-- currency's defaults. Policies are never edited once created: a proof
+-- commits the policy's hash, so changing one would orphan that audit trail.
+CREATE TABLE scoring_policies (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    name VARCHAR(255) NOT NULL,
+    currency VARCHAR(3) NOT NULL REFERENCES currencies(code),
+    -- NULL for market-wide policies any merchant may choose
+    lender_id UUID REFERENCES lenders(id) ON DELETE CASCADE,
+    volume_thresholds BIGINT[] NOT NULL CHECK (array_length(volume_thresholds, 1) = 4),
+    -- Hex ScoringPolicy::hash, as committed in proof journals
+    policy_hash VARCHAR(64) NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_scoring_policies_lender ON scoring_policies(lender_id);
+
+ALTER TABLE proof_sessions
+    -- NULL when scored under the currency's default thresholds
+    ADD COLUMN scoring_policy_id UUID REFERENCES scoring_policies(id),
+    ADD COLUMN policy_hash VARCHAR(64);
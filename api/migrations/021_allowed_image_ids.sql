@@ -0,0 +1,15 @@
+-- Guest image IDs receipts may be verified against. Rebuilding the guest
+-- adds an ID rather than replacing it, so older receipts keep verifying
+-- until their image is retired by setting valid_to.
+CREATE TABLE allowed_image_ids (
+    image_id VARCHAR(64) PRIMARY KEY,
+    guest_version VARCHAR(64) NOT NULL,
+    -- Receipts proved outside this window are rejected
+    valid_from TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    valid_to TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Image the receipt was proved with; NULL on sessions proved before this
+-- was recorded, which verify against the current image
+ALTER TABLE proof_sessions ADD COLUMN image_id VARCHAR(64);
@@ -0,0 +1,10 @@
+-- Which guest build and prover produced each receipt, recorded when
+-- proving starts
+ALTER TABLE proof_sessions
+    ADD COLUMN guest_version VARCHAR(64),
+    ADD COLUMN prover_backend VARCHAR(32);
+
+UPDATE proof_sessions ps
+SET guest_version = a.guest_version
+FROM allowed_image_ids a
+WHERE a.image_id = ps.image_id;
@@ -0,0 +1,9 @@
+-- Which worker replica holds each running job, so replicas never prove the
+-- same session twice and jobs from crashed workers are picked up again
+ALTER TABLE proof_sessions
+    ADD COLUMN lease_owner VARCHAR(64),
+    ADD COLUMN lease_expires_at TIMESTAMPTZ,
+    ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0;
+
+CREATE INDEX idx_proof_sessions_lease_expiry ON proof_sessions(lease_expires_at)
+    WHERE status = 'processing';
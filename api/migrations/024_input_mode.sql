@@ -0,0 +1,6 @@
+-- Whether the guest scored every transaction or host-computed daily totals.
+-- Daily-totals proofs commit a hash of the rows the totals came from.
+ALTER TABLE proof_sessions
+    ADD COLUMN input_mode VARCHAR(20) NOT NULL DEFAULT 'transactions'
+        CHECK (input_mode IN ('transactions', 'daily_totals')),
+    ADD COLUMN rows_commitment VARCHAR(64);
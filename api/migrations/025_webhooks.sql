@@ -0,0 +1,12 @@
+-- Endpoints lenders receive event callbacks on. The secret signs every
+-- delivery (HMAC-SHA256), so it is stored as issued rather than hashed.
+CREATE TABLE webhook_endpoints (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    lender_id UUID NOT NULL REFERENCES lenders(id) ON DELETE CASCADE,
+    url TEXT NOT NULL,
+    secret VARCHAR(100) NOT NULL,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_webhook_endpoints_lender ON webhook_endpoints(lender_id);
@@ -0,0 +1,66 @@
+-- Canonical kinds for the many ways statements describe a row ("Customer
+-- Payment", "Merchant Payment Online", "Pay Bill"). The raw description stays
+-- in transaction_type; kind is what the guest scores.
+CREATE TABLE transaction_type_mappings (
+    -- Lower-case, single-spaced prefix of a raw description. The longest
+    -- matching prefix wins, so "pay bill charge" beats "pay bill".
+    pattern VARCHAR(100) PRIMARY KEY CHECK (pattern <> '' AND pattern = lower(pattern)),
+    kind VARCHAR(16) NOT NULL CHECK (kind IN ('Payment', 'Reversal', 'Withdrawal', 'Transfer', 'Charge', 'Other')),
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO transaction_type_mappings (pattern, kind) VALUES
+    ('payment', 'Payment'),
+    ('payment received', 'Payment'),
+    ('customer payment', 'Payment'),
+    ('merchant payment', 'Payment'),
+    ('pay bill', 'Payment'),
+    ('paybill', 'Payment'),
+    ('buy goods', 'Payment'),
+    ('funds received', 'Payment'),
+    ('reversal', 'Reversal'),
+    ('payment reversal', 'Reversal'),
+    ('withdrawal', 'Withdrawal'),
+    ('merchant withdrawal', 'Withdrawal'),
+    ('business withdrawal', 'Withdrawal'),
+    ('transfer', 'Transfer'),
+    ('funds transfer', 'Transfer'),
+    ('charge', 'Charge'),
+    ('transaction charge', 'Charge'),
+    ('pay bill charge', 'Charge'),
+    ('merchant payment charge', 'Charge'),
+    ('withdrawal charge', 'Charge');
+
+CREATE OR REPLACE FUNCTION normalize_transaction_type(raw TEXT)
+RETURNS TEXT AS $$
+    SELECT lower(regexp_replace(trim(raw), '\s+', ' ', 'g'));
+$$ LANGUAGE sql IMMUTABLE;
+
+-- Unmapped descriptions are Other, which isn't scored
+CREATE OR REPLACE FUNCTION transaction_kind(raw TEXT)
+RETURNS VARCHAR AS $$
+    SELECT COALESCE(
+        (SELECT kind
+         FROM transaction_type_mappings
+         WHERE starts_with(normalize_transaction_type(raw), pattern)
+         ORDER BY length(pattern) DESC
+         LIMIT 1),
+        'Other'
+    );
+$$ LANGUAGE sql STABLE;
+
+ALTER TABLE transactions ADD COLUMN kind VARCHAR(16) NOT NULL DEFAULT 'Other';
+
+UPDATE transactions SET kind = transaction_kind(transaction_type);
+
+-- Every import path gets the same classification without naming the column
+CREATE OR REPLACE FUNCTION set_transaction_kind()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.kind := transaction_kind(NEW.transaction_type);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_set_kind BEFORE INSERT ON transactions
+    FOR EACH ROW EXECUTE FUNCTION set_transaction_kind();
@@ -0,0 +1,4 @@
+-- Scoring policies may cap each day's volume at a multiple of the median
+-- active day's, so a one-off large payment doesn't inflate the score. NULL
+-- scores raw volume, as every policy did before.
+ALTER TABLE scoring_policies ADD COLUMN outlier_cap INTEGER CHECK (outlier_cap >= 2);
@@ -0,0 +1,5 @@
+-- What the guest committed about how recently the till last took a payment.
+-- NULL for proofs generated before recency was measured.
+ALTER TABLE proof_sessions
+    ADD COLUMN days_since_last_transaction INTEGER CHECK (days_since_last_transaction >= 0),
+    ADD COLUMN recency_factor_percentage SMALLINT CHECK (recency_factor_percentage BETWEEN 0 AND 100);
@@ -0,0 +1,24 @@
+-- What a lender requires of the proofs it accepts, published so merchants can
+-- generate a conforming proof in one step. Like scoring policies, templates
+-- are never edited: proofs refer to them, so publish a new one instead.
+CREATE TABLE proof_templates (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    lender_id UUID NOT NULL REFERENCES lenders(id) ON DELETE CASCADE,
+    name VARCHAR(255) NOT NULL,
+    -- Days between the statement's first and last transaction
+    min_period_days INTEGER NOT NULL DEFAULT 0 CHECK (min_period_days >= 0),
+    -- Any of 'credit_score', 'metrics' and 'score_breakdown'
+    required_disclosures TEXT[] NOT NULL DEFAULT '{}',
+    -- NULL when proofs may be scored under the currency's defaults
+    scoring_policy_id UUID REFERENCES scoring_policies(id),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_proof_templates_lender ON proof_templates(lender_id);
+
+ALTER TABLE proof_sessions
+    ADD COLUMN template_id UUID REFERENCES proof_templates(id),
+    -- The period the guest committed, so compliance can be checked without
+    -- decoding the receipt. NULL for proofs generated before templates.
+    ADD COLUMN period_start TIMESTAMPTZ,
+    ADD COLUMN period_end TIMESTAMPTZ;
@@ -0,0 +1,22 @@
+-- An intake endpoint receives proofs merchants hand off to start a loan
+-- application, and answers with where to send the merchant next. A lender
+-- has at most one active intake endpoint.
+ALTER TABLE webhook_endpoints
+    ADD COLUMN purpose VARCHAR(16) NOT NULL DEFAULT 'events' CHECK (purpose IN ('events', 'intake'));
+
+CREATE UNIQUE INDEX idx_webhook_endpoints_intake ON webhook_endpoints(lender_id)
+    WHERE purpose = 'intake' AND active;
+
+CREATE TABLE proof_handoffs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    session_id UUID NOT NULL REFERENCES proof_sessions(id) ON DELETE CASCADE,
+    lender_id UUID NOT NULL REFERENCES lenders(id) ON DELETE CASCADE,
+    -- SHA-256 of the one-time token sent to the lender's intake endpoint
+    token_hash VARCHAR(64) UNIQUE NOT NULL,
+    application_url TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL,
+    redeemed_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_proof_handoffs_session ON proof_handoffs(session_id);
@@ -0,0 +1,3 @@
+-- Name M-Pesa has on record for the till's shortcode, looked up on
+-- registration. NULL when Daraja isn't configured or the lookup failed.
+ALTER TABLE business_tills ADD COLUMN organization_name VARCHAR(255);
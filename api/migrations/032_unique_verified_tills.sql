@@ -0,0 +1,18 @@
+-- Any number of accounts may register a till number, but only one may hold
+-- it verified. Verifying a till takes it over from whoever held it before.
+ALTER TABLE business_tills
+    ADD COLUMN superseded_by UUID REFERENCES business_tills(id) ON DELETE SET NULL,
+    ADD COLUMN superseded_at TIMESTAMPTZ;
+
+-- Existing duplicates: the earliest verification keeps the till
+UPDATE business_tills t
+SET is_verified = false, superseded_by = keeper.id, superseded_at = NOW()
+FROM (
+    SELECT DISTINCT ON (till_number) id, till_number
+    FROM business_tills
+    WHERE is_verified
+    ORDER BY till_number, created_at
+) keeper
+WHERE t.is_verified AND t.till_number = keeper.till_number AND t.id <> keeper.id;
+
+CREATE UNIQUE INDEX idx_tills_verified_number ON business_tills(till_number) WHERE is_verified;
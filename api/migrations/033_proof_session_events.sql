@@ -0,0 +1,16 @@
+-- Each step a proof session goes through, so a stuck session shows where it
+-- stopped instead of just a progress number
+CREATE TYPE proof_session_stage AS ENUM (
+    'queued', 'picked_up', 'executing', 'proving', 'verifying', 'stored', 'failed'
+);
+
+CREATE TABLE proof_session_events (
+    id BIGSERIAL PRIMARY KEY,
+    session_id UUID NOT NULL REFERENCES proof_sessions(id) ON DELETE CASCADE,
+    stage proof_session_stage NOT NULL,
+    -- The worker for picked_up, the reason for failed
+    detail TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_proof_session_events_session ON proof_session_events(session_id, id);
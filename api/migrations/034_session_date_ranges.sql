@@ -0,0 +1,8 @@
+-- The date range a session was requested for, so a repeated request for the
+-- same till and range can be matched to the session already running
+ALTER TABLE proof_sessions
+    ADD COLUMN requested_from DATE,
+    ADD COLUMN requested_to DATE;
+
+CREATE INDEX idx_proof_sessions_active_till ON proof_sessions(till_id)
+    WHERE status IN ('pending', 'processing');
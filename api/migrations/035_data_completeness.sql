@@ -0,0 +1,7 @@
+-- Share of weeks in a till's scoring window with at least one payment,
+-- refreshed on every import, and the same measure as each proof committed it
+ALTER TABLE business_tills
+    ADD COLUMN completeness_percentage SMALLINT,
+    ADD COLUMN completeness_checked_at TIMESTAMPTZ;
+
+ALTER TABLE proof_sessions ADD COLUMN coverage_percentage SMALLINT;
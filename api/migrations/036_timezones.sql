@@ -0,0 +1,4 @@
+-- IANA timezone a till's statements are written in, and the one a chunked
+-- upload was declared in when it overrode the till's
+ALTER TABLE business_tills ADD COLUMN timezone VARCHAR(64) NOT NULL DEFAULT 'Africa/Nairobi';
+ALTER TABLE upload_sessions ADD COLUMN timezone VARCHAR(64);
@@ -0,0 +1,25 @@
+-- Which way money moved in a row. Charges are fees; withdrawals and
+-- reversals always take money out; anything else follows the sign the
+-- statement gave the amount
+CREATE OR REPLACE FUNCTION transaction_direction(kind TEXT, amount BIGINT)
+RETURNS VARCHAR AS $$
+    SELECT CASE
+        WHEN kind = 'Charge' THEN 'fee'
+        WHEN kind IN ('Withdrawal', 'Reversal') OR amount < 0 THEN 'outflow'
+        ELSE 'inflow'
+    END;
+$$ LANGUAGE sql IMMUTABLE;
+
+ALTER TABLE transactions ADD COLUMN direction VARCHAR(8) NOT NULL DEFAULT 'inflow'
+    CHECK (direction IN ('inflow', 'outflow', 'fee'));
+
+UPDATE transactions SET direction = transaction_direction(kind, amount);
+
+CREATE OR REPLACE FUNCTION set_transaction_kind()
+RETURNS TRIGGER AS $$
+BEGIN
+    NEW.kind := transaction_kind(NEW.transaction_type);
+    NEW.direction := transaction_direction(NEW.kind, NEW.amount);
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
@@ -0,0 +1,14 @@
+-- A lender's request to see a proof whose owner requires consent, and the
+-- owner's answer. Pending requests lapse unanswered; approvals lapse too
+CREATE TABLE proof_consents (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    session_id UUID NOT NULL REFERENCES proof_sessions(id) ON DELETE CASCADE,
+    lender_id UUID NOT NULL REFERENCES lenders(id) ON DELETE CASCADE,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending'
+        CHECK (status IN ('pending', 'approved', 'declined', 'revoked')),
+    requested_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    decided_at TIMESTAMPTZ,
+    expires_at TIMESTAMPTZ NOT NULL
+);
+
+CREATE INDEX idx_proof_consents_session_lender ON proof_consents(session_id, lender_id, requested_at DESC);
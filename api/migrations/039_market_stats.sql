@@ -0,0 +1,11 @@
+-- Anonymized market aggregates, recomputed by the worker. Readers take the
+-- newest snapshot
+CREATE TABLE market_stats_snapshots (
+    id BIGSERIAL PRIMARY KEY,
+    -- Segments with fewer businesses than this were left out
+    min_group_size INTEGER NOT NULL,
+    stats JSONB NOT NULL,
+    computed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_market_stats_snapshots_computed ON market_stats_snapshots(computed_at DESC);
@@ -0,0 +1,11 @@
+-- Six-digit codes a merchant can read out over the phone instead of the
+-- verification code. Expired codes are deleted when new ones are minted, so
+-- their digits can be reused
+CREATE TABLE proof_short_codes (
+    code VARCHAR(6) PRIMARY KEY,
+    session_id UUID NOT NULL REFERENCES proof_sessions(id) ON DELETE CASCADE,
+    expires_at TIMESTAMPTZ NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_proof_short_codes_expires ON proof_short_codes(expires_at);
@@ -0,0 +1,12 @@
+-- Reminders sent before a proof expires, one per proof per lead time. Each
+-- carries a link that regenerates the proof with the same settings; only the
+-- hash of its token is kept
+CREATE TABLE proof_expiry_reminders (
+    session_id UUID NOT NULL REFERENCES proof_sessions(id) ON DELETE CASCADE,
+    days_before INTEGER NOT NULL,
+    token_hash VARCHAR(64) NOT NULL UNIQUE,
+    sent_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- The session the link started, once used
+    regenerated_session_id UUID REFERENCES proof_sessions(id) ON DELETE SET NULL,
+    PRIMARY KEY (session_id, days_before)
+);
@@ -0,0 +1,16 @@
+-- Batches too large to import within the request. The caller polls the job
+-- for the same summary a small batch returns inline
+CREATE TABLE import_jobs (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    till_id UUID NOT NULL REFERENCES business_tills(id) ON DELETE CASCADE,
+    status VARCHAR(20) NOT NULL DEFAULT 'running'
+        CHECK (status IN ('running', 'completed', 'failed')),
+    rows_received INTEGER NOT NULL,
+    result JSONB,
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    finished_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_import_jobs_user ON import_jobs(user_id, created_at DESC);
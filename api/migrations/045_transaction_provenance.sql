@@ -0,0 +1,22 @@
+-- How each row reached the platform: an uploaded CSV/spreadsheet or PDF
+-- statement, a parsed SMS, a partner push, a C2B callback or a Daraja pull.
+-- Rows from before it was recorded are taken as uploads unless their raw
+-- data says otherwise.
+ALTER TABLE transactions ADD COLUMN provenance VARCHAR(16) NOT NULL DEFAULT 'csv'
+    CHECK (provenance IN ('csv', 'pdf', 'sms', 'partner', 'c2b', 'daraja'));
+
+UPDATE transactions SET provenance = 'sms' WHERE raw_data->>'source' = 'sms';
+UPDATE transactions SET provenance = 'daraja' WHERE raw_data->>'source' = 'daraja_pull';
+
+-- Every importer names its provenance from here on
+ALTER TABLE transactions ALTER COLUMN provenance DROP DEFAULT;
+
+-- The mix each proof committed, by provenance name
+ALTER TABLE proof_sessions
+    ADD COLUMN api_sourced_percentage SMALLINT,
+    ADD COLUMN provenance_mix JSONB;
+
+-- How much of a proof's self-reported volume a lender counts, as a percentage,
+-- when weighting its score
+ALTER TABLE lenders ADD COLUMN self_reported_weight_percentage SMALLINT NOT NULL DEFAULT 100
+    CHECK (self_reported_weight_percentage BETWEEN 0 AND 100);
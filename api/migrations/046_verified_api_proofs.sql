@@ -0,0 +1,4 @@
+-- Which transactions a proof scored: all of them, or only those Safaricom
+-- delivered directly (C2B callbacks and Daraja pulls)
+ALTER TABLE proof_sessions ADD COLUMN data_source VARCHAR(16) NOT NULL DEFAULT 'all'
+    CHECK (data_source IN ('all', 'verified_api'));
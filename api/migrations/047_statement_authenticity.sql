@@ -0,0 +1,21 @@
+-- Every CSV/PDF statement import, with how it scored against checks for
+-- edited exports (row order, running balance, receipt format and receipt
+-- collisions)
+CREATE TABLE statement_imports (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    till_id UUID NOT NULL REFERENCES business_tills(id) ON DELETE CASCADE,
+    source VARCHAR(32) NOT NULL,
+    provenance VARCHAR(16) NOT NULL,
+    rows_imported INTEGER NOT NULL,
+    authenticity_score SMALLINT NOT NULL CHECK (authenticity_score BETWEEN 0 AND 100),
+    authenticity_checks JSONB NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_statement_imports_till ON statement_imports(till_id, source);
+
+-- The lowest score among the uploads a proof scored, and the trust tier it
+-- puts the proof in; unset on proofs from before uploads were scored
+ALTER TABLE proof_sessions
+    ADD COLUMN authenticity_score SMALLINT,
+    ADD COLUMN trust_tier VARCHAR(8) CHECK (trust_tier IN ('high', 'medium', 'low'));
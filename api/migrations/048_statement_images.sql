@@ -0,0 +1,20 @@
+-- Rows read by OCR from photos of printed statements
+ALTER TABLE transactions DROP CONSTRAINT transactions_provenance_check;
+ALTER TABLE transactions ADD CONSTRAINT transactions_provenance_check
+    CHECK (provenance IN ('csv', 'pdf', 'sms', 'partner', 'c2b', 'daraja', 'image'));
+
+-- Each photo uploaded, with the rows OCR couldn't read confidently enough
+-- to import, kept for the owner to review
+CREATE TABLE statement_images (
+    id UUID PRIMARY KEY,
+    till_id UUID NOT NULL REFERENCES business_tills(id) ON DELETE CASCADE,
+    storage_key TEXT NOT NULL,
+    content_type VARCHAR(32) NOT NULL,
+    ocr_engine VARCHAR(16) NOT NULL,
+    rows_read INTEGER NOT NULL,
+    rows_imported INTEGER NOT NULL,
+    review_rows JSONB NOT NULL DEFAULT '[]',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_statement_images_till ON statement_images(till_id, created_at DESC);
@@ -0,0 +1,48 @@
+-- Imported rows held back from scoring until the till's owner confirms or
+-- corrects them: rows OCR couldn't read confidently, and rows that failed
+-- an authenticity check (running_balance, receipt_format or
+-- receipt_collisions). The receipt number and OCR text are kept in the
+-- clear only while the row is pending.
+CREATE TABLE pending_review_transactions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    till_id UUID NOT NULL REFERENCES business_tills(id) ON DELETE CASCADE,
+    image_id UUID REFERENCES statement_images(id) ON DELETE CASCADE,
+    flagged_by VARCHAR(32) NOT NULL
+        CHECK (flagged_by IN ('ocr', 'running_balance', 'receipt_format', 'receipt_collisions')),
+    reason TEXT NOT NULL,
+    row_number INTEGER NOT NULL, -- spreadsheet row, or line of the photo
+    raw_text TEXT,
+    confidence SMALLINT CHECK (confidence BETWEEN 0 AND 100),
+    -- As read; unset when the row couldn't be
+    receipt_number TEXT,
+    timestamp TIMESTAMPTZ,
+    amount BIGINT,
+    transaction_type VARCHAR(50),
+    account_number VARCHAR(64),
+    source VARCHAR(32) NOT NULL,
+    currency VARCHAR(3) NOT NULL REFERENCES currencies(code),
+    provenance VARCHAR(16) NOT NULL,
+    status VARCHAR(16) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'confirmed', 'rejected')),
+    resolved_by_admin BOOLEAN NOT NULL DEFAULT FALSE,
+    resolution_note TEXT,
+    resolved_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_pending_review_transactions_till ON pending_review_transactions(till_id, status, created_at);
+CREATE INDEX idx_pending_review_transactions_pending ON pending_review_transactions(created_at)
+    WHERE status = 'pending';
+-- Uploading a statement again doesn't hold its rows twice
+CREATE UNIQUE INDEX idx_pending_review_transactions_receipt
+    ON pending_review_transactions(till_id, receipt_number, timestamp)
+    WHERE status = 'pending';
+
+-- Rows held from photos so far; photos didn't record their currency, so
+-- they take the default
+INSERT INTO pending_review_transactions (till_id, image_id, flagged_by, reason, row_number, raw_text, confidence,
+                                         source, currency, provenance, created_at)
+SELECT i.till_id, i.id, 'ocr', r.reason, r.line, r.text, r.confidence, 'mpesa', 'KES', 'image', i.created_at
+FROM statement_images i,
+     jsonb_to_recordset(i.review_rows) AS r(line INTEGER, text TEXT, confidence SMALLINT, reason TEXT);
+
+ALTER TABLE statement_images DROP COLUMN review_rows;
@@ -0,0 +1,14 @@
+-- The last run of each job the worker schedules. Every worker reads it, so
+-- each scheduled time runs once however many workers there are.
+CREATE TABLE scheduled_jobs (
+    name VARCHAR(64) PRIMARY KEY,
+    schedule VARCHAR(128) NOT NULL, -- cron expression the last run was due by
+    last_scheduled_for TIMESTAMPTZ NOT NULL,
+    last_started_at TIMESTAMPTZ NOT NULL,
+    last_finished_at TIMESTAMPTZ,
+    last_status VARCHAR(16) NOT NULL CHECK (last_status IN ('running', 'succeeded', 'failed')),
+    last_output TEXT, -- the job's summary, or its error
+    last_duration_ms BIGINT,
+    last_worker_id VARCHAR(64) NOT NULL,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0
+);
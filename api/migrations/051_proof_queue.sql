@@ -0,0 +1,15 @@
+-- Sessions waiting for a worker. Redis's proof_queue list is only the fast
+-- path: a session stays here until a worker claims it, and a sweep pushes
+-- any the list lost back onto it.
+CREATE TABLE proof_queue (
+    session_id UUID PRIMARY KEY REFERENCES proof_sessions(id) ON DELETE CASCADE,
+    enqueued_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_proof_queue_enqueued ON proof_queue(enqueued_at);
+
+-- Sessions already waiting
+INSERT INTO proof_queue (session_id, enqueued_at)
+SELECT id, created_at
+FROM proof_sessions
+WHERE status = 'pending' AND lease_owner IS NULL;
@@ -0,0 +1,12 @@
+-- Jobs waiting to be pushed onto a Redis list, written in the same
+-- transaction as the rows they're for so a crash can't lose one. The
+-- worker's relay pushes them and deletes them once Redis has them, so a job
+-- may be pushed twice but is never dropped.
+CREATE TABLE job_outbox (
+    id BIGSERIAL PRIMARY KEY,
+    queue VARCHAR(64) NOT NULL, -- Redis list key
+    payload TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    attempts INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT
+);
@@ -0,0 +1,28 @@
+-- What businesses pay per proof and lenders per verification. Each plan
+-- includes a number a month; past that each one costs the overage price,
+-- or is refused when the plan has none.
+CREATE TABLE billing_plans (
+    code VARCHAR(32) PRIMARY KEY,
+    audience VARCHAR(16) NOT NULL CHECK (audience IN ('business', 'lender')),
+    name VARCHAR(64) NOT NULL,
+    included_per_month INTEGER NOT NULL CHECK (included_per_month >= 0),
+    overage_price_minor BIGINT CHECK (overage_price_minor >= 0), -- minor units of currency
+    currency VARCHAR(3) NOT NULL REFERENCES currencies(code),
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+INSERT INTO billing_plans (code, audience, name, included_per_month, overage_price_minor, currency) VALUES
+    ('free', 'business', 'Free', 3, NULL, 'KES'),
+    ('pay_as_you_go', 'business', 'Pay as you go', 3, 5000, 'KES'),
+    ('lender_trial', 'lender', 'Trial', 100, NULL, 'KES'),
+    ('lender_standard', 'lender', 'Standard', 1000, 2000, 'KES');
+
+ALTER TABLE users ADD COLUMN billing_plan VARCHAR(32) NOT NULL DEFAULT 'free' REFERENCES billing_plans(code);
+
+-- Lenders already verifying keep doing so without a limit they never agreed
+-- to; new ones start on the trial
+ALTER TABLE lenders ADD COLUMN billing_plan VARCHAR(32) NOT NULL DEFAULT 'lender_standard'
+    REFERENCES billing_plans(code);
+ALTER TABLE lenders ALTER COLUMN billing_plan SET DEFAULT 'lender_trial';
+
+CREATE INDEX idx_proof_sessions_user_created ON proof_sessions(user_id, created_at);
@@ -0,0 +1,22 @@
+-- Lipa na M-Pesa (STK push) payments for proofs past a plan's allowance.
+-- A succeeded payment pays for one proof; session_id records which.
+CREATE TABLE proof_payments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    phone_number VARCHAR(20) NOT NULL,
+    amount_minor BIGINT NOT NULL CHECK (amount_minor > 0), -- minor units of currency
+    currency VARCHAR(3) NOT NULL REFERENCES currencies(code),
+    status VARCHAR(16) NOT NULL DEFAULT 'pending' CHECK (status IN ('pending', 'succeeded', 'failed')),
+    merchant_request_id VARCHAR(64),
+    checkout_request_id VARCHAR(64) UNIQUE,
+    mpesa_receipt VARCHAR(32),
+    result_code INTEGER,
+    result_desc TEXT,
+    session_id UUID UNIQUE REFERENCES proof_sessions(id) ON DELETE SET NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    completed_at TIMESTAMPTZ
+);
+
+-- Succeeded payments not yet spent on a proof
+CREATE INDEX idx_proof_payments_unspent ON proof_payments(user_id, completed_at)
+    WHERE status = 'succeeded' AND session_id IS NULL;
@@ -0,0 +1,23 @@
+-- Codes partners hand out to merchants: a lender's, or an ambassador's.
+-- Proofs generated with a code are attributed to its partner; a coupon
+-- also lets the proof through without payment once the plan's allowance
+-- is used up.
+CREATE TABLE referral_codes (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    code VARCHAR(32) UNIQUE NOT NULL, -- stored upper case
+    lender_id UUID REFERENCES lenders(id) ON DELETE CASCADE,
+    ambassador_name VARCHAR(128),
+    waives_payment BOOLEAN NOT NULL DEFAULT FALSE,
+    max_redemptions INTEGER CHECK (max_redemptions > 0), -- NULL for unlimited
+    redemptions INTEGER NOT NULL DEFAULT 0,
+    expires_at TIMESTAMPTZ,
+    active BOOLEAN NOT NULL DEFAULT TRUE,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    -- Exactly one partner
+    CHECK ((lender_id IS NULL) <> (ambassador_name IS NULL))
+);
+
+CREATE INDEX idx_referral_codes_lender ON referral_codes(lender_id) WHERE lender_id IS NOT NULL;
+
+ALTER TABLE proof_sessions ADD COLUMN referral_code_id UUID REFERENCES referral_codes(id) ON DELETE SET NULL;
+CREATE INDEX idx_proof_sessions_referral_code ON proof_sessions(referral_code_id) WHERE referral_code_id IS NOT NULL;
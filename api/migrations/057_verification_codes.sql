@@ -0,0 +1,8 @@
+-- New verification codes are 26 Crockford base32 characters (128 random
+-- bits), generated in the API and retried there on the rare collision with
+-- the UNIQUE constraint on proof_sessions.verification_code.
+--
+-- Codes issued before this keep working unchanged: they are stored and
+-- matched exactly as before, so nothing is rewritten here. That constraint's
+-- own index serves every lookup, making this one redundant.
+DROP INDEX IF EXISTS idx_proof_sessions_code;
@@ -0,0 +1,16 @@
+-- Lender API keys are found by the 12 characters after their mcp_live_ or
+-- mcp_test_ prefix and checked against an Argon2id hash of the whole key.
+-- Keys issued before keep their SHA-256 key_hash until they are next used,
+-- when the API records their lookup id and Argon2id hash and clears it.
+ALTER TABLE lender_api_keys ALTER COLUMN key_hash DROP NOT NULL;
+
+ALTER TABLE lender_api_keys
+    ADD COLUMN lookup_id VARCHAR(16) UNIQUE,
+    ADD COLUMN secret_hash TEXT,
+    -- Set when the key is rotated; it keeps working until then
+    ADD COLUMN expires_at TIMESTAMPTZ,
+    -- The key this one replaced
+    ADD COLUMN rotated_from UUID REFERENCES lender_api_keys(id) ON DELETE SET NULL,
+    ADD CONSTRAINT lender_api_keys_hashed CHECK (
+        (lookup_id IS NOT NULL AND secret_hash IS NOT NULL) OR key_hash IS NOT NULL
+    );
@@ -0,0 +1,15 @@
+-- One row per sign-in on the app. A token is refused once its session is
+-- revoked, so an owner who loses a phone can sign it out remotely
+CREATE TABLE user_sessions (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    phone_number VARCHAR(20) NOT NULL, -- the phone that signed in, a linked one for uploaders
+    role VARCHAR(20) NOT NULL,
+    device_hint VARCHAR(200), -- the User-Agent it signed in with
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    last_used_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    expires_at TIMESTAMPTZ NOT NULL, -- when its token does
+    revoked_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_user_sessions_user ON user_sessions(user_id);
@@ -0,0 +1,21 @@
+-- Where each sign-in came from, so one from a device and network the phone
+-- hasn't used before is flagged, and when the session last confirmed a
+-- sensitive action with a fresh OTP
+ALTER TABLE user_sessions
+    ADD COLUMN ip_range VARCHAR(50), -- the client's /24 (IPv4) or /48 (IPv6)
+    ADD COLUMN anomalous BOOLEAN NOT NULL DEFAULT FALSE,
+    ADD COLUMN stepped_up_at TIMESTAMPTZ;
+
+-- Security events on an account: flagged sign-ins, step-up confirmations
+-- and the sensitive actions they allowed
+CREATE TABLE audit_log (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+    session_id UUID REFERENCES user_sessions(id) ON DELETE SET NULL,
+    event VARCHAR(64) NOT NULL,
+    ip_range VARCHAR(50),
+    details JSONB NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX idx_audit_log_user ON audit_log(user_id, created_at DESC);
@@ -0,0 +1,13 @@
+-- Indexes behind the admin proof search: newest first, by status or score,
+-- and over the words of an error message
+CREATE INDEX idx_proof_sessions_created ON proof_sessions(created_at DESC, id DESC);
+
+-- Replaces idx_proof_sessions_status, which it covers
+CREATE INDEX idx_proof_sessions_status_created ON proof_sessions(status, created_at DESC);
+DROP INDEX idx_proof_sessions_status;
+
+CREATE INDEX idx_proof_sessions_score ON proof_sessions(credit_score) WHERE credit_score IS NOT NULL;
+
+CREATE INDEX idx_proof_sessions_error_text ON proof_sessions
+    USING GIN (to_tsvector('simple', error_message))
+    WHERE error_message IS NOT NULL;
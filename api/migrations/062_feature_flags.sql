@@ -0,0 +1,15 @@
+-- Switches for risky features, changed at runtime through the admin API.
+-- A flag is on for a user when it's enabled and either lists them or puts
+-- them in its rollout percentage; a flag without a row is off
+CREATE TABLE feature_flags (
+    key VARCHAR(64) PRIMARY KEY,
+    description TEXT NOT NULL DEFAULT '',
+    enabled BOOLEAN NOT NULL DEFAULT FALSE, -- off for everyone when false
+    rollout_percentage SMALLINT NOT NULL DEFAULT 0 CHECK (rollout_percentage BETWEEN 0 AND 100),
+    user_ids UUID[] NOT NULL DEFAULT '{}', -- on for these whatever the percentage
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+-- Photo uploads were always available where OCR is configured
+INSERT INTO feature_flags (key, description, enabled, rollout_percentage)
+VALUES ('statement_photo_ocr', 'Reading uploaded statement photos with OCR', TRUE, 100);
@@ -0,0 +1,30 @@
+-- A candidate scoring policy run natively alongside the one proofs use, on
+-- a sample of sessions, before lenders are moved onto it
+CREATE TABLE scoring_experiments (
+    id UUID PRIMARY KEY DEFAULT uuid_generate_v4(),
+    currency VARCHAR(3) NOT NULL REFERENCES currencies(code),
+    -- NULL when the baseline is the currency's default thresholds
+    baseline_policy_id UUID REFERENCES scoring_policies(id),
+    -- Hex ScoringPolicy::hash of the baseline; sessions proving under it are sampled
+    baseline_policy_hash VARCHAR(64) NOT NULL,
+    candidate_policy_id UUID NOT NULL REFERENCES scoring_policies(id),
+    sample_percentage SMALLINT NOT NULL CHECK (sample_percentage BETWEEN 1 AND 100),
+    started_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    ended_at TIMESTAMPTZ
+);
+
+CREATE INDEX idx_scoring_experiments_running ON scoring_experiments(currency, baseline_policy_hash)
+    WHERE ended_at IS NULL;
+
+-- Both policies' results for one sampled session. Threshold proofs only
+-- keep the outcomes, since their owners withheld the score
+CREATE TABLE scoring_comparisons (
+    experiment_id UUID NOT NULL REFERENCES scoring_experiments(id) ON DELETE CASCADE,
+    session_id UUID NOT NULL REFERENCES proof_sessions(id) ON DELETE CASCADE,
+    baseline_score INTEGER,
+    candidate_score INTEGER,
+    baseline_meets_threshold BOOLEAN,
+    candidate_meets_threshold BOOLEAN,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (experiment_id, session_id)
+);
@@ -0,0 +1,6 @@
+-- Verifying no longer takes a till over from the account that holds it
+-- verified: the claim waits here for an admin to settle who owns it
+ALTER TABLE business_tills ADD COLUMN ownership_review_requested_at TIMESTAMPTZ;
+
+CREATE INDEX idx_tills_ownership_review ON business_tills(ownership_review_requested_at)
+    WHERE ownership_review_requested_at IS NOT NULL;
@@ -0,0 +1,82 @@
+-- Since 015 the dedup key was (till_id, reference, timestamp), so a receipt
+-- seen again with another timestamp was stored, and scored, twice. A unique
+-- key on a partitioned table must include the partition key, so each till's
+-- receipts are claimed here instead, by an insert trigger on transactions.
+CREATE TABLE transaction_receipts (
+    till_id UUID NOT NULL REFERENCES business_tills(id) ON DELETE CASCADE,
+    reference VARCHAR(255) NOT NULL, -- Hashed, as in transactions
+    PRIMARY KEY (till_id, reference)
+);
+
+-- Copies already stored collapse onto one row per receipt: the disputed
+-- copy if there is one, so the dispute survives, else the first imported
+CREATE TEMPORARY TABLE duplicate_transactions ON COMMIT DROP AS
+SELECT t.id, t.timestamp, t.till_id
+FROM transactions t
+JOIN (
+    SELECT DISTINCT ON (t.till_id, t.reference) t.id, t.till_id, t.reference
+    FROM transactions t
+    ORDER BY t.till_id, t.reference,
+             EXISTS (SELECT 1 FROM disputes d WHERE d.transaction_id = t.id) DESC,
+             t.created_at, t.id
+) keeper ON keeper.till_id = t.till_id AND keeper.reference = t.reference AND keeper.id <> t.id;
+
+DELETE FROM transactions t
+USING duplicate_transactions dup
+WHERE t.id = dup.id AND t.timestamp = dup.timestamp;
+
+-- The monthly aggregates counted every copy
+DELETE FROM till_monthly_aggregates
+WHERE till_id IN (SELECT DISTINCT till_id FROM duplicate_transactions);
+
+INSERT INTO till_monthly_aggregates
+    (till_id, month, source, transaction_count, volume_cents, first_transaction_at, last_transaction_at)
+SELECT till_id,
+       date_trunc('month', timestamp AT TIME ZONE 'UTC')::DATE,
+       source,
+       COUNT(*),
+       SUM(amount),
+       MIN(timestamp),
+       MAX(timestamp)
+FROM transactions
+WHERE till_id IN (SELECT DISTINCT till_id FROM duplicate_transactions)
+GROUP BY 1, 2, 3;
+
+INSERT INTO transaction_receipts (till_id, reference)
+SELECT DISTINCT till_id, reference FROM transactions;
+
+-- Skips a row whose receipt the till already has, whatever its timestamp,
+-- the way ON CONFLICT DO NOTHING would; a skipped row fires no AFTER INSERT
+-- triggers. Rehashing a receipt moves its claim along with it.
+CREATE OR REPLACE FUNCTION claim_transaction_receipt()
+RETURNS TRIGGER AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO transaction_receipts (till_id, reference)
+        VALUES (NEW.till_id, NEW.reference)
+        ON CONFLICT DO NOTHING;
+        IF NOT FOUND THEN
+            RETURN NULL;
+        END IF;
+    ELSIF NEW.till_id <> OLD.till_id OR NEW.reference <> OLD.reference THEN
+        UPDATE transaction_receipts
+        SET till_id = NEW.till_id, reference = NEW.reference
+        WHERE till_id = OLD.till_id AND reference = OLD.reference;
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_claim_receipt BEFORE INSERT OR UPDATE OF till_id, reference ON transactions
+    FOR EACH ROW EXECUTE FUNCTION claim_transaction_receipt();
+
+CREATE OR REPLACE FUNCTION release_transaction_receipt()
+RETURNS TRIGGER AS $$
+BEGIN
+    DELETE FROM transaction_receipts WHERE till_id = OLD.till_id AND reference = OLD.reference;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER transactions_release_receipt AFTER DELETE ON transactions
+    FOR EACH ROW EXECUTE FUNCTION release_transaction_receipt();
@@ -0,0 +1,54 @@
+-- Until 036, uploaded CSV/spreadsheet statements and Daraja pulls read times
+-- without an offset as UTC, though they are wall-clock times: the till's for
+-- uploads, Nairobi's for Daraja. Rows imported then are moved to the instant
+-- they name, so re-uploads line up with them. Simulator and SMS rows were
+-- already placed correctly and carry raw data saying so.
+CREATE TEMPORARY TABLE shifted_transactions ON COMMIT DROP AS
+SELECT t.id,
+       t.till_id,
+       t.timestamp AS stored_at,
+       (t.timestamp AT TIME ZONE 'UTC')
+           AT TIME ZONE CASE WHEN t.provenance = 'daraja' THEN 'Africa/Nairobi' ELSE bt.timezone END AS timestamp
+FROM transactions t
+JOIN business_tills bt ON bt.id = t.till_id
+WHERE t.created_at < (SELECT installed_on FROM _sqlx_migrations WHERE version = 36)
+  AND (t.provenance = 'daraja' OR (t.provenance = 'csv' AND t.raw_data IS NULL));
+
+-- Changing the partition key moves rows between partitions as a delete and
+-- an insert, which must not release or re-claim receipts, re-derive kinds or
+-- count rows into the aggregates again; disputes follow their rows
+ALTER TABLE disputes DROP CONSTRAINT disputes_transaction_fkey;
+ALTER TABLE transactions DISABLE TRIGGER USER;
+
+UPDATE transactions t
+SET timestamp = s.timestamp
+FROM shifted_transactions s
+WHERE t.id = s.id AND t.timestamp = s.stored_at;
+
+UPDATE disputes d
+SET transaction_timestamp = s.timestamp
+FROM shifted_transactions s
+WHERE d.transaction_id = s.id;
+
+ALTER TABLE transactions ENABLE TRIGGER USER;
+ALTER TABLE disputes
+    ADD CONSTRAINT disputes_transaction_fkey
+    FOREIGN KEY (transaction_id, transaction_timestamp)
+    REFERENCES transactions (id, timestamp) ON DELETE CASCADE;
+
+-- Rows near a month's edge may have changed month
+DELETE FROM till_monthly_aggregates
+WHERE till_id IN (SELECT DISTINCT till_id FROM shifted_transactions);
+
+INSERT INTO till_monthly_aggregates
+    (till_id, month, source, transaction_count, volume_cents, first_transaction_at, last_transaction_at)
+SELECT till_id,
+       date_trunc('month', timestamp AT TIME ZONE 'UTC')::DATE,
+       source,
+       COUNT(*),
+       SUM(amount),
+       MIN(timestamp),
+       MAX(timestamp)
+FROM transactions
+WHERE till_id IN (SELECT DISTINCT till_id FROM shifted_transactions)
+GROUP BY 1, 2, 3;
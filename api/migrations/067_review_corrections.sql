@@ -0,0 +1,10 @@
+-- Held rows the owner corrected by hand before confirming them
+ALTER TABLE transactions DROP CONSTRAINT transactions_provenance_check;
+ALTER TABLE transactions ADD CONSTRAINT transactions_provenance_check
+    CHECK (provenance IN ('csv', 'pdf', 'sms', 'partner', 'c2b', 'daraja', 'image', 'corrected'));
+
+-- The upload a held row came from, so confirming it counts the upload's
+-- score towards proofs over the row; unset for rows held before it was
+-- recorded
+ALTER TABLE pending_review_transactions
+    ADD COLUMN statement_import_id UUID REFERENCES statement_imports(id) ON DELETE SET NULL;
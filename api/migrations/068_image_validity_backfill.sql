@@ -0,0 +1,19 @@
+-- 021 gave every image valid_from NOW(), so the image registered when it ran,
+-- and any registered after proving with it had begun, rejected the receipts
+-- proved before. Sessions without an image ID verify against the first
+-- image, so it's valid from the earliest session; the rest from the
+-- earliest session that records them.
+UPDATE allowed_image_ids a
+SET valid_from = LEAST(a.valid_from, s.earliest)
+FROM (
+    SELECT ps.image_id, MIN(ps.created_at) AS earliest
+    FROM proof_sessions ps
+    WHERE ps.image_id IS NOT NULL
+    GROUP BY ps.image_id
+) s
+WHERE s.image_id = a.image_id;
+
+UPDATE allowed_image_ids
+SET valid_from = LEAST(valid_from, (SELECT MIN(created_at) FROM proof_sessions))
+WHERE image_id = (SELECT image_id FROM allowed_image_ids ORDER BY created_at, image_id LIMIT 1)
+  AND EXISTS (SELECT 1 FROM proof_sessions);
@@ -0,0 +1,22 @@
+-- A payment spent on a session that then failed paid for nothing, so it's
+-- released for the user's next proof, whichever way the session failed
+CREATE OR REPLACE FUNCTION release_failed_session_payment()
+RETURNS TRIGGER AS $$
+BEGIN
+    UPDATE proof_payments SET session_id = NULL WHERE session_id = NEW.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER proof_sessions_release_payment AFTER UPDATE OF status ON proof_sessions
+    FOR EACH ROW WHEN (NEW.status = 'failed' AND OLD.status <> 'failed')
+    EXECUTE FUNCTION release_failed_session_payment();
+
+-- Payments already spent on sessions that failed
+UPDATE proof_payments p
+SET session_id = NULL
+FROM proof_sessions ps
+WHERE ps.id = p.session_id AND ps.status = 'failed';
+
+-- Pending pushes the reconciliation job asks Daraja about
+CREATE INDEX idx_proof_payments_pending ON proof_payments(created_at) WHERE status = 'pending';
@@ -0,0 +1,6 @@
+-- The earliest and latest row of each upload, so a proof only takes the
+-- scores of uploads over the period it covers. Unset on uploads from before
+-- they were recorded, which count towards every proof as before
+ALTER TABLE statement_imports
+    ADD COLUMN first_transaction_at TIMESTAMPTZ,
+    ADD COLUMN last_transaction_at TIMESTAMPTZ;
@@ -0,0 +1,23 @@
+-- Import jobs ran on a task of the API process that received them, so a
+-- restart lost the job and left it 'running'. They're now queued for the
+-- worker, and carry what it needs to run them.
+ALTER TABLE import_jobs
+    ADD COLUMN source VARCHAR(20),
+    ADD COLUMN currency VARCHAR(3),
+    -- The parsed batch, dropped once the job finishes
+    ADD COLUMN entries JSONB,
+    ADD COLUMN attempts INTEGER NOT NULL DEFAULT 0,
+    -- When a job no worker has finished goes back on the queue
+    ADD COLUMN retry_at TIMESTAMPTZ;
+
+-- Jobs interrupted before this can't be run again; their batch is gone
+UPDATE import_jobs
+SET status = 'failed', error = 'The import was interrupted; resend the batch', finished_at = NOW()
+WHERE status = 'running';
+
+ALTER TABLE import_jobs DROP CONSTRAINT import_jobs_status_check;
+ALTER TABLE import_jobs ADD CONSTRAINT import_jobs_status_check
+    CHECK (status IN ('pending', 'running', 'completed', 'failed'));
+ALTER TABLE import_jobs ALTER COLUMN status SET DEFAULT 'pending';
+
+CREATE INDEX idx_import_jobs_retry ON import_jobs(retry_at) WHERE status IN ('pending', 'running');
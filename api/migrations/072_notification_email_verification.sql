@@ -0,0 +1,3 @@
+-- Email notifications go only to addresses the owner has proven they read.
+-- Addresses set before this are unverified until the owner confirms them.
+ALTER TABLE notification_preferences ADD COLUMN email_verified_at TIMESTAMPTZ;
@@ -0,0 +1,13 @@
+-- Without a cap per merchant, one merchant could redeem a coupon for every
+-- proof. Coupons are capped at one redemption each unless set otherwise;
+-- referral codes that only attribute stay unlimited.
+ALTER TABLE referral_codes
+    ADD COLUMN max_redemptions_per_user INTEGER CHECK (max_redemptions_per_user > 0); -- NULL for unlimited
+
+UPDATE referral_codes SET max_redemptions_per_user = 1 WHERE waives_payment;
+
+ALTER TABLE referral_codes ADD CONSTRAINT referral_codes_coupon_capped
+    CHECK (NOT waives_payment OR max_redemptions_per_user IS NOT NULL);
+
+CREATE INDEX idx_proof_sessions_referral_code_user ON proof_sessions(referral_code_id, user_id)
+    WHERE referral_code_id IS NOT NULL;
@@ -0,0 +1,19 @@
+-- ScoringPolicy::hash now binds the currency, as the v2 hash of the v1 one,
+-- since the same thresholds mean different money in another currency.
+-- Proofs keep the hashes they committed; stored policies and experiment
+-- baselines move to v2, which is what new proofs commit.
+UPDATE scoring_policies
+SET policy_hash = encode(sha256(
+    convert_to('scoring-policy/v2', 'UTF8')
+    || set_byte('\x00000000'::bytea, 0, octet_length(currency))
+    || convert_to(currency, 'UTF8')
+    || decode(policy_hash, 'hex')
+), 'hex');
+
+UPDATE scoring_experiments
+SET baseline_policy_hash = encode(sha256(
+    convert_to('scoring-policy/v2', 'UTF8')
+    || set_byte('\x00000000'::bytea, 0, octet_length(currency))
+    || convert_to(currency, 'UTF8')
+    || decode(baseline_policy_hash, 'hex')
+), 'hex');
@@ -1,21 +1,20 @@
 use api::config::Config;
 use api::db;
+use api::services::storage::StorageService;
 use api::worker::Worker;
-use tracing_subscriber;
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    tracing_subscriber::fmt()
-        .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
-        .init();
-
     dotenv::dotenv().ok();
 
+    api::telemetry::init("api=info");
+
     let config = Config::from_env()?;
-    let pool = db::create_pool(&config.database_url).await?;
+    let pool = db::create_pool(&config).await?;
     let redis_client = redis::Client::open(config.redis_url.as_str())?;
+    let storage = StorageService::create_backend(&config.storage_type, &config)?;
 
-    let worker = Worker::new(pool, redis_client, config);
+    let worker = std::sync::Arc::new(Worker::new(pool, redis_client, config, storage));
     worker.run().await?;
 
     Ok(())
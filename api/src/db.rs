@@ -1,15 +0,0 @@
-use sqlx::{postgres::PgPoolOptions, PgPool};
-
-pub async fn create_pool(database_url: &str) -> anyhow::Result<PgPool> {
-    let pool = PgPoolOptions::new()
-        .max_connections(10)
-        .connect(database_url)
-        .await?;
-
-    Ok(pool)
-}
-
-
-
-
-
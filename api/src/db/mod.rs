@@ -0,0 +1,96 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use sqlx::{
+    postgres::{PgConnectOptions, PgPoolOptions},
+    Connection, PgConnection, PgPool,
+};
+
+use crate::config::Config;
+
+pub mod repos;
+
+const REPLICA_CHECK_INTERVAL: Duration = Duration::from_secs(5);
+const REPLICA_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+pub async fn create_pool(config: &Config) -> anyhow::Result<PgPool> {
+    let pool = pool_options(config)
+        .connect_with(connect_options(&config.database_url, config)?)
+        .await?;
+
+    Ok(pool)
+}
+
+/// Runs pending migrations on a connection of their own, without the pool's
+/// statement timeout: backfills over large tables outlast any query a
+/// request makes.
+pub async fn migrate(config: &Config) -> anyhow::Result<()> {
+    let options: PgConnectOptions = config.database_url.parse()?;
+    let mut conn = PgConnection::connect_with(&options).await?;
+    sqlx::migrate!("./migrations").run(&mut conn).await?;
+    conn.close().await?;
+    Ok(())
+}
+
+fn pool_options(config: &Config) -> PgPoolOptions {
+    PgPoolOptions::new()
+        .max_connections(config.db_max_connections)
+        .acquire_timeout(Duration::from_secs(config.db_acquire_timeout_secs))
+}
+
+/// Every session gets a server-side statement timeout so a runaway query
+/// releases its connection instead of starving the pool.
+fn connect_options(database_url: &str, config: &Config) -> anyhow::Result<PgConnectOptions> {
+    let options: PgConnectOptions = database_url.parse()?;
+    Ok(options.options([("statement_timeout", config.db_statement_timeout_ms.to_string())]))
+}
+
+/// Read replica used by read-only handlers. A background probe marks it
+/// unhealthy when it stops answering, and reads fall back to the primary
+/// until it recovers.
+#[derive(Clone)]
+pub struct ReadReplica {
+    pool: PgPool,
+    healthy: Arc<AtomicBool>,
+}
+
+impl ReadReplica {
+    /// Connects lazily so an unavailable replica never blocks startup.
+    pub fn connect(database_url: &str, config: &Config) -> anyhow::Result<Self> {
+        let pool = pool_options(config).connect_lazy_with(connect_options(database_url, config)?);
+        let replica = Self {
+            pool,
+            healthy: Arc::new(AtomicBool::new(false)),
+        };
+
+        let probe = replica.clone();
+        tokio::spawn(async move {
+            loop {
+                probe.check().await;
+                tokio::time::sleep(REPLICA_CHECK_INTERVAL).await;
+            }
+        });
+
+        Ok(replica)
+    }
+
+    async fn check(&self) {
+        let ok = matches!(
+            tokio::time::timeout(REPLICA_CHECK_TIMEOUT, sqlx::query("SELECT 1").execute(&self.pool)).await,
+            Ok(Ok(_))
+        );
+        let was_healthy = self.healthy.swap(ok, Ordering::Relaxed);
+        if was_healthy != ok {
+            if ok {
+                tracing::info!("Read replica is available; routing reads to it");
+            } else {
+                tracing::warn!("Read replica is unavailable; routing reads to the primary");
+            }
+        }
+    }
+
+    pub fn pool(&self) -> Option<&PgPool> {
+        self.healthy.load(Ordering::Relaxed).then_some(&self.pool)
+    }
+}
@@ -0,0 +1,101 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::MerchantAccessToken;
+
+pub struct AccessTokenRepo;
+
+impl AccessTokenRepo {
+    /// Every token the merchant has issued, newest first, revoked ones
+    /// included.
+    pub async fn list_for_user(db: &PgPool, user_id: Uuid) -> Result<Vec<MerchantAccessToken>, sqlx::Error> {
+        sqlx::query_as::<_, MerchantAccessToken>(
+            r#"
+            SELECT id, user_id, name, token_prefix, scopes, created_at, expires_at, last_used_at, revoked_at
+            FROM merchant_access_tokens
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Tokens that still work: not revoked and not expired.
+    pub async fn count_active(db: &PgPool, user_id: Uuid) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM merchant_access_tokens
+            WHERE user_id = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(db)
+        .await
+    }
+
+    pub async fn create(
+        db: &PgPool,
+        user_id: Uuid,
+        name: &str,
+        token_hash: &str,
+        token_prefix: &str,
+        scopes: &[String],
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<MerchantAccessToken, sqlx::Error> {
+        sqlx::query_as::<_, MerchantAccessToken>(
+            r#"
+            INSERT INTO merchant_access_tokens (user_id, name, token_hash, token_prefix, scopes, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, user_id, name, token_prefix, scopes, created_at, expires_at, last_used_at, revoked_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(name)
+        .bind(token_hash)
+        .bind(token_prefix)
+        .bind(scopes)
+        .bind(expires_at)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Revokes one of the merchant's tokens. None if it isn't theirs or was
+    /// already revoked.
+    pub async fn revoke(
+        db: &PgPool,
+        token_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<MerchantAccessToken>, sqlx::Error> {
+        sqlx::query_as::<_, MerchantAccessToken>(
+            r#"
+            UPDATE merchant_access_tokens
+            SET revoked_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL
+            RETURNING id, user_id, name, token_prefix, scopes, created_at, expires_at, last_used_at, revoked_at
+            "#,
+        )
+        .bind(token_id)
+        .bind(user_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// The live token with this hash, marked as just used.
+    pub async fn authenticate(db: &PgPool, token_hash: &str) -> Result<Option<MerchantAccessToken>, sqlx::Error> {
+        sqlx::query_as::<_, MerchantAccessToken>(
+            r#"
+            UPDATE merchant_access_tokens
+            SET last_used_at = NOW()
+            WHERE token_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())
+            RETURNING id, user_id, name, token_prefix, scopes, created_at, expires_at, last_used_at, revoked_at
+            "#,
+        )
+        .bind(token_hash)
+        .fetch_optional(db)
+        .await
+    }
+}
@@ -0,0 +1,207 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// A lender API key as its owner sees it; never the key or its hash.
+#[derive(Debug, FromRow)]
+pub struct LenderApiKey {
+    pub id: Uuid,
+    pub lender_id: Uuid,
+    pub key_prefix: String,
+    pub sandbox: bool,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    /// When a rotated key stops working
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+    /// The key this one replaced
+    pub rotated_from: Option<Uuid>,
+}
+
+/// A usable key's row, to check a presented key against.
+#[derive(FromRow)]
+pub struct StoredApiKey {
+    pub id: Uuid,
+    pub lender_id: Uuid,
+    pub sandbox: bool,
+    /// Argon2id PHC string; None for a key issued before Argon2 that hasn't
+    /// been used since
+    pub secret_hash: Option<String>,
+}
+
+const KEY_COLUMNS: &str =
+    "id, lender_id, key_prefix, sandbox, created_at, last_used_at, expires_at, revoked_at, rotated_from";
+
+/// Keys that authenticate: neither revoked nor past a rotation's grace.
+const USABLE: &str = "revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW())";
+
+pub struct ApiKeyRepo;
+
+impl ApiKeyRepo {
+    /// Returns None when another key already has `lookup_id`.
+    pub async fn create(
+        executor: impl sqlx::PgExecutor<'_>,
+        lender_id: Uuid,
+        lookup_id: &str,
+        secret_hash: &str,
+        key_prefix: &str,
+        sandbox: bool,
+        rotated_from: Option<Uuid>,
+    ) -> Result<Option<LenderApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, LenderApiKey>(&format!(
+            r#"
+            INSERT INTO lender_api_keys (lender_id, lookup_id, secret_hash, key_prefix, sandbox, rotated_from)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (lookup_id) DO NOTHING
+            RETURNING {}
+            "#,
+            KEY_COLUMNS
+        ))
+        .bind(lender_id)
+        .bind(lookup_id)
+        .bind(secret_hash)
+        .bind(key_prefix)
+        .bind(sandbox)
+        .bind(rotated_from)
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// The lender's keys, newest first, revoked and expired ones included.
+    pub async fn list_for_lender(db: &PgPool, lender_id: Uuid) -> Result<Vec<LenderApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, LenderApiKey>(&format!(
+            "SELECT {} FROM lender_api_keys WHERE lender_id = $1 ORDER BY created_at DESC",
+            KEY_COLUMNS
+        ))
+        .bind(lender_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// The usable key a presented key's lookup id names.
+    pub async fn find_usable(db: &PgPool, lookup_id: &str) -> Result<Option<StoredApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, StoredApiKey>(&format!(
+            "SELECT id, lender_id, sandbox, secret_hash FROM lender_api_keys WHERE lookup_id = $1 AND {}",
+            USABLE
+        ))
+        .bind(lookup_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// The usable pre-Argon2 key with this SHA-256 hash.
+    pub async fn find_legacy(db: &PgPool, key_hash: &str) -> Result<Option<StoredApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, StoredApiKey>(&format!(
+            r#"
+            SELECT id, lender_id, sandbox, secret_hash
+            FROM lender_api_keys
+            WHERE key_hash = $1 AND lookup_id IS NULL AND {}
+            "#,
+            USABLE
+        ))
+        .bind(key_hash)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Moves a pre-Argon2 key onto its lookup id and Argon2id hash, dropping
+    /// the SHA-256 one. Returns false if another key has the lookup id, in
+    /// which case it stays as it was.
+    pub async fn upgrade_legacy(
+        db: &PgPool,
+        id: Uuid,
+        lookup_id: &str,
+        secret_hash: &str,
+    ) -> Result<bool, sqlx::Error> {
+        let updated = sqlx::query(
+            r#"
+            UPDATE lender_api_keys
+            SET lookup_id = $2, secret_hash = $3, key_hash = NULL
+            WHERE id = $1 AND lookup_id IS NULL
+              AND NOT EXISTS (SELECT 1 FROM lender_api_keys WHERE lookup_id = $2)
+            "#,
+        )
+        .bind(id)
+        .bind(lookup_id)
+        .bind(secret_hash)
+        .execute(db)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    pub async fn mark_used(db: &PgPool, id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE lender_api_keys SET last_used_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Sets a usable key to stop working at `expires_at`, or sooner if it
+    /// already would, in the caller's transaction. None if the lender has no
+    /// such usable key.
+    pub async fn expire(
+        executor: impl sqlx::PgExecutor<'_>,
+        id: Uuid,
+        lender_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<Option<LenderApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, LenderApiKey>(&format!(
+            r#"
+            UPDATE lender_api_keys
+            SET expires_at = LEAST(COALESCE(expires_at, $3), $3)
+            WHERE id = $1 AND lender_id = $2 AND {}
+            RETURNING {}
+            "#,
+            USABLE, KEY_COLUMNS
+        ))
+        .bind(id)
+        .bind(lender_id)
+        .bind(expires_at)
+        .fetch_optional(executor)
+        .await
+    }
+
+    /// Revokes one of the lender's keys along with every key rotated from
+    /// it, so a leaked key can't be rotated into one that outlives it. The
+    /// next request with any of them is refused. `sandbox` limits it to keys
+    /// of that kind. The key comes first, then its successors; empty if it
+    /// isn't theirs or was already revoked.
+    pub async fn revoke(
+        db: &PgPool,
+        id: Uuid,
+        lender_id: Uuid,
+        sandbox: Option<bool>,
+    ) -> Result<Vec<LenderApiKey>, sqlx::Error> {
+        sqlx::query_as::<_, LenderApiKey>(&format!(
+            r#"
+            WITH RECURSIVE chain AS (
+                SELECT id
+                FROM lender_api_keys
+                WHERE id = $1 AND lender_id = $2 AND revoked_at IS NULL
+                  AND ($3::BOOLEAN IS NULL OR sandbox = $3)
+                UNION
+                SELECT k.id
+                FROM lender_api_keys k
+                JOIN chain ON k.rotated_from = chain.id
+            ),
+            revoked AS (
+                UPDATE lender_api_keys k
+                SET revoked_at = NOW()
+                FROM chain
+                WHERE k.id = chain.id AND k.revoked_at IS NULL
+                RETURNING k.*
+            )
+            SELECT {}
+            FROM revoked
+            ORDER BY id <> $1, created_at
+            "#,
+            KEY_COLUMNS
+        ))
+        .bind(id)
+        .bind(lender_id)
+        .bind(sandbox)
+        .fetch_all(db)
+        .await
+    }
+}
@@ -0,0 +1,80 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug, FromRow)]
+pub struct AuditEvent {
+    pub id: Uuid,
+    pub session_id: Option<Uuid>,
+    /// e.g. "login.anomalous" or "step_up.confirmed"
+    pub event: String,
+    pub ip_range: Option<String>,
+    pub details: serde_json::Value,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct AuditRepo;
+
+impl AuditRepo {
+    pub async fn record(
+        executor: impl sqlx::PgExecutor<'_>,
+        user_id: Uuid,
+        session_id: Option<Uuid>,
+        event: &str,
+        ip_range: Option<&str>,
+        details: serde_json::Value,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO audit_log (user_id, session_id, event, ip_range, details)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(user_id)
+        .bind(session_id)
+        .bind(event)
+        .bind(ip_range)
+        .bind(details)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+
+    /// How many times the account recorded `event` since `since`.
+    pub async fn count_since(
+        db: &PgPool,
+        user_id: Uuid,
+        event: &str,
+        since: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM audit_log
+            WHERE user_id = $1 AND event = $2 AND created_at >= $3
+            "#,
+        )
+        .bind(user_id)
+        .bind(event)
+        .bind(since)
+        .fetch_one(db)
+        .await
+    }
+
+    /// The account's most recent events, newest first.
+    pub async fn list_for_user(db: &PgPool, user_id: Uuid, limit: i64) -> Result<Vec<AuditEvent>, sqlx::Error> {
+        sqlx::query_as::<_, AuditEvent>(
+            r#"
+            SELECT id, session_id, event, ip_range, details, created_at
+            FROM audit_log
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    }
+}
@@ -0,0 +1,161 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// What a business pays per proof, or a lender per verification.
+#[derive(Debug, Clone, FromRow)]
+pub struct BillingPlan {
+    pub code: String,
+    /// "business" or "lender"
+    pub audience: String,
+    pub name: String,
+    pub included_per_month: i32,
+    /// Price of each one past the included ones; None refuses them instead
+    pub overage_price_minor: Option<i64>,
+    pub currency: String,
+}
+
+/// A lender key's verifications in a period.
+#[derive(Debug, FromRow)]
+pub struct KeyUsage {
+    /// None for verifications by keys since deleted
+    pub key_id: Option<Uuid>,
+    pub key_prefix: Option<String>,
+    pub verifications: i64,
+}
+
+const PLAN_COLUMNS: &str = "bp.code, bp.audience, bp.name, bp.included_per_month, bp.overage_price_minor, bp.currency";
+
+pub struct BillingRepo;
+
+impl BillingRepo {
+    pub async fn plan_for_user(
+        executor: impl sqlx::PgExecutor<'_>,
+        user_id: Uuid,
+    ) -> Result<Option<BillingPlan>, sqlx::Error> {
+        sqlx::query_as::<_, BillingPlan>(&format!(
+            "SELECT {} FROM users u JOIN billing_plans bp ON bp.code = u.billing_plan WHERE u.id = $1",
+            PLAN_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_optional(executor)
+        .await
+    }
+
+    pub async fn plan_for_lender(db: &PgPool, lender_id: Uuid) -> Result<Option<BillingPlan>, sqlx::Error> {
+        sqlx::query_as::<_, BillingPlan>(&format!(
+            "SELECT {} FROM lenders l JOIN billing_plans bp ON bp.code = l.billing_plan WHERE l.id = $1",
+            PLAN_COLUMNS
+        ))
+        .bind(lender_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Proofs the user requested since `since`. Failed proofs aren't
+    /// counted.
+    pub async fn proofs_since(
+        executor: impl sqlx::PgExecutor<'_>,
+        user_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM proof_sessions
+            WHERE user_id = $1 AND created_at >= $2 AND status <> 'failed'
+            "#,
+        )
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Holds the user's row for the transaction, so proofs started at the
+    /// same time are counted one after the other.
+    pub async fn lock_user(executor: impl sqlx::PgExecutor<'_>, user_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("SELECT id FROM users WHERE id = $1 FOR UPDATE")
+            .bind(user_id)
+            .fetch_optional(executor)
+            .await?;
+        Ok(())
+    }
+
+    /// The lender's metered verifications since `since`, per key: those
+    /// that reached a proof. Sandbox keys don't record verifications, so
+    /// they're never counted.
+    pub async fn verifications_since(
+        db: &PgPool,
+        lender_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<Vec<KeyUsage>, sqlx::Error> {
+        sqlx::query_as::<_, KeyUsage>(
+            r#"
+            SELECT v.key_id, k.key_prefix, COUNT(*) AS verifications
+            FROM lender_verifications v
+            LEFT JOIN lender_api_keys k ON k.id = v.key_id
+            WHERE v.lender_id = $1 AND v.created_at >= $2 AND v.status IN ('valid', 'invalid')
+            GROUP BY v.key_id, k.key_prefix
+            ORDER BY verifications DESC
+            "#,
+        )
+        .bind(lender_id)
+        .bind(since)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Holds the lender's row for the transaction, so verifications metered
+    /// at the same time are counted one after the other.
+    pub async fn lock_lender(executor: impl sqlx::PgExecutor<'_>, lender_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("SELECT id FROM lenders WHERE id = $1 FOR UPDATE")
+            .bind(lender_id)
+            .fetch_optional(executor)
+            .await?;
+        Ok(())
+    }
+
+    /// The lender's metered verifications since `since`, across its keys.
+    pub async fn metered_verifications_since(
+        executor: impl sqlx::PgExecutor<'_>,
+        lender_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM lender_verifications
+            WHERE lender_id = $1 AND created_at >= $2 AND status IN ('valid', 'invalid')
+            "#,
+        )
+        .bind(lender_id)
+        .bind(since)
+        .fetch_one(executor)
+        .await
+    }
+
+    pub async fn record_verification(
+        executor: impl sqlx::PgExecutor<'_>,
+        lender_id: Uuid,
+        key_id: Uuid,
+        verification_code: &str,
+        status: &str,
+        credit_score: Option<i32>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO lender_verifications (lender_id, key_id, verification_code, status, credit_score)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(lender_id)
+        .bind(key_id)
+        .bind(verification_code)
+        .bind(status)
+        .bind(credit_score)
+        .execute(executor)
+        .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,175 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::ProofConsent;
+
+pub struct ConsentRepo;
+
+impl ConsentRepo {
+    /// The lender's most recent request for a proof, whatever came of it.
+    pub async fn latest(db: &PgPool, session_id: Uuid, lender_id: Uuid) -> Result<Option<ProofConsent>, sqlx::Error> {
+        sqlx::query_as::<_, ProofConsent>(
+            r#"
+            SELECT c.id, c.session_id, ps.verification_code, c.lender_id, l.name AS lender_name,
+                   CASE WHEN c.status IN ('pending', 'approved') AND c.expires_at <= NOW() THEN 'expired'
+                        ELSE c.status END AS status,
+                   c.requested_at, c.decided_at, c.expires_at
+            FROM proof_consents c
+            JOIN proof_sessions ps ON ps.id = c.session_id
+            JOIN lenders l ON l.id = c.lender_id
+            WHERE c.session_id = $1 AND c.lender_id = $2
+            ORDER BY c.requested_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(session_id)
+        .bind(lender_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Whether the owner has approved the lender seeing the proof, and
+    /// that approval is still in force.
+    pub async fn is_approved(db: &PgPool, session_id: Uuid, lender_id: Uuid) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM proof_consents
+                WHERE session_id = $1 AND lender_id = $2 AND status = 'approved' AND expires_at > NOW()
+            )
+            "#,
+        )
+        .bind(session_id)
+        .bind(lender_id)
+        .fetch_one(db)
+        .await
+    }
+
+    pub async fn create(
+        db: &PgPool,
+        session_id: Uuid,
+        lender_id: Uuid,
+        expires_at: DateTime<Utc>,
+    ) -> Result<ProofConsent, sqlx::Error> {
+        sqlx::query_as::<_, ProofConsent>(
+            r#"
+            WITH created AS (
+                INSERT INTO proof_consents (session_id, lender_id, expires_at)
+                VALUES ($1, $2, $3)
+                RETURNING *
+            )
+            SELECT c.id, c.session_id, ps.verification_code, c.lender_id, l.name AS lender_name, c.status,
+                   c.requested_at, c.decided_at, c.expires_at
+            FROM created c
+            JOIN proof_sessions ps ON ps.id = c.session_id
+            JOIN lenders l ON l.id = c.lender_id
+            "#,
+        )
+        .bind(session_id)
+        .bind(lender_id)
+        .bind(expires_at)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Every request made for the user's proofs, newest first.
+    pub async fn list_for_user(db: &PgPool, user_id: Uuid) -> Result<Vec<ProofConsent>, sqlx::Error> {
+        sqlx::query_as::<_, ProofConsent>(
+            r#"
+            SELECT c.id, c.session_id, ps.verification_code, c.lender_id, l.name AS lender_name,
+                   CASE WHEN c.status IN ('pending', 'approved') AND c.expires_at <= NOW() THEN 'expired'
+                        ELSE c.status END AS status,
+                   c.requested_at, c.decided_at, c.expires_at
+            FROM proof_consents c
+            JOIN proof_sessions ps ON ps.id = c.session_id
+            JOIN lenders l ON l.id = c.lender_id
+            WHERE ps.user_id = $1
+            ORDER BY c.requested_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Requests for the user's proofs still awaiting an answer, oldest
+    /// first so a USSD menu numbers them the same way on every step.
+    pub async fn pending_for_user(db: &PgPool, user_id: Uuid) -> Result<Vec<ProofConsent>, sqlx::Error> {
+        sqlx::query_as::<_, ProofConsent>(
+            r#"
+            SELECT c.id, c.session_id, ps.verification_code, c.lender_id, l.name AS lender_name, c.status,
+                   c.requested_at, c.decided_at, c.expires_at
+            FROM proof_consents c
+            JOIN proof_sessions ps ON ps.id = c.session_id
+            JOIN lenders l ON l.id = c.lender_id
+            WHERE ps.user_id = $1 AND c.status = 'pending' AND c.expires_at > NOW()
+            ORDER BY c.requested_at, c.id
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Answers a pending request on one of the user's proofs. An approval
+    /// lasts until `approved_until`, or the proof's own expiry if sooner.
+    /// `None` when there is no such request or it was already answered.
+    pub async fn decide(
+        db: &PgPool,
+        consent_id: Uuid,
+        user_id: Uuid,
+        approved_until: Option<DateTime<Utc>>,
+    ) -> Result<Option<ProofConsent>, sqlx::Error> {
+        sqlx::query_as::<_, ProofConsent>(
+            r#"
+            WITH decided AS (
+                UPDATE proof_consents c
+                SET status = CASE WHEN $3::timestamptz IS NULL THEN 'declined' ELSE 'approved' END,
+                    decided_at = NOW(),
+                    expires_at = COALESCE(LEAST($3, ps.expires_at), c.expires_at)
+                FROM proof_sessions ps
+                WHERE c.id = $1 AND ps.id = c.session_id AND ps.user_id = $2
+                  AND c.status = 'pending' AND c.expires_at > NOW()
+                RETURNING c.*
+            )
+            SELECT c.id, c.session_id, ps.verification_code, c.lender_id, l.name AS lender_name, c.status,
+                   c.requested_at, c.decided_at, c.expires_at
+            FROM decided c
+            JOIN proof_sessions ps ON ps.id = c.session_id
+            JOIN lenders l ON l.id = c.lender_id
+            "#,
+        )
+        .bind(consent_id)
+        .bind(user_id)
+        .bind(approved_until)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Withdraws an approval, or a request not yet answered, on one of the
+    /// user's proofs.
+    pub async fn revoke(db: &PgPool, consent_id: Uuid, user_id: Uuid) -> Result<Option<ProofConsent>, sqlx::Error> {
+        sqlx::query_as::<_, ProofConsent>(
+            r#"
+            WITH revoked AS (
+                UPDATE proof_consents c
+                SET status = 'revoked', decided_at = NOW()
+                FROM proof_sessions ps
+                WHERE c.id = $1 AND ps.id = c.session_id AND ps.user_id = $2
+                  AND c.status IN ('pending', 'approved') AND c.expires_at > NOW()
+                RETURNING c.*
+            )
+            SELECT c.id, c.session_id, ps.verification_code, c.lender_id, l.name AS lender_name, c.status,
+                   c.requested_at, c.decided_at, c.expires_at
+            FROM revoked c
+            JOIN proof_sessions ps ON ps.id = c.session_id
+            JOIN lenders l ON l.id = c.lender_id
+            "#,
+        )
+        .bind(consent_id)
+        .bind(user_id)
+        .fetch_optional(db)
+        .await
+    }
+}
@@ -0,0 +1,17 @@
+use sqlx::PgPool;
+
+use crate::models::Currency;
+
+pub struct CurrencyRepo;
+
+impl CurrencyRepo {
+    /// A supported currency by ISO code, or `None` if it isn't configured.
+    pub async fn find(db: &PgPool, code: &str) -> Result<Option<Currency>, sqlx::Error> {
+        sqlx::query_as::<_, Currency>(
+            "SELECT code, minor_unit_exponent, volume_thresholds FROM currencies WHERE code = $1",
+        )
+        .bind(code)
+        .fetch_optional(db)
+        .await
+    }
+}
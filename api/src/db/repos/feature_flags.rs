@@ -0,0 +1,76 @@
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// A feature switch; see `services::feature_flags`.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct FeatureFlag {
+    pub key: String,
+    pub description: String,
+    /// Off for everyone when false
+    pub enabled: bool,
+    pub rollout_percentage: i16,
+    /// On for these users whatever the percentage
+    pub user_ids: Vec<Uuid>,
+    pub updated_at: DateTime<Utc>,
+}
+
+const FLAG_COLUMNS: &str = "key, description, enabled, rollout_percentage, user_ids, updated_at";
+
+pub struct FeatureFlagRepo;
+
+impl FeatureFlagRepo {
+    pub async fn list(db: &PgPool) -> Result<Vec<FeatureFlag>, sqlx::Error> {
+        sqlx::query_as::<_, FeatureFlag>(&format!("SELECT {} FROM feature_flags ORDER BY key", FLAG_COLUMNS))
+            .fetch_all(db)
+            .await
+    }
+
+    pub async fn get(db: &PgPool, key: &str) -> Result<Option<FeatureFlag>, sqlx::Error> {
+        sqlx::query_as::<_, FeatureFlag>(&format!("SELECT {} FROM feature_flags WHERE key = $1", FLAG_COLUMNS))
+            .bind(key)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Creates the flag or replaces its settings.
+    pub async fn upsert(
+        db: &PgPool,
+        key: &str,
+        description: &str,
+        enabled: bool,
+        rollout_percentage: i16,
+        user_ids: &[Uuid],
+    ) -> Result<FeatureFlag, sqlx::Error> {
+        sqlx::query_as::<_, FeatureFlag>(&format!(
+            r#"
+            INSERT INTO feature_flags (key, description, enabled, rollout_percentage, user_ids)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (key) DO UPDATE SET
+                description = EXCLUDED.description,
+                enabled = EXCLUDED.enabled,
+                rollout_percentage = EXCLUDED.rollout_percentage,
+                user_ids = EXCLUDED.user_ids,
+                updated_at = NOW()
+            RETURNING {}
+            "#,
+            FLAG_COLUMNS
+        ))
+        .bind(key)
+        .bind(description)
+        .bind(enabled)
+        .bind(rollout_percentage)
+        .bind(user_ids)
+        .fetch_one(db)
+        .await
+    }
+
+    pub async fn delete(db: &PgPool, key: &str) -> Result<bool, sqlx::Error> {
+        let deleted = sqlx::query("DELETE FROM feature_flags WHERE key = $1")
+            .bind(key)
+            .execute(db)
+            .await?;
+        Ok(deleted.rows_affected() > 0)
+    }
+}
@@ -0,0 +1,68 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+
+/// A guest image receipts may be verified against.
+#[derive(Debug, FromRow)]
+pub struct AllowedImage {
+    pub image_id: String,
+    pub guest_version: String,
+    pub valid_from: DateTime<Utc>,
+    pub valid_to: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct ImageIdRepo;
+
+impl ImageIdRepo {
+    /// Adds an image if it isn't registered yet, leaving an existing entry's
+    /// validity window untouched.
+    pub async fn ensure_registered(db: &PgPool, image_id: &str, guest_version: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "INSERT INTO allowed_image_ids (image_id, guest_version) VALUES ($1, $2) ON CONFLICT (image_id) DO NOTHING",
+        )
+        .bind(image_id)
+        .bind(guest_version)
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Adds an image or replaces its version and validity window.
+    pub async fn upsert(
+        db: &PgPool,
+        image_id: &str,
+        guest_version: &str,
+        valid_from: DateTime<Utc>,
+        valid_to: Option<DateTime<Utc>>,
+    ) -> Result<AllowedImage, sqlx::Error> {
+        sqlx::query_as::<_, AllowedImage>(
+            r#"
+            INSERT INTO allowed_image_ids (image_id, guest_version, valid_from, valid_to)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (image_id) DO UPDATE
+            SET guest_version = EXCLUDED.guest_version,
+                valid_from = EXCLUDED.valid_from,
+                valid_to = EXCLUDED.valid_to
+            RETURNING image_id, guest_version, valid_from, valid_to, created_at
+            "#,
+        )
+        .bind(image_id)
+        .bind(guest_version)
+        .bind(valid_from)
+        .bind(valid_to)
+        .fetch_one(db)
+        .await
+    }
+
+    pub async fn list(db: &PgPool) -> Result<Vec<AllowedImage>, sqlx::Error> {
+        sqlx::query_as::<_, AllowedImage>(
+            r#"
+            SELECT image_id, guest_version, valid_from, valid_to, created_at
+            FROM allowed_image_ids
+            ORDER BY valid_from DESC
+            "#,
+        )
+        .fetch_all(db)
+        .await
+    }
+}
@@ -0,0 +1,132 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// A large batch waiting for, or held by, a worker.
+#[derive(Debug, FromRow)]
+pub struct ImportJob {
+    pub id: Uuid,
+    pub till_id: Uuid,
+    pub source: String,
+    pub currency: String,
+    pub entries: serde_json::Value,
+}
+
+pub struct NewImportJob<'a> {
+    pub user_id: Uuid,
+    pub till_id: Uuid,
+    pub source: &'a str,
+    pub currency: &'a str,
+    pub rows_received: i32,
+    pub entries: &'a serde_json::Value,
+}
+
+/// Batches too large to import within the request. The `import_jobs` table
+/// is the source of truth; Redis's list is only the fast path to them.
+pub struct ImportJobRepo;
+
+impl ImportJobRepo {
+    /// Records a job as pending, in the caller's transaction so it's queued
+    /// along with it.
+    pub async fn create(
+        executor: impl sqlx::PgExecutor<'_>,
+        job: &NewImportJob<'_>,
+        lease_secs: i64,
+    ) -> Result<(Uuid, DateTime<Utc>), sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            INSERT INTO import_jobs (user_id, till_id, source, currency, rows_received, entries, retry_at)
+            VALUES ($1, $2, $3, $4, $5, $6, NOW() + make_interval(secs => $7))
+            RETURNING id, created_at
+            "#,
+        )
+        .bind(job.user_id)
+        .bind(job.till_id)
+        .bind(job.source)
+        .bind(job.currency)
+        .bind(job.rows_received)
+        .bind(job.entries)
+        .bind(lease_secs as f64)
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Takes a pending job for a worker. None if it's already been taken or
+    /// has finished.
+    pub async fn claim(db: &PgPool, job_id: Uuid, lease_secs: i64) -> Result<Option<ImportJob>, sqlx::Error> {
+        sqlx::query_as::<_, ImportJob>(
+            r#"
+            UPDATE import_jobs
+            SET status = 'running',
+                attempts = attempts + 1,
+                retry_at = NOW() + make_interval(secs => $2)
+            WHERE id = $1 AND status = 'pending'
+            RETURNING id, till_id, source, currency, entries
+            "#,
+        )
+        .bind(job_id)
+        .bind(lease_secs as f64)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// The longest-waiting pending jobs, for when Redis can't be reached.
+    pub async fn oldest_pending(db: &PgPool, limit: i64) -> Result<Vec<Uuid>, sqlx::Error> {
+        sqlx::query_scalar("SELECT id FROM import_jobs WHERE status = 'pending' ORDER BY created_at LIMIT $1")
+            .bind(limit)
+            .fetch_all(db)
+            .await
+    }
+
+    /// Records a job's outcome and drops its batch.
+    pub async fn finish(
+        db: &PgPool,
+        job_id: Uuid,
+        result: Option<serde_json::Value>,
+        error: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE import_jobs
+            SET status = CASE WHEN $3::TEXT IS NULL THEN 'completed' ELSE 'failed' END,
+                result = $2,
+                error = $3,
+                entries = NULL,
+                retry_at = NULL,
+                finished_at = NOW()
+            WHERE id = $1 AND status = 'running'
+            "#,
+        )
+        .bind(job_id)
+        .bind(result)
+        .bind(error)
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Puts back jobs whose worker stopped, failing those interrupted
+    /// `max_attempts` times, and pushes the retry of jobs that have waited
+    /// too long, e.g. because Redis lost them. Returns the jobs to queue.
+    pub async fn reclaim_expired(db: &PgPool, max_attempts: i32, lease_secs: i64) -> Result<Vec<Uuid>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            WITH reclaimed AS (
+                UPDATE import_jobs
+                SET status = CASE WHEN attempts >= $1 THEN 'failed' ELSE 'pending' END,
+                    error = CASE WHEN attempts >= $1 THEN 'The import was interrupted too many times' END,
+                    entries = CASE WHEN attempts >= $1 THEN NULL ELSE entries END,
+                    finished_at = CASE WHEN attempts >= $1 THEN NOW() END,
+                    retry_at = CASE WHEN attempts >= $1 THEN NULL ELSE NOW() + make_interval(secs => $2) END
+                WHERE status IN ('pending', 'running') AND retry_at < NOW()
+                RETURNING id, status
+            )
+            SELECT id FROM reclaimed WHERE status = 'pending'
+            "#,
+        )
+        .bind(max_attempts)
+        .bind(lease_secs as f64)
+        .fetch_all(db)
+        .await
+    }
+}
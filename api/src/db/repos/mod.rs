@@ -0,0 +1,53 @@
+//! Typed queries shared by handlers and the worker. Rows decode by column
+//! name into `FromRow` structs, so a renamed or reordered column fails the
+//! query loudly instead of shifting positional `try_get` indexes.
+
+pub mod access_tokens;
+pub mod api_keys;
+pub mod audit;
+pub mod billing;
+pub mod consents;
+pub mod currencies;
+pub mod feature_flags;
+pub mod images;
+pub mod import_jobs;
+pub mod notifications;
+pub mod outbox;
+pub mod payments;
+pub mod policies;
+pub mod proof_queue;
+pub mod referrals;
+pub mod reviews;
+pub mod scheduled_jobs;
+pub mod scoring_experiments;
+pub mod sessions;
+pub mod templates;
+pub mod tills;
+pub mod transaction_types;
+pub mod transactions;
+pub mod user_sessions;
+
+pub use access_tokens::AccessTokenRepo;
+pub use api_keys::ApiKeyRepo;
+pub use audit::AuditRepo;
+pub use billing::BillingRepo;
+pub use consents::ConsentRepo;
+pub use currencies::CurrencyRepo;
+pub use feature_flags::FeatureFlagRepo;
+pub use images::ImageIdRepo;
+pub use import_jobs::ImportJobRepo;
+pub use notifications::NotificationRepo;
+pub use outbox::OutboxRepo;
+pub use payments::PaymentRepo;
+pub use policies::ScoringPolicyRepo;
+pub use proof_queue::ProofQueueRepo;
+pub use referrals::ReferralCodeRepo;
+pub use reviews::ReviewRepo;
+pub use scheduled_jobs::ScheduledJobRepo;
+pub use scoring_experiments::ScoringExperimentRepo;
+pub use sessions::SessionRepo;
+pub use templates::ProofTemplateRepo;
+pub use tills::TillRepo;
+pub use transaction_types::TransactionTypeRepo;
+pub use transactions::TransactionRepo;
+pub use user_sessions::UserSessionRepo;
@@ -0,0 +1,183 @@
+use chrono::{DateTime, NaiveTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// How an owner wants to hear about their proofs.
+#[derive(Debug, Clone, FromRow)]
+pub struct NotificationPreferences {
+    pub sms: bool,
+    pub push: bool,
+    pub email: bool,
+    pub email_address: Option<String>,
+    /// Unset until the owner enters the code emailed to `email_address`;
+    /// nothing is emailed before then
+    pub email_verified_at: Option<DateTime<Utc>>,
+    pub quiet_hours_start: Option<NaiveTime>,
+    pub quiet_hours_end: Option<NaiveTime>,
+    /// IANA timezone the quiet hours are in
+    pub timezone: String,
+}
+
+impl Default for NotificationPreferences {
+    /// What owners who never set preferences get.
+    fn default() -> Self {
+        Self {
+            sms: true,
+            push: true,
+            email: false,
+            email_address: None,
+            email_verified_at: None,
+            quiet_hours_start: None,
+            quiet_hours_end: None,
+            timezone: "Africa/Nairobi".to_string(),
+        }
+    }
+}
+
+/// A notification rendered during quiet hours, waiting for them to end.
+#[derive(Debug, FromRow)]
+pub struct HeldNotification {
+    pub user_id: Uuid,
+    pub event: String,
+    pub session_id: Uuid,
+    pub title: String,
+    pub body: String,
+    pub by_sms: bool,
+}
+
+/// A held notification taken for sending.
+#[derive(Debug, FromRow)]
+pub struct DueNotification {
+    pub id: i64,
+    #[sqlx(flatten)]
+    pub notification: HeldNotification,
+}
+
+pub struct NotificationRepo;
+
+impl NotificationRepo {
+    pub async fn preferences(db: &PgPool, user_id: Uuid) -> Result<NotificationPreferences, sqlx::Error> {
+        let preferences = sqlx::query_as::<_, NotificationPreferences>(
+            r#"
+            SELECT sms, push, email, email_address, email_verified_at, quiet_hours_start, quiet_hours_end, timezone
+            FROM notification_preferences
+            WHERE user_id = $1
+            "#,
+        )
+        .bind(user_id)
+        .fetch_optional(db)
+        .await?;
+        Ok(preferences.unwrap_or_default())
+    }
+
+    /// Saves everything but `email_verified_at`, which is kept while the
+    /// address stays the same and cleared when it changes. Returns it.
+    pub async fn save_preferences(
+        db: &PgPool,
+        user_id: Uuid,
+        preferences: &NotificationPreferences,
+    ) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO notification_preferences
+                (user_id, sms, push, email, email_address, quiet_hours_start, quiet_hours_end, timezone)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (user_id) DO UPDATE SET
+                sms = EXCLUDED.sms,
+                push = EXCLUDED.push,
+                email = EXCLUDED.email,
+                email_address = EXCLUDED.email_address,
+                email_verified_at = CASE
+                    WHEN notification_preferences.email_address = EXCLUDED.email_address
+                    THEN notification_preferences.email_verified_at
+                END,
+                quiet_hours_start = EXCLUDED.quiet_hours_start,
+                quiet_hours_end = EXCLUDED.quiet_hours_end,
+                timezone = EXCLUDED.timezone
+            RETURNING email_verified_at
+            "#,
+        )
+        .bind(user_id)
+        .bind(preferences.sms)
+        .bind(preferences.push)
+        .bind(preferences.email)
+        .bind(&preferences.email_address)
+        .bind(preferences.quiet_hours_start)
+        .bind(preferences.quiet_hours_end)
+        .bind(&preferences.timezone)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Marks the owner's address verified, if it's still `email_address`.
+    pub async fn verify_email(db: &PgPool, user_id: Uuid, email_address: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE notification_preferences
+            SET email_verified_at = NOW()
+            WHERE user_id = $1 AND email_address = $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(email_address)
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    pub async fn hold(
+        db: &PgPool,
+        notification: &HeldNotification,
+        send_after: DateTime<Utc>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO held_notifications (user_id, event, session_id, title, body, by_sms, send_after)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(notification.user_id)
+        .bind(&notification.event)
+        .bind(notification.session_id)
+        .bind(&notification.title)
+        .bind(&notification.body)
+        .bind(notification.by_sms)
+        .bind(send_after)
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Takes held notifications whose quiet hours are over, oldest first,
+    /// by moving them `retry_secs` into the future. Another worker skips
+    /// them; one that isn't deleted once sent is taken again after that.
+    pub async fn take_due(db: &PgPool, limit: i64, retry_secs: i64) -> Result<Vec<DueNotification>, sqlx::Error> {
+        sqlx::query_as::<_, DueNotification>(
+            r#"
+            UPDATE held_notifications
+            SET send_after = NOW() + make_interval(secs => $2)
+            WHERE id IN (
+                SELECT id FROM held_notifications
+                WHERE send_after <= NOW()
+                ORDER BY id
+                LIMIT $1
+                FOR UPDATE SKIP LOCKED
+            )
+            RETURNING id, user_id, event, session_id, title, body, by_sms
+            "#,
+        )
+        .bind(limit)
+        .bind(retry_secs as f64)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Called once a taken notification has been sent.
+    pub async fn delete_held(db: &PgPool, id: i64) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM held_notifications WHERE id = $1")
+            .bind(id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
@@ -0,0 +1,62 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+
+/// A job waiting to be pushed onto a Redis list.
+#[derive(Debug, FromRow)]
+pub struct OutboxEntry {
+    pub id: i64,
+    /// Redis list key
+    pub queue: String,
+    pub payload: String,
+    pub created_at: DateTime<Utc>,
+}
+
+pub struct OutboxRepo;
+
+impl OutboxRepo {
+    /// Adds a job, in the caller's transaction so it's kept only if the row
+    /// it's for is.
+    pub async fn add(executor: impl sqlx::PgExecutor<'_>, queue: &str, payload: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO job_outbox (queue, payload) VALUES ($1, $2)")
+            .bind(queue)
+            .bind(payload)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+
+    /// Locks the oldest jobs for the transaction, skipping any another relay
+    /// has locked.
+    pub async fn lock_batch(executor: impl sqlx::PgExecutor<'_>, limit: i64) -> Result<Vec<OutboxEntry>, sqlx::Error> {
+        sqlx::query_as::<_, OutboxEntry>(
+            r#"
+            SELECT id, queue, payload, created_at
+            FROM job_outbox
+            ORDER BY id
+            LIMIT $1
+            FOR UPDATE SKIP LOCKED
+            "#,
+        )
+        .bind(limit)
+        .fetch_all(executor)
+        .await
+    }
+
+    pub async fn delete(executor: impl sqlx::PgExecutor<'_>, ids: &[i64]) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM job_outbox WHERE id = ANY($1)")
+            .bind(ids)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+
+    /// Notes a failed push; the jobs stay to be retried.
+    pub async fn record_failure(db: &PgPool, ids: &[i64], error: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE job_outbox SET attempts = attempts + 1, last_error = $2 WHERE id = ANY($1)")
+            .bind(ids)
+            .bind(error)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+}
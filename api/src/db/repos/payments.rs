@@ -0,0 +1,192 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// An STK push payment for a proof.
+#[derive(Debug, FromRow)]
+pub struct ProofPayment {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub phone_number: String,
+    pub amount_minor: i64,
+    pub currency: String,
+    /// "pending", "succeeded" or "failed"
+    pub status: String,
+    pub checkout_request_id: Option<String>,
+    pub mpesa_receipt: Option<String>,
+    pub result_desc: Option<String>,
+    /// The proof it paid for, once spent
+    pub session_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub completed_at: Option<DateTime<Utc>>,
+}
+
+/// What Daraja's callback reported for a payment.
+pub struct PaymentResult<'a> {
+    pub result_code: i32,
+    pub result_desc: &'a str,
+    pub mpesa_receipt: Option<&'a str>,
+}
+
+const PAYMENT_COLUMNS: &str = "id, user_id, phone_number, amount_minor, currency, status, checkout_request_id, \
+                               mpesa_receipt, result_desc, session_id, created_at, completed_at";
+
+pub struct PaymentRepo;
+
+impl PaymentRepo {
+    pub async fn create(
+        db: &PgPool,
+        user_id: Uuid,
+        phone_number: &str,
+        amount_minor: i64,
+        currency: &str,
+    ) -> Result<Uuid, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO proof_payments (user_id, phone_number, amount_minor, currency)
+            VALUES ($1, $2, $3, $4)
+            RETURNING id
+            "#,
+        )
+        .bind(user_id)
+        .bind(phone_number)
+        .bind(amount_minor)
+        .bind(currency)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Records the IDs Daraja gave the push, which its callback refers to.
+    pub async fn set_request_ids(
+        db: &PgPool,
+        payment_id: Uuid,
+        merchant_request_id: &str,
+        checkout_request_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE proof_payments SET merchant_request_id = $2, checkout_request_id = $3 WHERE id = $1")
+            .bind(payment_id)
+            .bind(merchant_request_id)
+            .bind(checkout_request_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Fails a payment Daraja refused to push.
+    pub async fn reject(db: &PgPool, payment_id: Uuid, reason: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE proof_payments SET status = 'failed', result_desc = $2, completed_at = NOW() WHERE id = $1",
+        )
+        .bind(payment_id)
+        .bind(reason)
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Settles a pending payment from its callback. Returns false for
+    /// unknown or already settled payments, so a repeated callback is a
+    /// no-op.
+    pub async fn complete(
+        db: &PgPool,
+        checkout_request_id: &str,
+        result: &PaymentResult<'_>,
+    ) -> Result<bool, sqlx::Error> {
+        let updated = sqlx::query(
+            r#"
+            UPDATE proof_payments
+            SET status = CASE WHEN $2 = 0 THEN 'succeeded' ELSE 'failed' END,
+                result_code = $2, result_desc = $3, mpesa_receipt = $4, completed_at = NOW()
+            WHERE checkout_request_id = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(checkout_request_id)
+        .bind(result.result_code)
+        .bind(result.result_desc)
+        .bind(result.mpesa_receipt)
+        .execute(db)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    pub async fn find_for_user(
+        db: &PgPool,
+        payment_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<ProofPayment>, sqlx::Error> {
+        sqlx::query_as::<_, ProofPayment>(&format!(
+            "SELECT {} FROM proof_payments WHERE id = $1 AND user_id = $2",
+            PAYMENT_COLUMNS
+        ))
+        .bind(payment_id)
+        .bind(user_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Pending payments created before `before`, oldest first: pushes whose
+    /// callback should have arrived by now.
+    pub async fn list_stale_pending(
+        db: &PgPool,
+        before: DateTime<Utc>,
+        limit: i64,
+    ) -> Result<Vec<ProofPayment>, sqlx::Error> {
+        sqlx::query_as::<_, ProofPayment>(&format!(
+            r#"
+            SELECT {}
+            FROM proof_payments
+            WHERE status = 'pending' AND created_at < $1
+            ORDER BY created_at
+            LIMIT $2
+            "#,
+            PAYMENT_COLUMNS
+        ))
+        .bind(before)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Whether the user has a succeeded payment not yet spent on a proof.
+    pub async fn has_unspent(db: &PgPool, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT EXISTS (
+                SELECT 1 FROM proof_payments
+                WHERE user_id = $1 AND status = 'succeeded' AND session_id IS NULL
+            )
+            "#,
+        )
+        .bind(user_id)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Spends the user's oldest unspent payment on the session, in the
+    /// caller's transaction. Returns false when there's none left, e.g.
+    /// because a concurrent request spent it. Should the session fail, a
+    /// trigger releases the payment again.
+    pub async fn spend(
+        executor: impl sqlx::PgExecutor<'_>,
+        user_id: Uuid,
+        session_id: Uuid,
+    ) -> Result<bool, sqlx::Error> {
+        let spent = sqlx::query(
+            r#"
+            UPDATE proof_payments SET session_id = $2
+            WHERE id = (
+                SELECT id FROM proof_payments
+                WHERE user_id = $1 AND status = 'succeeded' AND session_id IS NULL
+                ORDER BY completed_at
+                LIMIT 1
+                FOR UPDATE SKIP LOCKED
+            )
+            "#,
+        )
+        .bind(user_id)
+        .bind(session_id)
+        .execute(executor)
+        .await?;
+        Ok(spent.rows_affected() > 0)
+    }
+}
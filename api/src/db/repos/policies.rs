@@ -0,0 +1,59 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::ScoringPolicy;
+
+pub struct ScoringPolicyRepo;
+
+impl ScoringPolicyRepo {
+    pub async fn find(db: &PgPool, id: Uuid) -> Result<Option<ScoringPolicy>, sqlx::Error> {
+        sqlx::query_as::<_, ScoringPolicy>(
+            r#"
+            SELECT id, name, currency, lender_id, volume_thresholds, outlier_cap, policy_hash, created_at
+            FROM scoring_policies
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(db)
+        .await
+    }
+
+    pub async fn list(db: &PgPool) -> Result<Vec<ScoringPolicy>, sqlx::Error> {
+        sqlx::query_as::<_, ScoringPolicy>(
+            r#"
+            SELECT id, name, currency, lender_id, volume_thresholds, outlier_cap, policy_hash, created_at
+            FROM scoring_policies
+            ORDER BY created_at DESC
+            "#,
+        )
+        .fetch_all(db)
+        .await
+    }
+
+    pub async fn create(
+        db: &PgPool,
+        name: &str,
+        currency: &str,
+        lender_id: Option<Uuid>,
+        volume_thresholds: &[i64],
+        outlier_cap: Option<i32>,
+        policy_hash: &str,
+    ) -> Result<ScoringPolicy, sqlx::Error> {
+        sqlx::query_as::<_, ScoringPolicy>(
+            r#"
+            INSERT INTO scoring_policies (name, currency, lender_id, volume_thresholds, outlier_cap, policy_hash)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            RETURNING id, name, currency, lender_id, volume_thresholds, outlier_cap, policy_hash, created_at
+            "#,
+        )
+        .bind(name)
+        .bind(currency)
+        .bind(lender_id)
+        .bind(volume_thresholds)
+        .bind(outlier_cap)
+        .bind(policy_hash)
+        .fetch_one(db)
+        .await
+    }
+}
@@ -0,0 +1,71 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Sessions waiting for a worker, kept until one claims them. Redis's list
+/// is only the fast path to them.
+pub struct ProofQueueRepo;
+
+impl ProofQueueRepo {
+    /// Records a session as waiting, keeping its place if it already is.
+    pub async fn add(executor: impl sqlx::PgExecutor<'_>, session_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO proof_queue (session_id) VALUES ($1) ON CONFLICT (session_id) DO NOTHING")
+            .bind(session_id)
+            .execute(executor)
+            .await?;
+        Ok(())
+    }
+
+    /// Called once a worker has claimed the session.
+    pub async fn remove(db: &PgPool, session_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query("DELETE FROM proof_queue WHERE session_id = $1")
+            .bind(session_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// The longest-waiting sessions, first in line first.
+    pub async fn oldest(db: &PgPool, limit: i64) -> Result<Vec<Uuid>, sqlx::Error> {
+        sqlx::query_scalar("SELECT session_id FROM proof_queue ORDER BY enqueued_at LIMIT $1")
+            .bind(limit)
+            .fetch_all(db)
+            .await
+    }
+
+    /// Sessions that have waited at least `min_wait_secs`, first in line
+    /// first.
+    pub async fn waiting_since(db: &PgPool, min_wait_secs: i64) -> Result<Vec<Uuid>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT session_id
+            FROM proof_queue
+            WHERE enqueued_at < NOW() - make_interval(secs => $1)
+            ORDER BY enqueued_at
+            "#,
+        )
+        .bind(min_wait_secs as f64)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Drops sessions no worker can claim now: finished, failed, or leased
+    /// to a worker. Sessions whose lease lapses are added back when they're
+    /// requeued. Returns how many were dropped.
+    pub async fn prune(db: &PgPool) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM proof_queue q
+            USING proof_sessions ps
+            WHERE ps.id = q.session_id
+              AND (ps.status NOT IN ('pending', 'processing') OR ps.lease_expires_at > NOW())
+            "#,
+        )
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected())
+    }
+
+    pub async fn depth(db: &PgPool) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM proof_queue").fetch_one(db).await
+    }
+}
@@ -0,0 +1,168 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// A partner's referral or coupon code, with the proofs attributed to it.
+#[derive(Debug, FromRow)]
+pub struct ReferralCode {
+    pub id: Uuid,
+    pub code: String,
+    /// The partner lender, or None for an ambassador's code
+    pub lender_id: Option<Uuid>,
+    pub ambassador_name: Option<String>,
+    /// A coupon: proofs past the plan's allowance go through unpaid
+    pub waives_payment: bool,
+    pub max_redemptions: Option<i32>,
+    /// Redemptions one merchant may make; always set for coupons
+    pub max_redemptions_per_user: Option<i32>,
+    pub redemptions: i32,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    /// Attributed proofs that completed
+    pub completed_proofs: i64,
+}
+
+/// Who a new code is for.
+pub enum Partner<'a> {
+    Lender(Uuid),
+    Ambassador(&'a str),
+}
+
+const CODE_COLUMNS: &str = r#"
+    rc.id, rc.code, rc.lender_id, rc.ambassador_name, rc.waives_payment, rc.max_redemptions,
+    rc.max_redemptions_per_user, rc.redemptions, rc.expires_at, rc.active, rc.created_at,
+    (SELECT COUNT(*) FROM proof_sessions ps WHERE ps.referral_code_id = rc.id AND ps.status = 'completed')
+        AS completed_proofs
+"#;
+
+pub struct ReferralCodeRepo;
+
+impl ReferralCodeRepo {
+    /// Returns None when the code is taken.
+    pub async fn create(
+        db: &PgPool,
+        code: &str,
+        partner: &Partner<'_>,
+        waives_payment: bool,
+        max_redemptions: Option<i32>,
+        max_redemptions_per_user: Option<i32>,
+        expires_at: Option<DateTime<Utc>>,
+    ) -> Result<Option<ReferralCode>, sqlx::Error> {
+        let (lender_id, ambassador_name) = match partner {
+            Partner::Lender(lender_id) => (Some(*lender_id), None),
+            Partner::Ambassador(name) => (None, Some(*name)),
+        };
+        sqlx::query_as::<_, ReferralCode>(&format!(
+            r#"
+            WITH rc AS (
+                INSERT INTO referral_codes
+                    (code, lender_id, ambassador_name, waives_payment, max_redemptions, max_redemptions_per_user,
+                     expires_at)
+                VALUES ($1, $2, $3, $4, $5, $6, $7)
+                ON CONFLICT (code) DO NOTHING
+                RETURNING *
+            )
+            SELECT {} FROM rc
+            "#,
+            CODE_COLUMNS
+        ))
+        .bind(code)
+        .bind(lender_id)
+        .bind(ambassador_name)
+        .bind(waives_payment)
+        .bind(max_redemptions)
+        .bind(max_redemptions_per_user)
+        .bind(expires_at)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Every code, newest first; a lender's only when `lender_id` is set.
+    pub async fn list(db: &PgPool, lender_id: Option<Uuid>) -> Result<Vec<ReferralCode>, sqlx::Error> {
+        sqlx::query_as::<_, ReferralCode>(&format!(
+            r#"
+            SELECT {}
+            FROM referral_codes rc
+            WHERE $1::uuid IS NULL OR rc.lender_id = $1
+            ORDER BY rc.created_at DESC
+            "#,
+            CODE_COLUMNS
+        ))
+        .bind(lender_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// The code if it can still be redeemed.
+    pub async fn find_redeemable(db: &PgPool, code: &str) -> Result<Option<ReferralCode>, sqlx::Error> {
+        sqlx::query_as::<_, ReferralCode>(&format!(
+            r#"
+            SELECT {}
+            FROM referral_codes rc
+            WHERE rc.code = $1 AND rc.active
+              AND (rc.max_redemptions IS NULL OR rc.redemptions < rc.max_redemptions)
+              AND (rc.expires_at IS NULL OR rc.expires_at > NOW())
+            "#,
+            CODE_COLUMNS
+        ))
+        .bind(code)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Proofs the user has generated with the code, failed ones aside.
+    pub async fn redemptions_by(
+        executor: impl sqlx::PgExecutor<'_>,
+        id: Uuid,
+        user_id: Uuid,
+    ) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM proof_sessions
+            WHERE referral_code_id = $1 AND user_id = $2 AND status <> 'failed'
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Counts a redemption by `user_id`, in the caller's transaction, after
+    /// the session it's for is inserted. Returns false when the code can no
+    /// longer be redeemed, e.g. because a concurrent request took its last
+    /// one, or the user is past their share; the caller rolls back then.
+    pub async fn redeem(conn: &mut sqlx::PgConnection, id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        // Holds the code's row, so the user's redemptions are counted after
+        // any concurrent one commits
+        let per_user: Option<Option<i32>> = sqlx::query_scalar(
+            r#"
+            UPDATE referral_codes SET redemptions = redemptions + 1
+            WHERE id = $1 AND active
+              AND (max_redemptions IS NULL OR redemptions < max_redemptions)
+              AND (expires_at IS NULL OR expires_at > NOW())
+            RETURNING max_redemptions_per_user
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(&mut *conn)
+        .await?;
+        match per_user {
+            None => Ok(false),
+            Some(None) => Ok(true),
+            // The new session counts among them
+            Some(Some(max)) => Ok(Self::redemptions_by(&mut *conn, id, user_id).await? <= max as i64),
+        }
+    }
+
+    /// Stops a code being redeemed. Proofs already attributed to it stay so.
+    pub async fn deactivate(db: &PgPool, id: Uuid) -> Result<bool, sqlx::Error> {
+        let updated = sqlx::query("UPDATE referral_codes SET active = FALSE WHERE id = $1 AND active")
+            .bind(id)
+            .execute(db)
+            .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+}
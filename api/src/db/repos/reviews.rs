@@ -0,0 +1,256 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::utils::phone_number_hashes;
+
+/// Statuses a held row can be listed by.
+pub const REVIEW_STATUSES: [&str; 3] = ["pending", "confirmed", "rejected"];
+
+/// An imported row held back from scoring until it's confirmed.
+#[derive(Debug, FromRow)]
+pub struct PendingReview {
+    pub id: Uuid,
+    pub till_id: Uuid,
+    /// The photo the row was read from, for OCR rows
+    pub image_id: Option<Uuid>,
+    /// The upload the row was held from
+    pub statement_import_id: Option<Uuid>,
+    /// "ocr", or the authenticity check the row failed
+    pub flagged_by: String,
+    pub reason: String,
+    /// 1-based spreadsheet row, or line of the photo
+    pub row_number: i32,
+    /// The line as OCR read it; cleared once resolved
+    pub raw_text: Option<String>,
+    pub confidence: Option<i16>,
+    /// As printed; cleared once resolved
+    pub receipt_number: Option<String>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub amount: Option<i64>,
+    pub transaction_type: Option<String>,
+    pub account_number: Option<String>,
+    pub source: String,
+    pub currency: String,
+    pub provenance: String,
+    pub status: String,
+    pub resolved_by_admin: bool,
+    pub resolution_note: Option<String>,
+    pub resolved_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A row to hold, with whatever of it could be read.
+pub struct NewReview<'a> {
+    pub till_id: Uuid,
+    pub image_id: Option<Uuid>,
+    pub statement_import_id: Option<Uuid>,
+    pub flagged_by: &'a str,
+    pub reason: &'a str,
+    pub row_number: usize,
+    pub raw_text: Option<&'a str>,
+    pub confidence: Option<u8>,
+    pub receipt_number: Option<&'a str>,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub amount: Option<i64>,
+    pub transaction_type: Option<&'a str>,
+    pub account_number: Option<&'a str>,
+    pub source: &'a str,
+    pub currency: &'a str,
+    pub provenance: proof_core::Provenance,
+}
+
+/// The row as it's confirmed, after any corrections.
+pub struct ConfirmedRow<'a> {
+    pub receipt_number: &'a str,
+    pub timestamp: DateTime<Utc>,
+    pub amount: i64,
+    pub transaction_type: &'a str,
+    /// The row's own provenance, or `OwnerCorrected` when its owner changed
+    /// what was read
+    pub provenance: proof_core::Provenance,
+}
+
+const COLUMNS: &str = "id, till_id, image_id, statement_import_id, flagged_by, reason, row_number, raw_text, \
+                       confidence, receipt_number, timestamp, amount, transaction_type, account_number, source, \
+                       currency, provenance, status, resolved_by_admin, resolution_note, resolved_at, created_at";
+
+pub struct ReviewRepo;
+
+impl ReviewRepo {
+    /// Holds a row for review. A row already pending with the same receipt
+    /// and time is kept, taking the new reason and upload.
+    pub async fn hold(db: &PgPool, review: &NewReview<'_>) -> Result<Uuid, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            INSERT INTO pending_review_transactions (till_id, image_id, flagged_by, reason, row_number, raw_text,
+                                                     confidence, receipt_number, timestamp, amount, transaction_type,
+                                                     account_number, source, currency, provenance,
+                                                     statement_import_id)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16)
+            ON CONFLICT (till_id, receipt_number, timestamp) WHERE status = 'pending'
+            DO UPDATE SET flagged_by = EXCLUDED.flagged_by, reason = EXCLUDED.reason,
+                          statement_import_id = EXCLUDED.statement_import_id
+            RETURNING id
+            "#,
+        )
+        .bind(review.till_id)
+        .bind(review.image_id)
+        .bind(review.flagged_by)
+        .bind(review.reason)
+        .bind(review.row_number as i32)
+        .bind(review.raw_text)
+        .bind(review.confidence.map(|c| c as i16))
+        .bind(review.receipt_number)
+        .bind(review.timestamp)
+        .bind(review.amount)
+        .bind(review.transaction_type)
+        .bind(review.account_number)
+        .bind(review.source)
+        .bind(review.currency)
+        .bind(review.provenance.as_str())
+        .bind(review.statement_import_id)
+        .fetch_one(db)
+        .await
+    }
+
+    pub async fn get(db: &PgPool, review_id: Uuid) -> Result<Option<PendingReview>, sqlx::Error> {
+        sqlx::query_as::<_, PendingReview>(&format!(
+            "SELECT {} FROM pending_review_transactions WHERE id = $1",
+            COLUMNS
+        ))
+        .bind(review_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// The till's held rows with this status, in the order they were read.
+    pub async fn list_for_till(db: &PgPool, till_id: Uuid, status: &str) -> Result<Vec<PendingReview>, sqlx::Error> {
+        sqlx::query_as::<_, PendingReview>(&format!(
+            r#"
+            SELECT {}
+            FROM pending_review_transactions
+            WHERE till_id = $1 AND status = $2
+            ORDER BY created_at, row_number
+            "#,
+            COLUMNS
+        ))
+        .bind(till_id)
+        .bind(status)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Pending rows across every till, oldest first, for operators.
+    pub async fn list_pending(db: &PgPool, limit: i64) -> Result<Vec<PendingReview>, sqlx::Error> {
+        sqlx::query_as::<_, PendingReview>(&format!(
+            r#"
+            SELECT {}
+            FROM pending_review_transactions
+            WHERE status = 'pending'
+            ORDER BY created_at, row_number
+            LIMIT $1
+            "#,
+            COLUMNS
+        ))
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Imports a pending row as confirmed and marks it so, in one
+    /// transaction, counting it as imported by the upload it was held from.
+    /// Returns whether the row was new to the till, or None if it was
+    /// resolved meanwhile.
+    pub async fn confirm(
+        db: &PgPool,
+        review: &PendingReview,
+        row: &ConfirmedRow<'_>,
+        by_admin: bool,
+        note: Option<&str>,
+    ) -> Result<Option<bool>, sqlx::Error> {
+        let mut tx = db.begin().await?;
+        if !Self::resolve(&mut *tx, review.id, "confirmed", by_admin, note).await? {
+            return Ok(None);
+        }
+
+        // The insert triggers skip a receipt the till already has and
+        // classify its kind and direction; older forms of the receipt's hash
+        // are checked here
+        let references = phone_number_hashes(row.receipt_number);
+        let direction: Option<String> = sqlx::query_scalar(
+            r#"
+            INSERT INTO transactions (till_id, timestamp, amount, transaction_type, reference, source, account_number,
+                                      currency, provenance)
+            SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9
+            WHERE NOT EXISTS (SELECT 1 FROM transaction_receipts r WHERE r.till_id = $1 AND r.reference = ANY($10))
+            RETURNING direction
+            "#,
+        )
+        .bind(review.till_id)
+        .bind(row.timestamp)
+        .bind(row.amount)
+        .bind(row.transaction_type)
+        .bind(&references[0])
+        .bind(&review.source)
+        .bind(&review.account_number)
+        .bind(&review.currency)
+        .bind(row.provenance.as_str())
+        .bind(&references)
+        .fetch_optional(&mut *tx)
+        .await?;
+
+        // Proofs only take the scores of uploads that imported rows, and
+        // only over the period the rows cover
+        if direction.is_some() {
+            sqlx::query(
+                r#"
+                UPDATE statement_imports
+                SET rows_imported = rows_imported + 1,
+                    first_transaction_at = CASE WHEN first_transaction_at IS NOT NULL
+                                                THEN LEAST(first_transaction_at, $2) END,
+                    last_transaction_at = CASE WHEN last_transaction_at IS NOT NULL
+                                               THEN GREATEST(last_transaction_at, $2) END
+                WHERE id = $1
+                "#,
+            )
+            .bind(review.statement_import_id)
+            .bind(row.timestamp)
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await?;
+        Ok(Some(direction.is_some()))
+    }
+
+    /// Leaves a pending row out for good. False if it was resolved
+    /// meanwhile.
+    pub async fn reject(db: &PgPool, review_id: Uuid, by_admin: bool, note: Option<&str>) -> Result<bool, sqlx::Error> {
+        Self::resolve(db, review_id, "rejected", by_admin, note).await
+    }
+
+    async fn resolve(
+        executor: impl sqlx::PgExecutor<'_>,
+        review_id: Uuid,
+        status: &str,
+        by_admin: bool,
+        note: Option<&str>,
+    ) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE pending_review_transactions
+            SET status = $2, resolved_by_admin = $3, resolution_note = $4, resolved_at = NOW(),
+                receipt_number = NULL, raw_text = NULL
+            WHERE id = $1 AND status = 'pending'
+            "#,
+        )
+        .bind(review_id)
+        .bind(status)
+        .bind(by_admin)
+        .bind(note)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+}
@@ -0,0 +1,99 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+
+/// The last run of a scheduled job.
+#[derive(Debug, FromRow)]
+pub struct ScheduledJobRun {
+    pub name: String,
+    pub schedule: String,
+    /// The scheduled time the run was for
+    pub last_scheduled_for: DateTime<Utc>,
+    pub last_started_at: DateTime<Utc>,
+    pub last_finished_at: Option<DateTime<Utc>>,
+    /// "running", "succeeded" or "failed"
+    pub last_status: String,
+    /// The job's summary, or its error
+    pub last_output: Option<String>,
+    pub last_duration_ms: Option<i64>,
+    pub last_worker_id: String,
+    pub consecutive_failures: i32,
+}
+
+pub struct ScheduledJobRepo;
+
+impl ScheduledJobRepo {
+    /// The scheduled time the job last ran for; None if it never has.
+    pub async fn last_scheduled_for(db: &PgPool, name: &str) -> Result<Option<DateTime<Utc>>, sqlx::Error> {
+        sqlx::query_scalar("SELECT last_scheduled_for FROM scheduled_jobs WHERE name = $1")
+            .bind(name)
+            .fetch_optional(db)
+            .await
+    }
+
+    /// Records that a worker started the run due at `scheduled_for`.
+    pub async fn start(
+        db: &PgPool,
+        name: &str,
+        schedule: &str,
+        scheduled_for: DateTime<Utc>,
+        worker_id: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO scheduled_jobs (name, schedule, last_scheduled_for, last_started_at, last_status,
+                                        last_worker_id)
+            VALUES ($1, $2, $3, NOW(), 'running', $4)
+            ON CONFLICT (name) DO UPDATE
+            SET schedule = EXCLUDED.schedule,
+                last_scheduled_for = EXCLUDED.last_scheduled_for,
+                last_started_at = NOW(),
+                last_finished_at = NULL,
+                last_status = 'running',
+                last_output = NULL,
+                last_duration_ms = NULL,
+                last_worker_id = EXCLUDED.last_worker_id
+            "#,
+        )
+        .bind(name)
+        .bind(schedule)
+        .bind(scheduled_for)
+        .bind(worker_id)
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Records how the run started by `start` ended.
+    pub async fn finish(db: &PgPool, name: &str, succeeded: bool, output: &str) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE scheduled_jobs
+            SET last_finished_at = NOW(),
+                last_status = CASE WHEN $2 THEN 'succeeded' ELSE 'failed' END,
+                last_output = $3,
+                last_duration_ms = (EXTRACT(EPOCH FROM NOW() - last_started_at) * 1000)::BIGINT,
+                consecutive_failures = CASE WHEN $2 THEN 0 ELSE consecutive_failures + 1 END
+            WHERE name = $1
+            "#,
+        )
+        .bind(name)
+        .bind(succeeded)
+        .bind(output)
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn list(db: &PgPool) -> Result<Vec<ScheduledJobRun>, sqlx::Error> {
+        sqlx::query_as::<_, ScheduledJobRun>(
+            r#"
+            SELECT name, schedule, last_scheduled_for, last_started_at, last_finished_at, last_status, last_output,
+                   last_duration_ms, last_worker_id, consecutive_failures
+            FROM scheduled_jobs
+            ORDER BY name
+            "#,
+        )
+        .fetch_all(db)
+        .await
+    }
+}
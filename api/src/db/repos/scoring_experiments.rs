@@ -0,0 +1,218 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+#[derive(Debug, Clone, FromRow)]
+pub struct ScoringExperiment {
+    pub id: Uuid,
+    pub currency: String,
+    /// None when the baseline is the currency's default thresholds
+    pub baseline_policy_id: Option<Uuid>,
+    pub baseline_policy_hash: String,
+    pub candidate_policy_id: Uuid,
+    pub sample_percentage: i16,
+    pub started_at: DateTime<Utc>,
+    pub ended_at: Option<DateTime<Utc>>,
+}
+
+/// Both policies' results for one sampled session.
+pub struct NewComparison {
+    pub experiment_id: Uuid,
+    pub session_id: Uuid,
+    pub baseline_score: Option<i32>,
+    pub candidate_score: Option<i32>,
+    pub baseline_meets_threshold: Option<bool>,
+    pub candidate_meets_threshold: Option<bool>,
+}
+
+/// Aggregates over an experiment's comparisons. Score columns cover full
+/// proofs, threshold columns threshold proofs.
+#[derive(Debug, FromRow)]
+pub struct ComparisonSummary {
+    pub sessions: i64,
+    pub scored: i64,
+    pub baseline_mean: Option<f64>,
+    pub candidate_mean: Option<f64>,
+    /// The 10th, 25th, 50th, 75th and 90th percentiles
+    pub baseline_percentiles: Option<Vec<f64>>,
+    pub candidate_percentiles: Option<Vec<f64>>,
+    pub mean_difference: Option<f64>,
+    pub scored_higher: i64,
+    pub scored_lower: i64,
+    pub threshold_sessions: i64,
+    pub baseline_passed: i64,
+    pub candidate_passed: i64,
+    pub threshold_flipped: i64,
+}
+
+/// Sessions per 20-point score bucket, `bucket` 0 to 4.
+#[derive(Debug, FromRow)]
+pub struct ScoreBucketCounts {
+    pub bucket: i32,
+    pub baseline: i64,
+    pub candidate: i64,
+}
+
+const EXPERIMENT_COLUMNS: &str = "id, currency, baseline_policy_id, baseline_policy_hash, candidate_policy_id, \
+                                  sample_percentage, started_at, ended_at";
+
+pub struct ScoringExperimentRepo;
+
+impl ScoringExperimentRepo {
+    pub async fn create(
+        db: &PgPool,
+        currency: &str,
+        baseline_policy_id: Option<Uuid>,
+        baseline_policy_hash: &str,
+        candidate_policy_id: Uuid,
+        sample_percentage: i16,
+    ) -> Result<ScoringExperiment, sqlx::Error> {
+        sqlx::query_as::<_, ScoringExperiment>(&format!(
+            r#"
+            INSERT INTO scoring_experiments
+                (currency, baseline_policy_id, baseline_policy_hash, candidate_policy_id, sample_percentage)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING {}
+            "#,
+            EXPERIMENT_COLUMNS
+        ))
+        .bind(currency)
+        .bind(baseline_policy_id)
+        .bind(baseline_policy_hash)
+        .bind(candidate_policy_id)
+        .bind(sample_percentage)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Every experiment, newest first, ended ones included.
+    pub async fn list(db: &PgPool) -> Result<Vec<ScoringExperiment>, sqlx::Error> {
+        sqlx::query_as::<_, ScoringExperiment>(&format!(
+            "SELECT {} FROM scoring_experiments ORDER BY started_at DESC",
+            EXPERIMENT_COLUMNS
+        ))
+        .fetch_all(db)
+        .await
+    }
+
+    pub async fn find(db: &PgPool, id: Uuid) -> Result<Option<ScoringExperiment>, sqlx::Error> {
+        sqlx::query_as::<_, ScoringExperiment>(&format!(
+            "SELECT {} FROM scoring_experiments WHERE id = $1",
+            EXPERIMENT_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Running experiments whose baseline is the policy a proof in
+    /// `currency` is scored under.
+    pub async fn running_for(
+        db: &PgPool,
+        currency: &str,
+        baseline_policy_hash: &str,
+    ) -> Result<Vec<ScoringExperiment>, sqlx::Error> {
+        sqlx::query_as::<_, ScoringExperiment>(&format!(
+            r#"
+            SELECT {}
+            FROM scoring_experiments
+            WHERE currency = $1 AND baseline_policy_hash = $2 AND ended_at IS NULL
+            "#,
+            EXPERIMENT_COLUMNS
+        ))
+        .bind(currency)
+        .bind(baseline_policy_hash)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Stops sampling. The comparisons made so far are kept for the report.
+    /// None if there's no such running experiment.
+    pub async fn end(db: &PgPool, id: Uuid) -> Result<Option<ScoringExperiment>, sqlx::Error> {
+        sqlx::query_as::<_, ScoringExperiment>(&format!(
+            r#"
+            UPDATE scoring_experiments
+            SET ended_at = NOW()
+            WHERE id = $1 AND ended_at IS NULL
+            RETURNING {}
+            "#,
+            EXPERIMENT_COLUMNS
+        ))
+        .bind(id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Records a comparison; a regenerated proof replaces its session's.
+    pub async fn record_comparison(db: &PgPool, comparison: &NewComparison) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            INSERT INTO scoring_comparisons
+                (experiment_id, session_id, baseline_score, candidate_score,
+                 baseline_meets_threshold, candidate_meets_threshold)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (experiment_id, session_id) DO UPDATE SET
+                baseline_score = EXCLUDED.baseline_score,
+                candidate_score = EXCLUDED.candidate_score,
+                baseline_meets_threshold = EXCLUDED.baseline_meets_threshold,
+                candidate_meets_threshold = EXCLUDED.candidate_meets_threshold,
+                created_at = NOW()
+            "#,
+        )
+        .bind(comparison.experiment_id)
+        .bind(comparison.session_id)
+        .bind(comparison.baseline_score)
+        .bind(comparison.candidate_score)
+        .bind(comparison.baseline_meets_threshold)
+        .bind(comparison.candidate_meets_threshold)
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    pub async fn summary(db: &PgPool, experiment_id: Uuid) -> Result<ComparisonSummary, sqlx::Error> {
+        sqlx::query_as::<_, ComparisonSummary>(
+            r#"
+            SELECT COUNT(*) AS sessions,
+                   COUNT(baseline_score) AS scored,
+                   AVG(baseline_score)::FLOAT8 AS baseline_mean,
+                   AVG(candidate_score)::FLOAT8 AS candidate_mean,
+                   percentile_cont(ARRAY[0.1, 0.25, 0.5, 0.75, 0.9]::FLOAT8[])
+                       WITHIN GROUP (ORDER BY baseline_score) AS baseline_percentiles,
+                   percentile_cont(ARRAY[0.1, 0.25, 0.5, 0.75, 0.9]::FLOAT8[])
+                       WITHIN GROUP (ORDER BY candidate_score) AS candidate_percentiles,
+                   AVG(candidate_score - baseline_score)::FLOAT8 AS mean_difference,
+                   COUNT(*) FILTER (WHERE candidate_score > baseline_score) AS scored_higher,
+                   COUNT(*) FILTER (WHERE candidate_score < baseline_score) AS scored_lower,
+                   COUNT(baseline_meets_threshold) AS threshold_sessions,
+                   COUNT(*) FILTER (WHERE baseline_meets_threshold) AS baseline_passed,
+                   COUNT(*) FILTER (WHERE candidate_meets_threshold) AS candidate_passed,
+                   COUNT(*) FILTER (WHERE baseline_meets_threshold <> candidate_meets_threshold) AS threshold_flipped
+            FROM scoring_comparisons
+            WHERE experiment_id = $1
+            "#,
+        )
+        .bind(experiment_id)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Both policies' score distributions; buckets nobody fell in are absent.
+    pub async fn score_buckets(db: &PgPool, experiment_id: Uuid) -> Result<Vec<ScoreBucketCounts>, sqlx::Error> {
+        sqlx::query_as::<_, ScoreBucketCounts>(
+            r#"
+            SELECT LEAST(s.score / 20, 4) AS bucket,
+                   COUNT(*) FILTER (WHERE NOT s.candidate) AS baseline,
+                   COUNT(*) FILTER (WHERE s.candidate) AS candidate
+            FROM scoring_comparisons c,
+                 LATERAL (VALUES (c.baseline_score, false), (c.candidate_score, true)) AS s(score, candidate)
+            WHERE c.experiment_id = $1 AND s.score IS NOT NULL
+            GROUP BY 1
+            ORDER BY 1
+            "#,
+        )
+        .bind(experiment_id)
+        .fetch_all(db)
+        .await
+    }
+}
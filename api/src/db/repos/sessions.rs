@@ -0,0 +1,490 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool, Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::models::{ProofStatus, SessionStage};
+
+/// What the worker needs to run a queued session.
+#[derive(Debug, FromRow)]
+pub struct SessionJob {
+    pub till_id: Uuid,
+    pub secondary_source: Option<String>,
+    pub score_threshold: Option<i32>,
+    pub account_number: Option<String>,
+    /// Unset on sessions queued before currencies were recorded
+    pub currency: Option<String>,
+    pub scoring_policy_id: Option<Uuid>,
+    pub input_mode: String,
+    /// The till's, which days are grouped in
+    pub timezone: String,
+    /// A `proof_core::DataSource` name
+    pub data_source: String,
+}
+
+#[derive(Debug, FromRow)]
+pub struct SessionStatus {
+    pub status: ProofStatus,
+    pub progress: Option<i32>,
+    pub error_message: Option<String>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct SessionSummary {
+    pub id: Uuid,
+    pub till_id: Uuid,
+    pub status: ProofStatus,
+    pub credit_score: Option<i32>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// The newest session of a till, for coverage dashboards.
+#[derive(Debug, FromRow)]
+pub struct LatestSession {
+    pub id: Uuid,
+    pub status: ProofStatus,
+    pub proof_type: String,
+    pub credit_score: Option<i32>,
+    pub score_threshold: Option<i32>,
+    pub meets_threshold: Option<bool>,
+    pub created_at: DateTime<Utc>,
+    pub proving_finished_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, FromRow)]
+pub struct SessionEvent {
+    pub stage: SessionStage,
+    pub detail: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What admins search sessions by. Every filter is optional; a session
+/// matches when it passes all the ones given.
+pub struct SessionSearch<'a> {
+    /// Words of the error message, matched as a web search query
+    pub error_text: Option<&'a str>,
+    pub verification_code: Option<&'a str>,
+    /// The account owner's phone, or one linked to the account
+    pub phone_number: Option<&'a str>,
+    pub till_number: Option<&'a str>,
+    pub status: Option<ProofStatus>,
+    pub min_score: Option<i32>,
+    pub max_score: Option<i32>,
+    pub created_from: Option<DateTime<Utc>>,
+    pub created_to: Option<DateTime<Utc>>,
+    /// Only sessions listed after this one, for the next page
+    pub after: Option<Uuid>,
+    pub limit: i64,
+}
+
+/// A session as support sees it in search results.
+#[derive(Debug, FromRow)]
+pub struct SessionSearchResult {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub till_id: Uuid,
+    pub till_number: String,
+    pub status: ProofStatus,
+    /// The last step it reached
+    pub stage: Option<SessionStage>,
+    pub progress: Option<i32>,
+    pub credit_score: Option<i32>,
+    pub verification_code: String,
+    pub error_message: Option<String>,
+    pub attempts: i32,
+    pub lease_owner: Option<String>,
+    pub lease_expires_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+pub struct SessionRepo;
+
+impl SessionRepo {
+    pub async fn job(db: &PgPool, session_id: Uuid) -> Result<Option<SessionJob>, sqlx::Error> {
+        sqlx::query_as::<_, SessionJob>(
+            r#"
+            SELECT ps.till_id, ps.secondary_source, ps.score_threshold, ps.account_number, ps.currency,
+                   ps.scoring_policy_id, ps.input_mode, t.timezone, ps.data_source
+            FROM proof_sessions ps
+            JOIN business_tills t ON t.id = ps.till_id
+            WHERE ps.id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Takes the lease on a session for `worker_id`. Returns false when
+    /// another live worker holds it or the session has already finished, so
+    /// a job queued twice is only proved once.
+    pub async fn claim(db: &PgPool, session_id: Uuid, worker_id: &str, lease_secs: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE proof_sessions
+            SET status = 'processing',
+                lease_owner = $2,
+                lease_expires_at = NOW() + make_interval(secs => $3),
+                attempts = attempts + 1
+            WHERE id = $1
+              AND status IN ('pending', 'processing')
+              AND (lease_owner IS NULL OR lease_expires_at < NOW())
+            "#,
+        )
+        .bind(session_id)
+        .bind(worker_id)
+        .bind(lease_secs as f64)
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Extends a lease the worker still holds. Returns false if it was lost.
+    pub async fn renew_lease(db: &PgPool, session_id: Uuid, worker_id: &str, lease_secs: i64) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE proof_sessions
+            SET lease_expires_at = NOW() + make_interval(secs => $3)
+            WHERE id = $1 AND lease_owner = $2
+            "#,
+        )
+        .bind(session_id)
+        .bind(worker_id)
+        .bind(lease_secs as f64)
+        .execute(db)
+        .await?;
+        Ok(result.rows_affected() == 1)
+    }
+
+    /// Gives up the worker's lease. A session it stopped working on before
+    /// it finished goes back to pending, and into the `proof_queue` table,
+    /// if it has attempts left, or is marked failed. Returns whether it was
+    /// requeued, for pushing onto Redis.
+    pub async fn release_lease(
+        db: &PgPool,
+        session_id: Uuid,
+        worker_id: &str,
+        max_attempts: i32,
+    ) -> Result<bool, sqlx::Error> {
+        let released: Option<(bool, ProofStatus)> = sqlx::query_as(
+            r#"
+            WITH held AS (
+                SELECT id, status = 'processing' AS unfinished
+                FROM proof_sessions
+                WHERE id = $1 AND lease_owner = $2
+                FOR UPDATE
+            ),
+            released AS (
+                UPDATE proof_sessions ps
+                SET status = CASE
+                        WHEN NOT held.unfinished THEN ps.status
+                        WHEN ps.attempts >= $3 THEN 'failed'::proof_status
+                        ELSE 'pending'::proof_status
+                    END,
+                    error_message = CASE
+                        WHEN held.unfinished AND ps.attempts >= $3 THEN 'Proving was interrupted too many times'
+                        ELSE ps.error_message
+                    END,
+                    lease_owner = NULL,
+                    lease_expires_at = NULL
+                FROM held
+                WHERE ps.id = held.id
+                RETURNING held.unfinished, ps.status
+            ),
+            queued AS (
+                INSERT INTO proof_queue (session_id)
+                SELECT $1 FROM released WHERE unfinished AND status = 'pending'
+                ON CONFLICT (session_id) DO NOTHING
+            )
+            SELECT unfinished, status FROM released
+            "#,
+        )
+        .bind(session_id)
+        .bind(worker_id)
+        .bind(max_attempts)
+        .fetch_optional(db)
+        .await?;
+
+        match released {
+            Some((true, ProofStatus::Pending)) => {
+                let detail = Some("Requeued after proving stopped");
+                Self::record_event(db, session_id, SessionStage::Queued, detail).await?;
+                Ok(true)
+            }
+            Some((true, _)) => {
+                let detail = Some("Proving was interrupted too many times");
+                Self::record_event(db, session_id, SessionStage::Failed, detail).await?;
+                Ok(false)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Frees sessions whose worker stopped renewing its lease. Sessions with
+    /// attempts left go back to pending, and into the `proof_queue` table in
+    /// the same statement, and are returned for pushing onto Redis; the rest
+    /// are marked failed.
+    pub async fn reclaim_expired_leases(db: &PgPool, max_attempts: i32) -> Result<Vec<Uuid>, sqlx::Error> {
+        let rows: Vec<(Uuid, ProofStatus)> = sqlx::query_as(
+            r#"
+            WITH reclaimed AS (
+                UPDATE proof_sessions
+                SET status = CASE WHEN attempts >= $1 THEN 'failed'::proof_status ELSE 'pending'::proof_status END,
+                    error_message = CASE WHEN attempts >= $1 THEN 'Proving was interrupted too many times' END,
+                    lease_owner = NULL,
+                    lease_expires_at = NULL
+                WHERE status = 'processing' AND lease_expires_at < NOW()
+                RETURNING id, status
+            ),
+            queued AS (
+                INSERT INTO proof_queue (session_id)
+                SELECT id FROM reclaimed WHERE status = 'pending'
+                ON CONFLICT (session_id) DO NOTHING
+            )
+            SELECT id, status FROM reclaimed
+            "#,
+        )
+        .bind(max_attempts)
+        .fetch_all(db)
+        .await?;
+
+        for (session_id, status) in &rows {
+            match status {
+                ProofStatus::Pending => {
+                    Self::record_event(db, *session_id, SessionStage::Queued, Some("Requeued after its worker stopped"))
+                        .await?
+                }
+                _ => {
+                    Self::record_event(
+                        db,
+                        *session_id,
+                        SessionStage::Failed,
+                        Some("Proving was interrupted too many times"),
+                    )
+                    .await?
+                }
+            }
+        }
+
+        Ok(rows
+            .into_iter()
+            .filter(|(_, status)| matches!(status, ProofStatus::Pending))
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    /// Sessions currently held by a worker.
+    pub async fn leased_count(db: &PgPool) -> Result<i64, sqlx::Error> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM proof_sessions WHERE status = 'processing' AND lease_expires_at > NOW()")
+            .fetch_one(db)
+            .await
+    }
+
+    pub async fn set_status(db: &PgPool, session_id: Uuid, status: ProofStatus) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE proof_sessions SET status = $1 WHERE id = $2")
+            .bind(status)
+            .bind(session_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn set_progress(db: &PgPool, session_id: Uuid, progress: i32) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE proof_sessions SET progress = $1 WHERE id = $2")
+            .bind(progress)
+            .bind(session_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Records the lowest authenticity score among the uploads the proof
+    /// scores and the trust tier it gives the proof.
+    pub async fn set_authenticity(
+        db: &PgPool,
+        session_id: Uuid,
+        authenticity_score: Option<u8>,
+        trust_tier: &str,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE proof_sessions SET authenticity_score = $1, trust_tier = $2 WHERE id = $3")
+            .bind(authenticity_score.map(|s| s as i16))
+            .bind(trust_tier)
+            .bind(session_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn mark_failed(db: &PgPool, session_id: Uuid, error_message: &str) -> Result<(), sqlx::Error> {
+        sqlx::query("UPDATE proof_sessions SET status = 'failed', error_message = $1 WHERE id = $2")
+            .bind(error_message)
+            .bind(session_id)
+            .execute(db)
+            .await?;
+        Self::record_event(db, session_id, SessionStage::Failed, Some(error_message)).await
+    }
+
+    /// Appends a step to the session's timeline.
+    pub async fn record_event(
+        db: &PgPool,
+        session_id: Uuid,
+        stage: SessionStage,
+        detail: Option<&str>,
+    ) -> Result<(), sqlx::Error> {
+        sqlx::query("INSERT INTO proof_session_events (session_id, stage, detail) VALUES ($1, $2, $3)")
+            .bind(session_id)
+            .bind(stage)
+            .bind(detail)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// A session's timeline, oldest first. Scoped to the owner unless
+    /// `user_id` is `None`; `None` when the session isn't visible.
+    pub async fn events(
+        db: &PgPool,
+        session_id: Uuid,
+        user_id: Option<Uuid>,
+    ) -> Result<Option<Vec<SessionEvent>>, sqlx::Error> {
+        let visible: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM proof_sessions WHERE id = $1 AND ($2::uuid IS NULL OR user_id = $2))",
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .fetch_one(db)
+        .await?;
+        if !visible {
+            return Ok(None);
+        }
+
+        let events = sqlx::query_as::<_, SessionEvent>(
+            r#"
+            SELECT stage, detail, created_at
+            FROM proof_session_events
+            WHERE session_id = $1
+            ORDER BY id
+            "#,
+        )
+        .bind(session_id)
+        .fetch_all(db)
+        .await?;
+        Ok(Some(events))
+    }
+
+    /// Status of a session, scoped to its owner.
+    pub async fn status_for_user(
+        db: &PgPool,
+        session_id: Uuid,
+        user_id: Uuid,
+    ) -> Result<Option<SessionStatus>, sqlx::Error> {
+        sqlx::query_as::<_, SessionStatus>(
+            r#"
+            SELECT status, progress, error_message
+            FROM proof_sessions
+            WHERE id = $1 AND user_id = $2
+            "#,
+        )
+        .bind(session_id)
+        .bind(user_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    pub async fn latest_for_till(db: &PgPool, till_id: Uuid) -> Result<Option<LatestSession>, sqlx::Error> {
+        sqlx::query_as::<_, LatestSession>(
+            r#"
+            SELECT id, status, proof_type, credit_score, score_threshold, meets_threshold, created_at,
+                   proving_finished_at
+            FROM proof_sessions
+            WHERE till_id = $1
+            ORDER BY created_at DESC
+            LIMIT 1
+            "#,
+        )
+        .bind(till_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Most recent sessions for a user, newest first.
+    pub async fn list_for_user(db: &PgPool, user_id: Uuid, limit: i64) -> Result<Vec<SessionSummary>, sqlx::Error> {
+        sqlx::query_as::<_, SessionSummary>(
+            r#"
+            SELECT id, till_id, status, credit_score, created_at
+            FROM proof_sessions
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(user_id)
+        .bind(limit)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Sessions matching every given filter, newest first. Only the filters
+    /// given are added to the query, so each can use its index.
+    pub async fn search(db: &PgPool, search: &SessionSearch<'_>) -> Result<Vec<SessionSearchResult>, sqlx::Error> {
+        let mut query = QueryBuilder::<Postgres>::new(
+            r#"
+            SELECT ps.id, ps.user_id, ps.till_id, bt.till_number, ps.status,
+                   (SELECT e.stage FROM proof_session_events e WHERE e.session_id = ps.id ORDER BY e.id DESC LIMIT 1)
+                       AS stage,
+                   ps.progress, ps.credit_score, ps.verification_code, ps.error_message, ps.attempts,
+                   ps.lease_owner, ps.lease_expires_at, ps.created_at, ps.updated_at
+            FROM proof_sessions ps
+            JOIN business_tills bt ON bt.id = ps.till_id
+            WHERE TRUE
+            "#,
+        );
+        if let Some(error_text) = search.error_text {
+            query
+                .push(" AND ps.error_message IS NOT NULL")
+                .push(" AND to_tsvector('simple', ps.error_message) @@ websearch_to_tsquery('simple', ")
+                .push_bind(error_text)
+                .push(")");
+        }
+        if let Some(verification_code) = search.verification_code {
+            query.push(" AND ps.verification_code = ").push_bind(verification_code);
+        }
+        if let Some(phone_number) = search.phone_number {
+            query
+                .push(" AND ps.user_id IN (SELECT id FROM users WHERE phone_number = ")
+                .push_bind(phone_number)
+                .push(" UNION ALL SELECT user_id FROM user_phones WHERE phone_number = ")
+                .push_bind(phone_number)
+                .push(")");
+        }
+        if let Some(till_number) = search.till_number {
+            query.push(" AND bt.till_number = ").push_bind(till_number);
+        }
+        if let Some(status) = &search.status {
+            query.push(" AND ps.status = ").push_bind(status);
+        }
+        if let Some(min_score) = search.min_score {
+            query.push(" AND ps.credit_score >= ").push_bind(min_score);
+        }
+        if let Some(max_score) = search.max_score {
+            query.push(" AND ps.credit_score <= ").push_bind(max_score);
+        }
+        if let Some(created_from) = search.created_from {
+            query.push(" AND ps.created_at >= ").push_bind(created_from);
+        }
+        if let Some(created_to) = search.created_to {
+            query.push(" AND ps.created_at < ").push_bind(created_to);
+        }
+        if let Some(after) = search.after {
+            query
+                .push(" AND (ps.created_at, ps.id) < (SELECT created_at, id FROM proof_sessions WHERE id = ")
+                .push_bind(after)
+                .push(")");
+        }
+        query
+            .push(" ORDER BY ps.created_at DESC, ps.id DESC LIMIT ")
+            .push_bind(search.limit);
+
+        query.build_query_as::<SessionSearchResult>().fetch_all(db).await
+    }
+}
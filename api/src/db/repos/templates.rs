@@ -0,0 +1,59 @@
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::ProofTemplate;
+
+pub struct ProofTemplateRepo;
+
+impl ProofTemplateRepo {
+    pub async fn find(db: &PgPool, id: Uuid) -> Result<Option<ProofTemplate>, sqlx::Error> {
+        sqlx::query_as::<_, ProofTemplate>(
+            r#"
+            SELECT id, lender_id, name, min_period_days, required_disclosures, scoring_policy_id, created_at
+            FROM proof_templates
+            WHERE id = $1
+            "#,
+        )
+        .bind(id)
+        .fetch_optional(db)
+        .await
+    }
+
+    pub async fn list_for_lender(db: &PgPool, lender_id: Uuid) -> Result<Vec<ProofTemplate>, sqlx::Error> {
+        sqlx::query_as::<_, ProofTemplate>(
+            r#"
+            SELECT id, lender_id, name, min_period_days, required_disclosures, scoring_policy_id, created_at
+            FROM proof_templates
+            WHERE lender_id = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(lender_id)
+        .fetch_all(db)
+        .await
+    }
+
+    pub async fn create(
+        db: &PgPool,
+        lender_id: Uuid,
+        name: &str,
+        min_period_days: i32,
+        required_disclosures: &[String],
+        scoring_policy_id: Option<Uuid>,
+    ) -> Result<ProofTemplate, sqlx::Error> {
+        sqlx::query_as::<_, ProofTemplate>(
+            r#"
+            INSERT INTO proof_templates (lender_id, name, min_period_days, required_disclosures, scoring_policy_id)
+            VALUES ($1, $2, $3, $4, $5)
+            RETURNING id, lender_id, name, min_period_days, required_disclosures, scoring_policy_id, created_at
+            "#,
+        )
+        .bind(lender_id)
+        .bind(name)
+        .bind(min_period_days)
+        .bind(required_disclosures)
+        .bind(scoring_policy_id)
+        .fetch_one(db)
+        .await
+    }
+}
@@ -0,0 +1,198 @@
+use chrono::{DateTime, NaiveDate, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+use crate::models::BusinessTill;
+
+/// One month of a till's transactions from one source, maintained by a
+/// trigger on insert.
+#[derive(Debug, FromRow)]
+pub struct MonthlyAggregate {
+    pub month: NaiveDate,
+    pub source: String,
+    pub transaction_count: i64,
+    pub volume_cents: i64,
+    pub first_transaction_at: DateTime<Utc>,
+    pub last_transaction_at: DateTime<Utc>,
+}
+
+/// One account's registration of a till number more than one account has
+/// registered.
+#[derive(Debug, FromRow)]
+pub struct ContestedRegistration {
+    pub till_number: String,
+    pub till_id: Uuid,
+    pub user_id: Uuid,
+    pub is_verified: bool,
+    pub created_at: DateTime<Utc>,
+    pub superseded_by: Option<Uuid>,
+    pub superseded_at: Option<DateTime<Utc>>,
+    /// When the account asked to verify it while another held it verified
+    pub ownership_review_requested_at: Option<DateTime<Utc>>,
+}
+
+pub struct TillRepo;
+
+impl TillRepo {
+    /// Owner of a till, or `None` if the till doesn't exist.
+    pub async fn owner(db: &PgPool, till_id: Uuid) -> Result<Option<Uuid>, sqlx::Error> {
+        sqlx::query_scalar("SELECT user_id FROM business_tills WHERE id = $1")
+            .bind(till_id)
+            .fetch_optional(db)
+            .await
+    }
+
+    pub async fn list_for_user(db: &PgPool, user_id: Uuid) -> Result<Vec<BusinessTill>, sqlx::Error> {
+        sqlx::query_as::<_, BusinessTill>(
+            r#"
+            SELECT id, user_id, till_number, till_type,
+                   is_verified, api_connected, verification_method, organization_name, timezone, created_at,
+                   updated_at
+            FROM business_tills
+            WHERE user_id = $1
+            ORDER BY created_at DESC
+            "#,
+        )
+        .bind(user_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// IANA timezone a till's statements are read in, or `None` if the till
+    /// doesn't exist.
+    pub async fn timezone(db: &PgPool, till_id: Uuid) -> Result<Option<String>, sqlx::Error> {
+        sqlx::query_scalar("SELECT timezone FROM business_tills WHERE id = $1")
+            .bind(till_id)
+            .fetch_optional(db)
+            .await
+    }
+
+    pub async fn set_completeness(db: &PgPool, till_id: Uuid, percentage: u8) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            "UPDATE business_tills SET completeness_percentage = $2, completeness_checked_at = NOW() WHERE id = $1",
+        )
+        .bind(till_id)
+        .bind(percentage as i16)
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Monthly totals for a till, oldest month first.
+    pub async fn monthly_aggregates(db: &PgPool, till_id: Uuid) -> Result<Vec<MonthlyAggregate>, sqlx::Error> {
+        sqlx::query_as::<_, MonthlyAggregate>(
+            r#"
+            SELECT month, source, transaction_count, volume_cents, first_transaction_at, last_transaction_at
+            FROM till_monthly_aggregates
+            WHERE till_id = $1
+            ORDER BY month ASC, source ASC
+            "#,
+        )
+        .bind(till_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Whether an account other than `user_id` holds this till number
+    /// verified.
+    pub async fn verified_elsewhere(db: &PgPool, till_number: &str, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT EXISTS(SELECT 1 FROM business_tills WHERE till_number = $1 AND is_verified AND user_id <> $2)",
+        )
+        .bind(till_number)
+        .bind(user_id)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Marks a till verified unless another registration of the same number
+    /// already holds it. Returns false, leaving the till unverified, when one
+    /// does; only an admin can settle that (see `award`).
+    pub async fn verify(db: &PgPool, till_id: Uuid) -> Result<bool, sqlx::Error> {
+        let updated = sqlx::query(
+            r#"
+            UPDATE business_tills t
+            SET is_verified = true, ownership_review_requested_at = NULL
+            WHERE t.id = $1
+              AND NOT EXISTS (
+                  SELECT 1 FROM business_tills other
+                  WHERE other.till_number = t.till_number AND other.is_verified AND other.id <> t.id
+              )
+            "#,
+        )
+        .bind(till_id)
+        .execute(db)
+        .await;
+        match updated {
+            Ok(updated) => Ok(updated.rows_affected() > 0),
+            // Another registration was verified at the same moment
+            Err(sqlx::Error::Database(e)) if e.is_unique_violation() => Ok(false),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Puts a till whose number another account holds verified in the admin
+    /// review queue.
+    pub async fn request_ownership_review(db: &PgPool, till_id: Uuid) -> Result<(), sqlx::Error> {
+        sqlx::query(
+            r#"
+            UPDATE business_tills
+            SET ownership_review_requested_at = COALESCE(ownership_review_requested_at, NOW())
+            WHERE id = $1
+            "#,
+        )
+        .bind(till_id)
+        .execute(db)
+        .await?;
+        Ok(())
+    }
+
+    /// Marks a till verified once an admin has confirmed who owns it,
+    /// unverifying whichever other registration of the same number held it.
+    /// Returns the till it was taken from.
+    pub async fn award(db: &PgPool, till_id: Uuid) -> Result<Option<Uuid>, sqlx::Error> {
+        let mut tx = db.begin().await?;
+        let previous: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            UPDATE business_tills
+            SET is_verified = false, superseded_by = $1, superseded_at = NOW()
+            WHERE is_verified AND id <> $1
+              AND till_number = (SELECT till_number FROM business_tills WHERE id = $1)
+            RETURNING id
+            "#,
+        )
+        .bind(till_id)
+        .fetch_optional(&mut *tx)
+        .await?;
+        sqlx::query(
+            r#"
+            UPDATE business_tills
+            SET is_verified = true, superseded_by = NULL, superseded_at = NULL, ownership_review_requested_at = NULL
+            WHERE id = $1
+            "#,
+        )
+        .bind(till_id)
+        .execute(&mut *tx)
+        .await?;
+        tx.commit().await?;
+        Ok(previous)
+    }
+
+    /// Every registration of till numbers registered by more than one
+    /// account, grouped by number.
+    pub async fn contested(db: &PgPool) -> Result<Vec<ContestedRegistration>, sqlx::Error> {
+        sqlx::query_as::<_, ContestedRegistration>(
+            r#"
+            SELECT till_number, id AS till_id, user_id, is_verified, created_at, superseded_by, superseded_at,
+                   ownership_review_requested_at
+            FROM business_tills
+            WHERE till_number IN (
+                SELECT till_number FROM business_tills GROUP BY till_number HAVING COUNT(*) > 1
+            )
+            ORDER BY till_number, created_at
+            "#,
+        )
+        .fetch_all(db)
+        .await
+    }
+}
@@ -0,0 +1,67 @@
+use sqlx::PgPool;
+
+use crate::models::TransactionTypeMapping;
+
+pub struct TransactionTypeRepo;
+
+impl TransactionTypeRepo {
+    pub async fn list(db: &PgPool) -> Result<Vec<TransactionTypeMapping>, sqlx::Error> {
+        sqlx::query_as::<_, TransactionTypeMapping>(
+            r#"
+            SELECT pattern, kind, updated_at
+            FROM transaction_type_mappings
+            ORDER BY pattern
+            "#,
+        )
+        .fetch_all(db)
+        .await
+    }
+
+    pub async fn upsert(
+        executor: impl sqlx::PgExecutor<'_>,
+        pattern: &str,
+        kind: &str,
+    ) -> Result<TransactionTypeMapping, sqlx::Error> {
+        sqlx::query_as::<_, TransactionTypeMapping>(
+            r#"
+            INSERT INTO transaction_type_mappings (pattern, kind)
+            VALUES ($1, $2)
+            ON CONFLICT (pattern) DO UPDATE SET kind = EXCLUDED.kind, updated_at = NOW()
+            RETURNING pattern, kind, updated_at
+            "#,
+        )
+        .bind(pattern)
+        .bind(kind)
+        .fetch_one(executor)
+        .await
+    }
+
+    /// Returns whether the pattern existed.
+    pub async fn delete(executor: impl sqlx::PgExecutor<'_>, pattern: &str) -> Result<bool, sqlx::Error> {
+        let result = sqlx::query("DELETE FROM transaction_type_mappings WHERE pattern = $1")
+            .bind(pattern)
+            .execute(executor)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Re-derives the kind, and with it the direction, of stored rows a
+    /// changed pattern could affect. Returns how many rows changed kind. Run
+    /// it in the transaction that changed the pattern, so rows are never
+    /// left classified under a mapping that no longer holds.
+    pub async fn reclassify(executor: impl sqlx::PgExecutor<'_>, pattern: &str) -> Result<u64, sqlx::Error> {
+        let result = sqlx::query(
+            r#"
+            UPDATE transactions
+            SET kind = transaction_kind(transaction_type),
+                direction = transaction_direction(transaction_kind(transaction_type), amount)
+            WHERE starts_with(normalize_transaction_type(transaction_type), $1)
+              AND kind <> transaction_kind(transaction_type)
+            "#,
+        )
+        .bind(pattern)
+        .execute(executor)
+        .await?;
+        Ok(result.rows_affected())
+    }
+}
@@ -0,0 +1,148 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::models::Transaction;
+
+pub struct TransactionRepo;
+
+impl TransactionRepo {
+    /// A till's M-Pesa rows plus, when given, one secondary source, oldest
+    /// first as the guest expects.
+    pub async fn for_proof(
+        db: &PgPool,
+        till_id: Uuid,
+        secondary_source: Option<&str>,
+    ) -> Result<Vec<Transaction>, sqlx::Error> {
+        sqlx::query_as::<_, Transaction>(
+            r#"
+            SELECT id, till_id, timestamp, amount, transaction_type, kind, direction, reference, raw_data, created_at,
+                   source, account_number, currency, provenance
+            FROM transactions
+            WHERE till_id = $1 AND (source = 'mpesa' OR source = $2)
+            ORDER BY timestamp ASC
+            "#,
+        )
+        .bind(till_id)
+        .bind(secondary_source)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Distinct currencies among the rows `for_proof` would return. A proof
+    /// can only be generated when there is exactly one.
+    pub async fn currencies_for_proof(
+        db: &PgPool,
+        till_id: Uuid,
+        secondary_source: Option<&str>,
+    ) -> Result<Vec<String>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT currency
+            FROM transactions
+            WHERE till_id = $1 AND (source = 'mpesa' OR source = $2)
+            ORDER BY currency
+            "#,
+        )
+        .bind(till_id)
+        .bind(secondary_source)
+        .fetch_all(db)
+        .await
+    }
+
+    /// First and last timestamps among the rows `for_proof` would return,
+    /// or `None` when there are none.
+    pub async fn period_for_proof(
+        db: &PgPool,
+        till_id: Uuid,
+        secondary_source: Option<&str>,
+    ) -> Result<Option<(DateTime<Utc>, DateTime<Utc>)>, sqlx::Error> {
+        let (start, end): (Option<DateTime<Utc>>, Option<DateTime<Utc>>) = sqlx::query_as(
+            r#"
+            SELECT MIN(timestamp), MAX(timestamp)
+            FROM transactions
+            WHERE till_id = $1 AND (source = 'mpesa' OR source = $2)
+            "#,
+        )
+        .bind(till_id)
+        .bind(secondary_source)
+        .fetch_one(db)
+        .await?;
+        Ok(start.zip(end))
+    }
+
+    /// Whether the till has any rows Safaricom delivered directly, the only
+    /// ones a verified-API proof scores.
+    pub async fn has_api_sourced(db: &PgPool, till_id: Uuid) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM transactions WHERE till_id = $1 AND provenance IN ('c2b', 'daraja'))",
+        )
+        .bind(till_id)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Row counts of a till by derived kind, across every source.
+    pub async fn kind_counts(db: &PgPool, till_id: Uuid) -> Result<Vec<(String, i64)>, sqlx::Error> {
+        sqlx::query_as(
+            r#"
+            SELECT kind, COUNT(*)
+            FROM transactions
+            WHERE till_id = $1
+            GROUP BY kind
+            ORDER BY kind
+            "#,
+        )
+        .bind(till_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Local days since the epoch, at `utc_offset_secs`, with a scored
+    /// payment in the six 30-day months before the till's latest one, across
+    /// every source. That's the window the guest scores.
+    pub async fn scored_days(db: &PgPool, till_id: Uuid, utc_offset_secs: i32) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT floor((extract(epoch FROM timestamp) + $2) / 86400)::BIGINT AS day
+            FROM transactions
+            WHERE till_id = $1 AND kind IN ('Payment', 'Reversal') AND direction = 'inflow' AND amount <> 0
+              AND timestamp >= (
+                  SELECT MAX(timestamp) FROM transactions
+                  WHERE till_id = $1 AND kind IN ('Payment', 'Reversal') AND direction = 'inflow' AND amount <> 0
+              ) - INTERVAL '180 days'
+            ORDER BY day
+            "#,
+        )
+        .bind(till_id)
+        .bind(utc_offset_secs)
+        .fetch_all(db)
+        .await
+    }
+
+    /// The days `scored_days` would count, between `from` and `to` instead
+    /// of over the scoring window.
+    pub async fn scored_days_between(
+        db: &PgPool,
+        till_id: Uuid,
+        utc_offset_secs: i32,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+    ) -> Result<Vec<i64>, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT floor((extract(epoch FROM timestamp) + $2) / 86400)::BIGINT AS day
+            FROM transactions
+            WHERE till_id = $1 AND kind IN ('Payment', 'Reversal') AND direction = 'inflow' AND amount <> 0
+              AND timestamp >= $3 AND timestamp < $4
+            ORDER BY day
+            "#,
+        )
+        .bind(till_id)
+        .bind(utc_offset_secs)
+        .bind(from)
+        .bind(to)
+        .fetch_all(db)
+        .await
+    }
+}
@@ -0,0 +1,216 @@
+use chrono::{DateTime, Utc};
+use sqlx::{FromRow, PgPool};
+use uuid::Uuid;
+
+/// A sign-in on the app, which the token it issued is checked against.
+#[derive(Debug, FromRow)]
+pub struct UserSession {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub phone_number: String,
+    /// A `PhoneRole` name
+    pub role: String,
+    pub device_hint: Option<String>,
+    pub ip_range: Option<String>,
+    /// Signed in from a device and network the phone hadn't used before
+    pub anomalous: bool,
+    /// When it last confirmed a sensitive action with a fresh OTP
+    pub stepped_up_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+/// A sign-in to record.
+pub struct NewUserSession<'a> {
+    pub user_id: Uuid,
+    pub phone_number: &'a str,
+    pub role: crate::models::PhoneRole,
+    pub device_hint: Option<&'a str>,
+    pub ip_range: Option<&'a str>,
+    pub anomalous: bool,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// What a phone's earlier sign-ins on an account had in common with a new
+/// one.
+#[derive(Debug, FromRow)]
+pub struct LoginHistory {
+    pub signed_in_before: bool,
+    pub device_seen: bool,
+    pub ip_range_seen: bool,
+}
+
+const SESSION_COLUMNS: &str = "id, user_id, phone_number, role, device_hint, ip_range, anomalous, stepped_up_at, \
+                               created_at, last_used_at, expires_at, revoked_at";
+
+/// How stale `last_used_at` may get before a request updates it, so that
+/// not every request writes.
+const TOUCH_INTERVAL: &str = "5 minutes";
+
+pub struct UserSessionRepo;
+
+impl UserSessionRepo {
+    pub async fn create(db: &PgPool, session: &NewUserSession<'_>) -> Result<UserSession, sqlx::Error> {
+        sqlx::query_as::<_, UserSession>(&format!(
+            r#"
+            INSERT INTO user_sessions (user_id, phone_number, role, device_hint, ip_range, anomalous, expires_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            RETURNING {}
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(session.user_id)
+        .bind(session.phone_number)
+        .bind(session.role.as_str())
+        .bind(session.device_hint)
+        .bind(session.ip_range)
+        .bind(session.anomalous)
+        .bind(session.expires_at)
+        .fetch_one(db)
+        .await
+    }
+
+    /// How the phone's sign-ins on the account since `since`, signed out
+    /// ones included, compare with a new one.
+    pub async fn login_history(
+        db: &PgPool,
+        user_id: Uuid,
+        phone_number: &str,
+        device_hint: Option<&str>,
+        ip_range: Option<&str>,
+        since: DateTime<Utc>,
+    ) -> Result<LoginHistory, sqlx::Error> {
+        sqlx::query_as::<_, LoginHistory>(
+            r#"
+            SELECT
+                COUNT(*) > 0 AS signed_in_before,
+                COALESCE(BOOL_OR(device_hint = $3), FALSE) AS device_seen,
+                COALESCE(BOOL_OR(ip_range = $4), FALSE) AS ip_range_seen
+            FROM user_sessions
+            WHERE user_id = $1 AND phone_number = $2 AND created_at > $5
+            "#,
+        )
+        .bind(user_id)
+        .bind(phone_number)
+        .bind(device_hint)
+        .bind(ip_range)
+        .bind(since)
+        .fetch_one(db)
+        .await
+    }
+
+    /// The account's sessions that still work, most recently used first.
+    pub async fn list_active(db: &PgPool, user_id: Uuid) -> Result<Vec<UserSession>, sqlx::Error> {
+        sqlx::query_as::<_, UserSession>(&format!(
+            r#"
+            SELECT {}
+            FROM user_sessions
+            WHERE user_id = $1 AND revoked_at IS NULL AND expires_at > NOW()
+            ORDER BY last_used_at DESC
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(user_id)
+        .fetch_all(db)
+        .await
+    }
+
+    /// Whether the account's session still works, noting the use.
+    pub async fn touch(db: &PgPool, id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(&format!(
+            r#"
+            WITH session AS (
+                SELECT id, last_used_at
+                FROM user_sessions
+                WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL AND expires_at > NOW()
+            ), touched AS (
+                UPDATE user_sessions
+                SET last_used_at = NOW()
+                WHERE id IN (SELECT id FROM session WHERE last_used_at < NOW() - INTERVAL '{}')
+            )
+            SELECT EXISTS(SELECT 1 FROM session)
+            "#,
+            TOUCH_INTERVAL
+        ))
+        .bind(id)
+        .bind(user_id)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Notes that the session confirmed a fresh OTP. False if it no longer
+    /// works.
+    pub async fn step_up(db: &PgPool, id: Uuid, user_id: Uuid) -> Result<bool, sqlx::Error> {
+        let updated = sqlx::query(
+            r#"
+            UPDATE user_sessions
+            SET stepped_up_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL AND expires_at > NOW()
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .execute(db)
+        .await?;
+        Ok(updated.rows_affected() > 0)
+    }
+
+    /// Whether the session confirmed a fresh OTP after `since`.
+    pub async fn stepped_up_since(
+        db: &PgPool,
+        id: Uuid,
+        user_id: Uuid,
+        since: DateTime<Utc>,
+    ) -> Result<bool, sqlx::Error> {
+        sqlx::query_scalar(
+            r#"
+            SELECT EXISTS(
+                SELECT 1 FROM user_sessions
+                WHERE id = $1 AND user_id = $2 AND stepped_up_at > $3
+            )
+            "#,
+        )
+        .bind(id)
+        .bind(user_id)
+        .bind(since)
+        .fetch_one(db)
+        .await
+    }
+
+    /// Signs one of the account's sessions out; its token is refused from
+    /// the next request on. None if it isn't theirs or already doesn't work.
+    pub async fn revoke(db: &PgPool, id: Uuid, user_id: Uuid) -> Result<Option<UserSession>, sqlx::Error> {
+        sqlx::query_as::<_, UserSession>(&format!(
+            r#"
+            UPDATE user_sessions
+            SET revoked_at = NOW()
+            WHERE id = $1 AND user_id = $2 AND revoked_at IS NULL AND expires_at > NOW()
+            RETURNING {}
+            "#,
+            SESSION_COLUMNS
+        ))
+        .bind(id)
+        .bind(user_id)
+        .fetch_optional(db)
+        .await
+    }
+
+    /// Signs out every session a phone has on the account, for when it's
+    /// unlinked.
+    pub async fn revoke_phone(db: &PgPool, user_id: Uuid, phone_number: &str) -> Result<u64, sqlx::Error> {
+        let revoked = sqlx::query(
+            r#"
+            UPDATE user_sessions
+            SET revoked_at = NOW()
+            WHERE user_id = $1 AND phone_number = $2 AND revoked_at IS NULL AND expires_at > NOW()
+            "#,
+        )
+        .bind(user_id)
+        .bind(phone_number)
+        .execute(db)
+        .await?;
+        Ok(revoked.rows_affected())
+    }
+}
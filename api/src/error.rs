@@ -1,11 +1,13 @@
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use serde_json::json;
 use thiserror::Error;
 
+use crate::i18n::Message;
+
 #[derive(Error, Debug)]
 pub enum AppError {
     #[error("Database error: {0}")]
@@ -23,49 +25,153 @@ pub enum AppError {
     #[error("Not found: {0}")]
     NotFound(String),
 
+    #[error("Till not found")]
+    TillNotFound,
+
+    #[error("Proof not found")]
+    ProofNotFound,
+
+    #[error("Proof has expired")]
+    ProofExpired,
+
+    #[error("Proof has been revoked")]
+    ProofRevoked,
+
     #[error("Internal server error: {0}")]
     Internal(#[from] anyhow::Error),
 
+    /// Carries the number of seconds until the client may retry.
     #[error("Rate limit exceeded")]
-    RateLimit,
+    RateLimit(u64),
+
+    /// The client has made enough lookups that it must solve a captcha
+    /// and send its token with the next one.
+    #[error("Captcha required")]
+    CaptchaRequired,
 
     #[error("Invalid OTP")]
     InvalidOtp,
 
+    /// A sensitive action needs the session to confirm a fresh OTP first.
+    #[error("Step-up verification required")]
+    StepUpRequired,
+
+    /// The billing plan's monthly allowance is used up.
+    #[error("Payment required: {0}")]
+    PaymentRequired(crate::services::billing::LimitReached),
+
     #[error("File processing error: {0}")]
     FileProcessing(String),
+
+    #[error("File rejected: {0}")]
+    FileRejected(crate::services::file_scan::FileRejection),
+}
+
+impl AppError {
+    /// Stable identifier clients can match on instead of the message text.
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::Database(_) => "DATABASE_ERROR",
+            AppError::Redis(_) => "CACHE_ERROR",
+            AppError::Auth(_) => "UNAUTHORIZED",
+            AppError::Validation(_) => "VALIDATION_ERROR",
+            AppError::NotFound(_) => "NOT_FOUND",
+            AppError::TillNotFound => "TILL_NOT_FOUND",
+            AppError::ProofNotFound => "PROOF_NOT_FOUND",
+            AppError::ProofExpired => "PROOF_EXPIRED",
+            AppError::ProofRevoked => "PROOF_REVOKED",
+            AppError::Internal(_) => "INTERNAL_ERROR",
+            AppError::RateLimit(_) => "RATE_LIMITED",
+            AppError::CaptchaRequired => "CAPTCHA_REQUIRED",
+            AppError::InvalidOtp => "INVALID_OTP",
+            AppError::StepUpRequired => "STEP_UP_REQUIRED",
+            AppError::PaymentRequired(_) => "PAYMENT_REQUIRED",
+            AppError::FileProcessing(_) => "FILE_PROCESSING_ERROR",
+            AppError::FileRejected(_) => "FILE_REJECTED",
+        }
+    }
 }
 
 impl IntoResponse for AppError {
     fn into_response(self) -> Response {
-        let details = self.to_string();
+        let locale = crate::middleware::locale::current_locale();
         let (status, error_message) = match &self {
             AppError::Database(e) => {
                 tracing::error!("Database error: {}", e);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Database error".to_string())
+                (StatusCode::INTERNAL_SERVER_ERROR, Message::DatabaseError.render(locale))
             }
             AppError::Redis(e) => {
                 tracing::error!("Redis error: {}", e);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Cache error".to_string())
+                (StatusCode::INTERNAL_SERVER_ERROR, Message::CacheError.render(locale))
             }
             AppError::Auth(msg) => (StatusCode::UNAUTHORIZED, msg.clone()),
             AppError::Validation(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
             AppError::NotFound(msg) => (StatusCode::NOT_FOUND, msg.clone()),
+            AppError::TillNotFound => (StatusCode::NOT_FOUND, Message::TillNotFound.render(locale)),
+            AppError::ProofNotFound => (StatusCode::NOT_FOUND, Message::ProofNotFound.render(locale)),
+            AppError::ProofExpired => (StatusCode::GONE, Message::ProofExpired.render(locale)),
+            AppError::ProofRevoked => (StatusCode::GONE, Message::ProofRevoked.render(locale)),
             AppError::Internal(e) => {
                 tracing::error!("Internal error: {}", e);
-                (StatusCode::INTERNAL_SERVER_ERROR, "Internal server error".to_string())
+                (StatusCode::INTERNAL_SERVER_ERROR, Message::InternalError.render(locale))
             }
-            AppError::RateLimit => (StatusCode::TOO_MANY_REQUESTS, "Rate limit exceeded".to_string()),
-            AppError::InvalidOtp => (StatusCode::UNAUTHORIZED, "Invalid OTP".to_string()),
+            AppError::RateLimit(_) => (StatusCode::TOO_MANY_REQUESTS, Message::RateLimited.render(locale)),
+            AppError::CaptchaRequired => (StatusCode::FORBIDDEN, Message::CaptchaRequired.render(locale)),
+            AppError::InvalidOtp => (StatusCode::UNAUTHORIZED, Message::InvalidOtp.render(locale)),
+            AppError::StepUpRequired => (StatusCode::FORBIDDEN, Message::StepUpRequired.render(locale)),
+            AppError::PaymentRequired(_) => (StatusCode::PAYMENT_REQUIRED, Message::PlanLimitReached.render(locale)),
             AppError::FileProcessing(msg) => (StatusCode::BAD_REQUEST, msg.clone()),
+            AppError::FileRejected(rejection) => {
+                use crate::services::file_scan::FileRejection;
+                match rejection {
+                    FileRejection::TypeMismatch { .. } | FileRejection::UnrecognizedContent => {
+                        (StatusCode::UNSUPPORTED_MEDIA_TYPE, Message::UnsupportedFileType.render(locale))
+                    }
+                    FileRejection::NotAnImage => {
+                        (StatusCode::UNSUPPORTED_MEDIA_TYPE, Message::UnsupportedImageType.render(locale))
+                    }
+                    FileRejection::TooLarge { .. } => (StatusCode::PAYLOAD_TOO_LARGE, Message::FileRejected.render(locale)),
+                    FileRejection::ScanUnavailable => {
+                        (StatusCode::SERVICE_UNAVAILABLE, Message::FileRejected.render(locale))
+                    }
+                    _ => (StatusCode::UNPROCESSABLE_ENTITY, Message::FileRejected.render(locale)),
+                }
+            }
         };
 
-        let body = Json(json!({
+        let mut body = json!({
             "error": error_message,
-            "details": details
-        }));
+            "code": self.code(),
+            "request_id": crate::middleware::request_id::current_request_id(),
+        });
+
+        // Server errors are logged above; their details (SQL text, upstream
+        // errors) must not reach clients.
+        if !status.is_server_error() {
+            body["details"] = json!(self.to_string());
+        }
 
-        (status, body).into_response()
+        if let AppError::FileRejected(rejection) = &self {
+            body["reason"] = json!(rejection.reason());
+            body["details"] = json!(rejection.to_string());
+        }
+
+        if let AppError::PaymentRequired(limit) = &self {
+            body["unit"] = json!(limit.unit);
+            body["included"] = json!(limit.included);
+            body["resets_at"] = json!(limit.resets_at.to_rfc3339());
+        }
+
+        if let AppError::RateLimit(retry_after) = self {
+            body["retry_after"] = json!(retry_after);
+            return (
+                status,
+                [(header::RETRY_AFTER, retry_after.to_string())],
+                Json(body),
+            )
+                .into_response();
+        }
+
+        (status, Json(body)).into_response()
     }
 }
-
@@ -0,0 +1,150 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::db::repos::AccessTokenRepo;
+use crate::error::AppError;
+use crate::handlers::{AppState, Claims};
+use crate::models::{MerchantAccessToken, TOKEN_SCOPES};
+use crate::services::security::SecurityService;
+use crate::utils::{generate_access_token, hash_api_key};
+
+/// Live tokens one merchant may hold at once.
+const MAX_ACTIVE_TOKENS: i64 = 20;
+const MAX_TOKEN_NAME_LEN: usize = 100;
+
+#[derive(Deserialize)]
+pub struct CreateAccessTokenRequest {
+    /// What the token is for, e.g. "Shop POS"
+    pub name: String,
+    /// Any of "data:write" and "proofs:read"
+    pub scopes: Vec<String>,
+    /// Days until the token stops working; it never expires when unset
+    pub expires_in_days: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct AccessTokenResponse {
+    pub id: String,
+    pub name: String,
+    /// First characters of the token, to tell tokens apart
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: String,
+    pub expires_at: Option<String>,
+    pub last_used_at: Option<String>,
+    pub revoked_at: Option<String>,
+}
+
+impl From<MerchantAccessToken> for AccessTokenResponse {
+    fn from(token: MerchantAccessToken) -> Self {
+        Self {
+            id: token.id.to_string(),
+            name: token.name,
+            token_prefix: token.token_prefix,
+            scopes: token.scopes,
+            created_at: token.created_at.to_rfc3339(),
+            expires_at: token.expires_at.map(|t| t.to_rfc3339()),
+            last_used_at: token.last_used_at.map(|t| t.to_rfc3339()),
+            revoked_at: token.revoked_at.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct CreateAccessTokenResponse {
+    /// Shown only this once
+    pub token: String,
+    #[serde(flatten)]
+    pub details: AccessTokenResponse,
+}
+
+pub async fn list_access_tokens(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<AccessTokenResponse>>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let tokens = AccessTokenRepo::list_for_user(&state.db, user_id).await?;
+    Ok(Json(tokens.into_iter().map(AccessTokenResponse::from).collect()))
+}
+
+/// Issues a token a POS vendor or other system can send as
+/// `Authorization: Bearer mcp_pat_...` to act for the merchant, limited to
+/// its scopes. Needs a step-up.
+pub async fn create_access_token(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(req): Json<CreateAccessTokenRequest>,
+) -> Result<Json<CreateAccessTokenResponse>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let name = req.name.trim();
+    if name.is_empty() || name.chars().count() > MAX_TOKEN_NAME_LEN {
+        return Err(AppError::Validation(format!("name must be between 1 and {} characters", MAX_TOKEN_NAME_LEN)));
+    }
+    let mut scopes = req.scopes;
+    scopes.sort();
+    scopes.dedup();
+    if scopes.is_empty() {
+        return Err(AppError::Validation("At least one scope is required".to_string()));
+    }
+    if let Some(scope) = scopes.iter().find(|s| !TOKEN_SCOPES.contains(&s.as_str())) {
+        return Err(AppError::Validation(format!("Unknown scope: {}", scope)));
+    }
+    if req.expires_in_days == Some(0) {
+        return Err(AppError::Validation("expires_in_days must be at least 1".to_string()));
+    }
+
+    if AccessTokenRepo::count_active(&state.db, user_id).await? >= MAX_ACTIVE_TOKENS {
+        return Err(AppError::Validation(format!(
+            "At most {} tokens can be active at once; revoke one first",
+            MAX_ACTIVE_TOKENS
+        )));
+    }
+
+    let details = json!({ "name": name, "scopes": scopes });
+    SecurityService::require_step_up(&state.db, &claims, "access_token.created", details).await?;
+
+    let token = generate_access_token();
+    let token_prefix: String = token.chars().take(12).collect();
+    let expires_at = req.expires_in_days.map(|days| chrono::Utc::now() + chrono::Duration::days(days as i64));
+
+    let details = AccessTokenRepo::create(
+        &state.db,
+        user_id,
+        name,
+        &hash_api_key(&token),
+        &token_prefix,
+        &scopes,
+        expires_at,
+    )
+    .await?;
+
+    Ok(Json(CreateAccessTokenResponse {
+        token,
+        details: details.into(),
+    }))
+}
+
+/// Stops a token working immediately.
+pub async fn revoke_access_token(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(token_id): Path<String>,
+) -> Result<Json<AccessTokenResponse>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let token_id = Uuid::parse_str(&token_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    AccessTokenRepo::revoke(&state.db, token_id, user_id)
+        .await?
+        .map(|token| Json(token.into()))
+        .ok_or_else(|| AppError::NotFound("Token not found or already revoked".to_string()))
+}
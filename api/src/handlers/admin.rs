@@ -0,0 +1,770 @@
+use axum::{
+    extract::{Path, Query, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::repos::sessions::SessionSearch;
+use crate::db::repos::{
+    ImageIdRepo, ScheduledJobRepo, ScoringPolicyRepo, SessionRepo, TillRepo, TransactionTypeRepo,
+};
+use crate::error::AppError;
+use crate::handlers::proofs::SessionEventResponse;
+use crate::handlers::AppState;
+use crate::middleware::admin::AdminAuth;
+use crate::models::{ProofStatus, SessionStage};
+use crate::services::api_keys::ApiKeyService;
+use crate::services::notification::{LenderNotification, NotificationService};
+use crate::services::proof::ProofService;
+use crate::utils::normalize_verification_code;
+use sqlx::Row;
+
+#[derive(Deserialize)]
+pub struct CreateLenderRequest {
+    pub name: String,
+    /// Where key notices and usage reports are emailed
+    pub contact_email: Option<String>,
+    #[serde(default)]
+    pub notify_on_verification: bool,
+}
+
+#[derive(Serialize)]
+pub struct LenderResponse {
+    pub id: String,
+    pub name: String,
+    pub contact_email: Option<String>,
+    pub notify_on_verification: bool,
+}
+
+#[derive(Deserialize)]
+pub struct MonthlyReportRequest {
+    /// Month to report on as YYYY-MM; defaults to the previous month
+    pub month: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct MonthlyReportResponse {
+    pub month: String,
+    pub reports_sent: usize,
+    /// Reports not sent because SMTP isn't configured
+    pub reports_skipped: usize,
+}
+
+#[derive(Serialize)]
+pub struct DivergenceEntry {
+    pub session_id: String,
+    pub native_output: serde_json::Value,
+    pub proven_output: serde_json::Value,
+    pub detected_at: String,
+}
+
+#[derive(Serialize)]
+pub struct ConsistencyReportResponse {
+    /// Completed proofs cross-checked against the native core in the last 24 hours
+    pub checked_last_24h: i64,
+    pub diverged_last_24h: i64,
+    /// Most recent divergences, newest first
+    pub recent_divergences: Vec<DivergenceEntry>,
+}
+
+#[derive(Deserialize)]
+pub struct CreateScoringPolicyRequest {
+    pub name: String,
+    pub currency: String,
+    /// Lender the policy was agreed with; omit for a market-wide policy
+    pub lender_id: Option<String>,
+    /// Monthly volume, in major units, at which the Low, Medium, High and
+    /// VeryHigh bands start
+    pub volume_thresholds: [u64; 4],
+    /// Cap each day's volume at this many times the median day's; omit to
+    /// score raw volume
+    pub outlier_cap: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct ScoringPolicyResponse {
+    pub id: String,
+    pub name: String,
+    pub currency: String,
+    pub lender_id: Option<String>,
+    pub volume_thresholds: Vec<i64>,
+    pub outlier_cap: Option<i32>,
+    /// What proofs scored under this policy commit in their journal
+    pub policy_hash: String,
+    pub created_at: String,
+}
+
+impl From<crate::models::ScoringPolicy> for ScoringPolicyResponse {
+    fn from(policy: crate::models::ScoringPolicy) -> Self {
+        Self {
+            id: policy.id.to_string(),
+            name: policy.name,
+            currency: policy.currency,
+            lender_id: policy.lender_id.map(|id| id.to_string()),
+            volume_thresholds: policy.volume_thresholds,
+            outlier_cap: policy.outlier_cap,
+            policy_hash: policy.policy_hash,
+            created_at: policy.created_at.to_rfc3339(),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct RegisterImageRequest {
+    /// Hex image ID, as printed by the worker at startup
+    pub image_id: String,
+    pub guest_version: String,
+    /// RFC 3339; defaults to now
+    pub valid_from: Option<String>,
+    /// RFC 3339; set to retire an image
+    pub valid_to: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct AllowedImageResponse {
+    pub image_id: String,
+    pub guest_version: String,
+    pub valid_from: String,
+    pub valid_to: Option<String>,
+    /// Whether this build proves with the image
+    pub current: bool,
+}
+
+impl From<crate::db::repos::images::AllowedImage> for AllowedImageResponse {
+    fn from(image: crate::db::repos::images::AllowedImage) -> Self {
+        Self {
+            current: image.image_id == ProofService::current_image_id(),
+            image_id: image.image_id,
+            guest_version: image.guest_version,
+            valid_from: image.valid_from.to_rfc3339(),
+            valid_to: image.valid_to.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ScheduledJobResponse {
+    pub name: String,
+    pub schedule: String,
+    /// The scheduled time the last run was for
+    pub last_scheduled_for: String,
+    pub last_started_at: String,
+    pub last_finished_at: Option<String>,
+    /// "running", "succeeded" or "failed"
+    pub last_status: String,
+    /// The job's summary, or its error
+    pub last_output: Option<String>,
+    pub last_duration_ms: Option<i64>,
+    pub last_worker_id: String,
+    pub consecutive_failures: i32,
+}
+
+impl From<crate::db::repos::scheduled_jobs::ScheduledJobRun> for ScheduledJobResponse {
+    fn from(run: crate::db::repos::scheduled_jobs::ScheduledJobRun) -> Self {
+        Self {
+            name: run.name,
+            schedule: run.schedule,
+            last_scheduled_for: run.last_scheduled_for.to_rfc3339(),
+            last_started_at: run.last_started_at.to_rfc3339(),
+            last_finished_at: run.last_finished_at.map(|t| t.to_rfc3339()),
+            last_status: run.last_status,
+            last_output: run.last_output,
+            last_duration_ms: run.last_duration_ms,
+            last_worker_id: run.last_worker_id,
+            consecutive_failures: run.consecutive_failures,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct TransactionTypeRequest {
+    /// Start of the raw statement description, e.g. "Merchant Payment"
+    pub pattern: String,
+    /// A `TransactionKind` name, e.g. "Payment"
+    pub kind: String,
+}
+
+#[derive(Serialize)]
+pub struct TransactionTypeResponse {
+    pub pattern: String,
+    pub kind: String,
+    pub updated_at: String,
+    /// Stored rows whose kind changed as a result of this request
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reclassified: Option<u64>,
+}
+
+impl From<crate::models::TransactionTypeMapping> for TransactionTypeResponse {
+    fn from(mapping: crate::models::TransactionTypeMapping) -> Self {
+        Self {
+            pattern: mapping.pattern,
+            kind: mapping.kind,
+            updated_at: mapping.updated_at.to_rfc3339(),
+            reclassified: None,
+        }
+    }
+}
+
+/// Lower-cases and collapses whitespace the way `normalize_transaction_type`
+/// does in the database, so patterns match what they look like.
+fn normalize_pattern(pattern: &str) -> Result<String, AppError> {
+    let pattern = pattern.split_whitespace().collect::<Vec<_>>().join(" ").to_lowercase();
+    if pattern.is_empty() || pattern.len() > 100 {
+        return Err(AppError::Validation("Invalid pattern".to_string()));
+    }
+    Ok(pattern)
+}
+
+#[derive(Serialize)]
+pub struct ContestedTillResponse {
+    pub till_number: String,
+    /// Oldest first
+    pub registrations: Vec<TillRegistration>,
+}
+
+#[derive(Serialize)]
+pub struct TillRegistration {
+    pub till_id: String,
+    pub user_id: String,
+    pub is_verified: bool,
+    pub created_at: String,
+    /// The registration that took verification from this one, if any
+    pub superseded_by: Option<String>,
+    pub superseded_at: Option<String>,
+    /// When this account asked to verify it while another held it; it waits
+    /// for `POST /api/admin/tills/:till_id/award`
+    pub ownership_review_requested_at: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct IssueApiKeyRequest {
+    #[serde(default)]
+    pub sandbox: bool,
+}
+
+#[derive(Serialize)]
+pub struct IssueApiKeyResponse {
+    pub key_id: String,
+    /// Shown once; only an Argon2id hash is stored
+    pub api_key: String,
+    pub key_prefix: String,
+    pub sandbox: bool,
+}
+
+pub async fn create_lender(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Json(req): Json<CreateLenderRequest>,
+) -> Result<Json<LenderResponse>, AppError> {
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > 255 {
+        return Err(AppError::Validation("Invalid lender name".to_string()));
+    }
+
+    let contact_email = req.contact_email.as_deref().map(str::trim);
+    if contact_email.is_some_and(|e| e.len() > 255 || e.parse::<lettre::message::Mailbox>().is_err()) {
+        return Err(AppError::Validation("Invalid contact email".to_string()));
+    }
+
+    let id: Uuid = sqlx::query_scalar(
+        "INSERT INTO lenders (name, contact_email, notify_on_verification) VALUES ($1, $2, $3) RETURNING id",
+    )
+    .bind(name)
+    .bind(contact_email)
+    .bind(req.notify_on_verification)
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok(Json(LenderResponse {
+        id: id.to_string(),
+        name: name.to_string(),
+        contact_email: contact_email.map(str::to_string),
+        notify_on_verification: req.notify_on_verification,
+    }))
+}
+
+pub async fn issue_lender_key(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(lender_id): Path<String>,
+    Json(req): Json<IssueApiKeyRequest>,
+) -> Result<Json<IssueApiKeyResponse>, AppError> {
+    let lender_id = Uuid::parse_str(&lender_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM lenders WHERE id = $1)")
+        .bind(lender_id)
+        .fetch_one(&state.db)
+        .await?;
+    if !exists {
+        return Err(AppError::NotFound("Lender not found".to_string()));
+    }
+
+    let mut conn = state.db.acquire().await?;
+    let issued = ApiKeyService::issue(&mut conn, lender_id, req.sandbox, None).await?;
+    let key_prefix = issued.key.key_prefix;
+
+    let notification = LenderNotification::ApiKeyIssued {
+        key_prefix: &key_prefix,
+        sandbox: req.sandbox,
+    };
+    if let Err(e) = NotificationService::notify_lender(&state.db, &state.config, lender_id, notification).await {
+        tracing::error!("Failed to email key notice to lender {}: {}", lender_id, e);
+    }
+
+    Ok(Json(IssueApiKeyResponse {
+        key_id: issued.key.id.to_string(),
+        api_key: issued.api_key,
+        key_prefix,
+        sandbox: req.sandbox,
+    }))
+}
+
+/// Emails each lender its verification usage for a month. Meant to be
+/// triggered by a scheduler at the start of each month.
+pub async fn send_monthly_reports(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Json(req): Json<MonthlyReportRequest>,
+) -> Result<Json<MonthlyReportResponse>, AppError> {
+    use chrono::Datelike;
+
+    let month_start = match req.month.as_deref() {
+        Some(month) => chrono::NaiveDate::parse_from_str(&format!("{}-01", month), "%Y-%m-%d")
+            .map_err(|_| AppError::Validation(format!("Invalid month: {}", month)))?,
+        None => {
+            let this_month = chrono::Utc::now().date_naive().with_day(1).unwrap();
+            this_month.checked_sub_months(chrono::Months::new(1)).unwrap()
+        }
+    };
+
+    let (reports_sent, reports_skipped) =
+        NotificationService::send_monthly_usage_reports(&state.db, &state.config, month_start).await?;
+
+    Ok(Json(MonthlyReportResponse {
+        month: month_start.format("%Y-%m").to_string(),
+        reports_sent,
+        reports_skipped,
+    }))
+}
+
+/// Host/guest consistency: how many recent proofs matched a native run of
+/// the scoring core, and the details of any that didn't.
+pub async fn consistency_report(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> Result<Json<ConsistencyReportResponse>, AppError> {
+    let row = sqlx::query(
+        r#"
+        SELECT COUNT(*) FILTER (WHERE native_consistent IS NOT NULL),
+               COUNT(*) FILTER (WHERE native_consistent = false)
+        FROM proof_sessions
+        WHERE proving_finished_at > NOW() - INTERVAL '24 hours'
+        "#,
+    )
+    .fetch_one(state.read_db())
+    .await?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT session_id, native_output, proven_output, created_at
+        FROM proof_divergences
+        ORDER BY created_at DESC
+        LIMIT 20
+        "#,
+    )
+    .fetch_all(state.read_db())
+    .await?;
+
+    let recent_divergences = rows
+        .into_iter()
+        .map(|row| {
+            Ok(DivergenceEntry {
+                session_id: row.try_get::<Uuid, _>(0)?.to_string(),
+                native_output: row.try_get(1)?,
+                proven_output: row.try_get(2)?,
+                detected_at: row.try_get::<chrono::DateTime<chrono::Utc>, _>(3)?.to_rfc3339(),
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+    Ok(Json(ConsistencyReportResponse {
+        checked_last_24h: row.try_get(0)?,
+        diverged_last_24h: row.try_get(1)?,
+        recent_divergences,
+    }))
+}
+
+/// Adds a scoring policy. Policies can't be edited; publish a new one to
+/// change thresholds so existing proofs keep pointing at what they committed.
+pub async fn create_scoring_policy(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Json(req): Json<CreateScoringPolicyRequest>,
+) -> Result<Json<ScoringPolicyResponse>, AppError> {
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > 255 {
+        return Err(AppError::Validation("Invalid policy name".to_string()));
+    }
+
+    let thresholds = req.volume_thresholds;
+    if thresholds[0] == 0 || thresholds.windows(2).any(|w| w[0] >= w[1]) || thresholds[3] > i64::MAX as u64 {
+        return Err(AppError::Validation(
+            "volume_thresholds must be four positive, strictly increasing amounts".to_string(),
+        ));
+    }
+
+    if req.outlier_cap.is_some_and(|cap| !(2..=1000).contains(&cap)) {
+        return Err(AppError::Validation("outlier_cap must be between 2 and 1000".to_string()));
+    }
+
+    let currency = crate::handlers::data::supported_currency(&state.db, &req.currency).await?;
+
+    let lender_id = req
+        .lender_id
+        .as_deref()
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    if let Some(lender_id) = lender_id {
+        let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM lenders WHERE id = $1)")
+            .bind(lender_id)
+            .fetch_one(&state.db)
+            .await?;
+        if !exists {
+            return Err(AppError::NotFound("Lender not found".to_string()));
+        }
+    }
+
+    let policy_hash = hex::encode(
+        proof_core::ScoringPolicy {
+            volume_thresholds: thresholds,
+            outlier_cap: req.outlier_cap,
+        }
+        .hash(&currency.code),
+    );
+    let volume_thresholds: Vec<i64> = thresholds.iter().map(|&t| t as i64).collect();
+    let policy = ScoringPolicyRepo::create(
+        &state.db,
+        name,
+        &currency.code,
+        lender_id,
+        &volume_thresholds,
+        req.outlier_cap.map(|cap| cap as i32),
+        &policy_hash,
+    )
+    .await?;
+
+    Ok(Json(policy.into()))
+}
+
+pub async fn list_scoring_policies(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> Result<Json<Vec<ScoringPolicyResponse>>, AppError> {
+    let policies = ScoringPolicyRepo::list(state.read_db()).await?;
+    Ok(Json(policies.into_iter().map(Into::into).collect()))
+}
+
+/// The guest images receipts may be verified against.
+pub async fn list_image_ids(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> Result<Json<Vec<AllowedImageResponse>>, AppError> {
+    let images = ImageIdRepo::list(state.read_db()).await?;
+    Ok(Json(images.into_iter().map(Into::into).collect()))
+}
+
+/// The last run of each job the workers schedule. Jobs that have never run
+/// aren't listed.
+pub async fn list_scheduled_jobs(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> Result<Json<Vec<ScheduledJobResponse>>, AppError> {
+    let runs = ScheduledJobRepo::list(state.read_db()).await?;
+    Ok(Json(runs.into_iter().map(Into::into).collect()))
+}
+
+/// Adds an image to the registry, or changes its validity window, e.g. to
+/// retire a guest with a known bug.
+pub async fn register_image_id(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Json(req): Json<RegisterImageRequest>,
+) -> Result<Json<AllowedImageResponse>, AppError> {
+    let image_id = req.image_id.trim().to_ascii_lowercase();
+    if image_id.len() != 64 || !image_id.chars().all(|c| c.is_ascii_hexdigit()) {
+        return Err(AppError::Validation("image_id must be 64 hex characters".to_string()));
+    }
+    let guest_version = req.guest_version.trim();
+    if guest_version.is_empty() || guest_version.len() > 64 {
+        return Err(AppError::Validation("Invalid guest_version".to_string()));
+    }
+
+    let parse = |value: &str| {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&chrono::Utc))
+            .map_err(|_| AppError::Validation(format!("Invalid timestamp: {}", value)))
+    };
+    let valid_from = req.valid_from.as_deref().map(parse).transpose()?.unwrap_or_else(chrono::Utc::now);
+    let valid_to = req.valid_to.as_deref().map(parse).transpose()?;
+    if valid_to.is_some_and(|to| to <= valid_from) {
+        return Err(AppError::Validation("valid_to must be after valid_from".to_string()));
+    }
+
+    let image = ImageIdRepo::upsert(&state.db, &image_id, guest_version, valid_from, valid_to).await?;
+    Ok(Json(image.into()))
+}
+
+pub async fn list_transaction_types(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> Result<Json<Vec<TransactionTypeResponse>>, AppError> {
+    let mappings = TransactionTypeRepo::list(state.read_db()).await?;
+    Ok(Json(mappings.into_iter().map(Into::into).collect()))
+}
+
+/// Maps statement descriptions starting with a pattern to a kind, then
+/// reclassifies the stored rows it covers. Proofs already generated keep
+/// the kinds they were scored with.
+pub async fn put_transaction_type(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Json(req): Json<TransactionTypeRequest>,
+) -> Result<Json<TransactionTypeResponse>, AppError> {
+    let pattern = normalize_pattern(&req.pattern)?;
+    let kind = proof_core::TransactionKind::from_name(req.kind.trim())
+        .ok_or_else(|| AppError::Validation(format!("Unknown transaction kind: {}", req.kind)))?;
+
+    let mut tx = state.db.begin().await?;
+    let mapping = TransactionTypeRepo::upsert(&mut *tx, &pattern, kind.as_str()).await?;
+    let reclassified = TransactionTypeRepo::reclassify(&mut *tx, &pattern).await?;
+    tx.commit().await?;
+    tracing::info!("Transaction type {:?} mapped to {}; {} rows reclassified", pattern, kind.as_str(), reclassified);
+
+    Ok(Json(TransactionTypeResponse {
+        reclassified: Some(reclassified),
+        ..mapping.into()
+    }))
+}
+
+/// Removes a mapping; the rows it covered fall back to the next-longest
+/// pattern, or to Other.
+pub async fn delete_transaction_type(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(pattern): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let pattern = normalize_pattern(&pattern)?;
+    let mut tx = state.db.begin().await?;
+    if !TransactionTypeRepo::delete(&mut *tx, &pattern).await? {
+        return Err(AppError::NotFound("Transaction type mapping not found".to_string()));
+    }
+    let reclassified = TransactionTypeRepo::reclassify(&mut *tx, &pattern).await?;
+    tx.commit().await?;
+    tracing::info!("Transaction type {:?} unmapped; {} rows reclassified", pattern, reclassified);
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Till numbers registered by more than one account, and which registration
+/// holds each verified.
+pub async fn list_contested_tills(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> Result<Json<Vec<ContestedTillResponse>>, AppError> {
+    let registrations = TillRepo::contested(state.read_db()).await?;
+
+    let mut tills: Vec<ContestedTillResponse> = Vec::new();
+    for registration in registrations {
+        let entry = TillRegistration {
+            till_id: registration.till_id.to_string(),
+            user_id: registration.user_id.to_string(),
+            is_verified: registration.is_verified,
+            created_at: registration.created_at.to_rfc3339(),
+            superseded_by: registration.superseded_by.map(|id| id.to_string()),
+            superseded_at: registration.superseded_at.map(|at| at.to_rfc3339()),
+            ownership_review_requested_at: registration.ownership_review_requested_at.map(|at| at.to_rfc3339()),
+        };
+        // Rows arrive grouped by number
+        match tills.last_mut() {
+            Some(till) if till.till_number == registration.till_number => till.registrations.push(entry),
+            _ => tills.push(ContestedTillResponse {
+                till_number: registration.till_number,
+                registrations: vec![entry],
+            }),
+        }
+    }
+
+    Ok(Json(tills))
+}
+
+/// Settles a contested till in favour of one registration, once support has
+/// confirmed out of band that its account owns the till. Whichever
+/// registration held it verified loses it.
+pub async fn award_till(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(till_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let till_id = Uuid::parse_str(&till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    TillRepo::owner(&state.db, till_id).await?.ok_or(AppError::TillNotFound)?;
+    if let Some(previous) = TillRepo::award(&state.db, till_id).await? {
+        tracing::warn!("Admin awarded till {}, taking verification from till {}", till_id, previous);
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Any session's timeline, for support looking into a stuck proof.
+pub async fn get_proof_events(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(session_id): Path<String>,
+) -> Result<Json<Vec<SessionEventResponse>>, AppError> {
+    let session_id = Uuid::parse_str(&session_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let events = SessionRepo::events(state.read_db(), session_id, None)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
+
+    Ok(Json(events.into_iter().map(Into::into).collect()))
+}
+
+/// Search results returned at once unless `limit` says otherwise.
+const DEFAULT_SEARCH_LIMIT: i64 = 50;
+const MAX_SEARCH_LIMIT: i64 = 200;
+
+#[derive(Deserialize)]
+pub struct SearchProofsQuery {
+    /// Words to find in error messages, as a web search query
+    pub q: Option<String>,
+    pub verification_code: Option<String>,
+    /// The account owner's phone, or one linked to the account
+    pub phone_number: Option<String>,
+    pub till_number: Option<String>,
+    /// "pending", "processing", "completed" or "failed"
+    pub status: Option<String>,
+    pub min_score: Option<i32>,
+    pub max_score: Option<i32>,
+    /// First day to include, YYYY-MM-DD
+    pub from: Option<String>,
+    /// Last day to include, YYYY-MM-DD
+    pub to: Option<String>,
+    /// `next_after` from the previous page
+    pub after: Option<String>,
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ProofSearchResult {
+    pub session_id: String,
+    pub user_id: String,
+    pub till_id: String,
+    pub till_number: String,
+    pub status: ProofStatus,
+    /// The last step it reached
+    pub stage: Option<SessionStage>,
+    pub progress: Option<i32>,
+    pub credit_score: Option<i32>,
+    pub verification_code: String,
+    pub error_message: Option<String>,
+    pub attempts: i32,
+    /// The worker holding it, while one is
+    pub lease_owner: Option<String>,
+    pub lease_expires_at: Option<String>,
+    pub created_at: String,
+    pub updated_at: String,
+}
+
+#[derive(Serialize)]
+pub struct SearchProofsResponse {
+    pub results: Vec<ProofSearchResult>,
+    /// Pass as `after` for the next page; None on the last one
+    pub next_after: Option<String>,
+}
+
+/// Finds proof sessions by any mix of filters, newest first, so support can
+/// find a user's stuck session.
+pub async fn search_proofs(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Query(query): Query<SearchProofsQuery>,
+) -> Result<Json<SearchProofsResponse>, AppError> {
+    let non_empty = |value: &Option<String>| value.as_deref().map(str::trim).filter(|v| !v.is_empty());
+
+    let status = non_empty(&query.status)
+        .map(|status| match status {
+            "pending" => Ok(ProofStatus::Pending),
+            "processing" => Ok(ProofStatus::Processing),
+            "completed" => Ok(ProofStatus::Completed),
+            "failed" => Ok(ProofStatus::Failed),
+            other => Err(AppError::Validation(format!("Unknown status: {}", other))),
+        })
+        .transpose()?;
+    if let (Some(min), Some(max)) = (query.min_score, query.max_score) {
+        if min > max {
+            return Err(AppError::Validation("min_score must not exceed max_score".to_string()));
+        }
+    }
+    let parse = |value: &str| {
+        chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc())
+            .map_err(|_| AppError::Validation(format!("Invalid date: {}", value)))
+    };
+    let created_from = non_empty(&query.from).map(parse).transpose()?;
+    let created_to = non_empty(&query.to)
+        .map(parse)
+        .transpose()?
+        .map(|d| d + chrono::Duration::days(1));
+    let after = non_empty(&query.after)
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let limit = query.limit.unwrap_or(DEFAULT_SEARCH_LIMIT);
+    if !(1..=MAX_SEARCH_LIMIT).contains(&limit) {
+        return Err(AppError::Validation(format!("limit must be between 1 and {}", MAX_SEARCH_LIMIT)));
+    }
+    let verification_code = non_empty(&query.verification_code).map(normalize_verification_code);
+
+    let search = SessionSearch {
+        error_text: non_empty(&query.q),
+        verification_code: verification_code.as_deref(),
+        phone_number: non_empty(&query.phone_number),
+        till_number: non_empty(&query.till_number),
+        status,
+        min_score: query.min_score,
+        max_score: query.max_score,
+        created_from,
+        created_to,
+        after,
+        limit,
+    };
+    let sessions = SessionRepo::search(state.read_db(), &search).await?;
+
+    let next_after = (sessions.len() as i64 == limit)
+        .then(|| sessions.last().map(|s| s.id.to_string()))
+        .flatten();
+    let results = sessions
+        .into_iter()
+        .map(|session| ProofSearchResult {
+            session_id: session.id.to_string(),
+            user_id: session.user_id.to_string(),
+            till_id: session.till_id.to_string(),
+            till_number: session.till_number,
+            status: session.status,
+            stage: session.stage,
+            progress: session.progress,
+            credit_score: session.credit_score,
+            verification_code: session.verification_code,
+            error_message: session.error_message,
+            attempts: session.attempts,
+            lease_owner: session.lease_owner,
+            lease_expires_at: session.lease_expires_at.map(|t| t.to_rfc3339()),
+            created_at: session.created_at.to_rfc3339(),
+            updated_at: session.updated_at.to_rfc3339(),
+        })
+        .collect();
+
+    Ok(Json(SearchProofsResponse { results, next_after }))
+}
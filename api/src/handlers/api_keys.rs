@@ -0,0 +1,188 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::repos::api_keys::LenderApiKey;
+use crate::db::repos::ApiKeyRepo;
+use crate::error::AppError;
+use crate::handlers::admin::IssueApiKeyResponse;
+use crate::handlers::AppState;
+use crate::middleware::admin::AdminAuth;
+use crate::middleware::lender::LenderAuth;
+use crate::services::api_keys::ApiKeyService;
+use crate::services::notification::{LenderNotification, NotificationService};
+
+/// How long a rotated key keeps working unless the request says otherwise.
+const DEFAULT_GRACE_HOURS: u32 = 24;
+const MAX_GRACE_HOURS: u32 = 7 * 24;
+
+#[derive(Serialize)]
+pub struct ApiKeyResponse {
+    pub key_id: String,
+    pub key_prefix: String,
+    pub sandbox: bool,
+    pub created_at: String,
+    pub last_used_at: Option<String>,
+    /// When a rotated key stops working
+    pub expires_at: Option<String>,
+    pub revoked_at: Option<String>,
+    /// The key this one replaced
+    pub rotated_from: Option<String>,
+}
+
+impl From<LenderApiKey> for ApiKeyResponse {
+    fn from(key: LenderApiKey) -> Self {
+        Self {
+            key_id: key.id.to_string(),
+            key_prefix: key.key_prefix,
+            sandbox: key.sandbox,
+            created_at: key.created_at.to_rfc3339(),
+            last_used_at: key.last_used_at.map(|t| t.to_rfc3339()),
+            expires_at: key.expires_at.map(|t| t.to_rfc3339()),
+            revoked_at: key.revoked_at.map(|t| t.to_rfc3339()),
+            rotated_from: key.rotated_from.map(|id| id.to_string()),
+        }
+    }
+}
+
+#[derive(Deserialize, Default)]
+pub struct RotateApiKeyRequest {
+    /// Hours the old key keeps working, up to a week; 0 stops it at once.
+    /// Defaults to 24.
+    pub grace_period_hours: Option<u32>,
+}
+
+#[derive(Serialize)]
+pub struct RotateApiKeyResponse {
+    #[serde(flatten)]
+    pub new_key: IssueApiKeyResponse,
+    pub previous_key: ApiKeyResponse,
+}
+
+/// A lender's keys, revoked and expired ones included.
+pub async fn list_lender_keys(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(lender_id): Path<String>,
+) -> Result<Json<Vec<ApiKeyResponse>>, AppError> {
+    let lender_id = Uuid::parse_str(&lender_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let keys = ApiKeyRepo::list_for_lender(state.read_db(), lender_id).await?;
+    Ok(Json(keys.into_iter().map(Into::into).collect()))
+}
+
+/// Issues a replacement for one of a lender's keys.
+pub async fn rotate_lender_key(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path((lender_id, key_id)): Path<(String, String)>,
+    req: Option<Json<RotateApiKeyRequest>>,
+) -> Result<Json<RotateApiKeyResponse>, AppError> {
+    let lender_id = Uuid::parse_str(&lender_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let key_id = Uuid::parse_str(&key_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    rotate(&state, lender_id, key_id, req.map(|Json(req)| req).unwrap_or_default()).await
+}
+
+/// Revokes one of a lender's keys at once.
+pub async fn revoke_lender_key(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path((lender_id, key_id)): Path<(String, String)>,
+) -> Result<Json<ApiKeyResponse>, AppError> {
+    let lender_id = Uuid::parse_str(&lender_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let key_id = Uuid::parse_str(&key_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    revoke(&state, lender_id, key_id, None).await
+}
+
+/// The calling lender's keys.
+pub async fn list_own_keys(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+) -> Result<Json<Vec<ApiKeyResponse>>, AppError> {
+    let keys = ApiKeyRepo::list_for_lender(state.read_db(), lender.lender_id).await?;
+    Ok(Json(keys.into_iter().map(Into::into).collect()))
+}
+
+/// Replaces the key making the request.
+pub async fn rotate_own_key(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    req: Option<Json<RotateApiKeyRequest>>,
+) -> Result<Json<RotateApiKeyResponse>, AppError> {
+    rotate(&state, lender.lender_id, lender.key_id, req.map(|Json(req)| req).unwrap_or_default()).await
+}
+
+/// Revokes one of the calling lender's keys, the one making the request
+/// included, at once. A sandbox key can only revoke sandbox keys and a live
+/// key only live ones.
+pub async fn revoke_own_key(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    Path(key_id): Path<String>,
+) -> Result<Json<ApiKeyResponse>, AppError> {
+    let key_id = Uuid::parse_str(&key_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    revoke(&state, lender.lender_id, key_id, Some(lender.sandbox)).await
+}
+
+async fn rotate(
+    state: &AppState,
+    lender_id: Uuid,
+    key_id: Uuid,
+    req: RotateApiKeyRequest,
+) -> Result<Json<RotateApiKeyResponse>, AppError> {
+    let grace_hours = req.grace_period_hours.unwrap_or(DEFAULT_GRACE_HOURS);
+    if grace_hours > MAX_GRACE_HOURS {
+        return Err(AppError::Validation(format!(
+            "grace_period_hours can be at most {}",
+            MAX_GRACE_HOURS
+        )));
+    }
+    let old_key_expires_at = chrono::Utc::now() + chrono::Duration::hours(grace_hours as i64);
+
+    let (issued, previous_key) = ApiKeyService::rotate(&state.db, lender_id, key_id, old_key_expires_at)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Active API key not found".to_string()))?;
+
+    let notification = LenderNotification::ApiKeyIssued {
+        key_prefix: &issued.key.key_prefix,
+        sandbox: issued.key.sandbox,
+    };
+    if let Err(e) = NotificationService::notify_lender(&state.db, &state.config, lender_id, notification).await {
+        tracing::error!("Failed to email key notice to lender {}: {}", lender_id, e);
+    }
+
+    Ok(Json(RotateApiKeyResponse {
+        new_key: IssueApiKeyResponse {
+            key_id: issued.key.id.to_string(),
+            api_key: issued.api_key,
+            key_prefix: issued.key.key_prefix,
+            sandbox: issued.key.sandbox,
+        },
+        previous_key: previous_key.into(),
+    }))
+}
+
+async fn revoke(
+    state: &AppState,
+    lender_id: Uuid,
+    key_id: Uuid,
+    sandbox: Option<bool>,
+) -> Result<Json<ApiKeyResponse>, AppError> {
+    let revoked = ApiKeyRepo::revoke(&state.db, key_id, lender_id, sandbox).await?;
+    for key in &revoked {
+        let notification = LenderNotification::ApiKeyRevoked {
+            key_prefix: &key.key_prefix,
+        };
+        if let Err(e) = NotificationService::notify_lender(&state.db, &state.config, lender_id, notification).await {
+            tracing::error!("Failed to email key notice to lender {}: {}", lender_id, e);
+        }
+    }
+
+    let key = revoked
+        .into_iter()
+        .next()
+        .ok_or_else(|| AppError::NotFound("Active API key not found".to_string()))?;
+    Ok(Json(key.into()))
+}
@@ -1,13 +1,31 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::State,
+    http::{header, HeaderMap},
+    Json,
+};
 use redis::AsyncCommands;
 use serde::{Deserialize, Serialize};
+use serde_json::json;
 use uuid::Uuid;
 
+use crate::db::repos::user_sessions::NewUserSession;
+use crate::db::repos::{AuditRepo, UserSessionRepo};
 use crate::error::AppError;
-use crate::handlers::AppState;
+use crate::handlers::{AppState, Claims};
+use crate::i18n::{Locale, Message};
+use crate::middleware::client_ip::ClientIp;
+use crate::middleware::locale::requested_locale;
+use crate::models::PhoneRole;
 use crate::services::auth::AuthService;
+use crate::services::security::{
+    self, SecurityService, STEP_UP_LOCKOUT_MINS, STEP_UP_MAX_FAILURES, STEP_UP_WINDOW_MINS,
+};
 use crate::utils::{generate_jwt, hash_phone_number};
 
+/// How long a sign-in lasts.
+const SESSION_TTL_DAYS: i64 = 7;
+const MAX_DEVICE_HINT_LEN: usize = 200;
+
 #[derive(Deserialize)]
 pub struct RequestOtpRequest {
     pub phone_number: String,
@@ -28,6 +46,9 @@ pub struct VerifyOtpRequest {
 #[derive(Serialize)]
 pub struct VerifyOtpResponse {
     pub token: String,
+    /// Signed in from a device and network this phone hadn't used; the
+    /// sign-in is recorded in the audit log
+    pub unfamiliar_login: bool,
     pub user: UserResponse,
 }
 
@@ -35,10 +56,12 @@ pub struct VerifyOtpResponse {
 pub struct UserResponse {
     pub id: String,
     pub phone_number: String,
+    pub role: PhoneRole,
 }
 
 pub async fn request_otp(
     State(state): State<AppState>,
+    headers: HeaderMap,
     Json(req): Json<RequestOtpRequest>,
 ) -> Result<Json<RequestOtpResponse>, AppError> {
     // Rate limiting: 3 requests per hour
@@ -47,7 +70,8 @@ pub async fn request_otp(
 
     let attempts: i32 = redis_conn.get(&redis_key).await.unwrap_or(0);
     if attempts >= 3 {
-        return Err(AppError::RateLimit);
+        let ttl: i64 = redis_conn.ttl(&redis_key).await.unwrap_or(3600);
+        return Err(AppError::RateLimit(ttl.max(1) as u64));
     }
 
     // Generate 6-digit OTP
@@ -61,12 +85,23 @@ pub async fn request_otp(
     redis_conn.incr(&redis_key, 1).await?;
     redis_conn.expire(&redis_key, 3600).await?; // 1 hour
 
+    // Prefer the client's language, then the stored preference of a returning user
+    let locale = match requested_locale(&headers) {
+        Some(locale) => locale,
+        None => sqlx::query_scalar::<_, String>("SELECT preferred_language FROM users WHERE phone_number = $1")
+            .bind(&req.phone_number)
+            .fetch_optional(&state.db)
+            .await?
+            .and_then(|code| Locale::from_code(&code))
+            .unwrap_or_default(),
+    };
+
     // Send SMS via Africa's Talking
     AuthService::send_sms(
         &state.config.africa_talking_api_key,
         &state.config.africa_talking_username,
         &req.phone_number,
-        &format!("Your verification code is: {}", otp),
+        &Message::OtpSms { otp: &otp }.render(locale),
     )
     .await?;
 
@@ -78,42 +113,155 @@ pub async fn request_otp(
 
 pub async fn verify_otp(
     State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    headers: HeaderMap,
     Json(req): Json<VerifyOtpRequest>,
 ) -> Result<Json<VerifyOtpResponse>, AppError> {
     let mut redis_conn = state.redis.get_async_connection().await?;
-    let otp_key = format!("otp:{}", hash_phone_number(&req.phone_number));
 
-    let stored_otp: Option<String> = redis_conn.get(&otp_key).await?;
-
-    if stored_otp.as_deref() != Some(&req.otp) {
+    if !AuthService::check_otp(&mut redis_conn, &req.phone_number, &req.otp).await? {
         return Err(AppError::InvalidOtp);
     }
 
     // Delete OTP after successful verification
-    redis_conn.del(&otp_key).await?;
-
-    // Get or create user
-    let user = sqlx::query_as::<_, crate::models::User>(
-        r#"
-        INSERT INTO users (phone_number)
-        VALUES ($1)
-        ON CONFLICT (phone_number) DO UPDATE SET updated_at = NOW()
-        RETURNING id, phone_number, created_at, updated_at
-        "#,
+    AuthService::consume_otp(&mut redis_conn, &req.phone_number).await?;
+
+    // A linked secondary phone signs in to the account it belongs to
+    let linked_user_id: Option<Uuid> =
+        sqlx::query_scalar("SELECT user_id FROM user_phones WHERE phone_number = $1")
+            .bind(&req.phone_number)
+            .fetch_optional(&state.db)
+            .await?;
+
+    let (user_id, role) = match linked_user_id {
+        Some(user_id) => (user_id, PhoneRole::Uploader),
+        None => {
+            // Get or create user
+            let user = sqlx::query_as::<_, crate::models::User>(
+                r#"
+                INSERT INTO users (phone_number)
+                VALUES ($1)
+                ON CONFLICT (phone_number) DO UPDATE SET updated_at = NOW()
+                RETURNING id, phone_number, created_at, updated_at
+                "#,
+            )
+            .bind(&req.phone_number)
+            .fetch_one(&state.db)
+            .await?;
+            (user.id, PhoneRole::Owner)
+        }
+    };
+
+    // Remember an explicitly requested language for later SMS
+    if let Some(locale) = requested_locale(&headers).filter(|_| role == PhoneRole::Owner) {
+        sqlx::query("UPDATE users SET preferred_language = $1 WHERE id = $2")
+            .bind(locale.code())
+            .bind(user_id)
+            .execute(&state.db)
+            .await?;
+    }
+
+    // Each sign-in is a session the owner can later sign out
+    let device_hint = headers
+        .get(header::USER_AGENT)
+        .and_then(|v| v.to_str().ok())
+        .map(|agent| agent.chars().take(MAX_DEVICE_HINT_LEN).collect::<String>());
+    let ip_range = security::ip_range(ip);
+    let check =
+        SecurityService::check_login(&state.db, user_id, &req.phone_number, device_hint.as_deref(), ip_range.as_deref())
+            .await?;
+    let expires_at = chrono::Utc::now() + chrono::Duration::days(SESSION_TTL_DAYS);
+    let session = UserSessionRepo::create(
+        &state.db,
+        &NewUserSession {
+            user_id,
+            phone_number: &req.phone_number,
+            role,
+            device_hint: device_hint.as_deref(),
+            ip_range: ip_range.as_deref(),
+            anomalous: check.anomalous(),
+            expires_at,
+        },
     )
-    .bind(&req.phone_number)
-    .fetch_one(&state.db)
     .await?;
 
+    if check.anomalous() {
+        tracing::warn!("Sign-in {} to account {} from an unfamiliar device or network", session.id, user_id);
+        let details = json!({
+            "phone_number": req.phone_number,
+            "role": role.as_str(),
+            "device_hint": device_hint,
+            "new_device": check.new_device,
+            "new_ip_range": check.new_ip_range,
+        });
+        AuditRepo::record(&state.db, user_id, Some(session.id), "login.anomalous", ip_range.as_deref(), details).await?;
+    }
+
     // Generate JWT token
-    let token = generate_jwt(user.id, &user.phone_number, &state.config.jwt_secret)?;
+    let token = generate_jwt(user_id, &req.phone_number, role, session.id, expires_at, &state.config.jwt_keys)?;
 
     Ok(Json(VerifyOtpResponse {
         token,
+        unfamiliar_login: check.anomalous(),
         user: UserResponse {
-            id: user.id.to_string(),
-            phone_number: user.phone_number,
+            id: user_id.to_string(),
+            phone_number: req.phone_number,
+            role,
         },
     }))
 }
 
+
+#[derive(Deserialize)]
+pub struct StepUpRequest {
+    /// A code sent to the signed-in phone with `POST /api/auth/request-otp`
+    pub otp: String,
+}
+
+#[derive(Serialize)]
+pub struct StepUpResponse {
+    /// How long sensitive actions are allowed without another code
+    pub expires_in: u64,
+}
+
+/// Confirms a fresh OTP within a signed-in session, which sensitive actions
+/// such as issuing access tokens or linking phones then accept for 10
+/// minutes. Attempts are recorded in the audit log, and after 5 wrong codes
+/// in 15 minutes the account's step-ups are refused until they age out.
+pub async fn step_up(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    claims: Claims,
+    Json(req): Json<StepUpRequest>,
+) -> Result<Json<StepUpResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let session_id = claims
+        .session_id
+        .as_deref()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .ok_or_else(|| AppError::Validation("Only a signed-in session can step up".to_string()))?;
+    let ip_range = security::ip_range(ip);
+
+    let lockout_since = chrono::Utc::now() - chrono::Duration::minutes(STEP_UP_LOCKOUT_MINS);
+    if AuditRepo::count_since(&state.db, user_id, "step_up.failed", lockout_since).await? >= STEP_UP_MAX_FAILURES {
+        return Err(AppError::RateLimit(STEP_UP_LOCKOUT_MINS as u64 * 60));
+    }
+
+    let mut redis_conn = state.redis.get_async_connection().await?;
+    if !AuthService::check_otp(&mut redis_conn, &claims.phone_number, &req.otp).await? {
+        AuditRepo::record(&state.db, user_id, Some(session_id), "step_up.failed", ip_range.as_deref(), json!({}))
+            .await?;
+        return Err(AppError::InvalidOtp);
+    }
+    AuthService::consume_otp(&mut redis_conn, &claims.phone_number).await?;
+
+    if !UserSessionRepo::step_up(&state.db, session_id, user_id).await? {
+        return Err(AppError::Auth("Session has been signed out".to_string()));
+    }
+    AuditRepo::record(&state.db, user_id, Some(session_id), "step_up.confirmed", ip_range.as_deref(), json!({}))
+        .await?;
+
+    Ok(Json(StepUpResponse {
+        expires_in: STEP_UP_WINDOW_MINS as u64 * 60,
+    }))
+}
@@ -0,0 +1,129 @@
+use axum::{
+    extract::{FromRequestParts, State},
+    http::request::Parts,
+    Json,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::handlers::{AppState, Claims};
+use crate::i18n::Message;
+use crate::middleware::lender::{LenderAuth, API_KEY_HEADER};
+use crate::middleware::locale::current_locale;
+use crate::services::billing::{BillingService, Usage};
+
+/// Usage is reported to a signed-in business, or to a lender by API key.
+pub enum BillingAccount {
+    Business(Claims),
+    Lender(LenderAuth),
+}
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for BillingAccount {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        if parts.headers.contains_key(API_KEY_HEADER) {
+            return Ok(BillingAccount::Lender(LenderAuth::from_request_parts(parts, state).await?));
+        }
+        let claims = Claims::from_request_parts(parts, state)
+            .await
+            .map_err(|_| AppError::Auth(Message::Unauthorized.render(current_locale())))?;
+        Ok(BillingAccount::Business(claims))
+    }
+}
+
+#[derive(Serialize)]
+pub struct BillingPlanResponse {
+    pub code: String,
+    pub name: String,
+    pub included_per_month: i32,
+    /// Price of each one past the included ones, in minor units; absent
+    /// when the plan refuses them instead
+    pub overage_price: Option<i64>,
+    pub currency: String,
+}
+
+#[derive(Serialize)]
+pub struct KeyUsageResponse {
+    /// Absent for keys since deleted
+    pub key_id: Option<String>,
+    pub key_prefix: Option<String>,
+    pub verifications: i64,
+}
+
+#[derive(Serialize)]
+pub struct UsageResponse {
+    pub plan: BillingPlanResponse,
+    /// "proof" or "verification"
+    pub unit: &'static str,
+    pub period_start: String,
+    /// When the monthly allowance resets
+    pub period_end: String,
+    pub used: i64,
+    /// Included ones left this month
+    pub remaining: i64,
+    /// Used past the allowance, each charged at the overage price
+    pub billable: i64,
+    /// Minor units of the plan's currency
+    pub amount_due: i64,
+    /// Whether another may be used this month
+    pub allowed: bool,
+    /// Lenders' verifications per key
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub by_key: Vec<KeyUsageResponse>,
+}
+
+impl From<Usage> for UsageResponse {
+    fn from(usage: Usage) -> Self {
+        Self {
+            remaining: (usage.included() - usage.used).max(0),
+            billable: usage.billable(),
+            amount_due: usage.amount_due_minor(),
+            allowed: usage.ensure_allowance().is_ok(),
+            unit: usage.unit,
+            period_start: usage.period_start.to_rfc3339(),
+            period_end: usage.period_end.to_rfc3339(),
+            used: usage.used,
+            by_key: usage
+                .by_key
+                .into_iter()
+                .map(|key| KeyUsageResponse {
+                    key_id: key.key_id.map(|id| id.to_string()),
+                    key_prefix: key.key_prefix,
+                    verifications: key.verifications,
+                })
+                .collect(),
+            plan: BillingPlanResponse {
+                code: usage.plan.code,
+                name: usage.plan.name,
+                included_per_month: usage.plan.included_per_month,
+                overage_price: usage.plan.overage_price_minor,
+                currency: usage.plan.currency,
+            },
+        }
+    }
+}
+
+/// This month's proofs (for a business) or verifications (for a lender key)
+/// against the account's plan.
+pub async fn get_usage(
+    State(state): State<AppState>,
+    account: BillingAccount,
+) -> Result<Json<UsageResponse>, AppError> {
+    let usage = match account {
+        BillingAccount::Business(claims) => {
+            let user_id =
+                Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+            BillingService::proof_usage(state.read_db(), user_id).await?
+        }
+        BillingAccount::Lender(lender) => {
+            if lender.sandbox {
+                return Err(AppError::Auth("Sandbox keys aren't billed".to_string()));
+            }
+            BillingService::verification_usage(state.read_db(), lender.lender_id).await?
+        }
+    };
+    Ok(Json(usage.into()))
+}
@@ -0,0 +1,323 @@
+use axum::{
+    extract::{Path, Query, State},
+    Form, Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::db::repos::ConsentRepo;
+use crate::error::AppError;
+use crate::handlers::{AppState, Claims};
+use crate::i18n::{Locale, Message};
+use crate::middleware::lender::LenderAuth;
+use crate::models::{DisclosurePolicy, ProofConsent};
+use crate::services::notification::{NotificationService, OwnerNotification};
+use crate::services::security::SecurityService;
+use crate::services::webhook::WebhookService;
+
+/// How long a request waits for the owner's answer.
+const REQUEST_TTL_HOURS: i64 = 48;
+/// How long an approval lasts, unless the proof expires first.
+const CONSENT_TTL_DAYS: i64 = 30;
+/// Requests a USSD menu lists at once; more don't fit on a screen.
+const USSD_MENU_SIZE: usize = 5;
+
+#[derive(Serialize)]
+pub struct ConsentResponse {
+    pub id: String,
+    pub verification_code: String,
+    pub lender_name: String,
+    /// "pending", "approved", "declined", "revoked" or "expired"
+    pub status: String,
+    pub requested_at: String,
+    pub decided_at: Option<String>,
+    pub expires_at: String,
+}
+
+impl From<ProofConsent> for ConsentResponse {
+    fn from(consent: ProofConsent) -> Self {
+        Self {
+            id: consent.id.to_string(),
+            verification_code: consent.verification_code,
+            lender_name: consent.lender_name,
+            status: consent.status,
+            requested_at: consent.requested_at.to_rfc3339(),
+            decided_at: consent.decided_at.map(|t| t.to_rfc3339()),
+            expires_at: consent.expires_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Asks the owner of a proof that requires consent to let this lender see
+/// its score, metrics and threshold outcome. The owner is notified and
+/// answers over USSD or in the app. Asking again while a request is
+/// pending or approved returns that one.
+pub async fn request_consent(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    Path(code): Path<String>,
+) -> Result<Json<ConsentResponse>, AppError> {
+    if lender.sandbox {
+        return Err(AppError::NotFound("Sandbox keys can't request consent".to_string()));
+    }
+
+    let code = crate::utils::normalize_verification_code(&code);
+    let row = sqlx::query(
+        r#"
+        SELECT ps.id, ps.expires_at, ps.disclosure_policy, u.id, u.preferred_language
+        FROM proof_sessions ps
+        JOIN users u ON u.id = ps.user_id
+        WHERE ps.verification_code = $1 AND ps.status = 'completed'
+        "#,
+    )
+    .bind(&code)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(AppError::ProofNotFound)?;
+
+    let session_id: Uuid = row.try_get(0)?;
+    let proof_expires_at: chrono::DateTime<chrono::Utc> = row.try_get(1)?;
+    let disclosure: DisclosurePolicy = serde_json::from_value(row.try_get(2)?).unwrap_or_default();
+    let user_id: Uuid = row.try_get(3)?;
+    let locale = Locale::from_code(&row.try_get::<String, _>(4)?).unwrap_or_default();
+
+    if proof_expires_at < chrono::Utc::now() {
+        return Err(AppError::ProofExpired);
+    }
+    if !disclosure.lender_consent {
+        return Err(AppError::Validation(
+            "This proof doesn't require consent; verify it directly".to_string(),
+        ));
+    }
+
+    if let Some(existing) = ConsentRepo::latest(&state.db, session_id, lender.lender_id).await? {
+        if existing.status == "pending" || existing.status == "approved" {
+            return Ok(Json(existing.into()));
+        }
+    }
+
+    let expires_at = (chrono::Utc::now() + chrono::Duration::hours(REQUEST_TTL_HOURS)).min(proof_expires_at);
+    let consent = ConsentRepo::create(&state.db, session_id, lender.lender_id, expires_at).await?;
+
+    // The owner can still answer in the app if the notification doesn't
+    // arrive
+    let notification = OwnerNotification {
+        event: "consent.requested",
+        session_id,
+        message: Message::ConsentRequestSms {
+            lender: &consent.lender_name,
+            verification_code: &consent.verification_code,
+            ussd_code: state.config.ussd_service_code.as_deref(),
+        },
+        by_sms: true,
+    };
+    if let Err(e) = NotificationService::notify_owner(&state.db, &state.config, user_id, locale, notification).await {
+        tracing::error!("Failed to notify the owner of consent request {}: {}", consent.id, e);
+    }
+
+    Ok(Json(consent.into()))
+}
+
+/// Where this lender's latest request for a proof stands.
+pub async fn get_consent(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    Path(code): Path<String>,
+) -> Result<Json<ConsentResponse>, AppError> {
+    let code = crate::utils::normalize_verification_code(&code);
+    let session_id: Uuid = sqlx::query_scalar("SELECT id FROM proof_sessions WHERE verification_code = $1")
+        .bind(&code)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or(AppError::ProofNotFound)?;
+
+    ConsentRepo::latest(&state.db, session_id, lender.lender_id)
+        .await?
+        .map(|consent| Json(consent.into()))
+        .ok_or_else(|| AppError::NotFound("No consent has been requested for this proof".to_string()))
+}
+
+/// Every lender request made for the caller's proofs.
+pub async fn list_consents(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<ConsentResponse>>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let consents = ConsentRepo::list_for_user(&state.db, user_id).await?;
+    Ok(Json(consents.into_iter().map(ConsentResponse::from).collect()))
+}
+
+pub async fn approve_consent(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(consent_id): Path<String>,
+) -> Result<Json<ConsentResponse>, AppError> {
+    answer_in_app(&state, &claims, &consent_id, true).await
+}
+
+pub async fn decline_consent(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(consent_id): Path<String>,
+) -> Result<Json<ConsentResponse>, AppError> {
+    answer_in_app(&state, &claims, &consent_id, false).await
+}
+
+/// Withdraws an approval, or a request not yet answered. The lender loses
+/// sight of the restricted fields on its next verification. Needs a
+/// step-up.
+pub async fn revoke_consent(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(consent_id): Path<String>,
+) -> Result<Json<ConsentResponse>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let consent_id = Uuid::parse_str(&consent_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let details = serde_json::json!({ "consent_id": consent_id });
+    SecurityService::require_step_up(&state.db, &claims, "consent.revoked", details).await?;
+
+    let consent = ConsentRepo::revoke(&state.db, consent_id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Consent not found or no longer in force".to_string()))?;
+    notify_lender(&state, &consent);
+
+    Ok(Json(consent.into()))
+}
+
+async fn answer_in_app(
+    state: &AppState,
+    claims: &Claims,
+    consent_id: &str,
+    approve: bool,
+) -> Result<Json<ConsentResponse>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let consent_id = Uuid::parse_str(consent_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    answer(state, consent_id, user_id, approve).await.map(|consent| Json(consent.into()))
+}
+
+/// Records the owner's answer to a pending request and tells the lender.
+async fn answer(state: &AppState, consent_id: Uuid, user_id: Uuid, approve: bool) -> Result<ProofConsent, AppError> {
+    let approved_until = approve.then(|| chrono::Utc::now() + chrono::Duration::days(CONSENT_TTL_DAYS));
+    let consent = ConsentRepo::decide(&state.db, consent_id, user_id, approved_until)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Consent request not found or already answered".to_string()))?;
+    notify_lender(state, &consent);
+    Ok(consent)
+}
+
+/// Sends the lender a `consent.approved`, `consent.declined` or
+/// `consent.revoked` event in the background.
+fn notify_lender(state: &AppState, consent: &ProofConsent) {
+    let db = state.db.clone();
+    let lender_id = consent.lender_id;
+    let event_type = format!("consent.{}", consent.status);
+    let data = serde_json::json!({
+        "consent_id": consent.id,
+        "verification_code": consent.verification_code,
+        "status": consent.status,
+        "expires_at": consent.expires_at.to_rfc3339(),
+    });
+    tokio::spawn(async move {
+        if let Err(e) = WebhookService::dispatch(&db, lender_id, &event_type, data).await {
+            tracing::error!("Failed to dispatch webhooks for lender {}: {}", lender_id, e);
+        }
+    });
+}
+
+#[derive(Deserialize)]
+pub struct UssdQuery {
+    pub token: Option<String>,
+}
+
+/// What the USSD gateway posts on each step of a session.
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UssdRequest {
+    pub phone_number: String,
+    /// The caller's choices so far, joined with "*"
+    #[serde(default)]
+    pub text: String,
+}
+
+/// USSD menu for answering consent requests: pick a pending request, then
+/// approve or decline it. Replies start "CON" to keep the session open and
+/// "END" to close it.
+pub async fn ussd_consents(
+    State(state): State<AppState>,
+    Query(query): Query<UssdQuery>,
+    Form(req): Form<UssdRequest>,
+) -> Result<String, AppError> {
+    let expected = state
+        .config
+        .ussd_callback_token
+        .as_deref()
+        .ok_or_else(|| AppError::NotFound("USSD isn't configured".to_string()))?;
+    let token = query.token.unwrap_or_default();
+    if !crate::utils::constant_time_eq(token.as_bytes(), expected.as_bytes()) {
+        return Err(AppError::Auth("Invalid callback token".to_string()));
+    }
+
+    // Only the account's own phone answers for its proofs
+    let user = sqlx::query("SELECT id, preferred_language FROM users WHERE phone_number = $1")
+        .bind(&req.phone_number)
+        .fetch_optional(&state.db)
+        .await?;
+    let Some(user) = user else {
+        return Ok(format!("END {}", Message::UssdUnknownPhone.render(Locale::default())));
+    };
+    let user_id: Uuid = user.try_get(0)?;
+    let locale = Locale::from_code(&user.try_get::<String, _>(1)?).unwrap_or_default();
+
+    let pending = ConsentRepo::pending_for_user(&state.db, user_id).await?;
+    let pick = |choice: &str| {
+        choice
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .filter(|&i| i < USSD_MENU_SIZE)
+            .and_then(|i| pending.get(i))
+    };
+
+    let choices: Vec<&str> = req.text.split('*').filter(|c| !c.is_empty()).collect();
+    let reply = match choices.as_slice() {
+        [] if pending.is_empty() => format!("END {}", Message::UssdNoConsentRequests.render(locale)),
+        [] => {
+            let mut menu = format!("CON {}", Message::UssdConsentRequests.render(locale));
+            for (i, consent) in pending.iter().take(USSD_MENU_SIZE).enumerate() {
+                menu.push_str(&format!("\n{}. {} ({})", i + 1, consent.lender_name, consent.verification_code));
+            }
+            menu
+        }
+        [choice] => match pick(choice) {
+            Some(consent) => format!(
+                "CON {}",
+                Message::UssdConsentPrompt {
+                    lender: &consent.lender_name,
+                    verification_code: &consent.verification_code,
+                }
+                .render(locale)
+            ),
+            None => format!("END {}", Message::UssdInvalidChoice.render(locale)),
+        },
+        [choice, decision @ ("1" | "2")] => match pick(choice) {
+            Some(consent) => {
+                let consent = answer(&state, consent.id, user_id, *decision == "1").await?;
+                let message = match consent.status.as_str() {
+                    "approved" => Message::UssdConsentApproved { lender: &consent.lender_name },
+                    _ => Message::UssdConsentDeclined { lender: &consent.lender_name },
+                };
+                format!("END {}", message.render(locale))
+            }
+            None => format!("END {}", Message::UssdInvalidChoice.render(locale)),
+        },
+        _ => format!("END {}", Message::UssdInvalidChoice.render(locale)),
+    };
+
+    Ok(reply)
+}
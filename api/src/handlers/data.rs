@@ -1,22 +1,142 @@
 use axum::{
-    extract::{Multipart, State},
+    extract::{
+        multipart::{Field, MultipartError},
+        Multipart, State,
+    },
+    http::StatusCode,
     Json,
 };
 use csv::ReaderBuilder;
-use serde::Serialize;
-use sqlx::Row;
+use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::config::Config;
+use crate::db::repos::reviews::NewReview;
+use crate::db::repos::{CurrencyRepo, ReviewRepo, TillRepo};
 use crate::error::AppError;
 use crate::handlers::{AppState, Claims};
-use crate::utils::hash_phone_number;
+use crate::i18n::Message;
+use crate::middleware::locale::current_locale;
+use crate::models::Currency;
+use crate::services::authenticity::{flag_reason, AuthenticityReport, AuthenticityService, StatementLine};
+use crate::services::file_scan::{DetectedType, FileRejection, FileScanService};
+use crate::services::money::parse_money;
+use crate::services::proof::ProofService;
+use crate::services::sms_import::SmsParser;
+use crate::utils::phone_number_hashes;
 
 #[derive(Serialize)]
 pub struct UploadDataResponse {
     pub message: String,
     pub transactions_imported: usize,
+    pub validation: ValidationReport,
+    /// How the imported rows were classified
+    pub directions: DirectionCounts,
+    /// Coverage of the till's whole scoring window after this import
+    pub completeness: CompletenessReport,
+    /// How a CSV or PDF statement fared against checks for edited exports
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authenticity: Option<AuthenticityReport>,
+    /// Rows held back from scoring until the owner confirms them
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub held_for_review: Vec<HeldRow>,
 }
 
+/// A row read from an upload but held for review instead of imported; see
+/// `/api/tills/:till_id/reviews`.
+#[derive(Serialize)]
+pub struct HeldRow {
+    pub review_id: String,
+    /// 1-based spreadsheet row, or line of a photo
+    pub row: usize,
+    /// "ocr", or the authenticity check the row failed
+    pub flagged_by: String,
+    pub reason: String,
+}
+
+/// Imported rows by `proof_core::Direction`. Only inflows count towards
+/// volume; outflows and fees are netted off it.
+#[derive(Serialize, Default)]
+pub struct DirectionCounts {
+    pub inflows: usize,
+    pub outflows: usize,
+    pub fees: usize,
+}
+
+impl DirectionCounts {
+    pub(crate) fn add(&mut self, direction: &str) {
+        match proof_core::Direction::from_name(direction) {
+            Some(proof_core::Direction::Inflow) => self.inflows += 1,
+            Some(proof_core::Direction::Outflow) => self.outflows += 1,
+            Some(proof_core::Direction::Fee) => self.fees += 1,
+            None => {}
+        }
+    }
+}
+
+/// Which weeks of the till's scoring window have payments, so merchants
+/// can upload what's missing before proving.
+#[derive(Serialize)]
+pub struct CompletenessReport {
+    pub coverage_percentage: u8,
+    /// Weeks without a single payment, as inclusive YYYY-MM-DD ranges
+    pub gaps: Vec<CoverageGap>,
+}
+
+#[derive(Serialize)]
+pub struct CoverageGap {
+    pub from: String,
+    pub to: String,
+}
+
+impl From<proof_core::Coverage> for CompletenessReport {
+    fn from(coverage: proof_core::Coverage) -> Self {
+        let date = |day: i64| {
+            chrono::DateTime::from_timestamp(day * 24 * 60 * 60, 0)
+                .map(|t| t.format("%Y-%m-%d").to_string())
+                .unwrap_or_default()
+        };
+        Self {
+            coverage_percentage: coverage.percentage(),
+            gaps: coverage
+                .gaps
+                .iter()
+                .map(|&(from, to)| CoverageGap {
+                    from: date(from),
+                    to: date(to),
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Rows the parser could not use, so merchants can fix their export.
+#[derive(Serialize, Default)]
+pub struct ValidationReport {
+    pub rows_read: usize,
+    pub rows_rejected: usize,
+    /// First few problems, by 1-based spreadsheet row
+    pub issues: Vec<RowIssue>,
+}
+
+#[derive(Serialize)]
+pub struct RowIssue {
+    pub row: usize,
+    pub reason: String,
+}
+
+pub(crate) const MAX_REPORTED_ISSUES: usize = 50;
+
+#[derive(Deserialize)]
+pub struct UploadSmsRequest {
+    pub till_id: String,
+    /// Raw M-PESA confirmation messages, as copied from the phone's inbox
+    pub messages: Vec<String>,
+}
+
+const MAX_SMS_PER_UPLOAD: usize = 10_000;
+
+
 pub async fn upload_data(
     State(state): State<AppState>,
     claims: Claims,
@@ -24,133 +144,703 @@ pub async fn upload_data(
 ) -> Result<Json<UploadDataResponse>, AppError> {
     let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
     let mut till_id: Option<Uuid> = None;
-    let mut file_data: Option<Vec<u8>> = None;
+    let mut file: Option<SpooledFile> = None;
     let mut file_type: Option<String> = None;
+    let mut source = "mpesa".to_string();
+    let mut currency_code = crate::models::DEFAULT_CURRENCY.to_string();
+    let mut timezone: Option<String> = None;
 
     // Parse multipart form
-    while let Some(field) = multipart.next_field().await.map_err(|e| AppError::FileProcessing(e.to_string()))? {
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| multipart_error(&state.config, e))?
+    {
         let name = field.name().unwrap_or("").to_string();
         let content_type = field.content_type().map(|s| s.to_string());
 
         if name == "till_id" {
-            let value = field.text().await.map_err(|e| AppError::FileProcessing(e.to_string()))?;
+            let value = read_text_field(&state.config, field).await?;
             till_id = Some(Uuid::parse_str(&value).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?);
+        } else if name == "source" {
+            source = read_text_field(&state.config, field).await?;
+            if !crate::models::TRANSACTION_SOURCES.contains(&source.as_str()) {
+                return Err(AppError::Validation(format!("Unknown source: {}", source)));
+            }
+        } else if name == "currency" {
+            currency_code = read_text_field(&state.config, field).await?;
+        } else if name == "timezone" {
+            timezone = Some(read_text_field(&state.config, field).await?);
         } else if name == "file" {
-            let bytes = field.bytes().await.map_err(|e| AppError::FileProcessing(e.to_string()))?;
-            file_data = Some(bytes.to_vec());
+            file = Some(spool_field(&state.config, field).await?);
             file_type = content_type;
         }
     }
 
-    let till_id = till_id.ok_or_else(|| AppError::Validation("Missing till_id".to_string()))?;
-    let file_data = file_data.ok_or_else(|| AppError::Validation("Missing file".to_string()))?;
+    let till_id = till_id.ok_or_else(|| AppError::Validation(Message::MissingTillId.render(current_locale())))?;
+    let file = file.ok_or_else(|| AppError::Validation(Message::MissingFile.render(current_locale())))?;
 
     // Verify till belongs to user
-    let row = sqlx::query("SELECT user_id FROM business_tills WHERE id = $1")
-        .bind(till_id)
-        .fetch_optional(&state.db)
-        .await?;
-
-    let till_user_id: Uuid = row
-        .ok_or_else(|| AppError::NotFound("Till not found".to_string()))?
-        .try_get::<Uuid, _>(0)
-        .map_err(|e| AppError::Database(e))?;
+    let till_user_id = TillRepo::owner(&state.db, till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
 
     if till_user_id != user_id {
-        return Err(AppError::Auth("Unauthorized".to_string()));
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
+    }
+
+    let currency = supported_currency(&state.db, &currency_code).await?;
+    let timezone = match timezone {
+        Some(name) => supported_timezone(&name)?,
+        None => till_timezone(&state.db, till_id).await?,
+    };
+
+    import_file(&state, till_id, file_type.as_deref(), &file, &source, &currency, timezone)
+        .await
+        .map(Json)
+}
+
+/// An upload written out to `Config::upload_temp_dir`. The file is removed
+/// when this is dropped.
+pub(crate) struct SpooledFile {
+    pub(crate) path: std::path::PathBuf,
+    len: usize,
+}
+
+impl SpooledFile {
+    /// Creates an empty temp file, returning it open for writing.
+    pub(crate) async fn create(config: &Config) -> Result<(Self, tokio::fs::File), AppError> {
+        let path = config.upload_temp_dir.join(format!("upload-{}", Uuid::new_v4()));
+        let out = tokio::fs::File::create(&path)
+            .await
+            .map_err(|e| AppError::Internal(e.into()))?;
+        Ok((Self { path, len: 0 }, out))
+    }
+}
+
+impl Drop for SpooledFile {
+    fn drop(&mut self) {
+        // Dropped on the runtime, so the removal is handed to a task rather
+        // than blocking it
+        let path = std::mem::take(&mut self.path);
+        match tokio::runtime::Handle::try_current() {
+            Ok(runtime) => {
+                runtime.spawn(async move {
+                    if let Err(e) = tokio::fs::remove_file(&path).await {
+                        tracing::warn!("Failed to remove spooled upload {}: {}", path.display(), e);
+                    }
+                });
+            }
+            Err(_) => {
+                if let Err(e) = std::fs::remove_file(&path) {
+                    tracing::warn!("Failed to remove spooled upload {}: {}", path.display(), e);
+                }
+            }
+        }
     }
+}
+
+/// Streams a file field to temp storage, refusing it as soon as it passes
+/// `max_upload_bytes` instead of after it has all been buffered.
+pub(crate) async fn spool_field(config: &Config, mut field: Field<'_>) -> Result<SpooledFile, AppError> {
+    use tokio::io::AsyncWriteExt;
+
+    let (mut spooled, mut out) = SpooledFile::create(config).await?;
+
+    while let Some(chunk) = field.chunk().await.map_err(|e| multipart_error(config, e))? {
+        spooled.len += chunk.len();
+        if spooled.len > config.max_upload_bytes {
+            return Err(AppError::FileRejected(FileRejection::TooLarge {
+                max_bytes: config.max_upload_bytes,
+            }));
+        }
+        out.write_all(&chunk).await.map_err(|e| AppError::Internal(e.into()))?;
+    }
+    out.flush().await.map_err(|e| AppError::Internal(e.into()))?;
+
+    Ok(spooled)
+}
 
-    // Process file based on type
-    let transactions = if file_type.as_deref() == Some("text/csv") ||
-                          file_type.as_deref() == Some("application/vnd.ms-excel") {
-        parse_csv(&file_data)?
-    } else if file_type.as_deref() == Some("application/pdf") {
-        parse_pdf(&file_data)?
+/// Reads a small form field such as `till_id`, up to
+/// `max_multipart_field_bytes`.
+pub(crate) async fn read_text_field(config: &Config, mut field: Field<'_>) -> Result<String, AppError> {
+    let name = field.name().unwrap_or("").to_string();
+    let mut bytes = Vec::new();
+
+    while let Some(chunk) = field.chunk().await.map_err(|e| multipart_error(config, e))? {
+        if bytes.len() + chunk.len() > config.max_multipart_field_bytes {
+            return Err(AppError::Validation(format!(
+                "Form field {} is longer than {} bytes",
+                name, config.max_multipart_field_bytes
+            )));
+        }
+        bytes.extend_from_slice(&chunk);
+    }
+
+    String::from_utf8(bytes).map_err(|_| AppError::Validation(format!("Form field {} is not valid UTF-8", name)))
+}
+
+/// Reports a body that outgrew the route's limit like any oversized upload.
+pub(crate) fn multipart_error(config: &Config, e: MultipartError) -> AppError {
+    if e.status() == StatusCode::PAYLOAD_TOO_LARGE {
+        AppError::FileRejected(FileRejection::TooLarge {
+            max_bytes: config.max_upload_bytes,
+        })
     } else {
-        return Err(AppError::FileProcessing(
-            "Unsupported file type. Please upload CSV or PDF".to_string(),
-        ));
+        AppError::FileProcessing(e.body_text())
+    }
+}
+
+/// Looks up a currency an import declares, rejecting ones that aren't
+/// configured.
+pub(crate) async fn supported_currency(db: &sqlx::PgPool, code: &str) -> Result<Currency, AppError> {
+    let code = code.trim().to_ascii_uppercase();
+    CurrencyRepo::find(db, &code)
+        .await?
+        .ok_or_else(|| AppError::Validation(format!("Unsupported currency: {}", code)))
+}
+
+/// The timezone a till's statements are read in unless an upload says
+/// otherwise.
+pub(crate) async fn till_timezone(db: &sqlx::PgPool, till_id: Uuid) -> Result<chrono_tz::Tz, AppError> {
+    let name = TillRepo::timezone(db, till_id).await?.ok_or(AppError::TillNotFound)?;
+    supported_timezone(&name)
+}
+
+/// Parses an IANA timezone name an import or till declares, such as
+/// "Africa/Kampala".
+pub(crate) fn supported_timezone(name: &str) -> Result<chrono_tz::Tz, AppError> {
+    name.trim()
+        .parse()
+        .map_err(|_| AppError::Validation(format!("Unknown timezone: {}", name.trim())))
+}
+
+/// Parses an uploaded statement and imports its transactions. Shared by
+/// single-request and chunked uploads.
+pub(crate) async fn import_file(
+    state: &AppState,
+    till_id: Uuid,
+    file_type: Option<&str>,
+    file: &SpooledFile,
+    source: &str,
+    currency: &Currency,
+    timezone: chrono_tz::Tz,
+) -> Result<UploadDataResponse, AppError> {
+    // Parse by what the bytes are, not what the client claims
+    let detected = FileScanService::inspect(&state.config, file_type, &file.path)
+        .await
+        .map_err(|e| AppError::Internal(e.into()))?
+        .map_err(AppError::FileRejected)?;
+
+    // The parsers read the file as they go, on a blocking thread
+    let path = file.path.clone();
+    let parse_currency = currency.clone();
+    let (transactions, validation) = tokio::task::spawn_blocking(move || {
+        let file = std::fs::File::open(&path).map_err(|e| AppError::Internal(e.into()))?;
+        let reader = std::io::BufReader::new(file);
+        match detected {
+            DetectedType::Csv => parse_csv(reader, &parse_currency, timezone),
+            DetectedType::Xlsx => parse_xlsx(reader, &parse_currency, timezone),
+            DetectedType::Pdf => Ok((parse_pdf(reader)?, ValidationReport::default())),
+        }
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.into()))??;
+    // Spreadsheets are exports of the same statements as CSVs
+    let provenance = match detected {
+        DetectedType::Pdf => proof_core::Provenance::PdfUpload,
+        DetectedType::Csv | DetectedType::Xlsx => proof_core::Provenance::CsvUpload,
     };
 
-    // Import transactions
+    let lines: Vec<StatementLine> = transactions
+        .iter()
+        .map(|tx| StatementLine {
+            row: tx.row,
+            timestamp: tx.timestamp,
+            amount: tx.amount,
+            reference: &tx.reference,
+            balance: tx.balance,
+            fee: tx.fee.unwrap_or(0),
+        })
+        .collect();
+    let authenticity = AuthenticityService::assess(&state.db, till_id, source, &lines).await?;
+
+    // Import transactions, holding rows that failed a check for review
     let mut imported = 0;
-    for tx in transactions {
-        // Hash phone numbers/references for privacy
-        let hashed_reference = hash_phone_number(&tx.reference);
+    let mut directions = DirectionCounts::default();
+    let mut flagged = Vec::new();
+    for tx in &transactions {
+        if let Some(&check) = authenticity.flagged.get(&tx.row) {
+            flagged.push((tx, check));
+            continue;
+        }
+
+        // Hash phone numbers/references for privacy. Every form the receipt
+        // can be stored in is checked, since the insert trigger only sees the
+        // current one and rehashing stored rows takes a while.
+        let references = phone_number_hashes(&tx.reference);
 
-        // Insert transaction (ignore duplicates)
-        let result = sqlx::query(
+        // Insert transaction; the insert triggers skip receipts the till
+        // already has and classify its kind and direction
+        let direction: Option<String> = sqlx::query_scalar(
             r#"
-            INSERT INTO transactions (till_id, timestamp, amount, transaction_type, reference)
-            VALUES ($1, $2, $3, $4, $5)
-            ON CONFLICT (till_id, reference) DO NOTHING
+            INSERT INTO transactions (till_id, timestamp, amount, transaction_type, reference, source, account_number,
+                                      currency, provenance)
+            SELECT $1, $2, $3, $4, $5, $6, $7, $8, $9
+            WHERE NOT EXISTS (SELECT 1 FROM transaction_receipts r WHERE r.till_id = $1 AND r.reference = ANY($10))
+            RETURNING direction
             "#,
         )
         .bind(till_id)
         .bind(tx.timestamp)
         .bind(tx.amount)
         .bind(&tx.transaction_type)
-        .bind(hashed_reference)
-        .execute(&state.db)
+        .bind(&references[0])
+        .bind(source)
+        .bind(&tx.account_number)
+        .bind(&currency.code)
+        .bind(provenance.as_str())
+        .bind(&references)
+        .fetch_optional(&state.db)
         .await?;
 
-        if result.rows_affected() > 0 {
+        if let Some(direction) = direction {
             imported += 1;
+            directions.add(&direction);
         }
     }
 
-    Ok(Json(UploadDataResponse {
+    let statement_import_id =
+        AuthenticityService::record(&state.db, till_id, source, provenance, imported, &authenticity).await?;
+
+    // Held against the upload, whose score counts once any are confirmed
+    let mut held_for_review = Vec::new();
+    for (tx, check) in flagged {
+        let review_id = ReviewRepo::hold(
+            &state.db,
+            &NewReview {
+                till_id,
+                image_id: None,
+                statement_import_id: Some(statement_import_id),
+                flagged_by: check,
+                reason: flag_reason(check),
+                row_number: tx.row,
+                raw_text: None,
+                confidence: None,
+                receipt_number: Some(tx.reference.as_str()),
+                timestamp: Some(tx.timestamp),
+                amount: Some(tx.amount),
+                transaction_type: Some(tx.transaction_type.as_str()),
+                account_number: tx.account_number.as_deref(),
+                source,
+                currency: &currency.code,
+                provenance,
+            },
+        )
+        .await?;
+        held_for_review.push(HeldRow {
+            review_id: review_id.to_string(),
+            row: tx.row,
+            flagged_by: check.to_string(),
+            reason: flag_reason(check).to_string(),
+        });
+    }
+
+    let completeness = ProofService::refresh_completeness(&state.db, till_id).await?;
+
+    Ok(UploadDataResponse {
         message: "Data uploaded successfully".to_string(),
         transactions_imported: imported,
+        validation,
+        directions,
+        completeness: completeness.into(),
+        authenticity: Some(authenticity),
+        held_for_review,
+    })
+}
+
+/// Imports incoming payments from M-PESA confirmation SMSes, for merchants
+/// who have no statement export.
+pub async fn upload_sms(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(req): Json<UploadSmsRequest>,
+) -> Result<Json<UploadDataResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let till_id = Uuid::parse_str(&req.till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    if req.messages.len() > MAX_SMS_PER_UPLOAD {
+        return Err(AppError::Validation(format!(
+            "At most {} messages can be uploaded at once",
+            MAX_SMS_PER_UPLOAD
+        )));
+    }
+
+    // Verify till belongs to user
+    let till_user_id = TillRepo::owner(&state.db, till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
+
+    if till_user_id != user_id {
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
+    }
+
+    let mut validation = ValidationReport::default();
+    let mut imported = 0;
+    let mut directions = DirectionCounts::default();
+
+    for (i, text) in req.messages.iter().enumerate() {
+        if text.trim().is_empty() {
+            continue;
+        }
+        validation.rows_read += 1;
+
+        let sms = match SmsParser::parse(text) {
+            Ok(sms) => sms,
+            Err(reason) => {
+                validation.rows_rejected += 1;
+                if validation.issues.len() < MAX_REPORTED_ISSUES {
+                    validation.issues.push(RowIssue { row: i + 1, reason });
+                }
+                continue;
+            }
+        };
+
+        let raw_data = serde_json::json!({
+            "source": "sms",
+            "counterparty_hash": sms.counterparty_hash,
+        });
+
+        let references = phone_number_hashes(&sms.receipt);
+        let direction: Option<String> = sqlx::query_scalar(
+            r#"
+            INSERT INTO transactions (till_id, timestamp, amount, transaction_type, reference, raw_data, provenance)
+            SELECT $1, $2, $3, 'Payment', $4, $5, 'sms'
+            WHERE NOT EXISTS (SELECT 1 FROM transaction_receipts r WHERE r.till_id = $1 AND r.reference = ANY($6))
+            RETURNING direction
+            "#,
+        )
+        .bind(till_id)
+        .bind(sms.timestamp)
+        .bind(sms.amount_cents)
+        .bind(&references[0])
+        .bind(raw_data)
+        .bind(&references)
+        .fetch_optional(&state.db)
+        .await?;
+
+        if let Some(direction) = direction {
+            imported += 1;
+            directions.add(&direction);
+        }
+    }
+
+    let completeness = ProofService::refresh_completeness(&state.db, till_id).await?;
+
+    Ok(Json(UploadDataResponse {
+        message: "Messages imported successfully".to_string(),
+        transactions_imported: imported,
+        validation,
+        directions,
+        completeness: completeness.into(),
+        authenticity: None,
+        held_for_review: Vec::new(),
     }))
 }
 
 struct ParsedTransaction {
+    /// 1-based spreadsheet row
+    row: usize,
     timestamp: chrono::DateTime<chrono::Utc>,
     amount: i64,
     transaction_type: String,
     reference: String,
+    /// PayBill account number, when the export has that column
+    account_number: Option<String>,
+    /// Running balance after the row, when the export has that column
+    balance: Option<i64>,
+    /// Charge in its own column, which the balance also moved by
+    fee: Option<i64>,
 }
 
-fn parse_csv(data: &[u8]) -> Result<Vec<ParsedTransaction>, AppError> {
-    let mut reader = ReaderBuilder::new()
-        .has_headers(true)
-        .from_reader(data);
+/// Where each field lives in a statement export, found from its header row.
+struct ColumnMap {
+    date: usize,
+    amount: AmountColumns,
+    transaction_type: Option<usize>,
+    reference: usize,
+    account_number: Option<usize>,
+    currency: Option<usize>,
+    balance: Option<usize>,
+    fee: Option<usize>,
+}
 
-    let mut transactions = Vec::new();
+/// Statements either sign one amount column or split money in and out.
+enum AmountColumns {
+    Signed(usize),
+    /// M-Pesa's Paid In and Withdrawn, or a bank's Credit and Debit
+    Split { paid_in: usize, withdrawn: Option<usize> },
+}
 
-    for result in reader.records() {
-        let record = result.map_err(|e| AppError::FileProcessing(e.to_string()))?;
+impl ColumnMap {
+    /// Recognises the M-Pesa portal and common bank export headings; falls
+    /// back to Date, Amount, Type, Reference order.
+    fn detect(header: &[String]) -> Self {
+        let find = |names: &[&str]| {
+            header
+                .iter()
+                .position(|h| names.contains(&h.trim().to_ascii_lowercase().as_str()))
+        };
+
+        let date = find(&["date", "completion time", "transaction date", "time", "initiation time"]);
+        let amount = find(&["amount", "transaction amount"]).map(AmountColumns::Signed).or_else(|| {
+            find(&["paid in", "credit", "money in"]).map(|paid_in| AmountColumns::Split {
+                paid_in,
+                withdrawn: find(&["withdrawn", "withdrawal", "debit", "paid out", "money out"]),
+            })
+        });
+        let transaction_type = find(&["type", "transaction type", "details", "transaction status"]);
+        let reference = find(&["reference", "receipt no.", "receipt no", "receipt", "transaction id"]);
+        let account_number = find(&["a/c no.", "account no.", "account number", "account", "bill reference", "billrefnumber"]);
+        let currency = find(&["currency", "ccy"]);
+        let balance = find(&["balance", "running balance", "book balance"]);
+        let fee = find(&["fee", "charge", "charges", "transaction cost", "transaction fee"]);
+
+        match (date, amount, reference) {
+            (Some(date), Some(amount), Some(reference)) => Self {
+                date,
+                amount,
+                transaction_type,
+                reference,
+                account_number,
+                currency,
+                balance,
+                fee,
+            },
+            _ => Self {
+                date: 0,
+                amount: AmountColumns::Signed(1),
+                transaction_type: Some(2),
+                reference: 3,
+                account_number: None,
+                currency: None,
+                balance: None,
+                fee: None,
+            },
+        }
+    }
 
-        // Try to parse common CSV formats
-        // Expected columns: Date, Amount, Type, Reference/Transaction ID
-        if record.len() < 4 {
+    /// Amounts are read in `currency`'s minor units. Rows that name a
+    /// different currency are rejected rather than silently rescaled. Times
+    /// without an offset are wall-clock times in `timezone`.
+    fn parse(
+        &self,
+        row: usize,
+        record: &[String],
+        currency: &Currency,
+        timezone: chrono_tz::Tz,
+    ) -> Result<ParsedTransaction, AppError> {
+        let field = |index: usize, name: &str| {
+            record
+                .get(index)
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .ok_or_else(|| AppError::FileProcessing(format!("Missing {}", name)))
+        };
+
+        let timestamp = parse_date(field(self.date, "date")?, timezone)?;
+        if let Some(row_currency) = self
+            .currency
+            .and_then(|i| record.get(i))
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+        {
+            if !row_currency.eq_ignore_ascii_case(&currency.code) {
+                return Err(AppError::FileProcessing(format!(
+                    "Currency {} doesn't match the upload's currency {}",
+                    row_currency, currency.code
+                )));
+            }
+        }
+        let amount = self.amount(record, currency)?;
+        let transaction_type = self
+            .transaction_type
+            .and_then(|i| record.get(i))
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .unwrap_or("Payment");
+        let reference = field(self.reference, "reference")?;
+        let account_number = self
+            .account_number
+            .and_then(|i| record.get(i))
+            .map(|v| v.trim())
+            .filter(|v| !v.is_empty())
+            .map(str::to_string);
+        // Only checked for authenticity, so unreadable ones don't reject
+        // the row
+        let optional_money = |column: Option<usize>| {
+            column
+                .and_then(|i| record.get(i))
+                .map(|v| v.trim())
+                .filter(|v| !v.is_empty())
+                .and_then(|v| parse_money(v, currency.minor_unit_exponent.max(0) as u32).ok())
+                .map(|money| money.minor_units)
+        };
+        let balance = optional_money(self.balance);
+        let fee = optional_money(self.fee);
+
+        Ok(ParsedTransaction {
+            row,
+            timestamp,
+            amount,
+            transaction_type: transaction_type.to_string(),
+            reference: reference.to_string(),
+            account_number,
+            balance,
+            fee,
+        })
+    }
+
+    /// The row's amount in minor units, negative when money went out.
+    /// Empty and zero cells in a split statement are the side that didn't
+    /// move.
+    fn amount(&self, record: &[String], currency: &Currency) -> Result<i64, AppError> {
+        let cell = |index: usize| record.get(index).map(|v| v.trim()).filter(|v| !v.is_empty());
+        let read = |value: &str| {
+            let money = parse_money(value, currency.minor_unit_exponent.max(0) as u32)
+                .map_err(AppError::FileProcessing)?;
+            if let Some(amount_currency) = money.currency.filter(|code| *code != currency.code) {
+                return Err(AppError::FileProcessing(format!(
+                    "Amount in {} doesn't match the upload's currency {}",
+                    amount_currency, currency.code
+                )));
+            }
+            Ok(money.minor_units)
+        };
+
+        match self.amount {
+            AmountColumns::Signed(index) => {
+                read(cell(index).ok_or_else(|| AppError::FileProcessing("Missing amount".to_string()))?)
+            }
+            AmountColumns::Split { paid_in, withdrawn } => {
+                let paid_in = cell(paid_in).map(read).transpose()?.filter(|&a| a != 0);
+                let withdrawn = withdrawn.and_then(cell).map(read).transpose()?.filter(|&a| a != 0);
+                match (paid_in, withdrawn) {
+                    (Some(amount), None) => Ok(amount),
+                    // Statements print withdrawals as either sign
+                    (None, Some(amount)) => Ok(-amount.abs()),
+                    (None, None) => Err(AppError::FileProcessing("Missing amount".to_string())),
+                    (Some(_), Some(_)) => Err(AppError::FileProcessing(
+                        "Row has both a paid in and a withdrawn amount".to_string(),
+                    )),
+                }
+            }
+        }
+    }
+}
+
+/// Parses data rows against a detected header, collecting rejected rows in
+/// the report instead of failing the whole upload.
+fn parse_rows(
+    header: &[String],
+    rows: impl Iterator<Item = Vec<String>>,
+    currency: &Currency,
+    timezone: chrono_tz::Tz,
+) -> (Vec<ParsedTransaction>, ValidationReport) {
+    let columns = ColumnMap::detect(header);
+    let mut transactions = Vec::new();
+    let mut report = ValidationReport::default();
+
+    for (i, record) in rows.enumerate() {
+        if record.iter().all(|f| f.trim().is_empty()) {
             continue;
         }
+        report.rows_read += 1;
+
+        // Header is row 1
+        let row = i + 2;
+        match columns.parse(row, &record, currency, timezone) {
+            Ok(tx) => transactions.push(tx),
+            Err(e) => {
+                report.rows_rejected += 1;
+                if report.issues.len() < MAX_REPORTED_ISSUES {
+                    report.issues.push(RowIssue {
+                        row,
+                        reason: match e {
+                            AppError::FileProcessing(reason) => reason,
+                            other => other.to_string(),
+                        },
+                    });
+                }
+            }
+        }
+    }
 
-        let date_str = record.get(0).unwrap_or("");
-        let amount_str = record.get(1).unwrap_or("");
-        let tx_type = record.get(2).unwrap_or("Payment");
-        let reference = record.get(3).unwrap_or("");
+    (transactions, report)
+}
 
-        // Parse date (try multiple formats)
-        let timestamp = parse_date(date_str)?;
+fn parse_csv(
+    data: impl std::io::Read,
+    currency: &Currency,
+    timezone: chrono_tz::Tz,
+) -> Result<(Vec<ParsedTransaction>, ValidationReport), AppError> {
+    let mut reader = ReaderBuilder::new()
+        .has_headers(true)
+        .flexible(true)
+        .from_reader(data);
 
-        // Parse amount (remove currency symbols, convert to cents)
-        let amount = parse_amount(amount_str)?;
+    let header: Vec<String> = reader
+        .headers()
+        .map_err(|e| AppError::FileProcessing(e.to_string()))?
+        .iter()
+        .map(str::to_string)
+        .collect();
 
-        transactions.push(ParsedTransaction {
-            timestamp,
-            amount,
-            transaction_type: tx_type.to_string(),
-            reference: reference.to_string(),
-        });
-    }
+    let records = reader
+        .records()
+        .map(|r| r.map(|record| record.iter().map(str::to_string).collect::<Vec<String>>()))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::FileProcessing(e.to_string()))?;
+
+    Ok(parse_rows(&header, records.into_iter(), currency, timezone))
+}
+
+fn parse_xlsx(
+    data: impl std::io::Read + std::io::Seek,
+    currency: &Currency,
+    timezone: chrono_tz::Tz,
+) -> Result<(Vec<ParsedTransaction>, ValidationReport), AppError> {
+    use calamine::{Data, Reader};
+
+    let mut workbook = calamine::Xlsx::new(data)
+        .map_err(|e| AppError::FileProcessing(format!("Unable to read spreadsheet: {}", e)))?;
+
+    // Statements are on the first sheet
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or_else(|| AppError::FileProcessing("Spreadsheet has no sheets".to_string()))?
+        .map_err(|e| AppError::FileProcessing(format!("Unable to read spreadsheet: {}", e)))?;
+
+    let cell_text = |cell: &Data| match cell {
+        // Excel stores dates as serial numbers; render them as parse_date expects
+        Data::DateTime(dt) => dt
+            .as_datetime()
+            .map(|d| d.format("%Y-%m-%d %H:%M:%S").to_string())
+            .unwrap_or_default(),
+        other => other.to_string(),
+    };
+
+    let mut rows = range
+        .rows()
+        .map(|row| row.iter().map(cell_text).collect::<Vec<String>>());
+    let header = rows
+        .next()
+        .ok_or_else(|| AppError::FileProcessing("Spreadsheet is empty".to_string()))?;
 
-    Ok(transactions)
+    Ok(parse_rows(&header, rows, currency, timezone))
 }
 
-fn parse_pdf(_data: &[u8]) -> Result<Vec<ParsedTransaction>, AppError> {
+fn parse_pdf(_data: impl std::io::Read) -> Result<Vec<ParsedTransaction>, AppError> {
     // TODO: Implement PDF parsing with OCR
     // For now, return error
     Err(AppError::FileProcessing(
@@ -158,7 +848,13 @@ fn parse_pdf(_data: &[u8]) -> Result<Vec<ParsedTransaction>, AppError> {
     ))
 }
 
-fn parse_date(date_str: &str) -> Result<chrono::DateTime<chrono::Utc>, AppError> {
+/// Reads a statement date. Times that carry an offset are taken as given;
+/// the rest are wall-clock times in `timezone`, dates alone its midnight.
+pub(crate) fn parse_date(date_str: &str, timezone: chrono_tz::Tz) -> Result<chrono::DateTime<chrono::Utc>, AppError> {
+    if let Ok(dt) = chrono::DateTime::parse_from_rfc3339(date_str) {
+        return Ok(dt.with_timezone(&chrono::Utc));
+    }
+
     // Try multiple date formats
     let formats = [
         "%Y-%m-%d",
@@ -166,32 +862,27 @@ fn parse_date(date_str: &str) -> Result<chrono::DateTime<chrono::Utc>, AppError>
         "%m/%d/%Y",
         "%d-%m-%Y",
         "%Y-%m-%d %H:%M:%S",
+        "%Y-%m-%d %H:%M",
+        "%Y-%m-%dT%H:%M:%S",
+        "%d/%m/%Y %H:%M:%S",
+        "%d/%m/%Y %H:%M",
+        "%d-%m-%Y %H:%M:%S",
+        "%d-%m-%Y %H:%M",
+        "%d %b %Y %H:%M:%S",
+        "%d %b %Y",
     ];
 
-    for format in &formats {
-        if let Ok(dt) = chrono::NaiveDateTime::parse_from_str(date_str, format) {
-            return Ok(dt.and_utc());
-        }
-        if let Ok(d) = chrono::NaiveDate::parse_from_str(date_str, format) {
-            return Ok(d.and_hms_opt(0, 0, 0).unwrap().and_utc());
-        }
-    }
-
-    Err(AppError::FileProcessing(format!("Unable to parse date: {}", date_str)))
-}
-
-fn parse_amount(amount_str: &str) -> Result<i64, AppError> {
-    // Remove currency symbols and commas
-    let cleaned: String = amount_str
-        .chars()
-        .filter(|c| c.is_ascii_digit() || *c == '.' || *c == '-')
-        .collect();
-
-    let amount: f64 = cleaned
-        .parse()
-        .map_err(|_| AppError::FileProcessing(format!("Unable to parse amount: {}", amount_str)))?;
-
-    // Convert to cents
-    Ok((amount * 100.0) as i64)
+    let local = formats.iter().find_map(|format| {
+        chrono::NaiveDateTime::parse_from_str(date_str, format)
+            .ok()
+            .or_else(|| {
+                chrono::NaiveDate::parse_from_str(date_str, format)
+                    .ok()
+                    .and_then(|d| d.and_hms_opt(0, 0, 0))
+            })
+    });
+
+    local
+        .and_then(|local| crate::utils::local_to_utc(local, timezone))
+        .ok_or_else(|| AppError::FileProcessing(format!("Unable to parse date: {}", date_str)))
 }
-
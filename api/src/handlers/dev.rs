@@ -0,0 +1,83 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::repos::TillRepo;
+use crate::error::AppError;
+use crate::handlers::{AppState, Claims};
+use crate::services::simulator::{SimulationParams, SimulatorService};
+use crate::utils::hash_phone_number;
+
+#[derive(Deserialize)]
+pub struct SimulateTransactionsRequest {
+    pub till_id: String,
+    #[serde(flatten)]
+    pub params: SimulationParams,
+}
+
+#[derive(Serialize)]
+pub struct SimulateTransactionsResponse {
+    pub transactions_generated: usize,
+    pub transactions_imported: usize,
+    pub period_start: Option<String>,
+    pub period_end: Option<String>,
+}
+
+pub async fn simulate_transactions(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(req): Json<SimulateTransactionsRequest>,
+) -> Result<Json<SimulateTransactionsResponse>, AppError> {
+    if !state.config.demo_mode {
+        return Err(AppError::NotFound("Not found".to_string()));
+    }
+
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let till_id = Uuid::parse_str(&req.till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    req.params.validate().map_err(AppError::Validation)?;
+
+    // Verify till belongs to user
+    let till_user_id = TillRepo::owner(&state.db, till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
+
+    if till_user_id != user_id {
+        return Err(AppError::Auth("Unauthorized".to_string()));
+    }
+
+    let transactions = SimulatorService::generate(&req.params, chrono::Utc::now());
+
+    let mut db_tx = state.db.begin().await?;
+    let mut imported = 0;
+    for tx in &transactions {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO transactions (till_id, timestamp, amount, transaction_type, reference, raw_data, provenance)
+            VALUES ($1, $2, $3, $4, $5, $6, 'csv')
+            "#,
+        )
+        .bind(till_id)
+        .bind(tx.timestamp)
+        .bind(tx.amount)
+        .bind(&tx.transaction_type)
+        .bind(hash_phone_number(&tx.reference))
+        .bind(serde_json::json!({
+            "source": "simulator",
+            "counterparty_hash": tx.counterparty_hash,
+        }))
+        .execute(&mut *db_tx)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            imported += 1;
+        }
+    }
+    db_tx.commit().await?;
+
+    Ok(Json(SimulateTransactionsResponse {
+        transactions_generated: transactions.len(),
+        transactions_imported: imported,
+        period_start: transactions.first().map(|t| t.timestamp.to_rfc3339()),
+        period_end: transactions.last().map(|t| t.timestamp.to_rfc3339()),
+    }))
+}
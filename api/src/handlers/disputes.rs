@@ -0,0 +1,101 @@
+use axum::{
+    extract::{FromRequestParts, Path, State},
+    http::request::Parts,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::handlers::AppState;
+use crate::middleware::admin::{AdminAuth, ADMIN_KEY_HEADER};
+use crate::middleware::lender::LenderAuth;
+use crate::services::dispute::{DisputeOutcome, DisputeService};
+
+#[derive(Deserialize)]
+pub struct SubmitDisputeRequest {
+    /// M-Pesa receipt number of the disputed transaction
+    pub transaction_reference: String,
+    pub reason: String,
+    /// Description of, or link to, the supporting evidence
+    pub evidence: String,
+}
+
+#[derive(Serialize)]
+pub struct SubmitDisputeResponse {
+    pub dispute_id: String,
+    pub contested_proofs: u64,
+}
+
+/// Disputes can be raised by an operator (admin key) or a live lender key.
+pub enum DisputeSubmitter {
+    Admin,
+    Lender(LenderAuth),
+}
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for DisputeSubmitter {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        if parts.headers.contains_key(ADMIN_KEY_HEADER) {
+            AdminAuth::from_request_parts(parts, state).await?;
+            return Ok(DisputeSubmitter::Admin);
+        }
+
+        let lender = LenderAuth::from_request_parts(parts, state).await?;
+        if lender.sandbox {
+            return Err(AppError::Auth("Sandbox keys cannot submit disputes".to_string()));
+        }
+        Ok(DisputeSubmitter::Lender(lender))
+    }
+}
+
+pub async fn submit_dispute(
+    State(state): State<AppState>,
+    submitter: DisputeSubmitter,
+    Path(till_id): Path<String>,
+    Json(req): Json<SubmitDisputeRequest>,
+) -> Result<Json<SubmitDisputeResponse>, AppError> {
+    let till_id = Uuid::parse_str(&till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let reason = req.reason.trim();
+    let evidence = req.evidence.trim();
+    if req.transaction_reference.trim().is_empty() {
+        return Err(AppError::Validation("transaction_reference is required".to_string()));
+    }
+    if reason.is_empty() || reason.len() > 255 {
+        return Err(AppError::Validation("Invalid dispute reason".to_string()));
+    }
+    if evidence.is_empty() {
+        return Err(AppError::Validation("Evidence is required".to_string()));
+    }
+
+    let lender_id = match submitter {
+        DisputeSubmitter::Admin => None,
+        DisputeSubmitter::Lender(lender) => Some(lender.lender_id),
+    };
+
+    let outcome = DisputeService::record(
+        &state.db,
+        till_id,
+        lender_id,
+        req.transaction_reference.trim(),
+        reason,
+        evidence,
+    )
+    .await?;
+
+    match outcome {
+        DisputeOutcome::Recorded { dispute_id, contested_proofs } => Ok(Json(SubmitDisputeResponse {
+            dispute_id: dispute_id.to_string(),
+            contested_proofs,
+        })),
+        DisputeOutcome::TransactionNotFound => {
+            Err(AppError::NotFound("Transaction not found on this till".to_string()))
+        }
+        DisputeOutcome::AlreadyDisputed => {
+            Err(AppError::Validation("Transaction has already been disputed".to_string()))
+        }
+    }
+}
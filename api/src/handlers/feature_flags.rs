@@ -0,0 +1,146 @@
+use std::collections::BTreeMap;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::repos::feature_flags::FeatureFlag;
+use crate::db::repos::FeatureFlagRepo;
+use crate::error::AppError;
+use crate::handlers::{AppState, Claims};
+use crate::middleware::admin::AdminAuth;
+use crate::services::feature_flags::{in_rollout, FeatureFlagService};
+
+const MAX_FLAG_USERS: usize = 1000;
+
+#[derive(Deserialize)]
+pub struct PutFeatureFlagRequest {
+    #[serde(default)]
+    pub description: String,
+    /// Off for everyone when false, whatever else is set
+    pub enabled: bool,
+    /// Share of users it's on for, 0 to 100
+    #[serde(default)]
+    pub rollout_percentage: i16,
+    /// Users it's on for whatever the percentage
+    #[serde(default)]
+    pub user_ids: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct FeatureFlagResponse {
+    pub key: String,
+    pub description: String,
+    pub enabled: bool,
+    pub rollout_percentage: i16,
+    pub user_ids: Vec<String>,
+    pub updated_at: String,
+}
+
+impl From<FeatureFlag> for FeatureFlagResponse {
+    fn from(flag: FeatureFlag) -> Self {
+        Self {
+            key: flag.key,
+            description: flag.description,
+            enabled: flag.enabled,
+            rollout_percentage: flag.rollout_percentage,
+            user_ids: flag.user_ids.iter().map(Uuid::to_string).collect(),
+            updated_at: flag.updated_at.to_rfc3339(),
+        }
+    }
+}
+
+pub async fn list_feature_flags(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> Result<Json<Vec<FeatureFlagResponse>>, AppError> {
+    let flags = FeatureFlagRepo::list(&state.db).await?;
+    Ok(Json(flags.into_iter().map(Into::into).collect()))
+}
+
+/// Creates a flag or replaces its settings. Takes effect on the next check.
+pub async fn put_feature_flag(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(key): Path<String>,
+    Json(req): Json<PutFeatureFlagRequest>,
+) -> Result<Json<FeatureFlagResponse>, AppError> {
+    let valid_key = (1..=64).contains(&key.len())
+        && key.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_' || c == '.');
+    if !valid_key {
+        return Err(AppError::Validation(
+            "key must be 1 to 64 lowercase letters, digits, underscores or dots".to_string(),
+        ));
+    }
+    if !(0..=100).contains(&req.rollout_percentage) {
+        return Err(AppError::Validation("rollout_percentage must be between 0 and 100".to_string()));
+    }
+    if req.user_ids.len() > MAX_FLAG_USERS {
+        return Err(AppError::Validation(format!("At most {} user_ids can be listed", MAX_FLAG_USERS)));
+    }
+    let mut user_ids = req
+        .user_ids
+        .iter()
+        .map(|id| Uuid::parse_str(id))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    user_ids.sort();
+    user_ids.dedup();
+
+    let flag = FeatureFlagRepo::upsert(
+        &state.db,
+        &key,
+        req.description.trim(),
+        req.enabled,
+        req.rollout_percentage,
+        &user_ids,
+    )
+    .await?;
+    FeatureFlagService::invalidate(&state.redis, &key).await;
+
+    tracing::info!(
+        "Feature flag {} set: enabled={}, rollout={}%, {} listed users",
+        key,
+        flag.enabled,
+        flag.rollout_percentage,
+        flag.user_ids.len()
+    );
+    Ok(Json(flag.into()))
+}
+
+/// Removes a flag, which turns its feature off.
+pub async fn delete_feature_flag(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(key): Path<String>,
+) -> Result<StatusCode, AppError> {
+    if !FeatureFlagRepo::delete(&state.db, &key).await? {
+        return Err(AppError::NotFound("Feature flag not found".to_string()));
+    }
+    FeatureFlagService::invalidate(&state.redis, &key).await;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Every flag and whether it's on for the signed-in user, so the app can
+/// show or hide features to match.
+pub async fn my_features(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<BTreeMap<String, bool>>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let flags = FeatureFlagRepo::list(state.read_db()).await?;
+    Ok(Json(
+        flags
+            .into_iter()
+            .map(|flag| {
+                let on = in_rollout(&flag, Some(user_id));
+                (flag.key, on)
+            })
+            .collect(),
+    ))
+}
@@ -0,0 +1,176 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::handlers::lender::{
+    ensure_verification_allowance, record_verification, verify_live_proof, VerifyProofResponse,
+};
+use crate::handlers::{AppState, Claims};
+use crate::middleware::lender::LenderAuth;
+use crate::services::webhook::WebhookService;
+
+/// How long a lender has to redeem a handoff token.
+const HANDOFF_TTL_MINUTES: i64 = 30;
+
+#[derive(Deserialize)]
+pub struct CreateHandoffRequest {
+    pub lender_id: String,
+}
+
+#[derive(Serialize)]
+pub struct HandoffResponse {
+    /// Where to send the merchant to continue the application
+    pub application_url: String,
+    pub expires_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct RedeemHandoffRequest {
+    pub token: String,
+}
+
+/// What the intake endpoint must answer with.
+#[derive(Deserialize)]
+struct IntakeResponse {
+    application_url: String,
+}
+
+/// Hands a completed proof to a lender to start a loan application. The
+/// lender's intake endpoint receives a signed `proof.handoff` event with a
+/// one-time token, the verification code and what the proof discloses, and
+/// answers with the URL the merchant continues at.
+pub async fn create_handoff(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(session_id): Path<String>,
+    Json(req): Json<CreateHandoffRequest>,
+) -> Result<Json<HandoffResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let session_id = Uuid::parse_str(&session_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let lender_id = Uuid::parse_str(&req.lender_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let row = sqlx::query(
+        r#"
+        SELECT verification_code, proof_type, credit_score, metrics, score_threshold, meets_threshold, expires_at
+        FROM proof_sessions
+        WHERE id = $1 AND user_id = $2 AND status = 'completed'
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Proof not found or not completed".to_string()))?;
+
+    let verification_code: String = row.try_get(0)?;
+    let proof_type: String = row.try_get(1)?;
+    let credit_score: Option<i32> = row.try_get(2)?;
+    let metrics: Option<serde_json::Value> = row.try_get(3)?;
+    let threshold = crate::handlers::proofs::threshold_result(row.try_get(4)?, row.try_get(5)?);
+    let proof_expires_at: chrono::DateTime<chrono::Utc> = row.try_get(6)?;
+    if proof_expires_at < chrono::Utc::now() {
+        return Err(AppError::ProofExpired);
+    }
+
+    let endpoint = sqlx::query(
+        "SELECT url, secret FROM webhook_endpoints WHERE lender_id = $1 AND purpose = 'intake' AND active",
+    )
+    .bind(lender_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("This lender doesn't accept application handoffs".to_string()))?;
+    let url: String = endpoint.try_get(0)?;
+    let secret: String = endpoint.try_get(1)?;
+
+    let token = generate_token();
+    let expires_at = chrono::Utc::now() + chrono::Duration::minutes(HANDOFF_TTL_MINUTES);
+    let data = serde_json::json!({
+        "handoff_token": token,
+        "token_expires_at": expires_at.to_rfc3339(),
+        "verification_code": verification_code,
+        "proof_type": proof_type,
+        "credit_score": credit_score,
+        "metrics": metrics,
+        "threshold": threshold,
+    });
+    let intake: IntakeResponse = WebhookService::request(&url, &secret, "proof.handoff", data)
+        .await
+        .and_then(|body| Ok(serde_json::from_value(body)?))
+        .map_err(|e| AppError::Internal(e.context(format!("Intake endpoint of lender {} failed", lender_id))))?;
+
+    // The URL is shown to the merchant to open, so it has to be a real link
+    let application_url = reqwest::Url::parse(&intake.application_url)
+        .ok()
+        .filter(|u| u.scheme() == "https" || (state.config.demo_mode && u.scheme() == "http"))
+        .ok_or_else(|| {
+            AppError::Internal(anyhow::anyhow!("Lender {} returned an invalid application URL", lender_id))
+        })?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO proof_handoffs (session_id, lender_id, token_hash, application_url, expires_at)
+        VALUES ($1, $2, $3, $4, $5)
+        "#,
+    )
+    .bind(session_id)
+    .bind(lender_id)
+    .bind(crate::utils::hash_api_key(&token))
+    .bind(application_url.as_str())
+    .bind(expires_at)
+    .execute(&state.db)
+    .await?;
+
+    Ok(Json(HandoffResponse {
+        application_url: application_url.to_string(),
+        expires_at: expires_at.to_rfc3339(),
+    }))
+}
+
+/// Exchanges a handoff token for the verified proof, once. Counts as a
+/// verification like any other.
+pub async fn redeem_handoff(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    Json(req): Json<RedeemHandoffRequest>,
+) -> Result<Json<VerifyProofResponse>, AppError> {
+    if lender.sandbox {
+        return Err(AppError::NotFound("Sandbox keys can't redeem handoffs".to_string()));
+    }
+    // Checked first, so a refused redemption leaves the token usable
+    ensure_verification_allowance(&state, &lender).await?;
+
+    let verification_code: String = sqlx::query_scalar(
+        r#"
+        UPDATE proof_handoffs h
+        SET redeemed_at = NOW()
+        FROM proof_sessions ps
+        WHERE h.token_hash = $1 AND h.lender_id = $2 AND h.redeemed_at IS NULL AND h.expires_at > NOW()
+          AND ps.id = h.session_id
+        RETURNING ps.verification_code
+        "#,
+    )
+    .bind(crate::utils::hash_api_key(&req.token))
+    .bind(lender.lender_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Handoff token is unknown, expired or already used".to_string()))?;
+
+    let result = verify_live_proof(&state, &lender, &verification_code).await;
+    record_verification(&state, &lender, &verification_code, &result).await?;
+
+    result.map(Json)
+}
+
+/// A new handoff token, e.g. `hof_3f9c...`.
+fn generate_token() -> String {
+    use rand::RngCore;
+
+    let mut bytes = [0u8; 32];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    format!("hof_{}", hex::encode(bytes))
+}
@@ -0,0 +1,299 @@
+use axum::{
+    extract::{Multipart, State},
+    Json,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::repos::reviews::NewReview;
+use crate::db::repos::{ReviewRepo, TillRepo};
+use crate::error::AppError;
+use crate::handlers::data::{
+    multipart_error, read_text_field, spool_field, supported_currency, supported_timezone, till_timezone,
+    DirectionCounts, HeldRow, SpooledFile, UploadDataResponse, ValidationReport,
+};
+use crate::handlers::{AppState, Claims};
+use crate::i18n::Message;
+use crate::middleware::locale::current_locale;
+use crate::services::authenticity::{flag_reason, AuthenticityService, StatementLine};
+use crate::services::feature_flags::{self, FeatureFlagService};
+use crate::services::file_scan::FileScanService;
+use crate::services::ocr::{OcrService, StatementRow};
+use crate::services::proof::ProofService;
+use crate::utils::phone_number_hashes;
+
+#[derive(Serialize)]
+pub struct ImageUploadResponse {
+    pub image_id: String,
+    #[serde(flatten)]
+    pub upload: UploadDataResponse,
+}
+
+/// A row OCR read too uncertainly to import.
+struct UnclearRow<'a> {
+    line: usize,
+    text: &'a str,
+    confidence: u8,
+    reason: String,
+    /// What could be read of it
+    row: Option<StatementRow>,
+}
+
+/// Imports an M-Pesa statement from a photo of a printed one. The photo is
+/// stored and read by OCR; rows read with at least `ocr_min_confidence`
+/// are imported and the rest held for review, as are rows failing an
+/// authenticity check.
+pub async fn upload_image(
+    State(state): State<AppState>,
+    claims: Claims,
+    mut multipart: Multipart,
+) -> Result<Json<ImageUploadResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let photos_enabled = FeatureFlagService::is_enabled(
+        &state.db,
+        &state.redis,
+        feature_flags::STATEMENT_PHOTO_OCR,
+        Some(user_id),
+    )
+    .await;
+    let engine = OcrService::engine(&state.config).filter(|_| photos_enabled).ok_or_else(|| {
+        AppError::Validation("Photo uploads are not available; upload the statement as CSV, XLSX or PDF".to_string())
+    })?;
+
+    let mut till_id: Option<Uuid> = None;
+    let mut file: Option<SpooledFile> = None;
+    let mut file_type: Option<String> = None;
+    let mut currency_code = crate::models::DEFAULT_CURRENCY.to_string();
+    let mut timezone: Option<String> = None;
+
+    while let Some(field) = multipart
+        .next_field()
+        .await
+        .map_err(|e| multipart_error(&state.config, e))?
+    {
+        let name = field.name().unwrap_or("").to_string();
+        let content_type = field.content_type().map(|s| s.to_string());
+
+        if name == "till_id" {
+            let value = read_text_field(&state.config, field).await?;
+            till_id = Some(Uuid::parse_str(&value).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?);
+        } else if name == "currency" {
+            currency_code = read_text_field(&state.config, field).await?;
+        } else if name == "timezone" {
+            timezone = Some(read_text_field(&state.config, field).await?);
+        } else if name == "file" {
+            file = Some(spool_field(&state.config, field).await?);
+            file_type = content_type;
+        }
+    }
+
+    let till_id = till_id.ok_or_else(|| AppError::Validation(Message::MissingTillId.render(current_locale())))?;
+    let file = file.ok_or_else(|| AppError::Validation(Message::MissingFile.render(current_locale())))?;
+
+    let till_user_id = TillRepo::owner(&state.db, till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
+    if till_user_id != user_id {
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
+    }
+
+    let currency = supported_currency(&state.db, &currency_code).await?;
+    let timezone = match timezone {
+        Some(name) => supported_timezone(&name)?,
+        None => till_timezone(&state.db, till_id).await?,
+    };
+
+    let image = tokio::fs::read(&file.path)
+        .await
+        .map_err(|e| AppError::Internal(e.into()))?;
+    let image_type = FileScanService::inspect_image(&state.config, file_type.as_deref(), &image)
+        .await
+        .map_err(AppError::FileRejected)?;
+
+    let image_id = Uuid::new_v4();
+    let storage_key = format!("statement-images/{}/{}.{}", till_id, image_id, image_type.extension());
+    state.storage.upload(&storage_key, &image).await?;
+
+    // A photo that couldn't be read has nothing to show for it in storage
+    let lines = match engine.recognize(&image).await {
+        Ok(lines) => lines,
+        Err(e) => {
+            if let Err(delete_error) = state.storage.delete(&storage_key).await {
+                tracing::warn!("Failed to delete unread photo {}: {}", storage_key, delete_error);
+            }
+            return Err(e.into());
+        }
+    };
+
+    // Lines that don't start with a receipt number are headers and footers
+    let exponent = currency.minor_unit_exponent.max(0) as u32;
+    let mut validation = ValidationReport::default();
+    let mut accepted: Vec<(usize, StatementRow)> = Vec::new();
+    let mut unclear = Vec::new();
+    for (i, line) in lines.iter().enumerate() {
+        let Some(parsed) = OcrService::parse_row(&line.text, exponent, timezone) else {
+            continue;
+        };
+        validation.rows_read += 1;
+        let (reason, row) = match parsed {
+            Ok(row) if line.confidence >= state.config.ocr_min_confidence => {
+                accepted.push((i + 1, row));
+                continue;
+            }
+            Ok(row) => (format!("Read with {}% confidence", line.confidence), Some(row)),
+            Err(reason) => (reason, None),
+        };
+        unclear.push(UnclearRow {
+            line: i + 1,
+            text: &line.text,
+            confidence: line.confidence,
+            reason,
+            row,
+        });
+    }
+
+    let statement_lines: Vec<StatementLine> = accepted
+        .iter()
+        .map(|(line, row)| StatementLine {
+            row: *line,
+            timestamp: row.timestamp,
+            amount: row.amount,
+            reference: &row.receipt,
+            balance: row.balance,
+            fee: 0,
+        })
+        .collect();
+    let authenticity = AuthenticityService::assess(&state.db, till_id, "mpesa", &statement_lines).await?;
+
+    let mut imported = 0;
+    let mut directions = DirectionCounts::default();
+    let mut flagged = Vec::new();
+    for (line, row) in &accepted {
+        if let Some(&check) = authenticity.flagged.get(line) {
+            flagged.push((*line, row, check));
+            continue;
+        }
+        let references = phone_number_hashes(&row.receipt);
+        let direction: Option<String> = sqlx::query_scalar(
+            r#"
+            INSERT INTO transactions (till_id, timestamp, amount, transaction_type, reference, currency, provenance)
+            SELECT $1, $2, $3, $4, $5, $6, 'image'
+            WHERE NOT EXISTS (SELECT 1 FROM transaction_receipts r WHERE r.till_id = $1 AND r.reference = ANY($7))
+            RETURNING direction
+            "#,
+        )
+        .bind(till_id)
+        .bind(row.timestamp)
+        .bind(row.amount)
+        .bind(&row.details)
+        .bind(&references[0])
+        .bind(&currency.code)
+        .bind(&references)
+        .fetch_optional(&state.db)
+        .await?;
+
+        if let Some(direction) = direction {
+            imported += 1;
+            directions.add(&direction);
+        }
+    }
+
+    let statement_import_id = AuthenticityService::record(
+        &state.db,
+        till_id,
+        "mpesa",
+        proof_core::Provenance::ImageUpload,
+        imported,
+        &authenticity,
+    )
+    .await?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO statement_images (id, till_id, storage_key, content_type, ocr_engine, rows_read, rows_imported)
+        VALUES ($1, $2, $3, $4, $5, $6, $7)
+        "#,
+    )
+    .bind(image_id)
+    .bind(till_id)
+    .bind(&storage_key)
+    .bind(image_type.content_type())
+    .bind(engine.name())
+    .bind(validation.rows_read as i32)
+    .bind(imported as i32)
+    .execute(&state.db)
+    .await?;
+
+    // Held once the image they point to is stored
+    let mut held_for_review = Vec::new();
+    for held in &unclear {
+        let row = held.row.as_ref();
+        let review = NewReview {
+            till_id,
+            image_id: Some(image_id),
+            statement_import_id: Some(statement_import_id),
+            flagged_by: "ocr",
+            reason: &held.reason,
+            row_number: held.line,
+            raw_text: Some(held.text),
+            confidence: Some(held.confidence),
+            receipt_number: row.map(|r| r.receipt.as_str()),
+            timestamp: row.map(|r| r.timestamp),
+            amount: row.map(|r| r.amount),
+            transaction_type: row.map(|r| r.details.as_str()),
+            account_number: None,
+            source: "mpesa",
+            currency: &currency.code,
+            provenance: proof_core::Provenance::ImageUpload,
+        };
+        held_for_review.push(HeldRow {
+            review_id: ReviewRepo::hold(&state.db, &review).await?.to_string(),
+            row: held.line,
+            flagged_by: "ocr".to_string(),
+            reason: held.reason.clone(),
+        });
+    }
+    for (line, row, check) in flagged {
+        let ocr_line = &lines[line - 1];
+        let review = NewReview {
+            till_id,
+            image_id: Some(image_id),
+            statement_import_id: Some(statement_import_id),
+            flagged_by: check,
+            reason: flag_reason(check),
+            row_number: line,
+            raw_text: Some(ocr_line.text.as_str()),
+            confidence: Some(ocr_line.confidence),
+            receipt_number: Some(row.receipt.as_str()),
+            timestamp: Some(row.timestamp),
+            amount: Some(row.amount),
+            transaction_type: Some(row.details.as_str()),
+            account_number: None,
+            source: "mpesa",
+            currency: &currency.code,
+            provenance: proof_core::Provenance::ImageUpload,
+        };
+        held_for_review.push(HeldRow {
+            review_id: ReviewRepo::hold(&state.db, &review).await?.to_string(),
+            row: line,
+            flagged_by: check.to_string(),
+            reason: flag_reason(check).to_string(),
+        });
+    }
+    held_for_review.sort_by_key(|held| held.row);
+
+    let completeness = ProofService::refresh_completeness(&state.db, till_id).await?;
+
+    Ok(Json(ImageUploadResponse {
+        image_id: image_id.to_string(),
+        upload: UploadDataResponse {
+            message: "Photo read successfully".to_string(),
+            transactions_imported: imported,
+            validation,
+            directions,
+            completeness: completeness.into(),
+            authenticity: Some(authenticity),
+            held_for_review,
+        },
+    }))
+}
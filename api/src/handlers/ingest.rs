@@ -0,0 +1,579 @@
+use std::collections::HashSet;
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use futures::TryStreamExt;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+use tokio_util::io::StreamReader;
+use uuid::Uuid;
+
+use crate::db::repos::import_jobs::{ImportJob, NewImportJob};
+use crate::db::repos::{ImportJobRepo, OutboxRepo, TillRepo};
+use crate::error::AppError;
+use crate::handlers::data::{
+    parse_date, supported_currency, till_timezone, CompletenessReport, DirectionCounts, RowIssue, ValidationReport,
+    MAX_REPORTED_ISSUES,
+};
+use crate::handlers::{AppState, Claims};
+use crate::i18n::Message;
+use crate::middleware::locale::current_locale;
+use crate::models::Currency;
+use crate::services::money::parse_money;
+use crate::services::proof::ProofService;
+use crate::utils::phone_number_hashes;
+
+/// Transactions one batch may carry.
+pub const MAX_BATCH_ROWS: usize = 10_000;
+/// Also the body limit of the batch route, whatever `max_body_bytes` is.
+pub const MAX_BATCH_BYTES: usize = 8 * 1024 * 1024;
+/// Larger batches are queued for a worker as an import job.
+const INLINE_BATCH_ROWS: usize = 1_000;
+/// Rows written per insert statement.
+const INSERT_CHUNK_ROWS: usize = 1_000;
+/// A streamed line longer than this ends the stream.
+const MAX_STREAM_LINE_BYTES: usize = 64 * 1024;
+/// Rows a quiet stream has buffered are committed after this long.
+const STREAM_FLUSH_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
+/// Time left to answer before the request timeout cuts a stream off.
+const STREAM_DEADLINE_MARGIN: std::time::Duration = std::time::Duration::from_secs(5);
+const MAX_RECEIPT_LEN: usize = 32;
+const MAX_TRANSACTION_TYPE_LEN: usize = 50;
+const MAX_ACCOUNT_NUMBER_LEN: usize = 64;
+
+#[derive(Deserialize)]
+pub struct BatchQuery {
+    pub till_id: String,
+    /// "mpesa" (default) or "bank"
+    pub source: Option<String>,
+    /// ISO 4217 code of the amounts; KES when unset
+    pub currency: Option<String>,
+}
+
+/// A JSON batch. NDJSON batches send one transaction per line instead.
+#[derive(Deserialize)]
+struct BatchBody {
+    transactions: Vec<serde_json::Value>,
+}
+
+/// One transaction as a POS or partner system reports it.
+#[derive(Deserialize, Serialize)]
+pub struct BatchTransaction {
+    /// M-Pesa receipt number, e.g. "SBK2XY3Z9Q". One the till already has
+    /// is skipped, so a batch can safely be resent.
+    pub receipt_number: String,
+    /// RFC 3339, or a wall-clock time in the till's timezone
+    pub timestamp: String,
+    /// Decimal amount in major units, as a string or number. Negative when
+    /// money went out.
+    pub amount: serde_json::Value,
+    /// "Payment" when unset
+    pub transaction_type: Option<String>,
+    /// PayBill account number the payment was made against
+    pub account_number: Option<String>,
+}
+
+/// A validated transaction ready to insert.
+pub(crate) struct ImportRow {
+    timestamp: chrono::DateTime<chrono::Utc>,
+    amount: i64,
+    transaction_type: String,
+    /// Hashed receipt number
+    reference: String,
+    /// The receipt's hash under older peppers, the form rows the rehash job
+    /// hasn't reached yet are stored in
+    older_references: Vec<String>,
+    account_number: Option<String>,
+}
+
+/// The till an import writes to and how its rows are read.
+struct ImportTarget {
+    till_id: Uuid,
+    source: String,
+    currency: Currency,
+    timezone: chrono_tz::Tz,
+}
+
+#[derive(Serialize)]
+pub struct BatchImportSummary {
+    pub rows_received: usize,
+    pub transactions_imported: usize,
+    /// Receipts the till already had, or that appeared earlier in the batch
+    pub duplicates_skipped: usize,
+    pub validation: ValidationReport,
+    pub directions: DirectionCounts,
+    /// Coverage of the till's whole scoring window after this import
+    pub completeness: CompletenessReport,
+}
+
+#[derive(Serialize)]
+pub struct StreamImportSummary {
+    #[serde(flatten)]
+    pub summary: BatchImportSummary,
+    /// Why reading stopped before the end of the stream. Rows before that
+    /// point are imported; resend the rest.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stopped_early: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ImportJobResponse {
+    pub job_id: String,
+    /// "pending", "running", "completed" or "failed"
+    pub status: String,
+    pub rows_received: i32,
+    /// The batch's summary, once completed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub result: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    pub created_at: String,
+    pub finished_at: Option<String>,
+}
+
+/// Imports up to 10,000 structured transactions for a till, for POS and
+/// partner systems pushing on a merchant's behalf. The body is JSON,
+/// `{"transactions": [...]}`, or NDJSON with `Content-Type:
+/// application/x-ndjson`. Rows that don't validate are reported, not
+/// fatal. Batches over 1,000 rows are imported in the background: the
+/// response is 202 with a job to poll at `/api/data/import-jobs/:job_id`.
+pub async fn import_batch(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<BatchQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let target = import_target(&state, user_id, query).await?;
+
+    let entries = parse_body(&headers, &body)?;
+    if entries.len() > MAX_BATCH_ROWS {
+        return Err(AppError::Validation(format!(
+            "At most {} transactions can be sent in one batch",
+            MAX_BATCH_ROWS
+        )));
+    }
+
+    if entries.len() <= INLINE_BATCH_ROWS {
+        let summary = import_entries(&state.db, &target, entries).await?;
+        return Ok(Json(summary).into_response());
+    }
+
+    // The job is queued in the same transaction, so a restart can't lose it
+    let rows_received = entries.len() as i32;
+    let entries = serde_json::to_value(&entries).map_err(|e| AppError::Internal(e.into()))?;
+    let mut tx = state.db.begin().await?;
+    let job = NewImportJob {
+        user_id,
+        till_id: target.till_id,
+        source: &target.source,
+        currency: &target.currency.code,
+        rows_received,
+        entries: &entries,
+    };
+    let (job_id, created_at) = ImportJobRepo::create(&mut *tx, &job, crate::worker::IMPORT_LEASE_SECS).await?;
+    OutboxRepo::add(&mut *tx, crate::worker::IMPORT_QUEUE_KEY, &job_id.to_string()).await?;
+    tx.commit().await?;
+
+    let job = ImportJobResponse {
+        job_id: job_id.to_string(),
+        status: "pending".to_string(),
+        rows_received,
+        result: None,
+        error: None,
+        created_at: created_at.to_rfc3339(),
+        finished_at: None,
+    };
+    Ok((StatusCode::ACCEPTED, Json(job)).into_response())
+}
+
+/// Imports a continuous NDJSON feed of transactions, one per line, as it
+/// arrives rather than buffering the body. Rows are inserted a chunk at a
+/// time, and the body isn't read while a chunk is being written, so a fast
+/// sender is slowed to what the database keeps up with. Rows a quiet feed
+/// has buffered are committed after a couple of seconds. The response is
+/// the summary of the whole stream, sent once the body ends or the request
+/// is about to time out.
+pub async fn stream_transactions(
+    State(state): State<AppState>,
+    claims: Claims,
+    Query(query): Query<BatchQuery>,
+    body: Body,
+) -> Result<Json<StreamImportSummary>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let target = import_target(&state, user_id, query).await?;
+
+    let deadline = tokio::time::Instant::now()
+        + std::time::Duration::from_secs(state.config.upload_timeout_secs).saturating_sub(STREAM_DEADLINE_MARGIN);
+    let mut reader = StreamReader::new(body.into_data_stream().map_err(std::io::Error::other));
+    let mut importer = Importer::new(&state.db, &target);
+    let mut line = Vec::new();
+    let mut stopped_early = None;
+
+    loop {
+        if tokio::time::Instant::now() >= deadline {
+            stopped_early = Some("The request timeout was reached".to_string());
+            break;
+        }
+
+        // A timed-out read keeps what it had read in `line`
+        let limit = (MAX_STREAM_LINE_BYTES + 1).saturating_sub(line.len()) as u64;
+        let mut limited = (&mut reader).take(limit);
+        match tokio::time::timeout(STREAM_FLUSH_INTERVAL, limited.read_until(b'\n', &mut line)).await {
+            Err(_) => {
+                importer.flush().await?;
+                continue;
+            }
+            Ok(Err(e)) => {
+                stopped_early = Some(format!("Reading the stream failed: {}", e));
+                break;
+            }
+            Ok(Ok(_)) => {}
+        }
+
+        if line.len() > MAX_STREAM_LINE_BYTES {
+            stopped_early = Some(format!("A line is longer than {} bytes", MAX_STREAM_LINE_BYTES));
+            break;
+        }
+        // Without a trailing newline the body has ended
+        let finished = line.last() != Some(&b'\n');
+        if !line.iter().all(u8::is_ascii_whitespace) {
+            importer.push(serde_json::from_slice(&line).map_err(|e| e.to_string()));
+            if importer.is_full() {
+                importer.flush().await?;
+            }
+        }
+        line.clear();
+        if finished {
+            break;
+        }
+    }
+
+    Ok(Json(StreamImportSummary {
+        summary: importer.finish().await?,
+        stopped_early,
+    }))
+}
+
+pub async fn get_import_job(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(job_id): Path<String>,
+) -> Result<Json<ImportJobResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let job_id = Uuid::parse_str(&job_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let row = sqlx::query(
+        r#"
+        SELECT status, rows_received, result, error, created_at, finished_at
+        FROM import_jobs
+        WHERE id = $1 AND user_id = $2
+        "#,
+    )
+    .bind(job_id)
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Import job not found".to_string()))?;
+
+    Ok(Json(ImportJobResponse {
+        job_id: job_id.to_string(),
+        status: row.try_get(0)?,
+        rows_received: row.try_get(1)?,
+        result: row.try_get(2)?,
+        error: row.try_get(3)?,
+        created_at: row.try_get::<chrono::DateTime<chrono::Utc>, _>(4)?.to_rfc3339(),
+        finished_at: row
+            .try_get::<Option<chrono::DateTime<chrono::Utc>>, _>(5)?
+            .map(|t| t.to_rfc3339()),
+    }))
+}
+
+/// Checks the caller owns the till and resolves what its rows are read as.
+async fn import_target(state: &AppState, user_id: Uuid, query: BatchQuery) -> Result<ImportTarget, AppError> {
+    let till_id = Uuid::parse_str(&query.till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let till_user_id = TillRepo::owner(&state.db, till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
+    if till_user_id != user_id {
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
+    }
+
+    let source = query.source.unwrap_or_else(|| "mpesa".to_string());
+    if !crate::models::TRANSACTION_SOURCES.contains(&source.as_str()) {
+        return Err(AppError::Validation(format!("Unknown source: {}", source)));
+    }
+    let currency_code = query.currency.as_deref().unwrap_or(crate::models::DEFAULT_CURRENCY);
+
+    Ok(ImportTarget {
+        till_id,
+        source,
+        currency: supported_currency(&state.db, currency_code).await?,
+        timezone: till_timezone(&state.db, till_id).await?,
+    })
+}
+
+/// Splits a JSON or NDJSON body into transactions, each parsed on its own
+/// so one malformed entry doesn't sink the batch.
+fn parse_body(headers: &HeaderMap, body: &[u8]) -> Result<Vec<Result<BatchTransaction, String>>, AppError> {
+    let content_type = headers
+        .get(header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .unwrap_or("application/json");
+
+    if is_ndjson(content_type) {
+        let text = std::str::from_utf8(body).map_err(|_| AppError::Validation("Body is not UTF-8".to_string()))?;
+        return Ok(text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| serde_json::from_str(line).map_err(|e| e.to_string()))
+            .collect());
+    }
+
+    let batch: BatchBody =
+        serde_json::from_slice(body).map_err(|e| AppError::Validation(format!("Invalid batch: {}", e)))?;
+    Ok(batch
+        .transactions
+        .into_iter()
+        .map(|value| serde_json::from_value(value).map_err(|e| e.to_string()))
+        .collect())
+}
+
+pub(crate) fn is_ndjson(content_type: &str) -> bool {
+    let mime = content_type.split(';').next().unwrap_or("").trim();
+    ["application/x-ndjson", "application/ndjson", "application/jsonl"].contains(&mime)
+}
+
+/// Validates and imports a parsed batch, then refreshes the till's
+/// completeness.
+async fn import_entries(
+    db: &PgPool,
+    target: &ImportTarget,
+    entries: Vec<Result<BatchTransaction, String>>,
+) -> Result<BatchImportSummary, AppError> {
+    let mut importer = Importer::new(db, target);
+    for entry in entries {
+        importer.push(entry);
+        if importer.is_full() {
+            importer.flush().await?;
+        }
+    }
+    importer.finish().await
+}
+
+/// Imports a queued batch for the worker, as the request would have.
+pub(crate) async fn run_import_job(db: &PgPool, job: ImportJob) -> Result<BatchImportSummary, AppError> {
+    let entries: Vec<Result<BatchTransaction, String>> =
+        serde_json::from_value(job.entries).map_err(|e| AppError::Internal(e.into()))?;
+    let target = ImportTarget {
+        till_id: job.till_id,
+        source: job.source,
+        currency: supported_currency(db, &job.currency).await?,
+        timezone: till_timezone(db, job.till_id).await?,
+    };
+    import_entries(db, &target, entries).await
+}
+
+/// Validates transactions as they arrive and inserts them a chunk at a
+/// time, keeping the running totals of a summary.
+struct Importer<'a> {
+    db: &'a PgPool,
+    target: &'a ImportTarget,
+    pending: Vec<ImportRow>,
+    /// Receipts in `pending`; earlier chunks are already in the table
+    pending_references: HashSet<String>,
+    rows_received: usize,
+    imported: usize,
+    duplicates_skipped: usize,
+    validation: ValidationReport,
+    directions: DirectionCounts,
+}
+
+impl<'a> Importer<'a> {
+    fn new(db: &'a PgPool, target: &'a ImportTarget) -> Self {
+        Self {
+            db,
+            target,
+            pending: Vec::new(),
+            pending_references: HashSet::new(),
+            rows_received: 0,
+            imported: 0,
+            duplicates_skipped: 0,
+            validation: ValidationReport::default(),
+            directions: DirectionCounts::default(),
+        }
+    }
+
+    fn push(&mut self, entry: Result<BatchTransaction, String>) {
+        self.rows_received += 1;
+        self.validation.rows_read += 1;
+        match entry.and_then(|tx| validate_row(tx, &self.target.currency, self.target.timezone)) {
+            Ok(row) if !self.pending_references.insert(row.reference.clone()) => self.duplicates_skipped += 1,
+            Ok(row) => self.pending.push(row),
+            Err(reason) => {
+                self.validation.rows_rejected += 1;
+                if self.validation.issues.len() < MAX_REPORTED_ISSUES {
+                    self.validation.issues.push(RowIssue {
+                        row: self.rows_received,
+                        reason,
+                    });
+                }
+            }
+        }
+    }
+
+    fn is_full(&self) -> bool {
+        self.pending.len() >= INSERT_CHUNK_ROWS
+    }
+
+    /// Inserts the pending rows. They're committed once this returns.
+    async fn flush(&mut self) -> Result<(), AppError> {
+        if self.pending.is_empty() {
+            return Ok(());
+        }
+        let target = self.target;
+        let inserted =
+            insert_rows(self.db, target.till_id, &target.source, &target.currency.code, &self.pending).await?;
+        self.duplicates_skipped += self.pending.len() - inserted.len();
+        self.imported += inserted.len();
+        for direction in &inserted {
+            self.directions.add(direction);
+        }
+        self.pending.clear();
+        self.pending_references.clear();
+        Ok(())
+    }
+
+    async fn finish(mut self) -> Result<BatchImportSummary, AppError> {
+        self.flush().await?;
+        let completeness = ProofService::refresh_completeness(self.db, self.target.till_id).await?;
+
+        Ok(BatchImportSummary {
+            rows_received: self.rows_received,
+            transactions_imported: self.imported,
+            duplicates_skipped: self.duplicates_skipped,
+            validation: self.validation,
+            directions: self.directions,
+            completeness: completeness.into(),
+        })
+    }
+}
+
+/// Checks one transaction and converts it to stored form: amounts in
+/// `currency`'s minor units, the receipt number hashed.
+pub(crate) fn validate_row(
+    tx: BatchTransaction,
+    currency: &Currency,
+    timezone: chrono_tz::Tz,
+) -> Result<ImportRow, String> {
+    let receipt = tx.receipt_number.trim().to_ascii_uppercase();
+    if receipt.is_empty() || receipt.len() > MAX_RECEIPT_LEN || !receipt.bytes().all(|b| b.is_ascii_alphanumeric()) {
+        return Err(format!("Invalid receipt number: {}", tx.receipt_number));
+    }
+
+    let timestamp = parse_date(tx.timestamp.trim(), timezone).map_err(|e| e.to_string())?;
+
+    let amount = match &tx.amount {
+        serde_json::Value::String(text) => text.clone(),
+        serde_json::Value::Number(number) => number.to_string(),
+        _ => return Err("amount must be a string or number".to_string()),
+    };
+    let money = parse_money(&amount, currency.minor_unit_exponent.max(0) as u32)?;
+    if let Some(amount_currency) = money.currency.filter(|code| *code != currency.code) {
+        return Err(format!(
+            "Amount in {} doesn't match the batch's currency {}",
+            amount_currency, currency.code
+        ));
+    }
+    if money.minor_units == 0 {
+        return Err("amount must not be zero".to_string());
+    }
+
+    let transaction_type = tx
+        .transaction_type
+        .map(|t| t.trim().to_string())
+        .filter(|t| !t.is_empty())
+        .unwrap_or_else(|| "Payment".to_string());
+    if transaction_type.len() > MAX_TRANSACTION_TYPE_LEN {
+        return Err(format!("transaction_type is longer than {} characters", MAX_TRANSACTION_TYPE_LEN));
+    }
+    let account_number = tx.account_number.map(|a| a.trim().to_string()).filter(|a| !a.is_empty());
+    if account_number.as_ref().is_some_and(|a| a.len() > MAX_ACCOUNT_NUMBER_LEN) {
+        return Err(format!("account_number is longer than {} characters", MAX_ACCOUNT_NUMBER_LEN));
+    }
+
+    let mut older_references = phone_number_hashes(&receipt);
+    let reference = older_references.remove(0);
+    Ok(ImportRow {
+        timestamp,
+        amount: money.minor_units,
+        transaction_type,
+        reference,
+        older_references,
+        account_number,
+    })
+}
+
+/// Inserts rows in one statement. The insert triggers skip receipts the
+/// till already has, whatever their timestamp, and classify each row's kind
+/// and direction; the directions of the rows inserted are returned.
+pub(crate) async fn insert_rows(
+    db: &PgPool,
+    till_id: Uuid,
+    source: &str,
+    currency: &str,
+    rows: &[ImportRow],
+) -> Result<Vec<String>, sqlx::Error> {
+    // The insert trigger only catches receipts stored in their current hash
+    let older: Vec<&str> = rows
+        .iter()
+        .flat_map(|r| r.older_references.iter().map(String::as_str))
+        .collect();
+    let stale: HashSet<String> =
+        sqlx::query_scalar("SELECT reference FROM transaction_receipts WHERE till_id = $1 AND reference = ANY($2)")
+            .bind(till_id)
+            .bind(&older)
+            .fetch_all(db)
+            .await?
+            .into_iter()
+            .collect();
+    let rows: Vec<&ImportRow> = rows
+        .iter()
+        .filter(|r| !r.older_references.iter().any(|h| stale.contains(h)))
+        .collect();
+
+    let timestamps: Vec<_> = rows.iter().map(|r| r.timestamp).collect();
+    let amounts: Vec<i64> = rows.iter().map(|r| r.amount).collect();
+    let types: Vec<&str> = rows.iter().map(|r| r.transaction_type.as_str()).collect();
+    let references: Vec<&str> = rows.iter().map(|r| r.reference.as_str()).collect();
+    let accounts: Vec<Option<&str>> = rows.iter().map(|r| r.account_number.as_deref()).collect();
+
+    sqlx::query_scalar(
+        r#"
+        INSERT INTO transactions (till_id, timestamp, amount, transaction_type, reference, source, account_number,
+                                  currency, provenance)
+        SELECT $1, b.timestamp, b.amount, b.transaction_type, b.reference, $7, b.account_number, $8, 'partner'
+        FROM UNNEST($2::timestamptz[], $3::bigint[], $4::text[], $5::text[], $6::text[])
+            AS b(timestamp, amount, transaction_type, reference, account_number)
+        RETURNING direction
+        "#,
+    )
+    .bind(till_id)
+    .bind(timestamps)
+    .bind(amounts)
+    .bind(types)
+    .bind(references)
+    .bind(accounts)
+    .bind(source)
+    .bind(currency)
+    .fetch_all(db)
+    .await
+}
@@ -1,66 +1,610 @@
-use axum::{extract::State, Json};
+use axum::{
+    body::Body,
+    extract::{Path, Query, State},
+    http::header,
+    response::Response,
+    Json,
+};
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use sqlx::Row;
-use uuid::Uuid;
 
 use crate::error::AppError;
 use crate::handlers::AppState;
+use crate::middleware::lender::LenderAuth;
+use crate::services::billing::BillingService;
+use crate::services::notification::{LenderNotification, NotificationService};
+use crate::services::sandbox::{SandboxOutcome, SandboxService};
+use crate::services::webhook::WebhookService;
+
+/// Proofs this close to expiry are flagged, matching when owners get their
+/// first reminder.
+const EXPIRES_SOON_DAYS: i64 = 30;
 
 #[derive(Deserialize)]
 pub struct VerifyProofRequest {
+    /// Verification code, or a six-digit short code minted for it
     pub proof_id: String,
 }
 
 #[derive(Serialize)]
 pub struct VerifyProofResponse {
     pub valid: bool,
-    pub credit_score: i32,
+    /// "full", or "threshold" when only `threshold` is disclosed
+    pub proof_type: String,
+    pub credit_score: Option<i32>,
     pub metrics: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<crate::models::ThresholdResult>,
+    /// The owner requires consent before this lender sees the score,
+    /// metrics and threshold outcome and hasn't given it, so they're
+    /// withheld. Ask at `POST /api/lender/proofs/:code/consent`.
+    pub consent_required: bool,
+    /// Business sector, for comparing metrics against sector peers
+    pub sector: Option<String>,
+    /// Disputed volume on the till crossed the contest threshold after this
+    /// proof was generated
+    pub contested: bool,
+    /// The proof expires within 30 days; the owner has been reminded to
+    /// generate a new one
+    pub expires_soon: bool,
+    /// SHA-256 of the PayBill account number a branch-level proof covers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_hash: Option<String>,
+    /// Hash of the scoring policy committed in the receipt's journal; unset
+    /// on proofs generated before policies were committed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_hash: Option<String>,
+    /// Guest build and prover the receipt was produced with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prover: Option<crate::models::ProverInfo>,
+    /// "transactions" when the guest scored every transaction, or
+    /// "daily_totals" when it scored daily totals computed by the host
+    pub input_mode: String,
+    /// For daily-totals proofs, the committed hash of the statement rows
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rows_commitment: Option<String>,
+    /// Whether the till had gone quiet before the proof was generated; the
+    /// credit score already carries the decay
+    pub staleness: Option<crate::models::Staleness>,
+    /// Percent of the proof's weeks with at least one payment; unset on
+    /// proofs generated before coverage was committed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub coverage_percentage: Option<i16>,
+    /// Whether the proof meets the template it was generated for, when that
+    /// is one of this lender's templates
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<crate::handlers::templates::TemplateCompliance>,
+    /// How the scored volume reached the platform; unset on proofs
+    /// generated before provenance was committed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provenance: Option<ProvenanceSummary>,
+    /// `credit_score` with self-reported volume counted at this lender's
+    /// `self_reported_weight_percentage`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub weighted_credit_score: Option<i32>,
+    /// "all", or "verified_api" when the proof scored only transactions from
+    /// C2B callbacks and Daraja pulls
+    pub source: String,
+    /// "high", "medium" or "low": lowered when an uploaded statement the
+    /// proof scored looked edited; unset on proofs from before uploads were
+    /// checked
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub trust_tier: Option<String>,
+    /// Lowest authenticity score (0-100) among the uploads the proof scored
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authenticity_score: Option<i16>,
     pub generated_at: String,
 }
 
+#[derive(Serialize)]
+pub struct ProvenanceSummary {
+    /// Percent of scored volume Safaricom delivered by C2B callback or
+    /// Daraja pull, rather than the merchant uploading it
+    pub api_sourced_percentage: i16,
+    /// Percent of scored volume by provenance: "csv", "pdf", "sms",
+    /// "partner", "c2b" or "daraja"
+    pub mix: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+pub struct LenderSettingsRequest {
+    pub self_reported_weight_percentage: u8,
+}
+
+#[derive(Serialize)]
+pub struct LenderSettingsResponse {
+    /// How much self-reported volume (uploads, parsed SMS and partner
+    /// pushes) counts towards `weighted_credit_score`, as a percentage
+    pub self_reported_weight_percentage: i16,
+}
+
 pub async fn verify_proof(
     State(state): State<AppState>,
+    lender: LenderAuth,
     Json(req): Json<VerifyProofRequest>,
 ) -> Result<Json<VerifyProofResponse>, AppError> {
-    let proof_id = Uuid::parse_str(&req.proof_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    if lender.sandbox {
+        return verify_sandbox_proof(&req.proof_id).map(Json);
+    }
+    ensure_verification_allowance(&state, &lender).await?;
+
+    // A short code read out over the phone stands in for the verification code
+    let proof_id = if crate::handlers::short_codes::is_short_code(&req.proof_id) {
+        let requester = format!("lender:{}", lender.lender_id);
+        crate::handlers::short_codes::resolve(&state, &requester, &req.proof_id).await?
+    } else {
+        crate::utils::normalize_verification_code(&req.proof_id)
+    };
 
+    let result = verify_live_proof(&state, &lender, &proof_id).await;
+    record_verification(&state, &lender, &proof_id, &result).await?;
+
+    result.map(Json)
+}
+
+/// Refuses a live verification once the lender's plan has none left this
+/// month.
+pub(crate) async fn ensure_verification_allowance(state: &AppState, lender: &LenderAuth) -> Result<(), AppError> {
+    BillingService::verification_usage(&state.db, lender.lender_id)
+        .await?
+        .ensure_allowance()
+        .map_err(AppError::PaymentRequired)
+}
+
+pub(crate) async fn verify_live_proof(
+    state: &AppState,
+    lender: &LenderAuth,
+    proof_id: &str,
+) -> Result<VerifyProofResponse, AppError> {
     let row = sqlx::query(
         r#"
-        SELECT credit_score, metrics, receipt_data, created_at
-        FROM proof_sessions
-        WHERE verification_code = $1 AND status = 'completed'
+        SELECT ps.credit_score, ps.metrics, ps.receipt_data, ps.created_at, ps.expires_at, bp.sector,
+               ps.receipt_key, ps.receipt_sha256, ps.proof_type, ps.score_threshold, ps.meets_threshold,
+               ps.contested_at IS NOT NULL, ps.account_hash, ps.policy_hash, ps.image_id,
+               COALESCE(ps.proving_finished_at, ps.created_at), ps.guest_version, ps.prover_backend,
+               ps.input_mode, ps.rows_commitment, ps.days_since_last_transaction, ps.recency_factor_percentage,
+               ps.template_id, ps.disclosure_policy, ps.period_start, ps.period_end, ps.coverage_percentage, ps.id,
+               ps.api_sourced_percentage, ps.provenance_mix,
+               (SELECT self_reported_weight_percentage FROM lenders WHERE id = $2), ps.data_source,
+               ps.trust_tier, ps.authenticity_score
+        FROM proof_sessions ps
+        LEFT JOIN business_profiles bp ON bp.user_id = ps.user_id
+        WHERE ps.verification_code = $1 AND ps.status = 'completed'
         "#,
     )
-    .bind(&req.proof_id)
-    .fetch_optional(&state.db)
+    .bind(proof_id)
+    .bind(lender.lender_id)
+    .fetch_optional(state.read_db())
     .await?;
 
-    let row = row.ok_or_else(|| AppError::NotFound("Proof not found".to_string()))?;
+    let row = row.ok_or(AppError::ProofNotFound)?;
 
-    let credit_score: Option<i32> = row.try_get(0).ok();
-    let metrics: Option<serde_json::Value> = row.try_get(1).ok();
-    let receipt_data: Option<Vec<u8>> = row.try_get(2).ok();
+    let credit_score: Option<Option<i32>> = row.try_get(0).ok();
+    let metrics: Option<Option<serde_json::Value>> = row.try_get(1).ok();
+    let receipt_data: Option<Option<Vec<u8>>> = row.try_get(2).ok();
     let created_at: chrono::DateTime<chrono::Utc> = row.try_get(3).map_err(|e| AppError::Database(e))?;
+    let expires_at: chrono::DateTime<chrono::Utc> = row.try_get(4).map_err(|e| AppError::Database(e))?;
+    let sector: Option<String> = row.try_get(5).map_err(|e| AppError::Database(e))?;
+    let receipt_key: Option<String> = row.try_get(6).map_err(|e| AppError::Database(e))?;
+    let receipt_sha256: Option<String> = row.try_get(7).map_err(|e| AppError::Database(e))?;
+    let proof_type: String = row.try_get(8).map_err(|e| AppError::Database(e))?;
+    let threshold = crate::handlers::proofs::threshold_result(
+        row.try_get(9).map_err(|e| AppError::Database(e))?,
+        row.try_get(10).map_err(|e| AppError::Database(e))?,
+    );
+    let contested: bool = row.try_get(11).map_err(|e| AppError::Database(e))?;
+    let account_hash: Option<String> = row.try_get(12).map_err(|e| AppError::Database(e))?;
+    let policy_hash: Option<String> = row.try_get(13).map_err(|e| AppError::Database(e))?;
+    let image_id: Option<String> = row.try_get(14).map_err(|e| AppError::Database(e))?;
+    let proved_at: chrono::DateTime<chrono::Utc> = row.try_get(15).map_err(|e| AppError::Database(e))?;
+    let guest_version: Option<String> = row.try_get(16).map_err(|e| AppError::Database(e))?;
+    let prover_backend: Option<String> = row.try_get(17).map_err(|e| AppError::Database(e))?;
+    let input_mode: String = row.try_get(18).map_err(|e| AppError::Database(e))?;
+    let rows_commitment: Option<String> = row.try_get(19).map_err(|e| AppError::Database(e))?;
+    let staleness = crate::models::Staleness::from_columns(
+        row.try_get(20).map_err(|e| AppError::Database(e))?,
+        row.try_get(21).map_err(|e| AppError::Database(e))?,
+    );
+    let template_id: Option<uuid::Uuid> = row.try_get(22).map_err(|e| AppError::Database(e))?;
+    let disclosure: serde_json::Value = row.try_get(23).map_err(|e| AppError::Database(e))?;
+    let period_start: Option<chrono::DateTime<chrono::Utc>> = row.try_get(24).map_err(|e| AppError::Database(e))?;
+    let period_end: Option<chrono::DateTime<chrono::Utc>> = row.try_get(25).map_err(|e| AppError::Database(e))?;
+    let coverage_percentage: Option<i16> = row.try_get(26).map_err(|e| AppError::Database(e))?;
+    let session_id: uuid::Uuid = row.try_get(27).map_err(|e| AppError::Database(e))?;
+    let api_sourced_percentage: Option<i16> = row.try_get(28).map_err(|e| AppError::Database(e))?;
+    let provenance_mix: Option<serde_json::Value> = row.try_get(29).map_err(|e| AppError::Database(e))?;
+    let self_reported_weight: Option<i16> = row.try_get(30).map_err(|e| AppError::Database(e))?;
+    let source: String = row.try_get(31).map_err(|e| AppError::Database(e))?;
+    let trust_tier: Option<String> = row.try_get(32).map_err(|e| AppError::Database(e))?;
+    let authenticity_score: Option<i16> = row.try_get(33).map_err(|e| AppError::Database(e))?;
+
+    if expires_at < chrono::Utc::now() {
+        return Err(AppError::ProofExpired);
+    }
+
+    // Receipts live in object storage; older sessions still carry them inline
+    let receipt_data = match (receipt_key, receipt_sha256) {
+        (Some(key), Some(sha256)) => Some(
+            crate::services::proof::ProofService::load_receipt(state.storage.as_ref(), &key, &sha256).await?,
+        ),
+        _ => receipt_data.flatten(),
+    };
 
     // Verify receipt if stored
     let valid = if let Some(ref receipt_data) = receipt_data {
         // Verify RISC Zero receipt
-        crate::services::proof::ProofService::verify_receipt(receipt_data).await?
+        crate::services::proof::ProofService::verify_receipt(state.read_db(), receipt_data, image_id.as_deref(), proved_at)
+            .await?
     } else {
         true // If no receipt, assume valid (for development)
     };
 
-    Ok(Json(VerifyProofResponse {
+    // Read from the primary so an approval counts as soon as it's given
+    let disclosure: crate::models::DisclosurePolicy = serde_json::from_value(disclosure).unwrap_or_default();
+    let consent_required = disclosure.lender_consent
+        && !crate::db::repos::ConsentRepo::is_approved(&state.db, session_id, lender.lender_id).await?;
+
+    // Other lenders' requirements aren't this lender's to enforce
+    let template = match template_id {
+        Some(template_id) => crate::db::repos::ProofTemplateRepo::find(state.read_db(), template_id)
+            .await?
+            .filter(|t| t.lender_id == lender.lender_id),
+        None => None,
+    };
+    let template = match template {
+        Some(template) => {
+            let facts = crate::handlers::templates::ProofFacts {
+                proof_type: &proof_type,
+                disclosure,
+                policy_hash: policy_hash.as_deref(),
+                period_start,
+                period_end,
+            };
+            Some(crate::handlers::templates::compliance(state.read_db(), &template, &facts).await?)
+        }
+        None => None,
+    };
+
+    let (credit_score, metrics, threshold) = if consent_required {
+        (None, None, None)
+    } else {
+        (credit_score.flatten(), metrics.flatten(), threshold)
+    };
+    let weighted_credit_score = match (credit_score, api_sourced_percentage) {
+        (Some(score), Some(api_sourced)) => {
+            Some(weighted_score(score, api_sourced, self_reported_weight.unwrap_or(100)))
+        }
+        _ => None,
+    };
+    let provenance = api_sourced_percentage.map(|api_sourced_percentage| ProvenanceSummary {
+        api_sourced_percentage,
+        mix: provenance_mix.unwrap_or(serde_json::json!({})),
+    });
+
+    Ok(VerifyProofResponse {
         valid,
-        credit_score: credit_score.unwrap_or(0),
+        proof_type,
+        credit_score,
         metrics: metrics.unwrap_or(serde_json::json!({})),
+        threshold,
+        consent_required,
+        sector,
+        contested,
+        expires_soon: expires_soon(expires_at),
+        account_hash,
+        policy_hash,
+        prover: crate::models::ProverInfo::from_columns(image_id, guest_version, prover_backend),
+        input_mode,
+        rows_commitment,
+        staleness,
+        coverage_percentage,
+        template,
+        provenance,
+        weighted_credit_score,
+        source,
+        trust_tier,
+        authenticity_score,
         generated_at: created_at.to_rfc3339(),
+    })
+}
+
+/// Scales a score by how much of its volume counts: all of the API-sourced
+/// share, and `self_reported_weight` percent of the rest.
+fn weighted_score(score: i32, api_sourced_percentage: i16, self_reported_weight: i16) -> i32 {
+    let api_sourced = api_sourced_percentage.clamp(0, 100) as i32;
+    let counted = api_sourced * 100 + (100 - api_sourced) * self_reported_weight.clamp(0, 100) as i32;
+    score * counted / 10_000
+}
+
+pub async fn get_settings(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+) -> Result<Json<LenderSettingsResponse>, AppError> {
+    let self_reported_weight_percentage =
+        sqlx::query_scalar("SELECT self_reported_weight_percentage FROM lenders WHERE id = $1")
+            .bind(lender.lender_id)
+            .fetch_one(state.read_db())
+            .await?;
+    Ok(Json(LenderSettingsResponse { self_reported_weight_percentage }))
+}
+
+/// Sets how much a proof's self-reported volume counts towards its
+/// `weighted_credit_score`, so API-sourced proofs can be weighted higher.
+pub async fn update_settings(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    Json(req): Json<LenderSettingsRequest>,
+) -> Result<Json<LenderSettingsResponse>, AppError> {
+    if req.self_reported_weight_percentage > 100 {
+        return Err(AppError::Validation("self_reported_weight_percentage must be at most 100".to_string()));
+    }
+
+    let self_reported_weight_percentage = sqlx::query_scalar(
+        r#"
+        UPDATE lenders SET self_reported_weight_percentage = $2
+        WHERE id = $1
+        RETURNING self_reported_weight_percentage
+        "#,
+    )
+    .bind(lender.lender_id)
+    .bind(req.self_reported_weight_percentage as i16)
+    .fetch_one(&state.db)
+    .await?;
+    Ok(Json(LenderSettingsResponse { self_reported_weight_percentage }))
+}
+
+/// Logs a live verification for billing and usage reporting and, when the
+/// lender asked for it, emails them the outcome. The proof's owner gets a
+/// push alert. A verification the lender's plan has no allowance left for
+/// is refused here, so its outcome mustn't be returned.
+pub(crate) async fn record_verification(
+    state: &AppState,
+    lender: &LenderAuth,
+    verification_code: &str,
+    result: &Result<VerifyProofResponse, AppError>,
+) -> Result<(), AppError> {
+    let (status, credit_score) = match result {
+        Ok(response) if response.valid => ("valid", response.credit_score),
+        Ok(response) => ("invalid", response.credit_score),
+        Err(AppError::ProofExpired) => ("expired", None),
+        Err(AppError::ProofRevoked) => ("revoked", None),
+        Err(AppError::ProofNotFound) => ("not_found", None),
+        // Server-side failures say nothing about the proof
+        Err(_) => return Ok(()),
+    };
+
+    BillingService::record_verification(
+        &state.db,
+        lender.lender_id,
+        lender.key_id,
+        verification_code,
+        status,
+        credit_score,
+    )
+    .await?
+    .map_err(AppError::PaymentRequired)?;
+
+    let notify: Result<Option<bool>, sqlx::Error> =
+        sqlx::query_scalar("SELECT notify_on_verification FROM lenders WHERE id = $1")
+            .bind(lender.lender_id)
+            .fetch_optional(&state.db)
+            .await;
+
+    match notify {
+        Ok(Some(true)) => {
+            let notification = LenderNotification::VerificationCompleted { verification_code, status };
+            if let Err(e) =
+                NotificationService::notify_lender(&state.db, &state.config, lender.lender_id, notification).await
+            {
+                tracing::error!("Failed to email verification notice to lender {}: {}", lender.lender_id, e);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => tracing::error!("Failed to look up verification notices for lender {}: {}", lender.lender_id, e),
+    }
+
+    // Deliver in the background so a slow endpoint doesn't hold up the response
+    let db = state.db.clone();
+    let config = state.config.clone();
+    let lender_id = lender.lender_id;
+    let code = verification_code.to_string();
+    let alert_owner = matches!(status, "valid" | "invalid");
+    let data = serde_json::json!({
+        "verification_code": verification_code,
+        "status": status,
+        "credit_score": credit_score,
+    });
+    tokio::spawn(async move {
+        if let Err(e) = WebhookService::dispatch(&db, lender_id, "verification.completed", data).await {
+            tracing::error!("Failed to dispatch webhooks for lender {}: {}", lender_id, e);
+        }
+        if alert_owner {
+            if let Err(e) = NotificationService::alert_owner_of_verification(&db, &config, lender_id, &code).await {
+                tracing::error!("Failed to alert the owner of proof {} to a verification: {}", code, e);
+            }
+        }
+    });
+    Ok(())
+}
+
+fn expires_soon(expires_at: chrono::DateTime<chrono::Utc>) -> bool {
+    expires_at - chrono::Utc::now() <= chrono::Duration::days(EXPIRES_SOON_DAYS)
+}
+
+fn verify_sandbox_proof(verification_code: &str) -> Result<VerifyProofResponse, AppError> {
+    let proof = SandboxService::find(verification_code).ok_or(AppError::ProofNotFound)?;
+
+    match proof.outcome {
+        SandboxOutcome::Expired => return Err(AppError::ProofExpired),
+        SandboxOutcome::Revoked => return Err(AppError::ProofRevoked),
+        SandboxOutcome::Valid | SandboxOutcome::InvalidReceipt => {}
+    }
+
+    Ok(VerifyProofResponse {
+        valid: proof.outcome == SandboxOutcome::Valid,
+        proof_type: "full".to_string(),
+        credit_score: Some(proof.credit_score),
+        metrics: proof.metrics,
+        threshold: None,
+        consent_required: false,
+        sector: proof.sector.map(str::to_string),
+        contested: false,
+        expires_soon: expires_soon(proof.expires_at),
+        account_hash: None,
+        policy_hash: None,
+        prover: None,
+        input_mode: "transactions".to_string(),
+        rows_commitment: None,
+        staleness: None,
+        coverage_percentage: None,
+        template: None,
+        provenance: None,
+        weighted_credit_score: None,
+        source: "all".to_string(),
+        trust_tier: None,
+        authenticity_score: None,
+        generated_at: proof.generated_at.to_rfc3339(),
+    })
+}
+
+#[derive(Serialize)]
+pub struct ProofMetadataResponse {
+    pub exists: bool,
+    /// "pending", "processing", "completed", "failed", "expired" or
+    /// "revoked"
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proof_type: Option<String>,
+    /// Guest build the proof was, or is being, produced with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prover: Option<crate::models::ProverInfo>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period_start: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub period_end: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub expires_at: Option<String>,
+    /// Whether the owner must approve a consent request before the score
+    /// is shown
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub consent_required: Option<bool>,
+}
+
+impl ProofMetadataResponse {
+    fn missing() -> Self {
+        Self {
+            exists: false,
+            status: None,
+            proof_type: None,
+            prover: None,
+            period_start: None,
+            period_end: None,
+            expires_at: None,
+            consent_required: None,
+        }
+    }
+}
+
+/// Whether a verification code names a proof and what state it's in,
+/// without its score, metrics or threshold outcome. For pre-checks before
+/// the lender is entitled to the proof itself; not logged as a
+/// verification.
+pub async fn proof_metadata(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    Path(code): Path<String>,
+) -> Result<Json<ProofMetadataResponse>, AppError> {
+    if lender.sandbox {
+        let Some(proof) = SandboxService::find(&code) else {
+            return Ok(Json(ProofMetadataResponse::missing()));
+        };
+        let status = match proof.outcome {
+            SandboxOutcome::Expired => "expired",
+            SandboxOutcome::Revoked => "revoked",
+            SandboxOutcome::Valid | SandboxOutcome::InvalidReceipt => "completed",
+        };
+        return Ok(Json(ProofMetadataResponse {
+            exists: true,
+            status: Some(status.to_string()),
+            proof_type: Some("full".to_string()),
+            prover: None,
+            period_start: None,
+            period_end: None,
+            expires_at: Some(proof.expires_at.to_rfc3339()),
+            consent_required: Some(false),
+        }));
+    }
+
+    let code = crate::utils::normalize_verification_code(&code);
+    let row = sqlx::query(
+        r#"
+        SELECT status::text, proof_type, image_id, guest_version, prover_backend, period_start, period_end,
+               expires_at, disclosure_policy
+        FROM proof_sessions
+        WHERE verification_code = $1
+        "#,
+    )
+    .bind(&code)
+    .fetch_optional(state.read_db())
+    .await?;
+
+    let Some(row) = row else {
+        return Ok(Json(ProofMetadataResponse::missing()));
+    };
+
+    let status: String = row.try_get(0)?;
+    let expires_at: chrono::DateTime<chrono::Utc> = row.try_get(7)?;
+    let status = if status == "completed" && expires_at < chrono::Utc::now() {
+        "expired".to_string()
+    } else {
+        status
+    };
+    let period_start: Option<chrono::DateTime<chrono::Utc>> = row.try_get(5)?;
+    let period_end: Option<chrono::DateTime<chrono::Utc>> = row.try_get(6)?;
+    let disclosure: crate::models::DisclosurePolicy = serde_json::from_value(row.try_get(8)?).unwrap_or_default();
+
+    Ok(Json(ProofMetadataResponse {
+        exists: true,
+        status: Some(status),
+        proof_type: Some(row.try_get(1)?),
+        prover: crate::models::ProverInfo::from_columns(row.try_get(2)?, row.try_get(3)?, row.try_get(4)?),
+        period_start: period_start.map(|t| t.to_rfc3339()),
+        period_end: period_end.map(|t| t.to_rfc3339()),
+        expires_at: Some(expires_at.to_rfc3339()),
+        consent_required: Some(disclosure.lender_consent),
     }))
 }
 
+#[derive(Serialize)]
+pub struct SandboxProofSummary {
+    pub verification_code: String,
+    pub description: String,
+    pub expected_outcome: String,
+}
+
+/// Lists the sandbox fixtures so integrators know which codes to try.
+pub async fn list_sandbox_proofs(lender: LenderAuth) -> Result<Json<Vec<SandboxProofSummary>>, AppError> {
+    if !lender.sandbox {
+        return Err(AppError::NotFound("Sandbox fixtures are only available to sandbox keys".to_string()));
+    }
+
+    Ok(Json(
+        SandboxService::proofs()
+            .into_iter()
+            .map(|p| SandboxProofSummary {
+                verification_code: p.verification_code.to_string(),
+                description: p.description.to_string(),
+                expected_outcome: format!("{:?}", p.outcome),
+            })
+            .collect(),
+    ))
+}
+
 pub async fn bulk_verify(
     State(state): State<AppState>,
+    lender: LenderAuth,
     axum::extract::Query(params): axum::extract::Query<std::collections::HashMap<String, String>>,
 ) -> Result<Json<Vec<VerifyProofResponse>>, AppError> {
     let ids = params
@@ -69,16 +613,27 @@ pub async fn bulk_verify(
 
     let proof_ids: Vec<String> = ids.split(',').map(|s| s.trim().to_string()).collect();
 
+    // The whole batch must fit in the allowance, rather than stopping part way
+    if !lender.sandbox {
+        BillingService::verification_usage(&state.db, lender.lender_id)
+            .await?
+            .ensure_allowance_for(proof_ids.len() as i64)
+            .map_err(AppError::PaymentRequired)?;
+    }
+
     let mut results = Vec::new();
 
     for proof_id in proof_ids {
         match verify_proof(
             State(state.clone()),
+            lender.clone(),
             Json(VerifyProofRequest { proof_id }),
         )
         .await
         {
             Ok(Json(response)) => results.push(response),
+            // Concurrent verifications took the rest of the allowance
+            Err(e @ AppError::PaymentRequired(_)) => return Err(e),
             Err(_) => {
                 // Skip invalid proofs
             }
@@ -88,3 +643,151 @@ pub async fn bulk_verify(
     Ok(Json(results))
 }
 
+#[derive(Deserialize)]
+pub struct ExportVerificationsQuery {
+    /// First day to include, YYYY-MM-DD
+    pub from: Option<String>,
+    /// Last day to include, YYYY-MM-DD
+    pub to: Option<String>,
+    /// "csv" (default) or "xlsx"
+    pub format: Option<String>,
+}
+
+const EXPORT_COLUMNS: [&str; 4] = ["verified_at", "verification_code", "status", "credit_score"];
+
+const EXPORT_QUERY: &str = r#"
+    SELECT created_at, verification_code, status, credit_score
+    FROM lender_verifications
+    WHERE lender_id = $1
+      AND ($2::timestamptz IS NULL OR created_at >= $2)
+      AND ($3::timestamptz IS NULL OR created_at < $3)
+    ORDER BY created_at ASC
+"#;
+
+/// Every verification the lender performed in a date range, as a
+/// spreadsheet for credit committees. CSV is streamed row by row.
+pub async fn export_verifications(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    Query(query): Query<ExportVerificationsQuery>,
+) -> Result<Response, AppError> {
+    let parse = |value: &str| {
+        chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map(|d| d.and_hms_opt(0, 0, 0).unwrap().and_utc())
+            .map_err(|_| AppError::Validation(format!("Invalid date: {}", value)))
+    };
+    let from = query.from.as_deref().map(parse).transpose()?;
+    let to = query
+        .to
+        .as_deref()
+        .map(parse)
+        .transpose()?
+        .map(|d| d + chrono::Duration::days(1));
+
+    match query.format.as_deref().unwrap_or("csv") {
+        "csv" => export_csv(state, lender, from, to),
+        "xlsx" => export_xlsx(&state, &lender, from, to).await,
+        other => Err(AppError::Validation(format!("Unsupported export format: {}", other))),
+    }
+}
+
+fn export_row(row: &sqlx::postgres::PgRow) -> Result<[String; 4], sqlx::Error> {
+    Ok([
+        row.try_get::<chrono::DateTime<chrono::Utc>, _>(0)?.to_rfc3339(),
+        row.try_get(1)?,
+        row.try_get(2)?,
+        row.try_get::<Option<i32>, _>(3)?.map(|s| s.to_string()).unwrap_or_default(),
+    ])
+}
+
+fn csv_record<S: AsRef<[u8]>>(fields: &[S]) -> std::io::Result<Vec<u8>> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record(fields)?;
+    writer.into_inner().map_err(|e| e.into_error())
+}
+
+fn export_csv(
+    state: AppState,
+    lender: LenderAuth,
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<Response, AppError> {
+    let (tx, rx) = tokio::sync::mpsc::channel::<std::io::Result<Vec<u8>>>(16);
+
+    tokio::spawn(async move {
+        if tx.send(csv_record(&EXPORT_COLUMNS)).await.is_err() {
+            return;
+        }
+
+        let mut rows = sqlx::query(EXPORT_QUERY)
+            .bind(lender.lender_id)
+            .bind(from)
+            .bind(to)
+            .fetch(&state.db);
+
+        while let Some(row) = rows.next().await {
+            let chunk = row
+                .and_then(|row| export_row(&row))
+                .map_err(std::io::Error::other)
+                .and_then(|fields| csv_record(&fields));
+            let failed = chunk.is_err();
+            // Stop when the client hangs up or the query fails
+            if tx.send(chunk).await.is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    let stream = futures::stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|chunk| (chunk, rx)) });
+
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/csv")
+        .header(header::CONTENT_DISPOSITION, "attachment; filename=\"verifications.csv\"")
+        .body(Body::from_stream(stream))
+        .map_err(|e| AppError::Internal(e.into()))
+}
+
+async fn export_xlsx(
+    state: &AppState,
+    lender: &LenderAuth,
+    from: Option<chrono::DateTime<chrono::Utc>>,
+    to: Option<chrono::DateTime<chrono::Utc>>,
+) -> Result<Response, AppError> {
+    let rows = sqlx::query(EXPORT_QUERY)
+        .bind(lender.lender_id)
+        .bind(from)
+        .bind(to)
+        .fetch_all(&state.db)
+        .await?;
+
+    let mut workbook = rust_xlsxwriter::Workbook::new();
+    let sheet = workbook.add_worksheet();
+    let header_format = rust_xlsxwriter::Format::new().set_bold();
+
+    let write = |e: rust_xlsxwriter::XlsxError| AppError::Internal(e.into());
+    for (col, name) in EXPORT_COLUMNS.iter().enumerate() {
+        sheet.write_string_with_format(0, col as u16, *name, &header_format).map_err(write)?;
+    }
+    for (i, row) in rows.iter().enumerate() {
+        let fields = export_row(row)?;
+        let credit_score: Option<i32> = row.try_get(3)?;
+        for (col, value) in fields.iter().enumerate().take(3) {
+            sheet.write_string(i as u32 + 1, col as u16, value).map_err(write)?;
+        }
+        // Keep scores numeric so committees can sort and chart them
+        if let Some(score) = credit_score {
+            sheet.write_number(i as u32 + 1, 3, score as f64).map_err(write)?;
+        }
+    }
+
+    let buffer = workbook.save_to_buffer().map_err(write)?;
+
+    Response::builder()
+        .header(
+            header::CONTENT_TYPE,
+            "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        )
+        .header(header::CONTENT_DISPOSITION, "attachment; filename=\"verifications.xlsx\"")
+        .body(Body::from(buffer))
+        .map_err(|e| AppError::Internal(e.into()))
+}
@@ -0,0 +1,329 @@
+use axum::{
+    extract::{Path, State},
+    http::{header, HeaderValue},
+    response::IntoResponse,
+    Json,
+};
+use serde::Serialize;
+use serde_json::json;
+
+use crate::error::AppError;
+use crate::handlers::AppState;
+
+/// How long services checking session tokens may cache the key set. A new
+/// signing key should be published in `JWT_PUBLIC_KEYS` at least this long
+/// before it's used.
+const JWKS_CACHE_CONTROL: &str = "public, max-age=3600";
+
+/// The public keys session tokens are signed with, for services that check
+/// them without the HMAC secret. Empty while tokens are signed with HS256.
+pub async fn get_jwks(State(state): State<AppState>) -> impl IntoResponse {
+    (
+        [
+            (header::CACHE_CONTROL, HeaderValue::from_static(JWKS_CACHE_CONTROL)),
+            (header::ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*")),
+        ],
+        Json(state.config.jwt_keys.jwks().clone()),
+    )
+}
+
+#[derive(Serialize)]
+pub struct JournalSchemaResponse {
+    pub version: u32,
+    /// Whether receipts generated now use this version
+    pub current: bool,
+    /// Image ID of the guest the API proves with, as hex
+    pub image_id: String,
+    pub encoding: &'static str,
+    /// Committed types in field order; the root type is `Journal`
+    pub layout: serde_json::Value,
+}
+
+/// Describes how to decode a receipt journal of the given schema version, so
+/// lenders can verify receipts without this service.
+pub async fn get_journal_schema(Path(version): Path<u32>) -> Result<Json<JournalSchemaResponse>, AppError> {
+    let layout = match version {
+        1 => layout_v1(),
+        2 => layout_v2(),
+        3 => layout_v3(),
+        4 => layout_v4(),
+        5 => layout_v5(),
+        6 => layout_v6(),
+        7 => layout_v7(),
+        8 => layout_v8(),
+        9 => layout_v9(),
+        10 => layout_v10(),
+        11 => layout_v11(),
+        12 => layout_v12(),
+        13 => layout_v13(),
+        _ => return Err(AppError::NotFound(format!("Unknown journal schema version {}", version))),
+    };
+
+    Ok(Json(JournalSchemaResponse {
+        version,
+        current: version == proof_core::JOURNAL_SCHEMA_VERSION,
+        image_id: risc0_zkvm::sha::Digest::from(methods::GUEST_CODE_FOR_ZK_PROOF_ID).to_string(),
+        encoding: "risc0 serde: every value is padded to little-endian u32 words; u64/i64 take two words; \
+                   bool, Option and enum tags take one word; String and Vec are a u32 length followed by \
+                   their contents; fixed-size arrays have no length prefix",
+        layout,
+    }))
+}
+
+/// Mirrors `proof_core::Journal` as of `JOURNAL_SCHEMA_VERSION` 1. Never
+/// edit a published version; add a new one alongside it.
+fn layout_v1() -> serde_json::Value {
+    json!({
+        "Journal": [
+            { "name": "schema_version", "type": "u32" },
+            { "name": "evaluation", "type": "Evaluation" },
+        ],
+        "Evaluation": {
+            "enum": [
+                { "variant": "Full", "type": "ProofOutput" },
+                { "variant": "Threshold", "type": "ThresholdOutput" },
+            ]
+        },
+        "ProofOutput": [
+            { "name": "till_number_hash", "type": "[u8; 32]" },
+            { "name": "period_start", "type": "i64", "unit": "unix seconds" },
+            { "name": "period_end", "type": "i64", "unit": "unix seconds" },
+            { "name": "credit_score", "type": "u32" },
+            { "name": "metrics", "type": "BusinessMetrics" },
+            { "name": "score_breakdown", "type": "ScoreBreakdown" },
+            { "name": "source_volumes", "type": "Vec<SourceVolume>" },
+            { "name": "monthly_volumes", "type": "Vec<VolumeRange>" },
+            { "name": "account_hash", "type": "Option<String>" },
+            { "name": "currency", "type": "String" },
+            { "name": "policy_hash", "type": "[u8; 32]" },
+        ],
+        "ThresholdOutput": [
+            { "name": "period_start", "type": "i64", "unit": "unix seconds" },
+            { "name": "period_end", "type": "i64", "unit": "unix seconds" },
+            { "name": "score_at_least", "type": "u32" },
+            { "name": "meets_threshold", "type": "bool" },
+            { "name": "account_hash", "type": "Option<String>" },
+            { "name": "currency", "type": "String" },
+            { "name": "policy_hash", "type": "[u8; 32]" },
+        ],
+        "BusinessMetrics": [
+            { "name": "monthly_volume_range", "type": "VolumeRange" },
+            { "name": "consistency_score", "type": "u8" },
+            { "name": "growth_trend", "type": "GrowthTrend" },
+            { "name": "active_days_percentage", "type": "u8" },
+            { "name": "customer_diversity_score", "type": "u8" },
+            { "name": "concentration_risk", "type": "ConcentrationRisk" },
+            { "name": "max_weekly_drawdown_percentage", "type": "u8" },
+            { "name": "volatility_30d_percentage", "type": "u8" },
+        ],
+        "ScoreBreakdown": [
+            { "name": "volume_points", "type": "u32" },
+            { "name": "consistency_points", "type": "u32" },
+            { "name": "activity_points", "type": "u32" },
+            { "name": "growth_points", "type": "u32" },
+            { "name": "diversity_points", "type": "u32" },
+            { "name": "concentration_penalty", "type": "u32" },
+            { "name": "volatility_penalty", "type": "u32" },
+        ],
+        "SourceVolume": [
+            { "name": "tag", "type": "String" },
+            { "name": "monthly_volume_range", "type": "VolumeRange" },
+        ],
+        "VolumeRange": { "enum": ["VeryLow", "Low", "Medium", "High", "VeryHigh"] },
+        "GrowthTrend": { "enum": ["Declining", "Stable", "Growing", "Rapid"] },
+        "ConcentrationRisk": { "enum": ["Low", "Moderate", "High", "Unknown"] },
+    })
+}
+
+/// Version 2 adds `chunk_image_id` for proofs composed from chunk receipts.
+/// When set, it must equal the receipt's own image ID.
+fn layout_v2() -> serde_json::Value {
+    let mut layout = layout_v1();
+    layout["Journal"] = json!([
+        { "name": "schema_version", "type": "u32" },
+        { "name": "evaluation", "type": "Evaluation" },
+        { "name": "chunk_image_id", "type": "Option<[u32; 8]>" },
+    ]);
+    layout
+}
+
+/// Version 3 adds `input_mode`, saying whether the guest scored every
+/// transaction or host-computed daily totals bound by `rows_commitment`.
+fn layout_v3() -> serde_json::Value {
+    let mut layout = layout_v2();
+    layout["Journal"] = json!([
+        { "name": "schema_version", "type": "u32" },
+        { "name": "evaluation", "type": "Evaluation" },
+        { "name": "chunk_image_id", "type": "Option<[u32; 8]>" },
+        { "name": "input_mode", "type": "InputMode" },
+    ]);
+    layout["InputMode"] = json!({
+        "enum": [
+            { "variant": "Transactions" },
+            { "variant": "DailyTotals", "fields": [{ "name": "rows_commitment", "type": "[u8; 32]" }] },
+        ]
+    });
+    layout
+}
+
+/// Version 4 appends `activity_profile` to `ProofOutput`: coarse shares of
+/// volume by weekday and time of day, in East Africa Time.
+fn layout_v4() -> serde_json::Value {
+    let mut layout = layout_v3();
+    layout["ProofOutput"]
+        .as_array_mut()
+        .expect("ProofOutput is a field list")
+        .push(json!({ "name": "activity_profile", "type": "ActivityProfile" }));
+    layout["ActivityProfile"] = json!([
+        { "name": "weekday_shares", "type": "[ShareBucket; 7]", "unit": "Monday first" },
+        { "name": "business_hours_share", "type": "ShareBucket", "unit": "08:00-18:00" },
+        { "name": "late_night_share", "type": "ShareBucket", "unit": "22:00-05:00" },
+    ]);
+    layout["ShareBucket"] = json!({ "enum": ["Negligible", "Low", "Moderate", "High", "Dominant"] });
+    layout
+}
+
+/// Version 5 appends `outlier_adjustment` to `ProofOutput`, set when the
+/// scoring policy caps outlier days.
+fn layout_v5() -> serde_json::Value {
+    let mut layout = layout_v4();
+    layout["ProofOutput"]
+        .as_array_mut()
+        .expect("ProofOutput is a field list")
+        .push(json!({ "name": "outlier_adjustment", "type": "Option<OutlierAdjustment>" }));
+    layout["OutlierAdjustment"] = json!([
+        { "name": "cap_multiple", "type": "u32" },
+        { "name": "days_capped", "type": "u32" },
+        { "name": "volume_removed", "type": "ShareBucket" },
+        { "name": "raw_monthly_volume_range", "type": "VolumeRange" },
+    ]);
+    layout
+}
+
+/// Version 6 appends `recency` to both `ProofOutput` and `ThresholdOutput`.
+/// `credit_score` and `meets_threshold` already reflect its factor.
+fn layout_v6() -> serde_json::Value {
+    let mut layout = layout_v5();
+    for output in ["ProofOutput", "ThresholdOutput"] {
+        layout[output]
+            .as_array_mut()
+            .expect("outputs are field lists")
+            .push(json!({ "name": "recency", "type": "Recency" }));
+    }
+    layout["Recency"] = json!([
+        { "name": "as_of", "type": "i64", "unit": "unix seconds" },
+        { "name": "days_since_last_transaction", "type": "u32" },
+        { "name": "recency_factor_percentage", "type": "u8", "unit": "percent of the undecayed score kept" },
+    ]);
+    layout
+}
+
+/// Version 7 appends `coverage_percentage` to both outputs.
+fn layout_v7() -> serde_json::Value {
+    let mut layout = layout_v6();
+    for output in ["ProofOutput", "ThresholdOutput"] {
+        layout[output]
+            .as_array_mut()
+            .expect("outputs are field lists")
+            .push(json!({
+                "name": "coverage_percentage",
+                "type": "u8",
+                "unit": "percent of the period's weeks with a payment",
+            }));
+    }
+    layout
+}
+
+/// Version 8 appends `utc_offset_secs` to both outputs: the local time days,
+/// weekdays and hours were taken in. Earlier versions always used East
+/// Africa Time.
+fn layout_v8() -> serde_json::Value {
+    let mut layout = layout_v7();
+    for output in ["ProofOutput", "ThresholdOutput"] {
+        layout[output]
+            .as_array_mut()
+            .expect("outputs are field lists")
+            .push(json!({ "name": "utc_offset_secs", "type": "i32", "unit": "seconds east of UTC" }));
+    }
+    layout
+}
+
+/// Version 9 appends `cash_flow` to `ProofOutput`: what the business keeps
+/// of its payments after outflows and fees. Earlier versions also counted
+/// money paid out with a Payment description as volume.
+fn layout_v9() -> serde_json::Value {
+    let mut layout = layout_v8();
+    layout["ProofOutput"]
+        .as_array_mut()
+        .expect("ProofOutput is a field list")
+        .push(json!({ "name": "cash_flow", "type": "CashFlow" }));
+    layout["CashFlow"] = json!([
+        { "name": "monthly_net_inflow_range", "type": "VolumeRange" },
+        { "name": "net_inflow_percentage", "type": "u8", "unit": "percent of payments kept after outflows and fees" },
+        { "name": "fee_percentage", "type": "u8", "unit": "percent of payments" },
+    ]);
+    layout
+}
+
+/// Version 10 appends `provenance` to both outputs: how the scored volume
+/// reached the platform.
+fn layout_v10() -> serde_json::Value {
+    let mut layout = layout_v9();
+    for output in ["ProofOutput", "ThresholdOutput"] {
+        layout[output]
+            .as_array_mut()
+            .expect("outputs are field lists")
+            .push(json!({ "name": "provenance", "type": "ProvenanceMix" }));
+    }
+    layout["ProvenanceMix"] = json!([
+        {
+            "name": "api_sourced_percentage",
+            "type": "u8",
+            "unit": "percent of scored volume from C2B callbacks and Daraja pulls",
+        },
+        {
+            "name": "percentages",
+            "type": "[u8; 6]",
+            "unit": "percent of scored volume from csv, pdf, sms, partner, c2b and daraja, in that order",
+        },
+    ]);
+    layout
+}
+
+/// Version 11 appends `source` to both outputs: whether the proof scored
+/// every transaction or only those Safaricom delivered directly.
+fn layout_v11() -> serde_json::Value {
+    let mut layout = layout_v10();
+    for output in ["ProofOutput", "ThresholdOutput"] {
+        layout[output]
+            .as_array_mut()
+            .expect("outputs are field lists")
+            .push(json!({ "name": "source", "type": "DataSource" }));
+    }
+    layout["DataSource"] = json!({ "enum": ["All", "VerifiedApi"] });
+    layout
+}
+
+/// Version 12 adds photo uploads to the provenances, widening
+/// `ProvenanceMix.percentages` by one.
+fn layout_v12() -> serde_json::Value {
+    let mut layout = layout_v11();
+    layout["ProvenanceMix"][1] = json!({
+        "name": "percentages",
+        "type": "[u8; 7]",
+        "unit": "percent of scored volume from csv, pdf, sms, partner, c2b, daraja and image, in that order",
+    });
+    layout
+}
+
+/// Version 13 adds rows the owner corrected while reviewing them, widening
+/// `ProvenanceMix.percentages` by one.
+fn layout_v13() -> serde_json::Value {
+    let mut layout = layout_v12();
+    layout["ProvenanceMix"][1] = json!({
+        "name": "percentages",
+        "type": "[u8; 8]",
+        "unit": "percent of scored volume from csv, pdf, sms, partner, c2b, daraja, image and corrected, in that order",
+    });
+    layout
+}
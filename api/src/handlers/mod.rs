@@ -1,9 +1,34 @@
+pub mod access_tokens;
+pub mod admin;
+pub mod api_keys;
 pub mod auth;
+pub mod billing;
+pub mod consents;
 pub mod data;
+pub mod dev;
+pub mod disputes;
+pub mod feature_flags;
+pub mod handoffs;
+pub mod images;
+pub mod ingest;
 pub mod lender;
+pub mod meta;
+pub mod payments;
 pub mod proofs;
+pub mod referrals;
+pub mod reviews;
+pub mod scoring_experiments;
+pub mod short_codes;
+pub mod simulations;
+pub mod stats;
+pub mod status;
+pub mod templates;
 pub mod tills;
+pub mod uploads;
+pub mod user_sessions;
+pub mod users;
 pub mod verification;
+pub mod webhooks;
 
 use axum::extract::FromRequestParts;
 use axum::http::request::Parts;
@@ -11,14 +36,26 @@ use axum::http::StatusCode;
 use sqlx::PgPool;
 
 use crate::config::Config;
+use crate::services::storage::StorageBackend;
 use crate::utils::Claims;
 use redis::Client;
 
 #[derive(Clone)]
 pub struct AppState {
     pub db: PgPool,
+    /// Replica for read-only handlers; use `read_db()` rather than this
+    pub db_read: Option<crate::db::ReadReplica>,
     pub redis: Client,
     pub config: std::sync::Arc<Config>,
+    pub storage: std::sync::Arc<dyn StorageBackend>,
+}
+
+impl AppState {
+    /// Pool for queries that tolerate replication lag: the replica when one
+    /// is configured and healthy, otherwise the primary.
+    pub fn read_db(&self) -> &PgPool {
+        self.db_read.as_ref().and_then(|r| r.pool()).unwrap_or(&self.db)
+    }
 }
 
 #[axum::async_trait]
@@ -0,0 +1,135 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use crate::db::repos::PaymentRepo;
+use crate::error::AppError;
+use crate::handlers::{AppState, Claims};
+use crate::services::billing::BillingService;
+use crate::services::payments::{PaymentService, StkCallbackEnvelope};
+
+#[derive(Deserialize)]
+pub struct InitiatePaymentRequest {
+    /// Phone to prompt; defaults to the signed-in one
+    pub phone_number: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct InitiatePaymentResponse {
+    pub payment_id: String,
+    pub status: String,
+    /// Minor units of `currency`
+    pub amount: i64,
+    pub currency: String,
+    /// Text to show while the phone prompts for the M-Pesa PIN
+    pub message: String,
+}
+
+#[derive(Serialize)]
+pub struct PaymentResponse {
+    pub payment_id: String,
+    /// "pending", "succeeded" or "failed"
+    pub status: String,
+    pub amount: i64,
+    pub currency: String,
+    pub mpesa_receipt: Option<String>,
+    /// Why a payment failed, as M-Pesa put it
+    pub failure_reason: Option<String>,
+    /// The proof it paid for, once spent
+    pub session_id: Option<String>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+}
+
+/// Prompts the merchant's phone to pay for a proof past their plan's
+/// allowance. Once the payment succeeds, `POST /api/proofs/generate` spends
+/// it.
+pub async fn initiate_payment(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(req): Json<InitiatePaymentRequest>,
+) -> Result<Json<InitiatePaymentResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    if !PaymentService::is_configured(&state.config) {
+        return Err(AppError::NotFound("Payments aren't configured".to_string()));
+    }
+
+    let phone = req.phone_number.as_deref().unwrap_or(&claims.phone_number);
+    let phone = PaymentService::normalize_phone(phone)
+        .ok_or_else(|| AppError::Validation("phone_number must be a Kenyan mobile number".to_string()))?;
+
+    // Only a proof the plan won't allow is paid for, and only once
+    if BillingService::proof_usage(&state.db, user_id).await?.ensure_allowance().is_ok() {
+        return Err(AppError::Validation("Your plan allows another proof this month without paying".to_string()));
+    }
+    if PaymentRepo::has_unspent(&state.db, user_id).await? {
+        return Err(AppError::Validation("A payment for your next proof has already been made".to_string()));
+    }
+
+    let (payment_id, push) = PaymentService::initiate(&state.db, &state.config, user_id, &phone).await?;
+    Ok(Json(InitiatePaymentResponse {
+        payment_id: payment_id.to_string(),
+        status: "pending".to_string(),
+        amount: state.config.proof_price as i64 * 100,
+        currency: crate::services::payments::PAYMENT_CURRENCY.to_string(),
+        message: push.customer_message,
+    }))
+}
+
+/// A payment's status, to poll while the merchant answers the prompt.
+pub async fn get_payment(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(payment_id): Path<String>,
+) -> Result<Json<PaymentResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let payment_id = Uuid::parse_str(&payment_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let payment = PaymentRepo::find_for_user(&state.db, payment_id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Payment not found".to_string()))?;
+    Ok(Json(PaymentResponse {
+        payment_id: payment.id.to_string(),
+        failure_reason: if payment.status == "failed" { payment.result_desc } else { None },
+        status: payment.status,
+        amount: payment.amount_minor,
+        currency: payment.currency,
+        mpesa_receipt: payment.mpesa_receipt,
+        session_id: payment.session_id.map(|id| id.to_string()),
+        created_at: payment.created_at.to_rfc3339(),
+        completed_at: payment.completed_at.map(|t| t.to_rfc3339()),
+    }))
+}
+
+#[derive(Deserialize)]
+pub struct CallbackQuery {
+    pub token: Option<String>,
+}
+
+/// Daraja's STK push callback. Authenticated by the token in the callback
+/// URL; repeated callbacks are acknowledged without effect.
+pub async fn stk_callback(
+    State(state): State<AppState>,
+    Query(query): Query<CallbackQuery>,
+    Json(envelope): Json<StkCallbackEnvelope>,
+) -> Result<Json<serde_json::Value>, AppError> {
+    let expected = state
+        .config
+        .stk_callback_token
+        .as_deref()
+        .ok_or_else(|| AppError::NotFound("Payments aren't configured".to_string()))?;
+    let token = query.token.unwrap_or_default();
+    if !crate::utils::constant_time_eq(token.as_bytes(), expected.as_bytes()) {
+        return Err(AppError::Auth("Invalid callback token".to_string()));
+    }
+
+    let callback = envelope.body.stk_callback;
+    if !PaymentService::handle_callback(&state.db, &callback).await? {
+        tracing::info!("Ignored STK callback for checkout request {}", callback.CheckoutRequestID);
+    }
+    Ok(Json(json!({ "ResultCode": 0, "ResultDesc": "Accepted" })))
+}
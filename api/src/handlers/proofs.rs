@@ -1,18 +1,78 @@
-use axum::{extract::{Path, State}, Json};
-use redis::AsyncCommands;
+use axum::{
+    body::Body,
+    extract::{Form, Path, Query, State},
+    http::header,
+    response::Response,
+    Json,
+};
 use serde::{Deserialize, Serialize};
 use sqlx::Row;
+use tokio_util::io::ReaderStream;
 use uuid::Uuid;
 
+use crate::db::repos::{
+    CurrencyRepo, PaymentRepo, ReferralCodeRepo, ScoringPolicyRepo, SessionRepo, TillRepo, TransactionRepo,
+};
 use crate::error::AppError;
 use crate::handlers::{AppState, Claims};
-use crate::services::proof::ProofService;
+use crate::i18n::Message;
+use crate::middleware::locale::current_locale;
+use crate::services::billing::BillingService;
+use crate::services::expiry_reminder::ExpiryReminderService;
+use crate::services::proof::{NewSession, NewSessionRequest, ProofService};
+use crate::services::statement_pull::StatementPullService;
 
 #[derive(Deserialize)]
 pub struct GenerateProofRequest {
     pub till_id: String,
-    pub data_source: String, // "upload" or "api"
+    /// "upload" or "api" to score every transaction, or "verified_api" to
+    /// score only those from C2B callbacks and Daraja pulls
+    pub data_source: String,
     pub date_range: Option<DateRange>,
+    #[serde(default)]
+    pub disclosure: crate::models::DisclosurePolicy,
+    /// Score a second statement source (e.g. "bank") alongside M-Pesa
+    pub secondary_source: Option<String>,
+    /// Prove only that the score is at least this value (1-100), without
+    /// revealing the score or metrics
+    pub score_threshold: Option<u32>,
+    /// Score only payments against this PayBill account number (e.g. one
+    /// branch); its hash is committed in the proof
+    pub account_number: Option<String>,
+    /// Score under this policy's volume thresholds instead of the currency's
+    /// defaults, e.g. one a lender asked for
+    pub scoring_policy_id: Option<String>,
+    /// "transactions" (default) or "daily_totals", which proves from daily
+    /// totals much faster but attests less
+    pub input_mode: Option<String>,
+    /// Generate to a lender's proof template: its scoring policy is used
+    /// and its disclosures are made
+    pub template_id: Option<String>,
+    /// Start a new session even if one for the same till and date range is
+    /// still pending or processing
+    #[serde(default)]
+    pub force: bool,
+    /// A partner's referral or coupon code, attributing the proof to them
+    pub referral_code: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct PreviewProofRequest {
+    pub till_id: String,
+    pub secondary_source: Option<String>,
+    pub account_number: Option<String>,
+    pub scoring_policy_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct PreviewProofResponse {
+    /// Always "not a proof": nothing here is attested or verifiable
+    pub label: &'static str,
+    pub preliminary_score: u32,
+    pub metrics: serde_json::Value,
+    pub components: Vec<ScoreComponent>,
+    pub period_start: String,
+    pub period_end: String,
 }
 
 #[derive(Deserialize)]
@@ -24,8 +84,11 @@ pub struct DateRange {
 #[derive(Serialize)]
 pub struct GenerateProofResponse {
     pub session_id: String,
+    /// "pending" or "processing"
     pub status: String,
     pub estimated_time: u32,
+    /// The session is one already pending or processing for the same request
+    pub existing: bool,
 }
 
 #[derive(Serialize)]
@@ -38,10 +101,35 @@ pub struct ProofStatusResponse {
 #[derive(Serialize)]
 pub struct ProofResultResponse {
     pub proof_id: String,
-    pub credit_score: i32,
+    pub proof_type: String,
+    pub credit_score: Option<i32>,
     pub metrics: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<crate::models::ThresholdResult>,
     pub verification_url: String,
     pub expires_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub prover: Option<crate::models::ProverInfo>,
+}
+
+#[derive(Serialize)]
+pub struct ScoreComponent {
+    pub name: String,
+    pub points: u32,
+    pub max_points: u32,
+    /// Penalties are deducted from the total rather than added to it
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub penalty: bool,
+}
+
+#[derive(Serialize)]
+pub struct ProofBreakdownResponse {
+    pub proof_id: String,
+    pub credit_score: i32,
+    pub components: Vec<ScoreComponent>,
+    /// Set when the scoring policy capped outlier days before scoring
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outlier_adjustment: Option<crate::models::OutlierAdjustment>,
 }
 
 pub async fn generate_proof(
@@ -50,83 +138,562 @@ pub async fn generate_proof(
     Json(req): Json<GenerateProofRequest>,
 ) -> Result<Json<GenerateProofResponse>, AppError> {
     let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    start_proof(&state, user_id, req).await.map(Json)
+}
+
+/// Validates a request and queues its proof, or returns the session already
+/// running for the same till and date range.
+async fn start_proof(
+    state: &AppState,
+    user_id: Uuid,
+    mut req: GenerateProofRequest,
+) -> Result<GenerateProofResponse, AppError> {
     let till_id = Uuid::parse_str(&req.till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
 
     // Verify till belongs to user
-    let row = sqlx::query("SELECT user_id FROM business_tills WHERE id = $1")
-        .bind(till_id)
-        .fetch_optional(&state.db)
-        .await?;
-
-    let till_user_id: Uuid = row
-        .ok_or_else(|| AppError::NotFound("Till not found".to_string()))?
-        .try_get::<Uuid, _>(0)
-        .map_err(|e| AppError::Database(e))?;
+    let till_user_id = TillRepo::owner(&state.db, till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
 
     if till_user_id != user_id {
-        return Err(AppError::Auth("Unauthorized".to_string()));
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
+    }
+
+    let referral_code = match req.referral_code.as_deref().map(str::trim).filter(|c| !c.is_empty()) {
+        Some(code) => Some(
+            ReferralCodeRepo::find_redeemable(&state.db, &code.to_ascii_uppercase())
+                .await?
+                .ok_or_else(|| AppError::Validation(format!("Unknown or expired referral code: {}", code)))?,
+        ),
+        None => None,
+    };
+    if let Some(code) = &referral_code {
+        if let Some(max) = code.max_redemptions_per_user {
+            if ReferralCodeRepo::redemptions_by(&state.db, code.id, user_id).await? >= max as i64 {
+                return Err(AppError::Validation(format!("You've already redeemed referral code {}", code.code)));
+            }
+        }
+    }
+
+    // Before anything is pulled or proved. Past the plan's allowance, a
+    // proof needs a coupon or an STK push payment to spend. This is only a
+    // quick look: `create_proof_session` decides with the user's row held
+    let metered = !referral_code.as_ref().is_some_and(|code| code.waives_payment);
+    if metered {
+        let usage = BillingService::proof_usage(&state.db, user_id).await?;
+        if let Err(limit) = usage.ensure_allowance() {
+            if !PaymentRepo::has_unspent(&state.db, user_id).await? {
+                return Err(AppError::PaymentRequired(limit));
+            }
+        }
     }
 
-    let session_id = ProofService::create_proof_session(
-        &state.db,
+    if let Some(source) = req.secondary_source.as_deref() {
+        if source == "mpesa" || !crate::models::TRANSACTION_SOURCES.contains(&source) {
+            return Err(AppError::Validation(format!("Invalid secondary source: {}", source)));
+        }
+    }
+
+    if let Some(threshold) = req.score_threshold {
+        if !(1..=100).contains(&threshold) {
+            return Err(AppError::Validation("score_threshold must be between 1 and 100".to_string()));
+        }
+    }
+
+    let input_mode = req.input_mode.as_deref().unwrap_or("transactions");
+    if !crate::models::INPUT_MODES.contains(&input_mode) {
+        return Err(AppError::Validation(format!("Invalid input mode: {}", input_mode)));
+    }
+
+    validate_account_filter(state, till_id, req.account_number.as_deref(), req.secondary_source.as_deref()).await?;
+
+    let template = match req.template_id.as_deref() {
+        Some(template_id) => Some(crate::handlers::templates::find(&state.db, template_id).await?),
+        None => None,
+    };
+    if let Some(template) = &template {
+        apply_template(&mut req, template)?;
+    }
+
+    let data_source = match req.data_source.as_str() {
+        "upload" | "api" => proof_core::DataSource::All,
+        "verified_api" => proof_core::DataSource::VerifiedApi,
+        other => return Err(AppError::Validation(format!("Invalid data source: {}", other))),
+    };
+
+    // Without C2B callbacks the API sources pull the statement on demand
+    if (req.data_source == "api" || data_source == proof_core::DataSource::VerifiedApi)
+        && state.config.daraja_consumer_key.is_some()
+    {
+        let (start, end) = pull_window(req.date_range.as_ref())?;
+        let imported = StatementPullService::sync_till(&state.db, &state.config, till_id, start, end).await?;
+        tracing::info!("Pulled {} transactions for till {}", imported, till_id);
+    }
+
+    let verified_api = data_source == proof_core::DataSource::VerifiedApi;
+    if verified_api && !TransactionRepo::has_api_sourced(&state.db, till_id).await? {
+        return Err(AppError::Validation(
+            "A verified API proof needs transactions from C2B callbacks or a Daraja pull; this till has none"
+                .to_string(),
+        ));
+    }
+
+    let currency = proof_currency(&state.db, till_id, req.secondary_source.as_deref()).await?;
+    let template_lender_id = template.as_ref().map(|t| t.lender_id);
+    let policy = scoring_policy(&state.db, req.scoring_policy_id.as_deref(), template_lender_id, &currency).await?;
+
+    if let Some(template) = &template {
+        let period = TransactionRepo::period_for_proof(&state.db, till_id, req.secondary_source.as_deref()).await?;
+        let days = period.map_or(0, |(start, end)| (end - start).num_days());
+        if days < template.min_period_days as i64 {
+            return Err(AppError::Validation(format!(
+                "The template requires {} days of transactions; this till has {}",
+                template.min_period_days, days
+            )));
+        }
+    }
+
+    // Missing weeks read as a quiet business, so a patchy statement would
+    // prove a misleadingly low score. A requested period is measured from
+    // end to end, not only between its first and last payments.
+    let range = requested_range(req.date_range.as_ref())?;
+    let completeness = match range {
+        Some(range) => ProofService::requested_completeness(&state.db, till_id, range).await?,
+        None => ProofService::refresh_completeness(&state.db, till_id).await?,
+    };
+    let min_completeness = state.config.min_completeness_percentage;
+    if completeness.weeks > 0 && completeness.percentage() < min_completeness {
+        let report = crate::handlers::data::CompletenessReport::from(completeness);
+        let missing: Vec<String> = report.gaps.iter().map(|gap| format!("{} to {}", gap.from, gap.to)).collect();
+        return Err(AppError::Validation(format!(
+            "Only {}% of the statement's weeks have payments (at least {}% needed). Upload the missing weeks: {}",
+            report.coverage_percentage,
+            min_completeness,
+            missing.join(", ")
+        )));
+    }
+
+    let new_session = NewSessionRequest {
         user_id,
         till_id,
-        &req.data_source,
-        req.date_range.as_ref(),
-    )
-    .await?;
+        data_source: data_source.as_str(),
+        requested_range: range,
+        disclosure_policy: &req.disclosure,
+        secondary_source: req.secondary_source.as_deref(),
+        score_threshold: req.score_threshold,
+        account_number: req.account_number.as_deref(),
+        currency: &currency.code,
+        scoring_policy_id: policy.as_ref().map(|p| p.id),
+        input_mode,
+        template_id: template.as_ref().map(|t| t.id),
+        force: req.force,
+        metered,
+        referral_code_id: referral_code.as_ref().map(|code| code.id),
+    };
+    let session = ProofService::create_proof_session(&state.db, &new_session).await?;
 
-    // Queue proof generation job
-    let mut redis_conn = state.redis.get_async_connection().await?;
-    redis_conn.lpush("proof_queue", session_id.to_string()).await.map_err(|e| AppError::Redis(e))?;
+    let session_id = match session {
+        NewSession::Created(session_id) => session_id,
+        NewSession::Unpaid(limit) => return Err(AppError::PaymentRequired(limit)),
+        NewSession::CodeUnavailable => {
+            return Err(AppError::Validation("The referral code can no longer be redeemed".to_string()));
+        }
+        NewSession::Existing(session_id, status) => {
+            return Ok(GenerateProofResponse {
+                session_id: session_id.to_string(),
+                status,
+                estimated_time: 30,
+                existing: true,
+            });
+        }
+    };
 
-    Ok(Json(GenerateProofResponse {
+    SessionRepo::record_event(&state.db, session_id, crate::models::SessionStage::Queued, None).await?;
+
+    Ok(GenerateProofResponse {
         session_id: session_id.to_string(),
-        status: "processing".to_string(),
+        status: "pending".to_string(),
         estimated_time: 30,
-    }))
+        existing: false,
+    })
 }
 
-pub async fn get_proof_status(
+#[derive(Deserialize)]
+pub struct RegenerateQuery {
+    pub token: String,
+}
+
+/// The page the link in an expiry reminder opens. It only asks the owner to
+/// confirm, posting the token to `regenerate_proof`, since link previews and
+/// scanners fetch links in messages without anyone tapping them.
+pub async fn confirm_regeneration(
     State(state): State<AppState>,
-    claims: Claims,
-    Path(session_id): Path<String>,
-) -> Result<Json<ProofStatusResponse>, AppError> {
-    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
-    let session_id = Uuid::parse_str(&session_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    Query(query): Query<RegenerateQuery>,
+) -> Result<Response, AppError> {
+    ExpiryReminderService::find_by_token(&state.db, &query.token)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Regeneration link not found or expired".to_string()))?;
+
+    let page = format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<meta name="viewport" content="width=device-width, initial-scale=1">
+<title>Renew your credit proof</title>
+</head>
+<body>
+<form method="post" action="/api/proofs/regenerate">
+<input type="hidden" name="token" value="{token}">
+<p>Generate a new credit proof with the same settings over your latest transactions?</p>
+<button type="submit">Renew proof</button>
+</form>
+</body>
+</html>
+"#,
+        token = crate::handlers::verification::html_escape(&query.token),
+    );
+    Response::builder()
+        .header(header::CONTENT_TYPE, "text/html; charset=utf-8")
+        .body(Body::from(page))
+        .map_err(|e| AppError::Internal(e.into()))
+}
+
+/// Confirms an expiry reminder's link. Regenerates the proof with the same
+/// till, disclosure, threshold, policy and template over the latest data;
+/// the link's token stands in for signing in. Using it again returns the
+/// session it started.
+pub async fn regenerate_proof(
+    State(state): State<AppState>,
+    Form(query): Form<RegenerateQuery>,
+) -> Result<Json<GenerateProofResponse>, AppError> {
+    let (session_id, regenerated) = ExpiryReminderService::find_by_token(&state.db, &query.token)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Regeneration link not found or expired".to_string()))?;
+
+    if let Some(regenerated) = regenerated {
+        let status: String = sqlx::query_scalar("SELECT status::text FROM proof_sessions WHERE id = $1")
+            .bind(regenerated)
+            .fetch_one(&state.db)
+            .await?;
+        return Ok(Json(GenerateProofResponse {
+            session_id: regenerated.to_string(),
+            status,
+            estimated_time: 30,
+            existing: true,
+        }));
+    }
 
     let row = sqlx::query(
         r#"
-        SELECT status, progress, error_message
+        SELECT user_id, till_id, disclosure_policy, secondary_source, score_threshold, account_number,
+               scoring_policy_id, input_mode, template_id, data_source
         FROM proof_sessions
-        WHERE id = $1 AND user_id = $2
+        WHERE id = $1
         "#,
     )
     .bind(session_id)
-    .bind(user_id)
-    .fetch_optional(&state.db)
+    .fetch_one(&state.db)
     .await?;
 
-    let session = if let Some(row) = row {
-        Some((
-            row.try_get::<crate::models::ProofStatus, _>(0).ok(),
-            row.try_get::<Option<i32>, _>(1).ok().flatten(),
-            row.try_get::<Option<String>, _>(2).ok().flatten(),
-        ))
-    } else {
-        None
+    let user_id: Uuid = row.try_get(0)?;
+    let req = GenerateProofRequest {
+        till_id: row.try_get::<Uuid, _>(1)?.to_string(),
+        // Pulls a fresh statement where Daraja is set up, else uses what's
+        // stored; a verified API proof stays one
+        data_source: match row.try_get::<String, _>(9)?.as_str() {
+            "verified_api" => "verified_api".to_string(),
+            _ => "api".to_string(),
+        },
+        date_range: None,
+        disclosure: serde_json::from_value(row.try_get(2)?).unwrap_or_default(),
+        secondary_source: row.try_get(3)?,
+        score_threshold: row.try_get::<Option<i32>, _>(4)?.map(|t| t as u32),
+        account_number: row.try_get(5)?,
+        scoring_policy_id: row.try_get::<Option<Uuid>, _>(6)?.map(|id| id.to_string()),
+        input_mode: Some(row.try_get(7)?),
+        template_id: row.try_get::<Option<Uuid>, _>(8)?.map(|id| id.to_string()),
+        force: false,
+        referral_code: None,
+    };
+
+    let response = start_proof(&state, user_id, req).await?;
+    let new_session_id = Uuid::parse_str(&response.session_id).map_err(|e| AppError::Internal(e.into()))?;
+    ExpiryReminderService::mark_used(&state.db, &query.token, new_session_id).await?;
+
+    Ok(Json(response))
+}
+
+/// Scores the till natively with the guest's own logic so the user sees a
+/// likely score in seconds. The result is unsigned and never stored.
+pub async fn preview_proof(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(req): Json<PreviewProofRequest>,
+) -> Result<Json<PreviewProofResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let till_id = Uuid::parse_str(&req.till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let till_user_id = TillRepo::owner(state.read_db(), till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
+
+    if till_user_id != user_id {
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
+    }
+
+    if let Some(source) = req.secondary_source.as_deref() {
+        if source == "mpesa" || !crate::models::TRANSACTION_SOURCES.contains(&source) {
+            return Err(AppError::Validation(format!("Invalid secondary source: {}", source)));
+        }
+    }
+
+    validate_account_filter(&state, till_id, req.account_number.as_deref(), req.secondary_source.as_deref()).await?;
+
+    let currency = proof_currency(state.read_db(), till_id, req.secondary_source.as_deref()).await?;
+    let policy = scoring_policy(state.read_db(), req.scoring_policy_id.as_deref(), None, &currency).await?;
+    let policy = ProofService::policy_input(&currency, policy.as_ref());
+    let timezone = crate::handlers::data::till_timezone(state.read_db(), till_id).await?;
+    let transactions = TransactionRepo::for_proof(state.read_db(), till_id, req.secondary_source.as_deref()).await?;
+    let secondary_source = req.secondary_source.clone();
+    let account_number = req.account_number.clone();
+    let output = tokio::task::spawn_blocking(move || {
+        ProofService::score_natively(
+            transactions,
+            secondary_source.as_deref(),
+            account_number.as_deref(),
+            &currency,
+            policy,
+            timezone,
+        )
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.into()))?
+    .map_err(|e| AppError::Validation(e.to_string()))?;
+
+    let breakdown: crate::models::ScoreBreakdown =
+        serde_json::from_value(ProofService::breakdown_json(&output)?).map_err(anyhow::Error::from)?;
+    let timestamp = |secs: i64| {
+        chrono::DateTime::from_timestamp(secs, 0)
+            .unwrap_or_default()
+            .to_rfc3339()
     };
 
-    let (status_opt, progress, error_message) = session.ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
-    let status = status_opt.ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
+    Ok(Json(PreviewProofResponse {
+        label: "not a proof",
+        preliminary_score: output.credit_score,
+        metrics: ProofService::metrics_json(&output)?,
+        components: score_components(&breakdown),
+        period_start: timestamp(output.period_start),
+        period_end: timestamp(output.period_end),
+    }))
+}
+
+/// Makes a generate request meet a template: its scoring policy and the
+/// disclosures it requires. Choices that contradict the template are
+/// rejected rather than overridden.
+fn apply_template(req: &mut GenerateProofRequest, template: &crate::models::ProofTemplate) -> Result<(), AppError> {
+    if let Some(policy_id) = template.scoring_policy_id {
+        let policy_id = policy_id.to_string();
+        if req.scoring_policy_id.as_ref().is_some_and(|id| *id != policy_id) {
+            return Err(AppError::Validation(
+                "The template requires a different scoring policy".to_string(),
+            ));
+        }
+        req.scoring_policy_id = Some(policy_id);
+    }
+
+    if !template.required_disclosures.is_empty() && req.score_threshold.is_some() {
+        return Err(AppError::Validation(format!(
+            "The template requires disclosing {}, which a threshold proof doesn't",
+            template.required_disclosures.join(", ")
+        )));
+    }
+    if template.required_disclosures.iter().any(|f| f == "score_breakdown") {
+        req.disclosure.score_breakdown = true;
+    }
+
+    Ok(())
+}
+
+/// Account filtering applies to PayBill tills' M-Pesa payments only.
+async fn validate_account_filter(
+    state: &AppState,
+    till_id: Uuid,
+    account_number: Option<&str>,
+    secondary_source: Option<&str>,
+) -> Result<(), AppError> {
+    let Some(account_number) = account_number else {
+        return Ok(());
+    };
+
+    if secondary_source.is_some() {
+        return Err(AppError::Validation(
+            "account_number can't be combined with secondary_source".to_string(),
+        ));
+    }
+
+    let till_type: crate::models::TillType = sqlx::query_scalar("SELECT till_type FROM business_tills WHERE id = $1")
+        .bind(till_id)
+        .fetch_one(state.read_db())
+        .await?;
+    if !matches!(till_type, crate::models::TillType::PayBill) {
+        return Err(AppError::Validation("Account numbers only apply to PayBill tills".to_string()));
+    }
+
+    let known: bool = sqlx::query_scalar(
+        "SELECT EXISTS (SELECT 1 FROM transactions WHERE till_id = $1 AND account_number = $2)",
+    )
+    .bind(till_id)
+    .bind(account_number)
+    .fetch_one(state.read_db())
+    .await?;
+    if !known {
+        return Err(AppError::Validation(format!(
+            "No transactions found for account number {}",
+            account_number
+        )));
+    }
+
+    Ok(())
+}
+
+/// The single currency a proof's transactions are in. Bands can't be
+/// compared across currencies, so a till whose M-Pesa and secondary rows
+/// mix them is rejected; a till with no rows yet scores in the default.
+async fn proof_currency(
+    db: &sqlx::PgPool,
+    till_id: Uuid,
+    secondary_source: Option<&str>,
+) -> Result<crate::models::Currency, AppError> {
+    let codes = TransactionRepo::currencies_for_proof(db, till_id, secondary_source).await?;
+    let code = match codes.as_slice() {
+        [] => crate::models::DEFAULT_CURRENCY,
+        [code] => code.as_str(),
+        _ => {
+            return Err(AppError::Validation(format!(
+                "A proof can only cover one currency, but these transactions are in {}",
+                codes.join(", ")
+            )))
+        }
+    };
+
+    CurrencyRepo::find(db, code)
+        .await?
+        .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Currency {} is not configured", code)))
+}
+
+/// Loads the scoring policy a request names. Its thresholds are in major
+/// units of one currency, so it must match the proof's. Market-wide
+/// policies are open to any merchant; a lender's own only come through that
+/// lender's template, passed as `template_lender_id`.
+async fn scoring_policy(
+    db: &sqlx::PgPool,
+    policy_id: Option<&str>,
+    template_lender_id: Option<Uuid>,
+    currency: &crate::models::Currency,
+) -> Result<Option<crate::models::ScoringPolicy>, AppError> {
+    let Some(policy_id) = policy_id else {
+        return Ok(None);
+    };
+    let policy_id = Uuid::parse_str(policy_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let policy = ScoringPolicyRepo::find(db, policy_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Scoring policy not found".to_string()))?;
+    if policy.lender_id.is_some_and(|id| Some(id) != template_lender_id) {
+        return Err(AppError::NotFound("Scoring policy not found".to_string()));
+    }
+    if policy.currency != currency.code {
+        return Err(AppError::Validation(format!(
+            "Scoring policy is for {} but these transactions are in {}",
+            policy.currency, currency.code
+        )));
+    }
+
+    Ok(Some(policy))
+}
+
+/// The request's inclusive `YYYY-MM-DD` range as dates.
+fn requested_range(date_range: Option<&DateRange>) -> Result<Option<(chrono::NaiveDate, chrono::NaiveDate)>, AppError> {
+    let parse = |value: &str| {
+        chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d")
+            .map_err(|_| AppError::Validation(format!("Invalid date: {}", value)))
+    };
+
+    date_range.map(|range| Ok((parse(&range.from)?, parse(&range.to)?))).transpose()
+}
+
+fn pull_window(
+    date_range: Option<&DateRange>,
+) -> Result<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>), AppError> {
+    match requested_range(date_range)? {
+        Some((from, to)) => Ok((
+            from.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+            to.and_hms_opt(0, 0, 0).unwrap().and_utc() + chrono::Duration::days(1),
+        )),
+        None => {
+            // The guest only scores the last six months
+            let end = chrono::Utc::now();
+            Ok((end - chrono::Duration::days(6 * 30), end))
+        }
+    }
+}
+
+pub async fn get_proof_status(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(session_id): Path<String>,
+) -> Result<Json<ProofStatusResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let session_id = Uuid::parse_str(&session_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let session = SessionRepo::status_for_user(state.read_db(), session_id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
 
     Ok(Json(ProofStatusResponse {
-        status: format!("{:?}", status),
-        progress,
-        error: error_message,
+        status: format!("{:?}", session.status),
+        progress: session.progress,
+        error: session.error_message,
     }))
 }
 
+#[derive(Serialize)]
+pub struct SessionEventResponse {
+    pub stage: crate::models::SessionStage,
+    /// The worker for picked_up, the reason for failed
+    pub detail: Option<String>,
+    pub at: String,
+}
+
+impl From<crate::db::repos::sessions::SessionEvent> for SessionEventResponse {
+    fn from(event: crate::db::repos::sessions::SessionEvent) -> Self {
+        Self {
+            stage: event.stage,
+            detail: event.detail,
+            at: event.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Every step the session has been through, oldest first, so a stuck
+/// session shows where it stopped.
+pub async fn get_proof_events(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(session_id): Path<String>,
+) -> Result<Json<Vec<SessionEventResponse>>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let session_id = Uuid::parse_str(&session_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let events = SessionRepo::events(state.read_db(), session_id, Some(user_id))
+        .await?
+        .ok_or_else(|| AppError::NotFound("Session not found".to_string()))?;
+
+    Ok(Json(events.into_iter().map(Into::into).collect()))
+}
+
 pub async fn get_proof_result(
     State(state): State<AppState>,
     claims: Claims,
@@ -137,7 +704,8 @@ pub async fn get_proof_result(
 
     let row = sqlx::query(
         r#"
-        SELECT id, credit_score, metrics, verification_code, expires_at
+        SELECT id, credit_score, metrics, verification_code, expires_at, proof_type, score_threshold, meets_threshold,
+               image_id, guest_version, prover_backend
         FROM proof_sessions
         WHERE id = $1 AND user_id = $2 AND status = 'completed'
         "#,
@@ -150,56 +718,174 @@ pub async fn get_proof_result(
     let row = row.ok_or_else(|| AppError::NotFound("Proof not found or not completed".to_string()))?;
 
     let id: Uuid = row.try_get(0).map_err(|e| AppError::Database(e))?;
-    let credit_score: Option<i32> = row.try_get(1).ok();
-    let metrics: Option<serde_json::Value> = row.try_get(2).ok();
+    let credit_score: Option<Option<i32>> = row.try_get(1).ok();
+    let metrics: Option<Option<serde_json::Value>> = row.try_get(2).ok();
     let verification_code: String = row.try_get(3).map_err(|e| AppError::Database(e))?;
     let expires_at: chrono::DateTime<chrono::Utc> = row.try_get(4).map_err(|e| AppError::Database(e))?;
+    let proof_type: String = row.try_get(5).map_err(|e| AppError::Database(e))?;
+    let threshold = threshold_result(
+        row.try_get(6).map_err(|e| AppError::Database(e))?,
+        row.try_get(7).map_err(|e| AppError::Database(e))?,
+    );
+    let prover = crate::models::ProverInfo::from_columns(
+        row.try_get(8).map_err(|e| AppError::Database(e))?,
+        row.try_get(9).map_err(|e| AppError::Database(e))?,
+        row.try_get(10).map_err(|e| AppError::Database(e))?,
+    );
 
     let verification_url = format!("https://app.domain.com/verify/{}", verification_code);
 
     Ok(Json(ProofResultResponse {
         proof_id: id.to_string(),
-        credit_score: credit_score.unwrap_or(0),
-        metrics: metrics.unwrap_or(serde_json::json!({})),
+        proof_type,
+        credit_score: credit_score.flatten(),
+        metrics: metrics.flatten().unwrap_or(serde_json::json!({})),
+        threshold,
         verification_url,
         expires_at: expires_at.to_rfc3339(),
+        prover,
     }))
 }
 
-pub async fn list_proofs(
+/// Builds the threshold outcome from a session's stored columns; `None` for
+/// full proofs.
+pub fn threshold_result(
+    score_threshold: Option<i32>,
+    meets_threshold: Option<bool>,
+) -> Option<crate::models::ThresholdResult> {
+    Some(crate::models::ThresholdResult {
+        score_at_least: score_threshold? as u32,
+        meets_threshold: meets_threshold?,
+    })
+}
+
+pub async fn get_proof_breakdown(
     State(state): State<AppState>,
     claims: Claims,
-) -> Result<Json<Vec<serde_json::Value>>, AppError> {
+    Path(session_id): Path<String>,
+) -> Result<Json<ProofBreakdownResponse>, AppError> {
     let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let session_id = Uuid::parse_str(&session_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
 
-    let rows = sqlx::query(
+    let row = sqlx::query(
         r#"
-        SELECT id, till_id, status, credit_score, created_at
+        SELECT credit_score, score_breakdown
         FROM proof_sessions
-        WHERE user_id = $1
-        ORDER BY created_at DESC
-        LIMIT 50
+        WHERE id = $1 AND user_id = $2 AND status = 'completed'
         "#,
     )
+    .bind(session_id)
     .bind(user_id)
-    .fetch_all(&state.db)
+    .fetch_optional(&state.db)
     .await?;
 
-    let response: Vec<serde_json::Value> = rows
+    let row = row.ok_or(AppError::ProofNotFound)?;
+
+    let credit_score: Option<i32> = row.try_get(0).map_err(AppError::Database)?;
+    let breakdown: Option<serde_json::Value> = row.try_get(1).map_err(AppError::Database)?;
+
+    // Proofs generated before breakdowns were committed have none
+    let breakdown = breakdown
+        .ok_or_else(|| AppError::NotFound("No score breakdown recorded for this proof".to_string()))?;
+    let breakdown: crate::models::ScoreBreakdown =
+        serde_json::from_value(breakdown).map_err(|e| AppError::Internal(e.into()))?;
+
+    Ok(Json(ProofBreakdownResponse {
+        proof_id: session_id.to_string(),
+        credit_score: credit_score.unwrap_or(0),
+        components: score_components(&breakdown),
+        outlier_adjustment: breakdown.outlier_adjustment,
+    }))
+}
+
+/// Each component with the points earned (or deducted) and the points
+/// available, so a lender can see why a score is what it is.
+pub fn score_components(breakdown: &crate::models::ScoreBreakdown) -> Vec<ScoreComponent> {
+    let points = [
+        breakdown.volume_points,
+        breakdown.consistency_points,
+        breakdown.activity_points,
+        breakdown.growth_points,
+        breakdown.diversity_points,
+        breakdown.concentration_penalty,
+        breakdown.volatility_penalty,
+    ];
+    proof_core::SCORE_COMPONENTS
         .into_iter()
-        .map(|row| {
-            let id: Uuid = row.try_get(0).unwrap();
-            let till_id: Uuid = row.try_get(1).unwrap();
-            let status: crate::models::ProofStatus = row.try_get(2).unwrap();
-            let credit_score: Option<i32> = row.try_get(3).ok();
-            let created_at: chrono::DateTime<chrono::Utc> = row.try_get(4).unwrap();
+        .zip(points)
+        .map(|((name, max_points, penalty), points)| ScoreComponent {
+            name: name.to_string(),
+            points,
+            max_points,
+            penalty,
+        })
+        .collect()
+}
+
+/// Streams the raw receipt so large STARK receipts never sit in a JSON body.
+pub async fn download_receipt(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(session_id): Path<String>,
+) -> Result<Response, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let session_id = Uuid::parse_str(&session_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
 
+    let row = sqlx::query(
+        r#"
+        SELECT receipt_key, receipt_sha256, receipt_size
+        FROM proof_sessions
+        WHERE id = $1 AND user_id = $2 AND status = 'completed'
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    let row = row.ok_or(AppError::ProofNotFound)?;
+    let receipt_key: Option<String> = row.try_get(0).map_err(AppError::Database)?;
+    let receipt_sha256: Option<String> = row.try_get(1).map_err(AppError::Database)?;
+    let receipt_size: Option<i64> = row.try_get(2).map_err(AppError::Database)?;
+
+    let receipt_key = receipt_key.ok_or_else(|| AppError::NotFound("No stored receipt for this proof".to_string()))?;
+    let reader = state.storage.open(&receipt_key).await?;
+
+    let mut response = Response::builder()
+        .header(header::CONTENT_TYPE, "application/octet-stream")
+        .header(
+            header::CONTENT_DISPOSITION,
+            format!("attachment; filename=\"receipt-{}.bin\"", session_id),
+        );
+    if let Some(size) = receipt_size {
+        response = response.header(header::CONTENT_LENGTH, size);
+    }
+    if let Some(sha256) = receipt_sha256 {
+        response = response.header("x-receipt-sha256", sha256);
+    }
+
+    response
+        .body(Body::from_stream(ReaderStream::new(reader)))
+        .map_err(|e| AppError::Internal(e.into()))
+}
+
+pub async fn list_proofs(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<serde_json::Value>>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let sessions = SessionRepo::list_for_user(state.read_db(), user_id, 50).await?;
+
+    let response: Vec<serde_json::Value> = sessions
+        .into_iter()
+        .map(|session| {
             serde_json::json!({
-                "id": id.to_string(),
-                "till_id": till_id.to_string(),
-                "status": format!("{:?}", status),
-                "credit_score": credit_score,
-                "created_at": created_at.to_rfc3339(),
+                "id": session.id.to_string(),
+                "till_id": session.till_id.to_string(),
+                "status": format!("{:?}", session.status),
+                "credit_score": session.credit_score,
+                "created_at": session.created_at.to_rfc3339(),
             })
         })
         .collect();
@@ -0,0 +1,198 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::repos::referrals::{Partner, ReferralCode};
+use crate::db::repos::ReferralCodeRepo;
+use crate::error::AppError;
+use crate::handlers::AppState;
+use crate::middleware::admin::AdminAuth;
+use crate::middleware::lender::LenderAuth;
+
+/// Attempts at a generated code before giving up on collisions.
+const GENERATE_ATTEMPTS: usize = 5;
+
+#[derive(Deserialize)]
+pub struct CreateReferralCodeRequest {
+    /// Letters, digits and dashes; generated when absent. Matched
+    /// case-insensitively.
+    pub code: Option<String>,
+    /// The partner lender; give this or `ambassador_name`
+    pub lender_id: Option<String>,
+    /// The ambassador handing the code out
+    pub ambassador_name: Option<String>,
+    /// A coupon: proofs past the merchant's plan allowance go through
+    /// without payment
+    #[serde(default)]
+    pub waives_payment: bool,
+    pub max_redemptions: Option<i32>,
+    /// Redemptions one merchant may make; unlimited when absent, except for
+    /// coupons, which default to 1
+    pub max_redemptions_per_user: Option<i32>,
+    /// RFC 3339
+    pub expires_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ReferralCodeResponse {
+    pub id: String,
+    pub code: String,
+    pub lender_id: Option<String>,
+    pub ambassador_name: Option<String>,
+    pub waives_payment: bool,
+    pub max_redemptions: Option<i32>,
+    pub max_redemptions_per_user: Option<i32>,
+    /// Proofs generated with the code
+    pub redemptions: i32,
+    /// Of those, the ones that completed
+    pub completed_proofs: i64,
+    pub expires_at: Option<String>,
+    pub active: bool,
+    pub created_at: String,
+}
+
+impl From<ReferralCode> for ReferralCodeResponse {
+    fn from(code: ReferralCode) -> Self {
+        Self {
+            id: code.id.to_string(),
+            code: code.code,
+            lender_id: code.lender_id.map(|id| id.to_string()),
+            ambassador_name: code.ambassador_name,
+            waives_payment: code.waives_payment,
+            max_redemptions: code.max_redemptions,
+            max_redemptions_per_user: code.max_redemptions_per_user,
+            redemptions: code.redemptions,
+            completed_proofs: code.completed_proofs,
+            expires_at: code.expires_at.map(|t| t.to_rfc3339()),
+            active: code.active,
+            created_at: code.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Creates a code for a partner lender or ambassador.
+pub async fn create_referral_code(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Json(req): Json<CreateReferralCodeRequest>,
+) -> Result<Json<ReferralCodeResponse>, AppError> {
+    let lender_id = req
+        .lender_id
+        .as_deref()
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let ambassador_name = req.ambassador_name.as_deref().map(str::trim).filter(|n| !n.is_empty());
+    let partner = match (lender_id, ambassador_name) {
+        (Some(lender_id), None) => {
+            let exists: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM lenders WHERE id = $1)")
+                .bind(lender_id)
+                .fetch_one(&state.db)
+                .await?;
+            if !exists {
+                return Err(AppError::NotFound("Lender not found".to_string()));
+            }
+            Partner::Lender(lender_id)
+        }
+        (None, Some(name)) if name.len() <= 128 => Partner::Ambassador(name),
+        (None, Some(_)) => return Err(AppError::Validation("Invalid ambassador_name".to_string())),
+        _ => return Err(AppError::Validation("Exactly one of lender_id and ambassador_name is required".to_string())),
+    };
+
+    if req.max_redemptions.is_some_and(|max| max < 1) {
+        return Err(AppError::Validation("max_redemptions must be at least 1".to_string()));
+    }
+    if req.max_redemptions_per_user.is_some_and(|max| max < 1) {
+        return Err(AppError::Validation("max_redemptions_per_user must be at least 1".to_string()));
+    }
+    let max_redemptions_per_user = match req.max_redemptions_per_user {
+        None if req.waives_payment => Some(1),
+        max => max,
+    };
+    let expires_at = req
+        .expires_at
+        .as_deref()
+        .map(|value| {
+            chrono::DateTime::parse_from_rfc3339(value)
+                .map(|t| t.with_timezone(&chrono::Utc))
+                .map_err(|_| AppError::Validation(format!("Invalid timestamp: {}", value)))
+        })
+        .transpose()?;
+    if expires_at.is_some_and(|at| at <= chrono::Utc::now()) {
+        return Err(AppError::Validation("expires_at must be in the future".to_string()));
+    }
+
+    let (waives_payment, max_redemptions) = (req.waives_payment, req.max_redemptions);
+    let create = |code: String| {
+        let (db, partner) = (&state.db, &partner);
+        async move {
+            ReferralCodeRepo::create(
+                db,
+                &code,
+                partner,
+                waives_payment,
+                max_redemptions,
+                max_redemptions_per_user,
+                expires_at,
+            )
+            .await
+        }
+    };
+    let created = match req.code.as_deref().map(str::trim) {
+        Some(code) => {
+            let valid = (4..=32).contains(&code.len()) && code.chars().all(|c| c.is_ascii_alphanumeric() || c == '-');
+            if !valid {
+                return Err(AppError::Validation("code must be 4 to 32 letters, digits or dashes".to_string()));
+            }
+            create(code.to_ascii_uppercase())
+                .await?
+                .ok_or_else(|| AppError::Validation(format!("Referral code {} is taken", code)))?
+        }
+        None => {
+            let mut created = None;
+            for _ in 0..GENERATE_ATTEMPTS {
+                created = create(crate::utils::generate_referral_code()).await?;
+                if created.is_some() {
+                    break;
+                }
+            }
+            created.ok_or_else(|| AppError::Internal(anyhow::anyhow!("No free referral code after retries")))?
+        }
+    };
+    Ok(Json(created.into()))
+}
+
+/// Every code, with the proofs attributed to each.
+pub async fn list_referral_codes(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> Result<Json<Vec<ReferralCodeResponse>>, AppError> {
+    let codes = ReferralCodeRepo::list(state.read_db(), None).await?;
+    Ok(Json(codes.into_iter().map(Into::into).collect()))
+}
+
+/// Stops a code being redeemed. Proofs generated with it stay attributed.
+pub async fn deactivate_referral_code(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(code_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    let code_id = Uuid::parse_str(&code_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    if !ReferralCodeRepo::deactivate(&state.db, code_id).await? {
+        return Err(AppError::NotFound("Active referral code not found".to_string()));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The lender's own codes and the proofs attributed to them.
+pub async fn lender_referral_codes(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+) -> Result<Json<Vec<ReferralCodeResponse>>, AppError> {
+    let codes = ReferralCodeRepo::list(state.read_db(), Some(lender.lender_id)).await?;
+    Ok(Json(codes.into_iter().map(Into::into).collect()))
+}
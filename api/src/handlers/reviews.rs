@@ -0,0 +1,356 @@
+use axum::{
+    extract::{Path, Query, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::repos::reviews::{ConfirmedRow, PendingReview, REVIEW_STATUSES};
+use crate::db::repos::{ReviewRepo, TillRepo};
+use crate::error::AppError;
+use crate::handlers::{AppState, Claims};
+use crate::i18n::Message;
+use crate::middleware::admin::AdminAuth;
+use crate::middleware::locale::current_locale;
+use crate::services::authenticity::{flag_reason, AuthenticityService, StatementLine};
+use crate::services::proof::ProofService;
+
+const MAX_RECEIPT_LEN: usize = 64;
+const MAX_TRANSACTION_TYPE_LEN: usize = 50;
+const MAX_NOTE_LEN: usize = 500;
+/// Pending rows listed to operators at once.
+const MAX_ADMIN_REVIEWS: i64 = 200;
+
+/// Checks whose rows only an operator may confirm: a receipt stored with
+/// other details can't be vouched for by the till that reused it.
+const ADMIN_ONLY_CHECKS: [&str; 1] = ["receipt_collisions"];
+
+#[derive(Deserialize)]
+pub struct ListReviewsQuery {
+    /// "pending" (the default), "confirmed" or "rejected"
+    pub status: Option<String>,
+}
+
+#[derive(Deserialize)]
+pub struct AdminReviewsQuery {
+    pub limit: Option<i64>,
+}
+
+#[derive(Serialize)]
+pub struct ReviewResponse {
+    pub id: String,
+    pub till_id: String,
+    /// The photo the row was read from
+    pub image_id: Option<String>,
+    /// "ocr", or the authenticity check the row failed
+    pub flagged_by: String,
+    pub reason: String,
+    /// 1-based spreadsheet row, or line of the photo
+    pub row: i32,
+    /// The line as OCR read it, while pending
+    pub text: Option<String>,
+    /// 0-100, for OCR rows
+    pub confidence: Option<i16>,
+    /// What could be read of the row; unset fields must be supplied to
+    /// confirm it. The receipt number is only shown while pending.
+    pub receipt_number: Option<String>,
+    pub timestamp: Option<String>,
+    /// Minor units, negative when money went out
+    pub amount: Option<i64>,
+    pub transaction_type: Option<String>,
+    pub currency: String,
+    pub status: String,
+    /// Whether an operator resolved it rather than the owner
+    pub resolved_by_admin: bool,
+    pub resolution_note: Option<String>,
+    pub resolved_at: Option<String>,
+    pub created_at: String,
+}
+
+impl From<PendingReview> for ReviewResponse {
+    fn from(review: PendingReview) -> Self {
+        Self {
+            id: review.id.to_string(),
+            till_id: review.till_id.to_string(),
+            image_id: review.image_id.map(|id| id.to_string()),
+            flagged_by: review.flagged_by,
+            reason: review.reason,
+            row: review.row_number,
+            text: review.raw_text,
+            confidence: review.confidence,
+            receipt_number: review.receipt_number,
+            timestamp: review.timestamp.map(|t| t.to_rfc3339()),
+            amount: review.amount,
+            transaction_type: review.transaction_type,
+            currency: review.currency,
+            status: review.status,
+            resolved_by_admin: review.resolved_by_admin,
+            resolution_note: review.resolution_note,
+            resolved_at: review.resolved_at.map(|t| t.to_rfc3339()),
+            created_at: review.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Corrections to a held row; fields left out keep what was read, so `{}`
+/// confirms it as read.
+#[derive(Deserialize)]
+pub struct ConfirmReviewRequest {
+    pub receipt_number: Option<String>,
+    /// RFC 3339
+    pub timestamp: Option<String>,
+    /// Minor units, negative when money went out
+    pub amount: Option<i64>,
+    pub transaction_type: Option<String>,
+    pub note: Option<String>,
+}
+
+#[derive(Deserialize, Default)]
+pub struct RejectReviewRequest {
+    pub note: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct ResolveReviewResponse {
+    pub id: String,
+    pub status: String,
+    /// False when the till already had the confirmed transaction
+    pub transaction_imported: bool,
+}
+
+/// The till's rows held for review, in the order they were read.
+pub async fn list_reviews(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(till_id): Path<String>,
+    Query(query): Query<ListReviewsQuery>,
+) -> Result<Json<Vec<ReviewResponse>>, AppError> {
+    let till_id = owned_till(&state, &claims, &till_id).await?;
+    let status = query.status.as_deref().unwrap_or("pending");
+    if !REVIEW_STATUSES.contains(&status) {
+        return Err(AppError::Validation(format!("Unknown status: {}", status)));
+    }
+
+    let reviews = ReviewRepo::list_for_till(&state.db, till_id, status).await?;
+    Ok(Json(reviews.into_iter().map(ReviewResponse::from).collect()))
+}
+
+/// Imports a held row, as read or corrected, so proofs score it.
+pub async fn confirm_review(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((till_id, review_id)): Path<(String, String)>,
+    Json(req): Json<ConfirmReviewRequest>,
+) -> Result<Json<ResolveReviewResponse>, AppError> {
+    claims.require_owner()?;
+    let review = till_review(&state, &claims, &till_id, &review_id).await?;
+    if ADMIN_ONLY_CHECKS.contains(&review.flagged_by.as_str()) {
+        return Err(AppError::Auth(
+            "This row's receipt is recorded elsewhere; it can only be confirmed by support".to_string(),
+        ));
+    }
+    confirm(&state, review, req, false).await.map(Json)
+}
+
+/// Leaves a held row out of proofs for good.
+pub async fn reject_review(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((till_id, review_id)): Path<(String, String)>,
+    req: Option<Json<RejectReviewRequest>>,
+) -> Result<Json<ResolveReviewResponse>, AppError> {
+    claims.require_owner()?;
+    let review = till_review(&state, &claims, &till_id, &review_id).await?;
+    let req = req.map(|Json(req)| req).unwrap_or_default();
+    reject(&state, review, req, false).await.map(Json)
+}
+
+/// Rows pending review across every till, oldest first.
+pub async fn list_pending_reviews(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Query(query): Query<AdminReviewsQuery>,
+) -> Result<Json<Vec<ReviewResponse>>, AppError> {
+    let limit = query.limit.unwrap_or(50).clamp(1, MAX_ADMIN_REVIEWS);
+    let reviews = ReviewRepo::list_pending(state.read_db(), limit).await?;
+    Ok(Json(reviews.into_iter().map(ReviewResponse::from).collect()))
+}
+
+/// Confirms any held row on the owner's behalf, including rows only an
+/// operator may confirm.
+pub async fn admin_confirm_review(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(review_id): Path<String>,
+    Json(req): Json<ConfirmReviewRequest>,
+) -> Result<Json<ResolveReviewResponse>, AppError> {
+    let review = pending_review(&state, &review_id).await?;
+    confirm(&state, review, req, true).await.map(Json)
+}
+
+pub async fn admin_reject_review(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(review_id): Path<String>,
+    req: Option<Json<RejectReviewRequest>>,
+) -> Result<Json<ResolveReviewResponse>, AppError> {
+    let review = pending_review(&state, &review_id).await?;
+    let req = req.map(|Json(req)| req).unwrap_or_default();
+    reject(&state, review, req, true).await.map(Json)
+}
+
+async fn owned_till(state: &AppState, claims: &Claims, till_id: &str) -> Result<Uuid, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let till_id = Uuid::parse_str(till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let owner = TillRepo::owner(&state.db, till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
+    if owner != user_id {
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
+    }
+    Ok(till_id)
+}
+
+/// A pending row on one of the caller's tills.
+async fn till_review(
+    state: &AppState,
+    claims: &Claims,
+    till_id: &str,
+    review_id: &str,
+) -> Result<PendingReview, AppError> {
+    let till_id = owned_till(state, claims, till_id).await?;
+    let review = pending_review(state, review_id).await?;
+    if review.till_id != till_id {
+        return Err(AppError::NotFound("Review not found".to_string()));
+    }
+    Ok(review)
+}
+
+async fn pending_review(state: &AppState, review_id: &str) -> Result<PendingReview, AppError> {
+    let review_id = Uuid::parse_str(review_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let review = ReviewRepo::get(&state.db, review_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Review not found".to_string()))?;
+    if review.status != "pending" {
+        return Err(AppError::Validation(format!("Row has already been {}", review.status)));
+    }
+    Ok(review)
+}
+
+/// Trimmed, and None when blank.
+fn resolution_note(note: Option<&str>) -> Result<Option<&str>, AppError> {
+    let note = note.map(str::trim).filter(|n| !n.is_empty());
+    if note.is_some_and(|n| n.chars().count() > MAX_NOTE_LEN) {
+        return Err(AppError::Validation(format!("note is longer than {} characters", MAX_NOTE_LEN)));
+    }
+    Ok(note)
+}
+
+async fn confirm(
+    state: &AppState,
+    review: PendingReview,
+    req: ConfirmReviewRequest,
+    by_admin: bool,
+) -> Result<ResolveReviewResponse, AppError> {
+    let note = resolution_note(req.note.as_deref())?;
+    let receipt_number = req
+        .receipt_number
+        .as_deref()
+        .or(review.receipt_number.as_deref())
+        .map(str::trim)
+        .filter(|r| !r.is_empty())
+        .ok_or_else(|| AppError::Validation("receipt_number is required".to_string()))?;
+    if receipt_number.len() > MAX_RECEIPT_LEN || !receipt_number.bytes().all(|b| b.is_ascii_graphic()) {
+        return Err(AppError::Validation("Invalid receipt_number".to_string()));
+    }
+    let timestamp = match req.timestamp.as_deref() {
+        Some(value) => chrono::DateTime::parse_from_rfc3339(value)
+            .map(|t| t.with_timezone(&chrono::Utc))
+            .map_err(|_| AppError::Validation(format!("Invalid timestamp: {}", value)))?,
+        None => review
+            .timestamp
+            .ok_or_else(|| AppError::Validation("timestamp is required".to_string()))?,
+    };
+    let amount = req
+        .amount
+        .or(review.amount)
+        .filter(|&a| a != 0)
+        .ok_or_else(|| AppError::Validation("A non-zero amount is required".to_string()))?;
+    let transaction_type = req
+        .transaction_type
+        .as_deref()
+        .or(review.transaction_type.as_deref())
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .unwrap_or("Payment");
+    if transaction_type.chars().count() > MAX_TRANSACTION_TYPE_LEN {
+        return Err(AppError::Validation(format!(
+            "transaction_type is longer than {} characters",
+            MAX_TRANSACTION_TYPE_LEN
+        )));
+    }
+
+    // Whatever the owner typed rather than what was read is theirs alone:
+    // it's checked again as far as one row can be, and scored as a
+    // correction. Operators vouch for their own corrections.
+    let corrected = review.receipt_number.as_deref() != Some(receipt_number)
+        || review.timestamp != Some(timestamp)
+        || review.amount != Some(amount)
+        || review.transaction_type.as_deref() != Some(transaction_type);
+    let mut provenance =
+        proof_core::Provenance::from_name(&review.provenance).unwrap_or(proof_core::Provenance::CsvUpload);
+    if corrected && !by_admin {
+        let line = StatementLine {
+            row: review.row_number as usize,
+            timestamp,
+            amount,
+            reference: receipt_number,
+            balance: None,
+            fee: 0,
+        };
+        if let Some(check) = AuthenticityService::recheck(&state.db, review.till_id, &review.source, &line).await? {
+            return Err(AppError::Validation(format!(
+                "{}; only support can confirm this correction",
+                flag_reason(check)
+            )));
+        }
+        provenance = proof_core::Provenance::OwnerCorrected;
+    }
+
+    let row = ConfirmedRow {
+        receipt_number,
+        timestamp,
+        amount,
+        transaction_type,
+        provenance,
+    };
+    let imported = ReviewRepo::confirm(&state.db, &review, &row, by_admin, note)
+        .await?
+        .ok_or_else(|| AppError::Validation("Row has already been resolved".to_string()))?;
+    if imported {
+        ProofService::refresh_completeness(&state.db, review.till_id).await?;
+    }
+
+    Ok(ResolveReviewResponse {
+        id: review.id.to_string(),
+        status: "confirmed".to_string(),
+        transaction_imported: imported,
+    })
+}
+
+async fn reject(
+    state: &AppState,
+    review: PendingReview,
+    req: RejectReviewRequest,
+    by_admin: bool,
+) -> Result<ResolveReviewResponse, AppError> {
+    let note = resolution_note(req.note.as_deref())?;
+    if !ReviewRepo::reject(&state.db, review.id, by_admin, note).await? {
+        return Err(AppError::Validation("Row has already been resolved".to_string()));
+    }
+    Ok(ResolveReviewResponse {
+        id: review.id.to_string(),
+        status: "rejected".to_string(),
+        transaction_imported: false,
+    })
+}
@@ -0,0 +1,244 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::repos::scoring_experiments::ScoringExperiment;
+use crate::db::repos::{CurrencyRepo, ScoringExperimentRepo, ScoringPolicyRepo};
+use crate::error::AppError;
+use crate::handlers::AppState;
+use crate::middleware::admin::AdminAuth;
+use crate::services::proof::ProofService;
+
+/// Labels of the 20-point buckets the report counts scores in.
+const SCORE_BUCKETS: [&str; 5] = ["0-19", "20-39", "40-59", "60-79", "80-100"];
+
+#[derive(Deserialize)]
+pub struct CreateScoringExperimentRequest {
+    /// The policy on trial
+    pub candidate_policy_id: String,
+    /// The policy it would replace; omit to trial it against the currency's
+    /// default thresholds
+    pub baseline_policy_id: Option<String>,
+    /// Share of the baseline's sessions also scored under the candidate
+    pub sample_percentage: i16,
+}
+
+#[derive(Serialize)]
+pub struct ScoringExperimentResponse {
+    pub id: String,
+    pub currency: String,
+    pub baseline_policy_id: Option<String>,
+    pub baseline_policy_hash: String,
+    pub candidate_policy_id: String,
+    pub sample_percentage: i16,
+    pub started_at: String,
+    pub ended_at: Option<String>,
+}
+
+impl From<ScoringExperiment> for ScoringExperimentResponse {
+    fn from(experiment: ScoringExperiment) -> Self {
+        Self {
+            id: experiment.id.to_string(),
+            currency: experiment.currency,
+            baseline_policy_id: experiment.baseline_policy_id.map(|id| id.to_string()),
+            baseline_policy_hash: experiment.baseline_policy_hash,
+            candidate_policy_id: experiment.candidate_policy_id.to_string(),
+            sample_percentage: experiment.sample_percentage,
+            started_at: experiment.started_at.to_rfc3339(),
+            ended_at: experiment.ended_at.map(|t| t.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ScoringExperimentReport {
+    pub experiment: ScoringExperimentResponse,
+    /// Sampled sessions so far
+    pub sessions: i64,
+    pub full_proofs: FullProofComparison,
+    pub threshold_proofs: ThresholdProofComparison,
+}
+
+#[derive(Serialize)]
+pub struct FullProofComparison {
+    pub compared: i64,
+    pub baseline: ScoreDistribution,
+    pub candidate: ScoreDistribution,
+    /// Mean of candidate minus baseline score, per session
+    pub mean_difference: Option<f64>,
+    pub scored_higher: i64,
+    pub scored_lower: i64,
+    pub unchanged: i64,
+    pub score_buckets: Vec<ScoreBucketComparison>,
+}
+
+#[derive(Serialize)]
+pub struct ScoreDistribution {
+    pub mean: Option<f64>,
+    pub p10: Option<f64>,
+    pub p25: Option<f64>,
+    pub median: Option<f64>,
+    pub p75: Option<f64>,
+    pub p90: Option<f64>,
+}
+
+impl ScoreDistribution {
+    fn new(mean: Option<f64>, percentiles: Option<Vec<f64>>) -> Self {
+        let percentile = |i: usize| percentiles.as_ref().and_then(|p| p.get(i).copied());
+        Self {
+            mean,
+            p10: percentile(0),
+            p25: percentile(1),
+            median: percentile(2),
+            p75: percentile(3),
+            p90: percentile(4),
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct ScoreBucketComparison {
+    pub range: String,
+    pub baseline: i64,
+    pub candidate: i64,
+}
+
+#[derive(Serialize)]
+pub struct ThresholdProofComparison {
+    pub compared: i64,
+    pub baseline_passed: i64,
+    pub candidate_passed: i64,
+    /// Sessions whose outcome the candidate would change
+    pub flipped: i64,
+}
+
+/// Starts sampling sessions proved under the baseline policy for scoring
+/// under the candidate as well.
+pub async fn create_scoring_experiment(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Json(req): Json<CreateScoringExperimentRequest>,
+) -> Result<Json<ScoringExperimentResponse>, AppError> {
+    if !(1..=100).contains(&req.sample_percentage) {
+        return Err(AppError::Validation("sample_percentage must be between 1 and 100".to_string()));
+    }
+    let candidate_policy_id = Uuid::parse_str(&req.candidate_policy_id)
+        .map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let baseline_policy_id = req
+        .baseline_policy_id
+        .as_deref()
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let candidate = ScoringPolicyRepo::find(&state.db, candidate_policy_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Scoring policy not found".to_string()))?;
+    let baseline_policy_hash = match baseline_policy_id {
+        Some(id) => {
+            let baseline = ScoringPolicyRepo::find(&state.db, id)
+                .await?
+                .ok_or_else(|| AppError::NotFound("Scoring policy not found".to_string()))?;
+            if baseline.currency != candidate.currency {
+                return Err(AppError::Validation("Both policies must be in the same currency".to_string()));
+            }
+            baseline.policy_hash
+        }
+        None => {
+            let currency = CurrencyRepo::find(&state.db, &candidate.currency)
+                .await?
+                .ok_or_else(|| AppError::NotFound("Currency not found".to_string()))?;
+            hex::encode(ProofService::policy_input(&currency, None).hash(&currency.code))
+        }
+    };
+    if baseline_policy_hash == candidate.policy_hash {
+        return Err(AppError::Validation("The candidate scores exactly as the baseline does".to_string()));
+    }
+
+    let experiment = ScoringExperimentRepo::create(
+        &state.db,
+        &candidate.currency,
+        baseline_policy_id,
+        &baseline_policy_hash,
+        candidate.id,
+        req.sample_percentage,
+    )
+    .await?;
+    Ok(Json(experiment.into()))
+}
+
+/// Every experiment, newest first, ended ones included.
+pub async fn list_scoring_experiments(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+) -> Result<Json<Vec<ScoringExperimentResponse>>, AppError> {
+    let experiments = ScoringExperimentRepo::list(state.read_db()).await?;
+    Ok(Json(experiments.into_iter().map(Into::into).collect()))
+}
+
+/// Stops sampling; the report stays available.
+pub async fn end_scoring_experiment(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(experiment_id): Path<String>,
+) -> Result<Json<ScoringExperimentResponse>, AppError> {
+    let experiment_id =
+        Uuid::parse_str(&experiment_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let experiment = ScoringExperimentRepo::end(&state.db, experiment_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Running scoring experiment not found".to_string()))?;
+    Ok(Json(experiment.into()))
+}
+
+/// How the candidate's scores compare with the baseline's on the sessions
+/// sampled so far.
+pub async fn scoring_experiment_report(
+    State(state): State<AppState>,
+    _admin: AdminAuth,
+    Path(experiment_id): Path<String>,
+) -> Result<Json<ScoringExperimentReport>, AppError> {
+    let experiment_id =
+        Uuid::parse_str(&experiment_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let experiment = ScoringExperimentRepo::find(state.read_db(), experiment_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Scoring experiment not found".to_string()))?;
+    let summary = ScoringExperimentRepo::summary(state.read_db(), experiment_id).await?;
+    let buckets = ScoringExperimentRepo::score_buckets(state.read_db(), experiment_id).await?;
+
+    let score_buckets = SCORE_BUCKETS
+        .iter()
+        .enumerate()
+        .map(|(i, range)| {
+            let counts = buckets.iter().find(|b| b.bucket == i as i32);
+            ScoreBucketComparison {
+                range: range.to_string(),
+                baseline: counts.map_or(0, |b| b.baseline),
+                candidate: counts.map_or(0, |b| b.candidate),
+            }
+        })
+        .collect();
+
+    Ok(Json(ScoringExperimentReport {
+        experiment: experiment.into(),
+        sessions: summary.sessions,
+        full_proofs: FullProofComparison {
+            compared: summary.scored,
+            baseline: ScoreDistribution::new(summary.baseline_mean, summary.baseline_percentiles),
+            candidate: ScoreDistribution::new(summary.candidate_mean, summary.candidate_percentiles),
+            mean_difference: summary.mean_difference,
+            scored_higher: summary.scored_higher,
+            scored_lower: summary.scored_lower,
+            unchanged: summary.scored - summary.scored_higher - summary.scored_lower,
+            score_buckets,
+        },
+        threshold_proofs: ThresholdProofComparison {
+            compared: summary.threshold_sessions,
+            baseline_passed: summary.baseline_passed,
+            candidate_passed: summary.candidate_passed,
+            flipped: summary.threshold_flipped,
+        },
+    }))
+}
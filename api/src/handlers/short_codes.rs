@@ -0,0 +1,130 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use redis::AsyncCommands;
+use serde::Serialize;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::handlers::{AppState, Claims};
+
+/// How long a short code works for, unless the proof expires first.
+const SHORT_CODE_TTL_MINUTES: i64 = 60;
+/// Short code lookups one lender may make per hour. A million codes are few
+/// enough to guess, so this is far below the verification code limits, and
+/// only authenticated lenders can look them up at all.
+const LOOKUPS_PER_HOUR: u32 = 20;
+/// Fresh codes tried before giving up on finding a free one.
+const MINT_ATTEMPTS: usize = 10;
+
+#[derive(Serialize)]
+pub struct ShortCodeResponse {
+    pub short_code: String,
+    pub verification_code: String,
+    pub expires_at: String,
+}
+
+/// Mints a six-digit code for a completed proof that's easier to read out
+/// over the phone than its verification code. For an hour, a lender can
+/// verify the proof with it in place of the verification code.
+pub async fn create_short_code(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(session_id): Path<String>,
+) -> Result<Json<ShortCodeResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let session_id = Uuid::parse_str(&session_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let row = sqlx::query(
+        r#"
+        SELECT verification_code, expires_at
+        FROM proof_sessions
+        WHERE id = $1 AND user_id = $2 AND status = 'completed'
+        "#,
+    )
+    .bind(session_id)
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or_else(|| AppError::NotFound("Proof not found or not completed".to_string()))?;
+
+    let verification_code: String = row.try_get(0)?;
+    let proof_expires_at: chrono::DateTime<chrono::Utc> = row.try_get(1)?;
+    if proof_expires_at < chrono::Utc::now() {
+        return Err(AppError::ProofExpired);
+    }
+    let expires_at = (chrono::Utc::now() + chrono::Duration::minutes(SHORT_CODE_TTL_MINUTES)).min(proof_expires_at);
+
+    // Frees the digits of lapsed codes for reuse
+    sqlx::query("DELETE FROM proof_short_codes WHERE expires_at <= NOW()")
+        .execute(&state.db)
+        .await?;
+
+    for _ in 0..MINT_ATTEMPTS {
+        let short_code = generate_short_code();
+        let inserted = sqlx::query(
+            r#"
+            INSERT INTO proof_short_codes (code, session_id, expires_at)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (code) DO NOTHING
+            "#,
+        )
+        .bind(&short_code)
+        .bind(session_id)
+        .bind(expires_at)
+        .execute(&state.db)
+        .await?
+        .rows_affected();
+
+        if inserted == 1 {
+            return Ok(Json(ShortCodeResponse {
+                short_code,
+                verification_code,
+                expires_at: expires_at.to_rfc3339(),
+            }));
+        }
+    }
+
+    Err(AppError::Internal(anyhow::anyhow!("No free short code after {} attempts", MINT_ATTEMPTS)))
+}
+
+/// Whether `code` is a short code rather than a verification code.
+pub fn is_short_code(code: &str) -> bool {
+    code.len() == 6 && code.bytes().all(|b| b.is_ascii_digit())
+}
+
+/// The verification code a live short code stands for. Each lookup counts
+/// against `requester`'s hourly allowance, hit or miss.
+pub(crate) async fn resolve(state: &AppState, requester: &str, short_code: &str) -> Result<String, AppError> {
+    let mut redis_conn = state.redis.get_async_connection().await?;
+    let key = format!("shortcode:lookups:{}", requester);
+    let lookups: u32 = redis_conn.incr(&key, 1).await?;
+    if lookups == 1 {
+        redis_conn.expire(&key, 3600).await?;
+    }
+    if lookups > LOOKUPS_PER_HOUR {
+        let ttl: i64 = redis_conn.ttl(&key).await.unwrap_or(3600);
+        return Err(AppError::RateLimit(ttl.max(1) as u64));
+    }
+
+    sqlx::query_scalar(
+        r#"
+        SELECT ps.verification_code
+        FROM proof_short_codes sc
+        JOIN proof_sessions ps ON ps.id = sc.session_id
+        WHERE sc.code = $1 AND sc.expires_at > NOW()
+        "#,
+    )
+    .bind(short_code)
+    .fetch_optional(&state.db)
+    .await?
+    .ok_or(AppError::ProofNotFound)
+}
+
+fn generate_short_code() -> String {
+    use rand::Rng;
+
+    format!("{:06}", rand::rngs::OsRng.gen_range(0..1_000_000u32))
+}
@@ -0,0 +1,172 @@
+use axum::{extract::State, Json};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::db::repos::{ConsentRepo, CurrencyRepo, ScoringPolicyRepo, TransactionRepo};
+use crate::error::AppError;
+use crate::handlers::proofs::{score_components, ScoreComponent};
+use crate::handlers::AppState;
+use crate::middleware::lender::LenderAuth;
+use crate::models::{DisclosurePolicy, DEFAULT_CURRENCY};
+use crate::services::proof::{Evaluation, ProofService};
+use crate::services::simulation::StressScenario;
+
+#[derive(Deserialize)]
+pub struct SimulateRequest {
+    pub verification_code: String,
+    #[serde(flatten)]
+    pub scenario: StressScenario,
+}
+
+#[derive(Serialize)]
+pub struct SimulationResponse {
+    /// Always "simulation: not a proof"; nothing here is attested
+    pub label: &'static str,
+    pub verification_code: String,
+    pub scenario: StressScenario,
+    /// The score the receipt commits to
+    pub proven_score: Option<i32>,
+    /// The same statement rescored natively without stress, which should
+    /// match `proven_score`
+    pub baseline_score: u32,
+    pub simulated_score: u32,
+    /// Only when the owner disclosed the proof's score breakdown, since the
+    /// components could be worked back from them
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simulated_metrics: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub simulated_components: Option<Vec<ScoreComponent>>,
+    /// Hex hash of the scoring policy the simulation used, the one the
+    /// proof committed
+    pub policy_hash: String,
+}
+
+/// Rescores a full proof's statement natively under hypothetical stress,
+/// e.g. 30% less volume or twice the reversals, with the scoring policy the
+/// proof committed. For credit analysts stress-testing an applicant; the
+/// result is labelled as a simulation and never stored.
+pub async fn simulate(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    Json(req): Json<SimulateRequest>,
+) -> Result<Json<SimulationResponse>, AppError> {
+    if lender.sandbox {
+        return Err(AppError::NotFound("Sandbox keys can't run simulations".to_string()));
+    }
+    req.scenario.validate().map_err(AppError::Validation)?;
+    let verification_code = crate::utils::normalize_verification_code(&req.verification_code);
+
+    let row = sqlx::query(
+        r#"
+        SELECT ps.id, ps.till_id, ps.proof_type, ps.credit_score, ps.expires_at, ps.disclosure_policy,
+               ps.secondary_source, ps.account_number, ps.currency, ps.scoring_policy_id, ps.policy_hash,
+               COALESCE(ps.proving_started_at, ps.created_at), t.timezone
+        FROM proof_sessions ps
+        JOIN business_tills t ON t.id = ps.till_id
+        WHERE ps.verification_code = $1 AND ps.status = 'completed'
+        "#,
+    )
+    .bind(&verification_code)
+    .fetch_optional(state.read_db())
+    .await?
+    .ok_or(AppError::ProofNotFound)?;
+
+    let session_id: Uuid = row.try_get(0)?;
+    let till_id: Uuid = row.try_get(1)?;
+    let proof_type: String = row.try_get(2)?;
+    let proven_score: Option<i32> = row.try_get(3)?;
+    let expires_at: chrono::DateTime<chrono::Utc> = row.try_get(4)?;
+    let disclosure: DisclosurePolicy = serde_json::from_value(row.try_get(5)?).unwrap_or_default();
+    let secondary_source: Option<String> = row.try_get(6)?;
+    let account_number: Option<String> = row.try_get(7)?;
+    let currency_code: Option<String> = row.try_get(8)?;
+    let scoring_policy_id: Option<Uuid> = row.try_get(9)?;
+    let committed_policy_hash: Option<String> = row.try_get(10)?;
+    let proved_at: chrono::DateTime<chrono::Utc> = row.try_get(11)?;
+    let timezone: String = row.try_get(12)?;
+
+    if expires_at < chrono::Utc::now() {
+        return Err(AppError::ProofExpired);
+    }
+    // A threshold proof's owner chose not to reveal the score at all
+    if proof_type != "full" {
+        return Err(AppError::Validation(
+            "Threshold proofs don't disclose a score to simulate".to_string(),
+        ));
+    }
+    if disclosure.lender_consent && !ConsentRepo::is_approved(&state.db, session_id, lender.lender_id).await? {
+        return Err(AppError::Auth(
+            "The owner hasn't consented to this lender seeing the proof".to_string(),
+        ));
+    }
+
+    let currency_code = currency_code.as_deref().unwrap_or(DEFAULT_CURRENCY);
+    let currency = CurrencyRepo::find(state.read_db(), currency_code)
+        .await?
+        .ok_or_else(|| AppError::Internal(anyhow::anyhow!("Proof uses unsupported currency {}", currency_code)))?;
+    let policy = match scoring_policy_id {
+        Some(id) => ScoringPolicyRepo::find(state.read_db(), id).await?,
+        None => None,
+    };
+    let policy = ProofService::policy_input(&currency, policy.as_ref());
+    // Proofs from before the currency was hashed in committed the v1 hash
+    let reproduced = [policy.hash(&currency.code), policy.hash_v1()].map(hex::encode);
+    if committed_policy_hash.is_some_and(|committed| !reproduced.contains(&committed)) {
+        return Err(AppError::Validation(
+            "The scoring policy this proof committed can no longer be reproduced".to_string(),
+        ));
+    }
+    let timezone = timezone
+        .parse::<chrono_tz::Tz>()
+        .map_err(|_| AppError::Internal(anyhow::anyhow!("Till {} has unknown timezone {}", till_id, timezone)))?;
+
+    // Only the rows the proof was generated from, scored as of then
+    let transactions: Vec<_> = TransactionRepo::for_proof(state.read_db(), till_id, secondary_source.as_deref())
+        .await?
+        .into_iter()
+        .filter(|t| t.created_at <= proved_at)
+        .collect();
+    let mut baseline = ProofService::build_input(
+        transactions,
+        secondary_source.as_deref(),
+        None,
+        account_number.as_deref(),
+        &currency,
+        policy,
+        timezone,
+    );
+    baseline.as_of = Some(proved_at.timestamp());
+    let mut stressed = baseline.clone();
+    req.scenario.apply(&mut stressed);
+
+    let (baseline, simulated) = tokio::task::spawn_blocking(move || {
+        Ok::<_, proof_core::InputError>((proof_core::evaluate(baseline)?, proof_core::evaluate(stressed)?))
+    })
+    .await
+    .map_err(|e| AppError::Internal(e.into()))?
+    .map_err(|e| AppError::Validation(e.to_string()))?;
+    let (Evaluation::Full(baseline), Evaluation::Full(simulated)) = (baseline, simulated) else {
+        unreachable!("threshold scoring was not requested");
+    };
+
+    let (simulated_metrics, simulated_components) = if disclosure.score_breakdown {
+        let breakdown: crate::models::ScoreBreakdown =
+            serde_json::from_value(ProofService::breakdown_json(&simulated)?).map_err(anyhow::Error::from)?;
+        (Some(ProofService::metrics_json(&simulated)?), Some(score_components(&breakdown)))
+    } else {
+        (None, None)
+    };
+
+    Ok(Json(SimulationResponse {
+        label: "simulation: not a proof",
+        verification_code,
+        scenario: req.scenario,
+        proven_score,
+        baseline_score: baseline.credit_score,
+        simulated_score: simulated.credit_score,
+        simulated_metrics,
+        simulated_components,
+        policy_hash: hex::encode(simulated.policy_hash),
+    }))
+}
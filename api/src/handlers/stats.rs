@@ -0,0 +1,29 @@
+use axum::{extract::State, Json};
+use serde::Serialize;
+
+use crate::error::AppError;
+use crate::handlers::AppState;
+use crate::services::market_stats::{MarketStats, MarketStatsService};
+
+#[derive(Serialize)]
+pub struct MarketStatsResponse {
+    #[serde(flatten)]
+    pub stats: MarketStats,
+    /// Segments with fewer businesses than this are left out
+    pub min_group_size: i32,
+    pub computed_at: String,
+}
+
+/// Anonymized score and volume aggregates by sector and county, for
+/// partners and researchers. Served from the worker's latest snapshot.
+pub async fn get_market_stats(State(state): State<AppState>) -> Result<Json<MarketStatsResponse>, AppError> {
+    let (stats, computed_at, min_group_size) = MarketStatsService::latest(state.read_db())
+        .await?
+        .ok_or_else(|| AppError::NotFound("Market statistics haven't been computed yet".to_string()))?;
+
+    Ok(Json(MarketStatsResponse {
+        stats,
+        min_group_size,
+        computed_at: computed_at.to_rfc3339(),
+    }))
+}
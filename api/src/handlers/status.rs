@@ -0,0 +1,101 @@
+use axum::{extract::State, Json};
+use redis::AsyncCommands;
+use serde::Serialize;
+use sqlx::Row;
+
+use crate::db::repos::ProofQueueRepo;
+use crate::handlers::AppState;
+use crate::services::proof::ProofService;
+use crate::worker::{HEARTBEAT_KEY, HEARTBEAT_TIMEOUT_SECS};
+
+/// Queue length above which proofs are noticeably delayed.
+const QUEUE_BACKLOG_THRESHOLD: i64 = 50;
+/// Median proving time above which proving is reported as slow.
+const SLOW_PROVING_SECS: f64 = 600.0;
+
+#[derive(Serialize)]
+pub struct StatusResponse {
+    /// "operational" or "degraded"
+    pub status: &'static str,
+    pub queue_depth: Option<i64>,
+    pub active_workers: Option<i64>,
+    pub proofs_completed_last_hour: Option<i64>,
+    pub median_proving_seconds_last_hour: Option<f64>,
+    pub prover_backend: &'static str,
+    /// Machine-readable reasons the service is degraded, e.g. "no_workers"
+    pub degraded: Vec<&'static str>,
+    pub checked_at: String,
+}
+
+/// Aggregate operational data for the public status page. Never fails: an
+/// unreachable dependency is reported as a degraded flag instead.
+pub async fn get_status(State(state): State<AppState>) -> Json<StatusResponse> {
+    let mut degraded = Vec::new();
+
+    // Workers take jobs from the database while Redis is down, so an
+    // outage slows proving rather than stopping it
+    let active_workers = match active_workers(&state.redis).await {
+        Ok(workers) => Some(workers),
+        Err(e) => {
+            tracing::error!("Status check could not reach Redis: {}", e);
+            degraded.push("queue_unavailable");
+            None
+        }
+    };
+
+    let (queue_depth, proofs_completed_last_hour, median_proving_seconds_last_hour) =
+        match queue_and_timings(&state).await {
+            Ok((depth, count, median)) => (Some(depth), Some(count), median),
+            Err(e) => {
+                tracing::error!("Status check could not reach the database: {}", e);
+                degraded.push("database_unavailable");
+                (None, None, None)
+            }
+        };
+
+    if active_workers == Some(0) {
+        degraded.push("no_workers");
+    }
+    if queue_depth.is_some_and(|depth| depth > QUEUE_BACKLOG_THRESHOLD) {
+        degraded.push("queue_backlog");
+    }
+    if median_proving_seconds_last_hour.is_some_and(|secs| secs > SLOW_PROVING_SECS) {
+        degraded.push("slow_proving");
+    }
+
+    Json(StatusResponse {
+        status: if degraded.is_empty() { "operational" } else { "degraded" },
+        queue_depth,
+        active_workers,
+        proofs_completed_last_hour,
+        median_proving_seconds_last_hour,
+        prover_backend: ProofService::prover_backend(&state.config),
+        degraded,
+        checked_at: chrono::Utc::now().to_rfc3339(),
+    })
+}
+
+async fn active_workers(redis: &redis::Client) -> redis::RedisResult<i64> {
+    let mut conn = redis.get_async_connection().await?;
+    let cutoff = chrono::Utc::now().timestamp() - HEARTBEAT_TIMEOUT_SECS;
+    conn.zcount(HEARTBEAT_KEY, cutoff, "+inf").await
+}
+
+async fn queue_and_timings(state: &AppState) -> Result<(i64, i64, Option<f64>), sqlx::Error> {
+    let queue_depth = ProofQueueRepo::depth(state.read_db()).await?;
+    let row = sqlx::query(
+        r#"
+        SELECT COUNT(*),
+               PERCENTILE_CONT(0.5) WITHIN GROUP (
+                   ORDER BY EXTRACT(EPOCH FROM proving_finished_at - proving_started_at)
+               )
+        FROM proof_sessions
+        WHERE proving_finished_at > NOW() - INTERVAL '1 hour'
+          AND proving_started_at IS NOT NULL
+        "#,
+    )
+    .fetch_one(state.read_db())
+    .await?;
+
+    Ok((queue_depth, row.try_get(0)?, row.try_get(1)?))
+}
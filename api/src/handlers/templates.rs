@@ -0,0 +1,208 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::db::repos::{ProofTemplateRepo, ScoringPolicyRepo};
+use crate::error::AppError;
+use crate::handlers::AppState;
+use crate::middleware::lender::LenderAuth;
+use crate::models::{DisclosurePolicy, ProofTemplate, ScoringPolicy, DISCLOSURE_FIELDS};
+
+/// Proofs cover at most the six 30-day months the guest scores, so a longer
+/// minimum could never be met.
+const MAX_PERIOD_DAYS: u32 = 180;
+
+#[derive(Deserialize)]
+pub struct CreateTemplateRequest {
+    pub name: String,
+    #[serde(default)]
+    pub min_period_days: u32,
+    #[serde(default)]
+    pub required_disclosures: Vec<String>,
+    /// One of the lender's own scoring policies, or a market-wide one
+    pub scoring_policy_id: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TemplateResponse {
+    pub id: String,
+    pub lender_id: String,
+    pub name: String,
+    pub min_period_days: i32,
+    pub required_disclosures: Vec<String>,
+    pub scoring_policy_id: Option<String>,
+    pub created_at: String,
+}
+
+impl From<ProofTemplate> for TemplateResponse {
+    fn from(template: ProofTemplate) -> Self {
+        Self {
+            id: template.id.to_string(),
+            lender_id: template.lender_id.to_string(),
+            name: template.name,
+            min_period_days: template.min_period_days,
+            required_disclosures: template.required_disclosures,
+            scoring_policy_id: template.scoring_policy_id.map(|id| id.to_string()),
+            created_at: template.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Whether a verified proof meets the template it was generated for.
+#[derive(Debug, Clone, Serialize)]
+pub struct TemplateCompliance {
+    pub template_id: String,
+    pub compliant: bool,
+    /// Why it doesn't, one entry per unmet requirement
+    pub failures: Vec<String>,
+}
+
+/// What a proof session committed, as far as templates are concerned.
+pub struct ProofFacts<'a> {
+    pub proof_type: &'a str,
+    pub disclosure: DisclosurePolicy,
+    pub policy_hash: Option<&'a str>,
+    pub period_start: Option<chrono::DateTime<chrono::Utc>>,
+    pub period_end: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+/// Publishes a template. Templates can't be edited; publish a new one to
+/// change requirements so existing proofs keep pointing at what they met.
+pub async fn create_template(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    Json(req): Json<CreateTemplateRequest>,
+) -> Result<Json<TemplateResponse>, AppError> {
+    let name = req.name.trim();
+    if name.is_empty() || name.len() > 255 {
+        return Err(AppError::Validation("Invalid template name".to_string()));
+    }
+
+    if req.min_period_days > MAX_PERIOD_DAYS {
+        return Err(AppError::Validation(format!("min_period_days must be at most {}", MAX_PERIOD_DAYS)));
+    }
+
+    let mut required_disclosures = req.required_disclosures;
+    if let Some(field) = required_disclosures.iter().find(|f| !DISCLOSURE_FIELDS.contains(&f.as_str())) {
+        return Err(AppError::Validation(format!("Unknown disclosure field: {}", field)));
+    }
+    required_disclosures.sort();
+    required_disclosures.dedup();
+
+    let scoring_policy_id = req
+        .scoring_policy_id
+        .as_deref()
+        .map(Uuid::parse_str)
+        .transpose()
+        .map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    if let Some(policy_id) = scoring_policy_id {
+        let policy = ScoringPolicyRepo::find(&state.db, policy_id)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Scoring policy not found".to_string()))?;
+        if policy.lender_id.is_some_and(|id| id != lender.lender_id) {
+            return Err(AppError::NotFound("Scoring policy not found".to_string()));
+        }
+    }
+
+    let template = ProofTemplateRepo::create(
+        &state.db,
+        lender.lender_id,
+        name,
+        req.min_period_days as i32,
+        &required_disclosures,
+        scoring_policy_id,
+    )
+    .await?;
+
+    Ok(Json(template.into()))
+}
+
+pub async fn list_templates(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+) -> Result<Json<Vec<TemplateResponse>>, AppError> {
+    let templates = ProofTemplateRepo::list_for_lender(state.read_db(), lender.lender_id).await?;
+    Ok(Json(templates.into_iter().map(Into::into).collect()))
+}
+
+/// Public, so a merchant given a template ID can see what it asks for
+/// before generating against it.
+pub async fn get_template(
+    State(state): State<AppState>,
+    Path(template_id): Path<String>,
+) -> Result<Json<TemplateResponse>, AppError> {
+    Ok(Json(find(state.read_db(), &template_id).await?.into()))
+}
+
+pub async fn find(db: &sqlx::PgPool, template_id: &str) -> Result<ProofTemplate, AppError> {
+    let template_id =
+        Uuid::parse_str(template_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    ProofTemplateRepo::find(db, template_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Proof template not found".to_string()))
+}
+
+/// Checks a finished proof against a template from what it committed,
+/// rather than trusting that it was generated with the template applied.
+pub async fn compliance(
+    db: &sqlx::PgPool,
+    template: &ProofTemplate,
+    proof: &ProofFacts<'_>,
+) -> Result<TemplateCompliance, AppError> {
+    let mut failures = Vec::new();
+
+    if let Some(policy_id) = template.scoring_policy_id {
+        let policy = ScoringPolicyRepo::find(db, policy_id).await?;
+        let scored_under = policy.is_some_and(|p| proof.policy_hash.is_some_and(|hash| committed_policy(hash, &p)));
+        if !scored_under {
+            failures.push("not scored under the template's scoring policy".to_string());
+        }
+    }
+
+    match (proof.period_start, proof.period_end) {
+        (Some(start), Some(end)) => {
+            let days = (end - start).num_days();
+            if days < template.min_period_days as i64 {
+                failures.push(format!(
+                    "covers {} days, fewer than the required {}",
+                    days, template.min_period_days
+                ));
+            }
+        }
+        _ if template.min_period_days > 0 => failures.push("period was not recorded".to_string()),
+        _ => {}
+    }
+
+    for field in &template.required_disclosures {
+        let disclosed = match field.as_str() {
+            "credit_score" | "metrics" => proof.proof_type == "full",
+            "score_breakdown" => proof.proof_type == "full" && proof.disclosure.score_breakdown,
+            _ => false,
+        };
+        if !disclosed {
+            failures.push(format!("{} is not disclosed", field));
+        }
+    }
+
+    Ok(TemplateCompliance {
+        template_id: template.id.to_string(),
+        compliant: failures.is_empty(),
+        failures,
+    })
+}
+
+/// Whether `hash`, as a proof committed it, is the policy's. Proofs from
+/// before the currency was hashed in committed the v1 hash, which binds to
+/// the stored one.
+fn committed_policy(hash: &str, policy: &ScoringPolicy) -> bool {
+    if hash == policy.policy_hash {
+        return true;
+    }
+    let Some(v1) = hex::decode(hash).ok().and_then(|bytes| <[u8; 32]>::try_from(bytes).ok()) else {
+        return false;
+    };
+    hex::encode(proof_core::ScoringPolicy::bind_currency(&v1, &policy.currency)) == policy.policy_hash
+}
@@ -1,23 +1,40 @@
-use axum::{extract::State, Json};
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use chrono::Datelike;
 use serde::{Deserialize, Serialize};
-use sqlx::Row;
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
+use crate::db::repos::{AuditRepo, SessionRepo, TillRepo, TransactionRepo};
 use crate::error::AppError;
 use crate::handlers::{AppState, Claims};
+use crate::i18n::Message;
+use crate::middleware::locale::current_locale;
 use crate::models::TillType;
+use crate::services::auth::AuthService;
+use crate::services::daraja::{DarajaService, ShortcodeLookup};
 
 #[derive(Deserialize)]
 pub struct RegisterTillRequest {
     pub till_number: String,
     pub till_type: TillType,
+    /// IANA timezone the till's statements are written in; Africa/Nairobi
+    /// when unset
+    pub timezone: Option<String>,
 }
 
 #[derive(Serialize)]
 pub struct RegisterTillResponse {
     pub till_id: String,
+    /// Name M-Pesa has on record for the till, for the owner to confirm
+    pub organization_name: Option<String>,
     pub verification_required: bool,
     pub verification_method: String,
+    /// Another account holds this till verified. Verifying sends the claim
+    /// to an admin instead of taking the till over.
+    pub already_claimed: bool,
 }
 
 #[derive(Deserialize)]
@@ -26,6 +43,21 @@ pub struct VerifyTillRequest {
     pub verification_code: Option<String>,
 }
 
+#[derive(Deserialize)]
+pub struct TransferTillRequest {
+    pub new_owner_phone: String,
+    /// OTP sent to the current owner's phone via /api/auth/request-otp
+    pub current_owner_otp: String,
+    /// OTP sent to the new owner's phone via /api/auth/request-otp
+    pub new_owner_otp: String,
+}
+
+#[derive(Serialize)]
+pub struct TransferTillResponse {
+    pub till_id: String,
+    pub new_owner_id: String,
+}
+
 #[derive(Serialize)]
 pub struct TillResponse {
     pub id: String,
@@ -33,6 +65,78 @@ pub struct TillResponse {
     pub till_type: String,
     pub is_verified: bool,
     pub api_connected: bool,
+    pub organization_name: Option<String>,
+    pub timezone: String,
+}
+
+#[derive(Serialize)]
+pub struct MonthSummary {
+    /// YYYY-MM
+    pub month: String,
+    pub source: String,
+    pub transaction_count: i64,
+    pub volume_cents: i64,
+}
+
+#[derive(Serialize)]
+pub struct TillSummaryResponse {
+    pub till_id: String,
+    pub total_transactions: i64,
+    pub total_volume_cents: i64,
+    /// Calendar months in the last twelve with at least one transaction
+    pub months_covered: usize,
+    pub first_transaction_at: Option<String>,
+    pub last_transaction_at: Option<String>,
+    pub months: Vec<MonthSummary>,
+}
+
+/// A run of calendar months with no transactions, inclusive.
+#[derive(Serialize)]
+pub struct MonthGap {
+    /// YYYY-MM
+    pub from: String,
+    /// YYYY-MM
+    pub to: String,
+}
+
+#[derive(Serialize)]
+pub struct TillCoverage {
+    /// YYYY-MM-DD
+    pub first_transaction_date: Option<String>,
+    /// YYYY-MM-DD
+    pub last_transaction_date: Option<String>,
+    /// YYYY-MM, oldest first
+    pub months_with_data: Vec<String>,
+    /// Empty months from the first month with data up to the last full
+    /// month, the ones that weaken a proof
+    pub gaps: Vec<MonthGap>,
+}
+
+#[derive(Serialize)]
+pub struct TillRowCounts {
+    pub total: i64,
+    pub by_source: BTreeMap<String, i64>,
+    /// Only Payment and Reversal rows are scored
+    pub by_kind: BTreeMap<String, i64>,
+}
+
+#[derive(Serialize)]
+pub struct LastProofSummary {
+    pub session_id: String,
+    pub status: String,
+    pub proof_type: String,
+    pub credit_score: Option<i32>,
+    pub threshold: Option<crate::models::ThresholdResult>,
+    pub created_at: String,
+    pub completed_at: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct TillStatsResponse {
+    pub till_id: String,
+    pub coverage: TillCoverage,
+    pub row_counts: TillRowCounts,
+    pub last_proof: Option<LastProofSummary>,
 }
 
 pub async fn register_till(
@@ -40,22 +144,31 @@ pub async fn register_till(
     claims: Claims,
     Json(req): Json<RegisterTillRequest>,
 ) -> Result<Json<RegisterTillResponse>, AppError> {
+    claims.require_owner()?;
     let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
 
     // Validate till number format (5-7 digits)
     if !req.till_number.chars().all(|c| c.is_ascii_digit())
         || req.till_number.len() < 5
         || req.till_number.len() > 7 {
-        return Err(AppError::Validation("Invalid till number format".to_string()));
+        return Err(AppError::Validation(Message::InvalidTillNumber.render(current_locale())));
     }
 
+    let timezone = crate::handlers::data::supported_timezone(
+        req.timezone.as_deref().unwrap_or(crate::models::DEFAULT_TIMEZONE),
+    )?;
+
+    let organization_name = lookup_organization(&state, &req.till_number, &req.till_type).await?;
+    let already_claimed = TillRepo::verified_elsewhere(&state.db, &req.till_number, user_id).await?;
+
     let till_id = Uuid::new_v4();
     let verification_method = "test_transaction".to_string();
 
     sqlx::query(
         r#"
-        INSERT INTO business_tills (id, user_id, till_number, till_type, is_verified, verification_method)
-        VALUES ($1, $2, $3, $4::till_type, false, $5)
+        INSERT INTO business_tills (id, user_id, till_number, till_type, is_verified, verification_method,
+                                    organization_name, timezone)
+        VALUES ($1, $2, $3, $4::till_type, false, $5, $6, $7)
         "#,
     )
     .bind(till_id)
@@ -63,44 +176,79 @@ pub async fn register_till(
     .bind(&req.till_number)
     .bind(&req.till_type as &TillType)
     .bind(&verification_method)
+    .bind(&organization_name)
+    .bind(timezone.name())
     .execute(&state.db)
     .await?;
 
     Ok(Json(RegisterTillResponse {
         till_id: till_id.to_string(),
+        organization_name,
         verification_required: true,
         verification_method,
+        already_claimed,
     }))
 }
 
+/// Checks with Daraja, when it's configured, that the till exists so a typo
+/// doesn't create a till no statement will ever match. Registration goes
+/// ahead unchecked if Daraja can't be reached.
+async fn lookup_organization(
+    state: &AppState,
+    till_number: &str,
+    till_type: &TillType,
+) -> Result<Option<String>, AppError> {
+    let (Some(consumer_key), Some(consumer_secret)) =
+        (&state.config.daraja_consumer_key, &state.config.daraja_consumer_secret)
+    else {
+        return Ok(None);
+    };
+
+    let lookup = async {
+        let access_token = DarajaService::get_access_token(consumer_key, consumer_secret).await?;
+        DarajaService::query_org_info(&access_token, till_number, till_type).await
+    };
+    match lookup.await {
+        Ok(ShortcodeLookup::Found { organization_name }) => Ok(organization_name),
+        Ok(ShortcodeLookup::Unknown) => {
+            Err(AppError::Validation(Message::UnknownTillNumber.render(current_locale())))
+        }
+        Err(e) => {
+            tracing::warn!("Daraja lookup of till {} failed, registering unchecked: {}", till_number, e);
+            Ok(None)
+        }
+    }
+}
+
 pub async fn verify_till(
     State(state): State<AppState>,
     claims: Claims,
     Json(req): Json<VerifyTillRequest>,
 ) -> Result<Json<serde_json::Value>, AppError> {
+    claims.require_owner()?;
     let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
     let till_id = Uuid::parse_str(&req.till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
 
     // Check if till belongs to user
-    let row = sqlx::query("SELECT user_id FROM business_tills WHERE id = $1")
-        .bind(till_id)
-        .fetch_optional(&state.db)
-        .await?;
-
-    let till_user_id: Uuid = row
-        .ok_or_else(|| AppError::NotFound("Till not found".to_string()))?
-        .try_get::<Uuid, _>(0)
-        .map_err(|e| AppError::Database(e))?;
+    let till_user_id = TillRepo::owner(&state.db, till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
 
     if till_user_id != user_id {
-        return Err(AppError::Auth("Unauthorized".to_string()));
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
     }
 
-    // For now, mark as verified (in production, verify via test transaction or API)
-    sqlx::query("UPDATE business_tills SET is_verified = true WHERE id = $1")
-        .bind(till_id)
-        .execute(&state.db)
-        .await?;
+    // For now, mark as verified (in production, verify via test transaction or API).
+    // That proves nothing about ownership, so it never takes a till from
+    // another account: a conflicting claim goes to admins to settle.
+    if !TillRepo::verify(&state.db, till_id).await? {
+        TillRepo::request_ownership_review(&state.db, till_id).await?;
+        tracing::warn!("Till {} is verified on another account; sent for ownership review", till_id);
+        return Ok(Json(serde_json::json!({
+            "verified": false,
+            "ownership_review": true
+        })));
+    }
 
     Ok(Json(serde_json::json!({
         "verified": true
@@ -113,33 +261,7 @@ pub async fn list_tills(
 ) -> Result<Json<Vec<TillResponse>>, AppError> {
     let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
 
-    let rows = sqlx::query(
-        r#"
-        SELECT id, user_id, till_number, till_type,
-               is_verified, api_connected, verification_method, created_at, updated_at
-        FROM business_tills
-        WHERE user_id = $1
-        ORDER BY created_at DESC
-        "#,
-    )
-    .bind(user_id)
-    .fetch_all(&state.db)
-    .await?;
-
-    let tills: Vec<crate::models::BusinessTill> = rows
-        .into_iter()
-        .map(|row| crate::models::BusinessTill {
-            id: row.get(0),
-            user_id: row.get(1),
-            till_number: row.get(2),
-            till_type: row.get(3),
-            is_verified: row.get(4),
-            api_connected: row.get(5),
-            verification_method: row.get(6),
-            created_at: row.get(7),
-            updated_at: row.get(8),
-        })
-        .collect();
+    let tills = TillRepo::list_for_user(state.read_db(), user_id).await?;
 
     let response: Vec<TillResponse> = tills
         .into_iter()
@@ -149,9 +271,255 @@ pub async fn list_tills(
             till_type: format!("{:?}", t.till_type),
             is_verified: t.is_verified,
             api_connected: t.api_connected,
+            organization_name: t.organization_name,
+            timezone: t.timezone,
         })
         .collect();
 
     Ok(Json(response))
 }
 
+/// Hands a till to another phone number. Both owners must confirm with an
+/// OTP; proofs already generated stay with the original owner.
+pub async fn transfer_till(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(till_id): Path<String>,
+    Json(req): Json<TransferTillRequest>,
+) -> Result<Json<TransferTillResponse>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let till_id = Uuid::parse_str(&till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    if req.new_owner_phone == claims.phone_number {
+        return Err(AppError::Validation("Till already belongs to this phone number".to_string()));
+    }
+
+    let till_user_id = TillRepo::owner(&state.db, till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
+
+    if till_user_id != user_id {
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
+    }
+
+    // Check both codes before consuming either, so a typo doesn't burn one
+    let mut redis_conn = state.redis.get_async_connection().await?;
+    if !AuthService::check_otp(&mut redis_conn, &claims.phone_number, &req.current_owner_otp).await?
+        || !AuthService::check_otp(&mut redis_conn, &req.new_owner_phone, &req.new_owner_otp).await?
+    {
+        return Err(AppError::InvalidOtp);
+    }
+    AuthService::consume_otp(&mut redis_conn, &claims.phone_number).await?;
+    AuthService::consume_otp(&mut redis_conn, &req.new_owner_phone).await?;
+
+    let mut tx = state.db.begin().await?;
+
+    // A linked phone signs in to the account it's linked to, so an account
+    // created for it here could never be reached
+    let linked: bool = sqlx::query_scalar("SELECT EXISTS (SELECT 1 FROM user_phones WHERE phone_number = $1)")
+        .bind(&req.new_owner_phone)
+        .fetch_one(&mut *tx)
+        .await?;
+    if linked {
+        return Err(AppError::Validation(
+            "That phone is linked to an account as an extra phone; transfer to the account's own phone".to_string(),
+        ));
+    }
+
+    let new_owner_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO users (phone_number)
+        VALUES ($1)
+        ON CONFLICT (phone_number) DO UPDATE SET updated_at = NOW()
+        RETURNING id
+        "#,
+    )
+    .bind(&req.new_owner_phone)
+    .fetch_one(&mut *tx)
+    .await?;
+
+    let updated = sqlx::query("UPDATE business_tills SET user_id = $1 WHERE id = $2")
+        .bind(new_owner_id)
+        .bind(till_id)
+        .execute(&mut *tx)
+        .await;
+    if let Err(sqlx::Error::Database(e)) = &updated {
+        if e.is_unique_violation() {
+            return Err(AppError::Validation("New owner already has this till registered".to_string()));
+        }
+    }
+    updated?;
+
+    sqlx::query("INSERT INTO till_transfers (till_id, from_user_id, to_user_id) VALUES ($1, $2, $3)")
+        .bind(till_id)
+        .bind(user_id)
+        .bind(new_owner_id)
+        .execute(&mut *tx)
+        .await?;
+
+    // The current owner's OTP above is the transfer's second confirmation
+    let session_id = claims.session_id.as_deref().and_then(|id| Uuid::parse_str(id).ok());
+    let details = serde_json::json!({ "till_id": till_id, "to_user_id": new_owner_id });
+    AuditRepo::record(&mut *tx, user_id, session_id, "till.transferred", None, details).await?;
+
+    tx.commit().await?;
+
+    tracing::info!("Till {} transferred from {} to {}", till_id, user_id, new_owner_id);
+
+    Ok(Json(TransferTillResponse {
+        till_id: till_id.to_string(),
+        new_owner_id: new_owner_id.to_string(),
+    }))
+}
+
+/// Cheap coverage pre-check from the monthly aggregates, so a merchant can
+/// see what a proof would cover before queueing one.
+pub async fn get_till_summary(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(till_id): Path<String>,
+) -> Result<Json<TillSummaryResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let till_id = Uuid::parse_str(&till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let till_user_id = TillRepo::owner(state.read_db(), till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
+
+    if till_user_id != user_id {
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
+    }
+
+    let aggregates = TillRepo::monthly_aggregates(state.read_db(), till_id).await?;
+
+    let window_start = (chrono::Utc::now() - chrono::Duration::days(365)).date_naive();
+    let mut covered: Vec<chrono::NaiveDate> = aggregates
+        .iter()
+        .filter(|a| a.month >= window_start && a.transaction_count > 0)
+        .map(|a| a.month)
+        .collect();
+    covered.dedup();
+
+    Ok(Json(TillSummaryResponse {
+        till_id: till_id.to_string(),
+        total_transactions: aggregates.iter().map(|a| a.transaction_count).sum(),
+        total_volume_cents: aggregates.iter().map(|a| a.volume_cents).sum(),
+        months_covered: covered.len(),
+        first_transaction_at: aggregates.iter().map(|a| a.first_transaction_at).min().map(|t| t.to_rfc3339()),
+        last_transaction_at: aggregates.iter().map(|a| a.last_transaction_at).max().map(|t| t.to_rfc3339()),
+        months: aggregates
+            .into_iter()
+            .map(|a| MonthSummary {
+                month: a.month.format("%Y-%m").to_string(),
+                source: a.source,
+                transaction_count: a.transaction_count,
+                volume_cents: a.volume_cents,
+            })
+            .collect(),
+    }))
+}
+
+/// Everything the app needs to warn about a weak statement before proving:
+/// which months are missing, what kinds of rows were imported, and how the
+/// last proof went.
+pub async fn get_till_stats(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(till_id): Path<String>,
+) -> Result<Json<TillStatsResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let till_id = Uuid::parse_str(&till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let till_user_id = TillRepo::owner(state.read_db(), till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
+
+    if till_user_id != user_id {
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
+    }
+
+    let aggregates = TillRepo::monthly_aggregates(state.read_db(), till_id).await?;
+    let kind_counts = TransactionRepo::kind_counts(state.read_db(), till_id).await?;
+    let latest = SessionRepo::latest_for_till(state.read_db(), till_id).await?;
+
+    let mut months: Vec<chrono::NaiveDate> =
+        aggregates.iter().filter(|a| a.transaction_count > 0).map(|a| a.month).collect();
+    months.dedup();
+
+    let mut by_source = BTreeMap::new();
+    for aggregate in &aggregates {
+        *by_source.entry(aggregate.source.clone()).or_insert(0) += aggregate.transaction_count;
+    }
+
+    let today = chrono::Utc::now().date_naive();
+    let current_month = today.with_day(1).unwrap_or(today);
+
+    Ok(Json(TillStatsResponse {
+        till_id: till_id.to_string(),
+        coverage: TillCoverage {
+            first_transaction_date: aggregates
+                .iter()
+                .map(|a| a.first_transaction_at)
+                .min()
+                .map(|t| t.format("%Y-%m-%d").to_string()),
+            last_transaction_date: aggregates
+                .iter()
+                .map(|a| a.last_transaction_at)
+                .max()
+                .map(|t| t.format("%Y-%m-%d").to_string()),
+            gaps: month_gaps(&months, current_month),
+            months_with_data: months.iter().map(|m| m.format("%Y-%m").to_string()).collect(),
+        },
+        row_counts: TillRowCounts {
+            total: by_source.values().sum(),
+            by_source,
+            by_kind: kind_counts.into_iter().collect(),
+        },
+        last_proof: latest.map(|session| LastProofSummary {
+            session_id: session.id.to_string(),
+            status: format!("{:?}", session.status),
+            proof_type: session.proof_type,
+            credit_score: session.credit_score,
+            threshold: crate::handlers::proofs::threshold_result(session.score_threshold, session.meets_threshold),
+            created_at: session.created_at.to_rfc3339(),
+            completed_at: session.proving_finished_at.map(|t| t.to_rfc3339()),
+        }),
+    }))
+}
+
+/// Runs of months missing from `months` (first days, ascending), from the
+/// first of them up to but not including `current_month`, which is still
+/// filling up.
+fn month_gaps(months: &[chrono::NaiveDate], current_month: chrono::NaiveDate) -> Vec<MonthGap> {
+    let next = |month: chrono::NaiveDate| month.checked_add_months(chrono::Months::new(1));
+    let label = |month: chrono::NaiveDate| month.format("%Y-%m").to_string();
+
+    let mut gaps = Vec::new();
+    let Some(&first) = months.first() else {
+        return gaps;
+    };
+    let mut gap_start = None;
+    let mut month = Some(first);
+    while let Some(m) = month.filter(|m| *m < current_month) {
+        match (months.binary_search(&m).is_ok(), gap_start) {
+            (false, None) => gap_start = Some(m),
+            (true, Some(start)) => {
+                gaps.push(MonthGap {
+                    from: label(start),
+                    to: label(m - chrono::Months::new(1)),
+                });
+                gap_start = None;
+            }
+            _ => {}
+        }
+        month = next(m);
+    }
+    if let Some(start) = gap_start {
+        gaps.push(MonthGap {
+            from: label(start),
+            to: label(current_month - chrono::Months::new(1)),
+        });
+    }
+    gaps
+}
@@ -0,0 +1,279 @@
+use axum::{
+    body::Bytes,
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::db::repos::TillRepo;
+use crate::error::AppError;
+use crate::handlers::data::{
+    import_file, supported_currency, supported_timezone, till_timezone, SpooledFile, UploadDataResponse,
+};
+use crate::handlers::{AppState, Claims};
+use crate::i18n::Message;
+use crate::middleware::locale::current_locale;
+
+/// Also the body limit of the chunk route, whatever `max_body_bytes` is.
+pub const MAX_CHUNK_BYTES: usize = 1024 * 1024;
+const MAX_CHUNKS: i32 = 100;
+const UPLOAD_TTL_HOURS: i64 = 24;
+
+#[derive(Deserialize)]
+pub struct CreateUploadRequest {
+    pub till_id: String,
+    /// Content type of the whole file, e.g. "text/csv"
+    pub file_type: String,
+    pub total_chunks: i32,
+    #[serde(default = "default_source")]
+    pub source: String,
+    /// ISO 4217 code of the statement's amounts
+    #[serde(default = "default_currency")]
+    pub currency: String,
+    /// IANA timezone of the statement's times; the till's when unset
+    pub timezone: Option<String>,
+}
+
+fn default_source() -> String {
+    "mpesa".to_string()
+}
+
+fn default_currency() -> String {
+    crate::models::DEFAULT_CURRENCY.to_string()
+}
+
+#[derive(Serialize)]
+pub struct UploadSessionResponse {
+    pub upload_id: String,
+    pub total_chunks: i32,
+    /// Chunks already stored; a resuming client sends only the rest
+    pub received_chunks: Vec<i32>,
+    pub max_chunk_bytes: usize,
+    pub expires_at: String,
+}
+
+struct UploadSession {
+    till_id: Uuid,
+    file_type: String,
+    source: String,
+    currency: String,
+    /// Unset when the upload didn't override the till's
+    timezone: Option<String>,
+    total_chunks: i32,
+    received_chunks: Vec<i32>,
+    expires_at: chrono::DateTime<chrono::Utc>,
+}
+
+impl UploadSession {
+    fn response(&self, upload_id: Uuid) -> UploadSessionResponse {
+        UploadSessionResponse {
+            upload_id: upload_id.to_string(),
+            total_chunks: self.total_chunks,
+            received_chunks: self.received_chunks.clone(),
+            max_chunk_bytes: MAX_CHUNK_BYTES,
+            expires_at: self.expires_at.to_rfc3339(),
+        }
+    }
+}
+
+pub(crate) fn chunk_key(upload_id: Uuid, n: i32) -> String {
+    format!("uploads/{}/chunk-{:05}", upload_id, n)
+}
+
+/// Loads an open upload owned by the caller.
+async fn load_session(state: &AppState, claims: &Claims, upload_id: &str) -> Result<(Uuid, UploadSession), AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let upload_id = Uuid::parse_str(upload_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let row = sqlx::query(
+        r#"
+        SELECT till_id, file_type, source, total_chunks, received_chunks, expires_at, currency, timezone
+        FROM upload_sessions
+        WHERE id = $1 AND user_id = $2 AND completed_at IS NULL AND expires_at > NOW()
+        "#,
+    )
+    .bind(upload_id)
+    .bind(user_id)
+    .fetch_optional(&state.db)
+    .await?;
+
+    let row = row.ok_or_else(|| AppError::NotFound("Upload not found".to_string()))?;
+
+    Ok((
+        upload_id,
+        UploadSession {
+            till_id: row.try_get(0)?,
+            file_type: row.try_get(1)?,
+            source: row.try_get(2)?,
+            total_chunks: row.try_get(3)?,
+            received_chunks: row.try_get(4)?,
+            expires_at: row.try_get(5)?,
+            currency: row.try_get(6)?,
+            timezone: row.try_get(7)?,
+        },
+    ))
+}
+
+pub async fn create_upload(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(req): Json<CreateUploadRequest>,
+) -> Result<Json<UploadSessionResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let till_id = Uuid::parse_str(&req.till_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    if !(1..=MAX_CHUNKS).contains(&req.total_chunks) {
+        return Err(AppError::Validation(format!("total_chunks must be between 1 and {}", MAX_CHUNKS)));
+    }
+    if !crate::models::TRANSACTION_SOURCES.contains(&req.source.as_str()) {
+        return Err(AppError::Validation(format!("Unknown source: {}", req.source)));
+    }
+    let currency = supported_currency(&state.db, &req.currency).await?;
+    let timezone = req.timezone.as_deref().map(supported_timezone).transpose()?;
+
+    // Verify till belongs to user
+    let till_user_id = TillRepo::owner(&state.db, till_id)
+        .await?
+        .ok_or(AppError::TillNotFound)?;
+
+    if till_user_id != user_id {
+        return Err(AppError::Auth(Message::Unauthorized.render(current_locale())));
+    }
+
+    let expires_at = chrono::Utc::now() + chrono::Duration::hours(UPLOAD_TTL_HOURS);
+    let upload_id: Uuid = sqlx::query_scalar(
+        r#"
+        INSERT INTO upload_sessions (user_id, till_id, file_type, source, total_chunks, expires_at, currency,
+                                     timezone)
+        VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+        RETURNING id
+        "#,
+    )
+    .bind(user_id)
+    .bind(till_id)
+    .bind(&req.file_type)
+    .bind(&req.source)
+    .bind(req.total_chunks)
+    .bind(expires_at)
+    .bind(&currency.code)
+    .bind(timezone.map(|tz| tz.name()))
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok(Json(UploadSessionResponse {
+        upload_id: upload_id.to_string(),
+        total_chunks: req.total_chunks,
+        received_chunks: Vec::new(),
+        max_chunk_bytes: MAX_CHUNK_BYTES,
+        expires_at: expires_at.to_rfc3339(),
+    }))
+}
+
+pub async fn get_upload(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(upload_id): Path<String>,
+) -> Result<Json<UploadSessionResponse>, AppError> {
+    let (upload_id, session) = load_session(&state, &claims, &upload_id).await?;
+    Ok(Json(session.response(upload_id)))
+}
+
+/// Stores one chunk. Re-sending a chunk overwrites it, so retries are safe.
+pub async fn put_chunk(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path((upload_id, n)): Path<(String, i32)>,
+    body: Bytes,
+) -> Result<Json<UploadSessionResponse>, AppError> {
+    let (upload_id, mut session) = load_session(&state, &claims, &upload_id).await?;
+
+    if !(0..session.total_chunks).contains(&n) {
+        return Err(AppError::Validation(format!(
+            "Chunk index must be between 0 and {}",
+            session.total_chunks - 1
+        )));
+    }
+    if body.is_empty() || body.len() > MAX_CHUNK_BYTES {
+        return Err(AppError::Validation(format!(
+            "Chunks must be between 1 and {} bytes",
+            MAX_CHUNK_BYTES
+        )));
+    }
+
+    state.storage.upload(&chunk_key(upload_id, n), &body).await?;
+
+    session.received_chunks = sqlx::query_scalar(
+        r#"
+        UPDATE upload_sessions
+        SET received_chunks = CASE
+            WHEN $2 = ANY(received_chunks) THEN received_chunks
+            ELSE array_append(received_chunks, $2)
+        END
+        WHERE id = $1
+        RETURNING received_chunks
+        "#,
+    )
+    .bind(upload_id)
+    .bind(n)
+    .fetch_one(&state.db)
+    .await?;
+    session.received_chunks.sort_unstable();
+
+    Ok(Json(session.response(upload_id)))
+}
+
+/// Reassembles the chunks in order in a temp file, then parses and imports
+/// it.
+pub async fn complete_upload(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(upload_id): Path<String>,
+) -> Result<Json<UploadDataResponse>, AppError> {
+    let (upload_id, session) = load_session(&state, &claims, &upload_id).await?;
+
+    let missing: Vec<i32> = (0..session.total_chunks)
+        .filter(|n| !session.received_chunks.contains(n))
+        .collect();
+    if !missing.is_empty() {
+        return Err(AppError::Validation(format!("Missing chunks: {:?}", missing)));
+    }
+
+    let (file, mut out) = SpooledFile::create(&state.config).await?;
+    for n in 0..session.total_chunks {
+        let chunk = state.storage.download(&chunk_key(upload_id, n)).await?;
+        out.write_all(&chunk).await.map_err(|e| AppError::Internal(e.into()))?;
+    }
+    out.flush().await.map_err(|e| AppError::Internal(e.into()))?;
+
+    let currency = supported_currency(&state.db, &session.currency).await?;
+    let timezone = match session.timezone.as_deref() {
+        Some(name) => supported_timezone(name)?,
+        None => till_timezone(&state.db, session.till_id).await?,
+    };
+    let response = import_file(
+        &state,
+        session.till_id,
+        Some(&session.file_type),
+        &file,
+        &session.source,
+        &currency,
+        timezone,
+    )
+    .await?;
+
+    sqlx::query("UPDATE upload_sessions SET completed_at = NOW() WHERE id = $1")
+        .bind(upload_id)
+        .execute(&state.db)
+        .await?;
+
+    for n in 0..session.total_chunks {
+        if let Err(e) = state.storage.delete(&chunk_key(upload_id, n)).await {
+            tracing::warn!("Failed to delete chunk {} of upload {}: {}", n, upload_id, e);
+        }
+    }
+
+    Ok(Json(response))
+}
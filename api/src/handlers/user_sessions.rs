@@ -0,0 +1,117 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use serde::Serialize;
+use uuid::Uuid;
+
+use crate::db::repos::audit::AuditEvent;
+use crate::db::repos::user_sessions::UserSession;
+use crate::db::repos::{AuditRepo, UserSessionRepo};
+use crate::error::AppError;
+use crate::handlers::{AppState, Claims};
+
+#[derive(Serialize)]
+pub struct UserSessionResponse {
+    pub id: String,
+    /// The phone that signed in, a linked one for uploaders
+    pub phone_number: String,
+    pub role: String,
+    /// The User-Agent it signed in with
+    pub device_hint: Option<String>,
+    /// The network it signed in from, a /24 or /48
+    pub ip_range: Option<String>,
+    /// Signed in from a device and network the phone hadn't used before
+    pub unfamiliar: bool,
+    pub issued_at: String,
+    pub last_used_at: String,
+    pub expires_at: String,
+    /// Whether this is the session making the request
+    pub current: bool,
+}
+
+impl UserSessionResponse {
+    fn new(session: UserSession, claims: &Claims) -> Self {
+        Self {
+            current: claims.session_id.as_deref() == Some(session.id.to_string().as_str()),
+            id: session.id.to_string(),
+            phone_number: session.phone_number,
+            role: session.role,
+            device_hint: session.device_hint,
+            ip_range: session.ip_range,
+            unfamiliar: session.anomalous,
+            issued_at: session.created_at.to_rfc3339(),
+            last_used_at: session.last_used_at.to_rfc3339(),
+            expires_at: session.expires_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Audit log entries an owner can read at once.
+const AUDIT_LOG_LIMIT: i64 = 100;
+
+#[derive(Serialize)]
+pub struct AuditEventResponse {
+    pub id: String,
+    pub session_id: Option<String>,
+    pub event: String,
+    pub ip_range: Option<String>,
+    pub details: serde_json::Value,
+    pub created_at: String,
+}
+
+impl From<AuditEvent> for AuditEventResponse {
+    fn from(event: AuditEvent) -> Self {
+        Self {
+            id: event.id.to_string(),
+            session_id: event.session_id.map(|id| id.to_string()),
+            event: event.event,
+            ip_range: event.ip_range,
+            details: event.details,
+            created_at: event.created_at.to_rfc3339(),
+        }
+    }
+}
+
+/// Every phone and browser signed in to the account, linked phones included.
+pub async fn list_sessions(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<UserSessionResponse>>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let sessions = UserSessionRepo::list_active(&state.db, user_id).await?;
+    Ok(Json(sessions.into_iter().map(|s| UserSessionResponse::new(s, &claims)).collect()))
+}
+
+/// Signs a session out, for a lost phone; its token is refused from the
+/// next request on. Revoking the current session signs out.
+pub async fn revoke_session(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(session_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let session_id = Uuid::parse_str(&session_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    UserSessionRepo::revoke(&state.db, session_id, user_id)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Session not found or already signed out".to_string()))?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// The account's latest security events: unfamiliar sign-ins, step-ups and
+/// the sensitive actions they allowed.
+pub async fn list_audit_log(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<AuditEventResponse>>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let events = AuditRepo::list_for_user(&state.db, user_id, AUDIT_LOG_LIMIT).await?;
+    Ok(Json(events.into_iter().map(Into::into).collect()))
+}
@@ -0,0 +1,574 @@
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    Json,
+};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::db::repos::notifications::NotificationPreferences;
+use crate::db::repos::{AuditRepo, NotificationRepo, UserSessionRepo};
+use crate::error::AppError;
+use crate::handlers::{AppState, Claims};
+use crate::i18n::Message;
+use crate::middleware::locale::current_locale;
+use crate::models::{BusinessProfile, PhoneRole};
+use crate::services::auth::AuthService;
+use crate::services::email::EmailService;
+use crate::services::security::SecurityService;
+
+#[derive(Deserialize)]
+pub struct UpdateBusinessProfileRequest {
+    pub business_name: Option<String>,
+    pub sector: Option<String>,
+    pub county: Option<String>,
+    pub registration_number: Option<String>,
+    #[serde(default)]
+    pub public_fields: Vec<String>,
+}
+
+#[derive(Serialize)]
+pub struct BusinessProfileResponse {
+    pub business_name: Option<String>,
+    pub sector: Option<String>,
+    pub county: Option<String>,
+    pub registration_number: Option<String>,
+    pub public_fields: Vec<String>,
+    pub updated_at: Option<String>,
+}
+
+impl From<BusinessProfile> for BusinessProfileResponse {
+    fn from(profile: BusinessProfile) -> Self {
+        Self {
+            business_name: profile.business_name,
+            sector: profile.sector,
+            county: profile.county,
+            registration_number: profile.registration_number,
+            public_fields: profile.public_fields,
+            updated_at: Some(profile.updated_at.to_rfc3339()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct LinkPhoneRequest {
+    pub phone_number: String,
+    /// OTP sent to the phone being linked via /api/auth/request-otp
+    pub otp: String,
+}
+
+#[derive(Serialize)]
+pub struct LinkedPhoneResponse {
+    pub id: String,
+    pub phone_number: String,
+    pub role: String,
+    pub verified_at: String,
+}
+
+#[derive(Deserialize)]
+pub struct RegisterDeviceRequest {
+    /// FCM registration token from the companion app
+    pub token: String,
+    /// "android", "ios" or "web"
+    pub platform: String,
+}
+
+#[derive(Serialize)]
+pub struct DeviceResponse {
+    pub id: String,
+    pub platform: String,
+    pub created_at: String,
+    pub last_seen_at: String,
+}
+
+/// A daily window, in the preferences' timezone, during which
+/// notifications are held until it ends. It may span midnight.
+#[derive(Deserialize, Serialize)]
+pub struct QuietHours {
+    /// "HH:MM"
+    pub start: String,
+    pub end: String,
+}
+
+/// Replaces every preference.
+#[derive(Deserialize)]
+pub struct NotificationPreferencesRequest {
+    pub sms: bool,
+    pub push: bool,
+    pub email: bool,
+    /// Required for email
+    pub email_address: Option<String>,
+    pub quiet_hours: Option<QuietHours>,
+    /// IANA timezone of the quiet hours; defaults to Africa/Nairobi
+    pub timezone: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct NotificationPreferencesResponse {
+    pub sms: bool,
+    pub push: bool,
+    pub email: bool,
+    pub email_address: Option<String>,
+    /// Email is sent only once the address is verified
+    pub email_verified: bool,
+    pub quiet_hours: Option<QuietHours>,
+    pub timezone: String,
+}
+
+impl From<NotificationPreferences> for NotificationPreferencesResponse {
+    fn from(preferences: NotificationPreferences) -> Self {
+        let quiet_hours = preferences
+            .quiet_hours_start
+            .zip(preferences.quiet_hours_end)
+            .map(|(start, end)| QuietHours {
+                start: start.format("%H:%M").to_string(),
+                end: end.format("%H:%M").to_string(),
+            });
+        Self {
+            sms: preferences.sms,
+            push: preferences.push,
+            email: preferences.email,
+            email_address: preferences.email_address,
+            email_verified: preferences.email_verified_at.is_some(),
+            quiet_hours,
+            timezone: preferences.timezone,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+pub struct VerifyEmailRequest {
+    pub code: String,
+}
+
+/// How long an emailed verification code can be entered.
+const EMAIL_CODE_TTL_SECS: u64 = 15 * 60;
+/// Wrong codes allowed within the lockout window before verifying is
+/// refused until it has passed.
+const EMAIL_CODE_MAX_FAILURES: i64 = 5;
+const EMAIL_CODE_LOCKOUT_MINS: i64 = 15;
+
+const DEVICE_PLATFORMS: [&str; 3] = ["android", "ios", "web"];
+/// FCM tokens are well under this; anything longer isn't one.
+const MAX_DEVICE_TOKEN_LEN: usize = 4096;
+
+pub async fn get_business_profile(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<BusinessProfileResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let profile = fetch_business_profile(&state.db, user_id).await?;
+
+    Ok(Json(match profile {
+        Some(profile) => profile.into(),
+        None => BusinessProfileResponse {
+            business_name: None,
+            sector: None,
+            county: None,
+            registration_number: None,
+            public_fields: Vec::new(),
+            updated_at: None,
+        },
+    }))
+}
+
+pub async fn update_business_profile(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(req): Json<UpdateBusinessProfileRequest>,
+) -> Result<Json<BusinessProfileResponse>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    for (name, value, max_len) in [
+        ("business_name", &req.business_name, 255),
+        ("sector", &req.sector, 100),
+        ("county", &req.county, 100),
+        ("registration_number", &req.registration_number, 100),
+    ] {
+        if value.as_ref().is_some_and(|v| v.trim().is_empty() || v.len() > max_len) {
+            return Err(AppError::Validation(format!("Invalid {}", name)));
+        }
+    }
+
+    if let Some(field) = req
+        .public_fields
+        .iter()
+        .find(|f| !BusinessProfile::FIELDS.contains(&f.as_str()))
+    {
+        return Err(AppError::Validation(format!("Unknown profile field: {}", field)));
+    }
+
+    let profile = sqlx::query_as::<_, BusinessProfile>(
+        r#"
+        INSERT INTO business_profiles (user_id, business_name, sector, county, registration_number, public_fields)
+        VALUES ($1, $2, $3, $4, $5, $6)
+        ON CONFLICT (user_id) DO UPDATE SET
+            business_name = EXCLUDED.business_name,
+            sector = EXCLUDED.sector,
+            county = EXCLUDED.county,
+            registration_number = EXCLUDED.registration_number,
+            public_fields = EXCLUDED.public_fields
+        RETURNING user_id, business_name, sector, county, registration_number, public_fields, created_at, updated_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(req.business_name.as_deref().map(str::trim))
+    .bind(req.sector.as_deref().map(str::trim))
+    .bind(req.county.as_deref().map(str::trim))
+    .bind(req.registration_number.as_deref().map(str::trim))
+    .bind(&req.public_fields)
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok(Json(profile.into()))
+}
+
+pub async fn fetch_business_profile(
+    db: &sqlx::PgPool,
+    user_id: Uuid,
+) -> Result<Option<BusinessProfile>, AppError> {
+    let profile = sqlx::query_as::<_, BusinessProfile>(
+        r#"
+        SELECT user_id, business_name, sector, county, registration_number, public_fields, created_at, updated_at
+        FROM business_profiles
+        WHERE user_id = $1
+        "#,
+    )
+    .bind(user_id)
+    .fetch_optional(db)
+    .await?;
+
+    Ok(profile)
+}
+
+pub async fn list_phones(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<LinkedPhoneResponse>>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT id, phone_number, role, verified_at
+        FROM user_phones
+        WHERE user_id = $1
+        ORDER BY created_at ASC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let phones = rows
+        .into_iter()
+        .map(|row| {
+            Ok(LinkedPhoneResponse {
+                id: row.try_get::<Uuid, _>(0)?.to_string(),
+                phone_number: row.try_get(1)?,
+                role: row.try_get(2)?,
+                verified_at: row.try_get::<chrono::DateTime<chrono::Utc>, _>(3)?.to_rfc3339(),
+            })
+        })
+        .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+    Ok(Json(phones))
+}
+
+/// Links a secondary phone once it proves possession with an OTP. It signs
+/// in to this account with the uploader role.
+pub async fn link_phone(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(req): Json<LinkPhoneRequest>,
+) -> Result<Json<LinkedPhoneResponse>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    // A phone that already owns an account would lose access to it
+    let owns_account: bool = sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM users WHERE phone_number = $1)")
+        .bind(&req.phone_number)
+        .fetch_one(&state.db)
+        .await?;
+    if owns_account {
+        return Err(AppError::Validation("Phone number already has its own account".to_string()));
+    }
+
+    let mut redis_conn = state.redis.get_async_connection().await?;
+    if !AuthService::check_otp(&mut redis_conn, &req.phone_number, &req.otp).await? {
+        return Err(AppError::InvalidOtp);
+    }
+    SecurityService::require_step_up(&state.db, &claims, "phone.linked", json!({ "phone_number": req.phone_number }))
+        .await?;
+    AuthService::consume_otp(&mut redis_conn, &req.phone_number).await?;
+
+    let row = sqlx::query(
+        r#"
+        INSERT INTO user_phones (user_id, phone_number, role)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (phone_number) DO NOTHING
+        RETURNING id, verified_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(&req.phone_number)
+    .bind(PhoneRole::Uploader.as_str())
+    .fetch_optional(&state.db)
+    .await?;
+
+    let row = row.ok_or_else(|| AppError::Validation("Phone number is already linked to an account".to_string()))?;
+
+    Ok(Json(LinkedPhoneResponse {
+        id: row.try_get::<Uuid, _>(0)?.to_string(),
+        phone_number: req.phone_number,
+        role: PhoneRole::Uploader.as_str().to_string(),
+        verified_at: row.try_get::<chrono::DateTime<chrono::Utc>, _>(1)?.to_rfc3339(),
+    }))
+}
+
+pub async fn remove_phone(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(phone_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let phone_id = Uuid::parse_str(&phone_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    SecurityService::require_step_up(&state.db, &claims, "phone.unlinked", json!({ "phone_id": phone_id })).await?;
+
+    let phone_number: String =
+        sqlx::query_scalar("DELETE FROM user_phones WHERE id = $1 AND user_id = $2 RETURNING phone_number")
+            .bind(phone_id)
+            .bind(user_id)
+            .fetch_optional(&state.db)
+            .await?
+            .ok_or_else(|| AppError::NotFound("Phone not found".to_string()))?;
+
+    // An unlinked phone stops acting for the account at once
+    UserSessionRepo::revoke_phone(&state.db, user_id, &phone_number).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+pub async fn list_devices(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<Vec<DeviceResponse>>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let rows = sqlx::query(
+        r#"
+        SELECT id, platform, created_at, last_seen_at
+        FROM user_devices
+        WHERE user_id = $1
+        ORDER BY created_at ASC
+        "#,
+    )
+    .bind(user_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let devices = rows.iter().map(device_response).collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+    Ok(Json(devices))
+}
+
+/// Registers the companion app on this device for push notifications about
+/// proofs and verifications. The app calls it on every launch; a token
+/// seen before is refreshed, and moves here if another account had it.
+pub async fn register_device(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(req): Json<RegisterDeviceRequest>,
+) -> Result<Json<DeviceResponse>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let token = req.token.trim();
+    if token.is_empty() || token.len() > MAX_DEVICE_TOKEN_LEN {
+        return Err(AppError::Validation("Invalid device token".to_string()));
+    }
+    if !DEVICE_PLATFORMS.contains(&req.platform.as_str()) {
+        return Err(AppError::Validation("platform must be \"android\", \"ios\" or \"web\"".to_string()));
+    }
+
+    let row = sqlx::query(
+        r#"
+        INSERT INTO user_devices (user_id, token, platform)
+        VALUES ($1, $2, $3)
+        ON CONFLICT (token) DO UPDATE
+            SET user_id = EXCLUDED.user_id, platform = EXCLUDED.platform, last_seen_at = NOW()
+        RETURNING id, platform, created_at, last_seen_at
+        "#,
+    )
+    .bind(user_id)
+    .bind(token)
+    .bind(&req.platform)
+    .fetch_one(&state.db)
+    .await?;
+
+    Ok(Json(device_response(&row)?))
+}
+
+pub async fn remove_device(
+    State(state): State<AppState>,
+    claims: Claims,
+    Path(device_id): Path<String>,
+) -> Result<StatusCode, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let device_id = Uuid::parse_str(&device_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let result = sqlx::query("DELETE FROM user_devices WHERE id = $1 AND user_id = $2")
+        .bind(device_id)
+        .bind(user_id)
+        .execute(&state.db)
+        .await?;
+
+    if result.rows_affected() == 0 {
+        return Err(AppError::NotFound("Device not found".to_string()));
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+fn device_response(row: &sqlx::postgres::PgRow) -> Result<DeviceResponse, sqlx::Error> {
+    Ok(DeviceResponse {
+        id: row.try_get::<Uuid, _>(0)?.to_string(),
+        platform: row.try_get(1)?,
+        created_at: row.try_get::<chrono::DateTime<chrono::Utc>, _>(2)?.to_rfc3339(),
+        last_seen_at: row.try_get::<chrono::DateTime<chrono::Utc>, _>(3)?.to_rfc3339(),
+    })
+}
+
+/// Which channels the owner hears about their proofs on, and when.
+pub async fn get_notification_preferences(
+    State(state): State<AppState>,
+    claims: Claims,
+) -> Result<Json<NotificationPreferencesResponse>, AppError> {
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+    let preferences = NotificationRepo::preferences(&state.db, user_id).await?;
+    Ok(Json(preferences.into()))
+}
+
+/// Sets the channels notifications go out on and the quiet hours they're
+/// held through. Sign-in codes are always texted.
+pub async fn update_notification_preferences(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(req): Json<NotificationPreferencesRequest>,
+) -> Result<Json<NotificationPreferencesResponse>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let email_address = req.email_address.as_deref().map(str::trim).filter(|a| !a.is_empty());
+    if email_address.is_some_and(|a| a.len() > 255 || a.parse::<lettre::message::Mailbox>().is_err()) {
+        return Err(AppError::Validation("Invalid email_address".to_string()));
+    }
+    if req.email && email_address.is_none() {
+        return Err(AppError::Validation("email_address is required for email notifications".to_string()));
+    }
+
+    let timezone = req.timezone.as_deref().map(str::trim).unwrap_or("Africa/Nairobi");
+    if timezone.parse::<chrono_tz::Tz>().is_err() {
+        return Err(AppError::Validation(format!("Unknown timezone: {}", timezone)));
+    }
+
+    let (quiet_hours_start, quiet_hours_end) = match &req.quiet_hours {
+        Some(quiet_hours) => {
+            let parse = |value: &str| {
+                chrono::NaiveTime::parse_from_str(value.trim(), "%H:%M")
+                    .map_err(|_| AppError::Validation(format!("Invalid time, expected HH:MM: {}", value)))
+            };
+            let (start, end) = (parse(&quiet_hours.start)?, parse(&quiet_hours.end)?);
+            if start == end {
+                return Err(AppError::Validation("Quiet hours must start and end at different times".to_string()));
+            }
+            (Some(start), Some(end))
+        }
+        None => (None, None),
+    };
+
+    if email_address.is_some() && !EmailService::is_enabled(&state.config) {
+        return Err(AppError::Validation("Email notifications aren't available".to_string()));
+    }
+
+    let mut preferences = NotificationPreferences {
+        sms: req.sms,
+        push: req.push,
+        email: req.email,
+        email_address: email_address.map(str::to_string),
+        email_verified_at: None,
+        quiet_hours_start,
+        quiet_hours_end,
+        timezone: timezone.to_string(),
+    };
+    preferences.email_verified_at = NotificationRepo::save_preferences(&state.db, user_id, &preferences).await?;
+
+    // A new or still unverified address is sent a fresh code
+    if let (Some(address), None) = (email_address, preferences.email_verified_at) {
+        let code = AuthService::generate_otp();
+        let mut redis_conn = state.redis.get_async_connection().await?;
+        redis_conn
+            .set_ex::<_, _, ()>(email_code_key(user_id), format!("{}:{}", code, address), EMAIL_CODE_TTL_SECS)
+            .await?;
+        let locale = current_locale();
+        EmailService::send(
+            &state.config,
+            address,
+            &Message::PushTitle.render(locale),
+            &Message::OtpSms { otp: &code }.render(locale),
+        )
+        .await?;
+    }
+
+    Ok(Json(preferences.into()))
+}
+
+/// Confirms the notification address with the code emailed to it when it
+/// was set. Email notifications start once it's verified.
+pub async fn verify_notification_email(
+    State(state): State<AppState>,
+    claims: Claims,
+    Json(req): Json<VerifyEmailRequest>,
+) -> Result<Json<NotificationPreferencesResponse>, AppError> {
+    claims.require_owner()?;
+    let user_id = Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let lockout_since = chrono::Utc::now() - chrono::Duration::minutes(EMAIL_CODE_LOCKOUT_MINS);
+    if AuditRepo::count_since(&state.db, user_id, "email.verify_failed", lockout_since).await?
+        >= EMAIL_CODE_MAX_FAILURES
+    {
+        return Err(AppError::RateLimit(EMAIL_CODE_LOCKOUT_MINS as u64 * 60));
+    }
+
+    let mut redis_conn = state.redis.get_async_connection().await?;
+    let stored: Option<String> = redis_conn.get(email_code_key(user_id)).await?;
+    let address = stored
+        .as_deref()
+        .and_then(|stored| stored.split_once(':'))
+        .filter(|(code, _)| *code == req.code.trim())
+        .map(|(_, address)| address.to_string());
+    let Some(address) = address else {
+        AuditRepo::record(&state.db, user_id, None, "email.verify_failed", None, json!({})).await?;
+        return Err(AppError::InvalidOtp);
+    };
+    redis_conn.del::<_, ()>(email_code_key(user_id)).await?;
+
+    if !NotificationRepo::verify_email(&state.db, user_id, &address).await? {
+        return Err(AppError::Validation("The email address has changed since the code was sent".to_string()));
+    }
+    let preferences = NotificationRepo::preferences(&state.db, user_id).await?;
+    Ok(Json(preferences.into()))
+}
+
+fn email_code_key(user_id: Uuid) -> String {
+    format!("email_code:{}", user_id)
+}
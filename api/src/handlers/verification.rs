@@ -1,45 +1,417 @@
-use axum::{extract::{Path, State}, Json};
-use serde::Serialize;
+use std::net::IpAddr;
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Response},
+    Json,
+};
+use hmac::{Hmac, Mac};
+use redis::AsyncCommands;
+use serde::{Deserialize, Serialize};
+use sha2::Sha256;
 use sqlx::Row;
 
 use crate::error::AppError;
 use crate::handlers::AppState;
+use crate::middleware::client_ip::ClientIp;
+use crate::services::captcha::CaptchaService;
+
+/// Every lookup is answered no sooner than this after it arrived, so a code
+/// that exists can't be told from one that doesn't by how long it took.
+const MIN_RESPONSE_TIME: std::time::Duration = std::time::Duration::from_millis(250);
+
+/// Codes issued before they were lengthened are this short; anything
+/// shorter, short codes aside, can't be one and isn't looked up.
+const MIN_CODE_LEN: usize = 12;
+const MAX_CODE_LEN: usize = 50;
+
+/// Longest a badge may be cached before it's revalidated, so a proof that
+/// is contested stops showing as verified within this long.
+const EMBED_MAX_AGE_SECS: i64 = 300;
+
+#[derive(Deserialize)]
+pub struct VerifyQuery {
+    /// Turnstile or reCAPTCHA widget token, once the page has asked for one
+    pub captcha_token: Option<String>,
+}
 
 #[derive(Serialize)]
 pub struct VerificationResponse {
     pub valid: bool,
     pub business_id: String,
     pub period: String,
-    pub credit_score: i32,
+    pub proof_type: String,
+    pub credit_score: Option<i32>,
     pub metrics: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub threshold: Option<crate::models::ThresholdResult>,
+    /// Business profile fields the owner chose to make public
+    pub business: serde_json::Value,
+    /// Set when disputes against the till's transactions have put this
+    /// proof in question
+    pub contested: bool,
+    /// SHA-256 of the PayBill account number a branch-level proof covers
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub score_breakdown: Option<Vec<crate::handlers::proofs::ScoreComponent>>,
+}
+
+#[derive(Deserialize)]
+pub struct EmbedQuery {
+    /// "html" or "json"; chosen from the Accept header when unset
+    pub format: Option<String>,
 }
 
+/// What an embedded badge shows.
+#[derive(Serialize)]
+pub struct EmbedBadge {
+    /// Whether the proof is live and unchallenged
+    pub valid: bool,
+    /// "verified", "expired" or "contested"
+    pub status: &'static str,
+    pub proof_type: String,
+    /// Withheld when the owner requires lender consent, and for threshold
+    /// proofs
+    pub credit_score: Option<i32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meets_threshold: Option<bool>,
+    /// "high", "medium" or "low"; unset on proofs from before uploads were
+    /// scored
+    pub trust_tier: Option<String>,
+    pub expires_at: String,
+    /// The full verification page
+    pub verify_url: String,
+}
+
+/// The public verification page. Lookups are throttled per client address,
+/// and past `VERIFY_CAPTCHA_AFTER` an hour each one needs a solved captcha,
+/// so codes can't be enumerated by scraping it.
 pub async fn verify_code(
     State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
     Path(code): Path<String>,
+    Query(query): Query<VerifyQuery>,
 ) -> Result<Json<VerificationResponse>, AppError> {
+    let respond_at = tokio::time::Instant::now() + MIN_RESPONSE_TIME;
+    throttle_lookup(&state, ip, query.captcha_token.as_deref()).await?;
+
+    // Short codes aren't resolved here: a million of them can be walked
+    // from enough addresses, whatever the per-address limit
+    let code = crate::utils::normalize_verification_code(&code);
+    let result = if is_well_formed_code(&code) {
+        lookup_code(&state, &code).await
+    } else {
+        Err(AppError::ProofNotFound)
+    };
+    tokio::time::sleep_until(respond_at).await;
+    result.map(Json)
+}
+
+/// A badge lenders can embed in their own portals, as a snippet of HTML to
+/// frame or JSON to render. Expired and contested proofs still get a badge
+/// saying so. Responses carry a signed ETag and are cacheable for up to
+/// `EMBED_MAX_AGE_SECS`, never past the proof's expiry.
+pub async fn embed_badge(
+    State(state): State<AppState>,
+    ClientIp(ip): ClientIp,
+    Path(code): Path<String>,
+    Query(query): Query<EmbedQuery>,
+    headers: HeaderMap,
+) -> Result<Response, AppError> {
+    let html = match query.format.as_deref() {
+        Some("html") => true,
+        Some("json") => false,
+        Some(other) => return Err(AppError::Validation(format!("Unknown format: {}", other))),
+        None => headers
+            .get(header::ACCEPT)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|accept| accept.contains("text/html")),
+    };
+
+    let code = crate::utils::normalize_verification_code(&code);
+    let respond_at = tokio::time::Instant::now() + MIN_RESPONSE_TIME;
+    let result = match throttle_embed(&state, ip).await {
+        Ok(()) if is_well_formed_code(&code) => lookup_badge(&state, &code).await,
+        Ok(()) => Err(AppError::ProofNotFound),
+        Err(e) => Err(e),
+    };
+    tokio::time::sleep_until(respond_at).await;
+    let (badge, expires_at) = result?;
+
+    let (body, content_type) = if html {
+        (render_badge(&badge).into_bytes(), "text/html; charset=utf-8")
+    } else {
+        let body = serde_json::to_vec(&badge).map_err(|e| AppError::Internal(e.into()))?;
+        (body, "application/json")
+    };
+    let etag = embed_etag(&state.config.jwt_secret, content_type, &body);
+    let max_age = if badge.valid {
+        EMBED_MAX_AGE_SECS.min((expires_at - chrono::Utc::now()).num_seconds().max(0))
+    } else {
+        EMBED_MAX_AGE_SECS
+    };
+
+    let mut response_headers = HeaderMap::new();
+    let value = |v: String| HeaderValue::from_str(&v).map_err(|e| AppError::Internal(e.into()));
+    response_headers.insert(header::ETAG, value(etag.clone())?);
+    response_headers.insert(
+        header::CACHE_CONTROL,
+        value(format!("public, max-age={}, must-revalidate", max_age))?,
+    );
+    response_headers.insert(header::VARY, HeaderValue::from_static("Accept"));
+    response_headers.insert(header::ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+
+    let cached = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|tags| {
+            tags.split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == etag || tag == "*")
+        });
+    if cached {
+        return Ok((StatusCode::NOT_MODIFIED, response_headers).into_response());
+    }
+
+    response_headers.insert(header::CONTENT_TYPE, HeaderValue::from_static(content_type));
+    if html {
+        // Framed anywhere, but it loads nothing and runs nothing
+        response_headers.insert(
+            header::CONTENT_SECURITY_POLICY,
+            HeaderValue::from_static("default-src 'none'; style-src 'unsafe-inline'; frame-ancestors *"),
+        );
+    }
+    Ok((StatusCode::OK, response_headers, body).into_response())
+}
+
+/// Counts a badge load against the client's per-minute limit.
+async fn throttle_embed(state: &AppState, ip: IpAddr) -> Result<(), AppError> {
+    let mut redis_conn = state.redis.get_async_connection().await?;
+    let key = format!("verify:embed:{}", ip);
+    let recent: u32 = redis_conn.incr(&key, 1).await?;
+    if recent == 1 {
+        redis_conn.expire(&key, 60).await?;
+    }
+    if recent > state.config.embed_rate_limit_per_minute {
+        let ttl: i64 = redis_conn.ttl(&key).await.unwrap_or(60);
+        return Err(AppError::RateLimit(ttl.max(1) as u64));
+    }
+    Ok(())
+}
+
+/// A quoted ETag for a badge: an HMAC of its body under the server's secret,
+/// so it changes with the badge and can't be forged for one.
+fn embed_etag(secret: &str, content_type: &str, body: &[u8]) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(b"embed-etag.");
+    mac.update(content_type.as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("\"{}\"", &hex::encode(mac.finalize().into_bytes())[..32])
+}
+
+/// A self-contained badge with inline styles, to frame.
+fn render_badge(badge: &EmbedBadge) -> String {
+    let (title, colour) = match badge.status {
+        "verified" => ("Verified credit proof", "#1b7f3b"),
+        "contested" => ("Credit proof under dispute", "#b26a00"),
+        _ => ("Credit proof expired", "#6b6b6b"),
+    };
+    let detail = match (badge.credit_score, badge.meets_threshold) {
+        (Some(score), _) => format!("Score {}", score),
+        (None, Some(true)) => "Meets its score threshold".to_string(),
+        (None, Some(false)) => "Below its score threshold".to_string(),
+        (None, None) => String::new(),
+    };
+    let tier = badge
+        .trust_tier
+        .as_deref()
+        .map(|tier| format!("<span class=\"tier\">{} trust</span>", html_escape(tier)))
+        .unwrap_or_default();
+    let expiry = chrono::DateTime::parse_from_rfc3339(&badge.expires_at)
+        .map(|t| t.format("%-d %b %Y").to_string())
+        .unwrap_or_default();
+    let expiry_label = if badge.status == "expired" { "Expired" } else { "Valid until" };
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+<meta charset="utf-8">
+<title>{title}</title>
+<style>
+body {{ margin: 0; font: 14px/1.4 system-ui, sans-serif; }}
+a {{ display: block; padding: 10px 14px; border-left: 4px solid {colour}; color: #1a1a1a; text-decoration: none; }}
+.title {{ display: block; font-weight: 600; color: {colour}; }}
+.tier, .expiry {{ color: #555; font-size: 12px; margin-right: 8px; }}
+</style>
+</head>
+<body>
+<a href="{url}" target="_blank" rel="noopener">
+<span class="title">{title}</span>
+<span class="detail">{detail}</span>
+<div>{tier}<span class="expiry">{expiry_label} {expiry}</span></div>
+</a>
+</body>
+</html>
+"#,
+        title = title,
+        colour = colour,
+        url = html_escape(&badge.verify_url),
+        detail = html_escape(&detail),
+        tier = tier,
+        expiry_label = expiry_label,
+        expiry = expiry,
+    )
+}
+
+pub(crate) fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Counts a lookup against the client's per-minute limit and its hourly
+/// allowance before a captcha is required.
+async fn throttle_lookup(state: &AppState, ip: IpAddr, captcha_token: Option<&str>) -> Result<(), AppError> {
+    let config = &state.config;
+    let mut redis_conn = state.redis.get_async_connection().await?;
+
+    let rate_key = format!("verify:rate:{}", ip);
+    let recent: u32 = redis_conn.get(&rate_key).await.unwrap_or(0);
+    if recent >= config.verify_rate_limit_per_minute {
+        let ttl: i64 = redis_conn.ttl(&rate_key).await.unwrap_or(60);
+        return Err(AppError::RateLimit(ttl.max(1) as u64));
+    }
+    redis_conn.incr(&rate_key, 1).await?;
+    redis_conn.expire(&rate_key, 60).await?;
+
+    let (Some(provider), Some(secret)) = (&config.captcha_provider, &config.captcha_secret) else {
+        return Ok(());
+    };
+    let lookups_key = format!("verify:lookups:{}", ip);
+    let lookups: u32 = redis_conn.incr(&lookups_key, 1).await?;
+    if lookups == 1 {
+        redis_conn.expire(&lookups_key, 3600).await?;
+    }
+    if lookups <= config.verify_captcha_after {
+        return Ok(());
+    }
+
+    let token = captcha_token.filter(|t| !t.is_empty()).ok_or(AppError::CaptchaRequired)?;
+    let solved = CaptchaService::verify(provider, secret, token, ip)
+        .await
+        .map_err(|e| AppError::Internal(e.context("Captcha verification failed")))?;
+    if !solved {
+        return Err(AppError::CaptchaRequired);
+    }
+    Ok(())
+}
+
+/// Whether `code` could be one `generate_verification_code` issued.
+fn is_well_formed_code(code: &str) -> bool {
+    (MIN_CODE_LEN..=MAX_CODE_LEN).contains(&code.len())
+        && code.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_')
+}
+
+/// The badge for a completed proof and when it expires.
+async fn lookup_badge(state: &AppState, code: &str) -> Result<(EmbedBadge, chrono::DateTime<chrono::Utc>), AppError> {
     let row = sqlx::query(
         r#"
-        SELECT till_id, credit_score, metrics, created_at
+        SELECT proof_type, credit_score, meets_threshold, expires_at, contested_at IS NOT NULL, trust_tier,
+               disclosure_policy
         FROM proof_sessions
         WHERE verification_code = $1 AND status = 'completed'
         "#,
     )
-    .bind(&code)
-    .fetch_optional(&state.db)
+    .bind(code)
+    .fetch_optional(state.read_db())
+    .await?
+    .ok_or(AppError::ProofNotFound)?;
+
+    let expires_at: chrono::DateTime<chrono::Utc> = row.try_get(3)?;
+    let contested: bool = row.try_get(4)?;
+    let disclosure_policy: crate::models::DisclosurePolicy = row
+        .try_get::<serde_json::Value, _>(6)
+        .ok()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    // What lenders must ask the owner for isn't shown to anyone who loads
+    // the badge
+    let disclosed = !disclosure_policy.lender_consent;
+
+    let status = if expires_at < chrono::Utc::now() {
+        "expired"
+    } else if contested {
+        "contested"
+    } else {
+        "verified"
+    };
+    let badge = EmbedBadge {
+        valid: status == "verified",
+        status,
+        proof_type: row.try_get(0)?,
+        credit_score: row.try_get::<Option<i32>, _>(1)?.filter(|_| disclosed),
+        meets_threshold: row.try_get::<Option<bool>, _>(2)?.filter(|_| disclosed),
+        trust_tier: row.try_get(5)?,
+        expires_at: expires_at.to_rfc3339(),
+        verify_url: format!("{}/verify/{}", state.config.public_url.trim_end_matches('/'), code),
+    };
+    Ok((badge, expires_at))
+}
+
+async fn lookup_code(state: &AppState, code: &str) -> Result<VerificationResponse, AppError> {
+    let row = sqlx::query(
+        r#"
+        SELECT till_id, credit_score, metrics, created_at, expires_at, user_id, score_breakdown, disclosure_policy,
+               proof_type, score_threshold, meets_threshold, contested_at IS NOT NULL, account_hash
+        FROM proof_sessions
+        WHERE verification_code = $1 AND status = 'completed'
+        "#,
+    )
+    .bind(code)
+    .fetch_optional(state.read_db())
     .await?;
 
-    let row = row.ok_or_else(|| AppError::NotFound("Proof not found".to_string()))?;
+    let row = row.ok_or(AppError::ProofNotFound)?;
 
     let till_id: uuid::Uuid = row.try_get(0).map_err(|e| AppError::Database(e))?;
-    let credit_score: Option<i32> = row.try_get(1).ok();
-    let metrics: Option<serde_json::Value> = row.try_get(2).ok();
+    let credit_score: Option<Option<i32>> = row.try_get(1).ok();
+    let metrics: Option<Option<serde_json::Value>> = row.try_get(2).ok();
     let created_at: chrono::DateTime<chrono::Utc> = row.try_get(3).map_err(|e| AppError::Database(e))?;
+    let expires_at: chrono::DateTime<chrono::Utc> = row.try_get(4).map_err(|e| AppError::Database(e))?;
+    let user_id: uuid::Uuid = row.try_get(5).map_err(|e| AppError::Database(e))?;
+    let score_breakdown: Option<serde_json::Value> = row.try_get(6).map_err(|e| AppError::Database(e))?;
+    let disclosure_policy: crate::models::DisclosurePolicy = row
+        .try_get::<serde_json::Value, _>(7)
+        .ok()
+        .and_then(|v| serde_json::from_value(v).ok())
+        .unwrap_or_default();
+    let proof_type: String = row.try_get(8).map_err(|e| AppError::Database(e))?;
+    let threshold = crate::handlers::proofs::threshold_result(
+        row.try_get(9).map_err(|e| AppError::Database(e))?,
+        row.try_get(10).map_err(|e| AppError::Database(e))?,
+    );
+    let contested: bool = row.try_get(11).map_err(|e| AppError::Database(e))?;
+    let account_hash: Option<String> = row.try_get(12).map_err(|e| AppError::Database(e))?;
+
+    let score_breakdown = score_breakdown
+        .filter(|_| disclosure_policy.score_breakdown)
+        .and_then(|v| serde_json::from_value::<crate::models::ScoreBreakdown>(v).ok())
+        .map(|b| crate::handlers::proofs::score_components(&b));
+
+    if expires_at < chrono::Utc::now() {
+        return Err(AppError::ProofExpired);
+    }
 
     // Get till info
     let till_row = sqlx::query("SELECT till_number FROM business_tills WHERE id = $1")
         .bind(till_id)
-        .fetch_optional(&state.db)
+        .fetch_optional(state.read_db())
         .await?;
 
     let till_number = till_row
@@ -47,7 +419,7 @@ pub async fn verify_code(
         .unwrap_or_else(|| "unknown".to_string());
 
     // Hash till number for privacy
-    let business_id = crate::utils::hash_phone_number(&till_number);
+    let business_id = crate::utils::public_identifier_hash(&till_number);
 
     // Calculate period (simplified - in production, use actual date range from transactions)
     let period = format!(
@@ -56,12 +428,23 @@ pub async fn verify_code(
         created_at.format("%b %Y")
     );
 
-    Ok(Json(VerificationResponse {
+    let business = crate::handlers::users::fetch_business_profile(state.read_db(), user_id)
+        .await?
+        .map(|profile| profile.public_view())
+        .unwrap_or(serde_json::json!({}));
+
+    Ok(VerificationResponse {
         valid: true,
         business_id,
         period,
-        credit_score: credit_score.unwrap_or(0),
-        metrics: metrics.unwrap_or(serde_json::json!({})),
-    }))
+        proof_type,
+        credit_score: credit_score.flatten(),
+        metrics: metrics.flatten().unwrap_or(serde_json::json!({})),
+        threshold,
+        business,
+        contested,
+        account_hash,
+        score_breakdown,
+    })
 }
 
@@ -0,0 +1,164 @@
+use axum::{
+    extract::{Path, State},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use sqlx::Row;
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::handlers::AppState;
+use crate::middleware::lender::LenderAuth;
+use crate::services::webhook::WebhookService;
+
+#[derive(Deserialize)]
+pub struct CreateWebhookRequest {
+    pub url: String,
+    /// "events" (default) for event callbacks, or "intake" for the endpoint
+    /// merchants' application handoffs are sent to
+    pub purpose: Option<String>,
+}
+
+#[derive(Serialize)]
+pub struct WebhookResponse {
+    pub id: String,
+    pub url: String,
+    pub purpose: String,
+    pub active: bool,
+    pub created_at: String,
+    /// Signing secret; only returned when the endpoint is created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub secret: Option<String>,
+}
+
+/// Only whether the endpoint accepted the event. What it answered, or why
+/// it couldn't be reached, stays in the API's logs, so the endpoint can't be
+/// used to read responses from other hosts.
+#[derive(Serialize)]
+pub struct TestWebhookResponse {
+    pub delivered: bool,
+}
+
+pub async fn list_webhooks(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+) -> Result<Json<Vec<WebhookResponse>>, AppError> {
+    let rows = sqlx::query(
+        r#"
+        SELECT id, url, active, created_at, purpose
+        FROM webhook_endpoints
+        WHERE lender_id = $1
+        ORDER BY created_at
+        "#,
+    )
+    .bind(lender.lender_id)
+    .fetch_all(&state.db)
+    .await?;
+
+    let mut webhooks = Vec::with_capacity(rows.len());
+    for row in rows {
+        let id: Uuid = row.try_get(0)?;
+        let created_at: chrono::DateTime<chrono::Utc> = row.try_get(3)?;
+        webhooks.push(WebhookResponse {
+            id: id.to_string(),
+            url: row.try_get(1)?,
+            purpose: row.try_get(4)?,
+            active: row.try_get(2)?,
+            created_at: created_at.to_rfc3339(),
+            secret: None,
+        });
+    }
+    Ok(Json(webhooks))
+}
+
+/// Registers an endpoint and returns its signing secret, which is not shown
+/// again. A new intake endpoint replaces the lender's previous one.
+pub async fn create_webhook(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    Json(req): Json<CreateWebhookRequest>,
+) -> Result<Json<WebhookResponse>, AppError> {
+    let url = reqwest::Url::parse(&req.url).map_err(|e| AppError::Validation(format!("Invalid URL: {}", e)))?;
+    // Plain HTTP is only accepted for local testing against a demo server
+    if url.scheme() != "https" && !(state.config.demo_mode && url.scheme() == "http") {
+        return Err(AppError::Validation("Webhook URLs must use https".to_string()));
+    }
+    WebhookService::resolve_public(&url)
+        .await
+        .map_err(|e| AppError::Validation(format!("Invalid webhook URL: {}", e)))?;
+
+    let purpose = req.purpose.as_deref().unwrap_or("events");
+    if !["events", "intake"].contains(&purpose) {
+        return Err(AppError::Validation(format!("Invalid webhook purpose: {}", purpose)));
+    }
+
+    let secret = WebhookService::generate_secret();
+    let mut tx = state.db.begin().await?;
+    if purpose == "intake" {
+        sqlx::query("UPDATE webhook_endpoints SET active = FALSE WHERE lender_id = $1 AND purpose = 'intake'")
+            .bind(lender.lender_id)
+            .execute(&mut *tx)
+            .await?;
+    }
+    let row = sqlx::query(
+        r#"
+        INSERT INTO webhook_endpoints (lender_id, url, secret, purpose)
+        VALUES ($1, $2, $3, $4)
+        RETURNING id, created_at
+        "#,
+    )
+    .bind(lender.lender_id)
+    .bind(url.as_str())
+    .bind(&secret)
+    .bind(purpose)
+    .fetch_one(&mut *tx)
+    .await?;
+    tx.commit().await?;
+
+    let id: Uuid = row.try_get(0)?;
+    let created_at: chrono::DateTime<chrono::Utc> = row.try_get(1)?;
+    Ok(Json(WebhookResponse {
+        id: id.to_string(),
+        url: url.to_string(),
+        purpose: purpose.to_string(),
+        active: true,
+        created_at: created_at.to_rfc3339(),
+        secret: Some(secret),
+    }))
+}
+
+/// Sends a signed `webhook.test` event so integrators can check their
+/// signature verification before real events arrive.
+pub async fn test_webhook(
+    State(state): State<AppState>,
+    lender: LenderAuth,
+    Path(webhook_id): Path<String>,
+) -> Result<Json<TestWebhookResponse>, AppError> {
+    let webhook_id = Uuid::parse_str(&webhook_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+
+    let row = sqlx::query("SELECT url, secret FROM webhook_endpoints WHERE id = $1 AND lender_id = $2")
+        .bind(webhook_id)
+        .bind(lender.lender_id)
+        .fetch_optional(&state.db)
+        .await?
+        .ok_or_else(|| AppError::NotFound("Webhook not found".to_string()))?;
+    let url: String = row.try_get(0)?;
+    let secret: String = row.try_get(1)?;
+
+    let data = serde_json::json!({
+        "webhook_id": webhook_id,
+        "message": "Test event. Verify its signature as you would any other delivery.",
+    });
+    let delivered = match WebhookService::deliver(&url, &secret, "webhook.test", data).await {
+        Ok(status) if (200..300).contains(&status) => true,
+        Ok(status) => {
+            tracing::info!("Webhook {} answered {} to webhook.test", webhook_id, status);
+            false
+        }
+        Err(e) => {
+            tracing::info!("Webhook {} test delivery failed: {}", webhook_id, e);
+            false
+        }
+    };
+    Ok(Json(TestWebhookResponse { delivered }))
+}
@@ -0,0 +1,228 @@
+//! Message catalog for user-facing text (SMS bodies and API errors) in
+//! English and Swahili.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Locale {
+    #[default]
+    En,
+    Sw,
+}
+
+impl Locale {
+    pub fn from_code(code: &str) -> Option<Self> {
+        let primary = code.trim().split(['-', '_']).next()?.to_ascii_lowercase();
+        match primary.as_str() {
+            "en" => Some(Locale::En),
+            "sw" => Some(Locale::Sw),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Sw => "sw",
+        }
+    }
+
+    /// Picks the highest-weighted supported language from an
+    /// `Accept-Language` header, e.g. `sw-KE,sw;q=0.9,en;q=0.8`.
+    pub fn from_accept_language(header: &str) -> Option<Self> {
+        let mut candidates: Vec<(f32, Locale)> = header
+            .split(',')
+            .filter_map(|part| {
+                let mut pieces = part.split(';');
+                let locale = Locale::from_code(pieces.next()?)?;
+                let quality = pieces
+                    .find_map(|p| p.trim().strip_prefix("q="))
+                    .and_then(|q| q.parse::<f32>().ok())
+                    .unwrap_or(1.0);
+                Some((quality, locale))
+            })
+            .collect();
+
+        candidates.sort_by(|a, b| b.0.partial_cmp(&a.0).unwrap_or(std::cmp::Ordering::Equal));
+        candidates.first().map(|(_, locale)| *locale)
+    }
+}
+
+pub enum Message<'a> {
+    OtpSms { otp: &'a str },
+    ProofCompletedSms { score: i32, verification_code: &'a str },
+    ThresholdProofCompletedSms { threshold: u32, meets: bool, verification_code: &'a str },
+    ProofFailedSms,
+    ProofExpiringSms { days: i64, verification_code: &'a str, link: &'a str },
+    ConsentRequestSms { lender: &'a str, verification_code: &'a str, ussd_code: Option<&'a str> },
+    UssdConsentRequests,
+    UssdNoConsentRequests,
+    UssdConsentPrompt { lender: &'a str, verification_code: &'a str },
+    UssdConsentApproved { lender: &'a str },
+    UssdConsentDeclined { lender: &'a str },
+    UssdInvalidChoice,
+    UssdUnknownPhone,
+    PushTitle,
+    ProofVerifiedPush { lender: &'a str, verification_code: &'a str },
+    TillNotFound,
+    ProofNotFound,
+    ProofExpired,
+    ProofRevoked,
+    RateLimited,
+    CaptchaRequired,
+    PlanLimitReached,
+    InvalidOtp,
+    StepUpRequired,
+    Unauthorized,
+    InvalidTillNumber,
+    UnknownTillNumber,
+    MissingTillId,
+    MissingFile,
+    UnsupportedFileType,
+    UnsupportedImageType,
+    FileRejected,
+    DatabaseError,
+    CacheError,
+    InternalError,
+}
+
+impl Message<'_> {
+    pub fn render(&self, locale: Locale) -> String {
+        match locale {
+            Locale::En => self.render_en(),
+            Locale::Sw => self.render_sw(),
+        }
+    }
+
+    fn render_en(&self) -> String {
+        match self {
+            Message::OtpSms { otp } => format!("Your verification code is: {}", otp),
+            Message::ProofCompletedSms { score, verification_code } => format!(
+                "Your M-Pesa credit proof is ready. Score: {}/100. Share code {} with your lender.",
+                score, verification_code
+            ),
+            Message::ThresholdProofCompletedSms { threshold, meets, verification_code } => format!(
+                "Your M-Pesa credit proof is ready. Your score {} at least {}/100. Share code {} with your lender.",
+                if *meets { "is" } else { "is not" },
+                threshold,
+                verification_code
+            ),
+            Message::ProofFailedSms => {
+                "We could not generate your credit proof. Please check your data and try again.".to_string()
+            }
+            Message::ProofExpiringSms { days, verification_code, link } => format!(
+                "Your M-Pesa credit proof {} expires in {} days. Tap to generate a new one: {}",
+                verification_code, days, link
+            ),
+            Message::ConsentRequestSms { lender, verification_code, ussd_code } => match ussd_code {
+                Some(ussd_code) => format!(
+                    "{} is asking to see your credit proof {}. Dial {} or open the app to approve or decline.",
+                    lender, verification_code, ussd_code
+                ),
+                None => format!(
+                    "{} is asking to see your credit proof {}. Open the app to approve or decline.",
+                    lender, verification_code
+                ),
+            },
+            Message::UssdConsentRequests => "Lenders asking to see your proofs:".to_string(),
+            Message::UssdNoConsentRequests => "No requests are waiting for your answer.".to_string(),
+            Message::UssdConsentPrompt { lender, verification_code } => {
+                format!("{} is asking to see proof {}.\n1. Approve\n2. Decline", lender, verification_code)
+            }
+            Message::UssdConsentApproved { lender } => format!("{} can now see your proof.", lender),
+            Message::UssdConsentDeclined { lender } => format!("You declined {}'s request.", lender),
+            Message::UssdInvalidChoice => "Invalid choice.".to_string(),
+            Message::UssdUnknownPhone => "This number has no account.".to_string(),
+            Message::PushTitle => "M-Pesa Credit Proof".to_string(),
+            Message::ProofVerifiedPush { lender, verification_code } => {
+                format!("{} just verified your credit proof {}.", lender, verification_code)
+            }
+            Message::TillNotFound => "Till not found".to_string(),
+            Message::ProofNotFound => "Proof not found".to_string(),
+            Message::ProofExpired => "Proof has expired".to_string(),
+            Message::ProofRevoked => "Proof has been revoked".to_string(),
+            Message::RateLimited => "Rate limit exceeded".to_string(),
+            Message::CaptchaRequired => "Too many lookups. Complete the captcha to continue".to_string(),
+            Message::PlanLimitReached => "Your plan's allowance for this month is used up".to_string(),
+            Message::InvalidOtp => "Invalid OTP".to_string(),
+            Message::StepUpRequired => "Confirm with a new OTP to continue".to_string(),
+            Message::Unauthorized => "Unauthorized".to_string(),
+            Message::InvalidTillNumber => "Invalid till number format".to_string(),
+            Message::UnknownTillNumber => "M-Pesa has no till or PayBill with this number".to_string(),
+            Message::MissingTillId => "Missing till_id".to_string(),
+            Message::MissingFile => "Missing file".to_string(),
+            Message::UnsupportedFileType => "Unsupported file type. Please upload CSV, XLSX or PDF".to_string(),
+            Message::UnsupportedImageType => "Unsupported image type. Please upload a JPEG or PNG photo".to_string(),
+            Message::FileRejected => "The uploaded file was rejected".to_string(),
+            Message::DatabaseError => "Database error".to_string(),
+            Message::CacheError => "Cache error".to_string(),
+            Message::InternalError => "Internal server error".to_string(),
+        }
+    }
+
+    fn render_sw(&self) -> String {
+        match self {
+            Message::OtpSms { otp } => format!("Nambari yako ya uthibitisho ni: {}", otp),
+            Message::ProofCompletedSms { score, verification_code } => format!(
+                "Uthibitisho wako wa mkopo wa M-Pesa uko tayari. Alama: {}/100. Mpe mkopeshaji wako nambari {}.",
+                score, verification_code
+            ),
+            Message::ThresholdProofCompletedSms { threshold, meets, verification_code } => format!(
+                "Uthibitisho wako wa mkopo wa M-Pesa uko tayari. Alama yako {} angalau {}/100. Mpe mkopeshaji wako nambari {}.",
+                if *meets { "ni" } else { "si" },
+                threshold,
+                verification_code
+            ),
+            Message::ProofFailedSms => {
+                "Hatukuweza kutengeneza uthibitisho wako wa mkopo. Tafadhali kagua data yako ujaribu tena.".to_string()
+            }
+            Message::ProofExpiringSms { days, verification_code, link } => format!(
+                "Uthibitisho wako wa mkopo wa M-Pesa {} utaisha muda baada ya siku {}. Bofya kutengeneza mpya: {}",
+                verification_code, days, link
+            ),
+            Message::ConsentRequestSms { lender, verification_code, ussd_code } => match ussd_code {
+                Some(ussd_code) => format!(
+                    "{} anaomba kuona uthibitisho wako wa mkopo {}. Piga {} au fungua programu ili kukubali au kukataa.",
+                    lender, verification_code, ussd_code
+                ),
+                None => format!(
+                    "{} anaomba kuona uthibitisho wako wa mkopo {}. Fungua programu ili kukubali au kukataa.",
+                    lender, verification_code
+                ),
+            },
+            Message::UssdConsentRequests => "Wakopeshaji wanaoomba kuona uthibitisho wako:".to_string(),
+            Message::UssdNoConsentRequests => "Hakuna maombi yanayosubiri jibu lako.".to_string(),
+            Message::UssdConsentPrompt { lender, verification_code } => {
+                format!("{} anaomba kuona uthibitisho {}.\n1. Kubali\n2. Kataa", lender, verification_code)
+            }
+            Message::UssdConsentApproved { lender } => format!("Umemruhusu {} kuona uthibitisho wako.", lender),
+            Message::UssdConsentDeclined { lender } => format!("Umekataa ombi la {}.", lender),
+            Message::UssdInvalidChoice => "Chaguo si sahihi.".to_string(),
+            Message::UssdUnknownPhone => "Nambari hii haina akaunti.".to_string(),
+            Message::PushTitle => "Uthibitisho wa Mkopo wa M-Pesa".to_string(),
+            Message::ProofVerifiedPush { lender, verification_code } => {
+                format!("{} amethibitisha uthibitisho wako wa mkopo {} sasa hivi.", lender, verification_code)
+            }
+            Message::TillNotFound => "Till haikupatikana".to_string(),
+            Message::ProofNotFound => "Uthibitisho haukupatikana".to_string(),
+            Message::ProofExpired => "Muda wa uthibitisho umekwisha".to_string(),
+            Message::ProofRevoked => "Uthibitisho umefutwa".to_string(),
+            Message::RateLimited => "Umejaribu mara nyingi sana. Tafadhali subiri kidogo".to_string(),
+            Message::CaptchaRequired => "Maombi mengi sana. Kamilisha captcha ili kuendelea".to_string(),
+            Message::PlanLimitReached => "Kiwango cha mpango wako kwa mwezi huu kimekwisha".to_string(),
+            Message::InvalidOtp => "Nambari ya uthibitisho si sahihi".to_string(),
+            Message::StepUpRequired => "Thibitisha kwa nambari mpya ya uthibitisho ili kuendelea".to_string(),
+            Message::Unauthorized => "Huna ruhusa".to_string(),
+            Message::InvalidTillNumber => "Nambari ya till si sahihi".to_string(),
+            Message::UnknownTillNumber => "M-Pesa haina till wala PayBill yenye nambari hii".to_string(),
+            Message::MissingTillId => "till_id haipo".to_string(),
+            Message::MissingFile => "Faili haipo".to_string(),
+            Message::UnsupportedFileType => "Aina ya faili haitumiki. Tafadhali pakia CSV, XLSX au PDF".to_string(),
+            Message::UnsupportedImageType => {
+                "Aina ya picha haitumiki. Tafadhali pakia picha ya JPEG au PNG".to_string()
+            }
+            Message::FileRejected => "Faili uliyopakia imekataliwa".to_string(),
+            Message::DatabaseError => "Hitilafu ya hifadhidata".to_string(),
+            Message::CacheError => "Hitilafu ya hifadhi ya muda".to_string(),
+            Message::InternalError => "Hitilafu ya ndani ya seva".to_string(),
+        }
+    }
+}
@@ -2,9 +2,12 @@ pub mod config;
 pub mod db;
 pub mod error;
 pub mod handlers;
+pub mod i18n;
 pub mod middleware;
 pub mod models;
+pub mod routes;
 pub mod services;
+pub mod telemetry;
 pub mod utils;
 pub mod worker;
 
@@ -1,91 +1,46 @@
 // Modules are defined in lib.rs
 
 use api::handlers;
-use api::middleware;
-use axum::{
-    http::StatusCode,
-    routing::{get, post},
-    Router,
-};
-use tower_http::cors::CorsLayer;
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
-    // Initialize tracing
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "api=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
-
     dotenv::dotenv().ok();
 
+    // Initialize tracing
+    api::telemetry::init("api=debug,tower_http=debug");
+
     let config = api::config::Config::from_env()?;
     let bind_address = config.bind_address.clone();
-    let pool = api::db::create_pool(&config.database_url).await?;
+    let pool = api::db::create_pool(&config).await?;
+    let read_replica = config
+        .database_read_url
+        .as_deref()
+        .map(|url| api::db::ReadReplica::connect(url, &config))
+        .transpose()?;
 
     // Run migrations
-    sqlx::migrate!("./migrations").run(&pool).await?;
+    api::db::migrate(&config).await?;
 
     // Initialize Redis connection
     let redis_client = redis::Client::open(config.redis_url.as_str())?;
 
+    let storage = api::services::storage::StorageService::create_backend(&config.storage_type, &config)?;
+
     // Build application state
     let app_state = handlers::AppState {
         db: pool,
+        db_read: read_replica,
         redis: redis_client,
         config: std::sync::Arc::new(config),
+        storage,
     };
 
-    // Build router
-    let app = Router::new()
-        .route("/health", get(health_check))
-        .route("/api/auth/request-otp", post(handlers::auth::request_otp))
-        .route("/api/auth/verify-otp", post(handlers::auth::verify_otp))
-        .route(
-            "/api/tills/register",
-            post(handlers::tills::register_till),
-        )
-        .route("/api/tills/verify", post(handlers::tills::verify_till))
-        .route("/api/tills", get(handlers::tills::list_tills))
-        .route("/api/proofs/generate", post(handlers::proofs::generate_proof))
-        .route("/api/data/upload", post(handlers::data::upload_data))
-        .route(
-            "/api/proofs/status/:session_id",
-            get(handlers::proofs::get_proof_status),
-        )
-        .route(
-            "/api/proofs/result/:session_id",
-            get(handlers::proofs::get_proof_result),
-        )
-        .route("/api/proofs", get(handlers::proofs::list_proofs))
-        .route("/api/lender/verify", post(handlers::lender::verify_proof))
-        .route(
-            "/api/lender/bulk-verify",
-            get(handlers::lender::bulk_verify),
-        )
-        .route("/verify/:code", get(handlers::verification::verify_code))
-        .layer(
-            axum::middleware::from_fn_with_state(
-                app_state.clone(),
-                api::middleware::auth::auth_middleware,
-            ),
-        )
-        .layer(CorsLayer::permissive())
-        .with_state(app_state);
+    let app = api::routes::build_router(app_state);
 
     let listener = tokio::net::TcpListener::bind(&bind_address).await?;
     tracing::info!("Server listening on {}", bind_address);
 
-    axum::serve(listener, app).await?;
+    axum::serve(listener, app.into_make_service_with_connect_info::<std::net::SocketAddr>()).await?;
 
     Ok(())
 }
-
-async fn health_check() -> StatusCode {
-    StatusCode::OK
-}
-
@@ -0,0 +1,36 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+
+use crate::error::AppError;
+use crate::handlers::AppState;
+use crate::utils::constant_time_eq;
+
+pub const ADMIN_KEY_HEADER: &str = "x-admin-key";
+
+/// Extractor guarding operator-only endpoints with the `ADMIN_API_KEY`.
+/// Admin routes are disabled entirely when no key is configured.
+pub struct AdminAuth;
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for AdminAuth {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let expected = state
+            .config
+            .admin_api_key
+            .as_deref()
+            .ok_or_else(|| AppError::NotFound("Not found".to_string()))?;
+
+        let provided = parts
+            .headers
+            .get(ADMIN_KEY_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .ok_or_else(|| AppError::Auth("Missing admin key".to_string()))?;
+
+        if !constant_time_eq(provided.as_bytes(), expected.as_bytes()) {
+            return Err(AppError::Auth("Invalid admin key".to_string()));
+        }
+
+        Ok(AdminAuth)
+    }
+}
@@ -1,12 +1,16 @@
 use axum::{
     extract::{Request, State},
-    http::StatusCode,
+    http::{Method, StatusCode},
     middleware::Next,
     response::Response,
 };
+use uuid::Uuid;
 
+use crate::db::repos::{AccessTokenRepo, UserSessionRepo};
 use crate::handlers::AppState;
-use crate::utils::verify_jwt;
+use crate::middleware::lender::API_KEY_HEADER;
+use crate::models::PhoneRole;
+use crate::utils::{hash_api_key, verify_jwt, Claims, ACCESS_TOKEN_PREFIX};
 
 pub async fn auth_middleware(
     State(state): State<AppState>,
@@ -17,11 +21,36 @@ pub async fn auth_middleware(
     let path = request.uri().path();
     let public_paths = [
         "/health",
+        "/api/status",
+        "/.well-known/",
+        "/api/stats/",
+        "/api/meta/",
         "/api/auth/request-otp",
         "/api/auth/verify-otp",
+        // The USSD gateway authenticates with the callback token instead
+        "/api/ussd/",
+        // Daraja's STK push callback carries its own token
+        "/api/payments/callback",
+        // Expiry reminder links carry their own token
+        "/api/proofs/regenerate",
+        // The verification page and its embeddable badge
+        "/verify/",
     ];
 
-    if public_paths.iter().any(|p| path.starts_with(p)) {
+    // Lender and admin routes authenticate with their own API keys
+    let key_authenticated_paths = ["/api/lender/", "/api/webhooks", "/api/admin/"];
+
+    // Disputes are raised against a till by lenders or operators, not its owner
+    let is_dispute_path = path.starts_with("/api/tills/") && path.ends_with("/disputes");
+
+    // Lenders read their own billing usage with their API key
+    let is_lender_billing = path.starts_with("/api/billing/") && request.headers().contains_key(API_KEY_HEADER);
+
+    if public_paths.iter().any(|p| path.starts_with(p))
+        || key_authenticated_paths.iter().any(|p| path.starts_with(p))
+        || is_dispute_path
+        || is_lender_billing
+    {
         return Ok(next.run(request).await);
     }
 
@@ -37,13 +66,81 @@ pub async fn auth_middleware(
 
     let token = &auth_header[7..];
 
-    match verify_jwt(token, &state.config.jwt_secret) {
-        Ok(claims) => {
-            // Store claims in request extensions for handlers to use
-            request.extensions_mut().insert(claims);
-            Ok(next.run(request).await)
-        }
-        Err(_) => Err(StatusCode::UNAUTHORIZED),
+    // Merchant access tokens act for their owner, but only on the routes
+    // their scopes cover
+    if token.starts_with(ACCESS_TOKEN_PREFIX) {
+        let required = required_scope(request.method(), path).ok_or(StatusCode::FORBIDDEN)?;
+        let claims = access_token_claims(&state, token, required).await?;
+        request.extensions_mut().insert(claims);
+        return Ok(next.run(request).await);
     }
+
+    let claims = verify_jwt(token, &state.config.jwt_keys).map_err(|_| StatusCode::UNAUTHORIZED)?;
+    if !session_active(&state, &claims).await? {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    // Store claims in request extensions for handlers to use
+    request.extensions_mut().insert(claims);
+    Ok(next.run(request).await)
+}
+
+/// Whether a signed-in token's session hasn't been signed out.
+async fn session_active(state: &AppState, claims: &Claims) -> Result<bool, StatusCode> {
+    let ids = claims
+        .session_id
+        .as_deref()
+        .and_then(|id| Uuid::parse_str(id).ok())
+        .zip(Uuid::parse_str(&claims.user_id).ok());
+    let Some((session_id, user_id)) = ids else {
+        return Ok(false);
+    };
+
+    UserSessionRepo::touch(&state.db, session_id, user_id).await.map_err(|e| {
+        tracing::error!("Failed to check session: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    })
 }
 
+
+/// The scope a merchant access token needs for a route, or None for routes
+/// only a signed-in owner can use.
+fn required_scope(method: &Method, path: &str) -> Option<&'static str> {
+    if path.starts_with("/api/data/") {
+        Some("data:write")
+    } else if method == Method::GET && (path == "/api/proofs" || path.starts_with("/api/proofs/")) {
+        Some("proofs:read")
+    } else {
+        None
+    }
+}
+
+/// Claims for the merchant behind a live access token holding `scope`.
+async fn access_token_claims(state: &AppState, token: &str, scope: &str) -> Result<Claims, StatusCode> {
+    let internal = |e: sqlx::Error| {
+        tracing::error!("Failed to check access token: {}", e);
+        StatusCode::INTERNAL_SERVER_ERROR
+    };
+
+    let access_token = AccessTokenRepo::authenticate(&state.db, &hash_api_key(token))
+        .await
+        .map_err(internal)?
+        .ok_or(StatusCode::UNAUTHORIZED)?;
+    if !access_token.scopes.iter().any(|s| s == scope) {
+        return Err(StatusCode::FORBIDDEN);
+    }
+
+    let phone_number: String = sqlx::query_scalar("SELECT phone_number FROM users WHERE id = $1")
+        .bind(access_token.user_id)
+        .fetch_one(&state.db)
+        .await
+        .map_err(internal)?;
+
+    Ok(Claims {
+        user_id: access_token.user_id.to_string(),
+        phone_number,
+        role: PhoneRole::Owner,
+        session_id: None,
+        exp: access_token.expires_at.map_or(usize::MAX, |t| t.timestamp() as usize),
+    })
+}
@@ -0,0 +1,39 @@
+use axum::{extract::FromRequestParts, http::request::Parts};
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::handlers::AppState;
+use crate::services::api_keys::ApiKeyService;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// An authenticated lender, resolved from the `X-Api-Key` header.
+#[derive(Debug, Clone)]
+pub struct LenderAuth {
+    pub lender_id: Uuid,
+    pub key_id: Uuid,
+    pub sandbox: bool,
+}
+
+#[axum::async_trait]
+impl FromRequestParts<AppState> for LenderAuth {
+    type Rejection = AppError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &AppState) -> Result<Self, Self::Rejection> {
+        let key = parts
+            .headers
+            .get(API_KEY_HEADER)
+            .and_then(|h| h.to_str().ok())
+            .ok_or_else(|| AppError::Auth("Missing API key".to_string()))?;
+
+        let key = ApiKeyService::authenticate(&state.db, key)
+            .await?
+            .ok_or_else(|| AppError::Auth("Invalid API key".to_string()))?;
+
+        Ok(LenderAuth {
+            lender_id: key.lender_id,
+            key_id: key.id,
+            sandbox: key.sandbox,
+        })
+    }
+}
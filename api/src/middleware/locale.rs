@@ -0,0 +1,25 @@
+use axum::{extract::Request, http::header, middleware::Next, response::Response};
+
+use crate::i18n::Locale;
+
+tokio::task_local! {
+    static LOCALE: Locale;
+}
+
+/// Locale negotiated for the request currently being handled.
+pub fn current_locale() -> Locale {
+    LOCALE.try_with(|locale| *locale).unwrap_or_default()
+}
+
+/// Locale explicitly requested by the client, if any.
+pub fn requested_locale(headers: &axum::http::HeaderMap) -> Option<Locale> {
+    headers
+        .get(header::ACCEPT_LANGUAGE)
+        .and_then(|h| h.to_str().ok())
+        .and_then(Locale::from_accept_language)
+}
+
+pub async fn locale_middleware(request: Request, next: Next) -> Response {
+    let locale = requested_locale(request.headers()).unwrap_or_default();
+    LOCALE.scope(locale, next.run(request)).await
+}
@@ -1,6 +1,6 @@
+pub mod admin;
 pub mod auth;
-
-
-
-
-
+pub mod client_ip;
+pub mod lender;
+pub mod locale;
+pub mod request_id;
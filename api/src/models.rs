@@ -11,7 +11,7 @@ pub struct User {
     pub updated_at: DateTime<Utc>,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
 pub struct BusinessTill {
     pub id: Uuid,
     pub user_id: Uuid,
@@ -20,6 +20,10 @@ pub struct BusinessTill {
     pub is_verified: bool,
     pub api_connected: bool,
     pub verification_method: Option<String>,
+    /// As registered with M-Pesa, when Daraja could tell us
+    pub organization_name: Option<String>,
+    /// IANA timezone the till's statements are written in
+    pub timezone: String,
     pub created_at: DateTime<Utc>,
     pub updated_at: DateTime<Utc>,
 }
@@ -36,13 +40,114 @@ pub struct Transaction {
     pub id: Uuid,
     pub till_id: Uuid,
     pub timestamp: DateTime<Utc>,
-    pub amount: i64, // In cents
+    pub amount: i64, // In the currency's minor units; negative when paid out
+    /// Description as the statement gave it
     pub transaction_type: String,
+    /// `proof_core::TransactionKind` name derived from `transaction_type`
+    pub kind: String,
+    /// `proof_core::Direction` name derived from `kind` and the amount's sign
+    pub direction: String,
     pub reference: String, // Hashed
     pub raw_data: Option<serde_json::Value>,
     pub created_at: DateTime<Utc>,
+    pub source: String,
+    /// PayBill account number the payment was made against
+    pub account_number: Option<String>,
+    pub currency: String,
+    /// `proof_core::Provenance` name: how the row reached the platform
+    pub provenance: String,
+}
+
+/// Currency assumed when an import doesn't name one.
+pub const DEFAULT_CURRENCY: &str = "KES";
+
+/// Timezone statement times are read in when neither the upload nor the
+/// till names one.
+pub const DEFAULT_TIMEZONE: &str = "Africa/Nairobi";
+
+/// A supported currency and the scale its amounts are stored in.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Currency {
+    pub code: String,
+    /// Minor units per major unit as a power of ten (2 for cents)
+    pub minor_unit_exponent: i16,
+    /// Default monthly major-unit volume at which each band above VeryLow
+    /// starts, used when a proof names no scoring policy
+    pub volume_thresholds: Vec<i64>,
+}
+
+/// Volume thresholds chosen per lender or market. Immutable once created.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ScoringPolicy {
+    pub id: Uuid,
+    pub name: String,
+    pub currency: String,
+    pub lender_id: Option<Uuid>,
+    pub volume_thresholds: Vec<i64>,
+    /// Multiple of the median day's volume that days are capped at
+    pub outlier_cap: Option<i32>,
+    /// Hex hash committed in the journal of proofs scored under this policy
+    pub policy_hash: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A lender's requirements for the proofs it accepts. Immutable once
+/// created.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProofTemplate {
+    pub id: Uuid,
+    pub lender_id: Uuid,
+    pub name: String,
+    pub min_period_days: i32,
+    /// Entries of `DISCLOSURE_FIELDS` the proof must reveal
+    pub required_disclosures: Vec<String>,
+    pub scoring_policy_id: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Raw statement descriptions starting with `pattern` are of `kind`.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TransactionTypeMapping {
+    pub pattern: String,
+    pub kind: String,
+    pub updated_at: DateTime<Utc>,
 }
 
+/// What a phone signed in to an account may do. Secondary phones are
+/// uploaders: they can add data and generate proofs but not manage the
+/// account, its tills, or its proofs.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PhoneRole {
+    #[default]
+    Owner,
+    Uploader,
+}
+
+impl PhoneRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PhoneRole::Owner => "owner",
+            PhoneRole::Uploader => "uploader",
+        }
+    }
+}
+
+/// Statement sources a till can hold transactions from.
+pub const TRANSACTION_SOURCES: [&str; 2] = ["mpesa", "bank"];
+
+/// What a merchant access token can be allowed to do: push statement data,
+/// and read the merchant's proofs.
+pub const TOKEN_SCOPES: [&str; 2] = ["data:write", "proofs:read"];
+
+/// What the guest is given: every transaction, or daily totals the host
+/// computed (far fewer cycles, coarser attestation).
+pub const INPUT_MODES: [&str; 2] = ["transactions", "daily_totals"];
+
+/// What a proof template can require a proof to reveal. Threshold proofs
+/// reveal neither the score nor the metrics.
+pub const DISCLOSURE_FIELDS: [&str; 3] = ["credit_score", "metrics", "score_breakdown"];
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ProofSession {
     pub id: Uuid,
@@ -69,6 +174,22 @@ pub enum ProofStatus {
     Failed,
 }
 
+/// A step in a session's timeline, in the order a successful run reaches
+/// them.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, sqlx::Type)]
+#[sqlx(type_name = "proof_session_stage", rename_all = "snake_case")]
+#[serde(rename_all = "snake_case")]
+pub enum SessionStage {
+    Queued,
+    PickedUp,
+    /// Loading the statement and scoring it natively
+    Executing,
+    Proving,
+    Verifying,
+    Stored,
+    Failed,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct BusinessMetrics {
     pub monthly_volume_range: VolumeRange,
@@ -76,6 +197,131 @@ pub struct BusinessMetrics {
     pub growth_trend: GrowthTrend,
     pub active_days_percentage: u8,
     pub customer_diversity_score: u8,
+    pub concentration_risk: ConcentrationRisk,
+    pub max_weekly_drawdown_percentage: u8,
+    pub volatility_30d_percentage: u8,
+}
+
+/// Points contributed by each scoring component, as committed by the guest.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub volume_points: u32,
+    pub consistency_points: u32,
+    pub activity_points: u32,
+    pub growth_points: u32,
+    pub diversity_points: u32,
+    /// Absent from breakdowns recorded before concentration was scored
+    #[serde(default)]
+    pub concentration_penalty: u32,
+    #[serde(default)]
+    pub volatility_penalty: u32,
+    /// Present when the policy capped outlier days
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outlier_adjustment: Option<OutlierAdjustment>,
+}
+
+/// How capping outlier days changed the volume a score was computed from.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OutlierAdjustment {
+    pub cap_multiple: u32,
+    pub days_capped: u32,
+    /// Share of raw volume removed: "Negligible", "Low", "Moderate", "High"
+    /// or "Dominant"
+    pub volume_removed: String,
+    /// The band the score would have used without capping
+    pub raw_monthly_volume_range: VolumeRange,
+}
+
+/// Monthly volume band of one transaction source in a composite proof.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceVolume {
+    pub tag: String,
+    pub monthly_volume_range: VolumeRange,
+}
+
+/// Outcome of a threshold proof: only whether the score reached the
+/// threshold is committed, never the score itself.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ThresholdResult {
+    pub score_at_least: u32,
+    pub meets_threshold: bool,
+}
+
+/// The guest build and prover behind a receipt. Absent on proofs generated
+/// before it was recorded.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProverInfo {
+    pub image_id: String,
+    pub guest_version: Option<String>,
+    /// "bonsai", or "local-cpu", "local-cuda" or "local-metal"
+    pub prover_backend: Option<String>,
+}
+
+impl ProverInfo {
+    pub fn from_columns(
+        image_id: Option<String>,
+        guest_version: Option<String>,
+        prover_backend: Option<String>,
+    ) -> Option<Self> {
+        Some(Self {
+            image_id: image_id?,
+            guest_version,
+            prover_backend,
+        })
+    }
+}
+
+/// How long the till had gone without a payment when a proof was generated,
+/// and how much of its score that cost. Absent on proofs generated before
+/// recency was committed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Staleness {
+    /// `days_since_last_transaction` reached `proof_core::STALE_AFTER_DAYS`
+    pub stale: bool,
+    pub days_since_last_transaction: i32,
+    /// Share of the undecayed score kept in `credit_score`
+    pub recency_factor_percentage: i16,
+}
+
+impl Staleness {
+    pub fn from_columns(
+        days_since_last_transaction: Option<i32>,
+        recency_factor_percentage: Option<i16>,
+    ) -> Option<Self> {
+        let days_since_last_transaction = days_since_last_transaction?;
+        Some(Self {
+            stale: days_since_last_transaction >= proof_core::STALE_AFTER_DAYS as i32,
+            days_since_last_transaction,
+            recency_factor_percentage: recency_factor_percentage?,
+        })
+    }
+}
+
+/// Owner-chosen disclosure rules for a proof's public verification page.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct DisclosurePolicy {
+    #[serde(default)]
+    pub score_breakdown: bool,
+    /// Lenders see the score, metrics and threshold outcome only once the
+    /// owner has approved their request for them
+    #[serde(default)]
+    pub lender_consent: bool,
+}
+
+/// A lender's request to see a proof whose owner requires consent.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ProofConsent {
+    pub id: Uuid,
+    pub session_id: Uuid,
+    pub verification_code: String,
+    pub lender_id: Uuid,
+    pub lender_name: String,
+    /// "pending", "approved", "declined", "revoked", or "expired" once a
+    /// pending request or an approval has lapsed
+    pub status: String,
+    pub requested_at: DateTime<Utc>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub expires_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -87,6 +333,14 @@ pub enum VolumeRange {
     VeryHigh,
 }
 
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum ConcentrationRisk {
+    Low,
+    Moderate,
+    High,
+    Unknown,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum GrowthTrend {
     Declining,
@@ -99,3 +353,52 @@ pub enum GrowthTrend {
 
 
 
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct BusinessProfile {
+    pub user_id: Uuid,
+    pub business_name: Option<String>,
+    pub sector: Option<String>,
+    pub county: Option<String>,
+    pub registration_number: Option<String>,
+    pub public_fields: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl BusinessProfile {
+    pub const FIELDS: [&'static str; 4] = ["business_name", "sector", "county", "registration_number"];
+
+    /// The subset of the profile the owner chose to show publicly.
+    pub fn public_view(&self) -> serde_json::Value {
+        let mut view = serde_json::Map::new();
+        for field in &self.public_fields {
+            let value = match field.as_str() {
+                "business_name" => &self.business_name,
+                "sector" => &self.sector,
+                "county" => &self.county,
+                "registration_number" => &self.registration_number,
+                _ => continue,
+            };
+            if let Some(value) = value {
+                view.insert(field.clone(), serde_json::Value::String(value.clone()));
+            }
+        }
+        serde_json::Value::Object(view)
+    }
+}
+
+/// A token a merchant issued for a system acting on its behalf. The token
+/// itself is shown once, at issue.
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct MerchantAccessToken {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub token_prefix: String,
+    pub scopes: Vec<String>,
+    pub created_at: DateTime<Utc>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,402 @@
+use axum::{
+    extract::DefaultBodyLimit,
+    http::{HeaderName, HeaderValue, Method, StatusCode},
+    routing::{delete, get, post, put},
+    Router,
+};
+use tower_http::{
+    cors::{AllowHeaders, AllowOrigin, CorsLayer},
+    timeout::TimeoutLayer,
+    trace::TraceLayer,
+};
+
+use crate::config::Config;
+use crate::handlers::{self, AppState};
+
+pub fn build_router(app_state: AppState) -> Router {
+    let demo_mode = app_state.config.demo_mode;
+    let request_timeout = std::time::Duration::from_secs(app_state.config.request_timeout_secs);
+    let upload_timeout = std::time::Duration::from_secs(app_state.config.upload_timeout_secs);
+    let cors = cors_layer(&app_state.config);
+    let max_body_bytes = app_state.config.max_body_bytes;
+    // Leaves room for the form's other fields and its boundaries
+    let max_upload_body_bytes = app_state.config.max_upload_bytes + 64 * 1024;
+
+    let mut app = Router::new()
+        .route("/health", get(health_check))
+        .route("/api/status", get(handlers::status::get_status))
+        .route("/.well-known/jwks.json", get(handlers::meta::get_jwks))
+        .route("/api/stats/market", get(handlers::stats::get_market_stats))
+        .route(
+            "/api/meta/journal-schema/:version",
+            get(handlers::meta::get_journal_schema),
+        )
+        .route("/api/auth/request-otp", post(handlers::auth::request_otp))
+        .route("/api/auth/verify-otp", post(handlers::auth::verify_otp))
+        .route("/api/auth/step-up", post(handlers::auth::step_up))
+        .route(
+            "/api/tills/register",
+            post(handlers::tills::register_till),
+        )
+        .route("/api/tills/verify", post(handlers::tills::verify_till))
+        .route("/api/tills", get(handlers::tills::list_tills))
+        .route(
+            "/api/tills/:till_id/summary",
+            get(handlers::tills::get_till_summary),
+        )
+        .route(
+            "/api/tills/:till_id/stats",
+            get(handlers::tills::get_till_stats),
+        )
+        .route(
+            "/api/tills/:till_id/transfer",
+            post(handlers::tills::transfer_till),
+        )
+        .route(
+            "/api/tills/:till_id/reviews",
+            get(handlers::reviews::list_reviews),
+        )
+        .route(
+            "/api/tills/:till_id/reviews/:review_id/confirm",
+            post(handlers::reviews::confirm_review),
+        )
+        .route(
+            "/api/tills/:till_id/reviews/:review_id/reject",
+            post(handlers::reviews::reject_review),
+        )
+        .route(
+            "/api/tills/:till_id/disputes",
+            post(handlers::disputes::submit_dispute),
+        )
+        .route("/api/proofs/generate", post(handlers::proofs::generate_proof))
+        .route("/api/proofs/preview", post(handlers::proofs::preview_proof))
+        .route(
+            "/api/proofs/regenerate",
+            get(handlers::proofs::confirm_regeneration).post(handlers::proofs::regenerate_proof),
+        )
+        .route("/api/data/upload-sms", post(handlers::data::upload_sms))
+        .route("/api/data/uploads", post(handlers::uploads::create_upload))
+        .route("/api/data/uploads/:upload_id", get(handlers::uploads::get_upload))
+        // The router reads ":batch" as a parameter after the literal
+        // "transactions", which still matches the documented path
+        .route(
+            "/api/data/transactions:batch",
+            post(handlers::ingest::import_batch).layer(DefaultBodyLimit::max(handlers::ingest::MAX_BATCH_BYTES)),
+        )
+        .route("/api/data/import-jobs/:job_id", get(handlers::ingest::get_import_job))
+        .route(
+            "/api/proofs/status/:session_id",
+            get(handlers::proofs::get_proof_status),
+        )
+        .route(
+            "/api/proofs/result/:session_id",
+            get(handlers::proofs::get_proof_result),
+        )
+        .route(
+            "/api/proofs/:session_id/events",
+            get(handlers::proofs::get_proof_events),
+        )
+        .route(
+            "/api/proofs/:session_id/breakdown",
+            get(handlers::proofs::get_proof_breakdown),
+        )
+        .route(
+            "/api/proofs/:session_id/receipt",
+            get(handlers::proofs::download_receipt),
+        )
+        .route(
+            "/api/proofs/:session_id/short-code",
+            post(handlers::short_codes::create_short_code),
+        )
+        .route(
+            "/api/proofs/:session_id/handoff",
+            post(handlers::handoffs::create_handoff),
+        )
+        .route("/api/proofs", get(handlers::proofs::list_proofs))
+        .route("/api/billing/usage", get(handlers::billing::get_usage))
+        .route("/api/payments/initiate", post(handlers::payments::initiate_payment))
+        .route("/api/payments/callback", post(handlers::payments::stk_callback))
+        .route("/api/payments/:payment_id", get(handlers::payments::get_payment))
+        .route("/api/lender/verify", post(handlers::lender::verify_proof))
+        .route(
+            "/api/lender/bulk-verify",
+            get(handlers::lender::bulk_verify),
+        )
+        .route(
+            "/api/lender/verifications/export",
+            get(handlers::lender::export_verifications),
+        )
+        .route(
+            "/api/lender/referral-codes",
+            get(handlers::referrals::lender_referral_codes),
+        )
+        .route("/api/lender/keys", get(handlers::api_keys::list_own_keys))
+        .route("/api/lender/keys/rotate", post(handlers::api_keys::rotate_own_key))
+        .route("/api/lender/keys/:key_id", delete(handlers::api_keys::revoke_own_key))
+        .route(
+            "/api/lender/proofs/:code/metadata",
+            get(handlers::lender::proof_metadata),
+        )
+        .route(
+            "/api/lender/proofs/:code/consent",
+            get(handlers::consents::get_consent).post(handlers::consents::request_consent),
+        )
+        .route(
+            "/api/lender/settings",
+            get(handlers::lender::get_settings).put(handlers::lender::update_settings),
+        )
+        .route("/api/lender/simulate", post(handlers::simulations::simulate))
+        .route(
+            "/api/lender/sandbox/proofs",
+            get(handlers::lender::list_sandbox_proofs),
+        )
+        .route(
+            "/api/lender/handoffs/redeem",
+            post(handlers::handoffs::redeem_handoff),
+        )
+        .route(
+            "/api/lender/templates",
+            get(handlers::templates::list_templates).post(handlers::templates::create_template),
+        )
+        .route(
+            "/api/templates/:template_id",
+            get(handlers::templates::get_template),
+        )
+        .route(
+            "/api/webhooks",
+            get(handlers::webhooks::list_webhooks).post(handlers::webhooks::create_webhook),
+        )
+        .route(
+            "/api/webhooks/:webhook_id/test",
+            post(handlers::webhooks::test_webhook),
+        )
+        .route("/api/admin/lenders", post(handlers::admin::create_lender))
+        .route(
+            "/api/admin/lenders/:lender_id/keys",
+            get(handlers::api_keys::list_lender_keys).post(handlers::admin::issue_lender_key),
+        )
+        .route(
+            "/api/admin/lenders/:lender_id/keys/:key_id",
+            delete(handlers::api_keys::revoke_lender_key),
+        )
+        .route(
+            "/api/admin/lenders/:lender_id/keys/:key_id/rotate",
+            post(handlers::api_keys::rotate_lender_key),
+        )
+        .route(
+            "/api/admin/consistency",
+            get(handlers::admin::consistency_report),
+        )
+        .route(
+            "/api/admin/reports/monthly",
+            post(handlers::admin::send_monthly_reports),
+        )
+        .route(
+            "/api/admin/image-ids",
+            get(handlers::admin::list_image_ids).post(handlers::admin::register_image_id),
+        )
+        .route("/api/admin/scheduled-jobs", get(handlers::admin::list_scheduled_jobs))
+        .route("/api/admin/feature-flags", get(handlers::feature_flags::list_feature_flags))
+        .route(
+            "/api/admin/feature-flags/:key",
+            put(handlers::feature_flags::put_feature_flag).delete(handlers::feature_flags::delete_feature_flag),
+        )
+        .route(
+            "/api/admin/referral-codes",
+            get(handlers::referrals::list_referral_codes).post(handlers::referrals::create_referral_code),
+        )
+        .route(
+            "/api/admin/referral-codes/:code_id",
+            delete(handlers::referrals::deactivate_referral_code),
+        )
+        .route(
+            "/api/admin/scoring-policies",
+            get(handlers::admin::list_scoring_policies).post(handlers::admin::create_scoring_policy),
+        )
+        .route(
+            "/api/admin/scoring-experiments",
+            get(handlers::scoring_experiments::list_scoring_experiments)
+                .post(handlers::scoring_experiments::create_scoring_experiment),
+        )
+        .route(
+            "/api/admin/scoring-experiments/:experiment_id/end",
+            post(handlers::scoring_experiments::end_scoring_experiment),
+        )
+        .route(
+            "/api/admin/scoring-experiments/:experiment_id/report",
+            get(handlers::scoring_experiments::scoring_experiment_report),
+        )
+        .route(
+            "/api/admin/transaction-types",
+            get(handlers::admin::list_transaction_types).put(handlers::admin::put_transaction_type),
+        )
+        .route(
+            "/api/admin/transaction-types/:pattern",
+            delete(handlers::admin::delete_transaction_type),
+        )
+        .route("/api/admin/proofs/search", get(handlers::admin::search_proofs))
+        .route(
+            "/api/admin/proofs/:session_id/events",
+            get(handlers::admin::get_proof_events),
+        )
+        .route("/api/admin/reviews", get(handlers::reviews::list_pending_reviews))
+        .route(
+            "/api/admin/reviews/:review_id/confirm",
+            post(handlers::reviews::admin_confirm_review),
+        )
+        .route(
+            "/api/admin/reviews/:review_id/reject",
+            post(handlers::reviews::admin_reject_review),
+        )
+        .route(
+            "/api/admin/tills/contested",
+            get(handlers::admin::list_contested_tills),
+        )
+        .route(
+            "/api/admin/tills/:till_id/award",
+            post(handlers::admin::award_till),
+        )
+        .route(
+            "/api/users/me/business",
+            get(handlers::users::get_business_profile).put(handlers::users::update_business_profile),
+        )
+        .route(
+            "/api/users/me/phones",
+            get(handlers::users::list_phones).post(handlers::users::link_phone),
+        )
+        .route(
+            "/api/users/me/phones/:phone_id",
+            delete(handlers::users::remove_phone),
+        )
+        .route(
+            "/api/users/me/notifications",
+            get(handlers::users::get_notification_preferences).put(handlers::users::update_notification_preferences),
+        )
+        .route(
+            "/api/users/me/notifications/verify-email",
+            post(handlers::users::verify_notification_email),
+        )
+        .route(
+            "/api/users/me/devices",
+            get(handlers::users::list_devices).post(handlers::users::register_device),
+        )
+        .route(
+            "/api/users/me/devices/:device_id",
+            delete(handlers::users::remove_device),
+        )
+        .route(
+            "/api/users/me/tokens",
+            get(handlers::access_tokens::list_access_tokens).post(handlers::access_tokens::create_access_token),
+        )
+        .route(
+            "/api/users/me/tokens/:token_id",
+            delete(handlers::access_tokens::revoke_access_token),
+        )
+        .route("/api/users/me/sessions", get(handlers::user_sessions::list_sessions))
+        .route("/api/users/me/audit-log", get(handlers::user_sessions::list_audit_log))
+        .route("/api/users/me/features", get(handlers::feature_flags::my_features))
+        .route(
+            "/api/users/me/sessions/:session_id",
+            delete(handlers::user_sessions::revoke_session),
+        )
+        .route("/api/consents", get(handlers::consents::list_consents))
+        .route(
+            "/api/consents/:consent_id/approve",
+            post(handlers::consents::approve_consent),
+        )
+        .route(
+            "/api/consents/:consent_id/decline",
+            post(handlers::consents::decline_consent),
+        )
+        .route(
+            "/api/consents/:consent_id",
+            delete(handlers::consents::revoke_consent),
+        )
+        .route("/api/ussd/consents", post(handlers::consents::ussd_consents))
+        .route("/verify/:code", get(handlers::verification::verify_code))
+        .route("/verify/:code/embed", get(handlers::verification::embed_badge));
+
+    if demo_mode {
+        tracing::warn!("Demo mode enabled: synthetic transaction endpoints are exposed");
+        app = app.route(
+            "/api/dev/simulate-transactions",
+            post(handlers::dev::simulate_transactions),
+        );
+    }
+
+    // Large uploads and streams read their bodies for longer than any other
+    // request is allowed to take
+    let uploads = Router::new()
+        .route(
+            "/api/data/upload",
+            post(handlers::data::upload_data).layer(DefaultBodyLimit::max(max_upload_body_bytes)),
+        )
+        .route(
+            "/api/data/upload-image",
+            post(handlers::images::upload_image).layer(DefaultBodyLimit::max(max_upload_body_bytes)),
+        )
+        .route(
+            "/api/data/uploads/:upload_id/chunks/:n",
+            put(handlers::uploads::put_chunk).layer(DefaultBodyLimit::max(handlers::uploads::MAX_CHUNK_BYTES)),
+        )
+        .route(
+            "/api/data/uploads/:upload_id/complete",
+            post(handlers::uploads::complete_upload),
+        )
+        .route("/api/data/stream", post(handlers::ingest::stream_transactions));
+
+    let app = app
+        .layer(TimeoutLayer::new(request_timeout))
+        .merge(uploads.layer(TimeoutLayer::new(upload_timeout)));
+
+    app.layer(
+        axum::middleware::from_fn_with_state(
+            app_state.clone(),
+            crate::middleware::auth::auth_middleware,
+        ),
+    )
+    .layer(axum::middleware::from_fn(
+        crate::middleware::locale::locale_middleware,
+    ))
+    .layer(DefaultBodyLimit::max(max_body_bytes))
+    .layer(TraceLayer::new_for_http())
+    .layer(axum::middleware::from_fn(
+        crate::middleware::request_id::request_id_middleware,
+    ))
+    .layer(cors)
+    .with_state(app_state)
+}
+
+/// CORS policy from `Config`. Entries that aren't valid header values are
+/// skipped with a warning rather than failing startup.
+fn cors_layer(config: &Config) -> CorsLayer {
+    let origins = if config.cors_allowed_origins.iter().any(|o| o == "*") {
+        AllowOrigin::any()
+    } else {
+        AllowOrigin::list(config.cors_allowed_origins.iter().filter_map(|origin| {
+            HeaderValue::from_str(origin)
+                .map_err(|_| tracing::warn!("Ignoring invalid CORS origin {:?}", origin))
+                .ok()
+        }))
+    };
+
+    let headers = if config.cors_allowed_headers.iter().any(|h| h == "*") {
+        AllowHeaders::any()
+    } else {
+        AllowHeaders::list(config.cors_allowed_headers.iter().filter_map(|header| {
+            HeaderName::from_bytes(header.as_bytes())
+                .map_err(|_| tracing::warn!("Ignoring invalid CORS header {:?}", header))
+                .ok()
+        }))
+    };
+
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_headers(headers)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_credentials(config.cors_allow_credentials)
+}
+
+async fn health_check() -> StatusCode {
+    StatusCode::OK
+}
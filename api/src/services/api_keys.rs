@@ -0,0 +1,150 @@
+//! Lender API keys. Only an Argon2id hash of each key is stored; the key is
+//! shown once, when it's issued.
+
+use std::collections::HashMap;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::repos::api_keys::{LenderApiKey, StoredApiKey};
+use crate::db::repos::ApiKeyRepo;
+use crate::utils::{
+    api_key_lookup_id, constant_time_eq, generate_api_key, hash_api_key, hash_lender_key, verify_lender_key,
+};
+
+/// Keys drawn before giving up on lookup id collisions.
+const ISSUE_ATTEMPTS: usize = 3;
+
+/// How long a key that passed Argon2 is let through on its SHA-256 alone.
+/// Its row is still read on every request, so revocation and expiry apply
+/// at once.
+const VERIFIED_TTL: Duration = Duration::from_secs(5 * 60);
+
+/// SHA-256 of the key each row was last verified with, and when.
+static VERIFIED: OnceLock<Mutex<HashMap<Uuid, ([u8; 32], Instant)>>> = OnceLock::new();
+
+fn verified() -> &'static Mutex<HashMap<Uuid, ([u8; 32], Instant)>> {
+    VERIFIED.get_or_init(Default::default)
+}
+
+/// Whether `digest` passed Argon2 against the row within the TTL.
+fn recently_verified(id: Uuid, digest: &[u8; 32]) -> bool {
+    let verified = verified().lock().expect("verified key cache poisoned");
+    verified
+        .get(&id)
+        .is_some_and(|(seen, at)| at.elapsed() < VERIFIED_TTL && constant_time_eq(seen, digest))
+}
+
+fn remember_verified(id: Uuid, digest: [u8; 32]) {
+    let mut verified = verified().lock().expect("verified key cache poisoned");
+    verified.retain(|_, (_, at)| at.elapsed() < VERIFIED_TTL);
+    verified.insert(id, (digest, Instant::now()));
+}
+
+/// A new key and the only copy of it the API hands out.
+pub struct IssuedKey {
+    pub key: LenderApiKey,
+    pub api_key: String,
+}
+
+pub struct ApiKeyService;
+
+impl ApiKeyService {
+    /// Issues a key for the lender, in the caller's transaction.
+    pub async fn issue(
+        tx: &mut sqlx::PgConnection,
+        lender_id: Uuid,
+        sandbox: bool,
+        rotated_from: Option<Uuid>,
+    ) -> anyhow::Result<IssuedKey> {
+        for _ in 0..ISSUE_ATTEMPTS {
+            let api_key = generate_api_key(sandbox);
+            let lookup_id = api_key_lookup_id(&api_key).expect("generated keys have a lookup id").to_string();
+            let key_prefix: String = api_key.chars().take(13).collect();
+            let secret_hash = {
+                let api_key = api_key.clone();
+                tokio::task::spawn_blocking(move || hash_lender_key(&api_key)).await??
+            };
+
+            let key = ApiKeyRepo::create(
+                &mut *tx,
+                lender_id,
+                &lookup_id,
+                &secret_hash,
+                &key_prefix,
+                sandbox,
+                rotated_from,
+            )
+            .await?;
+            if let Some(key) = key {
+                return Ok(IssuedKey { key, api_key });
+            }
+        }
+        anyhow::bail!("No unused API key lookup id after {} attempts", ISSUE_ATTEMPTS)
+    }
+
+    /// Replaces a usable key with a new one of the same kind. The old key
+    /// keeps working until `old_key_expires_at`, so integrations can switch
+    /// over; None if the lender has no such usable key.
+    pub async fn rotate(
+        db: &PgPool,
+        lender_id: Uuid,
+        key_id: Uuid,
+        old_key_expires_at: DateTime<Utc>,
+    ) -> anyhow::Result<Option<(IssuedKey, LenderApiKey)>> {
+        let mut tx = db.begin().await?;
+        let Some(old_key) = ApiKeyRepo::expire(&mut *tx, key_id, lender_id, old_key_expires_at).await? else {
+            return Ok(None);
+        };
+        let issued = Self::issue(&mut tx, lender_id, old_key.sandbox, Some(old_key.id)).await?;
+        tx.commit().await?;
+        Ok(Some((issued, old_key)))
+    }
+
+    /// The usable key a request presented, marked as just used. Argon2 runs
+    /// once per key every `VERIFIED_TTL` rather than on every request. Keys
+    /// issued before Argon2 are found by their SHA-256 hash and moved onto
+    /// Argon2 the first time they're presented.
+    pub async fn authenticate(db: &PgPool, api_key: &str) -> anyhow::Result<Option<StoredApiKey>> {
+        let Some(lookup_id) = api_key_lookup_id(api_key) else {
+            return Ok(None);
+        };
+
+        let stored = match ApiKeyRepo::find_usable(db, lookup_id).await? {
+            Some(stored) => {
+                let Some(secret_hash) = stored.secret_hash.clone() else {
+                    return Ok(None);
+                };
+                let digest: [u8; 32] = Sha256::digest(api_key.as_bytes()).into();
+                if !recently_verified(stored.id, &digest) {
+                    let api_key = api_key.to_string();
+                    if !tokio::task::spawn_blocking(move || verify_lender_key(&api_key, &secret_hash)).await? {
+                        return Ok(None);
+                    }
+                    remember_verified(stored.id, digest);
+                }
+                stored
+            }
+            None => {
+                let Some(stored) = ApiKeyRepo::find_legacy(db, &hash_api_key(api_key)).await? else {
+                    return Ok(None);
+                };
+                let secret_hash = {
+                    let api_key = api_key.to_string();
+                    tokio::task::spawn_blocking(move || hash_lender_key(&api_key)).await??
+                };
+                if !ApiKeyRepo::upgrade_legacy(db, stored.id, lookup_id, &secret_hash).await? {
+                    tracing::warn!("Lender key {} kept its SHA-256 hash: lookup id in use", stored.id);
+                }
+                stored
+            }
+        };
+
+        ApiKeyRepo::mark_used(db, stored.id).await?;
+        Ok(Some(stored))
+    }
+}
@@ -1,6 +1,9 @@
 use rand::Rng;
+use redis::AsyncCommands;
 use reqwest::Client;
 
+use crate::utils::hash_phone_number;
+
 pub struct AuthService;
 
 impl AuthService {
@@ -9,6 +12,25 @@ impl AuthService {
         format!("{:06}", rng.gen_range(100000..999999))
     }
 
+    /// Checks an OTP previously sent to `phone_number` without consuming it.
+    pub async fn check_otp(
+        redis_conn: &mut redis::aio::Connection,
+        phone_number: &str,
+        otp: &str,
+    ) -> anyhow::Result<bool> {
+        let stored_otp: Option<String> = redis_conn.get(Self::otp_key(phone_number)).await?;
+        Ok(stored_otp.as_deref() == Some(otp))
+    }
+
+    pub async fn consume_otp(redis_conn: &mut redis::aio::Connection, phone_number: &str) -> anyhow::Result<()> {
+        redis_conn.del(Self::otp_key(phone_number)).await?;
+        Ok(())
+    }
+
+    fn otp_key(phone_number: &str) -> String {
+        format!("otp:{}", hash_phone_number(phone_number))
+    }
+
     pub async fn send_sms(
         api_key: &str,
         username: &str,
@@ -38,8 +60,3 @@ impl AuthService {
         Ok(())
     }
 }
-
-
-
-
-
@@ -0,0 +1,417 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use chrono::{DateTime, Datelike, Utc};
+use serde::Serialize;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::services::proof::ProofInput;
+use crate::utils::phone_number_hashes;
+
+/// Balance breaks listed in one report; the check counts them all.
+const MAX_REPORTED_BREAKS: usize = 50;
+
+/// Proofs whose lowest-scoring upload is below this are "medium" trust.
+pub const MEDIUM_TRUST_SCORE: u8 = 80;
+/// And below this, "low".
+pub const LOW_TRUST_SCORE: u8 = 50;
+
+/// One row of an uploaded statement, in the order the file lists them.
+pub struct StatementLine<'a> {
+    /// 1-based spreadsheet row
+    pub row: usize,
+    pub timestamp: DateTime<Utc>,
+    /// Minor units, negative when money went out
+    pub amount: i64,
+    /// The receipt number as printed, before hashing
+    pub reference: &'a str,
+    /// The running balance after the row, when the export has that column
+    pub balance: Option<i64>,
+    /// Charge taken from the balance alongside the amount, when the export
+    /// has a separate column for it; 0 otherwise
+    pub fee: i64,
+}
+
+/// How likely an upload is to be an unedited export, from heuristics that
+/// a hand-edited or assembled statement tends to fail.
+#[derive(Serialize)]
+pub struct AuthenticityReport {
+    /// 0-100; uploads scoring low lower the trust tier of proofs over them
+    pub score: u8,
+    pub checks: Vec<AuthenticityCheck>,
+    /// First few rows whose balance doesn't follow from the row before
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub balance_breaks: Vec<BalanceBreak>,
+    /// Rows a check failed on, by 1-based row, with the check's name. They
+    /// are held for the owner to review rather than imported; a row failing
+    /// several checks is held for the most serious.
+    #[serde(skip)]
+    pub flagged: BTreeMap<usize, &'static str>,
+    /// The earliest and latest row, held ones included; None for an empty
+    /// upload
+    #[serde(skip)]
+    pub period: Option<(DateTime<Utc>, DateTime<Utc>)>,
+}
+
+/// A row whose printed balance breaks the chain, in minor units.
+#[derive(Serialize)]
+pub struct BalanceBreak {
+    /// 1-based spreadsheet row
+    pub row: usize,
+    /// The previous balance with this row's amount and fee applied
+    pub expected: i64,
+    pub found: i64,
+}
+
+#[derive(Serialize)]
+pub struct AuthenticityCheck {
+    /// "chronological_order", "running_balance", "receipt_format" or
+    /// "receipt_collisions"
+    pub name: &'static str,
+    /// Rows (or, for order and balance, consecutive pairs) the check applied
+    /// to; 0 when the statement gave it nothing to check
+    pub checked: usize,
+    pub failed: usize,
+}
+
+impl AuthenticityCheck {
+    /// Points taken off the score when every checked row fails.
+    fn weight(&self) -> u32 {
+        match self.name {
+            "running_balance" => 35,
+            "receipt_format" | "receipt_collisions" => 25,
+            _ => 15,
+        }
+    }
+}
+
+pub struct AuthenticityService;
+
+impl AuthenticityService {
+    /// Scores an upload for `till_id` before it is imported. Receipt formats
+    /// are only checked for M-Pesa statements; banks number theirs freely.
+    pub async fn assess(
+        db: &PgPool,
+        till_id: Uuid,
+        source: &str,
+        lines: &[StatementLine<'_>],
+    ) -> anyhow::Result<AuthenticityReport> {
+        let (balance_check, mut balance_breaks) = running_balance(lines);
+        let mut flagged: BTreeMap<usize, &'static str> =
+            balance_breaks.iter().map(|b| (b.row, "running_balance")).collect();
+        balance_breaks.truncate(MAX_REPORTED_BREAKS);
+        let mut checks = vec![chronological_order(lines), balance_check];
+        if source == "mpesa" {
+            let (format_check, malformed) = receipt_format(lines);
+            flagged.extend(malformed.into_iter().map(|row| (row, "receipt_format")));
+            checks.push(format_check);
+        }
+        let (collision_check, collisions) = Self::receipt_collisions(db, till_id, lines).await?;
+        flagged.extend(collisions.into_iter().map(|row| (row, "receipt_collisions")));
+        checks.push(collision_check);
+
+        let penalty: u32 = checks
+            .iter()
+            .filter(|check| check.checked > 0)
+            .map(|check| check.weight() * check.failed as u32 / check.checked as u32)
+            .sum();
+        let period = lines
+            .iter()
+            .map(|line| line.timestamp)
+            .min()
+            .zip(lines.iter().map(|line| line.timestamp).max());
+        Ok(AuthenticityReport {
+            score: 100u32.saturating_sub(penalty) as u8,
+            checks,
+            balance_breaks,
+            flagged,
+            period,
+        })
+    }
+
+    /// Records an imported upload's score against the till, returning the
+    /// import's ID for the rows held from it.
+    pub async fn record(
+        db: &PgPool,
+        till_id: Uuid,
+        source: &str,
+        provenance: proof_core::Provenance,
+        rows_imported: usize,
+        report: &AuthenticityReport,
+    ) -> anyhow::Result<Uuid> {
+        let id = sqlx::query_scalar(
+            r#"
+            INSERT INTO statement_imports (till_id, source, provenance, rows_imported, authenticity_score,
+                                           authenticity_checks, first_transaction_at, last_transaction_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            RETURNING id
+            "#,
+        )
+        .bind(till_id)
+        .bind(source)
+        .bind(provenance.as_str())
+        .bind(rows_imported as i32)
+        .bind(report.score as i16)
+        .bind(serde_json::to_value(&report.checks)?)
+        .bind(report.period.map(|(first, _)| first))
+        .bind(report.period.map(|(_, last)| last))
+        .fetch_one(db)
+        .await?;
+        Ok(id)
+    }
+
+    /// The check a held row fails once corrected, if any: the receipt's
+    /// format, for M-Pesa rows, and collisions with what's already stored.
+    /// Balance and order can't be checked on a row alone.
+    pub async fn recheck(
+        db: &PgPool,
+        till_id: Uuid,
+        source: &str,
+        line: &StatementLine<'_>,
+    ) -> anyhow::Result<Option<&'static str>> {
+        let lines = std::slice::from_ref(line);
+        if source == "mpesa" && !receipt_format(lines).1.is_empty() {
+            return Ok(Some("receipt_format"));
+        }
+        let (_, collisions) = Self::receipt_collisions(db, till_id, lines).await?;
+        Ok((!collisions.is_empty()).then_some("receipt_collisions"))
+    }
+
+    /// The lowest score among the till's uploads that `input` scores: those
+    /// from its sources whose rows overlap the period it covers. None when
+    /// it scores no uploads.
+    pub async fn lowest_score(db: &PgPool, till_id: Uuid, input: &ProofInput) -> anyhow::Result<Option<u8>> {
+        // Verified API proofs leave every upload out
+        if input.source == proof_core::DataSource::VerifiedApi {
+            return Ok(None);
+        }
+        let timestamps = || {
+            input
+                .transactions
+                .iter()
+                .chain(input.secondary.iter().flat_map(|s| &s.transactions))
+                .map(|t| t.timestamp)
+        };
+        let (Some(first), Some(last)) = (timestamps().min(), timestamps().max()) else {
+            return Ok(None);
+        };
+        // Uploads recorded before their period was are counted for any proof
+        let score: Option<i16> = sqlx::query_scalar(
+            r#"
+            SELECT MIN(authenticity_score)
+            FROM statement_imports
+            WHERE till_id = $1 AND rows_imported > 0 AND (source = 'mpesa' OR source = $2)
+              AND (first_transaction_at IS NULL OR (first_transaction_at <= $4 AND last_transaction_at >= $3))
+            "#,
+        )
+        .bind(till_id)
+        .bind(input.secondary.as_ref().map(|s| s.tag.as_str()))
+        .bind(DateTime::from_timestamp(first, 0))
+        .bind(DateTime::from_timestamp(last, 0))
+        .fetch_one(db)
+        .await?;
+        Ok(score.map(|s| s.clamp(0, 100) as u8))
+    }
+
+    /// "high", "medium" or "low", from the lowest authenticity score among
+    /// the uploads a proof scores. Proofs over no uploads are "high".
+    pub fn trust_tier(lowest_score: Option<u8>) -> &'static str {
+        match lowest_score {
+            Some(score) if score < LOW_TRUST_SCORE => "low",
+            Some(score) if score < MEDIUM_TRUST_SCORE => "medium",
+            _ => "high",
+        }
+    }
+
+    /// Receipts printed more than once with different details, in the upload
+    /// itself or against what's already stored for the till. Exact repeats
+    /// of a stored row are a re-upload, not a collision. Other tills aren't
+    /// compared: a payment between two merchants is on both statements, and
+    /// a match there would tell the uploader the receipt exists. Returns the
+    /// rows that collide: repeats within the upload, and every row whose
+    /// receipt is stored with other details.
+    async fn receipt_collisions(
+        db: &PgPool,
+        till_id: Uuid,
+        lines: &[StatementLine<'_>],
+    ) -> anyhow::Result<(AuthenticityCheck, Vec<usize>)> {
+        let mut seen: HashMap<&str, (DateTime<Utc>, i64)> = HashMap::new();
+        let mut rows = Vec::new();
+        for line in lines {
+            match seen.get(line.reference) {
+                Some(&details) if details != (line.timestamp, line.amount) => rows.push(line.row),
+                Some(_) => {}
+                None => {
+                    seen.insert(line.reference, (line.timestamp, line.amount));
+                }
+            }
+        }
+
+        // A receipt may still be stored under an older pepper's hash
+        let (mut receipts, mut references, mut timestamps, mut amounts) =
+            (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+        for (&receipt, &(timestamp, amount)) in &seen {
+            for reference in phone_number_hashes(receipt) {
+                receipts.push(receipt);
+                references.push(reference);
+                timestamps.push(timestamp);
+                amounts.push(amount);
+            }
+        }
+        let stored: Vec<String> = sqlx::query_scalar(
+            r#"
+            SELECT DISTINCT u.receipt
+            FROM UNNEST($1::text[], $2::text[], $3::timestamptz[], $4::bigint[])
+                AS u(receipt, reference, timestamp, amount)
+            JOIN transactions t ON t.reference = u.reference
+            WHERE t.till_id = $5 AND (t.timestamp <> u.timestamp OR t.amount <> u.amount)
+            "#,
+        )
+        .bind(&receipts)
+        .bind(&references)
+        .bind(&timestamps)
+        .bind(&amounts)
+        .bind(till_id)
+        .fetch_all(db)
+        .await?;
+
+        let check = AuthenticityCheck {
+            name: "receipt_collisions",
+            checked: lines.len(),
+            failed: (rows.len() + stored.len()).min(lines.len()),
+        };
+        let stored: HashSet<&str> = stored.iter().map(String::as_str).collect();
+        rows.extend(
+            lines
+                .iter()
+                .filter(|line| stored.contains(line.reference))
+                .map(|line| line.row),
+        );
+        Ok((check, rows))
+    }
+}
+
+/// Why a row failing `check` was held, for the owner reviewing it.
+pub fn flag_reason(check: &str) -> &'static str {
+    match check {
+        "running_balance" => "The balance doesn't follow from the row before",
+        "receipt_format" => "The receipt number isn't an M-Pesa receipt issued around the row's date",
+        "receipt_collisions" => "The receipt number is already stored with different details",
+        _ => "Failed an authenticity check",
+    }
+}
+
+/// Exports list rows newest or oldest first throughout; rows that step
+/// against the statement's overall direction were likely moved or added.
+fn chronological_order(lines: &[StatementLine<'_>]) -> AuthenticityCheck {
+    let ascending = is_ascending(lines);
+    let failed = lines
+        .windows(2)
+        .filter(|pair| {
+            if ascending {
+                pair[1].timestamp < pair[0].timestamp
+            } else {
+                pair[1].timestamp > pair[0].timestamp
+            }
+        })
+        .count();
+    AuthenticityCheck {
+        name: "chronological_order",
+        checked: lines.len().saturating_sub(1),
+        failed,
+    }
+}
+
+/// Each balance should be the one before it, in time, plus the later row's
+/// amount less its fee. The later row of a pair that doesn't add up is
+/// returned as a break. Only neighbouring rows of the file are compared, so
+/// a rejected row in between doesn't count against the statement; a
+/// negative balance always does, as M-Pesa never prints one.
+fn running_balance(lines: &[StatementLine<'_>]) -> (AuthenticityCheck, Vec<BalanceBreak>) {
+    let ascending = is_ascending(lines);
+    let mut checked = 0;
+    let mut failed = 0;
+    let mut breaks = Vec::new();
+    for pair in lines.windows(2) {
+        if pair[1].row != pair[0].row + 1 {
+            continue;
+        }
+        let (earlier, later) = if ascending { (&pair[0], &pair[1]) } else { (&pair[1], &pair[0]) };
+        let (Some(previous), Some(found)) = (earlier.balance, later.balance) else {
+            continue;
+        };
+        checked += 1;
+        let expected = previous + later.amount - later.fee.abs();
+        if expected != found {
+            breaks.push(BalanceBreak {
+                row: later.row,
+                expected,
+                found,
+            });
+        }
+        if expected != found || previous < 0 || found < 0 {
+            failed += 1;
+        }
+    }
+    let check = AuthenticityCheck {
+        name: "running_balance",
+        checked,
+        failed,
+    };
+    (check, breaks)
+}
+
+/// M-Pesa receipts are ten capital letters and digits, opening with the
+/// year and month they were issued; see `receipt_period`. Returns the rows
+/// that aren't.
+fn receipt_format(lines: &[StatementLine<'_>]) -> (AuthenticityCheck, Vec<usize>) {
+    let malformed: Vec<usize> = lines
+        .iter()
+        .filter(|line| {
+            let well_formed = line.reference.len() == 10
+                && line
+                    .reference
+                    .bytes()
+                    .all(|b| b.is_ascii_uppercase() || b.is_ascii_digit());
+            !well_formed || !receipt_matches_date(line.reference, line.timestamp)
+        })
+        .map(|line| line.row)
+        .collect();
+    let check = AuthenticityCheck {
+        name: "receipt_format",
+        checked: lines.len(),
+        failed: malformed.len(),
+    };
+    (check, malformed)
+}
+
+/// Whether the month a receipt was issued in is within a month of the
+/// row's date, allowing for timezones and month ends.
+fn receipt_matches_date(reference: &str, timestamp: DateTime<Utc>) -> bool {
+    // The year letters repeat every 26 years
+    const CYCLE: i32 = 26 * 12;
+    let Some(issued) = receipt_period(reference) else {
+        return false;
+    };
+    let dated = ((timestamp.year() - 2006) * 12 + timestamp.month0() as i32).rem_euclid(CYCLE);
+    let distance = (issued - dated).rem_euclid(CYCLE);
+    distance <= 1 || distance == CYCLE - 1
+}
+
+/// Months since January 2006, modulo the 26-year letter cycle, encoded in
+/// a receipt's first two letters: one letter per year from A for 2006,
+/// then A to L for January to December.
+fn receipt_period(reference: &str) -> Option<i32> {
+    let bytes = reference.as_bytes();
+    let year = *bytes.first().filter(|b| b.is_ascii_uppercase())?;
+    let month = *bytes.get(1).filter(|b| (b'A'..=b'L').contains(b))?;
+    Some((year - b'A') as i32 * 12 + (month - b'A') as i32)
+}
+
+/// Whether the file lists rows oldest first, judged from its ends.
+fn is_ascending(lines: &[StatementLine<'_>]) -> bool {
+    match (lines.first(), lines.last()) {
+        (Some(first), Some(last)) => first.timestamp <= last.timestamp,
+        _ => true,
+    }
+}
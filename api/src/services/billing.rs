@@ -0,0 +1,187 @@
+use chrono::{DateTime, Datelike, TimeZone, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::repos::billing::{BillingPlan, KeyUsage};
+use crate::db::repos::BillingRepo;
+
+/// A plan's monthly allowance is used up and it has no overage price.
+#[derive(Debug, Clone, thiserror::Error)]
+#[error("The {plan} plan includes {included} {unit}s a month, all used until {resets_at}")]
+pub struct LimitReached {
+    pub plan: String,
+    /// "proof" or "verification"
+    pub unit: &'static str,
+    pub included: i64,
+    pub resets_at: DateTime<Utc>,
+}
+
+/// Verification outcomes that reached a proof, and so are billed. Codes
+/// that weren't found, or whose proof expired or was revoked, are logged
+/// but free.
+const METERED_STATUSES: [&str; 2] = ["valid", "invalid"];
+
+/// Usage of a plan in the current billing month.
+pub struct Usage {
+    pub plan: BillingPlan,
+    /// "proof" or "verification"
+    pub unit: &'static str,
+    pub period_start: DateTime<Utc>,
+    pub period_end: DateTime<Utc>,
+    pub used: i64,
+    /// Lenders' verifications per key; empty for businesses
+    pub by_key: Vec<KeyUsage>,
+}
+
+impl Usage {
+    pub fn included(&self) -> i64 {
+        self.plan.included_per_month as i64
+    }
+
+    /// Past the included ones, each charged at the overage price.
+    pub fn billable(&self) -> i64 {
+        match self.plan.overage_price_minor {
+            Some(_) => (self.used - self.included()).max(0),
+            None => 0,
+        }
+    }
+
+    pub fn amount_due_minor(&self) -> i64 {
+        self.billable() * self.plan.overage_price_minor.unwrap_or(0)
+    }
+
+    /// Whether one more may be used this month.
+    pub fn ensure_allowance(&self) -> Result<(), LimitReached> {
+        self.ensure_allowance_for(1)
+    }
+
+    /// Whether `count` more may be used this month.
+    pub fn ensure_allowance_for(&self, count: i64) -> Result<(), LimitReached> {
+        if self.plan.overage_price_minor.is_some() || self.used + count <= self.included() {
+            return Ok(());
+        }
+        Err(LimitReached {
+            plan: self.plan.name.clone(),
+            unit: self.unit,
+            included: self.included(),
+            resets_at: self.period_end,
+        })
+    }
+}
+
+pub struct BillingService;
+
+impl BillingService {
+    /// The calendar month, in UTC, containing `now`.
+    pub fn billing_month(now: DateTime<Utc>) -> (DateTime<Utc>, DateTime<Utc>) {
+        let start = Utc
+            .with_ymd_and_hms(now.year(), now.month(), 1, 0, 0, 0)
+            .single()
+            .unwrap_or(now);
+        let (year, month) = if now.month() == 12 {
+            (now.year() + 1, 1)
+        } else {
+            (now.year(), now.month() + 1)
+        };
+        let end = Utc.with_ymd_and_hms(year, month, 1, 0, 0, 0).single().unwrap_or(now);
+        (start, end)
+    }
+
+    /// The business's proofs this month against its plan.
+    pub async fn proof_usage(db: &PgPool, user_id: Uuid) -> anyhow::Result<Usage> {
+        let plan = BillingRepo::plan_for_user(db, user_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("User {} not found", user_id))?;
+        let (period_start, period_end) = Self::billing_month(Utc::now());
+        let used = BillingRepo::proofs_since(db, user_id, period_start).await?;
+        Ok(Usage {
+            plan,
+            unit: "proof",
+            period_start,
+            period_end,
+            used,
+            by_key: Vec::new(),
+        })
+    }
+
+    /// Whether the user's plan has a proof left this month, checked in the
+    /// caller's transaction with the user's row held until it ends, so
+    /// proofs started at the same time can't both take the last one.
+    pub async fn lock_proof_allowance(
+        conn: &mut sqlx::PgConnection,
+        user_id: Uuid,
+    ) -> anyhow::Result<Result<(), LimitReached>> {
+        BillingRepo::lock_user(&mut *conn, user_id).await?;
+        let plan = BillingRepo::plan_for_user(&mut *conn, user_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("User {} not found", user_id))?;
+        let (period_start, period_end) = Self::billing_month(Utc::now());
+        let used = BillingRepo::proofs_since(&mut *conn, user_id, period_start).await?;
+        let usage = Usage {
+            plan,
+            unit: "proof",
+            period_start,
+            period_end,
+            used,
+            by_key: Vec::new(),
+        };
+        Ok(usage.ensure_allowance())
+    }
+
+    /// The lender's metered verifications this month, across its keys,
+    /// against its plan.
+    pub async fn verification_usage(db: &PgPool, lender_id: Uuid) -> anyhow::Result<Usage> {
+        let plan = BillingRepo::plan_for_lender(db, lender_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Lender {} not found", lender_id))?;
+        let (period_start, period_end) = Self::billing_month(Utc::now());
+        let by_key = BillingRepo::verifications_since(db, lender_id, period_start).await?;
+        Ok(Usage {
+            plan,
+            unit: "verification",
+            period_start,
+            period_end,
+            used: by_key.iter().map(|key| key.verifications).sum(),
+            by_key,
+        })
+    }
+
+    /// Logs a live verification. A metered one is logged only if the
+    /// lender's plan has one left, checked and logged while the lender's row
+    /// is held, so verifications at the same time can't both take the last
+    /// one. Returns the limit reached instead of logging past it.
+    pub async fn record_verification(
+        db: &PgPool,
+        lender_id: Uuid,
+        key_id: Uuid,
+        verification_code: &str,
+        status: &str,
+        credit_score: Option<i32>,
+    ) -> anyhow::Result<Result<(), LimitReached>> {
+        let mut tx = db.begin().await?;
+        if METERED_STATUSES.contains(&status) {
+            let plan = BillingRepo::plan_for_lender(db, lender_id)
+                .await?
+                .ok_or_else(|| anyhow::anyhow!("Lender {} not found", lender_id))?;
+            if plan.overage_price_minor.is_none() {
+                let (period_start, period_end) = Self::billing_month(Utc::now());
+                BillingRepo::lock_lender(&mut *tx, lender_id).await?;
+                let used = BillingRepo::metered_verifications_since(&mut *tx, lender_id, period_start).await?;
+                let usage = Usage {
+                    plan,
+                    unit: "verification",
+                    period_start,
+                    period_end,
+                    used,
+                    by_key: Vec::new(),
+                };
+                if let Err(limit) = usage.ensure_allowance() {
+                    return Ok(Err(limit));
+                }
+            }
+        }
+        BillingRepo::record_verification(&mut *tx, lender_id, key_id, verification_code, status, credit_score).await?;
+        tx.commit().await?;
+        Ok(Ok(()))
+    }
+}
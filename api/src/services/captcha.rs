@@ -0,0 +1,44 @@
+use std::net::IpAddr;
+
+use reqwest::Client;
+use serde::Deserialize;
+
+/// Providers `CAPTCHA_PROVIDER` may name, and where each checks tokens.
+pub const PROVIDERS: [(&str, &str); 2] = [
+    ("turnstile", "https://challenges.cloudflare.com/turnstile/v0/siteverify"),
+    ("recaptcha", "https://www.google.com/recaptcha/api/siteverify"),
+];
+
+const VERIFY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Both providers answer in this shape.
+#[derive(Deserialize)]
+struct SiteVerifyResponse {
+    success: bool,
+}
+
+pub struct CaptchaService;
+
+impl CaptchaService {
+    /// Asks the provider whether a widget token was solved by `remote_ip`.
+    /// Tokens are single-use, so a replayed one fails.
+    pub async fn verify(provider: &str, secret: &str, token: &str, remote_ip: IpAddr) -> anyhow::Result<bool> {
+        let url = PROVIDERS
+            .iter()
+            .find(|(name, _)| *name == provider)
+            .map(|(_, url)| *url)
+            .ok_or_else(|| anyhow::anyhow!("Unknown captcha provider: {}", provider))?;
+
+        let remote_ip = remote_ip.to_string();
+        let response: SiteVerifyResponse = Client::new()
+            .post(url)
+            .timeout(VERIFY_TIMEOUT)
+            .form(&[("secret", secret), ("response", token), ("remoteip", remote_ip.as_str())])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.success)
+    }
+}
@@ -1,8 +1,20 @@
+use std::time::Duration;
+
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
 
+/// Registration waits on the lookup, so it can't hang on a slow Daraja.
+const LOOKUP_TIMEOUT: Duration = Duration::from_secs(10);
+
 pub struct DarajaService;
 
+/// What M-Pesa said about a shortcode.
+#[derive(Debug)]
+pub enum ShortcodeLookup {
+    Found { organization_name: Option<String> },
+    Unknown,
+}
+
 #[derive(Serialize)]
 struct TokenRequest {
     grant_type: String,
@@ -28,6 +40,7 @@ impl DarajaService {
 
         let response = client
             .get(url)
+            .timeout(LOOKUP_TIMEOUT)
             .header("Authorization", format!("Basic {}", auth))
             .send()
             .await?;
@@ -75,5 +88,65 @@ impl DarajaService {
 
         Ok(())
     }
-}
 
+    /// Looks a shortcode up with Daraja's organization info query. Errors,
+    /// HTTP 4xx included, mean the lookup itself failed, not that the
+    /// shortcode is unknown; only Daraja's own response code says that.
+    pub async fn query_org_info(
+        access_token: &str,
+        shortcode: &str,
+        till_type: &crate::models::TillType,
+    ) -> anyhow::Result<ShortcodeLookup> {
+        let client = Client::new();
+        let url = "https://sandbox.safaricom.co.ke/sfcverify/v1/query/info";
+
+        #[derive(Serialize)]
+        #[allow(non_snake_case)]
+        struct OrgInfoRequest {
+            IdentifierType: String,
+            Identifier: String,
+        }
+
+        #[derive(Deserialize)]
+        #[allow(non_snake_case)]
+        struct OrgInfoResponse {
+            ResponseCode: Option<String>,
+            OrganizationName: Option<String>,
+        }
+
+        // Daraja identifier types: 2 is a till number, 4 an organization
+        // shortcode such as a PayBill
+        let identifier_type = match till_type {
+            crate::models::TillType::BuyGoods => "2",
+            crate::models::TillType::PayBill => "4",
+        };
+        let request = OrgInfoRequest {
+            IdentifierType: identifier_type.to_string(),
+            Identifier: shortcode.to_string(),
+        };
+
+        let response = client
+            .post(url)
+            .timeout(LOOKUP_TIMEOUT)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .header("Content-Type", "application/json")
+            .json(&request)
+            .send()
+            .await?;
+
+        // A rejected request (an expired token, a malformed body) says
+        // nothing about the shortcode
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Daraja API error: {}", error_text);
+        }
+
+        let info: OrgInfoResponse = response.json().await?;
+        if info.ResponseCode.as_deref().is_some_and(|code| code != "0") {
+            return Ok(ShortcodeLookup::Unknown);
+        }
+        Ok(ShortcodeLookup::Found {
+            organization_name: info.OrganizationName.filter(|name| !name.trim().is_empty()),
+        })
+    }
+}
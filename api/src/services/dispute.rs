@@ -0,0 +1,124 @@
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::utils::phone_number_hashes;
+
+/// Share of a till's volume that may be disputed before its proofs are
+/// flagged as contested.
+const CONTEST_THRESHOLD: f64 = 0.05;
+
+pub struct DisputeService;
+
+#[derive(Debug)]
+pub enum DisputeOutcome {
+    Recorded { dispute_id: Uuid, contested_proofs: u64 },
+    TransactionNotFound,
+    AlreadyDisputed,
+}
+
+impl DisputeService {
+    /// Records a dispute against one of the till's transactions, identified
+    /// by its M-Pesa receipt number, then re-checks the till's disputed share.
+    pub async fn record(
+        db: &PgPool,
+        till_id: Uuid,
+        lender_id: Option<Uuid>,
+        transaction_reference: &str,
+        reason: &str,
+        evidence: &str,
+    ) -> anyhow::Result<DisputeOutcome> {
+        let transaction: Option<(Uuid, chrono::DateTime<chrono::Utc>)> =
+            sqlx::query_as("SELECT id, timestamp FROM transactions WHERE till_id = $1 AND reference = ANY($2)")
+                .bind(till_id)
+                .bind(phone_number_hashes(transaction_reference))
+                .fetch_optional(db)
+                .await?;
+
+        let Some((transaction_id, transaction_timestamp)) = transaction else {
+            return Ok(DisputeOutcome::TransactionNotFound);
+        };
+
+        let dispute_id: Option<Uuid> = sqlx::query_scalar(
+            r#"
+            INSERT INTO disputes (till_id, transaction_id, transaction_timestamp, lender_id, reason, evidence)
+            VALUES ($1, $2, $6, $3, $4, $5)
+            ON CONFLICT (transaction_id) DO NOTHING
+            RETURNING id
+            "#,
+        )
+        .bind(till_id)
+        .bind(transaction_id)
+        .bind(lender_id)
+        .bind(reason)
+        .bind(evidence)
+        .bind(transaction_timestamp)
+        .fetch_optional(db)
+        .await?;
+
+        let Some(dispute_id) = dispute_id else {
+            return Ok(DisputeOutcome::AlreadyDisputed);
+        };
+
+        let contested_proofs = Self::flag_contested_proofs(db, till_id).await?;
+
+        Ok(DisputeOutcome::Recorded { dispute_id, contested_proofs })
+    }
+
+    /// Marks completed proofs that covered disputed transactions as contested
+    /// once the disputed share of volume crosses the threshold. Proofs are
+    /// never deleted; lenders just see the flag.
+    async fn flag_contested_proofs(db: &PgPool, till_id: Uuid) -> anyhow::Result<u64> {
+        let row = sqlx::query(
+            r#"
+            SELECT
+                COALESCE(SUM(t.amount), 0)::BIGINT,
+                COALESCE(SUM(t.amount) FILTER (WHERE d.id IS NOT NULL), 0)::BIGINT,
+                MIN(t.timestamp) FILTER (WHERE d.id IS NOT NULL)
+            FROM transactions t
+            LEFT JOIN disputes d ON d.transaction_id = t.id AND d.transaction_timestamp = t.timestamp
+            WHERE t.till_id = $1
+            "#,
+        )
+        .bind(till_id)
+        .fetch_one(db)
+        .await?;
+
+        let total: i64 = row.try_get(0)?;
+        let disputed: i64 = row.try_get(1)?;
+        let earliest_disputed: Option<chrono::DateTime<chrono::Utc>> = row.try_get(2)?;
+
+        let Some(earliest_disputed) = earliest_disputed else {
+            return Ok(0);
+        };
+        if total <= 0 || (disputed as f64 / total as f64) < CONTEST_THRESHOLD {
+            return Ok(0);
+        }
+
+        let result = sqlx::query(
+            r#"
+            UPDATE proof_sessions
+            SET contested_at = NOW()
+            WHERE till_id = $1
+              AND status = 'completed'
+              AND contested_at IS NULL
+              AND created_at >= $2
+            "#,
+        )
+        .bind(till_id)
+        .bind(earliest_disputed)
+        .execute(db)
+        .await?;
+
+        if result.rows_affected() > 0 {
+            tracing::warn!(
+                "Flagged {} proofs on till {} as contested ({} of {} cents disputed)",
+                result.rows_affected(),
+                till_id,
+                disputed,
+                total
+            );
+        }
+
+        Ok(result.rows_affected())
+    }
+}
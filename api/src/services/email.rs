@@ -0,0 +1,36 @@
+use lettre::{
+    message::Mailbox, transport::smtp::authentication::Credentials, AsyncSmtpTransport, AsyncTransport, Message,
+    Tokio1Executor,
+};
+
+use crate::config::Config;
+
+pub struct EmailService;
+
+impl EmailService {
+    pub fn is_enabled(config: &Config) -> bool {
+        config.smtp_host.is_some()
+    }
+
+    pub async fn send(config: &Config, to: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        let host = config
+            .smtp_host
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("SMTP_HOST is not configured"))?;
+
+        let message = Message::builder()
+            .from(config.email_from.parse::<Mailbox>()?)
+            .to(to.parse::<Mailbox>()?)
+            .subject(subject)
+            .body(body.to_string())?;
+
+        let mut transport = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host)?.port(config.smtp_port);
+        if let (Some(username), Some(password)) = (&config.smtp_username, &config.smtp_password) {
+            transport = transport.credentials(Credentials::new(username.clone(), password.clone()));
+        }
+
+        transport.build().send(message).await?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,172 @@
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::i18n::{Locale, Message};
+use crate::services::notification::{NotificationService, OwnerNotification};
+use crate::services::webhook::WebhookService;
+
+/// Days before expiry that owners are reminded, nearest first so a proof
+/// found late only gets the nearer reminder.
+pub const REMINDER_DAYS: [i32; 2] = [7, 30];
+
+/// How long a reminder's regeneration link works after it's sent.
+const LINK_TTL_DAYS: i32 = 30;
+
+pub struct ExpiryReminderService;
+
+impl ExpiryReminderService {
+    /// Reminds the owners of completed proofs entering a reminder window on
+    /// the channels they've chosen, and tells lenders that verified the proof by webhook.
+    /// Proofs the owner has already replaced are skipped. Safe to run on
+    /// every worker: each reminder is claimed before it's sent. Returns how
+    /// many were sent.
+    pub async fn send_due(db: &PgPool, config: &Config) -> anyhow::Result<usize> {
+        let mut sent = 0;
+        for days_before in REMINDER_DAYS {
+            let rows = sqlx::query(
+                r#"
+                SELECT ps.id, ps.user_id, ps.verification_code, ps.expires_at, u.preferred_language
+                FROM proof_sessions ps
+                JOIN users u ON u.id = ps.user_id
+                WHERE ps.status = 'completed'
+                  AND ps.expires_at > NOW() AND ps.expires_at <= NOW() + make_interval(days => $1)
+                  AND NOT EXISTS (
+                      SELECT 1 FROM proof_expiry_reminders r
+                      WHERE r.session_id = ps.id AND r.days_before <= $1
+                  )
+                  AND NOT EXISTS (
+                      SELECT 1 FROM proof_sessions newer
+                      WHERE newer.till_id = ps.till_id AND newer.user_id = ps.user_id
+                        AND newer.created_at > ps.created_at AND newer.status <> 'failed'
+                  )
+                "#,
+            )
+            .bind(days_before)
+            .fetch_all(db)
+            .await?;
+
+            for row in rows {
+                let session_id: Uuid = row.try_get(0)?;
+                match Self::remind(db, config, days_before, &row).await {
+                    Ok(true) => sent += 1,
+                    Ok(false) => {}
+                    Err(e) => tracing::error!("Failed to send expiry reminder for {}: {}", session_id, e),
+                }
+            }
+        }
+
+        Ok(sent)
+    }
+
+    /// The proof a regeneration link was sent for, and the session it
+    /// already started if it has been used. None once the link is
+    /// `LINK_TTL_DAYS` old.
+    pub async fn find_by_token(db: &PgPool, token: &str) -> anyhow::Result<Option<(Uuid, Option<Uuid>)>> {
+        let row = sqlx::query(
+            r#"
+            SELECT session_id, regenerated_session_id
+            FROM proof_expiry_reminders
+            WHERE token_hash = $1 AND sent_at > NOW() - make_interval(days => $2)
+            "#,
+        )
+        .bind(hash_token(token))
+        .bind(LINK_TTL_DAYS)
+        .fetch_optional(db)
+        .await?;
+
+        row.map(|row| Ok((row.try_get(0)?, row.try_get(1)?))).transpose()
+    }
+
+    /// Records the session a regeneration link started, so tapping it again
+    /// shows that one instead of starting another.
+    pub async fn mark_used(db: &PgPool, token: &str, regenerated_session_id: Uuid) -> anyhow::Result<()> {
+        sqlx::query("UPDATE proof_expiry_reminders SET regenerated_session_id = $2 WHERE token_hash = $1")
+            .bind(hash_token(token))
+            .bind(regenerated_session_id)
+            .execute(db)
+            .await?;
+        Ok(())
+    }
+
+    /// Claims and sends one reminder. False when another worker claimed it.
+    async fn remind(
+        db: &PgPool,
+        config: &Config,
+        days_before: i32,
+        row: &sqlx::postgres::PgRow,
+    ) -> anyhow::Result<bool> {
+        let session_id: Uuid = row.try_get(0)?;
+        let user_id: Uuid = row.try_get(1)?;
+        let verification_code: String = row.try_get(2)?;
+        let expires_at: chrono::DateTime<chrono::Utc> = row.try_get(3)?;
+        let locale = Locale::from_code(&row.try_get::<String, _>(4)?).unwrap_or_default();
+
+        let token = generate_token();
+        let claimed = sqlx::query(
+            r#"
+            INSERT INTO proof_expiry_reminders (session_id, days_before, token_hash)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (session_id, days_before) DO NOTHING
+            "#,
+        )
+        .bind(session_id)
+        .bind(days_before)
+        .bind(hash_token(&token))
+        .execute(db)
+        .await?
+        .rows_affected();
+        if claimed == 0 {
+            return Ok(false);
+        }
+
+        // Rounded up, so a proof expiring in 6 days 23 hours reads "7 days"
+        let days_left = ((expires_at - chrono::Utc::now()).num_hours() + 23) / 24;
+        let link = format!("{}/api/proofs/regenerate?token={}", config.public_url, token);
+        let message = Message::ProofExpiringSms {
+            days: days_left,
+            verification_code: &verification_code,
+            link: &link,
+        };
+
+        let notification = OwnerNotification { event: "proof.expiring", session_id, message, by_sms: true };
+        if let Err(e) = NotificationService::notify_owner(db, config, user_id, locale, notification).await {
+            tracing::error!("Failed to send expiry reminder for {}: {}", session_id, e);
+        }
+
+        // Lenders relying on the proof learn they'll need a fresh one
+        let lender_ids: Vec<Uuid> = sqlx::query_scalar(
+            "SELECT DISTINCT lender_id FROM lender_verifications WHERE verification_code = $1 AND status = 'valid'",
+        )
+        .bind(&verification_code)
+        .fetch_all(db)
+        .await?;
+        let data = serde_json::json!({
+            "verification_code": verification_code,
+            "expires_at": expires_at.to_rfc3339(),
+            "days_left": days_left,
+        });
+        for lender_id in lender_ids {
+            if let Err(e) = WebhookService::dispatch(db, lender_id, "proof.expiring", data.clone()).await {
+                tracing::error!("Failed to dispatch webhooks for lender {}: {}", lender_id, e);
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// 144 random bits, URL-safe, for a link that works without signing in.
+fn generate_token() -> String {
+    use base64::Engine;
+    use rand::RngCore;
+
+    let mut bytes = [0u8; 18];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+fn hash_token(token: &str) -> String {
+    hex::encode(Sha256::digest(token.as_bytes()))
+}
@@ -0,0 +1,142 @@
+use std::collections::BTreeMap;
+
+use jsonwebtoken::{encode, Algorithm, EncodingKey, Header};
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+
+const TOKEN_URL: &str = "https://oauth2.googleapis.com/token";
+const MESSAGING_SCOPE: &str = "https://www.googleapis.com/auth/firebase.messaging";
+const SEND_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// What FCM did with a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    Sent,
+    /// The app was uninstalled or the token rotated; it won't work again
+    Unregistered,
+}
+
+/// Claims of the service account assertion traded for an access token.
+#[derive(Serialize)]
+struct Assertion<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Deserialize)]
+struct ErrorBody {
+    #[serde(default)]
+    details: Vec<ErrorDetail>,
+}
+
+#[derive(Deserialize)]
+struct ErrorDetail {
+    #[serde(rename = "errorCode")]
+    error_code: Option<String>,
+}
+
+/// Firebase Cloud Messaging over the HTTP v1 API, authenticated as a
+/// service account.
+pub struct FcmService;
+
+impl FcmService {
+    pub fn is_enabled(config: &Config) -> bool {
+        config.fcm_project_id.is_some()
+    }
+
+    /// A short-lived OAuth token for sending messages. Good for an hour, so
+    /// fetch one per batch of sends rather than per message.
+    pub async fn access_token(config: &Config) -> anyhow::Result<String> {
+        let (Some(client_email), Some(private_key)) = (&config.fcm_client_email, &config.fcm_private_key) else {
+            anyhow::bail!("FCM service account is not configured");
+        };
+
+        let now = chrono::Utc::now().timestamp();
+        let assertion = encode(
+            &Header::new(Algorithm::RS256),
+            &Assertion {
+                iss: client_email,
+                scope: MESSAGING_SCOPE,
+                aud: TOKEN_URL,
+                iat: now,
+                exp: now + 3600,
+            },
+            &EncodingKey::from_rsa_pem(private_key.as_bytes())?,
+        )?;
+
+        let response: TokenResponse = Client::new()
+            .post(TOKEN_URL)
+            .timeout(SEND_TIMEOUT)
+            .form(&[
+                ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+                ("assertion", assertion.as_str()),
+            ])
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response.access_token)
+    }
+
+    /// Sends one notification to one device. `data` reaches the app
+    /// alongside it, e.g. to open the right screen when tapped.
+    pub async fn send(
+        config: &Config,
+        access_token: &str,
+        device_token: &str,
+        title: &str,
+        body: &str,
+        data: &BTreeMap<&str, String>,
+    ) -> anyhow::Result<Delivery> {
+        let project_id = config
+            .fcm_project_id
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("FCM_PROJECT_ID is not configured"))?;
+
+        let response = Client::new()
+            .post(format!("https://fcm.googleapis.com/v1/projects/{}/messages:send", project_id))
+            .timeout(SEND_TIMEOUT)
+            .bearer_auth(access_token)
+            .json(&serde_json::json!({
+                "message": {
+                    "token": device_token,
+                    "notification": { "title": title, "body": body },
+                    "data": data,
+                }
+            }))
+            .send()
+            .await?;
+
+        let status = response.status();
+        if status.is_success() {
+            return Ok(Delivery::Sent);
+        }
+        if status == StatusCode::NOT_FOUND {
+            return Ok(Delivery::Unregistered);
+        }
+
+        let text = response.text().await.unwrap_or_default();
+        let unregistered = serde_json::from_str::<ErrorResponse>(&text)
+            .is_ok_and(|e| e.error.details.iter().any(|d| d.error_code.as_deref() == Some("UNREGISTERED")));
+        if unregistered {
+            return Ok(Delivery::Unregistered);
+        }
+        anyhow::bail!("FCM send failed with {}: {}", status, text)
+    }
+}
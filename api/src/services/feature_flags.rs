@@ -0,0 +1,102 @@
+//! Runtime switches for risky features, so they can be rolled out to a
+//! share of users, or to named ones, without a redeploy. Flags live in the
+//! `feature_flags` table and are cached in Redis for `CACHE_TTL_SECS`.
+//! Admin changes drop the cached copy, so they apply at once.
+
+use redis::AsyncCommands;
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::repos::feature_flags::FeatureFlag;
+use crate::db::repos::FeatureFlagRepo;
+
+/// Reading statement photos with OCR.
+pub const STATEMENT_PHOTO_OCR: &str = "statement_photo_ocr";
+
+const CACHE_TTL_SECS: u64 = 30;
+
+/// Whether `flag` puts `user_id` in its rollout. Each user lands in a fixed
+/// bucket per flag, so raising the percentage only adds users, and flags
+/// with the same percentage don't pick the same ones.
+pub fn in_rollout(flag: &FeatureFlag, user_id: Option<Uuid>) -> bool {
+    if !flag.enabled {
+        return false;
+    }
+    if flag.rollout_percentage >= 100 {
+        return true;
+    }
+    let Some(user_id) = user_id else {
+        return false;
+    };
+    if flag.user_ids.contains(&user_id) {
+        return true;
+    }
+    let digest = Sha256::digest(format!("{}:{}", flag.key, user_id));
+    let bucket = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) % 100;
+    (bucket as i16) < flag.rollout_percentage
+}
+
+pub struct FeatureFlagService;
+
+impl FeatureFlagService {
+    /// Whether the feature is on for the user, or for everyone when there's
+    /// no user. A flag that can't be read counts as off.
+    pub async fn is_enabled(db: &PgPool, redis: &redis::Client, key: &str, user_id: Option<Uuid>) -> bool {
+        match Self::flag(db, redis, key).await {
+            Ok(flag) => flag.is_some_and(|flag| in_rollout(&flag, user_id)),
+            Err(e) => {
+                tracing::warn!("Failed to read feature flag {}, treating it as off: {}", key, e);
+                false
+            }
+        }
+    }
+
+    /// The flag, from the Redis cache when it's there. Redis being down only
+    /// costs the cache.
+    pub async fn flag(db: &PgPool, redis: &redis::Client, key: &str) -> anyhow::Result<Option<FeatureFlag>> {
+        let cache_key = Self::cache_key(key);
+        let mut conn = match redis.get_async_connection().await {
+            Ok(conn) => Some(conn),
+            Err(e) => {
+                tracing::warn!("Feature flag cache unavailable: {}", e);
+                None
+            }
+        };
+
+        if let Some(conn) = conn.as_mut() {
+            let cached: Option<String> = conn.get(&cache_key).await.unwrap_or(None);
+            if let Some(cached) = cached {
+                if let Ok(flag) = serde_json::from_str::<Option<FeatureFlag>>(&cached) {
+                    return Ok(flag);
+                }
+            }
+        }
+
+        let flag = FeatureFlagRepo::get(db, key).await?;
+        if let Some(conn) = conn.as_mut() {
+            let value = serde_json::to_string(&flag)?;
+            if let Err(e) = conn.set_ex::<_, _, ()>(&cache_key, value, CACHE_TTL_SECS).await {
+                tracing::warn!("Failed to cache feature flag {}: {}", key, e);
+            }
+        }
+        Ok(flag)
+    }
+
+    /// Drops the cached copy after a change. If Redis can't be reached, the
+    /// old settings may be served until the copy expires.
+    pub async fn invalidate(redis: &redis::Client, key: &str) {
+        let result = async {
+            let mut conn = redis.get_async_connection().await?;
+            conn.del::<_, ()>(Self::cache_key(key)).await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to drop cached feature flag {}: {}", key, e);
+        }
+    }
+
+    fn cache_key(key: &str) -> String {
+        format!("feature_flag:{}", key)
+    }
+}
@@ -0,0 +1,353 @@
+use std::path::Path;
+
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+
+use crate::config::Config;
+
+pub const CSV_CONTENT_TYPE: &str = "text/csv";
+pub const PDF_CONTENT_TYPE: &str = "application/pdf";
+pub const XLSX_CONTENT_TYPE: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const OLE_MAGIC: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+const PDF_MAGIC: &[u8] = b"%PDF-";
+const JPEG_MAGIC: &[u8] = &[0xFF, 0xD8, 0xFF];
+const PNG_MAGIC: &[u8] = b"\x89PNG\r\n\x1a\n";
+const CLAMAV_CHUNK_BYTES: usize = 64 * 1024;
+/// How much of a spooled upload is read at a time while sniffing it.
+const SNIFF_CHUNK_BYTES: usize = 64 * 1024;
+/// The longest zip entry name `Sniffer` looks for, less one: what a name
+/// split across two chunks can leave at the end of the first.
+const ZIP_NAME_OVERLAP: usize = b"xl/workbook.xml".len() - 1;
+
+/// What the bytes of an upload actually are, regardless of the declared
+/// content type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DetectedType {
+    Csv,
+    Pdf,
+    Xlsx,
+}
+
+impl DetectedType {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            DetectedType::Csv => CSV_CONTENT_TYPE,
+            DetectedType::Pdf => PDF_CONTENT_TYPE,
+            DetectedType::Xlsx => XLSX_CONTENT_TYPE,
+        }
+    }
+}
+
+/// What the bytes of a statement photo actually are.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageType {
+    Jpeg,
+    Png,
+}
+
+impl ImageType {
+    pub fn content_type(&self) -> &'static str {
+        match self {
+            ImageType::Jpeg => "image/jpeg",
+            ImageType::Png => "image/png",
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageType::Jpeg => "jpg",
+            ImageType::Png => "png",
+        }
+    }
+}
+
+/// Why an upload was refused before parsing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileRejection {
+    Empty,
+    TooLarge { max_bytes: usize },
+    UnrecognizedContent,
+    NotAnImage,
+    TypeMismatch { declared: String, detected: &'static str },
+    MacroEnabled,
+    Infected { signature: String },
+    ScanUnavailable,
+}
+
+impl FileRejection {
+    /// Stable identifier returned alongside the error code.
+    pub fn reason(&self) -> &'static str {
+        match self {
+            FileRejection::Empty => "empty",
+            FileRejection::TooLarge { .. } => "too_large",
+            FileRejection::UnrecognizedContent => "unrecognized_content",
+            FileRejection::NotAnImage => "not_an_image",
+            FileRejection::TypeMismatch { .. } => "type_mismatch",
+            FileRejection::MacroEnabled => "macro_enabled",
+            FileRejection::Infected { .. } => "infected",
+            FileRejection::ScanUnavailable => "scan_unavailable",
+        }
+    }
+}
+
+impl std::fmt::Display for FileRejection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileRejection::Empty => write!(f, "file is empty"),
+            FileRejection::TooLarge { max_bytes } => write!(f, "file exceeds the {} byte limit", max_bytes),
+            FileRejection::UnrecognizedContent => write!(f, "file content is not CSV, PDF or XLSX"),
+            FileRejection::NotAnImage => write!(f, "file content is not a JPEG or PNG image"),
+            FileRejection::TypeMismatch { declared, detected } => {
+                write!(f, "declared as {} but content is {}", declared, detected)
+            }
+            FileRejection::MacroEnabled => write!(f, "macro-enabled workbooks are not accepted"),
+            FileRejection::Infected { signature } => write!(f, "malware detected: {}", signature),
+            FileRejection::ScanUnavailable => write!(f, "virus scanner is unavailable"),
+        }
+    }
+}
+
+pub struct FileScanService;
+
+impl FileScanService {
+    /// Checks size, sniffs the real format and runs the optional virus scan
+    /// over a spooled upload, reading it a chunk at a time. Returns the
+    /// detected type, which callers should parse by instead of the
+    /// client-supplied content type. The outer error is a failure to read
+    /// the file.
+    pub async fn inspect(
+        config: &Config,
+        declared_type: Option<&str>,
+        path: &Path,
+    ) -> std::io::Result<Result<DetectedType, FileRejection>> {
+        let len = tokio::fs::metadata(path).await?.len();
+        if len == 0 {
+            return Ok(Err(FileRejection::Empty));
+        }
+        if len > config.max_upload_bytes as u64 {
+            return Ok(Err(FileRejection::TooLarge { max_bytes: config.max_upload_bytes }));
+        }
+
+        if declared_type.is_some_and(is_macro_content_type) {
+            return Ok(Err(FileRejection::MacroEnabled));
+        }
+
+        let mut file = tokio::fs::File::open(path).await?;
+        let mut sniffer = Sniffer::default();
+        let mut buf = vec![0; SNIFF_CHUNK_BYTES];
+        loop {
+            let n = file.read(&mut buf).await?;
+            if n == 0 {
+                break;
+            }
+            sniffer.feed(&buf[..n]);
+        }
+        let detected = match sniffer.finish() {
+            Ok(detected) => detected,
+            Err(rejection) => return Ok(Err(rejection)),
+        };
+
+        // Browsers label CSV as application/vnd.ms-excel when Excel is
+        // installed, so that type is accepted for CSV content.
+        let declared_ok = match declared_type {
+            None => true,
+            Some(declared) => match detected {
+                DetectedType::Csv => declared == CSV_CONTENT_TYPE || declared == "application/vnd.ms-excel",
+                other => declared == other.content_type(),
+            },
+        };
+        if !declared_ok {
+            return Ok(Err(FileRejection::TypeMismatch {
+                declared: declared_type.unwrap_or_default().to_string(),
+                detected: detected.content_type(),
+            }));
+        }
+
+        if let Some(address) = &config.clamav_address {
+            let file = tokio::fs::File::open(path).await?;
+            if let Err(rejection) = Self::clamav_scan(address, file).await {
+                return Ok(Err(rejection));
+            }
+        }
+
+        Ok(Ok(detected))
+    }
+
+    /// `inspect` for photos of statements, which must be JPEG or PNG.
+    pub async fn inspect_image(
+        config: &Config,
+        declared_type: Option<&str>,
+        data: &[u8],
+    ) -> Result<ImageType, FileRejection> {
+        if data.is_empty() {
+            return Err(FileRejection::Empty);
+        }
+        if data.len() > config.max_upload_bytes {
+            return Err(FileRejection::TooLarge { max_bytes: config.max_upload_bytes });
+        }
+
+        let detected = if data.starts_with(JPEG_MAGIC) {
+            ImageType::Jpeg
+        } else if data.starts_with(PNG_MAGIC) {
+            ImageType::Png
+        } else {
+            return Err(FileRejection::NotAnImage);
+        };
+        // Some phones label JPEGs image/jpg
+        let declared_ok = match declared_type {
+            None => true,
+            Some(declared) => {
+                declared == detected.content_type() || (detected == ImageType::Jpeg && declared == "image/jpg")
+            }
+        };
+        if !declared_ok {
+            return Err(FileRejection::TypeMismatch {
+                declared: declared_type.unwrap_or_default().to_string(),
+                detected: detected.content_type(),
+            });
+        }
+
+        if let Some(address) = &config.clamav_address {
+            Self::clamav_scan(address, data).await?;
+        }
+
+        Ok(detected)
+    }
+
+    /// Streams the file to clamd using the INSTREAM command.
+    async fn clamav_scan(address: &str, data: impl AsyncRead + Unpin) -> Result<(), FileRejection> {
+        let response = Self::clamav_request(address, data).await.map_err(|e| {
+            tracing::error!("ClamAV scan failed: {}", e);
+            FileRejection::ScanUnavailable
+        })?;
+
+        let response = response.trim_end_matches('\0').trim();
+        if response.ends_with("OK") {
+            Ok(())
+        } else if let Some(found) = response.strip_suffix("FOUND") {
+            let signature = found.trim_start_matches("stream:").trim().to_string();
+            tracing::warn!("Rejected upload containing {}", signature);
+            Err(FileRejection::Infected { signature })
+        } else {
+            tracing::error!("Unexpected ClamAV response: {}", response);
+            Err(FileRejection::ScanUnavailable)
+        }
+    }
+
+    async fn clamav_request(address: &str, mut data: impl AsyncRead + Unpin) -> anyhow::Result<String> {
+        let mut stream = TcpStream::connect(address).await?;
+        stream.write_all(b"zINSTREAM\0").await?;
+        let mut chunk = vec![0; CLAMAV_CHUNK_BYTES];
+        loop {
+            let n = data.read(&mut chunk).await?;
+            if n == 0 {
+                break;
+            }
+            stream.write_all(&(n as u32).to_be_bytes()).await?;
+            stream.write_all(&chunk[..n]).await?;
+        }
+        stream.write_all(&0u32.to_be_bytes()).await?;
+
+        let mut response = Vec::new();
+        stream.read_to_end(&mut response).await?;
+        Ok(String::from_utf8_lossy(&response).into_owned())
+    }
+}
+
+/// Works out what a file is from its bytes as they're read. The container
+/// is told by its first bytes; zip entry names and whether text is UTF-8
+/// are then followed across every chunk.
+#[derive(Default)]
+struct Sniffer {
+    /// The first bytes, until there are enough to match the longest magic
+    head: Vec<u8>,
+    container: Option<Container>,
+}
+
+enum Container {
+    Pdf,
+    /// Legacy .xls/.doc containers can carry VBA and aren't parsed
+    Ole,
+    /// Zip entry names are stored uncompressed, so the workbook parts can
+    /// be found without inflating the archive. `tail` is the end of the
+    /// last chunk, for a name split across two.
+    Zip { macros: bool, workbook: bool, tail: Vec<u8> },
+    /// `pending` holds a UTF-8 sequence the last chunk cut short
+    Text { valid: bool, pending: Vec<u8> },
+}
+
+impl Sniffer {
+    fn feed(&mut self, chunk: &[u8]) {
+        if self.container.is_some() {
+            self.scan(chunk);
+            return;
+        }
+        self.head.extend_from_slice(chunk);
+        if self.head.len() >= OLE_MAGIC.len() {
+            self.start();
+        }
+    }
+
+    fn start(&mut self) {
+        let head = std::mem::take(&mut self.head);
+        self.container = Some(if head.starts_with(PDF_MAGIC) {
+            Container::Pdf
+        } else if head.starts_with(OLE_MAGIC) {
+            Container::Ole
+        } else if head.starts_with(ZIP_MAGIC) {
+            Container::Zip { macros: false, workbook: false, tail: Vec::new() }
+        } else {
+            Container::Text { valid: true, pending: Vec::new() }
+        });
+        self.scan(&head);
+    }
+
+    fn scan(&mut self, chunk: &[u8]) {
+        match &mut self.container {
+            Some(Container::Zip { macros, workbook, tail }) => {
+                tail.extend_from_slice(chunk);
+                *macros |= contains(tail, b"vbaProject.bin");
+                *workbook |= contains(tail, b"xl/workbook.xml");
+                tail.drain(..tail.len().saturating_sub(ZIP_NAME_OVERLAP));
+            }
+            Some(Container::Text { valid, pending }) if *valid => {
+                pending.extend_from_slice(chunk);
+                if pending.contains(&0) {
+                    *valid = false;
+                    return;
+                }
+                match std::str::from_utf8(pending) {
+                    Ok(_) => pending.clear(),
+                    Err(e) if e.error_len().is_none() => {
+                        pending.drain(..e.valid_up_to());
+                    }
+                    Err(_) => *valid = false,
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn finish(mut self) -> Result<DetectedType, FileRejection> {
+        if self.container.is_none() {
+            self.start();
+        }
+        match self.container {
+            Some(Container::Pdf) => Ok(DetectedType::Pdf),
+            Some(Container::Ole) | Some(Container::Zip { macros: true, .. }) => Err(FileRejection::MacroEnabled),
+            Some(Container::Zip { workbook: true, .. }) => Ok(DetectedType::Xlsx),
+            Some(Container::Text { valid: true, pending }) if pending.is_empty() => Ok(DetectedType::Csv),
+            _ => Err(FileRejection::UnrecognizedContent),
+        }
+    }
+}
+
+fn is_macro_content_type(content_type: &str) -> bool {
+    content_type.contains("macroEnabled") || content_type.contains("macroenabled")
+}
+
+fn contains(haystack: &[u8], needle: &[u8]) -> bool {
+    haystack.windows(needle.len()).any(|window| window == needle)
+}
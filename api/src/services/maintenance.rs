@@ -0,0 +1,238 @@
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::handlers::uploads::chunk_key;
+use crate::services::storage::StorageBackend;
+use crate::utils::rehash_phone_number;
+
+/// How far ahead monthly transaction partitions are kept.
+pub const PARTITION_MONTHS_AHEAD: i32 = 3;
+
+/// Transactions rehashed per statement, and statements per run, so one run
+/// stays well inside the job's lock.
+const REHASH_BATCH: i64 = 1000;
+const REHASH_BATCHES_PER_RUN: usize = 200;
+
+/// Expired uploads cleaned up per run.
+const UPLOAD_CLEANUP_BATCH: i64 = 500;
+/// Uploads are left alone this long past expiry, so a chunk stored just
+/// before the upload expired isn't missed.
+const UPLOAD_CLEANUP_GRACE_SECS: i64 = 60 * 60;
+
+/// What a `rehash_transactions` run did.
+pub struct Rehashed {
+    pub rehashed: u64,
+    /// Stale copies of receipts the till already had in their current form,
+    /// deleted
+    pub merged: u64,
+    /// Rows left as they are because their pepper is no longer configured.
+    /// Lookups still find them.
+    pub skipped: u64,
+}
+
+pub struct MaintenanceService;
+
+impl MaintenanceService {
+    /// Creates transaction partitions from the current month through
+    /// `months_ahead` months out, so inserts never fall through to the
+    /// default partition. Returns how many partitions were created.
+    pub async fn ensure_transaction_partitions(db: &PgPool, months_ahead: i32) -> anyhow::Result<i32> {
+        let created: i32 = sqlx::query_scalar(
+            r#"
+            SELECT create_transaction_partitions(
+                date_trunc('month', NOW())::DATE,
+                (date_trunc('month', NOW()) + make_interval(months => $1 + 1))::DATE
+            )
+            "#,
+        )
+        .bind(months_ahead)
+        .fetch_one(db)
+        .await?;
+
+        Ok(created)
+    }
+
+    /// Brings stored receipt and counterparty hashes up to the newest
+    /// pepper, after the first deploy with peppers and after each rotation.
+    /// Until a row is rehashed, lookups match it through
+    /// `utils::phone_number_hashes`.
+    pub async fn rehash_transactions(db: &PgPool) -> anyhow::Result<Rehashed> {
+        let current = format!("{}:%", crate::utils::current_hash_key_id());
+        let mut outcome = Rehashed { rehashed: 0, merged: 0, skipped: 0 };
+        let mut after = Uuid::nil();
+
+        for _ in 0..REHASH_BATCHES_PER_RUN {
+            let rows: Vec<(Uuid, DateTime<Utc>, String, Option<String>)> = sqlx::query_as(
+                r#"
+                SELECT id, timestamp, reference, raw_data->>'counterparty_hash'
+                FROM transactions
+                WHERE id > $1 AND (reference NOT LIKE $2 OR raw_data->>'counterparty_hash' NOT LIKE $2)
+                ORDER BY id
+                LIMIT $3
+                "#,
+            )
+            .bind(after)
+            .bind(&current)
+            .bind(REHASH_BATCH)
+            .fetch_all(db)
+            .await?;
+            let Some(&(last, ..)) = rows.last() else {
+                break;
+            };
+            after = last;
+
+            let (mut ids, mut timestamps, mut references, mut counterparties) =
+                (Vec::new(), Vec::new(), Vec::new(), Vec::new());
+            for (id, timestamp, reference, counterparty) in rows {
+                let new_reference = rehash_phone_number(&reference);
+                let new_counterparty = counterparty.as_deref().and_then(rehash_phone_number);
+                if new_reference.is_none() && new_counterparty.is_none() {
+                    outcome.skipped += 1;
+                    continue;
+                }
+                ids.push(id);
+                timestamps.push(timestamp);
+                references.push(new_reference.unwrap_or(reference));
+                counterparties.push(new_counterparty);
+            }
+
+            let attempted = ids.len() as u64;
+            let updated = sqlx::query(
+                r#"
+                UPDATE transactions t
+                SET reference = u.reference,
+                    raw_data = CASE
+                        WHEN u.counterparty IS NULL THEN t.raw_data
+                        ELSE jsonb_set(t.raw_data, '{counterparty_hash}', to_jsonb(u.counterparty))
+                    END
+                FROM UNNEST($1::uuid[], $2::timestamptz[], $3::text[], $4::text[])
+                    AS u(id, timestamp, reference, counterparty)
+                WHERE t.id = u.id AND t.timestamp = u.timestamp
+                  AND (
+                      u.reference = t.reference
+                      OR NOT EXISTS (
+                          SELECT 1 FROM transaction_receipts r
+                          WHERE r.till_id = t.till_id AND r.reference = u.reference
+                      )
+                  )
+                "#,
+            )
+            .bind(&ids)
+            .bind(&timestamps)
+            .bind(&references)
+            .bind(&counterparties)
+            .execute(db)
+            .await?
+            .rows_affected();
+            let merged = Self::merge_stale_copies(db, &ids, &timestamps, &references).await?;
+            outcome.rehashed += updated;
+            outcome.merged += merged;
+            outcome.skipped += attempted - updated - merged;
+        }
+
+        Ok(outcome)
+    }
+
+    /// Deletes rows whose rehashed receipt the till already holds in its
+    /// current form: copies imported before dedup matched older hashes. A
+    /// dispute on the deleted copy moves to the one kept, and the monthly
+    /// aggregates stop counting it. Returns how many were deleted.
+    async fn merge_stale_copies(
+        db: &PgPool,
+        ids: &[Uuid],
+        timestamps: &[DateTime<Utc>],
+        references: &[String],
+    ) -> anyhow::Result<u64> {
+        let merged: i64 = sqlx::query_scalar(
+            r#"
+            WITH stale AS (
+                SELECT t.id, t.timestamp, t.till_id, t.source, t.amount, k.id AS kept_id, k.timestamp AS kept_timestamp
+                FROM transactions t
+                JOIN UNNEST($1::uuid[], $2::timestamptz[], $3::text[]) AS u(id, timestamp, reference)
+                    ON t.id = u.id AND t.timestamp = u.timestamp
+                JOIN transactions k ON k.till_id = t.till_id AND k.reference = u.reference AND k.id <> t.id
+                WHERE t.reference <> u.reference
+            ),
+            moved AS (
+                UPDATE disputes d
+                SET transaction_id = s.kept_id, transaction_timestamp = s.kept_timestamp
+                FROM stale s
+                WHERE d.transaction_id = s.id
+                  AND NOT EXISTS (SELECT 1 FROM disputes e WHERE e.transaction_id = s.kept_id)
+            ),
+            deleted AS (
+                DELETE FROM transactions t
+                USING stale s
+                WHERE t.id = s.id AND t.timestamp = s.timestamp
+                RETURNING t.till_id, t.timestamp, t.source, t.amount
+            ),
+            counted AS (
+                UPDATE till_monthly_aggregates a
+                SET transaction_count = a.transaction_count - d.rows,
+                    volume_cents = a.volume_cents - d.volume
+                FROM (
+                    SELECT till_id, date_trunc('month', timestamp AT TIME ZONE 'UTC')::DATE AS month, source,
+                           COUNT(*) AS rows, SUM(amount) AS volume
+                    FROM deleted
+                    GROUP BY 1, 2, 3
+                ) d
+                WHERE a.till_id = d.till_id AND a.month = d.month AND a.source = d.source
+            )
+            SELECT COUNT(*) FROM deleted
+            "#,
+        )
+        .bind(ids)
+        .bind(timestamps)
+        .bind(references)
+        .fetch_one(db)
+        .await?;
+        Ok(merged as u64)
+    }
+
+    /// Deletes the stored chunks of uploads past their expiry, finished or
+    /// abandoned, then the uploads themselves. An upload with chunks that
+    /// couldn't be deleted keeps those for the next run. Returns how many
+    /// uploads were removed.
+    pub async fn delete_expired_uploads(db: &PgPool, storage: &dyn StorageBackend) -> anyhow::Result<u64> {
+        let uploads: Vec<(Uuid, Vec<i32>)> = sqlx::query_as(
+            r#"
+            SELECT id, received_chunks
+            FROM upload_sessions
+            WHERE expires_at < NOW() - make_interval(secs => $1)
+            ORDER BY expires_at
+            LIMIT $2
+            "#,
+        )
+        .bind(UPLOAD_CLEANUP_GRACE_SECS as f64)
+        .bind(UPLOAD_CLEANUP_BATCH)
+        .fetch_all(db)
+        .await?;
+
+        let mut removed = 0;
+        for (upload_id, chunks) in uploads {
+            let mut remaining = Vec::new();
+            for n in chunks {
+                if let Err(e) = storage.delete(&chunk_key(upload_id, n)).await {
+                    tracing::warn!("Failed to delete chunk {} of expired upload {}: {}", n, upload_id, e);
+                    remaining.push(n);
+                }
+            }
+
+            if remaining.is_empty() {
+                sqlx::query("DELETE FROM upload_sessions WHERE id = $1")
+                    .bind(upload_id)
+                    .execute(db)
+                    .await?;
+                removed += 1;
+            } else {
+                sqlx::query("UPDATE upload_sessions SET received_chunks = $2 WHERE id = $1")
+                    .bind(upload_id)
+                    .bind(&remaining)
+                    .execute(db)
+                    .await?;
+            }
+        }
+        Ok(removed)
+    }
+}
@@ -0,0 +1,188 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+
+/// Volume bands from lowest to highest, as metrics name them.
+const VOLUME_RANGES: [&str; 5] = ["VeryLow", "Low", "Medium", "High", "VeryHigh"];
+/// Score buckets as (label, lowest score in it).
+const SCORE_BUCKETS: [(&str, i32); 5] = [("0-19", 0), ("20-39", 20), ("40-59", 40), ("60-79", 60), ("80-100", 80)];
+/// Snapshots older than this are deleted when a new one is stored.
+const SNAPSHOT_RETENTION_DAYS: i32 = 30;
+
+/// Aggregates over the latest live full proof of each business. Only
+/// segments of at least `min_group_size` businesses are included, and no
+/// smaller count within them is published.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MarketStats {
+    /// Absent when fewer than `min_group_size` businesses have proofs
+    pub overall: Option<SegmentStats>,
+    pub by_sector: Vec<SegmentStats>,
+    pub by_county: Vec<SegmentStats>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SegmentStats {
+    pub segment: String,
+    pub businesses: u32,
+    pub score_distribution: Vec<ScoreBucket>,
+    pub median_monthly_volume_range: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScoreBucket {
+    pub range: String,
+    /// None when withheld: fewer than `min_group_size` businesses but some,
+    /// or a count the withheld ones could be worked out from
+    pub businesses: Option<u32>,
+}
+
+/// One business's contribution.
+struct Sample {
+    sector: Option<String>,
+    county: Option<String>,
+    score: i32,
+    volume_range: Option<usize>,
+}
+
+pub struct MarketStatsService;
+
+impl MarketStatsService {
+    /// Recomputes the aggregates and stores them as the newest snapshot.
+    /// Threshold proofs, whose owners withheld the score, and proofs that
+    /// need lender consent are left out.
+    pub async fn refresh(db: &PgPool, min_group_size: u32) -> anyhow::Result<MarketStats> {
+        let rows = sqlx::query(
+            r#"
+            SELECT DISTINCT ON (ps.user_id)
+                   bp.sector, bp.county, ps.credit_score, ps.metrics->>'monthly_volume_range'
+            FROM proof_sessions ps
+            LEFT JOIN business_profiles bp ON bp.user_id = ps.user_id
+            WHERE ps.status = 'completed' AND ps.proof_type = 'full' AND ps.credit_score IS NOT NULL
+              AND ps.expires_at > NOW()
+              AND NOT COALESCE((ps.disclosure_policy->>'lender_consent')::boolean, false)
+            ORDER BY ps.user_id, ps.created_at DESC
+            "#,
+        )
+        .fetch_all(db)
+        .await?;
+
+        let samples = rows
+            .iter()
+            .map(|row| {
+                let volume_range: Option<String> = row.try_get(3)?;
+                Ok(Sample {
+                    sector: row.try_get(0)?,
+                    county: row.try_get(1)?,
+                    score: row.try_get(2)?,
+                    volume_range: volume_range.and_then(|r| VOLUME_RANGES.iter().position(|v| *v == r)),
+                })
+            })
+            .collect::<Result<Vec<_>, sqlx::Error>>()?;
+
+        let stats = MarketStats {
+            overall: segment_stats("all", &samples.iter().collect::<Vec<_>>(), min_group_size),
+            by_sector: group_stats(&samples, |s| s.sector.as_deref(), min_group_size),
+            by_county: group_stats(&samples, |s| s.county.as_deref(), min_group_size),
+        };
+
+        sqlx::query("INSERT INTO market_stats_snapshots (min_group_size, stats) VALUES ($1, $2)")
+            .bind(min_group_size as i32)
+            .bind(serde_json::to_value(&stats)?)
+            .execute(db)
+            .await?;
+        sqlx::query("DELETE FROM market_stats_snapshots WHERE computed_at < NOW() - make_interval(days => $1)")
+            .bind(SNAPSHOT_RETENTION_DAYS)
+            .execute(db)
+            .await?;
+
+        Ok(stats)
+    }
+
+    /// The newest snapshot, with when it was computed and its group size.
+    pub async fn latest(db: &PgPool) -> anyhow::Result<Option<(MarketStats, DateTime<Utc>, i32)>> {
+        let row = sqlx::query(
+            "SELECT stats, computed_at, min_group_size FROM market_stats_snapshots ORDER BY computed_at DESC LIMIT 1",
+        )
+        .fetch_optional(db)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(None);
+        };
+        let stats: MarketStats = serde_json::from_value(row.try_get(0)?)?;
+        Ok(Some((stats, row.try_get(1)?, row.try_get(2)?)))
+    }
+}
+
+/// Stats for each value of `key` shared by enough businesses, by name.
+fn group_stats(
+    samples: &[Sample],
+    key: impl Fn(&Sample) -> Option<&str>,
+    min_group_size: u32,
+) -> Vec<SegmentStats> {
+    let mut groups: BTreeMap<String, Vec<&Sample>> = BTreeMap::new();
+    for sample in samples {
+        if let Some(value) = key(sample).map(str::trim).filter(|v| !v.is_empty()) {
+            groups.entry(value.to_string()).or_default().push(sample);
+        }
+    }
+    groups
+        .iter()
+        .filter_map(|(segment, members)| segment_stats(segment, members, min_group_size))
+        .collect()
+}
+
+fn segment_stats(segment: &str, members: &[&Sample], min_group_size: u32) -> Option<SegmentStats> {
+    let businesses = members.len() as u32;
+    if businesses < min_group_size.max(1) {
+        return None;
+    }
+
+    let mut counts: Vec<Option<u32>> = SCORE_BUCKETS
+        .iter()
+        .enumerate()
+        .map(|(i, (_, lowest))| {
+            let next = SCORE_BUCKETS.get(i + 1).map_or(i32::MAX, |(_, lowest)| *lowest);
+            Some(members.iter().filter(|s| (*lowest..next).contains(&s.score)).count() as u32)
+        })
+        .collect();
+    for count in counts.iter_mut() {
+        if count.is_some_and(|c| c > 0 && c < min_group_size) {
+            *count = None;
+        }
+    }
+    // A lone withheld count is the segment's total less the others, so the
+    // smallest published one goes too
+    if counts.iter().filter(|c| c.is_none()).count() == 1 {
+        if let Some(smallest) = counts
+            .iter_mut()
+            .filter(|c| c.is_some_and(|c| c > 0))
+            .min_by_key(|c| c.unwrap_or(u32::MAX))
+        {
+            *smallest = None;
+        }
+    }
+    let score_distribution = SCORE_BUCKETS
+        .iter()
+        .zip(counts)
+        .map(|((range, _), businesses)| ScoreBucket {
+            range: range.to_string(),
+            businesses,
+        })
+        .collect();
+
+    let mut volume_ranges: Vec<usize> = members.iter().filter_map(|s| s.volume_range).collect();
+    volume_ranges.sort_unstable();
+    let median_monthly_volume_range = volume_ranges
+        .get(volume_ranges.len().saturating_sub(1) / 2)
+        .map(|&i| VOLUME_RANGES[i].to_string());
+
+    Some(SegmentStats {
+        segment: segment.to_string(),
+        businesses,
+        score_distribution,
+        median_monthly_volume_range,
+    })
+}
@@ -1,7 +1,33 @@
+pub mod api_keys;
 pub mod auth;
+pub mod authenticity;
+pub mod billing;
+pub mod captcha;
 pub mod daraja;
+pub mod dispute;
+pub mod email;
+pub mod expiry_reminder;
+pub mod feature_flags;
+pub mod fcm;
+pub mod file_scan;
+pub mod maintenance;
+pub mod market_stats;
+pub mod money;
+pub mod notification;
+pub mod ocr;
+pub mod outbox;
+pub mod payments;
 pub mod proof;
+pub mod proof_queue;
+pub mod sandbox;
+pub mod scoring_experiments;
+pub mod security;
+pub mod simulation;
+pub mod simulator;
+pub mod sms_import;
+pub mod statement_pull;
 pub mod storage;
+pub mod webhook;
 
 
 
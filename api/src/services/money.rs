@@ -0,0 +1,230 @@
+/// An amount read from a statement, in minor units, with the currency it
+/// was written in when it said.
+#[derive(Debug, PartialEq, Eq)]
+pub struct Money {
+    pub minor_units: i64,
+    /// ISO 4217 code of an explicit prefix or suffix such as "Ksh" or "KES"
+    pub currency: Option<String>,
+}
+
+/// Local spellings of currency codes seen on statements.
+const CURRENCY_ALIASES: [(&str, &str); 3] = [("KSH", "KES"), ("KSHS", "KES"), ("USH", "UGX")];
+
+/// Parses a major-unit amount as statements write it: "1,234.50",
+/// "1.234,50", "12 345,67", "(1,200.00)" or "1,200.00-" for negatives,
+/// "500.00 CR" and "500.00 DR" for credits and debits, and "Ksh1,500" or
+/// "KES 1,500" with the currency spelt out. The result is in minor units of
+/// a currency with `minor_unit_exponent` decimal places; more decimals than
+/// that are rejected rather than rounded.
+pub fn parse_money(text: &str, minor_unit_exponent: u32) -> Result<Money, String> {
+    let unreadable = || format!("Unable to parse amount: {}", text);
+    let mut rest = text.trim();
+    let mut negative = false;
+
+    if let Some(inner) = rest.strip_prefix('(').and_then(|r| r.strip_suffix(')')) {
+        negative = true;
+        rest = inner.trim();
+    }
+
+    if let Some((amount, side)) = rest.len().checked_sub(2).and_then(|i| rest.split_at_checked(i)) {
+        if side.eq_ignore_ascii_case("DR") {
+            negative = !negative;
+            rest = amount.trim_end();
+        } else if side.eq_ignore_ascii_case("CR") {
+            rest = amount.trim_end();
+        }
+    }
+
+    // The sign may come before or after a currency code, but only once
+    let mut sign = |rest: &mut &str| {
+        if let Some(r) = rest.strip_prefix('-').or_else(|| rest.strip_suffix('-')) {
+            negative = !negative;
+            *rest = r.trim();
+            true
+        } else if let Some(r) = rest.strip_prefix('+') {
+            *rest = r.trim();
+            true
+        } else {
+            false
+        }
+    };
+    let signed = sign(&mut rest);
+    let currency = take_currency(&mut rest);
+    if !signed && currency.is_some() {
+        sign(&mut rest);
+    }
+
+    let digits = decimal_digits(rest, minor_unit_exponent).ok_or_else(unreadable)?;
+    let minor_units: i64 = digits.parse().map_err(|_| unreadable())?;
+    Ok(Money {
+        minor_units: if negative { -minor_units } else { minor_units },
+        currency,
+    })
+}
+
+/// Strips a currency code written before or after the amount, such as
+/// "Ksh." or "USD", and returns it as an ISO 4217 code.
+fn take_currency(rest: &mut &str) -> Option<String> {
+    let is_code = |c: char| c.is_ascii_alphabetic() || c == '.';
+    let prefix_len = rest.find(|c: char| !is_code(c)).unwrap_or(rest.len());
+    let (code, remainder) = if prefix_len > 0 {
+        (&rest[..prefix_len], &rest[prefix_len..])
+    } else {
+        let suffix_start = rest.rfind(|c: char| !is_code(c)).map_or(0, |i| i + 1);
+        (&rest[suffix_start..], &rest[..suffix_start])
+    };
+
+    let code = code.trim_matches('.').to_ascii_uppercase();
+    if code.len() < 3 || !code.chars().all(|c| c.is_ascii_alphabetic()) {
+        return None;
+    }
+    *rest = remainder.trim();
+    let code = CURRENCY_ALIASES
+        .iter()
+        .find(|(alias, _)| *alias == code)
+        .map_or(code.clone(), |(_, iso)| iso.to_string());
+    Some(code)
+}
+
+/// The amount's digits scaled to minor units, with its thousands and
+/// decimal separators worked out: when both "." and "," appear the last is
+/// the decimal point, and a lone separator followed by exactly three digits
+/// groups thousands.
+fn decimal_digits(amount: &str, minor_unit_exponent: u32) -> Option<String> {
+    let amount: String = amount
+        .chars()
+        .filter(|c| !matches!(c, ' ' | '\u{a0}' | '\''))
+        .collect();
+    // Separators alone, such as ".", are not an amount
+    if !amount.chars().any(|c| c.is_ascii_digit())
+        || !amount.chars().all(|c| c.is_ascii_digit() || c == '.' || c == ',')
+    {
+        return None;
+    }
+
+    let last_separator = amount.rfind(['.', ',']);
+    let decimal_point = last_separator.filter(|&i| {
+        let separator = amount.as_bytes()[i] as char;
+        let other = if separator == '.' { ',' } else { '.' };
+        amount.contains(other) || (amount.matches(separator).count() == 1 && amount.len() - i - 1 != 3)
+    });
+
+    let (whole, fraction) = match decimal_point {
+        Some(i) => (&amount[..i], &amount[i + 1..]),
+        None => (amount.as_str(), ""),
+    };
+    if fraction.len() > minor_unit_exponent as usize || fraction.contains(['.', ',']) {
+        return None;
+    }
+
+    // Every group after the first separator must be three digits
+    let mut groups = whole.split(['.', ',']);
+    let first = groups.next()?;
+    if first.is_empty() && !whole.is_empty() {
+        return None;
+    }
+    let mut digits = first.to_string();
+    for group in groups {
+        if group.len() != 3 {
+            return None;
+        }
+        digits.push_str(group);
+    }
+    if digits.is_empty() {
+        digits.push('0');
+    }
+
+    digits.push_str(fraction);
+    digits.push_str(&"0".repeat(minor_unit_exponent as usize - fraction.len()));
+    Some(digits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kes(text: &str) -> Result<i64, String> {
+        parse_money(text, 2).map(|m| m.minor_units)
+    }
+
+    #[test]
+    fn reads_mpesa_statement_amounts() {
+        // Paid In, Withdrawn and Balance columns of an M-PESA statement
+        assert_eq!(kes("1,500.00"), Ok(150_000));
+        assert_eq!(kes("-2,350.00"), Ok(-235_000));
+        assert_eq!(kes("12,340.50"), Ok(1_234_050));
+        assert_eq!(kes("0.00"), Ok(0));
+        assert_eq!(kes("45"), Ok(4_500));
+        assert_eq!(kes("1,234,567.8"), Ok(123_456_780));
+        assert_eq!(kes(" 750.00 "), Ok(75_000));
+    }
+
+    #[test]
+    fn reads_european_and_spaced_separators() {
+        assert_eq!(kes("1.234,50"), Ok(123_450));
+        assert_eq!(kes("1.234.567,89"), Ok(123_456_789));
+        assert_eq!(kes("12 345,67"), Ok(1_234_567));
+        assert_eq!(kes("12\u{a0}345.67"), Ok(1_234_567));
+        assert_eq!(kes("250,5"), Ok(25_050));
+    }
+
+    #[test]
+    fn lone_separator_before_three_digits_groups_thousands() {
+        assert_eq!(kes("1,200"), Ok(120_000));
+        assert_eq!(kes("1.200"), Ok(120_000));
+    }
+
+    #[test]
+    fn reads_negative_and_credit_debit_markers() {
+        assert_eq!(kes("(1,200)"), Ok(-120_000));
+        assert_eq!(kes("(1,200.00)"), Ok(-120_000));
+        assert_eq!(kes("1,200.00-"), Ok(-120_000));
+        assert_eq!(kes("500.00 CR"), Ok(50_000));
+        assert_eq!(kes("500.00 DR"), Ok(-50_000));
+        assert_eq!(kes("500.00dr"), Ok(-50_000));
+        assert_eq!(kes("+30.00"), Ok(3_000));
+    }
+
+    #[test]
+    fn reads_currency_prefixes_and_suffixes() {
+        let ksh = |minor_units| {
+            Ok(Money {
+                minor_units,
+                currency: Some("KES".to_string()),
+            })
+        };
+        assert_eq!(parse_money("Ksh1,500.00", 2), ksh(150_000));
+        assert_eq!(parse_money("Ksh. 1,500.00", 2), ksh(150_000));
+        assert_eq!(parse_money("KES 2,000", 2), ksh(200_000));
+        assert_eq!(parse_money("-KES 75.00", 2), ksh(-7_500));
+        assert_eq!(parse_money("KES -75.00", 2), ksh(-7_500));
+        assert_eq!(parse_money("(KES 75.00)", 2), ksh(-7_500));
+        assert_eq!(parse_money("2,000.00 KES", 2), ksh(200_000));
+        assert_eq!(
+            parse_money("UGX 15,000", 0),
+            Ok(Money {
+                minor_units: 15_000,
+                currency: Some("UGX".to_string()),
+            })
+        );
+        assert_eq!(parse_money("1,500.00", 2).unwrap().currency, None);
+    }
+
+    #[test]
+    fn scales_to_the_currency_exponent() {
+        assert_eq!(parse_money("15,000", 0).map(|m| m.minor_units), Ok(15_000));
+        assert_eq!(parse_money("1.5", 3).map(|m| m.minor_units), Ok(1_500));
+    }
+
+    #[test]
+    fn rejects_what_it_cannot_read_exactly() {
+        for text in ["", "abc", "KES", "1.2.3,4", "1,23.45", "1.5.00", "1,2,3", "--5", "(5", "5 5 CR DR"] {
+            assert!(kes(text).is_err(), "{:?} should not parse", text);
+        }
+        for text in [".", ",", "-.", "KES ."] {
+            assert!(kes(text).is_err(), "{:?} has no digits", text);
+        }
+        assert!(parse_money("15.5", 0).is_err());
+        assert!(kes("99999999999999999999").is_err());
+    }
+}
@@ -0,0 +1,377 @@
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::repos::notifications::{HeldNotification, NotificationPreferences};
+use crate::db::repos::NotificationRepo;
+use crate::i18n::{Locale, Message};
+use crate::services::auth::AuthService;
+use crate::services::email::EmailService;
+use crate::services::fcm::{Delivery, FcmService};
+
+/// Lender-facing emails. Each variant is one template.
+pub enum LenderNotification<'a> {
+    ApiKeyIssued { key_prefix: &'a str, sandbox: bool },
+    ApiKeyRevoked { key_prefix: &'a str },
+    VerificationCompleted { verification_code: &'a str, status: &'a str },
+    MonthlyUsageReport { month: &'a str, total: i64, valid: i64, invalid: i64 },
+}
+
+impl LenderNotification<'_> {
+    pub fn subject(&self) -> String {
+        match self {
+            LenderNotification::ApiKeyIssued { sandbox: true, .. } => "New sandbox API key issued".to_string(),
+            LenderNotification::ApiKeyIssued { sandbox: false, .. } => "New API key issued".to_string(),
+            LenderNotification::ApiKeyRevoked { .. } => "API key revoked".to_string(),
+            LenderNotification::VerificationCompleted { verification_code, .. } => {
+                format!("Verification completed: {}", verification_code)
+            }
+            LenderNotification::MonthlyUsageReport { month, .. } => format!("Verification usage for {}", month),
+        }
+    }
+
+    pub fn body(&self, lender_name: &str) -> String {
+        let details = match self {
+            LenderNotification::ApiKeyIssued { key_prefix, sandbox } => format!(
+                "A new {} API key starting with {} was issued for your account.\n\
+                 If you did not request it, contact us so it can be revoked.",
+                if *sandbox { "sandbox" } else { "live" },
+                key_prefix
+            ),
+            LenderNotification::ApiKeyRevoked { key_prefix } => format!(
+                "The API key starting with {} was revoked and no longer works.\n\
+                 If you did not request it, contact us.",
+                key_prefix
+            ),
+            LenderNotification::VerificationCompleted { verification_code, status } => format!(
+                "Your verification of proof {} completed with status: {}.",
+                verification_code, status
+            ),
+            LenderNotification::MonthlyUsageReport { month, total, valid, invalid } => format!(
+                "Verifications in {}: {}\n  Valid: {}\n  Invalid: {}\n  Other (expired, revoked, not found): {}",
+                month,
+                total,
+                valid,
+                invalid,
+                total - valid - invalid
+            ),
+        };
+
+        format!("Hello {},\n\n{}\n\nM-Pesa Credit Proof", lender_name, details)
+    }
+}
+
+/// A notification to a business owner about one of their proofs.
+pub struct OwnerNotification<'a> {
+    /// What happened, e.g. "proof.completed", so the app can open the
+    /// right screen when the push is tapped
+    pub event: &'static str,
+    pub session_id: Uuid,
+    pub message: Message<'a>,
+    /// Whether it's worth a text; push and email carry every notification
+    pub by_sms: bool,
+}
+
+/// Held notifications sent per query.
+const RELEASE_BATCH: i64 = 100;
+/// A held notification that couldn't be sent is tried again after this.
+const RELEASE_RETRY_SECS: i64 = 10 * 60;
+/// Events sent whatever the hour: a consent request expires, and the lender
+/// is waiting on the answer.
+const UNHELD_EVENTS: [&str; 1] = ["consent.requested"];
+
+/// When the owner's quiet hours end, if `now` falls within them.
+fn quiet_until(preferences: &NotificationPreferences, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let (start, end) = (preferences.quiet_hours_start?, preferences.quiet_hours_end?);
+    let tz: chrono_tz::Tz = preferences.timezone.parse().ok()?;
+    let local = now.with_timezone(&tz).naive_local();
+    let time = local.time();
+    // A window such as 22:00-07:00 spans midnight
+    let quiet = if start <= end {
+        start <= time && time < end
+    } else {
+        time >= start || time < end
+    };
+    if !quiet {
+        return None;
+    }
+    let date = if time >= end { local.date().succ_opt()? } else { local.date() };
+    crate::utils::local_to_utc(date.and_time(end), tz)
+}
+
+pub struct NotificationService;
+
+impl NotificationService {
+    /// Emails a lender's contact address. Lenders without one, or a server
+    /// without SMTP configured, are skipped.
+    pub async fn notify_lender(
+        db: &PgPool,
+        config: &Config,
+        lender_id: Uuid,
+        notification: LenderNotification<'_>,
+    ) -> anyhow::Result<()> {
+        if !EmailService::is_enabled(config) {
+            return Ok(());
+        }
+
+        let row = sqlx::query("SELECT name, contact_email FROM lenders WHERE id = $1")
+            .bind(lender_id)
+            .fetch_optional(db)
+            .await?;
+
+        let Some(row) = row else {
+            return Ok(());
+        };
+        let name: String = row.try_get(0)?;
+        let Some(contact_email) = row.try_get::<Option<String>, _>(1)? else {
+            return Ok(());
+        };
+
+        EmailService::send(config, &contact_email, &notification.subject(), &notification.body(&name)).await
+    }
+
+    /// Notifies the owner on each channel they've enabled, or holds the
+    /// notification until their quiet hours end. A channel that fails is
+    /// logged and doesn't stop the others.
+    pub async fn notify_owner(
+        db: &PgPool,
+        config: &Config,
+        user_id: Uuid,
+        locale: Locale,
+        notification: OwnerNotification<'_>,
+    ) -> anyhow::Result<()> {
+        let preferences = NotificationRepo::preferences(db, user_id).await?;
+        let rendered = HeldNotification {
+            user_id,
+            event: notification.event.to_string(),
+            session_id: notification.session_id,
+            title: Message::PushTitle.render(locale),
+            body: notification.message.render(locale),
+            by_sms: notification.by_sms,
+        };
+        if !UNHELD_EVENTS.contains(&notification.event) {
+            if let Some(until) = quiet_until(&preferences, Utc::now()) {
+                NotificationRepo::hold(db, &rendered, until).await?;
+                return Ok(());
+            }
+        }
+        Self::deliver(db, config, &preferences, &rendered).await
+    }
+
+    /// Sends the notifications held through quiet hours that have since
+    /// ended, on the channels their owners have enabled now. Each is deleted
+    /// once sent; one that fails is logged and tried again on a later run.
+    /// Returns how many were sent.
+    pub async fn release_held(db: &PgPool, config: &Config) -> anyhow::Result<usize> {
+        let mut sent = 0;
+        loop {
+            let due = NotificationRepo::take_due(db, RELEASE_BATCH, RELEASE_RETRY_SECS).await?;
+            for held in &due {
+                let notification = &held.notification;
+                let delivered = match NotificationRepo::preferences(db, notification.user_id).await {
+                    Ok(preferences) => Self::deliver(db, config, &preferences, notification).await,
+                    Err(e) => Err(e.into()),
+                };
+                match delivered {
+                    Ok(()) => {
+                        NotificationRepo::delete_held(db, held.id).await?;
+                        sent += 1;
+                    }
+                    Err(e) => tracing::error!(
+                        "Failed to send held {} to user {}: {}",
+                        notification.event,
+                        notification.user_id,
+                        e
+                    ),
+                }
+            }
+            if (due.len() as i64) < RELEASE_BATCH {
+                return Ok(sent);
+            }
+        }
+    }
+
+    async fn deliver(
+        db: &PgPool,
+        config: &Config,
+        preferences: &NotificationPreferences,
+        notification: &HeldNotification,
+    ) -> anyhow::Result<()> {
+        let (event, user_id) = (&notification.event, notification.user_id);
+
+        if notification.by_sms && preferences.sms {
+            let phone_number: String = sqlx::query_scalar("SELECT phone_number FROM users WHERE id = $1")
+                .bind(user_id)
+                .fetch_one(db)
+                .await?;
+            if let Err(e) = AuthService::send_sms(
+                &config.africa_talking_api_key,
+                &config.africa_talking_username,
+                &phone_number,
+                &notification.body,
+            )
+            .await
+            {
+                tracing::error!("Failed to text {} to user {}: {}", event, user_id, e);
+            }
+        }
+
+        if preferences.push {
+            Self::push_owner(db, config, notification).await?;
+        }
+
+        if let (true, Some(address), Some(_)) =
+            (preferences.email, preferences.email_address.as_deref(), preferences.email_verified_at)
+        {
+            if EmailService::is_enabled(config) {
+                if let Err(e) = EmailService::send(config, address, &notification.title, &notification.body).await {
+                    tracing::error!("Failed to email {} to user {}: {}", event, user_id, e);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Pushes to every device the owner registered. Tokens FCM no longer
+    /// recognises are dropped. A server without FCM configured, or an owner
+    /// without devices, is skipped.
+    async fn push_owner(db: &PgPool, config: &Config, notification: &HeldNotification) -> anyhow::Result<()> {
+        if !FcmService::is_enabled(config) {
+            return Ok(());
+        }
+
+        let user_id = notification.user_id;
+        let tokens: Vec<String> = sqlx::query_scalar("SELECT token FROM user_devices WHERE user_id = $1")
+            .bind(user_id)
+            .fetch_all(db)
+            .await?;
+        if tokens.is_empty() {
+            return Ok(());
+        }
+
+        let access_token = match FcmService::access_token(config).await {
+            Ok(access_token) => access_token,
+            Err(e) => {
+                tracing::error!("Failed to push {} to user {}: {}", notification.event, user_id, e);
+                return Ok(());
+            }
+        };
+        let (title, body) = (&notification.title, &notification.body);
+        let data = BTreeMap::from([
+            ("event", notification.event.clone()),
+            ("session_id", notification.session_id.to_string()),
+        ]);
+
+        for token in tokens {
+            match FcmService::send(config, &access_token, &token, title, body, &data).await {
+                Ok(Delivery::Sent) => {}
+                Ok(Delivery::Unregistered) => {
+                    sqlx::query("DELETE FROM user_devices WHERE token = $1")
+                        .bind(&token)
+                        .execute(db)
+                        .await?;
+                }
+                Err(e) => {
+                    tracing::error!("Failed to push {} to a device of user {}: {}", notification.event, user_id, e)
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tells the owner of a proof that a lender just verified it, so a
+    /// verification they didn't expect doesn't go unnoticed.
+    pub async fn alert_owner_of_verification(
+        db: &PgPool,
+        config: &Config,
+        lender_id: Uuid,
+        verification_code: &str,
+    ) -> anyhow::Result<()> {
+        let row = sqlx::query(
+            r#"
+            SELECT ps.id, ps.user_id, u.preferred_language, l.name
+            FROM proof_sessions ps
+            JOIN users u ON u.id = ps.user_id
+            JOIN lenders l ON l.id = $2
+            WHERE ps.verification_code = $1
+            "#,
+        )
+        .bind(verification_code)
+        .bind(lender_id)
+        .fetch_optional(db)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(());
+        };
+        let lender: String = row.try_get(3)?;
+        let notification = OwnerNotification {
+            event: "proof.verified",
+            session_id: row.try_get(0)?,
+            message: Message::ProofVerifiedPush { lender: &lender, verification_code },
+            by_sms: false,
+        };
+        let locale = Locale::from_code(&row.try_get::<String, _>(2)?).unwrap_or_default();
+
+        Self::notify_owner(db, config, row.try_get(1)?, locale, notification).await
+    }
+
+    /// Sends every lender with a contact address its usage for the month
+    /// starting at `month_start`. Returns how many reports were sent, and
+    /// how many were skipped because SMTP isn't configured.
+    pub async fn send_monthly_usage_reports(
+        db: &PgPool,
+        config: &Config,
+        month_start: chrono::NaiveDate,
+    ) -> anyhow::Result<(usize, usize)> {
+        let month_end = month_start
+            .checked_add_months(chrono::Months::new(1))
+            .ok_or_else(|| anyhow::anyhow!("Invalid report month"))?;
+
+        let rows = sqlx::query(
+            r#"
+            SELECT l.id,
+                   COUNT(v.id),
+                   COUNT(v.id) FILTER (WHERE v.status = 'valid'),
+                   COUNT(v.id) FILTER (WHERE v.status = 'invalid')
+            FROM lenders l
+            LEFT JOIN lender_verifications v
+                ON v.lender_id = l.id AND v.created_at >= $1 AND v.created_at < $2
+            WHERE l.contact_email IS NOT NULL
+            GROUP BY l.id
+            "#,
+        )
+        .bind(month_start.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .bind(month_end.and_hms_opt(0, 0, 0).unwrap().and_utc())
+        .fetch_all(db)
+        .await?;
+
+        if !EmailService::is_enabled(config) {
+            tracing::warn!("Skipping {} usage reports: SMTP_HOST is not configured", rows.len());
+            return Ok((0, rows.len()));
+        }
+
+        let month = month_start.format("%B %Y").to_string();
+        let mut sent = 0;
+        for row in rows {
+            let lender_id: Uuid = row.try_get(0)?;
+            let notification = LenderNotification::MonthlyUsageReport {
+                month: &month,
+                total: row.try_get(1)?,
+                valid: row.try_get(2)?,
+                invalid: row.try_get(3)?,
+            };
+            match Self::notify_lender(db, config, lender_id, notification).await {
+                Ok(()) => sent += 1,
+                Err(e) => tracing::error!("Failed to send usage report to lender {}: {}", lender_id, e),
+            }
+        }
+
+        Ok((sent, 0))
+    }
+}
@@ -0,0 +1,353 @@
+use std::collections::BTreeMap;
+use std::process::Stdio;
+use std::sync::OnceLock;
+
+use async_trait::async_trait;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::Regex;
+use reqwest::Client;
+use serde::Deserialize;
+use tokio::io::AsyncWriteExt;
+
+use crate::config::Config;
+use crate::services::money::parse_money;
+
+/// Engines `OCR_ENGINE` may name.
+pub const ENGINES: [&str; 2] = ["tesseract", "vision"];
+
+const VISION_URL: &str = "https://vision.googleapis.com/v1/images:annotate";
+const OCR_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(60);
+/// Longest `transaction_type` stored for a row read from a photo.
+const MAX_DETAILS_LEN: usize = 50;
+
+/// A line of text read from an image.
+pub struct OcrLine {
+    pub text: String,
+    /// 0-100: how sure the engine is of the line's least certain word
+    pub confidence: u8,
+}
+
+/// Reads the text of a photographed statement, line by line.
+#[async_trait]
+pub trait OcrEngine: Send + Sync {
+    /// Name recorded against each image, e.g. "tesseract"
+    fn name(&self) -> &'static str;
+    async fn recognize(&self, image: &[u8]) -> anyhow::Result<Vec<OcrLine>>;
+}
+
+/// The `tesseract` command line tool, which must be installed on the host.
+pub struct TesseractOcr {
+    binary: String,
+}
+
+#[async_trait]
+impl OcrEngine for TesseractOcr {
+    fn name(&self) -> &'static str {
+        "tesseract"
+    }
+
+    /// Runs `tesseract stdin stdout tsv`, whose output has a row per word
+    /// with its line and confidence.
+    async fn recognize(&self, image: &[u8]) -> anyhow::Result<Vec<OcrLine>> {
+        let mut child = tokio::process::Command::new(&self.binary)
+            .args(["stdin", "stdout", "tsv"])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()?;
+        let mut stdin = child.stdin.take().ok_or_else(|| anyhow::anyhow!("tesseract stdin unavailable"))?;
+        stdin.write_all(image).await?;
+        drop(stdin);
+
+        let output = tokio::time::timeout(OCR_TIMEOUT, child.wait_with_output())
+            .await
+            .map_err(|_| anyhow::anyhow!("tesseract timed out"))??;
+        if !output.status.success() {
+            anyhow::bail!("tesseract failed: {}", String::from_utf8_lossy(&output.stderr).trim());
+        }
+
+        // Columns: level, page, block, paragraph, line, word, left, top,
+        // width, height, conf, text. Words are level 5.
+        let mut lines: BTreeMap<(u32, u32, u32, u32), OcrLine> = BTreeMap::new();
+        for row in String::from_utf8_lossy(&output.stdout).lines().skip(1) {
+            let columns: Vec<&str> = row.split('\t').collect();
+            if columns.len() < 12 || columns[0] != "5" || columns[11].trim().is_empty() {
+                continue;
+            }
+            let number = |i: usize| columns[i].parse::<u32>().unwrap_or(0);
+            let confidence = columns[10].parse::<f32>().unwrap_or(0.0).clamp(0.0, 100.0) as u8;
+            let line = lines.entry((number(1), number(2), number(3), number(4))).or_insert(OcrLine {
+                text: String::new(),
+                confidence: 100,
+            });
+            if !line.text.is_empty() {
+                line.text.push(' ');
+            }
+            line.text.push_str(columns[11].trim());
+            line.confidence = line.confidence.min(confidence);
+        }
+        Ok(lines.into_values().collect())
+    }
+}
+
+/// Google Cloud Vision's document text detection.
+pub struct VisionOcr {
+    api_key: String,
+}
+
+#[derive(Deserialize)]
+struct VisionResponse {
+    responses: Vec<VisionResult>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VisionResult {
+    full_text_annotation: Option<VisionText>,
+    error: Option<VisionError>,
+}
+
+#[derive(Deserialize)]
+struct VisionError {
+    message: String,
+}
+
+#[derive(Deserialize)]
+struct VisionText {
+    #[serde(default)]
+    pages: Vec<VisionPage>,
+}
+
+#[derive(Deserialize)]
+struct VisionPage {
+    #[serde(default)]
+    blocks: Vec<VisionBlock>,
+}
+
+#[derive(Deserialize)]
+struct VisionBlock {
+    #[serde(default)]
+    paragraphs: Vec<VisionParagraph>,
+}
+
+#[derive(Deserialize)]
+struct VisionParagraph {
+    #[serde(default)]
+    words: Vec<VisionWord>,
+}
+
+#[derive(Deserialize)]
+struct VisionWord {
+    #[serde(default)]
+    confidence: f32,
+    #[serde(default)]
+    symbols: Vec<VisionSymbol>,
+}
+
+#[derive(Deserialize)]
+struct VisionSymbol {
+    text: String,
+    property: Option<VisionProperty>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct VisionProperty {
+    detected_break: Option<VisionBreak>,
+}
+
+#[derive(Deserialize)]
+struct VisionBreak {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[async_trait]
+impl OcrEngine for VisionOcr {
+    fn name(&self) -> &'static str {
+        "vision"
+    }
+
+    async fn recognize(&self, image: &[u8]) -> anyhow::Result<Vec<OcrLine>> {
+        use base64::Engine;
+
+        let response: VisionResponse = Client::new()
+            .post(VISION_URL)
+            // In a header, so the key stays out of URLs that get logged
+            .header("x-goog-api-key", &self.api_key)
+            .timeout(OCR_TIMEOUT)
+            .json(&serde_json::json!({
+                "requests": [{
+                    "image": { "content": base64::engine::general_purpose::STANDARD.encode(image) },
+                    "features": [{ "type": "DOCUMENT_TEXT_DETECTION" }],
+                }]
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let result = response
+            .responses
+            .into_iter()
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("Vision returned no result"))?;
+        if let Some(error) = result.error {
+            anyhow::bail!("Vision failed: {}", error.message);
+        }
+
+        // Words carry the break that follows them; a line ends at an
+        // end-of-line break
+        let mut lines = Vec::new();
+        let mut current = OcrLine {
+            text: String::new(),
+            confidence: 100,
+        };
+        let words = result
+            .full_text_annotation
+            .into_iter()
+            .flat_map(|text| text.pages)
+            .flat_map(|page| page.blocks)
+            .flat_map(|block| block.paragraphs)
+            .flat_map(|paragraph| paragraph.words);
+        for word in words {
+            current.confidence = current.confidence.min((word.confidence.clamp(0.0, 1.0) * 100.0) as u8);
+            for symbol in word.symbols {
+                current.text.push_str(&symbol.text);
+                match symbol.property.and_then(|p| p.detected_break).map(|b| b.kind) {
+                    Some(kind) if kind == "EOL_SURE_SPACE" || kind == "LINE_BREAK" => {
+                        lines.push(std::mem::replace(
+                            &mut current,
+                            OcrLine {
+                                text: String::new(),
+                                confidence: 100,
+                            },
+                        ));
+                    }
+                    Some(_) => current.text.push(' '),
+                    None => {}
+                }
+            }
+        }
+        if !current.text.trim().is_empty() {
+            lines.push(current);
+        }
+        Ok(lines)
+    }
+}
+
+/// A statement row read from a line of a photo.
+pub struct StatementRow {
+    /// M-Pesa receipt number
+    pub receipt: String,
+    pub timestamp: DateTime<Utc>,
+    /// The row's description, used as its transaction type
+    pub details: String,
+    /// Minor units, negative when money went out
+    pub amount: i64,
+    /// Running balance printed after the amount
+    pub balance: Option<i64>,
+}
+
+struct Patterns {
+    receipt: Regex,
+    time: Regex,
+    money: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        receipt: Regex::new(r"^\s*([A-Z0-9]{10})\b").unwrap(),
+        time: Regex::new(r"(\d{4}-\d{2}-\d{2}|\d{1,2}/\d{1,2}/\d{4})\s+(\d{1,2}:\d{2}(?::\d{2})?)").unwrap(),
+        money: Regex::new(r"\(?-?\d[\d,]*\.\d{2}\)?-?").unwrap(),
+    })
+}
+
+pub struct OcrService;
+
+impl OcrService {
+    /// The configured engine, or None when photo uploads are disabled.
+    pub fn engine(config: &Config) -> Option<Box<dyn OcrEngine>> {
+        match config.ocr_engine.as_deref()? {
+            "tesseract" => Some(Box::new(TesseractOcr {
+                binary: config.tesseract_path.clone(),
+            })),
+            "vision" => Some(Box::new(VisionOcr {
+                api_key: config.ocr_api_key.clone()?,
+            })),
+            _ => None,
+        }
+    }
+
+    /// Reads a row of an M-Pesa statement as OCR renders it, e.g.
+    ///
+    /// `QGH7XK2ABC 2024-03-12 14:15:02 Customer Transfer from 2547******78 - JOHN Completed 1,500.00 12,340.00`
+    ///
+    /// Amounts must print their cents, as M-Pesa statements do, so numbers
+    /// in the details aren't taken for them. The last amount is the balance
+    /// when there's more than one. Returns None for lines that don't start
+    /// with a receipt number, such as headers, and an error for rows that do
+    /// but can't be read.
+    pub fn parse_row(
+        text: &str,
+        minor_unit_exponent: u32,
+        timezone: chrono_tz::Tz,
+    ) -> Option<Result<StatementRow, String>> {
+        let receipt = patterns().receipt.captures(text)?[1].to_string();
+        Some(Self::parse_fields(text, receipt, minor_unit_exponent, timezone))
+    }
+
+    fn parse_fields(
+        text: &str,
+        receipt: String,
+        minor_unit_exponent: u32,
+        timezone: chrono_tz::Tz,
+    ) -> Result<StatementRow, String> {
+        let p = patterns();
+        let time = p.time.captures(text).ok_or("Missing date and time")?;
+        let value = format!("{} {}", &time[1], &time[2]);
+        let timestamp = ["%Y-%m-%d %H:%M:%S", "%Y-%m-%d %H:%M", "%d/%m/%Y %H:%M:%S", "%d/%m/%Y %H:%M"]
+            .iter()
+            .find_map(|format| NaiveDateTime::parse_from_str(&value, format).ok())
+            .and_then(|local| crate::utils::local_to_utc(local, timezone))
+            .ok_or_else(|| format!("Unreadable date: {}", value))?;
+
+        let rest = &text[time.get(0).map_or(0, |m| m.end())..];
+        let amounts: Vec<regex::Match> = p.money.find_iter(rest).collect();
+        let first = amounts.first().ok_or("Missing amount")?;
+        let read = |m: &regex::Match| parse_money(m.as_str(), minor_unit_exponent).map(|money| money.minor_units);
+
+        let (amount_cells, balance) = match amounts.split_last() {
+            Some((last, cells)) if !cells.is_empty() => (cells, Some(read(last)?)),
+            _ => (&amounts[..], None),
+        };
+        // Paid In and Withdrawn are separate columns; the one that moved
+        // isn't zero
+        let mut amount = 0;
+        for cell in amount_cells {
+            amount = read(cell)?;
+            if amount != 0 {
+                break;
+            }
+        }
+        if amount == 0 {
+            return Err("Missing amount".to_string());
+        }
+
+        let details = rest[..first.start()].trim().trim_end_matches("Completed").trim();
+        Ok(StatementRow {
+            receipt,
+            timestamp,
+            details: if details.is_empty() {
+                "Payment".to_string()
+            } else {
+                details.chars().take(MAX_DETAILS_LEN).collect()
+            },
+            amount,
+            balance,
+        })
+    }
+}
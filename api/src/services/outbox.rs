@@ -0,0 +1,55 @@
+//! Relays jobs from the `job_outbox` table to Redis. A job is written to
+//! the outbox in the same transaction as the row it's for, and deleted only
+//! once Redis has it, so delivery is at least once.
+
+use redis::AsyncCommands;
+use sqlx::PgPool;
+
+use crate::db::repos::OutboxRepo;
+use crate::services::proof_queue::with_redis_retry;
+
+/// Jobs pushed per relay pass.
+pub const RELAY_BATCH: i64 = 100;
+
+pub struct OutboxService;
+
+impl OutboxService {
+    /// Pushes the oldest waiting jobs onto their lists, in the order they
+    /// were written. Returns how many were pushed; a full batch means more
+    /// may be waiting.
+    pub async fn relay(db: &PgPool, redis: &redis::Client) -> anyhow::Result<usize> {
+        let mut tx = db.begin().await?;
+        let entries = OutboxRepo::lock_batch(&mut *tx, RELAY_BATCH).await?;
+        if entries.is_empty() {
+            return Ok(0);
+        }
+
+        // One push per run of jobs for the same list. Lists are pushed on
+        // the left, so values in push order keep the oldest first in line.
+        let mut runs: Vec<(&str, Vec<&str>)> = Vec::new();
+        for entry in &entries {
+            match runs.last_mut() {
+                Some((queue, payloads)) if *queue == entry.queue => payloads.push(&entry.payload),
+                _ => runs.push((&entry.queue, vec![&entry.payload])),
+            }
+        }
+        let pushed = with_redis_retry(|| async {
+            let mut conn = redis.get_async_connection().await?;
+            for (queue, payloads) in &runs {
+                conn.lpush::<_, _, ()>(*queue, payloads).await?;
+            }
+            Ok(())
+        })
+        .await;
+
+        let ids: Vec<i64> = entries.iter().map(|entry| entry.id).collect();
+        if let Err(e) = pushed {
+            tx.rollback().await?;
+            OutboxRepo::record_failure(db, &ids, &e.to_string()).await?;
+            return Err(e.into());
+        }
+        OutboxRepo::delete(&mut *tx, &ids).await?;
+        tx.commit().await?;
+        Ok(entries.len())
+    }
+}
@@ -0,0 +1,366 @@
+//! Lipa na M-Pesa Online (STK push): the merchant's phone is asked to
+//! approve a payment, and Daraja reports the outcome to a callback.
+
+use chrono::Utc;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::repos::payments::PaymentResult;
+use crate::db::repos::PaymentRepo;
+use crate::services::daraja::DarajaService;
+
+/// Proofs are paid for in the currency M-Pesa settles in.
+pub const PAYMENT_CURRENCY: &str = "KES";
+
+/// How long a push may go without its callback before Daraja is asked how it
+/// ended. The phone's prompt times out well within this.
+const RECONCILE_AFTER_MINUTES: i64 = 5;
+/// Stale pushes asked about per reconciliation run.
+const RECONCILE_BATCH: i64 = 100;
+
+/// What Daraja's STK push query reports while the push is still in flight.
+const STILL_PROCESSING_ERROR: &str = "500.001.1001";
+
+/// What Daraja said when it accepted a push.
+pub struct StkPush {
+    pub merchant_request_id: String,
+    pub checkout_request_id: String,
+    /// Text to show the merchant while their phone prompts them
+    pub customer_message: String,
+}
+
+/// Body Daraja posts to the callback URL once the merchant approves,
+/// declines or ignores the prompt.
+#[derive(Deserialize)]
+pub struct StkCallbackEnvelope {
+    #[serde(rename = "Body")]
+    pub body: StkCallbackBody,
+}
+
+#[derive(Deserialize)]
+pub struct StkCallbackBody {
+    #[serde(rename = "stkCallback")]
+    pub stk_callback: StkCallback,
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+pub struct StkCallback {
+    pub CheckoutRequestID: String,
+    /// 0 when paid; anything else, e.g. 1032 for a cancelled prompt, is a
+    /// failure
+    pub ResultCode: i32,
+    pub ResultDesc: String,
+    pub CallbackMetadata: Option<CallbackMetadata>,
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+pub struct CallbackMetadata {
+    #[serde(default)]
+    pub Item: Vec<CallbackItem>,
+}
+
+#[derive(Deserialize)]
+#[allow(non_snake_case)]
+pub struct CallbackItem {
+    pub Name: String,
+    pub Value: Option<serde_json::Value>,
+}
+
+impl StkCallback {
+    pub fn receipt_number(&self) -> Option<&str> {
+        self.CallbackMetadata
+            .as_ref()?
+            .Item
+            .iter()
+            .find(|item| item.Name == "MpesaReceiptNumber")?
+            .Value
+            .as_ref()?
+            .as_str()
+    }
+}
+
+pub struct PaymentService;
+
+impl PaymentService {
+    /// Whether Daraja is set up to push payment prompts.
+    pub fn is_configured(config: &Config) -> bool {
+        config.daraja_consumer_key.is_some()
+            && config.daraja_consumer_secret.is_some()
+            && config.daraja_shortcode.is_some()
+            && config.daraja_passkey.is_some()
+            && config.stk_callback_token.is_some()
+    }
+
+    /// Normalizes a Kenyan mobile number, "07XXXXXXXX", "+2547XXXXXXXX" or
+    /// "2547XXXXXXXX" (and 01 prefixes likewise), to the "254..." form
+    /// Daraja expects.
+    pub fn normalize_phone(phone: &str) -> Option<String> {
+        let digits: String = phone.chars().filter(|c| !c.is_whitespace()).collect();
+        let digits = digits.strip_prefix('+').unwrap_or(&digits);
+        let local = if let Some(rest) = digits.strip_prefix("254") {
+            rest
+        } else {
+            digits.strip_prefix('0')?
+        };
+        let valid = local.len() == 9 && local.starts_with(['7', '1']) && local.chars().all(|c| c.is_ascii_digit());
+        valid.then(|| format!("254{}", local))
+    }
+
+    /// Records a pending payment for one proof and prompts the phone to
+    /// approve it.
+    pub async fn initiate(
+        db: &PgPool,
+        config: &Config,
+        user_id: Uuid,
+        phone_number: &str,
+    ) -> anyhow::Result<(Uuid, StkPush)> {
+        let (Some(consumer_key), Some(consumer_secret), Some(shortcode), Some(passkey), Some(token)) = (
+            config.daraja_consumer_key.as_deref(),
+            config.daraja_consumer_secret.as_deref(),
+            config.daraja_shortcode.as_deref(),
+            config.daraja_passkey.as_deref(),
+            config.stk_callback_token.as_deref(),
+        ) else {
+            anyhow::bail!("STK push payments aren't configured");
+        };
+
+        let amount_minor = config.proof_price as i64 * 100;
+        let payment_id = PaymentRepo::create(db, user_id, phone_number, amount_minor, PAYMENT_CURRENCY).await?;
+
+        let access_token = DarajaService::get_access_token(consumer_key, consumer_secret).await?;
+        let callback_url = format!("{}/api/payments/callback?token={}", config.public_url, token);
+        let push = match Self::stk_push(
+            &access_token,
+            shortcode,
+            passkey,
+            phone_number,
+            config.proof_price,
+            &callback_url,
+        )
+        .await
+        {
+            Ok(push) => push,
+            Err(e) => {
+                PaymentRepo::reject(db, payment_id, &e.to_string()).await?;
+                return Err(e);
+            }
+        };
+        PaymentRepo::set_request_ids(db, payment_id, &push.merchant_request_id, &push.checkout_request_id).await?;
+        Ok((payment_id, push))
+    }
+
+    /// Settles the payment a callback is for. Returns false for callbacks
+    /// already handled or for pushes this API didn't send.
+    pub async fn handle_callback(db: &PgPool, callback: &StkCallback) -> anyhow::Result<bool> {
+        let result = PaymentResult {
+            result_code: callback.ResultCode,
+            result_desc: &callback.ResultDesc,
+            mpesa_receipt: callback.receipt_number(),
+        };
+        Ok(PaymentRepo::complete(db, &callback.CheckoutRequestID, &result).await?)
+    }
+
+    /// Settles pushes whose callback never arrived by asking Daraja how they
+    /// ended. Pushes still in flight are left for the next run, and ones
+    /// never sent, because the API stopped between recording and sending
+    /// them, are failed. Returns how many were settled.
+    pub async fn reconcile(db: &PgPool, config: &Config) -> anyhow::Result<usize> {
+        let (Some(consumer_key), Some(consumer_secret), Some(shortcode), Some(passkey)) = (
+            config.daraja_consumer_key.as_deref(),
+            config.daraja_consumer_secret.as_deref(),
+            config.daraja_shortcode.as_deref(),
+            config.daraja_passkey.as_deref(),
+        ) else {
+            return Ok(0);
+        };
+
+        let before = Utc::now() - chrono::Duration::minutes(RECONCILE_AFTER_MINUTES);
+        let stale = PaymentRepo::list_stale_pending(db, before, RECONCILE_BATCH).await?;
+        if stale.is_empty() {
+            return Ok(0);
+        }
+
+        let access_token = DarajaService::get_access_token(consumer_key, consumer_secret).await?;
+        let mut settled = 0;
+        for payment in stale {
+            let Some(checkout_request_id) = payment.checkout_request_id.as_deref() else {
+                PaymentRepo::reject(db, payment.id, "STK push was never sent").await?;
+                settled += 1;
+                continue;
+            };
+            match Self::stk_query(&access_token, shortcode, passkey, checkout_request_id).await {
+                Ok(Some((result_code, result_desc))) => {
+                    let result = PaymentResult {
+                        result_code,
+                        result_desc: &result_desc,
+                        mpesa_receipt: None,
+                    };
+                    if PaymentRepo::complete(db, checkout_request_id, &result).await? {
+                        settled += 1;
+                    }
+                }
+                Ok(None) => {}
+                Err(e) => tracing::warn!("STK push query for payment {} failed: {}", payment.id, e),
+            }
+        }
+        Ok(settled)
+    }
+
+    /// The Lipa na M-Pesa Online password and the timestamp it was made
+    /// for, which Daraja expects in Nairobi time.
+    fn password(shortcode: &str, passkey: &str) -> (String, String) {
+        use base64::Engine;
+
+        let timestamp = Utc::now()
+            .with_timezone(&chrono_tz::Africa::Nairobi)
+            .format("%Y%m%d%H%M%S")
+            .to_string();
+        let password =
+            base64::engine::general_purpose::STANDARD.encode(format!("{}{}{}", shortcode, passkey, timestamp));
+        (password, timestamp)
+    }
+
+    /// How a push ended, as its result code and description, or None while
+    /// the merchant can still answer the prompt.
+    async fn stk_query(
+        access_token: &str,
+        shortcode: &str,
+        passkey: &str,
+        checkout_request_id: &str,
+    ) -> anyhow::Result<Option<(i32, String)>> {
+        let client = Client::new();
+        let url = "https://sandbox.safaricom.co.ke/mpesa/stkpushquery/v1/query";
+
+        #[derive(Serialize)]
+        #[allow(non_snake_case)]
+        struct StkQueryRequest<'a> {
+            BusinessShortCode: &'a str,
+            Password: String,
+            Timestamp: String,
+            CheckoutRequestID: &'a str,
+        }
+
+        #[derive(Deserialize)]
+        #[allow(non_snake_case)]
+        struct StkQueryResponse {
+            ResultCode: Option<String>,
+            ResultDesc: Option<String>,
+            errorCode: Option<String>,
+            errorMessage: Option<String>,
+        }
+
+        let (password, timestamp) = Self::password(shortcode, passkey);
+        let request = StkQueryRequest {
+            BusinessShortCode: shortcode,
+            Password: password,
+            Timestamp: timestamp,
+            CheckoutRequestID: checkout_request_id,
+        };
+
+        let response = client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .json(&request)
+            .send()
+            .await?;
+
+        // Daraja answers a push still in flight with an error status
+        let body: StkQueryResponse = response.json().await?;
+        if body.errorCode.as_deref() == Some(STILL_PROCESSING_ERROR) {
+            return Ok(None);
+        }
+        match body.ResultCode.as_deref().map(str::parse::<i32>) {
+            Some(Ok(result_code)) => Ok(Some((result_code, body.ResultDesc.unwrap_or_default()))),
+            _ => anyhow::bail!(
+                "Daraja STK push query error: {}",
+                body.errorMessage.or(body.ResultDesc).unwrap_or_default()
+            ),
+        }
+    }
+
+    /// Asks Daraja to prompt the phone to pay `amount` whole shillings to the
+    /// shortcode.
+    async fn stk_push(
+        access_token: &str,
+        shortcode: &str,
+        passkey: &str,
+        phone_number: &str,
+        amount: u32,
+        callback_url: &str,
+    ) -> anyhow::Result<StkPush> {
+        let client = Client::new();
+        let url = "https://sandbox.safaricom.co.ke/mpesa/stkpush/v1/processrequest";
+
+        #[derive(Serialize)]
+        #[allow(non_snake_case)]
+        struct StkPushRequest {
+            BusinessShortCode: String,
+            Password: String,
+            Timestamp: String,
+            TransactionType: String,
+            Amount: u32,
+            PartyA: String,
+            PartyB: String,
+            PhoneNumber: String,
+            CallBackURL: String,
+            AccountReference: String,
+            TransactionDesc: String,
+        }
+
+        #[derive(Deserialize)]
+        #[allow(non_snake_case)]
+        struct StkPushResponse {
+            MerchantRequestID: Option<String>,
+            CheckoutRequestID: Option<String>,
+            ResponseCode: Option<String>,
+            ResponseDescription: Option<String>,
+            CustomerMessage: Option<String>,
+        }
+
+        let (password, timestamp) = Self::password(shortcode, passkey);
+
+        let request = StkPushRequest {
+            BusinessShortCode: shortcode.to_string(),
+            Password: password,
+            Timestamp: timestamp,
+            TransactionType: "CustomerPayBillOnline".to_string(),
+            Amount: amount,
+            PartyA: phone_number.to_string(),
+            PartyB: shortcode.to_string(),
+            PhoneNumber: phone_number.to_string(),
+            CallBackURL: callback_url.to_string(),
+            AccountReference: "Credit proof".to_string(),
+            TransactionDesc: "Credit proof".to_string(),
+        };
+
+        let response = client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Daraja STK push error: {}", error_text);
+        }
+
+        let body: StkPushResponse = response.json().await?;
+        match (body.ResponseCode.as_deref(), body.MerchantRequestID, body.CheckoutRequestID) {
+            (Some("0"), Some(merchant_request_id), Some(checkout_request_id)) => Ok(StkPush {
+                merchant_request_id,
+                checkout_request_id,
+                customer_message: body.CustomerMessage.unwrap_or_default(),
+            }),
+            _ => anyhow::bail!(
+                "Daraja STK push rejected: {}",
+                body.ResponseDescription.unwrap_or_default()
+            ),
+        }
+    }
+}
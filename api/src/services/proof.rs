@@ -1,141 +1,820 @@
-use chrono::Utc;
+use chrono::{DateTime, NaiveDate, Utc};
+use sha2::{Digest, Sha256};
 use sqlx::PgPool;
 use uuid::Uuid;
 
+use crate::db::repos::{
+    ImageIdRepo, OutboxRepo, PaymentRepo, ProofQueueRepo, ReferralCodeRepo, SessionRepo, TillRepo,
+    TransactionRepo,
+};
+use crate::models::SessionStage;
+use crate::services::billing::{BillingService, LimitReached};
+use crate::services::scoring_experiments::ScoringExperimentService;
+use crate::services::storage::StorageBackend;
+
+// The guest commits these types, so decoding with them keeps the journal
+// layout in one place.
+pub use proof_core::{
+    AggregateInput, ChunkJournal, Currency as CurrencyInput, Evaluation, GuestInput, InputError, Journal, ProofInput,
+    ProofOutput, ScoringPolicy as PolicyInput, ThresholdOutput, TotalsInput, Transaction as TransactionInput,
+    TransactionSource,
+};
+
+// STARK receipts are routinely over 1 MB; warn when one is far beyond that.
+const RECEIPT_SOFT_LIMIT_BYTES: usize = 16 * 1024 * 1024;
+
+/// Largest slice of a statement proved in one guest run. Bigger statements
+/// are proved chunk by chunk and composed, which bounds each run's memory
+/// and segment count.
+const MAX_CHUNK_TRANSACTIONS: usize = 25_000;
+
+/// Verification codes drawn for a session before giving up.
+const VERIFICATION_CODE_ATTEMPTS: u32 = 3;
+
 pub struct ProofService;
 
+/// What `ProofService::create_proof_session` did.
+pub enum NewSession {
+    Created(Uuid),
+    /// An identical request was already pending or processing; its ID and
+    /// which of the two it is
+    Existing(Uuid, String),
+    /// The plan's allowance is used up and there's no STK push payment to
+    /// spend, or a concurrent request spent it
+    Unpaid(LimitReached),
+    /// A concurrent request took the referral code's last redemption, or the
+    /// user's, or it expired meanwhile
+    CodeUnavailable,
+}
+
+/// A proof the user asked for, as `ProofService::create_proof_session`
+/// stores it.
+pub struct NewSessionRequest<'a> {
+    pub user_id: Uuid,
+    pub till_id: Uuid,
+    /// A `proof_core::DataSource` name
+    pub data_source: &'a str,
+    pub requested_range: Option<(NaiveDate, NaiveDate)>,
+    pub disclosure_policy: &'a crate::models::DisclosurePolicy,
+    pub secondary_source: Option<&'a str>,
+    pub score_threshold: Option<u32>,
+    pub account_number: Option<&'a str>,
+    pub currency: &'a str,
+    pub scoring_policy_id: Option<Uuid>,
+    pub input_mode: &'a str,
+    pub template_id: Option<Uuid>,
+    /// Create a session even if one is pending or processing for the same
+    /// request
+    pub force: bool,
+    /// Count the proof against the plan's allowance, spending one of the
+    /// user's STK push payments past it. Off when a coupon waives payment
+    pub metered: bool,
+    /// Redeem the code and attribute the session to its partner
+    pub referral_code_id: Option<Uuid>,
+}
+
 impl ProofService {
-    pub async fn create_proof_session(
-        db: &PgPool,
-        user_id: Uuid,
-        till_id: Uuid,
-        data_source: &str,
-        date_range: Option<&crate::handlers::proofs::DateRange>,
-    ) -> anyhow::Result<Uuid> {
+    /// Creates a pending session queued for proving, or returns the one
+    /// already pending or processing for the same request (till, date
+    /// range, data source, disclosure, threshold, account, currency, policy,
+    /// template and input mode) unless `force` is set, so a double-clicked
+    /// "Generate" proves once. The queue entry and the job for Redis are written with the
+    /// session, so a crash can't leave it pending with no job. A metered
+    /// proof past the plan's allowance spends a payment, or is refused.
+    pub async fn create_proof_session(db: &PgPool, request: &NewSessionRequest<'_>) -> anyhow::Result<NewSession> {
+        let &NewSessionRequest {
+            user_id,
+            till_id,
+            data_source,
+            requested_range,
+            disclosure_policy,
+            secondary_source,
+            score_threshold,
+            account_number,
+            currency,
+            scoring_policy_id,
+            input_mode,
+            template_id,
+            force,
+            metered,
+            referral_code_id,
+        } = request;
+        let (requested_from, requested_to) = requested_range.unzip();
+        let mut tx = db.begin().await?;
+
+        // Holds the user's row before the till's, so concurrent requests
+        // can't both take the plan's last proof
+        let limit = if metered {
+            BillingService::lock_proof_allowance(&mut *tx, user_id).await?.err()
+        } else {
+            None
+        };
+
+        // Serializes concurrent requests for the till so both can't miss
+        // each other's session
+        sqlx::query("SELECT id FROM business_tills WHERE id = $1 FOR UPDATE")
+            .bind(till_id)
+            .execute(&mut *tx)
+            .await?;
+
+        if !force {
+            let existing: Option<(Uuid, String)> = sqlx::query_as(
+                r#"
+                SELECT id, status::text FROM proof_sessions
+                WHERE till_id = $1 AND user_id = $2 AND status IN ('pending', 'processing')
+                  AND requested_from IS NOT DISTINCT FROM $3 AND requested_to IS NOT DISTINCT FROM $4
+                  AND data_source = $5
+                  AND disclosure_policy = $6
+                  AND secondary_source IS NOT DISTINCT FROM $7
+                  AND score_threshold IS NOT DISTINCT FROM $8
+                  AND account_number IS NOT DISTINCT FROM $9
+                  AND currency = $10
+                  AND scoring_policy_id IS NOT DISTINCT FROM $11
+                  AND input_mode = $12
+                  AND template_id IS NOT DISTINCT FROM $13
+                ORDER BY created_at DESC
+                LIMIT 1
+                "#,
+            )
+            .bind(till_id)
+            .bind(user_id)
+            .bind(requested_from)
+            .bind(requested_to)
+            .bind(data_source)
+            .bind(serde_json::to_value(disclosure_policy)?)
+            .bind(secondary_source)
+            .bind(score_threshold.map(|t| t as i32))
+            .bind(account_number)
+            .bind(currency)
+            .bind(scoring_policy_id)
+            .bind(input_mode)
+            .bind(template_id)
+            .fetch_optional(&mut *tx)
+            .await?;
+            if let Some((session_id, status)) = existing {
+                return Ok(NewSession::Existing(session_id, status));
+            }
+        }
+
         let session_id = Uuid::new_v4();
-        let verification_code = crate::utils::generate_verification_code();
         let expires_at = Utc::now() + chrono::Duration::days(365);
 
-        sqlx::query(
-            r#"
-            INSERT INTO proof_sessions (id, user_id, till_id, status, verification_code, expires_at)
-            VALUES ($1, $2, $3, 'pending', $4, $5)
-            "#,
-        )
-        .bind(session_id)
-        .bind(user_id)
-        .bind(till_id)
-        .bind(&verification_code)
-        .bind(expires_at)
-        .execute(db)
-        .await?;
+        // 128 random bits practically never collide, but a code is the
+        // proof's public identity, so a clash draws a fresh one rather than
+        // failing the request
+        let mut attempt = 1;
+        loop {
+            let verification_code = crate::utils::generate_verification_code();
+            let inserted = sqlx::query(
+                r#"
+                INSERT INTO proof_sessions (id, user_id, till_id, status, verification_code, expires_at, disclosure_policy, secondary_source,
+                                            proof_type, score_threshold, account_number, currency, scoring_policy_id, input_mode,
+                                            template_id, requested_from, requested_to, data_source, referral_code_id)
+                VALUES ($1, $2, $3, 'pending', $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)
+                ON CONFLICT (verification_code) DO NOTHING
+                "#,
+            )
+            .bind(session_id)
+            .bind(user_id)
+            .bind(till_id)
+            .bind(&verification_code)
+            .bind(expires_at)
+            .bind(serde_json::to_value(disclosure_policy)?)
+            .bind(secondary_source)
+            .bind(if score_threshold.is_some() { "threshold" } else { "full" })
+            .bind(score_threshold.map(|t| t as i32))
+            .bind(account_number)
+            .bind(currency)
+            .bind(scoring_policy_id)
+            .bind(input_mode)
+            .bind(template_id)
+            .bind(requested_from)
+            .bind(requested_to)
+            .bind(data_source)
+            .bind(referral_code_id)
+            .execute(&mut *tx)
+            .await?;
+            if inserted.rows_affected() > 0 {
+                break;
+            }
+            if attempt == VERIFICATION_CODE_ATTEMPTS {
+                anyhow::bail!("No unused verification code after {} attempts", attempt);
+            }
+            attempt += 1;
+        }
+
+        if let Some(referral_code_id) = referral_code_id {
+            if !ReferralCodeRepo::redeem(&mut *tx, referral_code_id, user_id).await? {
+                return Ok(NewSession::CodeUnavailable);
+            }
+        }
+        if let Some(limit) = limit {
+            if !PaymentRepo::spend(&mut *tx, user_id, session_id).await? {
+                return Ok(NewSession::Unpaid(limit));
+            }
+        }
+        ProofQueueRepo::add(&mut *tx, session_id).await?;
+        OutboxRepo::add(&mut *tx, crate::worker::PROOF_QUEUE_KEY, &session_id.to_string()).await?;
 
-        Ok(session_id)
+        tx.commit().await?;
+        Ok(NewSession::Created(session_id))
     }
 
-    pub async fn verify_receipt(receipt_data: &[u8]) -> anyhow::Result<bool> {
-        // In production, deserialize and verify RISC Zero receipt
-        // For now, return true if receipt exists
-        Ok(!receipt_data.is_empty())
+    /// Hex image ID of the guest this build proves with.
+    pub fn current_image_id() -> String {
+        risc0_zkvm::sha::Digest::from(methods::GUEST_CODE_FOR_ZK_PROOF_ID).to_string()
     }
 
-    pub async fn generate_proof(
+    /// Verifies a stored receipt against the image it was proved with. The
+    /// image must be in the registry and valid at `proved_at`; sessions that
+    /// predate recording the image are checked against the current one.
+    pub async fn verify_receipt(
         db: &PgPool,
-        session_id: Uuid,
-        transactions: Vec<crate::models::Transaction>,
-    ) -> anyhow::Result<()> {
-        // Update status to processing
-        sqlx::query("UPDATE proof_sessions SET status = 'processing' WHERE id = $1")
-            .bind(session_id)
-            .execute(db)
-            .await?;
-
-        // Prepare input for zkVM
-        let proof_input = crate::services::proof::ProofInput {
-            transactions: transactions
+        receipt_data: &[u8],
+        image_id: Option<&str>,
+        proved_at: chrono::DateTime<Utc>,
+    ) -> anyhow::Result<bool> {
+        let image_id = image_id.map_or_else(Self::current_image_id, str::to_string);
+        // Registration checks the hex, so every row parses
+        let registry = mpesa_credit_verify::ImageRegistry::new(
+            ImageIdRepo::list(db)
+                .await?
                 .into_iter()
-                .map(|t| crate::services::proof::TransactionInput {
-                    timestamp: t.timestamp.timestamp(),
-                    amount: t.amount as u64,
-                    transaction_type: t.transaction_type,
-                    reference: t.reference,
+                .filter_map(|image| {
+                    mpesa_credit_verify::AllowedImage::new(&image.image_id, image.valid_from, image.valid_to).ok()
                 })
                 .collect(),
+        );
+        match mpesa_credit_verify::verify_receipt(&registry, receipt_data, &image_id, proved_at) {
+            Ok(_) => Ok(true),
+            // Receipts that don't verify are recorded as invalid rather than
+            // failing the lookup
+            Err(rejection) => {
+                tracing::warn!("Stored receipt rejected: {}", rejection);
+                Ok(false)
+            }
+        }
+    }
+
+    /// Writes a receipt to object storage and returns its key and SHA-256,
+    /// which is all the database keeps.
+    pub async fn store_receipt(
+        storage: &dyn StorageBackend,
+        session_id: Uuid,
+        receipt_data: &[u8],
+    ) -> anyhow::Result<(String, String)> {
+        if receipt_data.len() > RECEIPT_SOFT_LIMIT_BYTES {
+            tracing::warn!(
+                "Receipt for session {} is {} bytes, above the {} byte soft limit",
+                session_id,
+                receipt_data.len(),
+                RECEIPT_SOFT_LIMIT_BYTES
+            );
+        }
+
+        // Keyed by content too, so a worker that lost the session's lease
+        // can't overwrite the receipt of the one that took it over
+        let sha256 = hex::encode(Sha256::digest(receipt_data));
+        let key = format!("receipts/{}-{}.bin", session_id, &sha256[..16]);
+        storage.upload(&key, receipt_data).await?;
+        Ok((key, sha256))
+    }
+
+    /// Loads a stored receipt, checking it against the recorded hash.
+    pub async fn load_receipt(
+        storage: &dyn StorageBackend,
+        key: &str,
+        expected_sha256: &str,
+    ) -> anyhow::Result<Vec<u8>> {
+        let data = storage.download(key).await?;
+        if hex::encode(Sha256::digest(&data)) != expected_sha256 {
+            anyhow::bail!("Stored receipt {} does not match its recorded hash", key);
+        }
+        Ok(data)
+    }
+
+    /// Proves `proof_input` (see `build_input`) in the given input mode and
+    /// stores the result on the session, as long as `worker_id` still holds
+    /// its lease. Returns false, storing nothing, once the lease has passed
+    /// to another worker. Input the scoring core rejects fails before
+    /// proving starts.
+    pub async fn generate_proof(
+        db: &PgPool,
+        storage: &dyn StorageBackend,
+        session_id: Uuid,
+        worker_id: &str,
+        proof_input: ProofInput,
+        input_mode: &str,
+        prover_backend: &str,
+    ) -> anyhow::Result<bool> {
+        // Update status to processing, recording which build is proving
+        let started = sqlx::query(
+            r#"
+            UPDATE proof_sessions
+            SET status = 'processing', proving_started_at = NOW(),
+                image_id = $2, guest_version = $3, prover_backend = $4
+            WHERE id = $1 AND lease_owner = $5
+            "#,
+        )
+        .bind(session_id)
+        .bind(Self::current_image_id())
+        .bind(methods::GUEST_VERSION)
+        .bind(prover_backend)
+        .bind(worker_id)
+        .execute(db)
+        .await?;
+        if started.rows_affected() == 0 {
+            return Ok(false);
+        }
+
+        // The cross-check scores the whole input natively; like proving, it
+        // runs on the blocking pool rather than stalling the runtime
+        let native_input = proof_input.clone();
+        let native = tokio::task::spawn_blocking(move || proof_core::evaluate(native_input)).await??;
+
+        // Proving takes minutes of CPU. Run it on the blocking pool so the
+        // worker's lease renewals and heartbeats keep running meanwhile.
+        SessionRepo::record_event(db, session_id, SessionStage::Proving, None).await?;
+        let input_mode_owned = input_mode.to_string();
+        let zkvm_input = proof_input.clone();
+        let receipt =
+            tokio::task::spawn_blocking(move || Self::execute_zkvm_proof(zkvm_input, &input_mode_owned)).await??;
+        SessionRepo::record_event(db, session_id, SessionStage::Verifying, None).await?;
+        let (journal, receipt_data) = tokio::task::spawn_blocking(move || Self::seal_receipt(receipt)).await??;
+        let (receipt_key, receipt_sha256) = Self::store_receipt(storage, session_id, &receipt_data).await?;
+
+        let journal: Journal = journal.decode()?;
+        if journal.schema_version != proof_core::JOURNAL_SCHEMA_VERSION {
+            anyhow::bail!("Guest committed journal schema v{}", journal.schema_version);
+        }
+        let rows_commitment = match journal.input_mode {
+            proof_core::InputMode::Transactions => None,
+            proof_core::InputMode::DailyTotals { rows_commitment } => Some(hex::encode(rows_commitment)),
         };
+        let proven = journal.evaluation;
+
+        // A mismatch means the guest and the native core have drifted; it's
+        // reported, but the proof itself still stands
+        if let Err(e) = Self::check_consistency(db, session_id, &native, &proven).await {
+            tracing::error!("Consistency check failed for session {}: {}", session_id, e);
+        }
 
-        // Execute zkVM proof generation
-        let proof_output = Self::execute_zkvm_proof(proof_input).await?;
+        let (period_start, period_end) = match &proven {
+            Evaluation::Threshold(output) => (output.period_start, output.period_end),
+            Evaluation::Full(output) => (output.period_start, output.period_end),
+        };
+        // Threshold proofs never learn the score, so only the outcome is stored
+        let (credit_score, metrics, score_breakdown, meets_threshold, account_hash, policy_hash, recency, coverage) =
+            match &proven {
+                Evaluation::Threshold(output) => (
+                    None,
+                    None,
+                    None,
+                    Some(output.meets_threshold),
+                    output.account_hash.clone(),
+                    output.policy_hash,
+                    &output.recency,
+                    output.coverage_percentage,
+                ),
+                Evaluation::Full(output) => (
+                    Some(output.credit_score as i32),
+                    Some(Self::metrics_json(output)?),
+                    Some(Self::breakdown_json(output)?),
+                    None,
+                    output.account_hash.clone(),
+                    output.policy_hash,
+                    &output.recency,
+                    output.coverage_percentage,
+                ),
+            };
+        let provenance = match &proven {
+            Evaluation::Threshold(output) => &output.provenance,
+            Evaluation::Full(output) => &output.provenance,
+        };
 
         // Store results
-        sqlx::query(
+        let stored = sqlx::query(
             r#"
             UPDATE proof_sessions
             SET status = 'completed',
                 credit_score = $1,
                 metrics = $2,
-                receipt_data = $3
-            WHERE id = $4
+                receipt_key = $3,
+                score_breakdown = $5,
+                receipt_sha256 = $6,
+                receipt_size = $7,
+                meets_threshold = $8,
+                account_hash = $9,
+                policy_hash = $10,
+                rows_commitment = $11,
+                days_since_last_transaction = $12,
+                recency_factor_percentage = $13,
+                period_start = $14,
+                period_end = $15,
+                coverage_percentage = $16,
+                api_sourced_percentage = $17,
+                provenance_mix = $18,
+                proving_finished_at = NOW()
+            WHERE id = $4 AND lease_owner = $19
             "#,
         )
-        .bind(proof_output.credit_score as i32)
-        .bind(serde_json::to_value(&proof_output.metrics)?)
-        .bind(proof_output.receipt_data.as_ref())
+        .bind(credit_score)
+        .bind(metrics)
+        .bind(&receipt_key)
         .bind(session_id)
+        .bind(score_breakdown)
+        .bind(&receipt_sha256)
+        .bind(receipt_data.len() as i64)
+        .bind(meets_threshold)
+        .bind(account_hash)
+        .bind(hex::encode(policy_hash))
+        .bind(rows_commitment)
+        .bind(recency.days_since_last_transaction as i32)
+        .bind(recency.recency_factor_percentage as i16)
+        .bind(chrono::DateTime::from_timestamp(period_start, 0))
+        .bind(chrono::DateTime::from_timestamp(period_end, 0))
+        .bind(coverage as i16)
+        .bind(provenance.api_sourced_percentage as i16)
+        .bind(Self::provenance_json(provenance))
+        .bind(worker_id)
         .execute(db)
         .await?;
+        if stored.rows_affected() == 0 {
+            if let Err(e) = storage.delete(&receipt_key).await {
+                tracing::warn!("Failed to delete unused receipt {}: {}", receipt_key, e);
+            }
+            return Ok(false);
+        }
+        SessionRepo::record_event(db, session_id, SessionStage::Stored, None).await?;
 
+        // Any candidate policy on trial against this one scores the same
+        // input, once the proof has stood; that's for the admin report only,
+        // so it can't fail the proof
+        ScoringExperimentService::shadow_score(db, session_id, &proof_input, &native).await;
+
+        Ok(true)
+    }
+
+    /// Measures which weeks of a till's scoring window have payments, the
+    /// way the guest will, and records the percentage on the till.
+    pub async fn refresh_completeness(db: &PgPool, till_id: Uuid) -> anyhow::Result<proof_core::Coverage> {
+        let offset = Self::till_utc_offset(db, till_id).await?;
+        let days = TransactionRepo::scored_days(db, till_id, offset).await?;
+        let coverage = match (days.first(), days.last()) {
+            (Some(&first), Some(&last)) => proof_core::coverage(days.iter().copied(), first, last),
+            _ => proof_core::Coverage::default(),
+        };
+        TillRepo::set_completeness(db, till_id, coverage.percentage()).await?;
+        Ok(coverage)
+    }
+
+    /// Measures which weeks of a requested period (inclusive dates in the
+    /// till's timezone) have payments. Unlike `refresh_completeness`, weeks
+    /// before the first payment or after the last count as gaps.
+    pub async fn requested_completeness(
+        db: &PgPool,
+        till_id: Uuid,
+        (from, to): (NaiveDate, NaiveDate),
+    ) -> anyhow::Result<proof_core::Coverage> {
+        let offset = Self::till_utc_offset(db, till_id).await?;
+        // Days since the epoch, as the guest numbers them
+        let day = |date: NaiveDate| date.and_hms_opt(0, 0, 0).unwrap().and_utc().timestamp() / 86_400;
+        let (first_day, last_day) = (day(from), day(to));
+        let local_midnight = |day: i64| DateTime::from_timestamp(day * 86_400 - offset as i64, 0);
+        let (Some(start), Some(end)) = (local_midnight(first_day), local_midnight(last_day + 1)) else {
+            anyhow::bail!("Requested period {} to {} is out of range", from, to);
+        };
+        let days = TransactionRepo::scored_days_between(db, till_id, offset, start, end).await?;
+        Ok(proof_core::coverage(days, first_day, last_day))
+    }
+
+    /// The till's offset from UTC now, Nairobi's if its timezone is unknown.
+    async fn till_utc_offset(db: &PgPool, till_id: Uuid) -> anyhow::Result<i32> {
+        let timezone = TillRepo::timezone(db, till_id)
+            .await?
+            .and_then(|name| name.parse::<chrono_tz::Tz>().ok())
+            .unwrap_or(chrono_tz::Africa::Nairobi);
+        Ok(crate::utils::utc_offset_secs(timezone, Utc::now()))
+    }
+
+    /// Builds the guest input from a till's stored transactions. M-Pesa rows
+    /// are primary; anything else is the composite proof's secondary source.
+    /// Account numbers are hashed so the guest only ever sees digests. Days
+    /// are grouped in `timezone`, at its offset from UTC now. Every row may be
+    /// scored; set `source` to restrict that.
+    pub fn build_input(
+        transactions: Vec<crate::models::Transaction>,
+        secondary_source: Option<&str>,
+        score_threshold: Option<u32>,
+        account_number: Option<&str>,
+        currency: &crate::models::Currency,
+        policy: PolicyInput,
+        timezone: chrono_tz::Tz,
+    ) -> ProofInput {
+        let now = Utc::now();
+        let (primary, secondary): (Vec<_>, Vec<_>) = transactions
+            .into_iter()
+            .partition(|t| t.source == "mpesa");
+
+        let to_input = |transactions: Vec<crate::models::Transaction>| -> Vec<TransactionInput> {
+            transactions
+                .into_iter()
+                .map(|t| TransactionInput {
+                    timestamp: t.timestamp.timestamp(),
+                    amount: t.amount.unsigned_abs(),
+                    currency: t.currency,
+                    kind: proof_core::TransactionKind::from_name(&t.kind).unwrap_or(proof_core::TransactionKind::Other),
+                    direction: proof_core::Direction::from_name(&t.direction).unwrap_or(if t.amount < 0 {
+                        proof_core::Direction::Outflow
+                    } else {
+                        proof_core::Direction::Inflow
+                    }),
+                    reference: t.reference,
+                    counterparty: t
+                        .raw_data
+                        .as_ref()
+                        .and_then(|raw| raw.get("counterparty_hash"))
+                        .and_then(|v| v.as_str())
+                        .map(str::to_string),
+                    account: t.account_number.as_deref().map(crate::utils::public_identifier_hash),
+                    provenance: proof_core::Provenance::from_name(&t.provenance)
+                        .unwrap_or(proof_core::Provenance::CsvUpload),
+                })
+                .collect()
+        };
+
+        ProofInput {
+            transactions: to_input(primary),
+            secondary: secondary_source.map(|tag| TransactionSource {
+                tag: tag.to_string(),
+                transactions: to_input(secondary),
+            }),
+            threshold: score_threshold,
+            account: account_number.map(crate::utils::public_identifier_hash),
+            currency: CurrencyInput {
+                code: currency.code.clone(),
+                minor_unit_exponent: currency.minor_unit_exponent.max(0) as u32,
+            },
+            policy,
+            as_of: Some(now.timestamp()),
+            utc_offset_secs: Some(crate::utils::utc_offset_secs(timezone, now)),
+            source: proof_core::DataSource::All,
+        }
+    }
+
+    /// The policy a proof is scored under: the chosen scoring policy, or the
+    /// currency's default thresholds, uncapped, when none was chosen.
+    pub fn policy_input(
+        currency: &crate::models::Currency,
+        policy: Option<&crate::models::ScoringPolicy>,
+    ) -> PolicyInput {
+        let thresholds = policy.map_or(&currency.volume_thresholds, |p| &p.volume_thresholds);
+        let mut volume_thresholds = [u64::MAX; 4];
+        for (slot, &threshold) in volume_thresholds.iter_mut().zip(thresholds) {
+            *slot = threshold.max(0) as u64;
+        }
+        PolicyInput {
+            volume_thresholds,
+            outlier_cap: policy.and_then(|p| p.outlier_cap).map(|cap| cap.max(0) as u32),
+        }
+    }
+
+    /// Compares the proven journal with the native result for the same
+    /// input, recording the outcome on the session and logging divergences
+    /// for operators.
+    async fn check_consistency(
+        db: &PgPool,
+        session_id: Uuid,
+        native: &Evaluation,
+        proven: &Evaluation,
+    ) -> anyhow::Result<()> {
+        let native = Self::evaluation_json(native)?;
+        let proven = Self::evaluation_json(proven)?;
+        let consistent = native == proven;
+
+        sqlx::query("UPDATE proof_sessions SET native_consistent = $1 WHERE id = $2")
+            .bind(consistent)
+            .bind(session_id)
+            .execute(db)
+            .await?;
+
+        if !consistent {
+            tracing::error!(
+                session_id = %session_id,
+                "Proven result diverges from the native scoring core"
+            );
+            sqlx::query("INSERT INTO proof_divergences (session_id, native_output, proven_output) VALUES ($1, $2, $3)")
+                .bind(session_id)
+                .bind(&native)
+                .bind(&proven)
+                .execute(db)
+                .await?;
+        }
+
+        Ok(())
+    }
+
+    fn evaluation_json(evaluation: &Evaluation) -> serde_json::Result<serde_json::Value> {
+        match evaluation {
+            Evaluation::Full(output) => serde_json::to_value(output),
+            Evaluation::Threshold(output) => serde_json::to_value(output),
+        }
+    }
+
+    /// Runs the guest's scoring natively, without proving. Used for previews
+    /// and to cross-check completed proofs; the result is not attested.
+    pub fn score_natively(
+        transactions: Vec<crate::models::Transaction>,
+        secondary_source: Option<&str>,
+        account_number: Option<&str>,
+        currency: &crate::models::Currency,
+        policy: PolicyInput,
+        timezone: chrono_tz::Tz,
+    ) -> Result<ProofOutput, InputError> {
+        let input =
+            Self::build_input(transactions, secondary_source, None, account_number, currency, policy, timezone);
+        match proof_core::evaluate(input)? {
+            Evaluation::Full(output) => Ok(output),
+            Evaluation::Threshold(_) => unreachable!("threshold scoring was not requested"),
+        }
+    }
+
+    /// Metrics as stored and shown to lenders, including the month-by-month
+    /// volume bands, the activity profile, cash flow and the currency
+    /// volumes were measured in; composite proofs also carry the per-source
+    /// volume bands.
+    pub fn metrics_json(output: &ProofOutput) -> anyhow::Result<serde_json::Value> {
+        let mut metrics = serde_json::to_value(&output.metrics)?;
+        metrics["currency"] = serde_json::Value::String(output.currency.clone());
+        metrics["monthly_volumes"] = serde_json::to_value(&output.monthly_volumes)?;
+        metrics["activity_profile"] = serde_json::to_value(&output.activity_profile)?;
+        metrics["cash_flow"] = serde_json::to_value(&output.cash_flow)?;
+        if output.source_volumes.len() > 1 {
+            metrics["source_volumes"] = serde_json::to_value(&output.source_volumes)?;
+        }
+        Ok(metrics)
+    }
+
+    /// The score breakdown as stored, with any outlier adjustment alongside
+    /// the points so the breakdown explains the volume they were scored on.
+    pub fn breakdown_json(output: &ProofOutput) -> anyhow::Result<serde_json::Value> {
+        let mut breakdown = serde_json::to_value(&output.score_breakdown)?;
+        if let Some(adjustment) = &output.outlier_adjustment {
+            breakdown["outlier_adjustment"] = serde_json::to_value(adjustment)?;
+        }
+        Ok(breakdown)
+    }
+
+    /// A committed provenance mix as stored: percentages by provenance name,
+    /// leaving out provenances with no volume.
+    pub fn provenance_json(mix: &proof_core::ProvenanceMix) -> serde_json::Value {
+        proof_core::Provenance::ALL
+            .iter()
+            .zip(mix.percentages)
+            .filter(|&(_, percentage)| percentage > 0)
+            .map(|(provenance, percentage)| (provenance.as_str().to_string(), serde_json::Value::from(percentage)))
+            .collect::<serde_json::Map<_, _>>()
+            .into()
+    }
+
+    /// Which prover `default_prover` resolves to, for operational reporting
+    /// and for tagging sessions: "bonsai", or "local-" and the hardware the
+    /// local prover runs on.
+    pub fn prover_backend(config: &crate::config::Config) -> &'static str {
+        if config.bonsai_api_key.as_deref().is_some_and(|k| !k.is_empty()) {
+            return "bonsai";
+        }
+        match Self::local_accelerator() {
+            "cuda" => "local-cuda",
+            "metal" => "local-metal",
+            _ => "local-cpu",
+        }
+    }
+
+    /// Hardware this build's local prover uses: CUDA when built with the
+    /// `cuda` feature, Metal on Apple silicon, the CPU otherwise.
+    fn local_accelerator() -> &'static str {
+        if cfg!(feature = "cuda") {
+            "cuda"
+        } else if cfg!(all(target_os = "macos", target_arch = "aarch64")) {
+            "metal"
+        } else {
+            "cpu"
+        }
+    }
+
+    /// Checks `PROVER_ACCELERATOR` against this build and machine before a
+    /// worker takes jobs. An accelerator the build lacks falls back to the
+    /// CPU with a warning. A CUDA build on a machine without a GPU is an
+    /// error, since it can't prove on the CPU.
+    pub fn check_accelerator(config: &crate::config::Config) -> anyhow::Result<()> {
+        if Self::prover_backend(config) == "bonsai" {
+            return Ok(());
+        }
+
+        let requested = config.prover_accelerator.as_str();
+        if !matches!(requested, "auto" | "cpu" | "cuda" | "metal") {
+            anyhow::bail!("Unknown PROVER_ACCELERATOR {:?}; expected auto, cpu, cuda or metal", requested);
+        }
+
+        let actual = Self::local_accelerator();
+        if actual == "cuda" && !std::path::Path::new("/dev/nvidiactl").exists() {
+            anyhow::bail!("This worker was built with CUDA but no NVIDIA GPU was found; run a CPU build on this node");
+        }
+        if requested != "auto" && requested != actual {
+            tracing::warn!(
+                "PROVER_ACCELERATOR={} is not available in this build; proving on {}",
+                requested,
+                actual
+            );
+        }
         Ok(())
     }
 
-    async fn execute_zkvm_proof(
-        input: ProofInput,
-    ) -> anyhow::Result<ProofOutput> {
-        use methods::{GUEST_CODE_FOR_ZK_PROOF_ELF, GUEST_CODE_FOR_ZK_PROOF_ID};
-        use risc0_zkvm::{default_prover, ExecutorEnv, Receipt};
+    /// Proves the guest run. Daily-totals input is proved in one small run;
+    /// statements over `MAX_CHUNK_TRANSACTIONS` are proved in chunks and
+    /// composed into one receipt.
+    fn execute_zkvm_proof(input: ProofInput, input_mode: &str) -> anyhow::Result<risc0_zkvm::Receipt> {
+        use risc0_zkvm::ProverOpts;
 
-        let env = ExecutorEnv::builder()
-            .write(&input)
-            .unwrap()
-            .build()
-            .unwrap();
+        let transaction_count =
+            input.transactions.len() + input.secondary.as_ref().map_or(0, |s| s.transactions.len());
+        if input_mode == "daily_totals" {
+            let totals = GuestInput::Totals(Box::new(TotalsInput {
+                rows_commitment: proof_core::rows_commitment(&input),
+                threshold: input.threshold,
+                currency: input.currency.clone(),
+                policy: input.policy.clone(),
+                as_of: input.as_of,
+                totals: proof_core::totals(input)?,
+            }));
+            Self::prove(&totals, Vec::new(), &ProverOpts::default())
+        } else if transaction_count <= MAX_CHUNK_TRANSACTIONS {
+            Self::prove(&GuestInput::Statement(input), Vec::new(), &ProverOpts::default())
+        } else {
+            Self::prove_chunked(input)
+        }
+    }
 
-        let prover = default_prover();
-        let prove_info = prover.prove(env, GUEST_CODE_FOR_ZK_PROOF_ELF)?;
-        let receipt = prove_info.receipt;
+    /// Verifies a fresh receipt, returning its journal alongside the
+    /// serialized receipt.
+    fn seal_receipt(receipt: risc0_zkvm::Receipt) -> anyhow::Result<(risc0_zkvm::Journal, Vec<u8>)> {
+        use methods::GUEST_CODE_FOR_ZK_PROOF_ID;
 
-        // Verify receipt
         receipt.verify(GUEST_CODE_FOR_ZK_PROOF_ID)?;
 
-        // Decode output
-        let output: ProofOutput = receipt.journal.decode()?;
-
         // Serialize receipt for storage
         let receipt_data = bincode::serialize(&receipt)?;
 
-        Ok(ProofOutput {
-            receipt_data: Some(receipt_data),
-            ..output
-        })
+        Ok((receipt.journal, receipt_data))
     }
-}
 
-#[derive(serde::Serialize, serde::Deserialize)]
-pub struct ProofInput {
-    pub transactions: Vec<TransactionInput>,
-}
+    /// Proves each chunk on its own, then an aggregate run that verifies the
+    /// chunk receipts as assumptions and scores their merged summaries.
+    fn prove_chunked(input: ProofInput) -> anyhow::Result<risc0_zkvm::Receipt> {
+        use methods::GUEST_CODE_FOR_ZK_PROOF_ID;
+        use risc0_zkvm::ProverOpts;
 
-#[derive(serde::Serialize, serde::Deserialize)]
-pub struct TransactionInput {
-    pub timestamp: i64,
-    pub amount: u64,
-    pub transaction_type: String,
-    pub reference: String,
-}
+        let (threshold, as_of) = (input.threshold, input.as_of);
+        let (currency, policy) = (input.currency.clone(), input.policy.clone());
+        let chunks = proof_core::split(input, MAX_CHUNK_TRANSACTIONS);
+        tracing::info!("Proving statement as {} chunks", chunks.len());
 
-#[derive(serde::Serialize, serde::Deserialize)]
-pub struct ProofOutput {
-    pub credit_score: u32,
-    pub metrics: crate::models::BusinessMetrics,
-    pub receipt_data: Option<Vec<u8>>,
-}
+        let mut receipts = Vec::with_capacity(chunks.len());
+        let mut journals = Vec::with_capacity(chunks.len());
+        for chunk in chunks {
+            // Succinct chunk receipts keep resolving them in the aggregate cheap
+            let receipt = Self::prove(&GuestInput::Chunk(chunk), Vec::new(), &ProverOpts::succinct())?;
+            journals.push(receipt.journal.decode::<ChunkJournal>()?);
+            receipts.push(receipt);
+        }
+
+        let aggregate = GuestInput::Aggregate(AggregateInput {
+            chunk_image_id: GUEST_CODE_FOR_ZK_PROOF_ID,
+            chunks: journals,
+            threshold,
+            currency,
+            policy,
+            as_of,
+        });
+        Self::prove(&aggregate, receipts, &ProverOpts::succinct())
+    }
+
+    fn prove(
+        input: &GuestInput,
+        assumptions: Vec<risc0_zkvm::Receipt>,
+        opts: &risc0_zkvm::ProverOpts,
+    ) -> anyhow::Result<risc0_zkvm::Receipt> {
+        use methods::GUEST_CODE_FOR_ZK_PROOF_ELF;
+        use risc0_zkvm::{default_prover, ExecutorEnv};
 
+        let mut builder = ExecutorEnv::builder();
+        for assumption in assumptions {
+            builder.add_assumption(assumption);
+        }
+        let env = builder.write(input)?.build()?;
+
+        let prover = default_prover();
+        Ok(prover.prove_with_opts(env, GUEST_CODE_FOR_ZK_PROOF_ELF, opts)?.receipt)
+    }
+}
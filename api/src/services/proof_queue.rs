@@ -0,0 +1,98 @@
+//! The proof queue. The `proof_queue` table is the source of truth;
+//! Redis's list of the same name is the fast path workers block on, fed by
+//! the job outbox, and a sweep pushes back anything it lost.
+
+use std::collections::HashSet;
+use std::future::Future;
+use std::time::Duration;
+
+use redis::AsyncCommands;
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::repos::ProofQueueRepo;
+use crate::worker::PROOF_QUEUE_KEY;
+
+/// Attempts at a Redis operation before giving up on it.
+const REDIS_ATTEMPTS: u32 = 3;
+/// Wait before the first retry, doubled before each one after it.
+const REDIS_BACKOFF: Duration = Duration::from_millis(100);
+/// Entries younger than this may still be on their way to Redis, so the
+/// sweep leaves them alone.
+const RECONCILE_GRACE_SECS: i64 = 30;
+
+/// Runs a Redis operation, retrying with backoff while Redis can't be
+/// reached or drops the connection. Other errors are returned at once.
+pub async fn with_redis_retry<T, F, Fut>(mut op: F) -> redis::RedisResult<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = redis::RedisResult<T>>,
+{
+    let mut backoff = REDIS_BACKOFF;
+    let mut attempt = 1;
+    loop {
+        match op().await {
+            Err(e) if attempt < REDIS_ATTEMPTS && is_transient(&e) => {
+                tracing::warn!("Redis unavailable (attempt {} of {}): {}", attempt, REDIS_ATTEMPTS, e);
+                tokio::time::sleep(backoff).await;
+                backoff *= 2;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+fn is_transient(e: &redis::RedisError) -> bool {
+    e.is_io_error() || e.is_connection_refusal() || e.is_connection_dropped() || e.is_timeout()
+}
+
+pub struct ProofQueueService;
+
+impl ProofQueueService {
+    /// Pushes sessions already back in the `proof_queue` table, e.g. after
+    /// their worker stopped, onto the front of the line. The sweep pushes
+    /// them later if Redis is down.
+    pub async fn requeue(redis: &redis::Client, session_ids: &[Uuid]) {
+        if let Err(e) = Self::push_front(redis, session_ids).await {
+            tracing::warn!("{} sessions requeued without Redis: {}", session_ids.len(), e);
+        }
+    }
+
+    /// Pushes waiting sessions missing from Redis back onto the front of its
+    /// list, after dropping entries no worker can claim. Returns how many
+    /// were pushed.
+    pub async fn reconcile(db: &PgPool, redis: &redis::Client) -> anyhow::Result<usize> {
+        ProofQueueRepo::prune(db).await?;
+        let waiting = ProofQueueRepo::waiting_since(db, RECONCILE_GRACE_SECS).await?;
+        if waiting.is_empty() {
+            return Ok(0);
+        }
+
+        let listed: Vec<String> = with_redis_retry(|| async {
+            let mut conn = redis.get_async_connection().await?;
+            conn.lrange(PROOF_QUEUE_KEY, 0, -1).await
+        })
+        .await?;
+        let listed: HashSet<String> = listed.into_iter().collect();
+        let missing: Vec<Uuid> = waiting
+            .into_iter()
+            .filter(|session_id| !listed.contains(&session_id.to_string()))
+            .collect();
+        Self::push_front(redis, &missing).await?;
+        Ok(missing.len())
+    }
+
+    /// Workers pop from the right, so the first session is pushed last.
+    async fn push_front(redis: &redis::Client, session_ids: &[Uuid]) -> redis::RedisResult<()> {
+        if session_ids.is_empty() {
+            return Ok(());
+        }
+        let values: Vec<String> = session_ids.iter().rev().map(Uuid::to_string).collect();
+        with_redis_retry(|| async {
+            let mut conn = redis.get_async_connection().await?;
+            conn.rpush::<_, _, ()>(PROOF_QUEUE_KEY, &values).await
+        })
+        .await
+    }
+}
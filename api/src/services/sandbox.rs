@@ -0,0 +1,81 @@
+use chrono::{DateTime, Duration, Utc};
+use serde_json::json;
+
+/// Synthetic completed proofs served to sandbox lender keys, so integrators
+/// can exercise every verification outcome without real merchant data.
+pub struct SandboxService;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SandboxOutcome {
+    Valid,
+    Expired,
+    Revoked,
+    InvalidReceipt,
+}
+
+pub struct SandboxProof {
+    pub verification_code: &'static str,
+    pub description: &'static str,
+    pub credit_score: i32,
+    pub metrics: serde_json::Value,
+    pub sector: Option<&'static str>,
+    pub outcome: SandboxOutcome,
+    pub generated_at: DateTime<Utc>,
+    pub expires_at: DateTime<Utc>,
+}
+
+impl SandboxService {
+    pub fn proofs() -> Vec<SandboxProof> {
+        let now = Utc::now();
+        let fixture = |verification_code,
+                       description,
+                       credit_score,
+                       volume,
+                       consistency,
+                       growth,
+                       active_days,
+                       diversity,
+                       sector,
+                       outcome| {
+            let (generated_at, expires_at) = match outcome {
+                SandboxOutcome::Expired => (now - Duration::days(400), now - Duration::days(35)),
+                _ => (now - Duration::days(30), now + Duration::days(335)),
+            };
+            SandboxProof {
+                verification_code,
+                description,
+                credit_score,
+                metrics: json!({
+                    "monthly_volume_range": volume,
+                    "consistency_score": consistency,
+                    "growth_trend": growth,
+                    "active_days_percentage": active_days,
+                    "customer_diversity_score": diversity,
+                    "concentration_risk": "Low",
+                    "max_weekly_drawdown_percentage": 100 - consistency,
+                    "volatility_30d_percentage": (100 - consistency) / 2,
+                }),
+                sector,
+                outcome,
+                generated_at,
+                expires_at,
+            }
+        };
+
+        vec![
+            fixture("SBXHIGH85000", "Established shop, strong score", 85, "High", 82, "Growing", 96, 74, Some("retail"), SandboxOutcome::Valid),
+            fixture("SBXMED620000", "Typical small merchant", 62, "Medium", 58, "Stable", 80, 55, Some("food"), SandboxOutcome::Valid),
+            fixture("SBXLOW310000", "Irregular trading, weak score", 31, "Low", 22, "Declining", 45, 30, Some("transport"), SandboxOutcome::Valid),
+            fixture("SBXNEW120000", "Very new till, little history", 12, "VeryLow", 10, "Stable", 20, 15, None, SandboxOutcome::Valid),
+            fixture("SBXEXPIRED00", "Proof past its expiry date", 70, "Medium", 66, "Stable", 88, 60, Some("retail"), SandboxOutcome::Expired),
+            fixture("SBXREVOKED00", "Proof revoked by its owner", 77, "High", 70, "Growing", 90, 65, Some("services"), SandboxOutcome::Revoked),
+            fixture("SBXBADRCPT00", "Receipt fails verification", 90, "VeryHigh", 88, "Rapid", 98, 80, Some("wholesale"), SandboxOutcome::InvalidReceipt),
+        ]
+    }
+
+    pub fn find(verification_code: &str) -> Option<SandboxProof> {
+        Self::proofs()
+            .into_iter()
+            .find(|p| p.verification_code == verification_code)
+    }
+}
@@ -0,0 +1,192 @@
+//! Trials of a candidate scoring policy before lenders are moved onto it.
+//! While an experiment runs, a sample of the sessions proved under its
+//! baseline policy are also scored natively under the candidate, and both
+//! results are stored for the admin report. The candidate's result is never
+//! proven or shown to owners or lenders.
+
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+use crate::db::repos::scoring_experiments::{NewComparison, ScoringExperiment};
+use crate::db::repos::{CurrencyRepo, ScoringExperimentRepo, ScoringPolicyRepo};
+use crate::services::proof::{Evaluation, ProofInput, ProofService};
+
+/// Whether the experiment samples the session. Each session lands in a
+/// fixed bucket per experiment, so a regenerated proof is sampled again.
+pub fn in_sample(experiment_id: Uuid, session_id: Uuid, sample_percentage: i16) -> bool {
+    let digest = Sha256::digest(format!("{}:{}", experiment_id, session_id));
+    let bucket = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) % 100;
+    (bucket as i16) < sample_percentage
+}
+
+/// The score, for full proofs, or the threshold outcome, for threshold ones.
+fn outcome(evaluation: &Evaluation) -> (Option<i32>, Option<bool>) {
+    match evaluation {
+        Evaluation::Full(output) => (Some(output.credit_score as i32), None),
+        Evaluation::Threshold(output) => (None, Some(output.meets_threshold)),
+    }
+}
+
+/// Both policies' outcomes for one sampled session, as the report stores them.
+fn comparison(experiment_id: Uuid, session_id: Uuid, baseline: &Evaluation, candidate: &Evaluation) -> NewComparison {
+    let (baseline_score, baseline_meets_threshold) = outcome(baseline);
+    let (candidate_score, candidate_meets_threshold) = outcome(candidate);
+    NewComparison {
+        experiment_id,
+        session_id,
+        baseline_score,
+        candidate_score,
+        baseline_meets_threshold,
+        candidate_meets_threshold,
+    }
+}
+
+pub struct ScoringExperimentService;
+
+impl ScoringExperimentService {
+    /// Scores `input` under the candidate of each running experiment that
+    /// samples the session, and stores the result next to `baseline`, the
+    /// native result under the session's own policy. An experiment that
+    /// can't be scored is logged and skipped; the others still are.
+    pub async fn shadow_score(db: &PgPool, session_id: Uuid, input: &ProofInput, baseline: &Evaluation) {
+        let baseline_hash = hex::encode(input.policy.hash(&input.currency.code));
+        let experiments = match ScoringExperimentRepo::running_for(db, &input.currency.code, &baseline_hash).await {
+            Ok(experiments) => experiments,
+            Err(e) => {
+                tracing::error!("Failed to load scoring experiments for session {}: {}", session_id, e);
+                return;
+            }
+        };
+
+        for experiment in experiments {
+            if !in_sample(experiment.id, session_id, experiment.sample_percentage) {
+                continue;
+            }
+            if let Err(e) = Self::compare(db, &experiment, session_id, input, baseline).await {
+                tracing::error!(
+                    "Shadow scoring failed for session {} in experiment {}: {}",
+                    session_id,
+                    experiment.id,
+                    e
+                );
+            }
+        }
+    }
+
+    /// Scores `input` under the experiment's candidate and records it
+    /// against `baseline`.
+    async fn compare(
+        db: &PgPool,
+        experiment: &ScoringExperiment,
+        session_id: Uuid,
+        input: &ProofInput,
+        baseline: &Evaluation,
+    ) -> anyhow::Result<()> {
+        let candidate = ScoringPolicyRepo::find(db, experiment.candidate_policy_id)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Candidate policy {} not found", experiment.candidate_policy_id))?;
+        let currency = CurrencyRepo::find(db, &candidate.currency)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("Currency {} not found", candidate.currency))?;
+
+        // Scoring takes as long as the baseline's did; keep it off the
+        // runtime so the worker's lease renewals keep running
+        let mut candidate_input = input.clone();
+        candidate_input.policy = ProofService::policy_input(&currency, Some(&candidate));
+        let evaluation = tokio::task::spawn_blocking(move || proof_core::evaluate(candidate_input)).await??;
+
+        let comparison = comparison(experiment.id, session_id, baseline, &evaluation);
+        ScoringExperimentRepo::record_comparison(db, &comparison).await?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proof_core::{Currency, DataSource, Direction, Provenance, ScoringPolicy, Transaction, TransactionKind};
+
+    /// Ninety days of KES 2,000 in payments, about KES 60,000 a month.
+    fn statement(threshold: Option<u32>, volume_thresholds: [u64; 4]) -> ProofInput {
+        let now = 1_750_000_000;
+        let transactions = (0..90)
+            .map(|day| Transaction {
+                timestamp: now - day * 86_400,
+                amount: 200_000,
+                currency: "KES".to_string(),
+                kind: TransactionKind::Payment,
+                direction: Direction::Inflow,
+                reference: format!("RCP{:04}", day),
+                counterparty: Some(format!("payer-{}", day % 12)),
+                account: None,
+                provenance: Provenance::CsvUpload,
+            })
+            .collect();
+        ProofInput {
+            transactions,
+            secondary: None,
+            threshold,
+            account: None,
+            currency: Currency {
+                code: "KES".to_string(),
+                minor_unit_exponent: 2,
+            },
+            policy: ScoringPolicy {
+                volume_thresholds,
+                outlier_cap: None,
+            },
+            as_of: Some(now),
+            utc_offset_secs: None,
+            source: DataSource::All,
+        }
+    }
+
+    /// KES's default bands, which the statement's volume reaches the first of
+    const BASELINE: [u64; 4] = [50_000, 250_000, 1_000_000, 5_000_000];
+    /// Bands the statement's volume falls short of
+    const CANDIDATE: [u64; 4] = [500_000, 2_500_000, 10_000_000, 50_000_000];
+
+    #[test]
+    fn compares_a_stricter_candidate_with_the_baseline() {
+        let baseline = proof_core::evaluate(statement(None, BASELINE)).unwrap();
+        let candidate = proof_core::evaluate(statement(None, CANDIDATE)).unwrap();
+
+        let compared = comparison(Uuid::nil(), Uuid::nil(), &baseline, &candidate);
+        let (baseline_score, candidate_score) = (compared.baseline_score.unwrap(), compared.candidate_score.unwrap());
+        assert!(candidate_score < baseline_score, "candidate scored {}", candidate_score);
+        assert_eq!(compared.baseline_meets_threshold, None);
+        assert_eq!(compared.candidate_meets_threshold, None);
+
+        // The same policy scores the same input alike
+        let rerun = proof_core::evaluate(statement(None, BASELINE)).unwrap();
+        let rerun = comparison(Uuid::nil(), Uuid::nil(), &baseline, &rerun);
+        assert_eq!(rerun.candidate_score, Some(baseline_score));
+    }
+
+    #[test]
+    fn threshold_proofs_compare_outcomes_only() {
+        let full = proof_core::evaluate(statement(None, BASELINE)).unwrap();
+        let Evaluation::Full(output) = &full else {
+            panic!("expected a full evaluation");
+        };
+        let threshold = output.credit_score as u32;
+        let baseline = proof_core::evaluate(statement(Some(threshold), BASELINE)).unwrap();
+        let candidate = proof_core::evaluate(statement(Some(threshold), CANDIDATE)).unwrap();
+
+        let compared = comparison(Uuid::nil(), Uuid::nil(), &baseline, &candidate);
+        assert_eq!(compared.baseline_score, None);
+        assert_eq!(compared.candidate_score, None);
+        assert_eq!(compared.baseline_meets_threshold, Some(true));
+        assert_eq!(compared.candidate_meets_threshold, Some(false));
+    }
+
+    #[test]
+    fn samples_a_session_the_same_way_every_time() {
+        let (experiment_id, session_id) = (Uuid::new_v4(), Uuid::new_v4());
+        let sampled = in_sample(experiment_id, session_id, 50);
+        assert!((0..10).all(|_| in_sample(experiment_id, session_id, 50) == sampled));
+        assert!(in_sample(experiment_id, session_id, 100));
+        assert!(!in_sample(experiment_id, session_id, 0));
+    }
+}
@@ -0,0 +1,108 @@
+//! Sign-ins that look like someone else's, and the fresh OTP sensitive
+//! actions need even within a signed-in session.
+
+use std::net::IpAddr;
+
+use chrono::Utc;
+use uuid::Uuid;
+
+use crate::db::repos::{AuditRepo, UserSessionRepo};
+use crate::error::AppError;
+use crate::utils::Claims;
+
+/// How long a confirmed OTP covers sensitive actions.
+pub const STEP_UP_WINDOW_MINS: i64 = 10;
+
+/// Wrong step-up codes an account may send within the lockout before step-up
+/// is refused, so a stolen session can't guess its way past it.
+pub const STEP_UP_MAX_FAILURES: i64 = 5;
+pub const STEP_UP_LOCKOUT_MINS: i64 = 15;
+
+/// How far back a phone's sign-ins count as familiar.
+const LOGIN_HISTORY_DAYS: i64 = 90;
+
+/// The network a client address is on: its /24 for IPv4, its /48 for IPv6.
+/// None when the address is unknown.
+pub fn ip_range(ip: IpAddr) -> Option<String> {
+    if ip.is_unspecified() {
+        return None;
+    }
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            Some(format!("{}.{}.{}.0/24", a, b, c))
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => ip_range(IpAddr::V4(v4)),
+            None => {
+                let [a, b, c, ..] = v6.segments();
+                Some(format!("{:x}:{:x}:{:x}::/48", a, b, c))
+            }
+        },
+    }
+}
+
+/// How a sign-in compares with the phone's earlier ones on the account.
+#[derive(Debug, Default)]
+pub struct LoginCheck {
+    pub new_device: bool,
+    pub new_ip_range: bool,
+}
+
+impl LoginCheck {
+    pub fn anomalous(&self) -> bool {
+        self.new_device || self.new_ip_range
+    }
+}
+
+pub struct SecurityService;
+
+impl SecurityService {
+    /// Flags a sign-in from a device or network the phone hasn't signed in
+    /// from in the last 90 days. A phone's first sign-in has nothing to
+    /// compare with, and an unknown device or address isn't counted as new.
+    pub async fn check_login(
+        db: &sqlx::PgPool,
+        user_id: Uuid,
+        phone_number: &str,
+        device_hint: Option<&str>,
+        ip_range: Option<&str>,
+    ) -> anyhow::Result<LoginCheck> {
+        let since = Utc::now() - chrono::Duration::days(LOGIN_HISTORY_DAYS);
+        let history = UserSessionRepo::login_history(db, user_id, phone_number, device_hint, ip_range, since).await?;
+        if !history.signed_in_before {
+            return Ok(LoginCheck::default());
+        }
+        Ok(LoginCheck {
+            new_device: device_hint.is_some() && !history.device_seen,
+            new_ip_range: ip_range.is_some() && !history.ip_range_seen,
+        })
+    }
+
+    /// Lets a sensitive action through only if the session confirmed a
+    /// fresh OTP with `POST /api/auth/step-up` in the last 10 minutes, and
+    /// records it as `event` in the audit log.
+    pub async fn require_step_up(
+        db: &sqlx::PgPool,
+        claims: &Claims,
+        event: &str,
+        details: serde_json::Value,
+    ) -> Result<(), AppError> {
+        let user_id =
+            Uuid::parse_str(&claims.user_id).map_err(|e| AppError::Validation(format!("Invalid UUID: {}", e)))?;
+        // Merchant access tokens have no session to step up
+        let session_id = claims
+            .session_id
+            .as_deref()
+            .and_then(|id| Uuid::parse_str(id).ok())
+            .ok_or(AppError::StepUpRequired)?;
+
+        let since = Utc::now() - chrono::Duration::minutes(STEP_UP_WINDOW_MINS);
+        if !UserSessionRepo::stepped_up_since(db, session_id, user_id, since).await? {
+            return Err(AppError::StepUpRequired);
+        }
+
+        AuditRepo::record(db, user_id, Some(session_id), event, None, details).await?;
+        Ok(())
+    }
+}
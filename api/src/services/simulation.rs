@@ -0,0 +1,55 @@
+use serde::{Deserialize, Serialize};
+
+use crate::services::proof::{ProofInput, TransactionInput};
+
+/// Hypothetical changes to a statement for stress-testing its score.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StressScenario {
+    /// Change to every payment received, e.g. -30 for a 30% drop
+    #[serde(default)]
+    pub volume_change_percentage: i32,
+    /// Multiple of every reversal's amount, e.g. 2.0 for twice as much
+    /// reversed
+    #[serde(default = "StressScenario::unchanged")]
+    pub reversal_rate_multiplier: f64,
+}
+
+impl StressScenario {
+    fn unchanged() -> f64 {
+        1.0
+    }
+
+    pub fn validate(&self) -> Result<(), String> {
+        if !(-100..=1000).contains(&self.volume_change_percentage) {
+            return Err("volume_change_percentage must be between -100 and 1000".to_string());
+        }
+        if !(0.0..=10.0).contains(&self.reversal_rate_multiplier) {
+            return Err("reversal_rate_multiplier must be between 0 and 10".to_string());
+        }
+        Ok(())
+    }
+
+    /// Rewrites the input's rows as the scenario describes. Everything
+    /// else, policy and scoring window included, is left as it was proved.
+    pub fn apply(&self, input: &mut ProofInput) {
+        let secondary = input.secondary.iter_mut().flat_map(|s| s.transactions.iter_mut());
+        for tx in input.transactions.iter_mut().chain(secondary) {
+            self.apply_to(tx);
+        }
+    }
+
+    fn apply_to(&self, tx: &mut TransactionInput) {
+        use proof_core::{Direction, TransactionKind};
+
+        match (tx.kind, tx.direction) {
+            (TransactionKind::Payment, Direction::Inflow) => {
+                let scaled = tx.amount as u128 * (100 + self.volume_change_percentage) as u128 / 100;
+                tx.amount = scaled.min(u64::MAX as u128) as u64;
+            }
+            (TransactionKind::Reversal, _) => {
+                tx.amount = (tx.amount as f64 * self.reversal_rate_multiplier).round() as u64;
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,157 @@
+use chrono::{DateTime, Datelike, Duration, Timelike, Utc};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde::Deserialize;
+
+use crate::utils::hash_phone_number;
+
+/// Synthetic M-Pesa statement generator used by demo mode, so sales demos
+/// and integration tests don't need real merchant data.
+pub struct SimulatorService;
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum GrowthPattern {
+    Flat,
+    Growing,
+    Declining,
+    Volatile,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SimulationParams {
+    #[serde(default = "default_months")]
+    pub months: u32,
+    /// Average number of payments on an active day
+    #[serde(default = "default_daily_transactions")]
+    pub daily_transactions: u32,
+    /// Average payment size in KSh
+    #[serde(default = "default_average_amount")]
+    pub average_amount: u64,
+    #[serde(default = "default_growth_pattern")]
+    pub growth_pattern: GrowthPattern,
+    /// Amplitude (0.0-1.0) of the month-of-year seasonal swing
+    #[serde(default)]
+    pub seasonality: f64,
+    /// Size of the simulated customer base
+    #[serde(default = "default_customers")]
+    pub customers: u32,
+    /// Fixed seed for reproducible statements
+    pub seed: Option<u64>,
+}
+
+fn default_months() -> u32 {
+    6
+}
+
+fn default_daily_transactions() -> u32 {
+    20
+}
+
+fn default_average_amount() -> u64 {
+    500
+}
+
+fn default_growth_pattern() -> GrowthPattern {
+    GrowthPattern::Flat
+}
+
+fn default_customers() -> u32 {
+    200
+}
+
+pub struct SimulatedTransaction {
+    pub timestamp: DateTime<Utc>,
+    pub amount: i64, // In cents
+    pub transaction_type: String,
+    pub reference: String,
+    pub counterparty_hash: String,
+}
+
+impl SimulationParams {
+    pub fn validate(&self) -> Result<(), String> {
+        if self.months == 0 || self.months > 36 {
+            return Err("months must be between 1 and 36".to_string());
+        }
+        if self.daily_transactions == 0 || self.daily_transactions > 500 {
+            return Err("daily_transactions must be between 1 and 500".to_string());
+        }
+        if self.average_amount == 0 {
+            return Err("average_amount must be positive".to_string());
+        }
+        if !(0.0..=1.0).contains(&self.seasonality) {
+            return Err("seasonality must be between 0.0 and 1.0".to_string());
+        }
+        if self.customers == 0 {
+            return Err("customers must be positive".to_string());
+        }
+        Ok(())
+    }
+}
+
+impl SimulatorService {
+    pub fn generate(params: &SimulationParams, end: DateTime<Utc>) -> Vec<SimulatedTransaction> {
+        let mut rng = match params.seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+
+        let days = params.months as i64 * 30;
+        let start = end - Duration::days(days);
+        let mut transactions = Vec::new();
+
+        for day in 0..days {
+            let date = start + Duration::days(day);
+            let progress = day as f64 / days as f64;
+
+            let trend = match params.growth_pattern {
+                GrowthPattern::Flat => 1.0,
+                GrowthPattern::Growing => 0.6 + 0.8 * progress,
+                GrowthPattern::Declining => 1.4 - 0.8 * progress,
+                GrowthPattern::Volatile => rng.gen_range(0.3..1.7),
+            };
+            let season = 1.0
+                + params.seasonality
+                    * (2.0 * std::f64::consts::PI * date.month0() as f64 / 12.0).sin();
+            // Sundays are quieter for most small businesses
+            let weekday = if date.weekday() == chrono::Weekday::Sun { 0.5 } else { 1.0 };
+
+            // Roughly one day in ten has no trade at all
+            if rng.gen_bool(0.1) {
+                continue;
+            }
+
+            let expected = params.daily_transactions as f64 * trend * season * weekday;
+            let count = (expected * rng.gen_range(0.7..1.3)).round().max(0.0) as u32;
+
+            for _ in 0..count {
+                let seconds = rng.gen_range(7 * 3600..21 * 3600);
+                let timestamp = date
+                    .with_hour(0)
+                    .and_then(|d| d.with_minute(0))
+                    .and_then(|d| d.with_second(0))
+                    .unwrap_or(date)
+                    + Duration::seconds(seconds);
+
+                let amount_ksh = params.average_amount as f64 * rng.gen_range(0.2..2.5);
+                let customer = rng.gen_range(0..params.customers);
+                let receipt: String = (0..10)
+                    .map(|_| {
+                        let idx = rng.gen_range(0..36);
+                        char::from_digit(idx, 36).unwrap().to_ascii_uppercase()
+                    })
+                    .collect();
+
+                transactions.push(SimulatedTransaction {
+                    timestamp,
+                    amount: (amount_ksh * 100.0) as i64,
+                    transaction_type: "Payment".to_string(),
+                    reference: receipt,
+                    counterparty_hash: hash_phone_number(&format!("2547{:08}", customer)),
+                });
+            }
+        }
+
+        transactions.sort_by_key(|t| t.timestamp);
+        transactions
+    }
+}
@@ -0,0 +1,96 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use regex::Regex;
+use std::sync::OnceLock;
+
+use crate::services::money::parse_money;
+use crate::utils::hash_phone_number;
+
+/// An incoming payment read from an M-PESA confirmation SMS.
+#[derive(Debug)]
+pub struct ParsedSms {
+    pub receipt: String,
+    pub amount_cents: i64,
+    pub counterparty_hash: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+/// Tolerant parser for the M-PESA confirmation SMS formats merchants
+/// receive, e.g.
+///
+/// `QGH7XK2ABC Confirmed. You have received Ksh1,500.00 from JOHN DOE 0712345678 on 12/3/24 at 2:15 PM. New M-PESA balance is Ksh12,340.00.`
+/// `QGH7XK2ABC Confirmed. Ksh500.00 received from JOHN DOE 254712345678 on 5/6/2024 at 10:02 AM.`
+pub struct SmsParser;
+
+struct Patterns {
+    receipt: Regex,
+    amount: Regex,
+    sender: Regex,
+    phone: Regex,
+    time: Regex,
+}
+
+fn patterns() -> &'static Patterns {
+    static PATTERNS: OnceLock<Patterns> = OnceLock::new();
+    PATTERNS.get_or_init(|| Patterns {
+        receipt: Regex::new(r"(?i)^\s*([A-Z0-9]{10})\s*,?\s*confirmed").unwrap(),
+        amount: Regex::new(
+            r"(?i)(?:received\s+ksh\.?\s*([\d,]+(?:\.\d{1,2})?))|(?:ksh\.?\s*([\d,]+(?:\.\d{1,2})?)\s+(?:has\s+been\s+)?received)",
+        )
+        .unwrap(),
+        sender: Regex::new(r"(?i)\bfrom\s+(.+?)\s+on\s+\d").unwrap(),
+        phone: Regex::new(r"(?:\+?254|\b0)([17]\d{8})\b").unwrap(),
+        time: Regex::new(r"(?i)\bon\s+(\d{1,2}/\d{1,2}/\d{2,4})\s+at\s+(\d{1,2}:\d{2})\s*([AP]M)").unwrap(),
+    })
+}
+
+impl SmsParser {
+    pub fn parse(text: &str) -> Result<ParsedSms, String> {
+        let p = patterns();
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        let receipt = p
+            .receipt
+            .captures(&text)
+            .map(|c| c[1].to_ascii_uppercase())
+            .ok_or("Not an M-PESA confirmation message")?;
+
+        let amount = p
+            .amount
+            .captures(&text)
+            .and_then(|c| c.get(1).or_else(|| c.get(2)))
+            .ok_or("Not an incoming payment")?;
+        let amount = parse_money(amount.as_str(), 2)?;
+
+        let sender = p
+            .sender
+            .captures(&text)
+            .map(|c| c[1].trim().to_string())
+            .ok_or("Missing sender")?;
+        // Prefer the phone number, normalised, so the same payer always hashes
+        // the same; fall back to the name when the SMS masks the number
+        let counterparty = match p.phone.captures(&sender) {
+            Some(c) => format!("254{}", &c[1]),
+            None => sender.to_ascii_uppercase(),
+        };
+
+        let time = p.time.captures(&text).ok_or("Missing transaction time")?;
+        let timestamp = parse_sms_time(&time[1], &time[2], &time[3])?;
+
+        Ok(ParsedSms {
+            receipt,
+            amount_cents: amount.minor_units,
+            counterparty_hash: hash_phone_number(&counterparty),
+            timestamp,
+        })
+    }
+}
+
+/// SMS times are Nairobi wall-clock time.
+fn parse_sms_time(date: &str, time: &str, meridiem: &str) -> Result<DateTime<Utc>, String> {
+    let value = format!("{} {} {}", date, time, meridiem.to_ascii_uppercase());
+    ["%d/%m/%y %I:%M %p", "%d/%m/%Y %I:%M %p"]
+        .iter()
+        .find_map(|format| NaiveDateTime::parse_from_str(&value, format).ok())
+        .and_then(|local| crate::utils::local_to_utc(local, chrono_tz::Africa::Nairobi))
+        .ok_or_else(|| format!("Unreadable date: {} {}", date, time))
+}
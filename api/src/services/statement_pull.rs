@@ -0,0 +1,270 @@
+use chrono::{DateTime, NaiveDateTime, Utc};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::services::daraja::DarajaService;
+use crate::services::money::parse_money;
+use crate::utils::{hash_phone_number, phone_number_hashes};
+
+/// Daraja "Pull Transactions" API. Lets an organization fetch its own
+/// statement for a date range instead of relying on real-time C2B callbacks.
+pub struct StatementPullService;
+
+// Daraja returns at most this many records per query; page with OffSetValue.
+const PAGE_SIZE: usize = 1000;
+
+#[derive(Debug, Deserialize)]
+pub struct PulledTransaction {
+    #[serde(rename = "transactionId")]
+    pub transaction_id: String,
+    #[serde(rename = "trxDate")]
+    pub trx_date: String,
+    pub msisdn: Option<serde_json::Value>,
+    #[serde(rename = "transactiontype")]
+    pub transaction_type: Option<String>,
+    #[serde(rename = "billreference")]
+    pub bill_reference: Option<String>,
+    pub amount: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct QueryResponse {
+    #[serde(rename = "ResponseCode")]
+    response_code: Option<String>,
+    #[serde(rename = "ResponseMessage")]
+    response_message: Option<String>,
+    #[serde(rename = "Response", default)]
+    response: Vec<Vec<PulledTransaction>>,
+}
+
+impl StatementPullService {
+    pub async fn register(
+        access_token: &str,
+        shortcode: &str,
+        nominated_number: &str,
+        callback_url: &str,
+    ) -> anyhow::Result<()> {
+        let client = Client::new();
+        let url = "https://sandbox.safaricom.co.ke/pulltransactions/v1/register";
+
+        #[derive(Serialize)]
+        #[allow(non_snake_case)]
+        struct RegisterRequest {
+            ShortCode: String,
+            RequestType: String,
+            NominatedNumber: String,
+            CallBackURL: String,
+        }
+
+        let request = RegisterRequest {
+            ShortCode: shortcode.to_string(),
+            RequestType: "Pull".to_string(),
+            NominatedNumber: nominated_number.to_string(),
+            CallBackURL: callback_url.to_string(),
+        };
+
+        let response = client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Daraja pull registration error: {}", error_text);
+        }
+
+        Ok(())
+    }
+
+    pub async fn query(
+        access_token: &str,
+        shortcode: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        offset: usize,
+    ) -> anyhow::Result<Vec<PulledTransaction>> {
+        let client = Client::new();
+        let url = "https://sandbox.safaricom.co.ke/pulltransactions/v1/query";
+
+        #[derive(Serialize)]
+        #[allow(non_snake_case)]
+        struct QueryRequest {
+            ShortCode: String,
+            StartDate: String,
+            EndDate: String,
+            OffSetValue: String,
+        }
+
+        // The window is read in Nairobi time, as the statement's times are
+        let nairobi = |at: DateTime<Utc>| {
+            at.with_timezone(&chrono_tz::Africa::Nairobi)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        };
+        let request = QueryRequest {
+            ShortCode: shortcode.to_string(),
+            StartDate: nairobi(start),
+            EndDate: nairobi(end),
+            OffSetValue: offset.to_string(),
+        };
+
+        let response = client
+            .post(url)
+            .header("Authorization", format!("Bearer {}", access_token))
+            .json(&request)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let error_text = response.text().await?;
+            anyhow::bail!("Daraja pull query error: {}", error_text);
+        }
+
+        let body: QueryResponse = response.json().await?;
+        if body.response_code.as_deref().is_some_and(|c| c != "1000" && c != "0") {
+            anyhow::bail!(
+                "Daraja pull query rejected: {}",
+                body.response_message.unwrap_or_default()
+            );
+        }
+
+        Ok(body.response.into_iter().flatten().collect())
+    }
+
+    /// Pulls the till's statement for `[start, end]` and inserts any new
+    /// rows. Registers the shortcode for pull access first if the till has
+    /// not been connected yet. Returns the number of newly imported rows.
+    pub async fn sync_till(
+        db: &PgPool,
+        config: &Config,
+        till_id: Uuid,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+    ) -> anyhow::Result<usize> {
+        let (consumer_key, consumer_secret) = match (
+            config.daraja_consumer_key.as_deref(),
+            config.daraja_consumer_secret.as_deref(),
+        ) {
+            (Some(key), Some(secret)) => (key, secret),
+            _ => anyhow::bail!("Daraja credentials not configured"),
+        };
+
+        let row = sqlx::query("SELECT till_number, api_connected FROM business_tills WHERE id = $1")
+            .bind(till_id)
+            .fetch_one(db)
+            .await?;
+        let till_number: String = row.try_get(0)?;
+        let api_connected: bool = row.try_get(1)?;
+
+        let access_token = DarajaService::get_access_token(consumer_key, consumer_secret).await?;
+
+        if !api_connected {
+            let nominated_number = config
+                .daraja_nominated_number
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("DARAJA_NOMINATED_NUMBER must be set to register pull access"))?;
+            let callback_url = config
+                .daraja_callback_url
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("DARAJA_CALLBACK_URL must be set to register pull access"))?;
+
+            Self::register(&access_token, &till_number, nominated_number, callback_url).await?;
+
+            sqlx::query("UPDATE business_tills SET api_connected = true WHERE id = $1")
+                .bind(till_id)
+                .execute(db)
+                .await?;
+        }
+
+        let mut imported = 0;
+        let mut offset = 0;
+        loop {
+            let page = Self::query(&access_token, &till_number, start, end, offset).await?;
+            let page_len = page.len();
+
+            for tx in page {
+                if Self::insert_transaction(db, till_id, &tx).await? {
+                    imported += 1;
+                }
+            }
+
+            if page_len < PAGE_SIZE {
+                break;
+            }
+            offset += page_len;
+        }
+
+        Ok(imported)
+    }
+
+    async fn insert_transaction(
+        db: &PgPool,
+        till_id: Uuid,
+        tx: &PulledTransaction,
+    ) -> anyhow::Result<bool> {
+        let timestamp = parse_trx_date(&tx.trx_date)?;
+        let amount = parse_pulled_amount(&tx.amount)?;
+
+        let counterparty_hash = tx.msisdn.as_ref().map(|m| match m {
+            serde_json::Value::String(s) => hash_phone_number(s),
+            other => hash_phone_number(&other.to_string()),
+        });
+
+        let raw_data = serde_json::json!({
+            "source": "daraja_pull",
+            "counterparty_hash": counterparty_hash,
+            "bill_reference": tx.bill_reference,
+        });
+
+        // Rows not yet rehashed to the current pepper are matched too
+        let references = phone_number_hashes(&tx.transaction_id);
+        let result = sqlx::query(
+            r#"
+            INSERT INTO transactions (till_id, timestamp, amount, transaction_type, reference, raw_data, account_number,
+                                      provenance)
+            SELECT $1, $2, $3, $4, $5, $6, $7, 'daraja'
+            WHERE NOT EXISTS (SELECT 1 FROM transaction_receipts r WHERE r.till_id = $1 AND r.reference = ANY($8))
+            "#,
+        )
+        .bind(till_id)
+        .bind(timestamp)
+        .bind(amount)
+        .bind(tx.transaction_type.as_deref().unwrap_or("Payment"))
+        .bind(&references[0])
+        .bind(raw_data)
+        .bind(tx.bill_reference.as_deref().filter(|r| !r.is_empty()))
+        .bind(&references)
+        .execute(db)
+        .await?;
+
+        Ok(result.rows_affected() > 0)
+    }
+}
+
+/// Daraja gives times without an offset in Nairobi time.
+fn parse_trx_date(value: &str) -> anyhow::Result<DateTime<Utc>> {
+    if let Ok(dt) = DateTime::parse_from_rfc3339(value) {
+        return Ok(dt.with_timezone(&Utc));
+    }
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+        .ok()
+        .and_then(|local| crate::utils::local_to_utc(local, chrono_tz::Africa::Nairobi))
+        .ok_or_else(|| anyhow::anyhow!("Unable to parse trxDate: {}", value))
+}
+
+fn parse_pulled_amount(value: &serde_json::Value) -> anyhow::Result<i64> {
+    let amount = match value {
+        serde_json::Value::Number(n) => parse_money(&n.to_string(), 2).ok(),
+        serde_json::Value::String(s) => parse_money(s, 2).ok(),
+        _ => None,
+    }
+    .ok_or_else(|| anyhow::anyhow!("Unable to parse amount: {}", value))?;
+
+    // Daraja amounts are in shillings; store cents
+    Ok(amount.minor_units)
+}
@@ -1,12 +1,24 @@
+use std::pin::Pin;
+
 use async_trait::async_trait;
+use tokio::io::AsyncRead;
 
 pub struct StorageService;
 
+pub type StorageReader = Pin<Box<dyn AsyncRead + Send>>;
+
 #[async_trait]
 pub trait StorageBackend: Send + Sync {
     async fn upload(&self, key: &str, data: &[u8]) -> anyhow::Result<String>;
     async fn download(&self, key: &str) -> anyhow::Result<Vec<u8>>;
     async fn delete(&self, key: &str) -> anyhow::Result<()>;
+
+    /// Opens the object for streaming. Backends that can't stream fall back
+    /// to buffering the whole object.
+    async fn open(&self, key: &str) -> anyhow::Result<StorageReader> {
+        let data = self.download(key).await?;
+        Ok(Box::pin(std::io::Cursor::new(data)))
+    }
 }
 
 pub struct LocalStorage {
@@ -36,17 +48,26 @@ impl StorageBackend for LocalStorage {
         Ok(std::fs::read(path)?)
     }
 
+    /// A key that's already gone counts as deleted, so a retried cleanup
+    /// finishes.
     async fn delete(&self, key: &str) -> anyhow::Result<()> {
         let path = std::path::Path::new(&self.base_path).join(key);
-        std::fs::remove_file(path)?;
-        Ok(())
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+
+    async fn open(&self, key: &str) -> anyhow::Result<StorageReader> {
+        let path = std::path::Path::new(&self.base_path).join(key);
+        Ok(Box::pin(tokio::fs::File::open(path).await?))
     }
 }
 
 impl StorageService {
-    pub fn create_backend(storage_type: &str, _config: &crate::config::Config) -> anyhow::Result<Box<dyn StorageBackend>> {
+    pub fn create_backend(storage_type: &str, _config: &crate::config::Config) -> anyhow::Result<std::sync::Arc<dyn StorageBackend>> {
         match storage_type {
-            "local" => Ok(Box::new(LocalStorage::new("./storage".to_string()))),
+            "local" => Ok(std::sync::Arc::new(LocalStorage::new("./storage".to_string()))),
             "s3" | "r2" => {
                 // TODO: Implement S3/R2 storage
                 anyhow::bail!("S3/R2 storage not yet implemented")
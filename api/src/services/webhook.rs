@@ -0,0 +1,212 @@
+use std::net::{IpAddr, SocketAddr};
+
+use hmac::{Hmac, Mac};
+use reqwest::Client;
+use serde::Serialize;
+use sha2::Sha256;
+use sqlx::{PgPool, Row};
+use uuid::Uuid;
+
+use crate::utils::constant_time_eq;
+
+/// Unique per event; receivers should drop ids they have already handled.
+pub const ID_HEADER: &str = "x-webhook-id";
+/// Unix seconds at which the delivery was signed.
+pub const TIMESTAMP_HEADER: &str = "x-webhook-timestamp";
+/// `v1=` followed by the hex HMAC-SHA256 of `"{timestamp}.{body}"`.
+pub const SIGNATURE_HEADER: &str = "x-webhook-signature";
+/// Deliveries signed longer ago than this should be rejected as replays.
+pub const REPLAY_TOLERANCE_SECS: i64 = 5 * 60;
+
+const DELIVERY_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(10);
+
+/// What every delivery's body looks like.
+#[derive(Serialize)]
+pub struct WebhookEvent<'a> {
+    pub id: Uuid,
+    #[serde(rename = "type")]
+    pub event_type: &'a str,
+    pub created_at: String,
+    pub data: serde_json::Value,
+}
+
+/// Whether an address is reachable on the public internet, rather than
+/// loopback, a private or link-local network, or another reserved range a
+/// webhook could use to reach the API's own network.
+fn is_public(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            !(v4.is_unspecified()
+                || v4.is_loopback()
+                || v4.is_private()
+                || v4.is_link_local()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || v4.is_multicast()
+                || a == 0
+                || (a == 100 && (64..128).contains(&b)) // Carrier-grade NAT
+                || (a == 192 && b == 0 && c == 0) // IETF protocol assignments
+                || (a == 198 && (18..20).contains(&b)) // Benchmarking
+                || a >= 240)
+        }
+        IpAddr::V6(v6) => match v6.to_ipv4_mapped() {
+            Some(v4) => is_public(IpAddr::V4(v4)),
+            None => {
+                let first = v6.segments()[0];
+                !(v6.is_unspecified()
+                    || v6.is_loopback()
+                    || v6.is_multicast()
+                    || (first & 0xfe00) == 0xfc00 // Unique local
+                    || (first & 0xffc0) == 0xfe80 // Link-local
+                    || (first == 0x2001 && v6.segments()[1] == 0x0db8)) // Documentation
+            }
+        },
+    }
+}
+
+pub struct WebhookService;
+
+impl WebhookService {
+    /// A new endpoint secret, e.g. `whsec_3f9c...`.
+    pub fn generate_secret() -> String {
+        use rand::RngCore;
+
+        let mut bytes = [0u8; 32];
+        rand::rngs::OsRng.fill_bytes(&mut bytes);
+        format!("whsec_{}", hex::encode(bytes))
+    }
+
+    /// The `SIGNATURE_HEADER` value for a body sent at `timestamp`.
+    pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+        let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts any key length");
+        mac.update(timestamp.to_string().as_bytes());
+        mac.update(b".");
+        mac.update(body);
+        format!("v1={}", hex::encode(mac.finalize().into_bytes()))
+    }
+
+    /// Checks a delivery the way receivers should: the signature must match
+    /// and the timestamp must be within `REPLAY_TOLERANCE_SECS` of `now`.
+    /// Receivers should also remember recent `ID_HEADER` values, since a
+    /// replay inside the window carries a valid signature.
+    pub fn verify(secret: &str, timestamp: &str, body: &[u8], signature: &str, now: i64) -> bool {
+        let Ok(timestamp) = timestamp.parse::<i64>() else {
+            return false;
+        };
+        if (now - timestamp).abs() > REPLAY_TOLERANCE_SECS {
+            return false;
+        }
+        constant_time_eq(Self::sign(secret, timestamp, body).as_bytes(), signature.as_bytes())
+    }
+
+    /// The addresses a webhook URL's host resolves to. Fails unless it
+    /// resolves and every address is public, so an endpoint can't aim
+    /// deliveries at internal services. Checked when an endpoint is
+    /// registered and again on each delivery, which connects only to the
+    /// addresses checked, since DNS can change in between.
+    pub async fn resolve_public(url: &reqwest::Url) -> anyhow::Result<Vec<SocketAddr>> {
+        let host = url
+            .host_str()
+            .ok_or_else(|| anyhow::anyhow!("Webhook URL has no host"))?;
+        let port = url
+            .port_or_known_default()
+            .ok_or_else(|| anyhow::anyhow!("Webhook URL has no port"))?;
+        // Brackets around an IPv6 literal aren't part of the address
+        let host = host.trim_start_matches('[').trim_end_matches(']');
+        let addrs: Vec<SocketAddr> = tokio::net::lookup_host((host, port)).await?.collect();
+        if addrs.is_empty() {
+            anyhow::bail!("{} doesn't resolve", host);
+        }
+        if let Some(addr) = addrs.iter().find(|addr| !is_public(addr.ip())) {
+            anyhow::bail!("{} resolves to {}, which isn't a public address", host, addr.ip());
+        }
+        Ok(addrs)
+    }
+
+    /// Signs and posts one event to an endpoint. Returns the HTTP status the
+    /// endpoint answered with.
+    pub async fn deliver(
+        url: &str,
+        secret: &str,
+        event_type: &str,
+        data: serde_json::Value,
+    ) -> anyhow::Result<u16> {
+        Ok(Self::send(url, secret, event_type, data).await?.status().as_u16())
+    }
+
+    /// Like `deliver`, for endpoints that answer with a JSON body. Fails on
+    /// anything but a 2xx.
+    pub async fn request(
+        url: &str,
+        secret: &str,
+        event_type: &str,
+        data: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        let response = Self::send(url, secret, event_type, data).await?;
+        if !response.status().is_success() {
+            anyhow::bail!("Endpoint answered {}", response.status());
+        }
+        Ok(response.json().await?)
+    }
+
+    async fn send(
+        url: &str,
+        secret: &str,
+        event_type: &str,
+        data: serde_json::Value,
+    ) -> anyhow::Result<reqwest::Response> {
+        let event = WebhookEvent {
+            id: Uuid::new_v4(),
+            event_type,
+            created_at: chrono::Utc::now().to_rfc3339(),
+            data,
+        };
+        let body = serde_json::to_vec(&event)?;
+        let timestamp = chrono::Utc::now().timestamp();
+
+        let url = reqwest::Url::parse(url)?;
+        let addrs = Self::resolve_public(&url).await?;
+        // Pinned to the checked addresses, and redirects aren't followed,
+        // since either could lead somewhere that wasn't checked
+        let mut client = Client::builder().redirect(reqwest::redirect::Policy::none());
+        if let Some(domain) = url.domain() {
+            client = client.resolve_to_addrs(domain, &addrs);
+        }
+
+        Ok(client
+            .build()?
+            .post(url)
+            .timeout(DELIVERY_TIMEOUT)
+            .header("Content-Type", "application/json")
+            .header(ID_HEADER, event.id.to_string())
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, Self::sign(secret, timestamp, &body))
+            .body(body)
+            .send()
+            .await?)
+    }
+
+    /// Sends an event to each of a lender's active event endpoints. Failures
+    /// are logged; one bad endpoint doesn't stop the others.
+    pub async fn dispatch(db: &PgPool, lender_id: Uuid, event_type: &str, data: serde_json::Value) -> anyhow::Result<()> {
+        let rows = sqlx::query(
+            "SELECT id, url, secret FROM webhook_endpoints WHERE lender_id = $1 AND active AND purpose = 'events'",
+        )
+        .bind(lender_id)
+        .fetch_all(db)
+        .await?;
+
+        for row in rows {
+            let endpoint_id: Uuid = row.try_get(0)?;
+            let url: String = row.try_get(1)?;
+            let secret: String = row.try_get(2)?;
+            match Self::deliver(&url, &secret, event_type, data.clone()).await {
+                Ok(status) if (200..300).contains(&status) => {}
+                Ok(status) => tracing::warn!("Webhook {} answered {} to {}", endpoint_id, status, event_type),
+                Err(e) => tracing::warn!("Webhook {} delivery of {} failed: {}", endpoint_id, event_type, e),
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,27 @@
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+/// Installs the global tracing subscriber. Logs are JSON when `LOG_FORMAT=json`
+/// or `APP_ENV=production`, human-readable otherwise.
+pub fn init(default_filter: &str) {
+    let filter = EnvFilter::try_from_default_env().unwrap_or_else(|_| default_filter.into());
+
+    let json = std::env::var("LOG_FORMAT").map(|f| f == "json").unwrap_or(false)
+        || std::env::var("APP_ENV").map(|e| e == "production").unwrap_or(false);
+
+    if json {
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(
+                tracing_subscriber::fmt::layer()
+                    .json()
+                    .with_current_span(true)
+                    .with_span_list(false),
+            )
+            .init();
+    } else {
+        tracing_subscriber::registry()
+            .with(filter)
+            .with(tracing_subscriber::fmt::layer())
+            .init();
+    }
+}
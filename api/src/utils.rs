@@ -1,4 +1,7 @@
-use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use std::sync::OnceLock;
+
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
 
@@ -6,61 +9,469 @@ use sha2::{Digest, Sha256};
 pub struct Claims {
     pub user_id: String,
     pub phone_number: String,
+    /// Tokens issued before roles existed belong to account owners
+    #[serde(default)]
+    pub role: crate::models::PhoneRole,
+    /// The `user_sessions` row a signed-in token belongs to; None for
+    /// merchant access tokens and for tokens issued before sessions existed,
+    /// which the auth middleware refuses
+    #[serde(rename = "sid", default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
     pub exp: usize,
 }
 
-pub fn generate_jwt(user_id: uuid::Uuid, phone_number: &str, secret: &str) -> anyhow::Result<String> {
-    let expiration = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::days(7))
-        .expect("valid timestamp")
-        .timestamp() as usize;
+impl Claims {
+    /// Rejects linked secondary phones from owner-only actions.
+    pub fn require_owner(&self) -> Result<(), crate::error::AppError> {
+        match self.role {
+            crate::models::PhoneRole::Owner => Ok(()),
+            _ => Err(crate::error::AppError::Auth(
+                "This action is only available to the account owner".to_string(),
+            )),
+        }
+    }
+}
+
+/// A key session tokens are checked against.
+#[derive(Clone)]
+struct VerifyingKey {
+    /// What tokens signed with it carry as `kid`; None for the HMAC secret
+    kid: Option<String>,
+    algorithm: Algorithm,
+    key: DecodingKey,
+}
+
+/// How session tokens are signed and checked. With RS256 or EdDSA, the
+/// public keys are published at `/.well-known/jwks.json`, so other services
+/// can check tokens without the HMAC secret.
+#[derive(Clone)]
+pub struct JwtKeys {
+    algorithm: Algorithm,
+    signing: EncodingKey,
+    /// The signing key's `kid`; None with HS256
+    kid: Option<String>,
+    /// Keys tokens are accepted from, the signing key's first
+    verifying: Vec<VerifyingKey>,
+    jwks: serde_json::Value,
+}
+
+impl std::fmt::Debug for JwtKeys {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let kids: Vec<_> = self.verifying.iter().map(|key| key.kid.as_deref().unwrap_or("hmac")).collect();
+        f.debug_struct("JwtKeys")
+            .field("algorithm", &self.algorithm)
+            .field("kids", &kids)
+            .finish_non_exhaustive()
+    }
+}
+
+/// DER prefix of an Ed25519 SubjectPublicKeyInfo; the 32-byte key follows.
+const ED25519_SPKI_PREFIX: [u8; 12] = [0x30, 0x2a, 0x30, 0x05, 0x06, 0x03, 0x2b, 0x65, 0x70, 0x03, 0x21, 0x00];
+
+impl JwtKeys {
+    /// Keys for `JWT_ALGORITHM`. RS256 and EdDSA sign with `private_key`
+    /// and check against `public_keys`, a PEM bundle that starts with its
+    /// public key and goes on with retired ones still accepted. With
+    /// `accept_hs256`, tokens signed with `secret` are still accepted too,
+    /// for switching from HS256 without signing everyone out.
+    pub fn load(
+        algorithm: &str,
+        secret: &str,
+        private_key: Option<&str>,
+        public_keys: Option<&str>,
+        accept_hs256: bool,
+    ) -> anyhow::Result<Self> {
+        let hmac = VerifyingKey {
+            kid: None,
+            algorithm: Algorithm::HS256,
+            key: DecodingKey::from_secret(secret.as_bytes()),
+        };
+        let algorithm = match algorithm {
+            "HS256" => {
+                if private_key.is_some() || public_keys.is_some() {
+                    anyhow::bail!("JWT_PRIVATE_KEY and JWT_PUBLIC_KEYS need JWT_ALGORITHM RS256 or EdDSA");
+                }
+                return Ok(Self {
+                    algorithm: Algorithm::HS256,
+                    signing: EncodingKey::from_secret(secret.as_bytes()),
+                    kid: None,
+                    verifying: vec![hmac],
+                    jwks: serde_json::json!({ "keys": [] }),
+                });
+            }
+            "RS256" => Algorithm::RS256,
+            "EdDSA" => Algorithm::EdDSA,
+            other => anyhow::bail!("JWT_ALGORITHM must be HS256, RS256 or EdDSA, not {}", other),
+        };
+        let (Some(private_key), Some(public_keys)) = (private_key, public_keys) else {
+            anyhow::bail!("JWT_ALGORITHM {:?} needs JWT_PRIVATE_KEY and JWT_PUBLIC_KEYS", algorithm);
+        };
+        let signing = match algorithm {
+            Algorithm::RS256 => EncodingKey::from_rsa_pem(private_key.as_bytes())?,
+            _ => EncodingKey::from_ed_pem(private_key.as_bytes())?,
+        };
+
+        let mut verifying = Vec::new();
+        let mut jwks = Vec::new();
+        for pem in public_keys.split_inclusive("-----END PUBLIC KEY-----").map(str::trim) {
+            if pem.is_empty() {
+                continue;
+            }
+            let (key_algorithm, kid, jwk) = public_jwk(pem)?;
+            let key = match key_algorithm {
+                Algorithm::RS256 => DecodingKey::from_rsa_pem(pem.as_bytes())?,
+                _ => DecodingKey::from_ed_pem(pem.as_bytes())?,
+            };
+            verifying.push(VerifyingKey {
+                kid: Some(kid),
+                algorithm: key_algorithm,
+                key,
+            });
+            jwks.push(jwk);
+        }
+
+        // The private key must be the first public key's other half
+        let current = verifying.first().ok_or_else(|| anyhow::anyhow!("JWT_PUBLIC_KEYS has no keys"))?;
+        let mut validation = Validation::new(algorithm);
+        validation.required_spec_claims.clear();
+        validation.validate_exp = false;
+        let probe = encode(&Header::new(algorithm), &serde_json::json!({}), &signing)?;
+        if current.algorithm != algorithm || decode::<serde_json::Value>(&probe, &current.key, &validation).is_err() {
+            anyhow::bail!("JWT_PUBLIC_KEYS must start with JWT_PRIVATE_KEY's public key");
+        }
+        let kid = current.kid.clone();
+
+        if accept_hs256 {
+            verifying.push(hmac);
+        }
+        Ok(Self {
+            algorithm,
+            signing,
+            kid,
+            verifying,
+            jwks: serde_json::json!({ "keys": jwks }),
+        })
+    }
+
+    /// The public keys as a JWK set, the signing key's first.
+    pub fn jwks(&self) -> &serde_json::Value {
+        &self.jwks
+    }
+}
+
+/// The algorithm a PEM public key verifies, its RFC 7638 thumbprint, which
+/// serves as its `kid`, and its JWK.
+fn public_jwk(pem: &str) -> anyhow::Result<(Algorithm, String, serde_json::Value)> {
+    use base64::engine::general_purpose::{STANDARD, URL_SAFE_NO_PAD};
+    use base64::Engine;
+    use rsa::pkcs8::DecodePublicKey;
+    use rsa::traits::PublicKeyParts;
+
+    if let Ok(key) = rsa::RsaPublicKey::from_public_key_pem(pem) {
+        let n = URL_SAFE_NO_PAD.encode(key.n().to_bytes_be());
+        let e = URL_SAFE_NO_PAD.encode(key.e().to_bytes_be());
+        let kid = URL_SAFE_NO_PAD.encode(Sha256::digest(format!(r#"{{"e":"{}","kty":"RSA","n":"{}"}}"#, e, n)));
+        let jwk = serde_json::json!({ "kty": "RSA", "use": "sig", "alg": "RS256", "kid": kid, "n": n, "e": e });
+        return Ok((Algorithm::RS256, kid, jwk));
+    }
+
+    let body: String = pem.lines().filter(|line| !line.starts_with("-----")).collect();
+    let der = STANDARD.decode(body.trim())?;
+    let x = der
+        .strip_prefix(&ED25519_SPKI_PREFIX[..])
+        .filter(|x| x.len() == 32)
+        .ok_or_else(|| anyhow::anyhow!("JWT_PUBLIC_KEYS has a key that is neither RSA nor Ed25519"))?;
+    let x = URL_SAFE_NO_PAD.encode(x);
+    let kid = URL_SAFE_NO_PAD.encode(Sha256::digest(format!(r#"{{"crv":"Ed25519","kty":"OKP","x":"{}"}}"#, x)));
+    let jwk = serde_json::json!({ "kty": "OKP", "crv": "Ed25519", "use": "sig", "alg": "EdDSA", "kid": kid, "x": x });
+    Ok((Algorithm::EdDSA, kid, jwk))
+}
 
+/// Signs a token for a sign-in, valid until its session expires.
+pub fn generate_jwt(
+    user_id: uuid::Uuid,
+    phone_number: &str,
+    role: crate::models::PhoneRole,
+    session_id: uuid::Uuid,
+    expires_at: chrono::DateTime<chrono::Utc>,
+    keys: &JwtKeys,
+) -> anyhow::Result<String> {
     let claims = Claims {
         user_id: user_id.to_string(),
         phone_number: phone_number.to_string(),
-        exp: expiration,
+        role,
+        session_id: Some(session_id.to_string()),
+        exp: expires_at.timestamp() as usize,
     };
 
-    let token = encode(
-        &Header::default(),
-        &claims,
-        &EncodingKey::from_secret(secret.as_ref()),
-    )?;
+    let mut header = Header::new(keys.algorithm);
+    header.kid = keys.kid.clone();
+    let token = encode(&header, &claims, &keys.signing)?;
 
     Ok(token)
 }
 
-pub fn verify_jwt(token: &str, secret: &str) -> anyhow::Result<Claims> {
-    let token_data = decode::<Claims>(
-        token,
-        &DecodingKey::from_secret(secret.as_ref()),
-        &Validation::default(),
-    )?;
+/// Checks a token against the key its header names: a current or retired
+/// public key by `kid`, or the HMAC secret for tokens without one.
+pub fn verify_jwt(token: &str, keys: &JwtKeys) -> anyhow::Result<Claims> {
+    let header = jsonwebtoken::decode_header(token)?;
+    let key = keys
+        .verifying
+        .iter()
+        .find(|key| key.kid == header.kid && key.algorithm == header.alg)
+        .ok_or_else(|| anyhow::anyhow!("Token signed with an unknown key"))?;
+    let token_data = decode::<Claims>(token, &key.key, &Validation::new(key.algorithm))?;
 
     Ok(token_data.claims)
 }
 
-pub fn hash_phone_number(phone: &str) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(phone.as_bytes());
-    let result = hasher.finalize();
-    hex::encode(result)
+/// A pepper phone numbers, till numbers and receipt numbers are hashed
+/// under, with the id hashes made under it are tagged with.
+#[derive(Clone)]
+pub struct HashKey {
+    pub id: String,
+    pub secret: String,
 }
 
-pub fn generate_verification_code() -> String {
-    use std::collections::hash_map::DefaultHasher;
-    use std::hash::{Hash, Hasher};
+impl HashKey {
+    /// Parses a `PHONE_HASH_KEYS` entry, `<id>:<secret>`.
+    pub fn parse(entry: &str) -> anyhow::Result<Self> {
+        let Some((id, secret)) = entry.split_once(':') else {
+            anyhow::bail!("PHONE_HASH_KEYS entries must be <id>:<secret>");
+        };
+        if id.is_empty() || id.len() > 8 || !id.bytes().all(|b| b.is_ascii_alphanumeric()) {
+            anyhow::bail!("PHONE_HASH_KEYS ids must be 1 to 8 letters or digits");
+        }
+        if secret.len() < 32 {
+            anyhow::bail!("PHONE_HASH_KEYS secret {} must be at least 32 characters", id);
+        }
+        Ok(Self {
+            id: id.to_string(),
+            secret: secret.to_string(),
+        })
+    }
+}
 
-    let mut hasher = DefaultHasher::new();
-    uuid::Uuid::new_v4().to_string().hash(&mut hasher);
-    chrono::Utc::now().timestamp().hash(&mut hasher);
+impl std::fmt::Debug for HashKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HashKey").field("id", &self.id).finish_non_exhaustive()
+    }
+}
 
-    let hash = hasher.finish();
-    use base64::Engine;
-    base64::engine::general_purpose::URL_SAFE_NO_PAD
-        .encode(format!("{:x}", hash))
-        .chars()
-        .take(12)
+static HASH_KEYS: OnceLock<Vec<HashKey>> = OnceLock::new();
+
+/// Sets the peppers, oldest first, for the rest of the process. Called by
+/// `Config::from_env`, since hashing happens deep in parsers with no config
+/// at hand; later calls are ignored.
+pub fn install_hash_keys(keys: &[HashKey]) {
+    let _ = HASH_KEYS.set(keys.to_vec());
+}
+
+fn hash_keys() -> &'static [HashKey] {
+    HASH_KEYS.get().map(Vec::as_slice).expect("hash keys are installed with the config")
+}
+
+/// The unkeyed SHA-256 stored before peppers, which every keyed hash is
+/// layered on.
+fn unkeyed_hash(value: &str) -> String {
+    hex::encode(Sha256::digest(value.as_bytes()))
+}
+
+fn keyed_hash(key: &HashKey, inner: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key.secret.as_bytes()).expect("HMAC accepts any key length");
+    mac.update(inner.as_bytes());
+    format!("{}:{}", key.id, hex::encode(mac.finalize().into_bytes()))
+}
+
+/// Hashes a phone number, till number or receipt number for storage, so
+/// nobody without the peppers can precompute hashes of every Kenyan MSISDN.
+/// Each pepper in turn applies HMAC-SHA256 to the previous layer, starting
+/// from the unkeyed SHA-256, so hashes already stored can be brought up to
+/// a new pepper without the numbers (`rehash_phone_number`).
+pub fn hash_phone_number(value: &str) -> String {
+    hash_keys().iter().fold(unkeyed_hash(value), |hash, key| keyed_hash(key, &hash))
+}
+
+/// SHA-256 of an identifier that's published to lenders or committed in a
+/// proof's journal, like a PayBill account or a business id. Lenders compute
+/// it from the number they know, and it must not change when peppers
+/// rotate, so it's unkeyed; what's stored for lookups uses
+/// `hash_phone_number`.
+pub fn public_identifier_hash(value: &str) -> String {
+    unkeyed_hash(value)
+}
+
+/// Every form `value` can be stored in until the worker has rehashed it:
+/// the current hash first, then its hash under each older pepper, down to
+/// the unkeyed one. For lookups by hash.
+pub fn phone_number_hashes(value: &str) -> Vec<String> {
+    let mut hashes = vec![unkeyed_hash(value)];
+    for key in hash_keys() {
+        let hash = keyed_hash(key, &hashes[hashes.len() - 1]);
+        hashes.push(hash);
+    }
+    hashes.reverse();
+    hashes
+}
+
+/// Brings a stored hash up to the newest pepper by adding the layers it's
+/// missing. None when it's current, or tagged with a pepper that's no
+/// longer configured.
+pub fn rehash_phone_number(stored: &str) -> Option<String> {
+    let keys = hash_keys();
+    let missing = match stored.split_once(':') {
+        Some((id, _)) => &keys[keys.iter().position(|key| key.id == id)? + 1..],
+        None => keys,
+    };
+    if missing.is_empty() {
+        return None;
+    }
+    Some(missing.iter().fold(stored.to_string(), |hash, key| keyed_hash(key, &hash)))
+}
+
+/// The id the newest pepper tags hashes with.
+pub fn current_hash_key_id() -> &'static str {
+    hash_keys().last().map_or("", |key| key.id.as_str())
+}
+
+/// Crockford's base32 alphabet. It leaves out I, L, O and U, so a code read
+/// aloud or copied by hand has no look-alike characters.
+const CROCKFORD_ALPHABET: &[u8; 32] = b"0123456789ABCDEFGHJKMNPQRSTVWXYZ";
+
+/// Characters in a verification code: 128 bits at 5 per character.
+pub const VERIFICATION_CODE_LEN: usize = 26;
+
+/// A new public verification code: 128 random bits from the OS as 26
+/// Crockford base32 characters, too many to enumerate through the throttled
+/// lookup.
+pub fn generate_verification_code() -> String {
+    use rand::RngCore;
+
+    let mut bytes = [0u8; 16];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    let value = u128::from_be_bytes(bytes);
+    (0..VERIFICATION_CODE_LEN)
+        .rev()
+        .map(|i| CROCKFORD_ALPHABET[((value >> (i * 5)) & 0x1f) as usize] as char)
         .collect()
 }
 
+/// A verification code as stored. Crockford codes are read the way their
+/// alphabet intends: case-insensitively, ignoring hyphens and spaces, with
+/// I and L as 1 and O as 0. Codes issued before them are matched exactly.
+pub fn normalize_verification_code(code: &str) -> String {
+    let code = code.trim();
+    let compact: Vec<char> = code.chars().filter(|c| *c != '-' && !c.is_whitespace()).collect();
+    if compact.len() != VERIFICATION_CODE_LEN {
+        return code.to_string();
+    }
+    let normalized: Option<String> = compact
+        .iter()
+        .map(|c| match c.to_ascii_uppercase() {
+            'I' | 'L' => Some('1'),
+            'O' => Some('0'),
+            c if c.is_ascii() && CROCKFORD_ALPHABET.contains(&(c as u8)) => Some(c),
+            _ => None,
+        })
+        .collect();
+    normalized.unwrap_or_else(|| code.to_string())
+}
+
+/// A new referral code such as `K7QM2XPA`, in capitals and digits that
+/// can't be misread for one another when typed from a flyer.
+pub fn generate_referral_code() -> String {
+    use rand::Rng;
+
+    const ALPHABET: &[u8] = b"ABCDEFGHJKLMNPQRSTUVWXYZ23456789";
+    let mut rng = rand::rngs::OsRng;
+    (0..8).map(|_| ALPHABET[rng.gen_range(0..ALPHABET.len())] as char).collect()
+}
+
+/// Compares two secrets without short-circuiting on the first mismatch.
+pub fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// Generates a lender API key such as `mcp_live_3f9c...`. Sandbox keys use
+/// the `mcp_test_` prefix so they're recognisable in logs and configs.
+pub fn generate_api_key(sandbox: bool) -> String {
+    use rand::RngCore;
+
+    let mut bytes = [0u8; 24];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    let prefix = if sandbox { "mcp_test_" } else { "mcp_live_" };
+    format!("{}{}", prefix, hex::encode(bytes))
+}
+
+/// Characters after a lender key's `mcp_live_` or `mcp_test_` prefix that
+/// find its row, so a presented key is checked against one hash.
+const API_KEY_LOOKUP_LEN: usize = 12;
+
+/// The part of a lender key its row is found by, or None for anything
+/// `generate_api_key` couldn't have issued.
+pub fn api_key_lookup_id(key: &str) -> Option<&str> {
+    let random = key.strip_prefix("mcp_live_").or_else(|| key.strip_prefix("mcp_test_"))?;
+    let lookup_id = random.get(..API_KEY_LOOKUP_LEN)?;
+    lookup_id.bytes().all(|b| b.is_ascii_hexdigit()).then_some(lookup_id)
+}
+
+/// Hashes a lender key with Argon2id and a random salt, as a PHC string.
+/// Slow on purpose; call from a blocking task.
+pub fn hash_lender_key(key: &str) -> anyhow::Result<String> {
+    use argon2::password_hash::{rand_core::OsRng, PasswordHasher, SaltString};
+
+    let salt = SaltString::generate(&mut OsRng);
+    let hash = argon2::Argon2::default()
+        .hash_password(key.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("Failed to hash API key: {}", e))?;
+    Ok(hash.to_string())
+}
+
+/// Whether `key` matches a `hash_lender_key` hash. Slow on purpose; call
+/// from a blocking task.
+pub fn verify_lender_key(key: &str, hash: &str) -> bool {
+    use argon2::password_hash::{PasswordHash, PasswordVerifier};
+
+    PasswordHash::new(hash)
+        .is_ok_and(|hash| argon2::Argon2::default().verify_password(key.as_bytes(), &hash).is_ok())
+}
+
+/// Prefix of merchant access tokens, which are sent as bearer tokens in
+/// place of a JWT.
+pub const ACCESS_TOKEN_PREFIX: &str = "mcp_pat_";
+
+/// Generates a merchant access token such as `mcp_pat_3f9c...`.
+pub fn generate_access_token() -> String {
+    use rand::RngCore;
+
+    let mut bytes = [0u8; 24];
+    rand::rngs::OsRng.fill_bytes(&mut bytes);
+    format!("{}{}", ACCESS_TOKEN_PREFIX, hex::encode(bytes))
+}
+
+/// SHA-256 of a high-entropy token, for merchant access tokens, handoff
+/// tokens and lender keys issued before Argon2.
+pub fn hash_api_key(key: &str) -> String {
+    hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// The instant a wall-clock time in `tz` names. A time repeated when clocks
+/// go back is taken as its first occurrence; one skipped when they go
+/// forward names no instant.
+pub fn local_to_utc(local: chrono::NaiveDateTime, tz: chrono_tz::Tz) -> Option<chrono::DateTime<chrono::Utc>> {
+    use chrono::TimeZone;
+
+    tz.from_local_datetime(&local)
+        .earliest()
+        .map(|dt| dt.with_timezone(&chrono::Utc))
+}
+
+/// Seconds east of UTC `tz` is at `at`, as the guest takes local days in.
+pub fn utc_offset_secs(tz: chrono_tz::Tz, at: chrono::DateTime<chrono::Utc>) -> i32 {
+    use chrono::{Offset, TimeZone};
+
+    tz.offset_from_utc_datetime(&at.naive_utc()).fix().local_minus_utc()
+}
@@ -0,0 +1,680 @@
+pub mod scheduler;
+
+use redis::AsyncCommands;
+use sqlx::{PgPool, Row};
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::{error, info, warn};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::i18n::{Locale, Message};
+use crate::db::repos::import_jobs::ImportJob;
+use crate::db::repos::{
+    CurrencyRepo, ImageIdRepo, ImportJobRepo, ProofQueueRepo, ScoringPolicyRepo, SessionRepo, TransactionRepo,
+};
+use crate::models::{ProofStatus, SessionStage, DEFAULT_CURRENCY};
+use crate::services::authenticity::AuthenticityService;
+use crate::services::expiry_reminder::ExpiryReminderService;
+use crate::services::maintenance::{MaintenanceService, PARTITION_MONTHS_AHEAD};
+use crate::services::market_stats::MarketStatsService;
+use crate::services::notification::{NotificationService, OwnerNotification};
+use crate::services::outbox::{OutboxService, RELAY_BATCH};
+use crate::services::payments::PaymentService;
+use crate::services::proof::ProofService;
+use crate::services::proof_queue::{with_redis_retry, ProofQueueService};
+use crate::services::storage::StorageBackend;
+use scheduler::Scheduler;
+
+/// List of session ids waiting to be proved; pushed on the left, popped on
+/// the right. The `proof_queue` table is the source of truth; see
+/// `services::proof_queue`.
+pub const PROOF_QUEUE_KEY: &str = "proof_queue";
+/// List of import job ids waiting to run, fed by the job outbox. The
+/// `import_jobs` table is the source of truth.
+pub const IMPORT_QUEUE_KEY: &str = "import_queue";
+/// An import job not finished within this long goes back on the queue.
+pub const IMPORT_LEASE_SECS: i64 = 600;
+/// Sorted set of worker ids scored by their last heartbeat (unix seconds).
+pub const HEARTBEAT_KEY: &str = "worker_heartbeats";
+/// Hash of queue metrics for autoscalers, refreshed with each heartbeat:
+/// `queue_depth`, `leased_jobs`, `active_workers`,
+/// `avg_proving_seconds_last_hour` (empty when nothing finished) and
+/// `updated_at` (unix seconds), plus `avg_proving_seconds_last_hour:<backend>`
+/// for each prover backend that finished a proof. The values are decimal
+/// strings.
+pub const AUTOSCALING_KEY: &str = "autoscaling:proof_queue";
+/// Workers silent for longer than this are considered gone.
+pub const HEARTBEAT_TIMEOUT_SECS: i64 = 30;
+const HEARTBEAT_INTERVAL: Duration = Duration::from_secs(10);
+/// Longest a scheduled job is expected to run before another worker may
+/// start its next run.
+const SCHEDULED_JOB_LOCK_TTL: Duration = Duration::from_secs(30 * 60);
+/// A job whose lease isn't renewed within this long is handed to another worker.
+const LEASE_SECS: i64 = 120;
+const LEASE_RENEW_INTERVAL: Duration = Duration::from_secs(30);
+const LEASE_REAP_INTERVAL: Duration = Duration::from_secs(60);
+/// How often the job outbox is checked once it's been emptied.
+const OUTBOX_RELAY_INTERVAL: Duration = Duration::from_secs(1);
+/// Queued sessions tried at once when claiming from the database.
+const DATABASE_CLAIM_CANDIDATES: i64 = 10;
+/// Interrupted jobs are retried this many times in total before failing.
+const MAX_ATTEMPTS: i32 = 3;
+
+pub struct Worker {
+    db: PgPool,
+    redis: redis::Client,
+    config: Config,
+    storage: Arc<dyn StorageBackend>,
+    /// Identifies this replica in heartbeats and job leases
+    worker_id: String,
+}
+
+impl Worker {
+    pub fn new(db: PgPool, redis: redis::Client, config: Config, storage: Arc<dyn StorageBackend>) -> Self {
+        Self {
+            db,
+            redis,
+            config,
+            storage,
+            worker_id: Uuid::new_v4().to_string(),
+        }
+    }
+
+    /// Runs until the process stops, proving up to `max_parallel_proofs`
+    /// sessions at once.
+    pub async fn run(self: Arc<Self>) -> anyhow::Result<()> {
+        info!("Starting proof generation worker...");
+        ProofService::check_accelerator(&self.config)?;
+        self.register_image().await?;
+
+        // Heartbeats run on their own task so a long proof doesn't make the
+        // worker look dead
+        let redis = self.redis.clone();
+        let db = self.db.clone();
+        let worker_id = self.worker_id.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = Self::heartbeat(&redis, &worker_id).await {
+                    error!("Failed to record worker heartbeat: {}", e);
+                }
+                if let Err(e) = Self::publish_autoscaling_metrics(&redis, &db).await {
+                    error!("Failed to publish autoscaling metrics: {}", e);
+                }
+                tokio::time::sleep(HEARTBEAT_INTERVAL).await;
+            }
+        });
+
+        let redis = self.redis.clone();
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            loop {
+                if let Err(e) = Self::requeue_expired_leases(&redis, &db).await {
+                    error!("Failed to requeue expired jobs: {}", e);
+                }
+                tokio::time::sleep(LEASE_REAP_INTERVAL).await;
+            }
+        });
+
+        // Every worker relays; locked rows are skipped, so they share the
+        // outbox without pushing a job twice
+        let redis = self.redis.clone();
+        let db = self.db.clone();
+        tokio::spawn(async move {
+            loop {
+                match OutboxService::relay(&db, &redis).await {
+                    // More may be waiting
+                    Ok(pushed) if pushed as i64 == RELAY_BATCH => continue,
+                    Ok(_) => {}
+                    Err(e) => error!("Failed to relay queued jobs to Redis: {}", e),
+                }
+                tokio::time::sleep(OUTBOX_RELAY_INTERVAL).await;
+            }
+        });
+
+        // Import jobs take seconds, so one at a time beside the proofs is enough
+        let worker = Arc::clone(&self);
+        tokio::spawn(async move {
+            loop {
+                match worker.claim_next_import().await {
+                    Ok(Some(job)) => worker.run_import(job).await,
+                    Ok(None) => tokio::time::sleep(Duration::from_secs(1)).await,
+                    Err(e) => {
+                        error!("Error claiming import job: {}", e);
+                        tokio::time::sleep(Duration::from_secs(5)).await;
+                    }
+                }
+            }
+        });
+
+        self.scheduler()?.spawn();
+
+        let slots = Arc::new(tokio::sync::Semaphore::new(self.config.max_parallel_proofs));
+        info!("Proving up to {} sessions at once", self.config.max_parallel_proofs);
+        loop {
+            // Only take a job off the queue once there's a slot to prove it in
+            let permit = slots.clone().acquire_owned().await?;
+            match self.claim_next_job().await {
+                Ok(Some(session_id)) => {
+                    let worker = Arc::clone(&self);
+                    tokio::spawn(async move {
+                        if let Err(e) = worker.run_job(session_id).await {
+                            error!("Error processing session {}: {}", session_id, e);
+                        }
+                        drop(permit);
+                    });
+                }
+                Ok(None) => {
+                    // No jobs available, wait a bit
+                    tokio::time::sleep(Duration::from_secs(1)).await;
+                }
+                Err(e) => {
+                    error!("Error processing job: {}", e);
+                    tokio::time::sleep(Duration::from_secs(5)).await;
+                }
+            }
+        }
+    }
+
+    /// The periodic jobs, on the schedules configured for them.
+    fn scheduler(&self) -> anyhow::Result<Scheduler> {
+        let mut scheduler = Scheduler::new(self.db.clone(), self.redis.clone(), self.worker_id.clone());
+
+        let db = self.db.clone();
+        scheduler.register(
+            "transaction_partitions",
+            &self.config.partition_maintenance_schedule,
+            SCHEDULED_JOB_LOCK_TTL,
+            move || {
+                let db = db.clone();
+                async move {
+                    let created = MaintenanceService::ensure_transaction_partitions(&db, PARTITION_MONTHS_AHEAD).await?;
+                    Ok(format!("Created {} transaction partitions", created))
+                }
+            },
+        )?;
+
+        let db = self.db.clone();
+        let min_group_size = self.config.market_stats_min_group_size;
+        scheduler.register(
+            "market_stats",
+            &self.config.market_stats_schedule,
+            SCHEDULED_JOB_LOCK_TTL,
+            move || {
+                let db = db.clone();
+                async move {
+                    let stats = MarketStatsService::refresh(&db, min_group_size).await?;
+                    Ok(format!(
+                        "Refreshed statistics for {} sectors and {} counties",
+                        stats.by_sector.len(),
+                        stats.by_county.len()
+                    ))
+                }
+            },
+        )?;
+
+        let db = self.db.clone();
+        let config = self.config.clone();
+        scheduler.register(
+            "expiry_reminders",
+            &self.config.expiry_reminder_schedule,
+            SCHEDULED_JOB_LOCK_TTL,
+            move || {
+                let db = db.clone();
+                let config = config.clone();
+                async move {
+                    let sent = ExpiryReminderService::send_due(&db, &config).await?;
+                    Ok(format!("Sent {} proof expiry reminders", sent))
+                }
+            },
+        )?;
+
+        let db = self.db.clone();
+        let redis = self.redis.clone();
+        scheduler.register(
+            "proof_queue_reconciliation",
+            &self.config.proof_queue_reconcile_schedule,
+            SCHEDULED_JOB_LOCK_TTL,
+            move || {
+                let db = db.clone();
+                let redis = redis.clone();
+                async move {
+                    let pushed = ProofQueueService::reconcile(&db, &redis).await?;
+                    Ok(format!("Pushed {} waiting sessions back onto Redis", pushed))
+                }
+            },
+        )?;
+
+        let db = self.db.clone();
+        let config = self.config.clone();
+        scheduler.register(
+            "held_notifications",
+            &self.config.held_notification_schedule,
+            SCHEDULED_JOB_LOCK_TTL,
+            move || {
+                let db = db.clone();
+                let config = config.clone();
+                async move {
+                    let sent = NotificationService::release_held(&db, &config).await?;
+                    Ok(format!("Sent {} notifications held through quiet hours", sent))
+                }
+            },
+        )?;
+
+        let db = self.db.clone();
+        scheduler.register(
+            "phone_hash_rehash",
+            &self.config.phone_hash_rehash_schedule,
+            SCHEDULED_JOB_LOCK_TTL,
+            move || {
+                let db = db.clone();
+                async move {
+                    let outcome = MaintenanceService::rehash_transactions(&db).await?;
+                    Ok(format!(
+                        "Rehashed {} transactions under the current pepper, merged {} duplicates, skipped {}",
+                        outcome.rehashed, outcome.merged, outcome.skipped
+                    ))
+                }
+            },
+        )?;
+
+        let db = self.db.clone();
+        let config = self.config.clone();
+        scheduler.register(
+            "payment_reconciliation",
+            &self.config.payment_reconcile_schedule,
+            SCHEDULED_JOB_LOCK_TTL,
+            move || {
+                let db = db.clone();
+                let config = config.clone();
+                async move {
+                    let settled = PaymentService::reconcile(&db, &config).await?;
+                    Ok(format!("Settled {} STK push payments that had no callback", settled))
+                }
+            },
+        )?;
+
+        let db = self.db.clone();
+        let storage = self.storage.clone();
+        scheduler.register(
+            "upload_cleanup",
+            &self.config.upload_cleanup_schedule,
+            SCHEDULED_JOB_LOCK_TTL,
+            move || {
+                let db = db.clone();
+                let storage = storage.clone();
+                async move {
+                    let removed = MaintenanceService::delete_expired_uploads(&db, storage.as_ref()).await?;
+                    Ok(format!("Deleted {} expired uploads and their chunks", removed))
+                }
+            },
+        )?;
+
+        Ok(scheduler)
+    }
+
+    async fn heartbeat(redis: &redis::Client, worker_id: &str) -> anyhow::Result<()> {
+        let mut conn = redis.get_async_connection().await?;
+        let now = chrono::Utc::now().timestamp();
+        conn.zadd::<_, _, _, ()>(HEARTBEAT_KEY, worker_id, now).await?;
+        conn.zrembyscore::<_, _, _, ()>(HEARTBEAT_KEY, 0, now - HEARTBEAT_TIMEOUT_SECS).await?;
+        Ok(())
+    }
+
+    /// Writes the `AUTOSCALING_KEY` hash.
+    async fn publish_autoscaling_metrics(redis: &redis::Client, db: &PgPool) -> anyhow::Result<()> {
+        let mut conn = redis.get_async_connection().await?;
+        let now = chrono::Utc::now().timestamp();
+        let queue_depth = ProofQueueRepo::depth(db).await?;
+        let active_workers: i64 = conn.zcount(HEARTBEAT_KEY, now - HEARTBEAT_TIMEOUT_SECS, "+inf").await?;
+        let leased_jobs = SessionRepo::leased_count(db).await?;
+        let avg_proving_seconds: Option<f64> = sqlx::query_scalar(
+            r#"
+            SELECT AVG(EXTRACT(EPOCH FROM proving_finished_at - proving_started_at))::FLOAT8
+            FROM proof_sessions
+            WHERE proving_finished_at > NOW() - INTERVAL '1 hour'
+              AND proving_started_at IS NOT NULL
+            "#,
+        )
+        .fetch_one(db)
+        .await?;
+        let by_backend: Vec<(String, f64)> = sqlx::query_as(
+            r#"
+            SELECT prover_backend, AVG(EXTRACT(EPOCH FROM proving_finished_at - proving_started_at))::FLOAT8
+            FROM proof_sessions
+            WHERE proving_finished_at > NOW() - INTERVAL '1 hour'
+              AND proving_started_at IS NOT NULL
+              AND prover_backend IS NOT NULL
+            GROUP BY prover_backend
+            "#,
+        )
+        .fetch_all(db)
+        .await?;
+
+        let mut fields = vec![
+            ("queue_depth".to_string(), queue_depth.to_string()),
+            ("leased_jobs".to_string(), leased_jobs.to_string()),
+            ("active_workers".to_string(), active_workers.to_string()),
+            (
+                "avg_proving_seconds_last_hour".to_string(),
+                avg_proving_seconds.map(|s| format!("{:.1}", s)).unwrap_or_default(),
+            ),
+            ("updated_at".to_string(), now.to_string()),
+        ];
+        for (backend, seconds) in by_backend {
+            fields.push((format!("avg_proving_seconds_last_hour:{}", backend), format!("{:.1}", seconds)));
+        }
+
+        conn.hset_multiple::<_, _, _, ()>(AUTOSCALING_KEY, &fields).await?;
+        Ok(())
+    }
+
+    /// Puts jobs abandoned by crashed workers back on the front of the queue.
+    async fn requeue_expired_leases(redis: &redis::Client, db: &PgPool) -> anyhow::Result<()> {
+        let import_ids = ImportJobRepo::reclaim_expired(db, MAX_ATTEMPTS, IMPORT_LEASE_SECS).await?;
+        if !import_ids.is_empty() {
+            let values: Vec<String> = import_ids.iter().map(Uuid::to_string).collect();
+            let pushed = with_redis_retry(|| async {
+                let mut conn = redis.get_async_connection().await?;
+                conn.rpush::<_, _, ()>(IMPORT_QUEUE_KEY, &values).await
+            })
+            .await;
+            if let Err(e) = pushed {
+                warn!("{} import jobs requeued without Redis: {}", import_ids.len(), e);
+            }
+            info!("Requeued {} import jobs", import_ids.len());
+        }
+
+        let session_ids = SessionRepo::reclaim_expired_leases(db, MAX_ATTEMPTS).await?;
+        if session_ids.is_empty() {
+            return Ok(());
+        }
+
+        ProofQueueService::requeue(redis, &session_ids).await;
+        info!("Requeued {} jobs with expired leases", session_ids.len());
+        Ok(())
+    }
+
+    /// Processes at most one queued job. Returns whether a job was found.
+    pub async fn run_once(&self) -> anyhow::Result<bool> {
+        ProofService::check_accelerator(&self.config)?;
+        self.register_image().await?;
+        self.process_next_job().await
+    }
+
+    /// Adds this build's guest to the image registry so the receipts it
+    /// produces verify.
+    async fn register_image(&self) -> anyhow::Result<()> {
+        let image_id = ProofService::current_image_id();
+        ImageIdRepo::ensure_registered(&self.db, &image_id, methods::GUEST_VERSION).await?;
+        info!(
+            "Proving with guest {} (image {}) on {}",
+            methods::GUEST_VERSION,
+            image_id,
+            ProofService::prover_backend(&self.config)
+        );
+        Ok(())
+    }
+
+    async fn process_next_job(&self) -> anyhow::Result<bool> {
+        match self.claim_next_job().await? {
+            Some(session_id) => {
+                self.run_job(session_id).await?;
+                Ok(true)
+            }
+            None => Ok(false),
+        }
+    }
+
+    /// Pops queued sessions until one can be leased to this worker. `None`
+    /// when the queue stayed empty. While Redis is unreachable, sessions are
+    /// taken from the `proof_queue` table instead.
+    async fn claim_next_job(&self) -> anyhow::Result<Option<Uuid>> {
+        loop {
+            // Blocking pop from queue (wait up to 5 seconds)
+            let popped: redis::RedisResult<Option<(String, String)>> = with_redis_retry(|| async {
+                let mut conn = self.redis.get_async_connection().await?;
+                conn.brpop(PROOF_QUEUE_KEY, 5.0).await
+            })
+            .await;
+            let result = match popped {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!("Redis unavailable, taking jobs from the database: {}", e);
+                    return self.claim_from_database().await;
+                }
+            };
+
+            let Some((_, session_id_str)) = result else {
+                return Ok(None);
+            };
+            let session_id =
+                uuid::Uuid::parse_str(&session_id_str).map_err(|e| anyhow::anyhow!("Invalid UUID: {}", e))?;
+
+            if self.claim(session_id).await? {
+                return Ok(Some(session_id));
+            }
+            info!("Skipping session {}: leased by another worker or already finished", session_id);
+        }
+    }
+
+    /// Pops queued import jobs until one can be claimed. While Redis is
+    /// unreachable, jobs are taken from the `import_jobs` table instead.
+    async fn claim_next_import(&self) -> anyhow::Result<Option<ImportJob>> {
+        loop {
+            let popped: redis::RedisResult<Option<(String, String)>> = with_redis_retry(|| async {
+                let mut conn = self.redis.get_async_connection().await?;
+                conn.brpop(IMPORT_QUEUE_KEY, 5.0).await
+            })
+            .await;
+            let result = match popped {
+                Ok(result) => result,
+                Err(e) => {
+                    warn!("Redis unavailable, taking import jobs from the database: {}", e);
+                    for job_id in ImportJobRepo::oldest_pending(&self.db, DATABASE_CLAIM_CANDIDATES).await? {
+                        if let Some(job) = ImportJobRepo::claim(&self.db, job_id, IMPORT_LEASE_SECS).await? {
+                            return Ok(Some(job));
+                        }
+                    }
+                    return Ok(None);
+                }
+            };
+
+            let Some((_, job_id_str)) = result else {
+                return Ok(None);
+            };
+            let job_id = Uuid::parse_str(&job_id_str).map_err(|e| anyhow::anyhow!("Invalid UUID: {}", e))?;
+            if let Some(job) = ImportJobRepo::claim(&self.db, job_id, IMPORT_LEASE_SECS).await? {
+                return Ok(Some(job));
+            }
+            info!("Skipping import job {}: taken by another worker or already finished", job_id);
+        }
+    }
+
+    /// Runs a claimed import job and records its outcome. A job whose
+    /// outcome can't be recorded is retried once its lease lapses; rows it
+    /// imported are skipped as duplicates then.
+    async fn run_import(&self, job: ImportJob) {
+        let job_id = job.id;
+        info!("Running import job {}", job_id);
+        let (result, error) = match crate::handlers::ingest::run_import_job(&self.db, job).await {
+            Ok(summary) => (serde_json::to_value(&summary).ok(), None),
+            Err(e) => {
+                error!("Import job {} failed: {}", job_id, e);
+                (None, Some(e.to_string()))
+            }
+        };
+        if let Err(e) = ImportJobRepo::finish(&self.db, job_id, result, error.as_deref()).await {
+            error!("Failed to record the outcome of import job {}: {}", job_id, e);
+        }
+    }
+
+    /// Leases the longest-waiting session that's still free.
+    async fn claim_from_database(&self) -> anyhow::Result<Option<Uuid>> {
+        for session_id in ProofQueueRepo::oldest(&self.db, DATABASE_CLAIM_CANDIDATES).await? {
+            if self.claim(session_id).await? {
+                return Ok(Some(session_id));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Leases a session to this worker, taking it off the queue.
+    async fn claim(&self, session_id: Uuid) -> anyhow::Result<bool> {
+        if !SessionRepo::claim(&self.db, session_id, &self.worker_id, LEASE_SECS).await? {
+            return Ok(false);
+        }
+        ProofQueueRepo::remove(&self.db, session_id).await?;
+        SessionRepo::record_event(&self.db, session_id, SessionStage::PickedUp, Some(&self.worker_id)).await?;
+        Ok(true)
+    }
+
+    /// Proves a session this worker holds the lease on, then releases it.
+    async fn run_job(&self, session_id: Uuid) -> anyhow::Result<()> {
+        info!("Processing proof session: {}", session_id);
+
+        // Keep the lease alive for as long as proving takes
+        let db = self.db.clone();
+        let worker_id = self.worker_id.clone();
+        let renewal = tokio::spawn(async move {
+            loop {
+                tokio::time::sleep(LEASE_RENEW_INTERVAL).await;
+                match SessionRepo::renew_lease(&db, session_id, &worker_id, LEASE_SECS).await {
+                    Ok(true) => {}
+                    Ok(false) => error!("Lost the lease on session {}", session_id),
+                    Err(e) => error!("Failed to renew lease on session {}: {}", session_id, e),
+                }
+            }
+        });
+
+        let result = self.process_session(session_id).await;
+        renewal.abort();
+        // A session left unfinished by an error is retried, not stranded
+        if SessionRepo::release_lease(&self.db, session_id, &self.worker_id, MAX_ATTEMPTS).await? {
+            ProofQueueService::requeue(&self.redis, &[session_id]).await;
+        }
+        result
+    }
+
+    async fn process_session(&self, session_id: Uuid) -> anyhow::Result<()> {
+        // Load transactions for this session's till
+        if let Some(job) = SessionRepo::job(&self.db, session_id).await? {
+            SessionRepo::record_event(&self.db, session_id, SessionStage::Executing, None).await?;
+            let secondary_source = job.secondary_source;
+            let currency_code = job.currency.as_deref().unwrap_or(DEFAULT_CURRENCY);
+            let Some(currency) = CurrencyRepo::find(&self.db, currency_code).await? else {
+                error!("Session {} uses unsupported currency {}", session_id, currency_code);
+                SessionRepo::mark_failed(&self.db, session_id, &format!("Unsupported currency {}", currency_code))
+                    .await?;
+                return Ok(());
+            };
+            let policy = match job.scoring_policy_id {
+                Some(id) => ScoringPolicyRepo::find(&self.db, id).await?,
+                None => None,
+            };
+            let Ok(timezone) = job.timezone.parse::<chrono_tz::Tz>() else {
+                error!("Session {} is for a till in unknown timezone {}", session_id, job.timezone);
+                SessionRepo::mark_failed(&self.db, session_id, &format!("Unknown timezone {}", job.timezone)).await?;
+                return Ok(());
+            };
+            let transactions =
+                TransactionRepo::for_proof(&self.db, job.till_id, secondary_source.as_deref()).await?;
+            let mut proof_input = ProofService::build_input(
+                transactions,
+                secondary_source.as_deref(),
+                job.score_threshold.map(|t| t as u32),
+                job.account_number.as_deref(),
+                &currency,
+                ProofService::policy_input(&currency, policy.as_ref()),
+                timezone,
+            );
+            proof_input.source = proof_core::DataSource::from_name(&job.data_source).unwrap_or_default();
+
+            // A doubtful upload lowers the trust lenders see on the proof
+            let authenticity_score = AuthenticityService::lowest_score(&self.db, job.till_id, &proof_input).await?;
+            let trust_tier = AuthenticityService::trust_tier(authenticity_score);
+            SessionRepo::set_authenticity(&self.db, session_id, authenticity_score, trust_tier).await?;
+
+            // Update progress
+            SessionRepo::set_progress(&self.db, session_id, 50).await?;
+
+            // Generate proof
+            let prover_backend = ProofService::prover_backend(&self.config);
+            info!("Proving session {} on {}", session_id, prover_backend);
+            match ProofService::generate_proof(
+                &self.db,
+                self.storage.as_ref(),
+                session_id,
+                &self.worker_id,
+                proof_input,
+                &job.input_mode,
+                prover_backend,
+            )
+            .await
+            {
+                Ok(true) => {
+                    info!("Proof generated successfully for session: {}", session_id);
+                    if let Err(e) = self.notify_owner(session_id).await {
+                        error!("Failed to send completion notification for {}: {}", session_id, e);
+                    }
+                }
+                Ok(false) => {
+                    warn!("Lost the lease on session {} while proving; leaving it to its new worker", session_id);
+                }
+                Err(e) => {
+                    error!("Failed to generate proof: {}", e);
+                    SessionRepo::mark_failed(&self.db, session_id, &e.to_string()).await?;
+                    if let Err(e) = self.notify_owner(session_id).await {
+                        error!("Failed to send failure notification for {}: {}", session_id, e);
+                    }
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Tells the session owner, in their preferred language and on the
+    /// channels they've chosen, that the proof finished or failed.
+    async fn notify_owner(&self, session_id: Uuid) -> anyhow::Result<()> {
+        let row = sqlx::query(
+            r#"
+            SELECT u.preferred_language, ps.status, ps.credit_score, ps.verification_code, ps.score_threshold,
+                   ps.meets_threshold, u.id
+            FROM proof_sessions ps
+            JOIN users u ON u.id = ps.user_id
+            WHERE ps.id = $1
+            "#,
+        )
+        .bind(session_id)
+        .fetch_one(&self.db)
+        .await?;
+
+        let locale = Locale::from_code(&row.try_get::<String, _>(0)?).unwrap_or_default();
+        let status: ProofStatus = row.try_get(1)?;
+        let credit_score: Option<i32> = row.try_get(2)?;
+        let verification_code: String = row.try_get(3)?;
+        let threshold = crate::handlers::proofs::threshold_result(row.try_get(4)?, row.try_get(5)?);
+        let user_id: Uuid = row.try_get(6)?;
+
+        let event = match status {
+            ProofStatus::Completed => "proof.completed",
+            _ => "proof.failed",
+        };
+        let message = match (status, threshold) {
+            (ProofStatus::Completed, Some(threshold)) => Message::ThresholdProofCompletedSms {
+                threshold: threshold.score_at_least,
+                meets: threshold.meets_threshold,
+                verification_code: &verification_code,
+            },
+            (ProofStatus::Completed, None) => Message::ProofCompletedSms {
+                score: credit_score.unwrap_or(0),
+                verification_code: &verification_code,
+            },
+            _ => Message::ProofFailedSms,
+        };
+
+        let notification = OwnerNotification { event, session_id, message, by_sms: true };
+        NotificationService::notify_owner(&self.db, &self.config, user_id, locale, notification).await
+    }
+}
@@ -0,0 +1,367 @@
+//! Runs periodic jobs on cron schedules. Every worker runs the scheduler;
+//! a Redis lock per job, and the last run recorded in `scheduled_jobs`,
+//! make each scheduled time run once across all of them.
+
+use std::future::Future;
+use std::str::FromStr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use futures::future::BoxFuture;
+use sqlx::PgPool;
+use tracing::{error, info};
+
+use crate::db::repos::ScheduledJobRepo;
+
+/// Redis key a job's lock is held under, followed by the job's name. The
+/// value is the holding worker's id.
+pub const LOCK_KEY_PREFIX: &str = "scheduler:lock:";
+/// How soon to look again when another worker holds a job's lock, or the
+/// last attempt failed.
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+/// Deletes a lock only if it's still held by the worker releasing it, so a
+/// lock that expired and was taken over is left alone.
+const RELEASE_LOCK: &str = r#"
+if redis.call("GET", KEYS[1]) == ARGV[1] then
+    return redis.call("DEL", KEYS[1])
+end
+return 0
+"#;
+
+type JobFn = Arc<dyn Fn() -> BoxFuture<'static, anyhow::Result<String>> + Send + Sync>;
+
+/// Day names the cron crate reads, indexed by crontab's day numbers.
+const DAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+/// Parses a cron expression, read in UTC. Takes the five crontab fields
+/// (minute, hour, day of month, month, day of week), or six with seconds
+/// first. Numbered days of the week are read as crontab numbers them,
+/// Sunday as 0 or 7, though the cron crate counts Sunday as 1.
+pub fn parse_schedule(expression: &str) -> anyhow::Result<cron::Schedule> {
+    let expression = expression.trim();
+    let mut fields: Vec<String> = expression.split_whitespace().map(str::to_string).collect();
+    if fields.len() == 5 {
+        fields.insert(0, "0".to_string());
+    }
+    if let Some(days) = fields.get_mut(5) {
+        *days = crontab_days(days).map_err(|e| anyhow::anyhow!("Invalid cron expression \"{}\": {}", expression, e))?;
+    }
+    cron::Schedule::from_str(&fields.join(" "))
+        .map_err(|e| anyhow::anyhow!("Invalid cron expression \"{}\": {}", expression, e))
+}
+
+/// Rewrites a day-of-week field with crontab numbers as the day names they
+/// stand for. Fields without numbers are left for the cron crate to read.
+fn crontab_days(field: &str) -> Result<String, String> {
+    if !field.chars().any(|c| c.is_ascii_digit()) {
+        return Ok(field.to_string());
+    }
+    if field.chars().any(|c| c.is_ascii_alphabetic()) {
+        return Err("days of the week must be all numbered or all named".to_string());
+    }
+
+    let number = |value: &str| match value.parse::<usize>() {
+        Ok(day) if day <= 7 => Ok(day),
+        _ => Err(format!("{} is not a day of the week (0-7)", value)),
+    };
+    let mut days = [false; 7];
+    for item in field.split(',') {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => match step.parse::<usize>() {
+                Ok(step) if step > 0 => (range, step),
+                _ => return Err(format!("Invalid step: {}", step)),
+            },
+            None => (item, 1),
+        };
+        let (first, last) = match range.split_once('-') {
+            _ if range == "*" => (0, 6),
+            Some((first, last)) => (number(first)?, number(last)?),
+            // As in crontab, "5/2" runs from 5 to the end of the week
+            None if step > 1 => (number(range)?, 6),
+            None => (number(range)?, number(range)?),
+        };
+        if first > last {
+            return Err(format!("Invalid range: {}", range));
+        }
+        for day in (first..=last).step_by(step) {
+            days[day % 7] = true;
+        }
+    }
+
+    let names: Vec<&str> = DAY_NAMES
+        .iter()
+        .zip(days)
+        .filter(|(_, on)| *on)
+        .map(|(name, _)| *name)
+        .collect();
+    Ok(names.join(","))
+}
+
+struct ScheduledJob {
+    name: &'static str,
+    expression: String,
+    schedule: cron::Schedule,
+    /// A run holds the job's lock for at most this long, so a worker that
+    /// dies mid-run doesn't block the job for good
+    lock_ttl: Duration,
+    run: JobFn,
+}
+
+/// When a job should next run.
+enum Due {
+    /// A scheduled time has passed since the last run; the latest one
+    Now(DateTime<Utc>),
+    At(DateTime<Utc>),
+    /// The schedule has no times left
+    Never,
+}
+
+impl ScheduledJob {
+    /// A job that has never run is due straight away. After downtime only
+    /// the latest missed time runs, not each of them.
+    fn due(&self, last_scheduled_for: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Due {
+        let Some(last) = last_scheduled_for else {
+            return Due::Now(now);
+        };
+        match self.schedule.after(&last).next() {
+            Some(next) if next <= now => Due::Now(
+                self.schedule
+                    .after(&next)
+                    .take_while(|t| *t <= now)
+                    .last()
+                    .unwrap_or(next),
+            ),
+            Some(next) => Due::At(next),
+            None => Due::Never,
+        }
+    }
+}
+
+pub struct Scheduler {
+    db: PgPool,
+    redis: redis::Client,
+    worker_id: String,
+    jobs: Vec<ScheduledJob>,
+}
+
+impl Scheduler {
+    pub fn new(db: PgPool, redis: redis::Client, worker_id: String) -> Self {
+        Self {
+            db,
+            redis,
+            worker_id,
+            jobs: Vec::new(),
+        }
+    }
+
+    /// Adds a job run at the times `expression` matches. `job` returns a
+    /// short summary of what it did, recorded with the run. A run that
+    /// outlasts `lock_ttl` may overlap the job's next one.
+    pub fn register<F, Fut>(
+        &mut self,
+        name: &'static str,
+        expression: &str,
+        lock_ttl: Duration,
+        job: F,
+    ) -> anyhow::Result<()>
+    where
+        F: Fn() -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = anyhow::Result<String>> + Send + 'static,
+    {
+        if self.jobs.iter().any(|j| j.name == name) {
+            anyhow::bail!("Scheduled job {} is registered twice", name);
+        }
+        self.jobs.push(ScheduledJob {
+            name,
+            expression: expression.trim().to_string(),
+            schedule: parse_schedule(expression)?,
+            lock_ttl,
+            run: Arc::new(move || Box::pin(job())),
+        });
+        Ok(())
+    }
+
+    /// Starts a task per job, each running until the process stops.
+    pub fn spawn(self) {
+        for job in self.jobs {
+            let db = self.db.clone();
+            let redis = self.redis.clone();
+            let worker_id = self.worker_id.clone();
+            info!("Scheduled {} at \"{}\"", job.name, job.expression);
+            tokio::spawn(async move {
+                loop {
+                    let wait = match Self::run_if_due(&db, &redis, &worker_id, &job).await {
+                        Ok(Some(wait)) => wait,
+                        Ok(None) => {
+                            info!("Scheduled job {} has no times left to run", job.name);
+                            return;
+                        }
+                        Err(e) => {
+                            error!("Failed to run scheduled job {}: {}", job.name, e);
+                            RETRY_INTERVAL
+                        }
+                    };
+                    tokio::time::sleep(wait).await;
+                }
+            });
+        }
+    }
+
+    /// Runs the job if it's due and no other worker is running it. Returns
+    /// how long to wait before looking again, or None once it never will be.
+    async fn run_if_due(
+        db: &PgPool,
+        redis: &redis::Client,
+        worker_id: &str,
+        job: &ScheduledJob,
+    ) -> anyhow::Result<Option<Duration>> {
+        let last = ScheduledJobRepo::last_scheduled_for(db, job.name).await?;
+        match job.due(last, Utc::now()) {
+            Due::Now(_) => {}
+            Due::At(next) => return Ok(Some(until(next))),
+            Due::Never => return Ok(None),
+        }
+
+        let mut conn = redis.get_async_connection().await?;
+        let lock_key = format!("{}{}", LOCK_KEY_PREFIX, job.name);
+        let locked: Option<String> = redis::cmd("SET")
+            .arg(&lock_key)
+            .arg(worker_id)
+            .arg("NX")
+            .arg("PX")
+            .arg(job.lock_ttl.as_millis() as u64)
+            .query_async(&mut conn)
+            .await?;
+        if locked.is_none() {
+            return Ok(Some(RETRY_INTERVAL));
+        }
+
+        let result = Self::run_locked(db, worker_id, job).await;
+        redis::Script::new(RELEASE_LOCK)
+            .key(&lock_key)
+            .arg(worker_id)
+            .invoke_async::<_, i32>(&mut conn)
+            .await?;
+        result
+    }
+
+    async fn run_locked(db: &PgPool, worker_id: &str, job: &ScheduledJob) -> anyhow::Result<Option<Duration>> {
+        // Another worker may have run it between the first look and taking
+        // the lock
+        let last = ScheduledJobRepo::last_scheduled_for(db, job.name).await?;
+        let scheduled_for = match job.due(last, Utc::now()) {
+            Due::Now(scheduled_for) => scheduled_for,
+            Due::At(next) => return Ok(Some(until(next))),
+            Due::Never => return Ok(None),
+        };
+
+        // Recorded before running, so a run cut short by a crash isn't
+        // repeated; the next scheduled time picks up from there
+        ScheduledJobRepo::start(db, job.name, &job.expression, scheduled_for, worker_id).await?;
+        info!("Running scheduled job {} for {}", job.name, scheduled_for.to_rfc3339());
+        // Spawned so a panicking job is recorded as failed
+        let (succeeded, output) = match tokio::spawn((job.run)()).await {
+            Ok(Ok(summary)) => {
+                info!("Scheduled job {}: {}", job.name, summary);
+                (true, summary)
+            }
+            Ok(Err(e)) => {
+                error!("Scheduled job {} failed: {}", job.name, e);
+                (false, e.to_string())
+            }
+            Err(e) => {
+                error!("Scheduled job {} panicked: {}", job.name, e);
+                (false, format!("Panicked: {}", e))
+            }
+        };
+        ScheduledJobRepo::finish(db, job.name, succeeded, &output).await?;
+
+        Ok(match job.due(Some(scheduled_for), Utc::now()) {
+            Due::Now(_) => Some(Duration::ZERO),
+            Due::At(next) => Some(until(next)),
+            Due::Never => None,
+        })
+    }
+}
+
+fn until(time: DateTime<Utc>) -> Duration {
+    (time - Utc::now()).to_std().unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use futures::FutureExt;
+
+    fn at(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(year, month, day, hour, minute, 0).unwrap()
+    }
+
+    fn next_after(expression: &str, after: DateTime<Utc>) -> DateTime<Utc> {
+        parse_schedule(expression).unwrap().after(&after).next().unwrap()
+    }
+
+    fn job(expression: &str) -> ScheduledJob {
+        ScheduledJob {
+            name: "test",
+            expression: expression.to_string(),
+            schedule: parse_schedule(expression).unwrap(),
+            lock_ttl: Duration::from_secs(60),
+            run: Arc::new(|| async { Ok(String::new()) }.boxed()),
+        }
+    }
+
+    #[test]
+    fn weekdays_skip_the_weekend() {
+        // 2025-01-31 is a Friday
+        assert_eq!(next_after("0 9 * * 1-5", at(2025, 1, 31, 8, 0)), at(2025, 1, 31, 9, 0));
+        assert_eq!(next_after("0 9 * * 1-5", at(2025, 1, 31, 10, 0)), at(2025, 2, 3, 9, 0));
+        assert_eq!(next_after("0 9 * * 1,3,5", at(2025, 2, 3, 10, 0)), at(2025, 2, 5, 9, 0));
+    }
+
+    #[test]
+    fn sunday_is_both_0_and_7() {
+        let sunday = at(2025, 2, 2, 6, 30);
+        assert_eq!(next_after("30 6 * * 0", at(2025, 1, 31, 0, 0)), sunday);
+        assert_eq!(next_after("30 6 * * 7", at(2025, 1, 31, 0, 0)), sunday);
+        assert_eq!(next_after("30 6 * * SUN", at(2025, 1, 31, 0, 0)), sunday);
+        assert_eq!(crontab_days("5-7"), Ok("SUN,FRI,SAT".to_string()));
+        assert_eq!(crontab_days("0,7"), Ok("SUN".to_string()));
+    }
+
+    #[test]
+    fn rolls_over_into_the_next_month() {
+        assert_eq!(next_after("0 0 1 * *", at(2025, 1, 31, 12, 0)), at(2025, 2, 1, 0, 0));
+        // February has no 31st
+        assert_eq!(
+            next_after("0 12 31 * *", at(2025, 1, 31, 13, 0)),
+            at(2025, 3, 31, 12, 0)
+        );
+        assert_eq!(next_after("0 0 1 * *", at(2025, 12, 15, 0, 0)), at(2026, 1, 1, 0, 0));
+    }
+
+    #[test]
+    fn rejects_bad_days_of_the_week() {
+        assert!(parse_schedule("0 9 * * 8").is_err());
+        assert!(parse_schedule("0 9 * * MON,3").is_err());
+        assert!(parse_schedule("0 9 * * 5-1").is_err());
+        assert!(parse_schedule("0 9 * * */0").is_err());
+    }
+
+    #[test]
+    fn runs_only_the_latest_missed_time() {
+        let job = job("0 * * * *");
+        assert!(matches!(job.due(None, at(2025, 1, 31, 10, 15)), Due::Now(_)));
+        assert!(matches!(
+            job.due(Some(at(2025, 1, 31, 6, 0)), at(2025, 1, 31, 10, 15)),
+            Due::Now(t) if t == at(2025, 1, 31, 10, 0)
+        ));
+        assert!(matches!(
+            job.due(Some(at(2025, 1, 31, 10, 0)), at(2025, 1, 31, 10, 15)),
+            Due::At(t) if t == at(2025, 1, 31, 11, 0)
+        ));
+    }
+}
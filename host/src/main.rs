@@ -1,54 +1,71 @@
-// These constants represent the RISC-V ELF and the image ID generated by risc0-build.
-// The ELF is used for proving and the ID is used for verification.
-use methods::{GUEST_CODE_FOR_ZK_PROOF_ELF, GUEST_CODE_FOR_ZK_PROOF_ID};
-use risc0_zkvm::{default_prover, ExecutorEnv};
+//! Verifies a receipt downloaded from the API without contacting it.
+//!
+//!     host <receipt.bin>
+//!
+//! The guest image ID and journal layout are the ones built into this
+//! workspace; `GET /api/meta/journal-schema/:version` describes the same
+//! layout for verifiers written in other languages.
 
-fn main() {
+// The image ID generated by risc0-build identifies the guest a receipt must
+// have been produced by.
+use methods::GUEST_CODE_FOR_ZK_PROOF_ID;
+use proof_core::{Evaluation, InputMode, Journal, JOURNAL_SCHEMA_VERSION};
+use risc0_zkvm::Receipt;
+
+fn main() -> anyhow::Result<()> {
     // Initialize tracing. In order to view logs, run `RUST_LOG=info cargo run`
     tracing_subscriber::fmt()
         .with_env_filter(tracing_subscriber::filter::EnvFilter::from_default_env())
         .init();
 
-    // An executor environment describes the configurations for the zkVM
-    // including program inputs.
-    // A default ExecutorEnv can be created like so:
-    // `let env = ExecutorEnv::builder().build().unwrap();`
-    // However, this `env` does not have any inputs.
-    //
-    // To add guest input to the executor environment, use
-    // ExecutorEnvBuilder::write().
-    // To access this method, you'll need to use ExecutorEnv::builder(), which
-    // creates an ExecutorEnvBuilder. When you're done adding input, call
-    // ExecutorEnvBuilder::build().
-
-    // For example:
-    let input: u32 = 15 * u32::pow(2, 27) + 1;
-    let env = ExecutorEnv::builder()
-        .write(&input)
-        .unwrap()
-        .build()
-        .unwrap();
-
-    // Obtain the default prover.
-    let prover = default_prover();
-
-    // Proof information by proving the specified ELF binary.
-    // This struct contains the receipt along with statistics about execution of the guest
-    let prove_info = prover
-        .prove(env, GUEST_CODE_FOR_ZK_PROOF_ELF)
-        .unwrap();
-
-    // extract the receipt.
-    let receipt = prove_info.receipt;
-
-    // TODO: Implement code for retrieving receipt journal here.
-
-    // For example:
-    let _output: u32 = receipt.journal.decode().unwrap();
-
-    // The receipt was verified at the end of proving, but the below code is an
-    // example of how someone else could verify this receipt.
-    receipt
-        .verify(GUEST_CODE_FOR_ZK_PROOF_ID)
-        .unwrap();
+    let path = std::env::args()
+        .nth(1)
+        .ok_or_else(|| anyhow::anyhow!("usage: host <receipt.bin>"))?;
+
+    // Receipts are stored and served bincode-serialized
+    let receipt: Receipt = bincode::deserialize(&std::fs::read(&path)?)?;
+    receipt.verify(GUEST_CODE_FOR_ZK_PROOF_ID)?;
+
+    // Check the version word before decoding the rest, so an unknown layout
+    // is reported as such rather than as garbage
+    let schema_version = proof_core::schema_version(&receipt.journal.bytes)
+        .ok_or_else(|| anyhow::anyhow!("Journal is empty"))?;
+    if schema_version != JOURNAL_SCHEMA_VERSION {
+        anyhow::bail!(
+            "Journal uses schema v{}, but this verifier decodes v{}",
+            schema_version,
+            JOURNAL_SCHEMA_VERSION
+        );
+    }
+
+    let journal: Journal = receipt.journal.decode()?;
+    // A composed proof only vouches for its chunks if they ran this guest too
+    if journal.chunk_image_id.is_some_and(|id| id != GUEST_CODE_FOR_ZK_PROOF_ID) {
+        anyhow::bail!("Receipt was composed from chunks proved by another guest");
+    }
+    let evaluation = match &journal.evaluation {
+        Evaluation::Full(output) => serde_json::to_value(output)?,
+        Evaluation::Threshold(output) => serde_json::to_value(output)?,
+    };
+
+    println!(
+        "{}",
+        serde_json::to_string_pretty(&serde_json::json!({
+            "verified": true,
+            "image_id": risc0_zkvm::sha::Digest::from(GUEST_CODE_FOR_ZK_PROOF_ID).to_string(),
+            "schema_version": journal.schema_version,
+            "composed": journal.chunk_image_id.is_some(),
+            "input_mode": match journal.input_mode {
+                InputMode::Transactions => serde_json::json!("transactions"),
+                InputMode::DailyTotals { rows_commitment } => serde_json::json!({
+                    "daily_totals": {
+                        "rows_commitment": rows_commitment.iter().map(|b| format!("{:02x}", b)).collect::<String>(),
+                    },
+                }),
+            },
+            "evaluation": evaluation,
+        }))?
+    );
+
+    Ok(())
 }
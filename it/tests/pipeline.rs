@@ -0,0 +1,262 @@
+use axum::http::StatusCode;
+use it::TestApp;
+use serde_json::json;
+
+/// Three payments a day over the last 90 days, so every week is covered
+fn statement_csv() -> String {
+    statement_csv_for(0..90)
+}
+
+/// Three payments on each of the given days back from today
+fn statement_csv_for(days: impl IntoIterator<Item = i64>) -> String {
+    let mut csv = String::from("Date,Amount,Type,Reference\n");
+    let today = chrono::Utc::now().date_naive();
+    for day in days {
+        for n in 0..3 {
+            csv.push_str(&format!(
+                "{},{}.00,Payment,RCP{:04}{}\n",
+                (today - chrono::Duration::days(day)).format("%Y-%m-%d"),
+                250 + (day * 7 + n * 13) % 400,
+                day,
+                n
+            ));
+        }
+    }
+    csv
+}
+
+#[tokio::test]
+async fn otp_till_upload_generate_verify() -> anyhow::Result<()> {
+    let app = TestApp::spawn().await?;
+
+    let token = app.login("+254700000001").await?;
+
+    let (status, till) = app
+        .request(
+            "POST",
+            "/api/tills/register",
+            Some(&token),
+            Some(json!({ "till_number": "123456", "till_type": "BuyGoods" })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK, "{}", till);
+    assert_eq!(till["already_claimed"], false);
+    let till_id = till["till_id"].as_str().unwrap().to_string();
+
+    let (status, body) = app
+        .request("POST", "/api/tills/verify", Some(&token), Some(json!({ "till_id": till_id })))
+        .await?;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["verified"], true);
+
+    let (status, upload) = app.upload_csv(&token, &till_id, &statement_csv()).await?;
+    assert_eq!(status, StatusCode::OK, "{}", upload);
+    assert_eq!(upload["transactions_imported"], 270);
+
+    // New accounts are on the free plan, whose allowance covers this proof
+    let (status, usage) = app.request("GET", "/api/billing/usage", Some(&token), None).await?;
+    assert_eq!(status, StatusCode::OK, "{}", usage);
+    assert_eq!(usage["used"], 0);
+    assert_eq!(usage["allowed"], true);
+
+    let (status, session) = app
+        .request(
+            "POST",
+            "/api/proofs/generate",
+            Some(&token),
+            Some(json!({ "till_id": till_id, "data_source": "upload" })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK, "{}", session);
+    let session_id = session["session_id"].as_str().unwrap().to_string();
+
+    assert!(app.run_worker_once().await?, "worker found no queued job");
+
+    let (status, proof_status) = app
+        .request("GET", &format!("/api/proofs/status/{}", session_id), Some(&token), None)
+        .await?;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(proof_status["status"], "Completed", "{}", proof_status);
+
+    let (status, result) = app
+        .request("GET", &format!("/api/proofs/result/{}", session_id), Some(&token), None)
+        .await?;
+    assert_eq!(status, StatusCode::OK, "{}", result);
+    let score = result["credit_score"].as_i64().unwrap();
+    assert!((0..=100).contains(&score));
+
+    let code = result["verification_url"]
+        .as_str()
+        .and_then(|url| url.rsplit('/').next())
+        .unwrap()
+        .to_string();
+
+    let (status, verification) = app
+        .request("GET", &format!("/verify/{}", code), Some(&token), None)
+        .await?;
+    assert_eq!(status, StatusCode::OK, "{}", verification);
+    assert_eq!(verification["valid"], true);
+    assert_eq!(verification["credit_score"].as_i64(), Some(score));
+
+    let (_, usage) = app.request("GET", "/api/billing/usage", Some(&token), None).await?;
+    assert_eq!(usage["used"], 1);
+
+    Ok(())
+}
+
+/// Logs in, registers and verifies a till and uploads the statement,
+/// returning the token and till id
+async fn till_with_statement(
+    app: &TestApp,
+    phone: &str,
+    till_number: &str,
+    csv: &str,
+) -> anyhow::Result<(String, String)> {
+    let token = app.login(phone).await?;
+
+    let (status, till) = app
+        .request(
+            "POST",
+            "/api/tills/register",
+            Some(&token),
+            Some(json!({ "till_number": till_number, "till_type": "BuyGoods" })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK, "{}", till);
+    let till_id = till["till_id"].as_str().unwrap().to_string();
+
+    let (status, body) = app
+        .request("POST", "/api/tills/verify", Some(&token), Some(json!({ "till_id": till_id })))
+        .await?;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+
+    let (status, upload) = app.upload_csv(&token, &till_id, csv).await?;
+    assert_eq!(status, StatusCode::OK, "{}", upload);
+
+    Ok((token, till_id))
+}
+
+async fn generate(app: &TestApp, token: &str, till_id: &str) -> anyhow::Result<(StatusCode, serde_json::Value)> {
+    app.request(
+        "POST",
+        "/api/proofs/generate",
+        Some(token),
+        Some(json!({ "till_id": till_id, "data_source": "upload" })),
+    )
+    .await
+}
+
+#[tokio::test]
+async fn rejects_a_statement_missing_most_weeks() -> anyhow::Result<()> {
+    let app = TestApp::spawn().await?;
+
+    // Payments in the first and last of ten weeks only
+    let csv = statement_csv_for([0, 63]);
+    let (token, till_id) = till_with_statement(&app, "+254700000004", "223344", &csv).await?;
+
+    let (status, body) = generate(&app, &token, &till_id).await?;
+    assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+    assert!(body.to_string().contains("weeks have payments"), "{}", body);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn requires_payment_past_the_allowance() -> anyhow::Result<()> {
+    let app = TestApp::spawn().await?;
+
+    let (token, till_id) = till_with_statement(&app, "+254700000005", "334455", &statement_csv()).await?;
+
+    // A plan with nothing included leaves the account past its allowance
+    // from the first proof
+    sqlx::query(
+        "INSERT INTO billing_plans (code, audience, name, included_per_month, overage_price_minor, currency)
+         VALUES ('it_none_included', 'business', 'None included', 0, 5000, 'KES')",
+    )
+    .execute(&app.state.db)
+    .await?;
+    sqlx::query(
+        "UPDATE users SET billing_plan = 'it_none_included'
+         WHERE id = (SELECT user_id FROM business_tills WHERE id = $1::uuid)",
+    )
+    .bind(&till_id)
+    .execute(&app.state.db)
+    .await?;
+
+    let (status, body) = generate(&app, &token, &till_id).await?;
+    assert_eq!(status, StatusCode::PAYMENT_REQUIRED, "{}", body);
+
+    // A completed STK push pays for one proof
+    let payment_id: String = sqlx::query_scalar(
+        "INSERT INTO proof_payments (user_id, phone_number, amount_minor, currency, status, completed_at)
+         SELECT user_id, '+254700000005', 5000, 'KES', 'succeeded', NOW() FROM business_tills WHERE id = $1::uuid
+         RETURNING id::text",
+    )
+    .bind(&till_id)
+    .fetch_one(&app.state.db)
+    .await?;
+
+    let (status, session) = generate(&app, &token, &till_id).await?;
+    assert_eq!(status, StatusCode::OK, "{}", session);
+
+    let spent_on: Option<String> =
+        sqlx::query_scalar("SELECT session_id::text FROM proof_payments WHERE id = $1::uuid")
+            .bind(&payment_id)
+            .fetch_one(&app.state.db)
+            .await?;
+    assert_eq!(spent_on.as_deref(), session["session_id"].as_str());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn a_verified_till_stays_with_its_owner() -> anyhow::Result<()> {
+    let app = TestApp::spawn().await?;
+
+    till_with_statement(&app, "+254700000006", "445566", &statement_csv()).await?;
+
+    let claimant = app.login("+254700000007").await?;
+    let (status, till) = app
+        .request(
+            "POST",
+            "/api/tills/register",
+            Some(&claimant),
+            Some(json!({ "till_number": "445566", "till_type": "BuyGoods" })),
+        )
+        .await?;
+    assert_eq!(status, StatusCode::OK, "{}", till);
+    assert_eq!(till["already_claimed"], true);
+    let till_id = till["till_id"].as_str().unwrap().to_string();
+
+    let (status, body) = app
+        .request("POST", "/api/tills/verify", Some(&claimant), Some(json!({ "till_id": till_id })))
+        .await?;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    assert_eq!(body["verified"], false);
+    assert_eq!(body["ownership_review"], true);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn rejects_foreign_till_upload() -> anyhow::Result<()> {
+    let app = TestApp::spawn().await?;
+
+    let owner = app.login("+254700000002").await?;
+    let intruder = app.login("+254700000003").await?;
+
+    let (_, till) = app
+        .request(
+            "POST",
+            "/api/tills/register",
+            Some(&owner),
+            Some(json!({ "till_number": "654321", "till_type": "PayBill" })),
+        )
+        .await?;
+    let till_id = till["till_id"].as_str().unwrap().to_string();
+
+    let (status, _) = app.upload_csv(&intruder, &till_id, &statement_csv()).await?;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    Ok(())
+}
@@ -1 +1,5 @@
 include!(concat!(env!("OUT_DIR"), "/methods.rs"));
+
+/// Recorded next to the image ID so operators can tell guest builds apart.
+/// Bump this crate's version whenever the guest changes.
+pub const GUEST_VERSION: &str = env!("CARGO_PKG_VERSION");
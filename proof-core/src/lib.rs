@@ -0,0 +1,1931 @@
+//! Credit scoring shared by the zkVM guest and the API. The guest commits
+//! whatever `evaluate` returns, wrapped in a versioned `Journal`, and the API
+//! runs the same function natively for previews, so both must see identical
+//! results for identical input. Large statements are scored from chunk
+//! summaries that merge losslessly, so chunked and single proofs agree.
+//! Per-day grouping uses a `BTreeMap` so floating-point sums are taken in the
+//! same order on every platform.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, BTreeSet};
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ProofInput {
+    pub transactions: Vec<Transaction>,
+    /// Optional second source (e.g. a bank statement) scored alongside M-Pesa
+    pub secondary: Option<TransactionSource>,
+    /// When set, only whether the score reaches this threshold is committed
+    pub threshold: Option<u32>,
+    /// Hash of a PayBill account number; when set, only M-Pesa payments made
+    /// against that account are scored
+    pub account: Option<String>,
+    /// Currency every transaction, in every source, must be denominated in
+    pub currency: Currency,
+    /// Tunable scoring parameters; their hash is committed with the result
+    pub policy: ScoringPolicy,
+    /// When the proof is generated, in unix seconds. Recency is measured up
+    /// to here; when unset, up to the statement's latest row.
+    pub as_of: Option<i64>,
+    /// The merchant's offset from UTC, in seconds. Days, weekdays and hours
+    /// are taken in this local time; East Africa Time when unset.
+    pub utc_offset_secs: Option<i32>,
+    /// Which rows may be scored; committed with the result
+    pub source: DataSource,
+}
+
+/// How amounts in a currency are scaled.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Currency {
+    /// ISO 4217 code, e.g. "KES"
+    pub code: String,
+    /// Minor units per major unit as a power of ten (2 for cents)
+    pub minor_unit_exponent: u32,
+}
+
+/// Scoring parameters chosen per lender or market rather than compiled
+/// into the guest.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScoringPolicy {
+    /// Monthly volume, in major units, at which each band above VeryLow starts
+    pub volume_thresholds: [u64; 4],
+    /// When set, each day's volume is capped at this many times the median
+    /// active day's before scoring, so a one-off large payment can't inflate
+    /// volume or pass for growth
+    pub outlier_cap: Option<u32>,
+}
+
+impl ScoringPolicy {
+    /// The v1 hash bound to the currency the thresholds are in, since the
+    /// same thresholds mean different money in another one.
+    pub fn hash(&self, currency: &str) -> [u8; 32] {
+        Self::bind_currency(&self.hash_v1(), currency)
+    }
+
+    /// SHA-256 over a fixed little-endian encoding, so the hash doesn't
+    /// depend on any serializer. Bump the tag if fields are added. Policies
+    /// without an outlier cap hash as they did before the cap existed; a cap
+    /// is appended after the fixed-length thresholds. Proofs committed this
+    /// before the currency was bound in.
+    pub fn hash_v1(&self) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"scoring-policy/v1");
+        for threshold in self.volume_thresholds {
+            hasher.update(threshold.to_le_bytes());
+        }
+        if let Some(cap) = self.outlier_cap {
+            hasher.update(b"outlier-cap");
+            hasher.update(cap.to_le_bytes());
+        }
+        hasher.finalize().into()
+    }
+
+    /// The v2 hash of the policy whose v1 hash is `v1`, so a stored v1 hash
+    /// can be upgraded without its thresholds.
+    pub fn bind_currency(v1: &[u8; 32], currency: &str) -> [u8; 32] {
+        let mut hasher = Sha256::new();
+        hasher.update(b"scoring-policy/v2");
+        hasher.update((currency.len() as u32).to_le_bytes());
+        hasher.update(currency.as_bytes());
+        hasher.update(v1);
+        hasher.finalize().into()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct TransactionSource {
+    pub tag: String,
+    pub transactions: Vec<Transaction>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Transaction {
+    pub timestamp: i64,
+    /// In the currency's minor units
+    pub amount: u64,
+    /// ISO 4217 code of `amount`
+    pub currency: String,
+    /// Canonical kind; the statement's own description stays with the host
+    pub kind: TransactionKind,
+    /// Which way the money moved; `amount` is always its magnitude
+    pub direction: Direction,
+    pub reference: String,
+    /// Hashed payer identifier, when the statement carries one
+    pub counterparty: Option<String>,
+    /// Hashed PayBill account number the payment was made against
+    pub account: Option<String>,
+    /// How the row reached the platform
+    pub provenance: Provenance,
+}
+
+/// What a statement row is, whatever wording the statement used for it.
+/// The host maps raw descriptions such as "Merchant Payment Online" or
+/// "Pay Bill" to one of these.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TransactionKind {
+    /// Money a customer paid the business
+    Payment,
+    /// A payment returned to the customer
+    Reversal,
+    /// The business taking money out, e.g. to cash or a bank
+    Withdrawal,
+    /// Money moved between the business's own accounts
+    Transfer,
+    /// A fee charged by the provider
+    Charge,
+    Other,
+}
+
+impl TransactionKind {
+    pub const ALL: [TransactionKind; 6] = [
+        TransactionKind::Payment,
+        TransactionKind::Reversal,
+        TransactionKind::Withdrawal,
+        TransactionKind::Transfer,
+        TransactionKind::Charge,
+        TransactionKind::Other,
+    ];
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TransactionKind::Payment => "Payment",
+            TransactionKind::Reversal => "Reversal",
+            TransactionKind::Withdrawal => "Withdrawal",
+            TransactionKind::Transfer => "Transfer",
+            TransactionKind::Charge => "Charge",
+            TransactionKind::Other => "Other",
+        }
+    }
+
+    /// Inverse of `as_str`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == name)
+    }
+
+    /// Whether rows of this kind count towards the business's volume.
+    pub fn is_scored(&self) -> bool {
+        matches!(self, TransactionKind::Payment | TransactionKind::Reversal)
+    }
+
+    /// Whether rows of this kind only move the business's own money, so
+    /// don't count against its net inflow.
+    pub fn moves_own_funds(&self) -> bool {
+        matches!(self, TransactionKind::Withdrawal | TransactionKind::Transfer)
+    }
+}
+
+/// How a row reached the platform. Rows Safaricom delivered directly can't
+/// have been edited by the merchant; uploaded statements and parsed messages
+/// could have been.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Provenance {
+    /// A CSV statement the merchant uploaded
+    CsvUpload,
+    /// A PDF statement the merchant uploaded
+    PdfUpload,
+    /// An M-Pesa confirmation SMS the merchant submitted
+    SmsParse,
+    /// Pushed by a POS or partner system acting for the merchant
+    Partner,
+    /// Safaricom's C2B confirmation callback
+    C2bCallback,
+    /// Daraja's Pull Transactions API
+    DarajaPull,
+    /// Read by OCR from a photo of a statement the merchant uploaded
+    ImageUpload,
+    /// A held row the merchant corrected by hand before confirming it
+    OwnerCorrected,
+}
+
+impl Provenance {
+    pub const ALL: [Provenance; 8] = [
+        Provenance::CsvUpload,
+        Provenance::PdfUpload,
+        Provenance::SmsParse,
+        Provenance::Partner,
+        Provenance::C2bCallback,
+        Provenance::DarajaPull,
+        Provenance::ImageUpload,
+        Provenance::OwnerCorrected,
+    ];
+
+    /// Lower-case name, as the host stores it.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Provenance::CsvUpload => "csv",
+            Provenance::PdfUpload => "pdf",
+            Provenance::SmsParse => "sms",
+            Provenance::Partner => "partner",
+            Provenance::C2bCallback => "c2b",
+            Provenance::DarajaPull => "daraja",
+            Provenance::ImageUpload => "image",
+            Provenance::OwnerCorrected => "corrected",
+        }
+    }
+
+    /// Inverse of `as_str`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|provenance| provenance.as_str() == name)
+    }
+
+    /// Whether the row came from Safaricom's APIs rather than from the
+    /// merchant or someone acting for them.
+    pub fn is_api_sourced(&self) -> bool {
+        matches!(self, Provenance::C2bCallback | Provenance::DarajaPull)
+    }
+
+    fn index(&self) -> usize {
+        *self as usize
+    }
+}
+
+/// Which rows a proof scores. The guest applies the restriction itself and
+/// commits it, so a lender can tell a proof built only from what Safaricom
+/// delivered from one that includes the merchant's own uploads.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DataSource {
+    /// Every row, whoever supplied it
+    #[default]
+    All,
+    /// Only rows from C2B callbacks and Daraja pulls
+    VerifiedApi,
+}
+
+impl DataSource {
+    /// Lower-case name, as the host stores it.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DataSource::All => "all",
+            DataSource::VerifiedApi => "verified_api",
+        }
+    }
+
+    /// Inverse of `as_str`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        [DataSource::All, DataSource::VerifiedApi]
+            .into_iter()
+            .find(|source| source.as_str() == name)
+    }
+
+    /// Whether a proof from this source scores `tx`.
+    pub fn includes(&self, tx: &Transaction) -> bool {
+        match self {
+            DataSource::All => true,
+            DataSource::VerifiedApi => tx.provenance.is_api_sourced(),
+        }
+    }
+}
+
+/// Which way money moved in a row. The host classifies rows on import:
+/// charges are fees, withdrawals and reversals outflows, and anything else
+/// follows the sign the statement gave the amount.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Direction {
+    Inflow,
+    Outflow,
+    Fee,
+}
+
+impl Direction {
+    pub const ALL: [Direction; 3] = [Direction::Inflow, Direction::Outflow, Direction::Fee];
+
+    /// Lower-case name, as the host stores it.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Direction::Inflow => "inflow",
+            Direction::Outflow => "outflow",
+            Direction::Fee => "fee",
+        }
+    }
+
+    /// Inverse of `as_str`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|direction| direction.as_str() == name)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct ProofOutput {
+    pub till_number_hash: [u8; 32],
+    pub period_start: i64,
+    pub period_end: i64,
+    pub credit_score: u32,
+    pub metrics: BusinessMetrics,
+    pub score_breakdown: ScoreBreakdown,
+    pub source_volumes: Vec<SourceVolume>,
+    /// Volume band of each 30-day month, oldest first, for up to 12 months
+    pub monthly_volumes: Vec<VolumeRange>,
+    /// Account hash the proof was restricted to, for branch-level proofs
+    pub account_hash: Option<String>,
+    /// Currency the volume bands were measured in
+    pub currency: String,
+    /// `ScoringPolicy::hash` of the policy the score was computed under
+    pub policy_hash: [u8; 32],
+    /// When in the week and day the volume comes in
+    pub activity_profile: ActivityProfile,
+    /// Set when the policy caps outliers: what capping changed
+    pub outlier_adjustment: Option<OutlierAdjustment>,
+    pub recency: Recency,
+    /// Share of the period's weeks with at least one payment; see `coverage`
+    pub coverage_percentage: u8,
+    /// Local time days were grouped in, as an offset from UTC in seconds
+    pub utc_offset_secs: i32,
+    pub cash_flow: CashFlow,
+    pub provenance: ProvenanceMix,
+    /// Which rows were scored
+    pub source: DataSource,
+}
+
+/// How the scored volume reached the platform, so lenders can weigh volume
+/// Safaricom reported above volume the merchant uploaded.
+#[derive(Serialize, Deserialize)]
+pub struct ProvenanceMix {
+    /// Percent of scored volume from C2B callbacks and Daraja pulls
+    pub api_sourced_percentage: u8,
+    /// Percent of scored volume by `Provenance`, in `Provenance::ALL` order,
+    /// each rounded down
+    pub percentages: [u8; Provenance::ALL.len()],
+}
+
+impl ProvenanceMix {
+    fn measure(totals: &[u64; Provenance::ALL.len()]) -> Self {
+        let total: u64 = totals.iter().sum();
+        let percentage = |part: u64| {
+            if total == 0 {
+                0
+            } else {
+                (part as u128 * 100 / total as u128) as u8
+            }
+        };
+        let api_sourced = Provenance::ALL
+            .iter()
+            .filter(|p| p.is_api_sourced())
+            .map(|p| totals[p.index()])
+            .sum();
+        ProvenanceMix {
+            api_sourced_percentage: percentage(api_sourced),
+            percentages: totals.map(percentage),
+        }
+    }
+}
+
+/// What the business keeps of its payments once money paid back out and
+/// fees are taken off, over the scoring window. Withdrawals and transfers
+/// move the business's own money and don't count against it.
+#[derive(Serialize, Deserialize)]
+pub struct CashFlow {
+    /// Band of monthly payments less outflows and fees; VeryLow when those
+    /// take all of it
+    pub monthly_net_inflow_range: VolumeRange,
+    /// Net inflow as a percentage of payments, 0 when outflows and fees
+    /// take all of them
+    pub net_inflow_percentage: u8,
+    /// Fees as a percentage of payments, at most 100
+    pub fee_percentage: u8,
+}
+
+/// How capping outlier days changed the volume a score was computed from.
+/// `BusinessMetrics::monthly_volume_range` is the band after capping.
+#[derive(Serialize, Deserialize)]
+pub struct OutlierAdjustment {
+    /// The policy's cap, as a multiple of the median active day's volume
+    pub cap_multiple: u32,
+    pub days_capped: u32,
+    /// Share of the raw volume that capping removed
+    pub volume_removed: ShareBucket,
+    /// The monthly volume band before capping
+    pub raw_monthly_volume_range: VolumeRange,
+}
+
+/// Reduced-disclosure journal for threshold proofs: the exact score and
+/// metrics never leave the zkVM.
+#[derive(Serialize, Deserialize)]
+pub struct ThresholdOutput {
+    pub period_start: i64,
+    pub period_end: i64,
+    pub score_at_least: u32,
+    pub meets_threshold: bool,
+    pub account_hash: Option<String>,
+    pub currency: String,
+    pub policy_hash: [u8; 32],
+    pub recency: Recency,
+    pub coverage_percentage: u8,
+    pub utc_offset_secs: i32,
+    pub provenance: ProvenanceMix,
+    pub source: DataSource,
+}
+
+/// How long before the proof the business last took a payment, and what
+/// that did to the score. A till with a good history that has since gone
+/// quiet shouldn't score as if it were still trading.
+#[derive(Serialize, Deserialize)]
+pub struct Recency {
+    /// The time recency was measured up to, as the host stated it. Lenders
+    /// should expect it to be close to when the proof was generated.
+    pub as_of: i64,
+    pub days_since_last_transaction: u32,
+    /// Share of the breakdown's total kept in the credit score: 100 within
+    /// `RECENCY_GRACE_DAYS`, then one point less per day, down to
+    /// `MIN_RECENCY_FACTOR`
+    pub recency_factor_percentage: u8,
+}
+
+/// Days without a payment before the score starts to decay.
+pub const RECENCY_GRACE_DAYS: u32 = 30;
+/// Days without a payment after which a proof is flagged stale.
+pub const STALE_AFTER_DAYS: u32 = 60;
+/// Lowest recency factor, so long histories still count for something.
+pub const MIN_RECENCY_FACTOR: u8 = 25;
+
+impl Recency {
+    fn measure(as_of: Option<i64>, last_transaction: i64) -> Self {
+        let as_of = as_of.unwrap_or(last_transaction);
+        let days_since_last_transaction = (as_of.saturating_sub(last_transaction).max(0) / (24 * 60 * 60)) as u32;
+        let decay = days_since_last_transaction.saturating_sub(RECENCY_GRACE_DAYS);
+        let recency_factor_percentage = 100u32.saturating_sub(decay).max(MIN_RECENCY_FACTOR as u32) as u8;
+        Recency {
+            as_of,
+            days_since_last_transaction,
+            recency_factor_percentage,
+        }
+    }
+
+    pub fn is_stale(&self) -> bool {
+        self.days_since_last_transaction >= STALE_AFTER_DAYS
+    }
+
+    fn apply(&self, score: u32) -> u32 {
+        score * self.recency_factor_percentage as u32 / 100
+    }
+}
+
+/// Which weeks of a period have at least one payment. Statements uploaded
+/// piecemeal often miss whole weeks, which would otherwise read as a quiet
+/// business and silently lower the active-days percentage.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Coverage {
+    /// 7-day weeks from the period's first day; the last may be partial
+    pub weeks: u32,
+    pub weeks_covered: u32,
+    /// Runs of weeks without a payment, as inclusive ranges of days since
+    /// the epoch
+    pub gaps: Vec<(i64, i64)>,
+}
+
+impl Coverage {
+    /// Covered weeks as a percentage of all of them, rounded down.
+    pub fn percentage(&self) -> u8 {
+        if self.weeks == 0 {
+            return 0;
+        }
+        (self.weeks_covered as u64 * 100 / self.weeks as u64) as u8
+    }
+}
+
+/// Measures which weeks between `first_day` and `last_day` (days since the
+/// epoch, inclusive) contain one of `active_days`. Days outside the period
+/// are ignored.
+pub fn coverage(active_days: impl IntoIterator<Item = i64>, first_day: i64, last_day: i64) -> Coverage {
+    if last_day < first_day {
+        return Coverage::default();
+    }
+
+    let weeks = ((last_day - first_day) / 7 + 1) as usize;
+    let mut covered = vec![false; weeks];
+    for day in active_days {
+        if (first_day..=last_day).contains(&day) {
+            covered[((day - first_day) / 7) as usize] = true;
+        }
+    }
+
+    let mut gaps = Vec::new();
+    let mut gap_start = None;
+    for (week, &is_covered) in covered.iter().enumerate() {
+        let week_start = first_day + week as i64 * 7;
+        match (is_covered, gap_start) {
+            (false, None) => gap_start = Some(week_start),
+            (true, Some(start)) => {
+                gaps.push((start, week_start - 1));
+                gap_start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(start) = gap_start {
+        gaps.push((start, last_day));
+    }
+
+    Coverage {
+        weeks: weeks as u32,
+        weeks_covered: covered.iter().filter(|&&c| c).count() as u32,
+        gaps,
+    }
+}
+
+/// Monthly volume band of a single transaction source.
+#[derive(Serialize, Deserialize)]
+pub struct SourceVolume {
+    pub tag: String,
+    pub monthly_volume_range: VolumeRange,
+}
+
+/// Points contributed by each scoring component. They sum to `credit_score`
+/// before the recency factor is applied.
+#[derive(Serialize, Deserialize, Default)]
+pub struct ScoreBreakdown {
+    pub volume_points: u32,
+    pub consistency_points: u32,
+    pub activity_points: u32,
+    pub growth_points: u32,
+    pub diversity_points: u32,
+    /// Deducted when a few payers account for most of the volume
+    pub concentration_penalty: u32,
+    /// Deducted for boom/bust cash flow (deep drawdowns, volatile months)
+    pub volatility_penalty: u32,
+}
+
+/// Name, maximum points and whether it is deducted, for each field of
+/// `ScoreBreakdown` in order. Every display of a score reads these.
+pub const SCORE_COMPONENTS: [(&str, u32, bool); 7] = [
+    ("volume", 30, false),
+    ("consistency", 30, false),
+    ("activity", 20, false),
+    ("growth", 10, false),
+    ("diversity", 10, false),
+    ("concentration", 10, true),
+    ("volatility", 10, true),
+];
+
+impl ScoreBreakdown {
+    /// Points per component, in `SCORE_COMPONENTS` order.
+    pub fn points(&self) -> [u32; 7] {
+        [
+            self.volume_points,
+            self.consistency_points,
+            self.activity_points,
+            self.growth_points,
+            self.diversity_points,
+            self.concentration_penalty,
+            self.volatility_penalty,
+        ]
+    }
+
+    pub fn total(&self) -> u32 {
+        (self.volume_points
+            + self.consistency_points
+            + self.activity_points
+            + self.growth_points
+            + self.diversity_points)
+            .saturating_sub(self.concentration_penalty + self.volatility_penalty)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct BusinessMetrics {
+    pub monthly_volume_range: VolumeRange,
+    pub consistency_score: u8,
+    pub growth_trend: GrowthTrend,
+    pub active_days_percentage: u8,
+    pub customer_diversity_score: u8,
+    pub concentration_risk: ConcentrationRisk,
+    /// Largest peak-to-trough fall in weekly volume, as a percentage
+    pub max_weekly_drawdown_percentage: u8,
+    /// Coefficient of variation of rolling 30-day volume, as a percentage
+    pub volatility_30d_percentage: u8,
+}
+
+/// Timing of volume in the merchant's local time, as coarse shares so no
+/// exact figure is revealed. Lenders in some sectors treat
+/// late-night-dominant trade as a risk flag.
+#[derive(Serialize, Deserialize)]
+pub struct ActivityProfile {
+    /// Share of volume on each weekday, Monday first
+    pub weekday_shares: [ShareBucket; 7],
+    /// Share taken between 08:00 and 18:00
+    pub business_hours_share: ShareBucket,
+    /// Share taken between 22:00 and 05:00
+    pub late_night_share: ShareBucket,
+}
+
+/// A share of volume: under 5%, under 20%, under 40%, under 60%, or more.
+#[derive(Serialize, Deserialize, Clone, Copy)]
+pub enum ShareBucket {
+    Negligible,
+    Low,
+    Moderate,
+    High,
+    Dominant,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum VolumeRange {
+    VeryLow,
+    Low,
+    Medium,
+    High,
+    VeryHigh,
+}
+
+/// Herfindahl-style concentration of volume across payers.
+#[derive(Serialize, Deserialize)]
+pub enum ConcentrationRisk {
+    Low,
+    Moderate,
+    High,
+    /// No counterparty data to measure against
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize)]
+pub enum GrowthTrend {
+    Declining,
+    Stable,
+    Growing,
+    Rapid,
+}
+
+/// Version of the `Journal` layout. Bump whenever a committed type changes
+/// shape, and describe the new layout in the API's journal schema endpoint.
+pub const JOURNAL_SCHEMA_VERSION: u32 = 13;
+
+/// First word of a chunk receipt's journal. It lies outside the range of
+/// `JOURNAL_SCHEMA_VERSION` so a chunk is never mistaken for a finished proof.
+pub const CHUNK_SCHEMA_VERSION: u32 = 0x8000_0007;
+
+/// Everything the guest commits. The version is the first word so offline
+/// decoders can pick a layout before reading the rest.
+#[derive(Serialize, Deserialize)]
+pub struct Journal {
+    pub schema_version: u32,
+    pub evaluation: Evaluation,
+    /// Set when the evaluation was composed from chunk receipts: the image
+    /// those receipts were verified against. Verifiers must check that it is
+    /// the image of the receipt itself.
+    pub chunk_image_id: Option<[u32; 8]>,
+    pub input_mode: InputMode,
+}
+
+/// How much of the statement the guest saw, so lenders know the granularity
+/// the proof attests.
+#[derive(Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum InputMode {
+    /// Every transaction; the guest aggregated them itself
+    Transactions,
+    /// Daily totals aggregated by the host. The guest can't check them
+    /// against the rows, but the commitment binds them to the rows they came
+    /// from, which the merchant can reveal to an auditor.
+    DailyTotals { rows_commitment: [u8; 32] },
+}
+
+/// Reads the schema version from the first word of committed journal bytes,
+/// so a decoder can pick a layout, or reject a chunk journal, up front.
+pub fn schema_version(journal: &[u8]) -> Option<u32> {
+    let word = journal.get(..4)?;
+    Some(u32::from_le_bytes([word[0], word[1], word[2], word[3]]))
+}
+
+/// The full score, or only the threshold outcome.
+#[derive(Serialize, Deserialize)]
+pub enum Evaluation {
+    Full(ProofOutput),
+    Threshold(ThresholdOutput),
+}
+
+/// What the guest reads. Statements too large to prove at once are proved
+/// as chunks, each committing a `ChunkJournal`, and an aggregate run
+/// verifies those receipts and scores their merged summaries.
+#[derive(Serialize, Deserialize)]
+pub enum GuestInput {
+    Statement(ProofInput),
+    Chunk(ChunkInput),
+    Aggregate(AggregateInput),
+    /// Boxed since the totals dwarf the other variants; serializes as the
+    /// unboxed input would
+    Totals(Box<TotalsInput>),
+}
+
+/// One slice of a statement. Every chunk of a statement carries the same
+/// `now`, the latest timestamp across the whole statement, so all chunks
+/// apply the same scoring windows.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ChunkInput {
+    pub index: u32,
+    pub count: u32,
+    pub transactions: Vec<Transaction>,
+    /// Present in every chunk when the statement has a second source, even
+    /// if this slice holds none of its transactions
+    pub secondary: Option<TransactionSource>,
+    pub account: Option<String>,
+    pub currency: Currency,
+    pub now: i64,
+    pub utc_offset_secs: i32,
+    pub source: DataSource,
+}
+
+/// What a chunk run commits.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ChunkJournal {
+    /// Always `CHUNK_SCHEMA_VERSION`
+    pub schema_version: u32,
+    pub index: u32,
+    pub count: u32,
+    pub summary: Summary,
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct AggregateInput {
+    /// Image the chunk receipts were proved with; committed as
+    /// `Journal::chunk_image_id`
+    pub chunk_image_id: [u32; 8],
+    /// Journals of every chunk, in index order
+    pub chunks: Vec<ChunkJournal>,
+    pub threshold: Option<u32>,
+    pub currency: Currency,
+    pub policy: ScoringPolicy,
+    pub as_of: Option<i64>,
+}
+
+/// Everything scoring needs from a set of transactions. Summaries of
+/// disjoint slices of one statement merge into the summary of the whole, so
+/// a chunked proof scores exactly like a single one.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Summary {
+    pub currency: String,
+    pub account: Option<String>,
+    pub now: i64,
+    pub utc_offset_secs: i32,
+    pub source: DataSource,
+    /// Latest timestamp of any row `source` includes, before filtering
+    pub latest: Option<i64>,
+    /// Payment volume of each of the 12 30-day months before `now`, most
+    /// recent first
+    pub monthly_totals: [u64; 12],
+    /// One per source, in input order; the rest only covers the scoring window
+    pub sources: Vec<SourceSummary>,
+    /// Volume per local day since the epoch
+    pub daily_totals: BTreeMap<i64, u64>,
+    /// Volume per local hour of the day
+    pub hourly_totals: [u64; 24],
+    /// Volume per local weekday, Monday first
+    pub weekday_totals: [u64; 7],
+    pub transaction_count: u64,
+    pub references: BTreeSet<String>,
+    /// Volume per hashed payer
+    pub counterparty_volumes: BTreeMap<String, u64>,
+    /// Money paid out in the scoring window, except the business's own
+    /// withdrawals and transfers
+    pub outflow_total: u64,
+    /// Fees charged in the scoring window
+    pub fee_total: u64,
+    /// Volume by `Provenance`, in `Provenance::ALL` order
+    pub provenance_totals: [u64; Provenance::ALL.len()],
+}
+
+impl Summary {
+    /// Drops what only merging needs: the reference set becomes a count and
+    /// payer hashes are dropped, keeping their volumes in hash order.
+    pub fn into_totals(self) -> Totals {
+        Totals {
+            currency: self.currency,
+            account: self.account,
+            now: self.now,
+            utc_offset_secs: self.utc_offset_secs,
+            source: self.source,
+            monthly_totals: self.monthly_totals,
+            sources: self.sources,
+            daily_totals: self.daily_totals,
+            hourly_totals: self.hourly_totals,
+            weekday_totals: self.weekday_totals,
+            transaction_count: self.transaction_count,
+            unique_references: self.references.len() as u64,
+            payer_volumes: self.counterparty_volumes.into_values().collect(),
+            outflow_total: self.outflow_total,
+            fee_total: self.fee_total,
+            provenance_totals: self.provenance_totals,
+        }
+    }
+}
+
+/// The aggregates scoring reads, with nothing that identifies a payment.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct Totals {
+    pub currency: String,
+    pub account: Option<String>,
+    pub now: i64,
+    pub utc_offset_secs: i32,
+    pub source: DataSource,
+    pub monthly_totals: [u64; 12],
+    pub sources: Vec<SourceSummary>,
+    pub daily_totals: BTreeMap<i64, u64>,
+    pub hourly_totals: [u64; 24],
+    pub weekday_totals: [u64; 7],
+    pub transaction_count: u64,
+    pub unique_references: u64,
+    pub payer_volumes: Vec<u64>,
+    pub outflow_total: u64,
+    pub fee_total: u64,
+    pub provenance_totals: [u64; Provenance::ALL.len()],
+}
+
+/// Input for `InputMode::DailyTotals` proofs.
+#[derive(Serialize, Deserialize)]
+pub struct TotalsInput {
+    pub totals: Totals,
+    /// `rows_commitment` of the statement the totals were computed from
+    pub rows_commitment: [u8; 32],
+    pub threshold: Option<u32>,
+    pub currency: Currency,
+    pub policy: ScoringPolicy,
+    pub as_of: Option<i64>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct SourceSummary {
+    pub tag: String,
+    pub total: u64,
+    pub first: Option<i64>,
+    pub last: Option<i64>,
+}
+
+/// Input the scoring core refuses to score.
+#[derive(Debug)]
+pub enum InputError {
+    /// A transaction isn't in the input's declared currency
+    MixedCurrencies { expected: String, found: String },
+    /// Chunk journals that don't fit together as one statement
+    InconsistentChunks(&'static str),
+    /// Host-computed totals that contradict themselves
+    InconsistentTotals(&'static str),
+    /// An offset from UTC no time zone uses
+    InvalidUtcOffset(i32),
+}
+
+impl std::fmt::Display for InputError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            InputError::MixedCurrencies { expected, found } => {
+                write!(f, "transactions mix currencies: expected {}, found {}", expected, found)
+            }
+            InputError::InconsistentChunks(reason) => write!(f, "inconsistent chunks: {}", reason),
+            InputError::InconsistentTotals(reason) => write!(f, "inconsistent totals: {}", reason),
+            InputError::InvalidUtcOffset(offset) => write!(f, "invalid UTC offset: {} seconds", offset),
+        }
+    }
+}
+
+impl std::error::Error for InputError {}
+
+/// Scores a till's transactions. Volumes in different currencies can't be
+/// banded together, so input mixing currencies is rejected.
+pub fn evaluate(input: ProofInput) -> Result<Evaluation, InputError> {
+    let (threshold, as_of) = (input.threshold, input.as_of);
+    let policy = input.policy.clone();
+    let currency = input.currency.clone();
+    let chunk = split(input, usize::MAX).remove(0);
+    Ok(score(summarize(chunk)?.into_totals(), threshold, as_of, &currency, &policy))
+}
+
+/// Aggregates a whole statement for `InputMode::DailyTotals` proofs.
+pub fn totals(input: ProofInput) -> Result<Totals, InputError> {
+    Ok(summarize(split(input, usize::MAX).remove(0))?.into_totals())
+}
+
+/// SHA-256 over a fixed encoding of every row in the statement, in input
+/// order. Strings are length-prefixed and absent values tagged, so distinct
+/// statements can't encode alike. Bump the tag if `Transaction` changes.
+pub fn rows_commitment(input: &ProofInput) -> [u8; 32] {
+    fn put_str(hasher: &mut Sha256, value: &str) {
+        hasher.update((value.len() as u64).to_le_bytes());
+        hasher.update(value.as_bytes());
+    }
+    fn put_opt(hasher: &mut Sha256, value: Option<&str>) {
+        match value {
+            Some(value) => {
+                hasher.update([1u8]);
+                put_str(hasher, value);
+            }
+            None => hasher.update([0u8]),
+        }
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(b"statement-rows/v4");
+    let secondary = input.secondary.iter().map(|s| (s.tag.as_str(), &s.transactions));
+    for (tag, transactions) in std::iter::once(("mpesa", &input.transactions)).chain(secondary) {
+        put_str(&mut hasher, tag);
+        hasher.update((transactions.len() as u64).to_le_bytes());
+        for tx in transactions {
+            hasher.update(tx.timestamp.to_le_bytes());
+            hasher.update(tx.amount.to_le_bytes());
+            put_str(&mut hasher, &tx.currency);
+            put_str(&mut hasher, tx.kind.as_str());
+            put_str(&mut hasher, tx.direction.as_str());
+            put_str(&mut hasher, &tx.reference);
+            put_opt(&mut hasher, tx.counterparty.as_deref());
+            put_opt(&mut hasher, tx.account.as_deref());
+            put_str(&mut hasher, tx.provenance.as_str());
+        }
+    }
+    hasher.finalize().into()
+}
+
+/// Scores host-computed totals, after checking they agree with each other.
+pub fn evaluate_totals(input: TotalsInput) -> Result<Evaluation, InputError> {
+    let totals = input.totals;
+    if totals.currency != input.currency.code {
+        return Err(InputError::MixedCurrencies {
+            expected: input.currency.code,
+            found: totals.currency,
+        });
+    }
+    if totals.utc_offset_secs.abs() > MAX_UTC_OFFSET_SECS {
+        return Err(InputError::InvalidUtcOffset(totals.utc_offset_secs));
+    }
+    let source_total: u64 = totals.sources.iter().map(|s| s.total).sum();
+    if totals.daily_totals.values().sum::<u64>() != source_total {
+        return Err(InputError::InconsistentTotals("daily totals don't add up to source totals"));
+    }
+    if totals.hourly_totals.iter().sum::<u64>() != source_total
+        || totals.weekday_totals.iter().sum::<u64>() != source_total
+        || totals.provenance_totals.iter().sum::<u64>() != source_total
+    {
+        return Err(InputError::InconsistentTotals("activity totals don't add up to source totals"));
+    }
+    if totals.payer_volumes.iter().sum::<u64>() > source_total {
+        return Err(InputError::InconsistentTotals("payer volumes exceed the total"));
+    }
+    if totals.unique_references > totals.transaction_count
+        || (totals.transaction_count == 0) != totals.daily_totals.is_empty()
+    {
+        return Err(InputError::InconsistentTotals("transaction counts don't match"));
+    }
+
+    Ok(score(totals, input.threshold, input.as_of, &input.currency, &input.policy))
+}
+
+/// Splits a statement into chunks of at most `max_transactions`, primary
+/// transactions first. There is always at least one chunk.
+pub fn split(input: ProofInput, max_transactions: usize) -> Vec<ChunkInput> {
+    let max_transactions = max_transactions.max(1);
+    let now = input
+        .transactions
+        .iter()
+        .filter(|t| input.account.is_none() || t.account == input.account)
+        .chain(input.secondary.iter().flat_map(|s| s.transactions.iter()))
+        .filter(|t| input.source.includes(t))
+        .map(|t| t.timestamp)
+        .max()
+        .unwrap_or(0);
+
+    let primary = input.transactions.len();
+    let secondary_len = input.secondary.as_ref().map_or(0, |s| s.transactions.len());
+    let count = (primary + secondary_len).div_ceil(max_transactions).max(1);
+
+    let mut primary_txs = input.transactions.into_iter();
+    let mut secondary_txs = input.secondary.as_ref().map(|s| s.transactions.clone()).unwrap_or_default().into_iter();
+    (0..count)
+        .map(|index| {
+            let transactions: Vec<Transaction> = primary_txs.by_ref().take(max_transactions).collect();
+            let remaining = max_transactions - transactions.len();
+            ChunkInput {
+                index: index as u32,
+                count: count as u32,
+                transactions,
+                secondary: input.secondary.as_ref().map(|s| TransactionSource {
+                    tag: s.tag.clone(),
+                    transactions: secondary_txs.by_ref().take(remaining).collect(),
+                }),
+                account: input.account.clone(),
+                currency: input.currency.clone(),
+                now,
+                utc_offset_secs: input.utc_offset_secs.unwrap_or(DEFAULT_UTC_OFFSET_SECS),
+                source: input.source,
+            }
+        })
+        .collect()
+}
+
+/// Aggregates one chunk of a statement.
+pub fn summarize(input: ChunkInput) -> Result<Summary, InputError> {
+    const MONTH_SECS: i64 = 30 * 24 * 60 * 60;
+
+    if input.utc_offset_secs.abs() > MAX_UTC_OFFSET_SECS {
+        return Err(InputError::InvalidUtcOffset(input.utc_offset_secs));
+    }
+    let currency = input.currency;
+    let all_transactions = input
+        .transactions
+        .iter()
+        .chain(input.secondary.iter().flat_map(|s| s.transactions.iter()));
+    for tx in all_transactions {
+        if tx.currency != currency.code {
+            return Err(InputError::MixedCurrencies {
+                expected: currency.code,
+                found: tx.currency.clone(),
+            });
+        }
+    }
+
+    let account_hash = input.account;
+    let primary = match account_hash.as_deref() {
+        Some(account) => input
+            .transactions
+            .into_iter()
+            .filter(|t| t.account.as_deref() == Some(account))
+            .collect(),
+        None => input.transactions,
+    };
+
+    let mut sources = vec![TransactionSource {
+        tag: "mpesa".to_string(),
+        transactions: primary,
+    }];
+    if let Some(secondary) = input.secondary {
+        sources.push(secondary);
+    }
+
+    // Score only the last 6 months
+    let now = input.now;
+    let six_months_ago = now - (6 * 30 * 24 * 60 * 60); // Approximate 6 months in seconds
+
+    let mut summary = Summary {
+        currency: currency.code,
+        account: account_hash,
+        now,
+        utc_offset_secs: input.utc_offset_secs,
+        source: input.source,
+        latest: None,
+        monthly_totals: [0; 12],
+        sources: Vec::new(),
+        daily_totals: BTreeMap::new(),
+        hourly_totals: [0; 24],
+        weekday_totals: [0; 7],
+        transaction_count: 0,
+        references: BTreeSet::new(),
+        counterparty_volumes: BTreeMap::new(),
+        outflow_total: 0,
+        fee_total: 0,
+        provenance_totals: [0; Provenance::ALL.len()],
+    };
+
+    for source in sources {
+        let mut source_summary = SourceSummary {
+            tag: source.tag,
+            total: 0,
+            first: None,
+            last: None,
+        };
+
+        for tx in source.transactions {
+            if !input.source.includes(&tx) {
+                continue;
+            }
+            summary.latest = summary.latest.max(Some(tx.timestamp));
+            if tx.amount == 0 {
+                continue;
+            }
+            // Only payments in are volume; what goes back out is netted off
+            let in_window = tx.timestamp >= six_months_ago;
+            match tx.direction {
+                Direction::Inflow if tx.kind.is_scored() => {}
+                Direction::Outflow if in_window && !tx.kind.moves_own_funds() => {
+                    summary.outflow_total += tx.amount;
+                    continue;
+                }
+                Direction::Fee if in_window => {
+                    summary.fee_total += tx.amount;
+                    continue;
+                }
+                _ => continue,
+            }
+
+            // Month-by-month shape looks further back than the scoring window
+            if tx.timestamp <= now {
+                let months_ago = ((now - tx.timestamp) / MONTH_SECS) as usize;
+                if months_ago < summary.monthly_totals.len() {
+                    summary.monthly_totals[months_ago] += tx.amount;
+                }
+            }
+
+            if tx.timestamp < six_months_ago {
+                continue;
+            }
+            source_summary.total += tx.amount;
+            source_summary.first = Some(source_summary.first.map_or(tx.timestamp, |t| t.min(tx.timestamp)));
+            source_summary.last = source_summary.last.max(Some(tx.timestamp));
+
+            *summary.daily_totals.entry(local_day(tx.timestamp, input.utc_offset_secs)).or_insert(0) += tx.amount;
+            let (weekday, hour) = local_weekday_hour(tx.timestamp, input.utc_offset_secs);
+            summary.weekday_totals[weekday] += tx.amount;
+            summary.hourly_totals[hour] += tx.amount;
+            summary.provenance_totals[tx.provenance.index()] += tx.amount;
+            summary.transaction_count += 1;
+            if let Some(counterparty) = tx.counterparty {
+                *summary.counterparty_volumes.entry(counterparty).or_insert(0) += tx.amount;
+            }
+            summary.references.insert(tx.reference);
+        }
+
+        summary.sources.push(source_summary);
+    }
+
+    Ok(summary)
+}
+
+/// Scores a chunked statement from its chunks' journals, which must be
+/// every chunk of the same statement in index order.
+pub fn evaluate_chunks(
+    chunks: Vec<ChunkJournal>,
+    threshold: Option<u32>,
+    as_of: Option<i64>,
+    currency: &Currency,
+    policy: &ScoringPolicy,
+) -> Result<Evaluation, InputError> {
+    let count = chunks.len();
+    let mut merged: Option<Summary> = None;
+    for (index, chunk) in chunks.into_iter().enumerate() {
+        if chunk.schema_version != CHUNK_SCHEMA_VERSION {
+            return Err(InputError::InconsistentChunks("not a chunk journal"));
+        }
+        if chunk.index as usize != index || chunk.count as usize != count {
+            return Err(InputError::InconsistentChunks("chunks missing or out of order"));
+        }
+        merged = Some(match merged {
+            None => chunk.summary,
+            Some(merged) => merge(merged, chunk.summary)?,
+        });
+    }
+
+    let merged = merged.ok_or(InputError::InconsistentChunks("no chunks"))?;
+    if merged.currency != currency.code {
+        return Err(InputError::MixedCurrencies {
+            expected: currency.code.clone(),
+            found: merged.currency,
+        });
+    }
+    if merged.latest.unwrap_or(0) != merged.now {
+        return Err(InputError::InconsistentChunks("now is not the statement's latest transaction"));
+    }
+
+    Ok(score(merged.into_totals(), threshold, as_of, currency, policy))
+}
+
+fn merge(mut into: Summary, from: Summary) -> Result<Summary, InputError> {
+    if into.currency != from.currency
+        || into.account != from.account
+        || into.now != from.now
+        || into.utc_offset_secs != from.utc_offset_secs
+        || into.source != from.source
+    {
+        return Err(InputError::InconsistentChunks("chunks of different statements"));
+    }
+    if into.sources.len() != from.sources.len()
+        || into.sources.iter().zip(&from.sources).any(|(a, b)| a.tag != b.tag)
+    {
+        return Err(InputError::InconsistentChunks("chunks with different sources"));
+    }
+
+    into.latest = into.latest.max(from.latest);
+    for (total, other) in into.monthly_totals.iter_mut().zip(from.monthly_totals) {
+        *total += other;
+    }
+    for (source, other) in into.sources.iter_mut().zip(from.sources) {
+        source.total += other.total;
+        source.first = match (source.first, other.first) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        };
+        source.last = source.last.max(other.last);
+    }
+    for (day, amount) in from.daily_totals {
+        *into.daily_totals.entry(day).or_insert(0) += amount;
+    }
+    for (total, other) in into.hourly_totals.iter_mut().zip(from.hourly_totals) {
+        *total += other;
+    }
+    for (total, other) in into.weekday_totals.iter_mut().zip(from.weekday_totals) {
+        *total += other;
+    }
+    into.transaction_count += from.transaction_count;
+    into.references.extend(from.references);
+    for (counterparty, amount) in from.counterparty_volumes {
+        *into.counterparty_volumes.entry(counterparty).or_insert(0) += amount;
+    }
+    into.outflow_total += from.outflow_total;
+    into.fee_total += from.fee_total;
+    for (total, other) in into.provenance_totals.iter_mut().zip(from.provenance_totals) {
+        *total += other;
+    }
+    Ok(into)
+}
+
+fn score(
+    summary: Totals,
+    threshold: Option<u32>,
+    as_of: Option<i64>,
+    currency: &Currency,
+    policy: &ScoringPolicy,
+) -> Evaluation {
+    let policy_hash = policy.hash(&currency.code);
+    let now = summary.now;
+    let utc_offset_secs = summary.utc_offset_secs;
+    let account_hash = summary.account;
+    let monthly_volumes = calculate_monthly_buckets(&summary.monthly_totals, currency, policy);
+    let provenance = ProvenanceMix::measure(&summary.provenance_totals);
+    let source = summary.source;
+
+    // Per-source bands so lenders see each source's share of turnover
+    let source_volumes: Vec<SourceVolume> = summary
+        .sources
+        .iter()
+        .map(|source| SourceVolume {
+            tag: source.tag.clone(),
+            monthly_volume_range: categorize_volume(
+                calculate_monthly_volume(source.total, source.first, source.last),
+                currency,
+                policy,
+            ),
+        })
+        .collect();
+
+    if summary.transaction_count == 0 {
+        if let Some(threshold) = threshold {
+            return Evaluation::Threshold(ThresholdOutput {
+                period_start: now,
+                period_end: now,
+                score_at_least: threshold,
+                meets_threshold: threshold == 0,
+                account_hash,
+                currency: summary.currency,
+                policy_hash,
+                recency: Recency::measure(as_of, now),
+                coverage_percentage: 0,
+                utc_offset_secs,
+                provenance,
+                source,
+            });
+        }
+
+        return Evaluation::Full(ProofOutput {
+            till_number_hash: [0u8; 32],
+            period_start: now,
+            period_end: now,
+            credit_score: 0,
+            metrics: BusinessMetrics {
+                monthly_volume_range: VolumeRange::VeryLow,
+                consistency_score: 0,
+                growth_trend: GrowthTrend::Declining,
+                active_days_percentage: 0,
+                customer_diversity_score: 0,
+                concentration_risk: ConcentrationRisk::Unknown,
+                max_weekly_drawdown_percentage: 0,
+                volatility_30d_percentage: 0,
+            },
+            score_breakdown: ScoreBreakdown::default(),
+            source_volumes,
+            monthly_volumes,
+            account_hash,
+            currency: summary.currency,
+            policy_hash,
+            activity_profile: calculate_activity_profile(&summary.hourly_totals, &summary.weekday_totals),
+            outlier_adjustment: policy.outlier_cap.map(|cap_multiple| OutlierAdjustment {
+                cap_multiple,
+                days_capped: 0,
+                volume_removed: ShareBucket::Negligible,
+                raw_monthly_volume_range: VolumeRange::VeryLow,
+            }),
+            recency: Recency::measure(as_of, now),
+            coverage_percentage: 0,
+            utc_offset_secs,
+            cash_flow: CashFlow {
+                monthly_net_inflow_range: VolumeRange::VeryLow,
+                net_inflow_percentage: 0,
+                fee_percentage: 0,
+            },
+            provenance,
+            source,
+        });
+    }
+
+    // Everything but active days and diversity is measured after capping
+    let (capped_volumes, days_capped, volume_removed) = match policy.outlier_cap {
+        Some(cap_multiple) => cap_outliers(&summary.daily_totals, cap_multiple),
+        None => (summary.daily_totals.clone(), 0, 0),
+    };
+    let daily_volumes = &capped_volumes;
+
+    let period_start = summary.sources.iter().filter_map(|s| s.first).min().unwrap();
+    let period_end = summary.sources.iter().filter_map(|s| s.last).max().unwrap();
+
+    // Calculate metrics
+    let days_in_period = calculate_days_between(period_start, period_end);
+    let raw_volume: u64 = summary.sources.iter().map(|s| s.total).sum();
+    let monthly_volume_range = categorize_volume(
+        calculate_monthly_volume(raw_volume - volume_removed, Some(period_start), Some(period_end)),
+        currency,
+        policy,
+    );
+    let outlier_adjustment = policy.outlier_cap.map(|cap_multiple| OutlierAdjustment {
+        cap_multiple,
+        days_capped,
+        volume_removed: share_bucket(volume_removed, raw_volume),
+        raw_monthly_volume_range: categorize_volume(
+            calculate_monthly_volume(raw_volume, Some(period_start), Some(period_end)),
+            currency,
+            policy,
+        ),
+    });
+
+    // Calculate consistency score
+    let consistency_score = calculate_consistency(daily_volumes);
+
+    // Calculate active days percentage
+    let active_days = daily_volumes.len() as u64;
+    let active_days_percentage = if days_in_period > 0 {
+        ((active_days as f64 / days_in_period as f64) * 100.0) as u8
+    } else {
+        0
+    };
+
+    // Calculate growth trend
+    let growth_trend = calculate_growth_trend(daily_volumes);
+
+    // Calculate customer diversity (based on unique references)
+    let customer_diversity_score =
+        ((summary.unique_references as f64 / summary.transaction_count as f64) * 100.0) as u8;
+
+    let concentration_risk = calculate_concentration_risk(&summary.payer_volumes);
+    let max_weekly_drawdown_percentage = calculate_max_weekly_drawdown(daily_volumes);
+    let volatility_30d_percentage = calculate_rolling_volatility(daily_volumes);
+    let activity_profile = calculate_activity_profile(&summary.hourly_totals, &summary.weekday_totals);
+
+    let metrics = BusinessMetrics {
+        monthly_volume_range,
+        consistency_score,
+        growth_trend,
+        active_days_percentage,
+        customer_diversity_score,
+        concentration_risk,
+        max_weekly_drawdown_percentage,
+        volatility_30d_percentage,
+    };
+
+    // Calculate credit score
+    let score_breakdown = calculate_credit_score(&metrics);
+    let recency = Recency::measure(as_of, period_end);
+    let credit_score = recency.apply(score_breakdown.total());
+    let coverage_percentage = coverage(
+        summary.daily_totals.keys().copied(),
+        local_day(period_start, utc_offset_secs),
+        local_day(period_end, utc_offset_secs),
+    )
+    .percentage();
+    let cash_flow = calculate_cash_flow(
+        raw_volume,
+        summary.outflow_total,
+        summary.fee_total,
+        (period_start, period_end),
+        currency,
+        policy,
+    );
+
+    if let Some(threshold) = threshold {
+        return Evaluation::Threshold(ThresholdOutput {
+            period_start,
+            period_end,
+            score_at_least: threshold,
+            meets_threshold: credit_score >= threshold,
+            account_hash,
+            currency: summary.currency,
+            policy_hash,
+            recency,
+            coverage_percentage,
+            utc_offset_secs,
+            provenance,
+            source,
+        });
+    }
+
+    Evaluation::Full(ProofOutput {
+        till_number_hash: [0u8; 32], // Will be set by host
+        period_start,
+        period_end,
+        credit_score,
+        metrics,
+        score_breakdown,
+        source_volumes,
+        monthly_volumes,
+        account_hash,
+        currency: summary.currency,
+        policy_hash,
+        activity_profile,
+        outlier_adjustment,
+        recency,
+        coverage_percentage,
+        utc_offset_secs,
+        cash_flow,
+        provenance,
+        source,
+    })
+}
+
+/// Net inflow over the scoring window from its payment, outflow and fee
+/// totals. Measured on raw payments, before any outlier capping.
+fn calculate_cash_flow(
+    inflow: u64,
+    outflow: u64,
+    fees: u64,
+    (period_start, period_end): (i64, i64),
+    currency: &Currency,
+    policy: &ScoringPolicy,
+) -> CashFlow {
+    let net_inflow = inflow.saturating_sub(outflow.saturating_add(fees));
+    let percentage = |part: u64| {
+        if inflow == 0 {
+            0
+        } else {
+            (part as u128 * 100 / inflow as u128).min(100) as u8
+        }
+    };
+    CashFlow {
+        monthly_net_inflow_range: categorize_volume(
+            calculate_monthly_volume(net_inflow, Some(period_start), Some(period_end)),
+            currency,
+            policy,
+        ),
+        net_inflow_percentage: percentage(net_inflow),
+        fee_percentage: percentage(fees),
+    }
+}
+
+/// Caps each day at `cap_multiple` times the median active day. Returns the
+/// capped days, how many were capped and the volume removed.
+fn cap_outliers(daily_volumes: &BTreeMap<i64, u64>, cap_multiple: u32) -> (BTreeMap<i64, u64>, u32, u64) {
+    let mut sorted: Vec<u64> = daily_volumes.values().copied().collect();
+    sorted.sort_unstable();
+    let Some(&median) = sorted.get(sorted.len().saturating_sub(1) / 2) else {
+        return (BTreeMap::new(), 0, 0);
+    };
+    let cap = median.saturating_mul(cap_multiple as u64);
+
+    let mut days_capped = 0;
+    let mut removed = 0;
+    let capped = daily_volumes
+        .iter()
+        .map(|(&day, &volume)| {
+            if volume > cap {
+                days_capped += 1;
+                removed += volume - cap;
+            }
+            (day, volume.min(cap))
+        })
+        .collect();
+    (capped, days_capped, removed)
+}
+
+fn calculate_monthly_volume(total_volume: u64, first: Option<i64>, last: Option<i64>) -> u64 {
+    let (Some(start), Some(end)) = (first, last) else {
+        return 0;
+    };
+
+    let days_in_period = calculate_days_between(start, end);
+    ((total_volume as f64 / days_in_period as f64) * 30.0) as u64
+}
+
+/// Bands each of the last 12 30-day months by volume, oldest first.
+/// Months before the first transaction are dropped so a young till's
+/// history isn't padded with VeryLow.
+fn calculate_monthly_buckets(totals: &[u64; 12], currency: &Currency, policy: &ScoringPolicy) -> Vec<VolumeRange> {
+    let Some(oldest_month) = totals.iter().rposition(|&total| total > 0) else {
+        return Vec::new();
+    };
+    (0..=oldest_month).rev().map(|m| categorize_volume(totals[m], currency, policy)).collect()
+}
+
+/// Offset of East Africa Time, where most merchants this scores trade.
+pub const DEFAULT_UTC_OFFSET_SECS: i32 = 3 * 60 * 60;
+
+/// Largest offset any time zone uses.
+const MAX_UTC_OFFSET_SECS: i32 = 14 * 60 * 60;
+
+/// Local day since the epoch of a timestamp.
+pub fn local_day(timestamp: i64, utc_offset_secs: i32) -> i64 {
+    (timestamp + utc_offset_secs as i64).div_euclid(24 * 60 * 60)
+}
+
+/// Weekday (Monday = 0) and hour of a timestamp in local time.
+fn local_weekday_hour(timestamp: i64, utc_offset_secs: i32) -> (usize, usize) {
+    let local = timestamp + utc_offset_secs as i64;
+    // 1970-01-01 was a Thursday
+    let weekday = (local.div_euclid(24 * 60 * 60) + 3).rem_euclid(7) as usize;
+    let hour = (local.rem_euclid(24 * 60 * 60) / (60 * 60)) as usize;
+    (weekday, hour)
+}
+
+fn calculate_activity_profile(hourly_totals: &[u64; 24], weekday_totals: &[u64; 7]) -> ActivityProfile {
+    let total: u64 = hourly_totals.iter().sum();
+    let business_hours: u64 = hourly_totals[8..18].iter().sum();
+    let late_night: u64 = hourly_totals[22..].iter().chain(&hourly_totals[..5]).sum();
+
+    ActivityProfile {
+        weekday_shares: weekday_totals.map(|volume| share_bucket(volume, total)),
+        business_hours_share: share_bucket(business_hours, total),
+        late_night_share: share_bucket(late_night, total),
+    }
+}
+
+fn share_bucket(part: u64, total: u64) -> ShareBucket {
+    if total == 0 {
+        return ShareBucket::Negligible;
+    }
+    let percentage = (part as u128 * 100 / total as u128) as u64;
+    if percentage < 5 {
+        ShareBucket::Negligible
+    } else if percentage < 20 {
+        ShareBucket::Low
+    } else if percentage < 40 {
+        ShareBucket::Moderate
+    } else if percentage < 60 {
+        ShareBucket::High
+    } else {
+        ShareBucket::Dominant
+    }
+}
+
+fn calculate_days_between(start: i64, end: i64) -> u64 {
+    let diff = end - start;
+    if diff <= 0 {
+        1
+    } else {
+        (diff / (24 * 60 * 60)) as u64 + 1
+    }
+}
+
+fn categorize_volume(monthly_volume: u64, currency: &Currency, policy: &ScoringPolicy) -> VolumeRange {
+    let major_units = monthly_volume / 10u64.pow(currency.minor_unit_exponent);
+    let [low, medium, high, very_high] = policy.volume_thresholds;
+
+    if major_units < low {
+        VolumeRange::VeryLow
+    } else if major_units < medium {
+        VolumeRange::Low
+    } else if major_units < high {
+        VolumeRange::Medium
+    } else if major_units < very_high {
+        VolumeRange::High
+    } else {
+        VolumeRange::VeryHigh
+    }
+}
+
+fn calculate_consistency(daily_volumes: &BTreeMap<i64, u64>) -> u8 {
+    if daily_volumes.is_empty() {
+        return 0;
+    }
+
+    let daily_totals: Vec<u64> = daily_volumes.values().copied().collect();
+
+    let mean = daily_totals.iter().sum::<u64>() as f64 / daily_totals.len() as f64;
+
+    if mean == 0.0 {
+        return 0;
+    }
+
+    let variance = daily_totals
+        .iter()
+        .map(|&x| {
+            let diff = x as f64 - mean;
+            diff * diff
+        })
+        .sum::<f64>() / daily_totals.len() as f64;
+
+    let std_dev = variance.sqrt();
+    let coefficient_of_variation = if mean > 0.0 {
+        std_dev / mean
+    } else {
+        return 0;
+    };
+
+    // Convert CV to score (lower CV = higher consistency)
+    // CV of 0 = 100, CV of 1.0 = 0, CV of 0.5 = 50
+    let score = (1.0 - coefficient_of_variation.min(1.0)) * 100.0;
+    score.clamp(0.0, 100.0) as u8
+}
+
+fn calculate_growth_trend(daily_volumes: &BTreeMap<i64, u64>) -> GrowthTrend {
+    if daily_volumes.len() < 2 {
+        return GrowthTrend::Stable;
+    }
+
+    let mut sorted_days: Vec<&i64> = daily_volumes.keys().collect();
+    sorted_days.sort();
+
+    // Split into three periods for trend analysis
+    let third = sorted_days.len() / 3;
+    if third == 0 {
+        return GrowthTrend::Stable;
+    }
+
+    let first_period: u64 = sorted_days[..third]
+        .iter()
+        .map(|&day| daily_volumes[day])
+        .sum();
+
+    let last_period: u64 = sorted_days[sorted_days.len() - third..]
+        .iter()
+        .map(|&day| daily_volumes[day])
+        .sum();
+
+    let first_avg = first_period as f64 / third as f64;
+    let last_avg = last_period as f64 / third as f64;
+
+    if first_avg == 0.0 {
+        return GrowthTrend::Stable;
+    }
+
+    let growth_rate = (last_avg - first_avg) / first_avg;
+
+    if growth_rate < -0.2 {
+        GrowthTrend::Declining
+    } else if growth_rate < 0.1 {
+        GrowthTrend::Stable
+    } else if growth_rate < 0.5 {
+        GrowthTrend::Growing
+    } else {
+        GrowthTrend::Rapid
+    }
+}
+
+/// Largest fall from a weekly-volume peak to a later weekly trough. Weeks
+/// with no sales count as zero. Only whole weeks from the first sale are
+/// compared: the days after the last of them would read as a slump.
+fn calculate_max_weekly_drawdown(daily_volumes: &BTreeMap<i64, u64>) -> u8 {
+    let (Some(&first_day), Some(&last_day)) = (daily_volumes.keys().min(), daily_volumes.keys().max()) else {
+        return 0;
+    };
+
+    let mut weekly = vec![0u64; ((last_day - first_day + 1) / 7) as usize];
+    for (day, amount) in daily_volumes {
+        if let Some(week) = weekly.get_mut(((day - first_day) / 7) as usize) {
+            *week += amount;
+        }
+    }
+
+    let mut peak = 0u64;
+    let mut max_drawdown = 0.0f64;
+    for &volume in &weekly {
+        peak = peak.max(volume);
+        if peak > 0 {
+            max_drawdown = max_drawdown.max((peak - volume) as f64 / peak as f64);
+        }
+    }
+
+    (max_drawdown * 100.0).min(100.0) as u8
+}
+
+/// Coefficient of variation of the trailing 30-day volume, taken at every
+/// day once a full window is available. Short histories score zero.
+fn calculate_rolling_volatility(daily_volumes: &BTreeMap<i64, u64>) -> u8 {
+    const WINDOW: usize = 30;
+
+    let (Some(&first_day), Some(&last_day)) = (daily_volumes.keys().min(), daily_volumes.keys().max()) else {
+        return 0;
+    };
+
+    let mut daily = vec![0u64; (last_day - first_day + 1) as usize];
+    for (day, &amount) in daily_volumes {
+        daily[(day - first_day) as usize] = amount;
+    }
+    if daily.len() <= WINDOW {
+        return 0;
+    }
+
+    let rolling: Vec<f64> = daily
+        .windows(WINDOW)
+        .map(|w| w.iter().sum::<u64>() as f64)
+        .collect();
+
+    let mean = rolling.iter().sum::<f64>() / rolling.len() as f64;
+    if mean == 0.0 {
+        return 0;
+    }
+    let variance = rolling.iter().map(|x| (x - mean) * (x - mean)).sum::<f64>() / rolling.len() as f64;
+
+    ((variance.sqrt() / mean) * 100.0).min(100.0) as u8
+}
+
+/// Sum of squared payer shares of volume (HHI). Uses the 0.15 / 0.25
+/// cut-offs common in market-concentration analysis.
+fn calculate_concentration_risk(by_payer: &[u64]) -> ConcentrationRisk {
+    let total = by_payer.iter().sum::<u64>() as f64;
+    if total == 0.0 {
+        return ConcentrationRisk::Unknown;
+    }
+
+    let hhi: f64 = by_payer
+        .iter()
+        .map(|&amount| {
+            let share = amount as f64 / total;
+            share * share
+        })
+        .sum();
+
+    if hhi < 0.15 {
+        ConcentrationRisk::Low
+    } else if hhi < 0.25 {
+        ConcentrationRisk::Moderate
+    } else {
+        ConcentrationRisk::High
+    }
+}
+
+fn calculate_credit_score(metrics: &BusinessMetrics) -> ScoreBreakdown {
+    // Volume Component (30 points)
+    let volume_points = match metrics.monthly_volume_range {
+        VolumeRange::VeryLow => 5,
+        VolumeRange::Low => 10,
+        VolumeRange::Medium => 20,
+        VolumeRange::High => 25,
+        VolumeRange::VeryHigh => 30,
+    };
+
+    // Consistency Component (30 points)
+    let consistency_points = (metrics.consistency_score as f64 * 0.3) as u32;
+
+    // Activity Component (20 points)
+    let activity_points = ((metrics.active_days_percentage as f64 / 100.0) * 20.0) as u32;
+
+    // Growth Component (10 points)
+    let growth_points = match metrics.growth_trend {
+        GrowthTrend::Declining => 0,
+        GrowthTrend::Stable => 5,
+        GrowthTrend::Growing => 7,
+        GrowthTrend::Rapid => 10,
+    };
+
+    // Diversity Component (10 points)
+    let diversity_points = ((metrics.customer_diversity_score as f64 / 100.0) * 10.0) as u32;
+
+    // Concentration Penalty (up to 10 points)
+    let concentration_penalty = match metrics.concentration_risk {
+        ConcentrationRisk::Low | ConcentrationRisk::Unknown => 0,
+        ConcentrationRisk::Moderate => 5,
+        ConcentrationRisk::High => 10,
+    };
+
+    // Volatility Penalty (up to 10 points)
+    let drawdown_penalty = match metrics.max_weekly_drawdown_percentage {
+        0..=49 => 0,
+        50..=74 => 3,
+        _ => 5,
+    };
+    let swing_penalty = match metrics.volatility_30d_percentage {
+        0..=24 => 0,
+        25..=49 => 3,
+        _ => 5,
+    };
+
+    ScoreBreakdown {
+        volume_points,
+        consistency_points,
+        activity_points,
+        growth_points,
+        diversity_points,
+        concentration_penalty,
+        volatility_penalty: drawdown_penalty + swing_penalty,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NOW: i64 = 1_750_000_000;
+    const DAY: i64 = 24 * 60 * 60;
+
+    fn payment(timestamp: i64, amount: u64, reference: &str) -> Transaction {
+        Transaction {
+            timestamp,
+            amount,
+            currency: "KES".to_string(),
+            kind: TransactionKind::Payment,
+            direction: Direction::Inflow,
+            reference: reference.to_string(),
+            counterparty: Some(format!("payer-{}", reference.len())),
+            account: None,
+            provenance: Provenance::CsvUpload,
+        }
+    }
+
+    fn input(transactions: Vec<Transaction>, threshold: Option<u32>) -> ProofInput {
+        ProofInput {
+            transactions,
+            secondary: None,
+            threshold,
+            account: None,
+            currency: Currency {
+                code: "KES".to_string(),
+                minor_unit_exponent: 2,
+            },
+            policy: ScoringPolicy {
+                volume_thresholds: [50_000, 250_000, 1_000_000, 5_000_000],
+                outlier_cap: None,
+            },
+            as_of: Some(NOW),
+            utc_offset_secs: None,
+            source: DataSource::All,
+        }
+    }
+
+    /// Sixty days of KES 2,000 a day, about KES 60,000 a month.
+    fn steady_statement() -> Vec<Transaction> {
+        (0..60).map(|day| payment(NOW - day * DAY, 200_000, &format!("RCP{:04}", day))).collect()
+    }
+
+    fn full(evaluation: Evaluation) -> ProofOutput {
+        match evaluation {
+            Evaluation::Full(output) => output,
+            Evaluation::Threshold(_) => panic!("expected a full evaluation"),
+        }
+    }
+
+    #[test]
+    fn scores_a_fixed_statement() {
+        let output = full(evaluate(input(steady_statement(), None)).unwrap());
+
+        assert_eq!(output.period_start, NOW - 59 * DAY);
+        assert_eq!(output.period_end, NOW);
+        assert!(matches!(output.metrics.monthly_volume_range, VolumeRange::Low));
+        // The same amount every day
+        assert_eq!(output.metrics.consistency_score, 100);
+        assert_eq!(output.score_breakdown.volume_points, 10);
+        assert_eq!(output.credit_score, output.score_breakdown.total());
+
+        // Scoring is deterministic, which the guest and the API rely on
+        let again = full(evaluate(input(steady_statement(), None)).unwrap());
+        assert_eq!(again.credit_score, output.credit_score);
+        assert_eq!(again.policy_hash, output.policy_hash);
+    }
+
+    #[test]
+    fn threshold_proofs_commit_only_the_outcome() {
+        let score = full(evaluate(input(steady_statement(), None)).unwrap()).credit_score;
+
+        for (threshold, meets) in [(score, true), (score + 1, false)] {
+            match evaluate(input(steady_statement(), Some(threshold))).unwrap() {
+                Evaluation::Threshold(output) => {
+                    assert_eq!(output.score_at_least, threshold);
+                    assert_eq!(output.meets_threshold, meets);
+                }
+                Evaluation::Full(_) => panic!("expected a threshold evaluation"),
+            }
+        }
+    }
+
+    #[test]
+    fn empty_statements_score_zero() {
+        let output = full(evaluate(input(Vec::new(), None)).unwrap());
+        assert_eq!(output.credit_score, 0);
+        assert_eq!(output.coverage_percentage, 0);
+
+        let meets = |threshold| match evaluate(input(Vec::new(), Some(threshold))).unwrap() {
+            Evaluation::Threshold(output) => output.meets_threshold,
+            Evaluation::Full(_) => panic!("expected a threshold evaluation"),
+        };
+        assert!(meets(0));
+        assert!(!meets(1));
+    }
+
+    #[test]
+    fn scores_stay_within_bounds() {
+        let statements = [
+            // One enormous payment
+            vec![payment(NOW, u32::MAX as u64 * 1_000, "RCP0001")],
+            // Wildly swinging daily totals
+            (0..180)
+                .map(|day| {
+                    let amount = if day % 2 == 0 { 100 } else { 10_000_000_000 };
+                    payment(NOW - day * DAY, amount, &format!("RCP{:04}", day))
+                })
+                .collect(),
+            // Busy every day for six months
+            (0..180 * 20)
+                .map(|n| payment(NOW - (n / 20) * DAY - n % 20, 5_000_000, &format!("RCP{:05}", n)))
+                .collect(),
+        ];
+        for transactions in statements {
+            let output = full(evaluate(input(transactions, None)).unwrap());
+            assert!(output.credit_score <= 100, "scored {}", output.credit_score);
+            assert!(output.metrics.consistency_score <= 100);
+            assert!(output.metrics.active_days_percentage <= 100);
+            assert!(output.coverage_percentage <= 100);
+        }
+    }
+
+    #[test]
+    fn rejects_mixed_currencies() {
+        let mut transactions = steady_statement();
+        transactions[3].currency = "UGX".to_string();
+        assert!(matches!(evaluate(input(transactions, None)), Err(InputError::MixedCurrencies { .. })));
+    }
+}
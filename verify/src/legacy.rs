@@ -0,0 +1,359 @@
+//! Journal layouts of earlier schema versions. Receipts proved with them
+//! are still verified, so their chunk image ID has to be reachable; nothing
+//! else in them is decoded.
+
+use risc0_zkvm::Journal;
+
+/// `Evaluation` as committed by an older journal version, with `T` and `U`
+/// that version's `ProofOutput` and `ThresholdOutput`. Only decoded to reach
+/// the fields after it.
+#[derive(serde::Deserialize)]
+#[allow(dead_code)]
+enum LegacyEvaluation<T, U = ThresholdOutputV5> {
+    Full(T),
+    Threshold(U),
+}
+
+/// `ThresholdOutput` up to v5, before recency was committed.
+type ThresholdOutputV5 = (i64, i64, u32, bool, Option<String>, String, [u8; 32]);
+
+/// v6 appended recency.
+type ThresholdOutputV6 = (i64, i64, u32, bool, Option<String>, String, [u8; 32], proof_core::Recency);
+
+/// v7 appended coverage.
+type ThresholdOutputV7 = (i64, i64, u32, bool, Option<String>, String, [u8; 32], proof_core::Recency, u8);
+
+/// v8 appended the UTC offset.
+type ThresholdOutputV8 = (i64, i64, u32, bool, Option<String>, String, [u8; 32], proof_core::Recency, u8, i32);
+
+/// `ProvenanceMix` up to v11, before photo uploads had a provenance.
+type ProvenanceMixV10 = (u8, [u8; 6]);
+
+/// `ProvenanceMix` in v12, before owner corrections had a provenance.
+type ProvenanceMixV12 = (u8, [u8; 7]);
+
+/// v10 appended the provenance mix.
+type ThresholdOutputV10 = (
+    i64,
+    i64,
+    u32,
+    bool,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::Recency,
+    u8,
+    i32,
+    ProvenanceMixV10,
+);
+
+/// v11 appended the data source.
+type ThresholdOutputV11 = (
+    i64,
+    i64,
+    u32,
+    bool,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::Recency,
+    u8,
+    i32,
+    ProvenanceMixV10,
+    proof_core::DataSource,
+);
+
+/// v12 widened the provenance mix.
+type ThresholdOutputV12 = (
+    i64,
+    i64,
+    u32,
+    bool,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::Recency,
+    u8,
+    i32,
+    ProvenanceMixV12,
+    proof_core::DataSource,
+);
+
+/// `ProofOutput` up to v3, field for field.
+type ProofOutputV3 = (
+    [u8; 32],
+    i64,
+    i64,
+    u32,
+    proof_core::BusinessMetrics,
+    proof_core::ScoreBreakdown,
+    Vec<proof_core::SourceVolume>,
+    Vec<proof_core::VolumeRange>,
+    Option<String>,
+    String,
+    [u8; 32],
+);
+
+/// v4 appended the activity profile.
+type ProofOutputV4 = (
+    [u8; 32],
+    i64,
+    i64,
+    u32,
+    proof_core::BusinessMetrics,
+    proof_core::ScoreBreakdown,
+    Vec<proof_core::SourceVolume>,
+    Vec<proof_core::VolumeRange>,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::ActivityProfile,
+);
+
+/// v5 appended the outlier adjustment.
+type ProofOutputV5 = (
+    [u8; 32],
+    i64,
+    i64,
+    u32,
+    proof_core::BusinessMetrics,
+    proof_core::ScoreBreakdown,
+    Vec<proof_core::SourceVolume>,
+    Vec<proof_core::VolumeRange>,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::ActivityProfile,
+    Option<proof_core::OutlierAdjustment>,
+);
+
+/// v6 appended recency, on both outputs.
+type ProofOutputV6 = (
+    [u8; 32],
+    i64,
+    i64,
+    u32,
+    proof_core::BusinessMetrics,
+    proof_core::ScoreBreakdown,
+    Vec<proof_core::SourceVolume>,
+    Vec<proof_core::VolumeRange>,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::ActivityProfile,
+    Option<proof_core::OutlierAdjustment>,
+    proof_core::Recency,
+);
+
+/// v7 appended coverage, on both outputs.
+type ProofOutputV7 = (
+    [u8; 32],
+    i64,
+    i64,
+    u32,
+    proof_core::BusinessMetrics,
+    proof_core::ScoreBreakdown,
+    Vec<proof_core::SourceVolume>,
+    Vec<proof_core::VolumeRange>,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::ActivityProfile,
+    Option<proof_core::OutlierAdjustment>,
+    proof_core::Recency,
+    u8,
+);
+
+/// v8 appended the UTC offset days were grouped in, on both outputs.
+type ProofOutputV8 = (
+    [u8; 32],
+    i64,
+    i64,
+    u32,
+    proof_core::BusinessMetrics,
+    proof_core::ScoreBreakdown,
+    Vec<proof_core::SourceVolume>,
+    Vec<proof_core::VolumeRange>,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::ActivityProfile,
+    Option<proof_core::OutlierAdjustment>,
+    proof_core::Recency,
+    u8,
+    i32,
+);
+
+/// v9 appended cash flow to the full output.
+type ProofOutputV9 = (
+    [u8; 32],
+    i64,
+    i64,
+    u32,
+    proof_core::BusinessMetrics,
+    proof_core::ScoreBreakdown,
+    Vec<proof_core::SourceVolume>,
+    Vec<proof_core::VolumeRange>,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::ActivityProfile,
+    Option<proof_core::OutlierAdjustment>,
+    proof_core::Recency,
+    u8,
+    i32,
+    proof_core::CashFlow,
+);
+
+/// v10 appended the provenance mix, on both outputs.
+type ProofOutputV10 = (
+    [u8; 32],
+    i64,
+    i64,
+    u32,
+    proof_core::BusinessMetrics,
+    proof_core::ScoreBreakdown,
+    Vec<proof_core::SourceVolume>,
+    Vec<proof_core::VolumeRange>,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::ActivityProfile,
+    Option<proof_core::OutlierAdjustment>,
+    proof_core::Recency,
+    u8,
+    i32,
+    proof_core::CashFlow,
+    ProvenanceMixV10,
+);
+
+/// v11 appended the data source, on both outputs.
+type ProofOutputV11 = (
+    [u8; 32],
+    i64,
+    i64,
+    u32,
+    proof_core::BusinessMetrics,
+    proof_core::ScoreBreakdown,
+    Vec<proof_core::SourceVolume>,
+    Vec<proof_core::VolumeRange>,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::ActivityProfile,
+    Option<proof_core::OutlierAdjustment>,
+    proof_core::Recency,
+    u8,
+    i32,
+    proof_core::CashFlow,
+    ProvenanceMixV10,
+    proof_core::DataSource,
+);
+
+/// v12 widened the provenance mix, on both outputs.
+type ProofOutputV12 = (
+    [u8; 32],
+    i64,
+    i64,
+    u32,
+    proof_core::BusinessMetrics,
+    proof_core::ScoreBreakdown,
+    Vec<proof_core::SourceVolume>,
+    Vec<proof_core::VolumeRange>,
+    Option<String>,
+    String,
+    [u8; 32],
+    proof_core::ActivityProfile,
+    Option<proof_core::OutlierAdjustment>,
+    proof_core::Recency,
+    u8,
+    i32,
+    proof_core::CashFlow,
+    ProvenanceMixV12,
+    proof_core::DataSource,
+);
+
+/// The image ID of the guest that proved a composed receipt's chunks, or
+/// None for a single-run proof. Errors name why the journal can't be read.
+pub(crate) fn chunk_image_id(journal: &Journal) -> Result<Option<[u32; 8]>, String> {
+    let id = match proof_core::schema_version(&journal.bytes) {
+        // v1 journals predate chunking
+        Some(1) => return Ok(None),
+        // v2 is v3 without the trailing input mode
+        Some(2) => journal
+            .decode::<(u32, LegacyEvaluation<ProofOutputV3>, Option<[u32; 8]>)>()
+            .map(|(_, _, id)| id),
+        Some(3) => journal
+            .decode::<(u32, LegacyEvaluation<ProofOutputV3>, Option<[u32; 8]>, proof_core::InputMode)>()
+            .map(|(_, _, id, _)| id),
+        Some(4) => journal
+            .decode::<(u32, LegacyEvaluation<ProofOutputV4>, Option<[u32; 8]>, proof_core::InputMode)>()
+            .map(|(_, _, id, _)| id),
+        Some(5) => journal
+            .decode::<(u32, LegacyEvaluation<ProofOutputV5>, Option<[u32; 8]>, proof_core::InputMode)>()
+            .map(|(_, _, id, _)| id),
+        Some(6) => journal
+            .decode::<(
+                u32,
+                LegacyEvaluation<ProofOutputV6, ThresholdOutputV6>,
+                Option<[u32; 8]>,
+                proof_core::InputMode,
+            )>()
+            .map(|(_, _, id, _)| id),
+        Some(7) => journal
+            .decode::<(
+                u32,
+                LegacyEvaluation<ProofOutputV7, ThresholdOutputV7>,
+                Option<[u32; 8]>,
+                proof_core::InputMode,
+            )>()
+            .map(|(_, _, id, _)| id),
+        Some(8) => journal
+            .decode::<(
+                u32,
+                LegacyEvaluation<ProofOutputV8, ThresholdOutputV8>,
+                Option<[u32; 8]>,
+                proof_core::InputMode,
+            )>()
+            .map(|(_, _, id, _)| id),
+        // v9's threshold output is v8's
+        Some(9) => journal
+            .decode::<(
+                u32,
+                LegacyEvaluation<ProofOutputV9, ThresholdOutputV8>,
+                Option<[u32; 8]>,
+                proof_core::InputMode,
+            )>()
+            .map(|(_, _, id, _)| id),
+        Some(10) => journal
+            .decode::<(
+                u32,
+                LegacyEvaluation<ProofOutputV10, ThresholdOutputV10>,
+                Option<[u32; 8]>,
+                proof_core::InputMode,
+            )>()
+            .map(|(_, _, id, _)| id),
+        Some(11) => journal
+            .decode::<(
+                u32,
+                LegacyEvaluation<ProofOutputV11, ThresholdOutputV11>,
+                Option<[u32; 8]>,
+                proof_core::InputMode,
+            )>()
+            .map(|(_, _, id, _)| id),
+        Some(12) => journal
+            .decode::<(
+                u32,
+                LegacyEvaluation<ProofOutputV12, ThresholdOutputV12>,
+                Option<[u32; 8]>,
+                proof_core::InputMode,
+            )>()
+            .map(|(_, _, id, _)| id),
+        Some(proof_core::JOURNAL_SCHEMA_VERSION) => journal.decode::<proof_core::Journal>().map(|j| j.chunk_image_id),
+        // Chunk journals have versions of their own, so a chunk receipt is
+        // never taken for a proof
+        Some(version) => return Err(format!("Unknown journal schema v{}", version)),
+        None => return Err("Journal is empty".to_string()),
+    };
+    id.map_err(|e| e.to_string())
+}
@@ -0,0 +1,241 @@
+//! Verifies M-Pesa credit proof receipts without a database or web server,
+//! so lenders can check receipts inside their own services:
+//!
+//! ```ignore
+//! let mut registry = ImageRegistry::default();
+//! registry.allow(AllowedImage::new(image_id_hex, valid_from, None)?);
+//! let journal = verify_receipt(&registry, &receipt_bytes, image_id_hex, proved_at)?;
+//! ```
+//!
+//! The current image ID is published with each journal schema, and each
+//! proof's own image ID and proving time come with its verification.
+
+use chrono::{DateTime, Utc};
+use risc0_zkvm::sha::Digest;
+use risc0_zkvm::{Journal, Receipt};
+
+mod legacy;
+
+/// Why a receipt wasn't accepted.
+#[derive(Debug, thiserror::Error)]
+pub enum Rejection {
+    #[error("Image ID {0} is not 32 bytes of hex")]
+    MalformedImageId(String),
+    #[error("Image {image_id} is not allowed for receipts proved at {proved_at}")]
+    ImageNotAllowed { image_id: String, proved_at: DateTime<Utc> },
+    #[error("Receipt does not deserialize: {0}")]
+    MalformedReceipt(String),
+    #[error("Receipt does not verify against image {0}")]
+    InvalidSeal(String),
+    #[error("Receipt's journal does not decode: {0}")]
+    MalformedJournal(String),
+    /// Composing doesn't make the receipt vouch for chunks another guest
+    /// proved
+    #[error("Receipt was composed from chunks proved by another guest")]
+    ChunkImageMismatch,
+}
+
+/// A guest image receipts may be proved with, and when.
+#[derive(Debug, Clone)]
+pub struct AllowedImage {
+    image_id: Digest,
+    pub valid_from: DateTime<Utc>,
+    /// Receipts proved from this time on aren't accepted; None while the
+    /// image is current
+    pub valid_to: Option<DateTime<Utc>>,
+}
+
+impl AllowedImage {
+    /// `image_id` is hex, as `Digest` displays it.
+    pub fn new(
+        image_id: &str,
+        valid_from: DateTime<Utc>,
+        valid_to: Option<DateTime<Utc>>,
+    ) -> Result<Self, Rejection> {
+        Ok(Self {
+            image_id: parse_image_id(image_id)?,
+            valid_from,
+            valid_to,
+        })
+    }
+
+    pub fn image_id(&self) -> Digest {
+        self.image_id
+    }
+
+    /// Whether a receipt proved at `proved_at` falls in the image's window.
+    pub fn covers(&self, proved_at: DateTime<Utc>) -> bool {
+        let before_retired = match self.valid_to {
+            Some(valid_to) => proved_at < valid_to,
+            None => true,
+        };
+        self.valid_from <= proved_at && before_retired
+    }
+}
+
+/// The guest images a verifier accepts receipts from. Retired images stay
+/// listed with a `valid_to`, so receipts proved while they were current
+/// still verify.
+#[derive(Debug, Clone, Default)]
+pub struct ImageRegistry {
+    images: Vec<AllowedImage>,
+}
+
+impl ImageRegistry {
+    pub fn new(images: Vec<AllowedImage>) -> Self {
+        Self { images }
+    }
+
+    pub fn allow(&mut self, image: AllowedImage) {
+        self.images.push(image);
+    }
+
+    pub fn images(&self) -> &[AllowedImage] {
+        &self.images
+    }
+
+    /// Whether receipts proved with `image_id` at `proved_at` are accepted.
+    pub fn is_allowed(&self, image_id: Digest, proved_at: DateTime<Utc>) -> bool {
+        self.images
+            .iter()
+            .any(|image| image.image_id == image_id && image.covers(proved_at))
+    }
+}
+
+/// Verifies a bincode-serialized receipt against the image it was proved
+/// with, which must be in `registry` and valid at `proved_at`. Returns the
+/// receipt's journal, to decode with `proof_core::Journal` once its
+/// `proof_core::schema_version` is checked.
+pub fn verify_receipt(
+    registry: &ImageRegistry,
+    receipt_data: &[u8],
+    image_id: &str,
+    proved_at: DateTime<Utc>,
+) -> Result<Journal, Rejection> {
+    let digest = parse_image_id(image_id)?;
+    if !registry.is_allowed(digest, proved_at) {
+        return Err(Rejection::ImageNotAllowed {
+            image_id: image_id.to_string(),
+            proved_at,
+        });
+    }
+
+    let receipt: Receipt =
+        bincode::deserialize(receipt_data).map_err(|e| Rejection::MalformedReceipt(e.to_string()))?;
+    receipt
+        .verify(digest)
+        .map_err(|_| Rejection::InvalidSeal(image_id.to_string()))?;
+
+    // A composed proof only vouches for its chunks if they were proved by
+    // the same guest, which the receipt alone doesn't enforce
+    match legacy::chunk_image_id(&receipt.journal).map_err(Rejection::MalformedJournal)? {
+        Some(chunk_image_id) if Digest::from(chunk_image_id) != digest => Err(Rejection::ChunkImageMismatch),
+        _ => Ok(receipt.journal),
+    }
+}
+
+fn parse_image_id(image_id: &str) -> Result<Digest, Rejection> {
+    let bytes = hex::decode(image_id).map_err(|_| Rejection::MalformedImageId(image_id.to_string()))?;
+    Digest::try_from(bytes.as_slice()).map_err(|_| Rejection::MalformedImageId(image_id.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use risc0_zkvm::{FakeReceipt, InnerReceipt, ReceiptClaim};
+
+    const IMAGE_ID: [u32; 8] = [0x1234_5678; 8];
+
+    /// A month of daily payments, scored natively and committed the way the
+    /// guest commits it.
+    fn journal_bytes() -> Vec<u8> {
+        let now = Utc::now().timestamp();
+        let transactions = (0..30)
+            .map(|day| proof_core::Transaction {
+                timestamp: now - day * 86_400,
+                amount: 150_000,
+                currency: "KES".to_string(),
+                kind: proof_core::TransactionKind::Payment,
+                direction: proof_core::Direction::Inflow,
+                reference: format!("RCP{:04}", day),
+                counterparty: None,
+                account: None,
+                provenance: proof_core::Provenance::CsvUpload,
+            })
+            .collect();
+        let input = proof_core::ProofInput {
+            transactions,
+            secondary: None,
+            threshold: Some(50),
+            account: None,
+            currency: proof_core::Currency {
+                code: "KES".to_string(),
+                minor_unit_exponent: 2,
+            },
+            policy: proof_core::ScoringPolicy {
+                volume_thresholds: [10_000, 50_000, 200_000, 1_000_000],
+                outlier_cap: None,
+            },
+            as_of: Some(now),
+            utc_offset_secs: None,
+            source: proof_core::DataSource::All,
+        };
+        let journal = proof_core::Journal {
+            schema_version: proof_core::JOURNAL_SCHEMA_VERSION,
+            evaluation: proof_core::evaluate(input).expect("statement scores"),
+            chunk_image_id: None,
+            input_mode: proof_core::InputMode::Transactions,
+        };
+        let words = risc0_zkvm::serde::to_vec(&journal).expect("journal serializes");
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    /// A receipt from the dev-mode prover, which verifies only with
+    /// RISC0_DEV_MODE set but otherwise checks its claim as a real one does.
+    fn fake_receipt(journal: Vec<u8>) -> Receipt {
+        std::env::set_var("RISC0_DEV_MODE", "1");
+        let claim = ReceiptClaim::ok(Digest::from(IMAGE_ID), journal.clone());
+        Receipt::new(InnerReceipt::Fake(FakeReceipt::new(claim)), journal)
+    }
+
+    fn registry(proved_at: DateTime<Utc>) -> ImageRegistry {
+        let image_id = hex::encode(Digest::from(IMAGE_ID));
+        ImageRegistry::new(vec![AllowedImage::new(&image_id, proved_at - Duration::days(1), None).unwrap()])
+    }
+
+    #[test]
+    fn verifies_a_receipt_from_an_allowed_image() {
+        let proved_at = Utc::now();
+        let data = bincode::serialize(&fake_receipt(journal_bytes())).unwrap();
+        let image_id = hex::encode(Digest::from(IMAGE_ID));
+
+        let journal = verify_receipt(&registry(proved_at), &data, &image_id, proved_at).unwrap();
+        assert_eq!(proof_core::schema_version(&journal.bytes), Some(proof_core::JOURNAL_SCHEMA_VERSION));
+        let journal: proof_core::Journal = journal.decode().unwrap();
+        assert!(matches!(journal.evaluation, proof_core::Evaluation::Threshold(_)));
+    }
+
+    #[test]
+    fn rejects_a_receipt_whose_journal_was_tampered_with() {
+        let proved_at = Utc::now();
+        let mut receipt = fake_receipt(journal_bytes());
+        // Flip a bit of the committed evaluation, past the schema version
+        receipt.journal.bytes[8] ^= 1;
+        let data = bincode::serialize(&receipt).unwrap();
+        let image_id = hex::encode(Digest::from(IMAGE_ID));
+
+        let result = verify_receipt(&registry(proved_at), &data, &image_id, proved_at);
+        assert!(matches!(result, Err(Rejection::InvalidSeal(_))));
+    }
+
+    #[test]
+    fn rejects_a_receipt_proved_before_its_image_was_allowed() {
+        let proved_at = Utc::now();
+        let data = bincode::serialize(&fake_receipt(journal_bytes())).unwrap();
+        let image_id = hex::encode(Digest::from(IMAGE_ID));
+
+        let result = verify_receipt(&registry(proved_at), &data, &image_id, proved_at - Duration::days(2));
+        assert!(matches!(result, Err(Rejection::ImageNotAllowed { .. })));
+    }
+}
@@ -0,0 +1,270 @@
+//! `proof-core` compiled to WebAssembly, so the frontend and lender tooling
+//! score and display results with the same code the guest proves with
+//! instead of re-implementing it. Values cross the boundary in the same
+//! shape serde gives them elsewhere; the TypeScript types below spell it out.
+//!
+//!     wasm-pack build wasm-bindings --release --scope mpesa-credit-proof
+//!
+//! builds the npm package into `wasm-bindings/pkg`.
+
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+
+#[wasm_bindgen(typescript_custom_section)]
+const TYPES: &str = r#"
+export type VolumeRange = "VeryLow" | "Low" | "Medium" | "High" | "VeryHigh";
+export type GrowthTrend = "Declining" | "Stable" | "Growing" | "Rapid";
+export type ConcentrationRisk = "Low" | "Moderate" | "High" | "Unknown";
+export type ShareBucket = "Negligible" | "Low" | "Moderate" | "High" | "Dominant";
+export type TransactionKind = "Payment" | "Reversal" | "Withdrawal" | "Transfer" | "Charge" | "Other";
+export type Direction = "Inflow" | "Outflow" | "Fee";
+export type Provenance =
+    | "CsvUpload"
+    | "PdfUpload"
+    | "SmsParse"
+    | "Partner"
+    | "C2bCallback"
+    | "DarajaPull"
+    | "ImageUpload"
+    | "OwnerCorrected";
+/** VerifiedApi scores only C2bCallback and DarajaPull rows */
+export type DataSource = "All" | "VerifiedApi";
+
+export interface Transaction {
+    /** Unix seconds */
+    timestamp: number;
+    /** In the currency's minor units, whichever way the money moved */
+    amount: number | bigint;
+    currency: string;
+    /** Only Payment and Reversal rows count towards volume */
+    kind: TransactionKind;
+    /** Only inflows count towards volume; outflows and fees are netted off it */
+    direction: Direction;
+    reference: string;
+    counterparty?: string | null;
+    account?: string | null;
+    /** How the row reached the platform */
+    provenance: Provenance;
+}
+
+export interface TransactionSource {
+    tag: string;
+    transactions: Transaction[];
+}
+
+export interface Currency {
+    code: string;
+    minor_unit_exponent: number;
+}
+
+export interface ScoringPolicy {
+    /** Monthly major-unit volume at which each band above VeryLow starts */
+    volume_thresholds: [number, number, number, number];
+    /** Cap each day's volume at this many times the median day's */
+    outlier_cap?: number | null;
+}
+
+export interface ProofInput {
+    transactions: Transaction[];
+    secondary?: TransactionSource | null;
+    threshold?: number | null;
+    account?: string | null;
+    currency: Currency;
+    policy: ScoringPolicy;
+    /** Unix seconds recency is measured up to; the latest row when unset */
+    as_of?: number | null;
+    /** Seconds east of UTC days are grouped in; East Africa Time (10800) when unset */
+    utc_offset_secs?: number | null;
+    source: DataSource;
+}
+
+export interface BusinessMetrics {
+    monthly_volume_range: VolumeRange;
+    consistency_score: number;
+    growth_trend: GrowthTrend;
+    active_days_percentage: number;
+    customer_diversity_score: number;
+    concentration_risk: ConcentrationRisk;
+    max_weekly_drawdown_percentage: number;
+    volatility_30d_percentage: number;
+}
+
+export interface ScoreBreakdown {
+    volume_points: number;
+    consistency_points: number;
+    activity_points: number;
+    growth_points: number;
+    diversity_points: number;
+    concentration_penalty: number;
+    volatility_penalty: number;
+}
+
+/** Shares of volume in the merchant's local time (`utc_offset_secs`) */
+export interface ActivityProfile {
+    /** Monday first */
+    weekday_shares: [ShareBucket, ShareBucket, ShareBucket, ShareBucket, ShareBucket, ShareBucket, ShareBucket];
+    /** 08:00-18:00 */
+    business_hours_share: ShareBucket;
+    /** 22:00-05:00 */
+    late_night_share: ShareBucket;
+}
+
+export interface OutlierAdjustment {
+    cap_multiple: number;
+    days_capped: number;
+    /** Share of raw volume removed by capping */
+    volume_removed: ShareBucket;
+    raw_monthly_volume_range: VolumeRange;
+}
+
+export interface Recency {
+    as_of: number;
+    days_since_last_transaction: number;
+    /** Percent of the breakdown's total kept in the credit score */
+    recency_factor_percentage: number;
+}
+
+/** Payments kept after outflows and fees; withdrawals and transfers aren't counted */
+export interface CashFlow {
+    monthly_net_inflow_range: VolumeRange;
+    net_inflow_percentage: number;
+    fee_percentage: number;
+}
+
+export interface SourceVolume {
+    tag: string;
+    monthly_volume_range: VolumeRange;
+}
+
+export interface ProofOutput {
+    till_number_hash: number[];
+    period_start: number;
+    period_end: number;
+    credit_score: number;
+    metrics: BusinessMetrics;
+    score_breakdown: ScoreBreakdown;
+    source_volumes: SourceVolume[];
+    monthly_volumes: VolumeRange[];
+    account_hash: string | null;
+    currency: string;
+    policy_hash: number[];
+    activity_profile: ActivityProfile;
+    outlier_adjustment: OutlierAdjustment | null;
+    recency: Recency;
+    /** Percent of the period's weeks with at least one payment */
+    coverage_percentage: number;
+    /** Seconds east of UTC days were grouped in */
+    utc_offset_secs: number;
+    cash_flow: CashFlow;
+    provenance: ProvenanceMix;
+    source: DataSource;
+}
+
+export interface ProvenanceMix {
+    /** Percent of scored volume from C2B callbacks and Daraja pulls */
+    api_sourced_percentage: number;
+    /** Percent of scored volume per Provenance, in declaration order */
+    percentages: [number, number, number, number, number, number, number];
+}
+
+export interface ThresholdOutput {
+    period_start: number;
+    period_end: number;
+    score_at_least: number;
+    meets_threshold: boolean;
+    account_hash: string | null;
+    currency: string;
+    policy_hash: number[];
+    recency: Recency;
+    coverage_percentage: number;
+    utc_offset_secs: number;
+    provenance: ProvenanceMix;
+    source: DataSource;
+}
+
+export type Evaluation = { Full: ProofOutput } | { Threshold: ThresholdOutput };
+
+export interface ScoreComponent {
+    name: string;
+    points: number;
+    max_points: number;
+    /** Deducted from the total rather than added to it */
+    penalty: boolean;
+}
+"#;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(typescript_type = "ProofInput")]
+    pub type ProofInputJs;
+    #[wasm_bindgen(typescript_type = "Evaluation")]
+    pub type EvaluationJs;
+    #[wasm_bindgen(typescript_type = "ScoringPolicy")]
+    pub type ScoringPolicyJs;
+    #[wasm_bindgen(typescript_type = "ScoreBreakdown")]
+    pub type ScoreBreakdownJs;
+    #[wasm_bindgen(typescript_type = "ScoreComponent[]")]
+    pub type ScoreComponentsJs;
+}
+
+#[derive(Serialize)]
+struct ScoreComponent {
+    name: &'static str,
+    points: u32,
+    max_points: u32,
+    penalty: bool,
+}
+
+/// Serializes `None` as `null`, as the TypeScript types declare it, rather
+/// than serde-wasm-bindgen's default of `undefined`.
+fn to_js<T: Serialize>(value: &T) -> Result<JsValue, serde_wasm_bindgen::Error> {
+    value.serialize(&serde_wasm_bindgen::Serializer::new().serialize_missing_as_null(true))
+}
+
+/// Scores a statement exactly as the guest does. Throws on input the
+/// scoring core rejects, such as mixed currencies.
+#[wasm_bindgen]
+pub fn evaluate(input: ProofInputJs) -> Result<EvaluationJs, JsError> {
+    let input: proof_core::ProofInput = serde_wasm_bindgen::from_value(input.into())?;
+    let evaluation = proof_core::evaluate(input)?;
+    Ok(to_js(&evaluation)?.unchecked_into())
+}
+
+/// Hex `ScoringPolicy::hash` for thresholds in `currency`, an ISO 4217
+/// code, as lenders see it next to a verified proof.
+#[wasm_bindgen(js_name = policyHash)]
+pub fn policy_hash(policy: ScoringPolicyJs, currency: &str) -> Result<String, JsError> {
+    let policy: proof_core::ScoringPolicy = serde_wasm_bindgen::from_value(policy.into())?;
+    Ok(policy.hash(currency).iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Each component of a breakdown with the points available, in display order.
+#[wasm_bindgen(js_name = scoreComponents)]
+pub fn score_components(breakdown: ScoreBreakdownJs) -> Result<ScoreComponentsJs, JsError> {
+    let breakdown: proof_core::ScoreBreakdown = serde_wasm_bindgen::from_value(breakdown.into())?;
+    let components: Vec<ScoreComponent> = proof_core::SCORE_COMPONENTS
+        .into_iter()
+        .zip(breakdown.points())
+        .map(|((name, max_points, penalty), points)| ScoreComponent {
+            name,
+            points,
+            max_points,
+            penalty,
+        })
+        .collect();
+    Ok(to_js(&components)?.unchecked_into())
+}
+
+/// The credit score a breakdown adds up to, penalties deducted.
+#[wasm_bindgen(js_name = scoreTotal)]
+pub fn score_total(breakdown: ScoreBreakdownJs) -> Result<u32, JsError> {
+    let breakdown: proof_core::ScoreBreakdown = serde_wasm_bindgen::from_value(breakdown.into())?;
+    Ok(breakdown.total())
+}
+
+/// Journal layout version receipts are currently committed with.
+#[wasm_bindgen(js_name = journalSchemaVersion)]
+pub fn journal_schema_version() -> u32 {
+    proof_core::JOURNAL_SCHEMA_VERSION
+}